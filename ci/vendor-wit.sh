@@ -57,7 +57,7 @@ make_vendor "wasi-http" "
 
 make_vendor "wasi-config" "config@f4d699b"
 
-make_vendor "wasi-keyvalue" "keyvalue@219ea36"
+make_vendor "wasi-keyvalue" "keyvalue@v0.2.0-draft2"
 
 rm -rf $cache_dir
 
@@ -116,6 +116,13 @@ pub struct DataFlowGraph {
     /// parallel for the time being.
     user_stack_maps: alloc::collections::BTreeMap<Inst, UserStackMapEntryVec>,
 
+    /// Call instructions that are exception landing pads.
+    ///
+    /// If the callee of such a call unwinds the stack to this frame, then
+    /// execution resumes just after the call with every register but the
+    /// stack and frame pointers clobbered. See `set_exception_landing_pad`.
+    exception_landing_pads: alloc::collections::BTreeSet<Inst>,
+
     /// basic blocks in the function and their parameters.
     ///
     /// This map is not in program order. That is handled by `Layout`, and so is the sequence of
@@ -167,6 +174,7 @@ impl DataFlowGraph {
             insts: Insts(PrimaryMap::new()),
             results: SecondaryMap::new(),
             user_stack_maps: alloc::collections::BTreeMap::new(),
+            exception_landing_pads: alloc::collections::BTreeSet::new(),
             blocks: Blocks(PrimaryMap::new()),
             dynamic_types: DynamicTypes::new(),
             value_lists: ValueListPool::new(),
@@ -186,6 +194,7 @@ impl DataFlowGraph {
         self.insts.0.clear();
         self.results.clear();
         self.user_stack_maps.clear();
+        self.exception_landing_pads.clear();
         self.blocks.0.clear();
         self.dynamic_types.clear();
         self.value_lists.clear();
@@ -586,6 +595,30 @@ impl DataFlowGraph {
         assert!(opcode.is_safepoint());
         self.user_stack_maps.entry(inst).or_default().push(entry);
     }
+
+    /// Is the given call instruction an exception landing pad?
+    pub fn is_exception_landing_pad(&self, inst: Inst) -> bool {
+        self.exception_landing_pads.contains(&inst)
+    }
+
+    /// Make the given call instruction an exception landing pad.
+    ///
+    /// A runtime may unwind the stack from within the callee straight back to
+    /// the point just after this call, skipping any frames in between. To
+    /// make that possible, the call clobbers every register except the stack
+    /// and frame pointers, and its location is recorded in the compiled code's
+    /// exception landing pads (see `MachBufferFinalized::exception_landing_pads`).
+    /// Code following the call must be prepared to observe arbitrary values
+    /// for the call's results when the callee unwinds.
+    ///
+    /// # Panics
+    ///
+    /// Panics if the given instruction is not a (non-tail) call instruction.
+    pub fn set_exception_landing_pad(&mut self, inst: Inst) {
+        let opcode = self.insts[inst].opcode();
+        assert!(opcode.is_safepoint());
+        self.exception_landing_pads.insert(inst);
+    }
 }
 
 /// Where did a value come from?
@@ -971,10 +971,10 @@ impl MachInst for Inst {
     }
 
     fn is_included_in_clobbers(&self) -> bool {
-        let (caller, callee) = match self {
+        let (caller, callee_clobbers) = match self {
             Inst::Args { .. } => return false,
-            Inst::Call { info } => (info.caller_conv, info.callee_conv),
-            Inst::CallInd { info } => (info.caller_conv, info.callee_conv),
+            Inst::Call { info } => (info.caller_conv, info.clobbers),
+            Inst::CallInd { info } => (info.caller_conv, info.clobbers),
             _ => return true,
         };
 
@@ -989,8 +989,11 @@ impl MachInst for Inst {
         //
         // See the note in [crate::isa::aarch64::abi::is_caller_save_reg] for
         // more information on this ABI-implementation hack.
+        //
+        // Note that the call's own clobber set is used for the callee, rather
+        // than that of its calling convention, since calls that are exception
+        // landing pads clobber everything.
         let caller_clobbers = AArch64MachineDeps::get_regs_clobbered_by_call(caller);
-        let callee_clobbers = AArch64MachineDeps::get_regs_clobbered_by_call(callee);
 
         let mut all_clobbers = caller_clobbers;
         all_clobbers.union_from(callee_clobbers);
@@ -1115,7 +1115,6 @@ impl Inst {
             }
 
             &Inst::Call { ref info } => {
-                sink.add_call_site();
                 sink.add_reloc(Reloc::RiscvCallPlt, &info.dest, 0);
 
                 Inst::construct_auipc_and_jalr(Some(writable_link_reg()), writable_link_reg(), 0)
@@ -1127,6 +1126,8 @@ impl Inst {
                     sink.push_user_stack_map(state, offset, s);
                 }
 
+                sink.add_call_site();
+
                 let callee_pop_size = i32::try_from(info.callee_pop_size).unwrap();
                 if callee_pop_size > 0 {
                     for inst in Riscv64MachineDeps::gen_sp_reg_adjust(-callee_pop_size) {
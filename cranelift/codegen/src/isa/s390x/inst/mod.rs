@@ -1058,11 +1058,18 @@ impl MachInst for Inst {
         // that the callee clobbers, the caller is also allowed to clobber. This
         // both saves work and enables us to more precisely follow the
         // half-caller-save, half-callee-save SysV ABI for some vector
-        // registers.
+        // registers. Calls that are exception landing pads clobber everything,
+        // however, so those are always included.
+        fn is_included<T>(info: &CallInfo<T>) -> bool {
+            let caller_clobbers = S390xMachineDeps::get_regs_clobbered_by_call(info.caller_conv);
+            let mut all_clobbers = caller_clobbers;
+            all_clobbers.union_from(info.clobbers);
+            info.caller_conv != info.callee_conv || all_clobbers != caller_clobbers
+        }
         match self {
             &Inst::Args { .. } => false,
-            &Inst::Call { ref info, .. } => info.caller_conv != info.callee_conv,
-            &Inst::CallInd { ref info, .. } => info.caller_conv != info.callee_conv,
+            &Inst::Call { ref info, .. } => is_included(info),
+            &Inst::CallInd { ref info, .. } => is_included(info),
             &Inst::ElfTlsGetOffset { .. } => false,
             _ => true,
         }
@@ -12,7 +12,7 @@ use crate::isa::s390x::inst::{
 };
 use crate::isa::s390x::S390xBackend;
 use crate::machinst::isle::*;
-use crate::machinst::{CallInfo, MachLabel, Reg};
+use crate::machinst::{all_allocatable_regs, CallInfo, MachLabel, Reg};
 use crate::{
     ir::{
         condcodes::*, immediates::*, types::*, ArgumentExtension, ArgumentPurpose, AtomicRmwOp,
@@ -980,10 +980,20 @@ impl IsleContext<'_, '_, MInst, S390xBackend> {
         uses: &CallArgList,
         defs: &CallRetList,
     ) -> CallInfo<()> {
+        let caller_conv = self.lower_ctx.abi().call_conv(self.lower_ctx.sigs());
         let sig_data = &self.lower_ctx.sigs()[abi];
-        // Get clobbers: all caller-saves. These may include return value
-        // regs, which we will remove from the clobber set later.
-        let clobbers = S390xMachineDeps::get_regs_clobbered_by_call(sig_data.call_conv());
+        // Get clobbers: all caller-saves, or every allocatable register if the
+        // callee may unwind straight back to just after this call. These may
+        // include return value regs, which we will remove from the clobber set
+        // later.
+        let clobbers = if self.lower_ctx.is_exception_landing_pad() {
+            all_allocatable_regs(S390xMachineDeps::get_machine_env(
+                &self.backend.flags,
+                caller_conv,
+            ))
+        } else {
+            S390xMachineDeps::get_regs_clobbered_by_call(sig_data.call_conv())
+        };
         let callee_pop_size = if sig_data.call_conv() == CallConv::Tail {
             sig_data.sized_stack_arg_space() as u32
         } else {
@@ -995,7 +1005,7 @@ impl IsleContext<'_, '_, MInst, S390xBackend> {
             defs: defs.clone(),
             clobbers,
             callee_pop_size,
-            caller_conv: self.lower_ctx.abi().call_conv(self.lower_ctx.sigs()),
+            caller_conv,
             callee_conv: self.lower_ctx.sigs()[abi].call_conv(),
         }
     }
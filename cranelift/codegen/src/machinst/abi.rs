@@ -1976,6 +1976,25 @@ pub enum IsTailCall {
     No,
 }
 
+/// Get every register that the register allocator may use in `env`.
+///
+/// A call that is an exception landing pad clobbers all of these, so that
+/// nothing but the stack and frame pointers needs to be restored when the
+/// stack is unwound to it.
+pub(crate) fn all_allocatable_regs(env: &MachineEnv) -> PRegSet {
+    let mut regs = PRegSet::empty();
+    for class in env
+        .preferred_regs_by_class
+        .iter()
+        .chain(&env.non_preferred_regs_by_class)
+    {
+        for reg in class {
+            regs.add(*reg);
+        }
+    }
+    regs
+}
+
 /// ABI object for a callsite.
 pub struct CallSite<M: ABIMachineSpec> {
     /// The called function's signature.
@@ -2377,9 +2396,15 @@ impl<M: ABIMachineSpec> CallSite<M> {
         let uses = mem::take(&mut self.uses);
         let defs = mem::take(&mut self.defs);
         let clobbers = {
-            // Get clobbers: all caller-saves. These may include return value
-            // regs, which we will remove from the clobber set below.
-            let mut clobbers = <M>::get_regs_clobbered_by_call(ctx.sigs()[self.sig].call_conv);
+            // Get clobbers: all caller-saves, or every allocatable register
+            // if the callee may unwind straight back to just after this
+            // call. These may include return value regs, which we will remove
+            // from the clobber set below.
+            let mut clobbers = if ctx.is_exception_landing_pad() {
+                all_allocatable_regs(M::get_machine_env(&self.flags, self.caller_conv))
+            } else {
+                <M>::get_regs_clobbered_by_call(ctx.sigs()[self.sig].call_conv)
+            };
 
             // Remove retval regs from clobbers.
             for def in &defs {
@@ -254,6 +254,8 @@ pub struct MachBuffer<I: VCodeInst> {
     /// Each entry is an `(offset, span, stack_map)` triple. Entries are sorted
     /// by code offset, and each stack map covers `span` bytes on the stack.
     user_stack_maps: SmallVec<[(CodeOffset, u32, ir::UserStackMap); 8]>,
+    /// Any exception landing pads for this code, sorted by code offset.
+    exception_landing_pads: SmallVec<[MachExceptionLandingPad; 8]>,
     /// Any unwind info at a given location.
     unwind_info: SmallVec<[(CodeOffset, UnwindInst); 8]>,
     /// The current source location in progress (after `start_srcloc()` and
@@ -333,6 +335,7 @@ impl MachBufferFinalized<Stencil> {
                 .map(|srcloc| srcloc.apply_base_srcloc(base_srcloc))
                 .collect(),
             user_stack_maps: self.user_stack_maps,
+            exception_landing_pads: self.exception_landing_pads,
             unwind_info: self.unwind_info,
             alignment: self.alignment,
         }
@@ -364,6 +367,8 @@ pub struct MachBufferFinalized<T: CompilePhase> {
     /// Each entry is an `(offset, span, stack_map)` triple. Entries are sorted
     /// by code offset, and each stack map covers `span` bytes on the stack.
     pub(crate) user_stack_maps: SmallVec<[(CodeOffset, u32, ir::UserStackMap); 8]>,
+    /// Any exception landing pads for this code, sorted by code offset.
+    pub(crate) exception_landing_pads: SmallVec<[MachExceptionLandingPad; 8]>,
     /// Any unwind info at a given location.
     pub unwind_info: SmallVec<[(CodeOffset, UnwindInst); 8]>,
     /// The required alignment of this buffer.
@@ -443,6 +448,7 @@ impl<I: VCodeInst> MachBuffer<I> {
             call_sites: SmallVec::new(),
             srclocs: SmallVec::new(),
             user_stack_maps: SmallVec::new(),
+            exception_landing_pads: SmallVec::new(),
             unwind_info: SmallVec::new(),
             cur_srcloc: None,
             label_offsets: SmallVec::new(),
@@ -1531,6 +1537,7 @@ impl<I: VCodeInst> MachBuffer<I> {
             call_sites: self.call_sites,
             srclocs,
             user_stack_maps: self.user_stack_maps,
+            exception_landing_pads: self.exception_landing_pads,
             unwind_info: self.unwind_info,
             alignment,
         }
@@ -1670,6 +1677,34 @@ impl<I: VCodeInst> MachBuffer<I> {
         stack_map.finalize(emit_state.frame_layout().sp_to_sized_stack_slots());
         self.user_stack_maps.push((return_addr, span, stack_map));
     }
+
+    /// Record that the call instruction that was just emitted is an exception
+    /// landing pad.
+    ///
+    /// The landing pad is the current offset, after any stack pointer
+    /// adjustments that are part of the call's emission, so that the stack
+    /// pointer is at its usual place within the frame when execution resumes
+    /// there.
+    pub fn add_exception_landing_pad(&mut self, emit_state: &I::State) -> &MachExceptionLandingPad {
+        let return_addr = self
+            .call_sites
+            .last()
+            .expect("exception landing pads must be calls")
+            .ret_addr;
+        let landing_pad = self.cur_offset();
+        let frame_size = emit_state.frame_layout().active_size();
+        trace!(
+            "Adding exception landing pad @ {landing_pad:#x} for call returning to \
+             {return_addr:#x} in a frame of {frame_size} bytes"
+        );
+        debug_assert!(return_addr <= landing_pad);
+        self.exception_landing_pads.push(MachExceptionLandingPad {
+            return_addr,
+            landing_pad,
+            frame_size,
+        });
+        self.exception_landing_pads.last().unwrap()
+    }
 }
 
 impl<I: VCodeInst> Extend<u8> for MachBuffer<I> {
@@ -1742,6 +1777,11 @@ impl<T: CompilePhase> MachBufferFinalized<T> {
     pub fn call_sites(&self) -> &[MachCallSite] {
         &self.call_sites[..]
     }
+
+    /// Get the list of exception landing pads for this code.
+    pub fn exception_landing_pads(&self) -> &[MachExceptionLandingPad] {
+        &self.exception_landing_pads[..]
+    }
 }
 
 /// Metadata about a constant.
@@ -1908,6 +1948,26 @@ pub struct MachCallSite {
     pub ret_addr: CodeOffset,
 }
 
+/// An exception landing pad resulting from a compilation.
+///
+/// See `DataFlowGraph::set_exception_landing_pad`.
+#[derive(Clone, Debug, PartialEq)]
+#[cfg_attr(
+    feature = "enable-serde",
+    derive(serde_derive::Serialize, serde_derive::Deserialize)
+)]
+pub struct MachExceptionLandingPad {
+    /// The offset of the call's return address, *relative to the containing
+    /// section*.
+    pub return_addr: CodeOffset,
+    /// The offset at which to resume execution after unwinding to this call,
+    /// *relative to the containing section*.
+    pub landing_pad: CodeOffset,
+    /// The size of the frame, from the frame pointer down to the stack
+    /// pointer, at the landing pad.
+    pub frame_size: u32,
+}
+
 /// A source-location mapping resulting from a compilation.
 #[derive(PartialEq, Debug, Clone)]
 #[cfg_attr(
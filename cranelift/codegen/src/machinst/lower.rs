@@ -745,6 +745,18 @@ impl<'func, I: VCodeInst> Lower<'func, I> {
                 }
             }
 
+            // Likewise forward whether the instruction is an exception landing
+            // pad to its vcode safepoint.
+            if self.f.dfg.is_exception_landing_pad(inst) {
+                let end = self.vcode.vcode.num_insts();
+                let iix = (start..end)
+                    .map(InsnIndex::new)
+                    .find(|iix| self.vcode.vcode[*iix].is_safepoint())
+                    .expect("exception landing pad was not lowered to a call");
+                self.vcode
+                    .add_exception_landing_pad(BackwardsInsnIndex::new(iix.index()));
+            }
+
             // maybe insert random instruction
             if ctrl_plane.get_decision() {
                 if ctrl_plane.get_decision() {
@@ -1190,6 +1202,13 @@ impl<'func, I: VCodeInst> Lower<'func, I> {
         &self.f.dfg
     }
 
+    /// Is the instruction currently being lowered a call that is an exception
+    /// landing pad?
+    pub fn is_exception_landing_pad(&self) -> bool {
+        self.cur_inst
+            .map_or(false, |inst| self.f.dfg.is_exception_landing_pad(inst))
+    }
+
     /// Get the `Callee`.
     pub fn abi(&self) -> &Callee<I::ABIMachineSpec> {
         self.vcode.abi()
@@ -29,7 +29,7 @@ use regalloc2::{
     Edit, Function as RegallocFunction, InstOrEdit, InstRange, MachineEnv, Operand,
     OperandConstraint, OperandKind, PRegSet, RegClass,
 };
-use rustc_hash::FxHashMap;
+use rustc_hash::{FxHashMap, FxHashSet};
 
 use core::mem::take;
 use cranelift_entity::{entity_impl, Keys};
@@ -102,6 +102,10 @@ pub struct VCode<I: VCodeInst> {
     /// user stack map.
     user_stack_maps: FxHashMap<BackwardsInsnIndex, ir::UserStackMap>,
 
+    /// The set of (backwards instruction indices of) calls that are exception
+    /// landing pads.
+    exception_landing_pads: FxHashSet<BackwardsInsnIndex>,
+
     /// Operands: pre-regalloc references to virtual registers with
     /// constraints, in one flattened array. This allows the regalloc
     /// to efficiently access all operands without requiring expensive
@@ -589,6 +593,12 @@ impl<I: VCodeInst> VCodeBuilder<I> {
         let old_entry = self.vcode.user_stack_maps.insert(inst, stack_map);
         debug_assert!(old_entry.is_none());
     }
+
+    /// Mark the associated call instruction as an exception landing pad.
+    pub fn add_exception_landing_pad(&mut self, inst: BackwardsInsnIndex) {
+        let inserted = self.vcode.exception_landing_pads.insert(inst);
+        debug_assert!(inserted);
+    }
 }
 
 const NO_INST_OFFSET: CodeOffset = u32::MAX;
@@ -608,6 +618,7 @@ impl<I: VCodeInst> VCode<I> {
             vreg_types: vec![],
             insts: Vec::with_capacity(10 * n_blocks),
             user_stack_maps: FxHashMap::default(),
+            exception_landing_pads: FxHashSet::default(),
             operands: Vec::with_capacity(30 * n_blocks),
             operand_ranges: Ranges::with_capacity(10 * n_blocks),
             clobbers: FxHashMap::default(),
@@ -881,6 +892,12 @@ impl<I: VCodeInst> VCode<I> {
                             cur_srcloc = Some(srcloc);
                         }
 
+                        // Is this call an exception landing pad? If so, it is
+                        // recorded once the call has been emitted below.
+                        let is_exception_landing_pad = self
+                            .exception_landing_pads
+                            .remove(&iix.to_backwards_insn_index(self.num_insts()));
+
                         // If this is a safepoint, compute a stack map
                         // and pass it to the emit state.
                         let stack_map_disasm = if self.insts[iix.index()].is_safepoint() {
@@ -945,6 +962,12 @@ impl<I: VCodeInst> VCode<I> {
                                 disasm.push_str(&stack_map_disasm);
                                 disasm.push('\n');
                             }
+                            if is_exception_landing_pad {
+                                let landing_pad = buffer.add_exception_landing_pad(&state);
+                                if want_disasm {
+                                    writeln!(&mut disasm, "  ; {landing_pad:?}").unwrap();
+                                }
+                            }
                         }
                     }
 
@@ -1027,6 +1050,10 @@ impl<I: VCodeInst> VCode<I> {
             }
         }
 
+        debug_assert!(
+            self.exception_landing_pads.is_empty(),
+            "all exception landing pads should have been emitted",
+        );
         debug_assert!(
             self.user_stack_maps.is_empty(),
             "any stack maps should have been consumed by instruction emission, still have: {:#?}",
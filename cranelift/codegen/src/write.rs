@@ -464,7 +464,8 @@ pub fn write_operands(w: &mut dyn Write, dfg: &DataFlowGraph, inst: Inst) -> fmt
             func_ref, ref args, ..
         } => {
             write!(w, " {}({})", func_ref, DisplayValues(args.as_slice(pool)))?;
-            write_user_stack_map_entries(w, dfg, inst)
+            write_user_stack_map_entries(w, dfg, inst)?;
+            write_exception_landing_pad(w, dfg, inst)
         }
         CallIndirect {
             sig_ref, ref args, ..
@@ -477,7 +478,8 @@ pub fn write_operands(w: &mut dyn Write, dfg: &DataFlowGraph, inst: Inst) -> fmt
                 args[0],
                 DisplayValues(&args[1..])
             )?;
-            write_user_stack_map_entries(w, dfg, inst)
+            write_user_stack_map_entries(w, dfg, inst)?;
+            write_exception_landing_pad(w, dfg, inst)
         }
         FuncAddr { func_ref, .. } => write!(w, " {func_ref}"),
         StackLoad {
@@ -560,6 +562,13 @@ fn write_user_stack_map_entries(w: &mut dyn Write, dfg: &DataFlowGraph, inst: In
     Ok(())
 }
 
+fn write_exception_landing_pad(w: &mut dyn Write, dfg: &DataFlowGraph, inst: Inst) -> fmt::Result {
+    if dfg.is_exception_landing_pad(inst) {
+        write!(w, ", exception_landing_pad")?;
+    }
+    Ok(())
+}
+
 /// Displayable slice of values.
 struct DisplayValues<'a>(&'a [Value]);
 
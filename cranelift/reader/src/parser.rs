@@ -2501,29 +2501,40 @@ impl<'a> Parser<'a> {
         // and attach stack map entries, if present.
         let ctrl_typevar = self.infer_typevar(ctx, opcode, explicit_ctrl_type, &inst_data)?;
         let inst = ctx.function.dfg.make_inst(inst_data);
-        if opcode.is_call() && !opcode.is_return() && self.optional(Token::Comma) {
-            self.match_identifier("stack_map", "expected `stack_map = [...]`")?;
-            self.match_token(Token::Equal, "expected `= [...]`")?;
-            self.match_token(Token::LBracket, "expected `[...]`")?;
-            while !self.optional(Token::RBracket) {
-                let ty = self.match_type("expected `<type> @ <slot> + <offset>`")?;
-                self.match_token(Token::At, "expected `@ <slot> + <offset>`")?;
-                let slot = self.match_ss("expected `<slot> + <offset>`")?;
-                let offset: u32 = match self.token() {
-                    Some(Token::Integer(s)) if s.starts_with('+') => {
-                        self.match_uimm32("expected a u32 offset")?.into()
-                    }
-                    _ => {
-                        self.match_token(Token::Plus, "expected `+ <offset>`")?;
-                        self.match_uimm32("expected a u32 offset")?.into()
+        if opcode.is_call() && !opcode.is_return() {
+            while self.optional(Token::Comma) {
+                if let Some(Token::Identifier("exception_landing_pad")) = self.token() {
+                    self.consume();
+                    ctx.function.dfg.set_exception_landing_pad(inst);
+                    continue;
+                }
+                self.match_identifier(
+                    "stack_map",
+                    "expected `stack_map = [...]` or `exception_landing_pad`",
+                )?;
+                self.match_token(Token::Equal, "expected `= [...]`")?;
+                self.match_token(Token::LBracket, "expected `[...]`")?;
+                while !self.optional(Token::RBracket) {
+                    let ty = self.match_type("expected `<type> @ <slot> + <offset>`")?;
+                    self.match_token(Token::At, "expected `@ <slot> + <offset>`")?;
+                    let slot = self.match_ss("expected `<slot> + <offset>`")?;
+                    let offset: u32 = match self.token() {
+                        Some(Token::Integer(s)) if s.starts_with('+') => {
+                            self.match_uimm32("expected a u32 offset")?.into()
+                        }
+                        _ => {
+                            self.match_token(Token::Plus, "expected `+ <offset>`")?;
+                            self.match_uimm32("expected a u32 offset")?.into()
+                        }
+                    };
+                    ctx.function.dfg.append_user_stack_map_entry(
+                        inst,
+                        ir::UserStackMapEntry { ty, slot, offset },
+                    );
+                    if !self.optional(Token::Comma) {
+                        self.match_token(Token::RBracket, "expected `,` or `]`")?;
+                        break;
                     }
-                };
-                ctx.function
-                    .dfg
-                    .append_user_stack_map_entry(inst, ir::UserStackMapEntry { ty, slot, offset });
-                if !self.optional(Token::Comma) {
-                    self.match_token(Token::RBracket, "expected `,` or `]`")?;
-                    break;
                 }
             }
         }
@@ -15,6 +15,7 @@ const FEATURES: &[&str] = &[
     "DEMANGLE",
     "THREADS",
     "GC",
+    "STACK_SWITCHING",
     "CRANELIFT",
     "WINCH",
 ];
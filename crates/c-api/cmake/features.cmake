@@ -38,6 +38,7 @@ feature(addr2line ON)
 feature(demangle ON)
 feature(threads ON)
 feature(gc ON)
+feature(stack-switching ON)
 feature(async ON)
 feature(cranelift ON)
 feature(winch ON)
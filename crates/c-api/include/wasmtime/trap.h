@@ -49,6 +49,12 @@ enum wasmtime_trap_code_enum {
   WASMTIME_TRAP_CODE_INTERRUPT,
   /// Execution has run out of the configured fuel amount.
   WASMTIME_TRAP_CODE_OUT_OF_FUEL,
+  /// The GC heap has no room for an allocation, even after a collection.
+  WASMTIME_TRAP_CODE_GC_HEAP_OUT_OF_MEMORY,
+  /// A continuation suspended with a tag that no enclosing resume handles.
+  WASMTIME_TRAP_CODE_UNHANDLED_TAG,
+  /// A continuation was resumed after it had already been resumed.
+  WASMTIME_TRAP_CODE_CONTINUATION_ALREADY_CONSUMED,
 };
 
 /**
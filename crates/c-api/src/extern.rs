@@ -21,6 +21,7 @@ pub extern "C" fn wasm_extern_kind(e: &wasm_extern_t) -> wasm_externkind_t {
         Extern::Table(_) => crate::WASM_EXTERN_TABLE,
         Extern::Memory(_) => crate::WASM_EXTERN_MEMORY,
         Extern::SharedMemory(_) => todo!(),
+        Extern::Tag(_) => crate::abort("tags"),
     }
 }
 
@@ -141,6 +142,7 @@ impl From<Extern> for wasmtime_extern_t {
                     sharedmemory: ManuallyDrop::new(Box::new(sharedmemory)),
                 },
             },
+            Extern::Tag(_) => crate::abort("tags"),
         }
     }
 }
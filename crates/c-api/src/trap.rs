@@ -134,6 +134,9 @@ pub extern "C" fn wasmtime_trap_code(raw: &wasm_trap_t, code: &mut u8) -> bool {
         Trap::UnreachableCodeReached => 9,
         Trap::Interrupt => 10,
         Trap::OutOfFuel => 11,
+        Trap::GcHeapOutOfMemory => 12,
+        Trap::UnhandledTag => 13,
+        Trap::ContinuationAlreadyConsumed => 14,
         Trap::AlwaysTrapAdapter => unreachable!("component model not supported"),
         _ => unreachable!(),
     };
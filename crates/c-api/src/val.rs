@@ -95,6 +95,7 @@ impl wasm_val_t {
                 },
             },
             Val::AnyRef(_) => crate::abort("creating a wasm_val_t from an anyref"),
+            Val::ExnRef(_) => crate::abort("creating a wasm_val_t from an exnref"),
             Val::ExternRef(_) => crate::abort("creating a wasm_val_t from an externref"),
             Val::V128(_) => crate::abort("creating a wasm_val_t from a v128"),
         }
@@ -257,6 +258,7 @@ impl wasmtime_val_t {
                     v128: val.as_u128().to_le_bytes(),
                 },
             },
+            Val::ExnRef(_) => crate::abort("creating a wasmtime_val_t from an exnref"),
         }
     }
 
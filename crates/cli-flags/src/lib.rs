@@ -216,7 +216,21 @@ wasmtime_option_group! {
         /// Configure whether logs are emitted to files
         pub log_to_files: Option<bool>,
         /// Enable coredump generation to this file after a WebAssembly trap.
+        ///
+        /// The file name may contain `{pid}`, `{timestamp}`, `{module}` and,
+        /// for `wasmtime serve`, `{request_id}`, which are substituted when
+        /// each core dump is written. If the result names an existing file a
+        /// `-N` suffix is added before its extension rather than overwriting.
         pub coredump: Option<String>,
+        /// Maximum number of core dumps matching the `coredump` path to keep,
+        /// removing the oldest ones once there are more.
+        pub coredump_max_count: Option<usize>,
+        /// Maximum total size, in bytes, of core dumps matching the `coredump`
+        /// path to keep, removing the oldest ones once they're larger.
+        pub coredump_max_size: Option<u64>,
+        /// Record the values of locals and the operand stack in coredumps,
+        /// at the cost of slower Cranelift-compiled code.
+        pub coredump_frame_state: Option<bool>,
     }
 
     enum Debug {
@@ -320,6 +334,13 @@ wasmtime_option_group! {
         pub function_references: Option<bool>,
         /// Configure support for the GC proposal.
         pub gc: Option<bool>,
+        /// Configure support for the exception-handling proposal.
+        pub exceptions: Option<bool>,
+        /// Configure support for the stack-switching proposal.
+        pub stack_switching: Option<bool>,
+        /// The garbage collector implementation to use for GC types
+        /// (`drc`, `mark-sweep`, or `null`).
+        pub collector: Option<wasmtime::Collector>,
         /// Configure support for the custom-page-sizes proposal.
         pub custom_page_sizes: Option<bool>,
         /// Configure support for the wide-arithmetic proposal.
@@ -346,6 +367,36 @@ wasmtime_option_group! {
         pub threads: Option<bool>,
         /// Enable support for WASI HTTP API (experimental)
         pub http: Option<bool>,
+        /// Trust the PEM-encoded certificate authorities in the given file for
+        /// outgoing HTTPS requests, in addition to the Mozilla root
+        /// certificates.
+        pub http_ca_bundle: Option<String>,
+        /// Present the PEM-encoded certificate chain in the given file to
+        /// servers which ask for a client certificate in outgoing HTTPS
+        /// requests. Requires `-S http-client-key`.
+        pub http_client_cert: Option<String>,
+        /// The PEM-encoded private key for `-S http-client-cert`.
+        pub http_client_key: Option<String>,
+        /// Only allow outgoing HTTP requests to hosts matching the given rule,
+        /// or any other given with this option.
+        ///
+        /// Rules are host names such as `example.com` or `*.example.com`, IP
+        /// addresses, or CIDR ranges such as `10.0.0.0/8`. Host names and IP
+        /// addresses may be followed by a port, e.g. `example.com:443`.
+        pub http_allow_host: Vec<String>,
+        /// Deny outgoing HTTP requests to hosts matching the given rule, using
+        /// the same syntax as `-S http-allow-host`.
+        pub http_deny_host: Vec<String>,
+        /// Deny outgoing HTTP requests which don't use HTTPS.
+        pub http_https_only: Option<bool>,
+        /// Set the given header on all outgoing HTTP requests, e.g.
+        /// `-S http-header=authorization=token`.
+        pub http_header: Vec<KeyValuePair>,
+        /// The maximum size, in bytes, of the bodies of outgoing HTTP requests.
+        pub http_max_request_body_size: Option<u64>,
+        /// Tunnel all outgoing HTTP requests through the HTTP proxy at the given
+        /// host and port, e.g. `-S http-proxy=proxy.internal:3128`.
+        pub http_proxy: Option<String>,
         /// Enable support for WASI config API (experimental)
         pub config: Option<bool>,
         /// Enable support for WASI key-value API (experimental)
@@ -370,7 +421,8 @@ wasmtime_option_group! {
         /// directory name: e.g., `--wasi-nn-graph openvino:/foo/bar` will preload
         /// an OpenVINO model named `bar`. Note that which model encodings are
         /// available is dependent on the backends implemented in the
-        /// `wasmtime_wasi_nn` crate.
+        /// `wasmtime_wasi_nn` crate; `tract` loads ONNX models with the
+        /// pure-Rust tract backend when it is compiled in.
         pub nn_graph: Vec<WasiNnGraph>,
         /// Flag for WASI preview2 to inherit the host's network within the
         /// guest so it has full access to all addresses/ports/etc.
@@ -381,6 +433,26 @@ wasmtime_option_group! {
         pub tcp: Option<bool>,
         /// Indicates whether `wasi:sockets` UDP support is enabled or not.
         pub udp: Option<bool>,
+        /// Allow `wasi:sockets` to use the addresses matching the given rule,
+        /// e.g. `-S socket-allow=connect=10.0.0.0/8:443`.
+        ///
+        /// Rules are `USES=ADDRS:PORTS`: `USES` is one of `tcp-bind`,
+        /// `tcp-connect`, `udp-bind`, `udp-connect`, `udp-send`, `bind`,
+        /// `connect`, `tcp`, `udp` or `*`, `ADDRS` is an IP address, a CIDR
+        /// range or `*`, and `PORTS` is a port, a range such as `8000-8999` or
+        /// `*`. IPv6 addresses followed by ports go in brackets. Addresses
+        /// that no rule allows are denied, unless `-S inherit-network` is
+        /// given.
+        pub socket_allow: Vec<String>,
+        /// Deny `wasi:sockets` the addresses matching the given rule, using the
+        /// same syntax as `-S socket-allow`.
+        pub socket_deny: Vec<String>,
+        /// Load `wasi:sockets` rules from the given file, which has a rule on
+        /// each line preceded by `allow` or `deny`, e.g.
+        /// `allow connect=10.0.0.0/8:443`.
+        pub socket_policy: Option<String>,
+        /// Log every socket address which is denied to the guest to stderr.
+        pub socket_log_denied: Option<bool>,
         /// Enable WASI APIs marked as: @unstable(feature = network-error-code)
         pub network_error_code: Option<bool>,
         /// Allows imports from the `wasi_unstable` core wasm module.
@@ -389,10 +461,44 @@ wasmtime_option_group! {
         ///
         /// This option can be further overwritten with `--env` flags.
         pub inherit_env: Option<bool>,
+        /// Make runs reproducible: clocks are virtual and only advance when
+        /// the guest sleeps or polls, randomness is seeded, directory listings
+        /// are sorted, filesystem timestamps are frozen and stdio is never a
+        /// terminal.
+        ///
+        /// This also enables `-W nan-canonicalization` and
+        /// `-W relaxed-simd-deterministic` unless they're given explicitly.
+        pub deterministic: Option<bool>,
+        /// The seed of the random number generators of `-S deterministic`,
+        /// which defaults to 0. Implies `-S deterministic`.
+        pub deterministic_seed: Option<u64>,
         /// Pass a wasi config variable to the program.
         pub config_var: Vec<KeyValuePair>,
+        /// Provide wasi config variables from a TOML or JSON file, which is
+        /// reloaded whenever it changes.
+        ///
+        /// Nested tables are flattened into dotted keys, e.g. `url` in the
+        /// `[database]` table is the variable `database.url`. Variables passed
+        /// with `-S config-var` take precedence over those in the file.
+        pub config_file: Option<String>,
+        /// Provide the host's environment variables starting with the given
+        /// prefix as wasi config variables, without the prefix.
+        ///
+        /// For example `-S config-env-prefix=APP_` provides `APP_PORT` as
+        /// `PORT`. These take precedence over `-S config-file` but not over
+        /// `-S config-var`.
+        pub config_env_prefix: Option<String>,
         /// Preset data for the In-Memory provider of WASI key-value API.
         pub keyvalue_in_memory_data: Vec<KeyValuePair>,
+        /// Register a store for the WASI key-value API under the given
+        /// identifier.
+        ///
+        /// The store is either `memory`, which keeps its data in memory, or
+        /// `file:<path>`, which persists its data to the given file. For
+        /// example `-S keyvalue-store=cache=memory` or
+        /// `-S keyvalue-store=users=file:users.kv`. Stores are shared by all
+        /// instances, including all requests handled by `wasmtime serve`.
+        pub keyvalue_store: Vec<KeyValuePair>,
     }
 
     enum Wasi {
@@ -492,6 +598,13 @@ impl CommonOptions {
         self.debug.configure_with(&self.debug_raw);
         self.wasm.configure_with(&self.wasm_raw);
         self.wasi.configure_with(&self.wasi_raw);
+        if self.wasi.deterministic_seed.is_some() {
+            self.wasi.deterministic.get_or_insert(true);
+        }
+        if self.wasi.deterministic == Some(true) {
+            self.wasm.nan_canonicalization.get_or_insert(true);
+            self.wasm.relaxed_simd_deterministic.get_or_insert(true);
+        }
     }
 
     pub fn init_logging(&mut self) -> Result<()> {
@@ -551,6 +664,18 @@ impl CommonOptions {
             #[cfg(not(feature = "coredump"))]
             anyhow::bail!("support for coredumps disabled at compile time");
         }
+        if self.debug.coredump.is_none()
+            && (self.debug.coredump_max_count.is_some() || self.debug.coredump_max_size.is_some())
+        {
+            anyhow::bail!(
+                "`-D coredump-max-count` and `-D coredump-max-size` require `-D coredump`"
+            );
+        }
+        match_feature! {
+            ["coredump" : self.debug.coredump_frame_state]
+            enable => config.record_frame_state(enable),
+            _ => err,
+        }
         match_feature! {
             ["cranelift" : self.opts.opt_level]
             level => config.cranelift_opt_level(level),
@@ -785,6 +910,11 @@ impl CommonOptions {
         if let Some(enable) = self.wasm.relaxed_simd_deterministic {
             config.relaxed_simd_deterministic(enable);
         }
+        match_feature! {
+            ["gc" : self.wasm.collector]
+            collector => config.collector(collector),
+            _ => err,
+        }
         match_feature! {
             ["cranelift" : self.wasm.wmemcheck]
             enable => config.wmemcheck(enable),
@@ -846,6 +976,8 @@ impl CommonOptions {
             ("gc", gc, wasm_gc)
             ("gc", reference_types, wasm_reference_types)
             ("gc", function_references, wasm_function_references)
+            ("gc", exceptions, wasm_exceptions)
+            ("stack-switching", stack_switching, wasm_stack_switching)
         }
         Ok(())
     }
@@ -3,7 +3,10 @@ use cranelift_codegen::{
     ir, isa::unwind::CfaUnwindInfo, isa::unwind::UnwindInfo, Final, MachBufferFinalized,
     MachSrcLoc, ValueLabelsRanges,
 };
-use wasmtime_environ::{FilePos, InstructionAddressMap, PrimaryMap, TrapInformation};
+use wasmtime_environ::{
+    ExceptionLandingPad, FilePos, FrameStateInfo, InstructionAddressMap, PrimaryMap,
+    TrapInformation,
+};
 
 #[derive(Debug, Clone, PartialEq, Eq, Default)]
 /// Metadata to translate from binary offsets back to the original
@@ -50,6 +53,8 @@ pub struct CompiledFunctionMetadata {
     pub start_srcloc: FilePos,
     /// End source location.
     pub end_srcloc: FilePos,
+    /// Where the function's Wasm locals live in its frame, if known.
+    pub frame_state: Option<FrameStateInfo>,
 }
 
 /// Compiled function: machine code body, jump table offsets, and unwind information.
@@ -95,6 +100,18 @@ impl CompiledFunction {
         self.buffer.traps().iter().filter_map(mach_trap_to_trap)
     }
 
+    /// Returns an iterator to the function's exception landing pads.
+    pub fn exception_landing_pads(&self) -> impl Iterator<Item = ExceptionLandingPad> + '_ {
+        self.buffer
+            .exception_landing_pads()
+            .iter()
+            .map(|pad| ExceptionLandingPad {
+                return_offset: pad.return_addr,
+                landing_pad_offset: pad.landing_pad,
+                frame_size: pad.frame_size,
+            })
+    }
+
     /// Get the function's address map from the metadata.
     pub fn address_map(&self) -> &FunctionAddressMap {
         &self.metadata.address_map
@@ -155,6 +172,11 @@ impl CompiledFunction {
         self.metadata.cfa_unwind_info = Some(unwind);
     }
 
+    /// Set the frame state in the function's metadata.
+    pub fn set_frame_state(&mut self, frame_state: FrameStateInfo) {
+        self.metadata.frame_state = Some(frame_state);
+    }
+
     /// Set the sized stack slots.
     pub fn set_sized_stack_slots(&mut self, slots: ir::StackSlots) {
         self.metadata.sized_stack_slots = slots;
@@ -1,4 +1,5 @@
 use crate::debug::DwarfSectionRelocTarget;
+use crate::frame_state::FrameState;
 use crate::func_environ::FuncEnvironment;
 use crate::translate::{FuncEnvironment as _, FuncTranslator};
 use crate::TRAP_INTERNAL_ASSERT;
@@ -26,10 +27,10 @@ use std::path;
 use std::sync::{Arc, Mutex};
 use wasmparser::{FuncValidatorAllocations, FunctionBody};
 use wasmtime_environ::{
-    AddressMapSection, BuiltinFunctionIndex, CacheStore, CompileError, DefinedFuncIndex, FlagValue,
-    FunctionBodyData, FunctionLoc, ModuleTranslation, ModuleTypesBuilder, PtrSize,
-    RelocationTarget, StackMapInformation, StaticModuleIndex, TrapEncodingBuilder, Tunables,
-    VMOffsets, WasmFuncType, WasmFunctionInfo, WasmValType,
+    AddressMapSection, BuiltinFunctionIndex, CacheStore, CompileError, DefinedFuncIndex,
+    ExceptionEncodingBuilder, FlagValue, FunctionBodyData, FunctionLoc, ModuleTranslation,
+    ModuleTypesBuilder, PtrSize, RelocationTarget, StackMapInformation, StaticModuleIndex,
+    TrapEncodingBuilder, Tunables, VMOffsets, WasmFuncType, WasmFunctionInfo, WasmValType,
 };
 
 #[cfg(feature = "component-model")]
@@ -230,7 +231,13 @@ impl wasmtime_environ::Compiler for Compiler {
             write!(output, "{}", context.func.display()).unwrap();
         }
 
-        let (info, func) = compiler.finish_with_info(Some((&body, &self.tunables)))?;
+        let frame_state = func_env.frame_state.take();
+        if let Some(frame_state) = &frame_state {
+            frame_state.finish(&mut compiler.cx.codegen_context.func);
+        }
+
+        let (info, func) =
+            compiler.finish_with_info(Some((&body, &self.tunables)), frame_state.as_ref())?;
 
         let timing = cranelift_codegen::timing::take_current();
         log::debug!("{:?} translated in {:?}", func_index, timing.total());
@@ -387,6 +394,7 @@ impl wasmtime_environ::Compiler for Compiler {
         }
         let mut addrs = AddressMapSection::default();
         let mut traps = TrapEncodingBuilder::default();
+        let mut exceptions = ExceptionEncodingBuilder::default();
 
         let mut ret = Vec::with_capacity(funcs.len());
         for (i, (sym, func)) in funcs.iter().enumerate() {
@@ -397,6 +405,10 @@ impl wasmtime_environ::Compiler for Compiler {
                 addrs.push(range.clone(), &addr.instructions);
             }
             traps.push(range.clone(), &func.traps().collect::<Vec<_>>());
+            exceptions.push(
+                range.clone(),
+                &func.exception_landing_pads().collect::<Vec<_>>(),
+            );
             builder.append_padding(self.linkopts.padding_between_functions);
             let info = FunctionLoc {
                 start: u32::try_from(range.start).unwrap(),
@@ -411,6 +423,7 @@ impl wasmtime_environ::Compiler for Compiler {
             addrs.append_to(obj);
         }
         traps.append_to(obj);
+        exceptions.append_to(obj);
 
         Ok(ret)
     }
@@ -798,7 +811,7 @@ impl FunctionCompiler<'_> {
     }
 
     fn finish(self) -> Result<CompiledFunction, CompileError> {
-        let (info, func) = self.finish_with_info(None)?;
+        let (info, func) = self.finish_with_info(None, None)?;
         assert!(info.stack_maps.is_empty());
         Ok(func)
     }
@@ -806,6 +819,7 @@ impl FunctionCompiler<'_> {
     fn finish_with_info(
         mut self,
         body_and_tunables: Option<(&FunctionBody<'_>, &Tunables)>,
+        frame_state: Option<&FrameState>,
     ) -> Result<(WasmFunctionInfo, CompiledFunction), CompileError> {
         let context = &mut self.cx.codegen_context;
         let isa = &*self.compiler.isa;
@@ -870,6 +884,13 @@ impl FunctionCompiler<'_> {
             }
         }
 
+        let frame_state = frame_state.map(|frame_state| {
+            frame_state.info(
+                &compiled_code.sized_stackslot_offsets,
+                compiled_code.frame_size,
+            )
+        });
+
         let stack_maps =
             clif_to_env_stack_maps(compiled_code.buffer.take_user_stack_maps().into_iter());
         compiled_function
@@ -880,6 +901,7 @@ impl FunctionCompiler<'_> {
             WasmFunctionInfo {
                 start_srcloc: compiled_function.metadata().address_map.start_srcloc,
                 stack_maps: stack_maps.into(),
+                frame_state,
             },
             compiled_function,
         ))
@@ -894,14 +916,21 @@ fn clif_to_env_stack_maps(
     let mut stack_maps = Vec::with_capacity(clif_stack_maps.len());
     for (code_offset, mapped_bytes, stack_map) in clif_stack_maps {
         let mut bitset = CompoundBitSet::new();
+        let mut cont_bitset = CompoundBitSet::new();
         for (ty, offset) in stack_map.entries() {
-            assert_eq!(ty, ir::types::I32);
-            bitset.insert(usize::try_from(offset).unwrap());
+            let offset = usize::try_from(offset).unwrap();
+            match ty {
+                ir::types::I32 => bitset.insert(offset),
+                // Continuation references are the only `i64` values that are
+                // included in stack maps.
+                ir::types::I64 => cont_bitset.insert(offset),
+                _ => unreachable!("unexpected type in stack map: {ty}"),
+            };
         }
-        if bitset.is_empty() {
+        if bitset.is_empty() && cont_bitset.is_empty() {
             continue;
         }
-        let stack_map = wasmtime_environ::StackMap::new(mapped_bytes, bitset);
+        let stack_map = wasmtime_environ::StackMap::new(mapped_bytes, bitset, cont_bitset);
         stack_maps.push(StackMapInformation {
             code_offset,
             stack_map,
@@ -0,0 +1,224 @@
+//! Recording of Wasm-level frame state, i.e. the values of locals and of the
+//! operand stack, into each frame so that it can be recovered in core dumps.
+//!
+//! Each function that records its frame state gets a single explicit stack
+//! slot laid out as follows:
+//!
+//! ```text
+//! | vmctx (8 bytes) | depth (u32) | padding |
+//! | local 0 (8 bytes) | local 1 | ...                  (numeric locals only)
+//! | operand 0 (16 bytes) | operand 1 | ...
+//! ```
+//!
+//! Locals are stored whenever they are written to. The operand stack is
+//! stored lazily before each operator, skipping any entries which are
+//! statically known to already hold the right value.
+
+use cranelift_codegen::entity::EntityRef;
+use cranelift_codegen::ir::{self, InstBuilder};
+use cranelift_entity::PrimaryMap;
+use cranelift_frontend::{FunctionBuilder, Variable};
+use wasmtime_environ::{
+    FrameStateInfo, FrameStateLocal, FrameStateOperandStack, WasmValType, FRAME_STATE_OPERAND_SIZE,
+    FRAME_STATE_TAG_F32, FRAME_STATE_TAG_F64, FRAME_STATE_TAG_I32, FRAME_STATE_TAG_I64,
+    FRAME_STATE_TAG_MISSING,
+};
+
+const VMCTX_OFFSET: u32 = 0;
+const DEPTH_OFFSET: u32 = 8;
+const LOCALS_OFFSET: u32 = 16;
+const LOCAL_SIZE: u32 = 8;
+const OPERAND_VALUE_OFFSET: u32 = 8;
+
+/// Returns the tag that an operand stack entry of type `ty` is recorded with.
+fn tag(ty: Option<WasmValType>) -> u32 {
+    match ty {
+        Some(WasmValType::I32) => FRAME_STATE_TAG_I32,
+        Some(WasmValType::I64) => FRAME_STATE_TAG_I64,
+        Some(WasmValType::F32) => FRAME_STATE_TAG_F32,
+        Some(WasmValType::F64) => FRAME_STATE_TAG_F64,
+        _ => FRAME_STATE_TAG_MISSING,
+    }
+}
+
+fn offset(offset: u32) -> i32 {
+    i32::try_from(offset).unwrap()
+}
+
+/// The frame state being recorded by the function that is currently being
+/// translated.
+pub(crate) struct FrameState {
+    slot: ir::StackSlot,
+    /// Each local's type and, if recorded, its offset within `slot`.
+    locals: Vec<(WasmValType, Option<u32>)>,
+    /// Offset within `slot` of the first operand stack entry.
+    operands_offset: u32,
+    /// The deepest operand stack that is recorded.
+    max_depth: u32,
+
+    /// The block in which the values below were stored. At the start of a
+    /// new block nothing is known about what's been stored.
+    known_block: Option<ir::Block>,
+    /// The currently stored depth of the operand stack, if known.
+    known_depth: Option<u32>,
+    /// The values and tags currently stored in each operand stack entry, if
+    /// known.
+    known_operands: Vec<Option<(ir::Value, u32)>>,
+}
+
+impl FrameState {
+    /// Creates the stack slot for a function with the given locals.
+    pub fn new(func: &mut ir::Function, locals: Vec<WasmValType>) -> FrameState {
+        let mut next = LOCALS_OFFSET;
+        let locals: Vec<_> = locals
+            .into_iter()
+            .map(|ty| {
+                let offset = match ty {
+                    WasmValType::I32 | WasmValType::I64 | WasmValType::F32 | WasmValType::F64 => {
+                        next += LOCAL_SIZE;
+                        Some(next - LOCAL_SIZE)
+                    }
+                    _ => None,
+                };
+                (ty, offset)
+            })
+            .collect();
+
+        // The final size of the slot is only known once the whole function
+        // has been translated, see `finish`.
+        let slot = func.create_sized_stack_slot(ir::StackSlotData::new(
+            ir::StackSlotKind::ExplicitSlot,
+            next,
+            3,
+        ));
+        FrameState {
+            slot,
+            locals,
+            operands_offset: next,
+            max_depth: 0,
+            known_block: None,
+            known_depth: None,
+            known_operands: Vec::new(),
+        }
+    }
+
+    /// Stores the function's `vmctx` and the initial values of its locals.
+    ///
+    /// This must be called at the start of the function's entry block.
+    pub fn function_entry(&mut self, builder: &mut FunctionBuilder, vmctx: ir::Value) {
+        builder
+            .ins()
+            .stack_store(vmctx, self.slot, offset(VMCTX_OFFSET));
+        for i in 0..self.locals.len() {
+            if let Some(local_offset) = self.locals[i].1 {
+                let val = builder.use_var(Variable::new(i));
+                builder
+                    .ins()
+                    .stack_store(val, self.slot, offset(local_offset));
+            }
+        }
+    }
+
+    /// Stores the current state of the operand stack, as well as the value of
+    /// any local written by `op`, just before `op` is executed.
+    ///
+    /// The `types` of the operand stack entries are those reported by the
+    /// validator, which should line up with the values in `stack`.
+    pub fn before_operator(
+        &mut self,
+        op: &wasmparser::Operator,
+        builder: &mut FunctionBuilder,
+        stack: &[ir::Value],
+        types: &[Option<WasmValType>],
+    ) {
+        let block = builder.current_block();
+        if block != self.known_block {
+            self.known_block = block;
+            self.known_depth = None;
+            self.known_operands.clear();
+        }
+
+        // If we can't tell what's on the operand stack then record it as
+        // empty rather than recording something wrong.
+        let operands = if stack.len() == types.len() {
+            stack
+        } else {
+            &[]
+        };
+
+        let depth = u32::try_from(operands.len()).unwrap();
+        if self.known_depth != Some(depth) {
+            let depth_val = builder.ins().iconst(ir::types::I32, i64::from(depth));
+            builder
+                .ins()
+                .stack_store(depth_val, self.slot, offset(DEPTH_OFFSET));
+            self.known_depth = Some(depth);
+        }
+        self.max_depth = self.max_depth.max(depth);
+
+        self.known_operands.resize(operands.len(), None);
+        for (i, (val, ty)) in operands.iter().zip(types).enumerate() {
+            let tag = tag(*ty);
+            if self.known_operands[i] == Some((*val, tag)) {
+                continue;
+            }
+            let entry = self.operands_offset + u32::try_from(i).unwrap() * FRAME_STATE_OPERAND_SIZE;
+            let tag_val = builder.ins().iconst(ir::types::I32, i64::from(tag));
+            builder.ins().stack_store(tag_val, self.slot, offset(entry));
+            if tag != FRAME_STATE_TAG_MISSING {
+                builder
+                    .ins()
+                    .stack_store(*val, self.slot, offset(entry + OPERAND_VALUE_OFFSET));
+            }
+            self.known_operands[i] = Some((*val, tag));
+        }
+
+        let local_index = match *op {
+            wasmparser::Operator::LocalSet { local_index }
+            | wasmparser::Operator::LocalTee { local_index } => local_index,
+            _ => return,
+        };
+        let local = &self.locals[usize::try_from(local_index).unwrap()];
+        if let (Some(local_offset), Some(val)) = (local.1, stack.last()) {
+            builder
+                .ins()
+                .stack_store(*val, self.slot, offset(local_offset));
+        }
+    }
+
+    /// Sizes the stack slot now that the deepest recorded operand stack is
+    /// known. This must be called before the function is compiled.
+    pub fn finish(&self, func: &mut ir::Function) {
+        func.sized_stack_slots[self.slot].size =
+            self.operands_offset + self.max_depth * FRAME_STATE_OPERAND_SIZE;
+    }
+
+    /// Describes the recorded state, given the offsets of the compiled
+    /// function's stack slots and the size of its frame.
+    pub fn info(
+        &self,
+        slot_offsets: &PrimaryMap<ir::StackSlot, u32>,
+        frame_size: u32,
+    ) -> FrameStateInfo {
+        // Stack slots are located relative to the bottom of the frame's fixed
+        // storage, which is `frame_size` bytes below the frame pointer.
+        let slot_offset = offset(slot_offsets[self.slot]) - offset(frame_size);
+        let fp_offset = |o: u32| slot_offset + offset(o);
+        FrameStateInfo {
+            vmctx_offset: fp_offset(VMCTX_OFFSET),
+            locals: self
+                .locals
+                .iter()
+                .map(|(ty, o)| FrameStateLocal {
+                    ty: *ty,
+                    offset: o.map(fp_offset),
+                })
+                .collect(),
+            operand_stack: Some(FrameStateOperandStack {
+                depth_offset: fp_offset(DEPTH_OFFSET),
+                entries_offset: fp_offset(self.operands_offset),
+                max_depth: self.max_depth,
+            }),
+        }
+    }
+}
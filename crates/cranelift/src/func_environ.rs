@@ -1,8 +1,9 @@
+use crate::frame_state::FrameState;
 use crate::translate::{
     FuncEnvironment as _, FuncTranslationState, GlobalVariable, Heap, HeapData, HeapStyle,
     StructFieldsVec, TableData, TableSize, TargetEnvironment,
 };
-use crate::{gc, BuiltinFunctionSignatures, TRAP_INTERNAL_ASSERT};
+use crate::{gc, stack_switching, BuiltinFunctionSignatures, TRAP_INTERNAL_ASSERT};
 use cranelift_codegen::cursor::FuncCursor;
 use cranelift_codegen::ir::condcodes::IntCC;
 use cranelift_codegen::ir::immediates::{Imm64, Offset32};
@@ -21,8 +22,8 @@ use wasmparser::{Operator, WasmFeatures};
 use wasmtime_environ::{
     BuiltinFunctionIndex, DataIndex, ElemIndex, EngineOrModuleTypeIndex, FuncIndex, GlobalIndex,
     IndexType, Memory, MemoryIndex, MemoryPlan, MemoryStyle, Module, ModuleInternedTypeIndex,
-    ModuleTranslation, ModuleTypesBuilder, PtrSize, Table, TableIndex, TableStyle, Tunables,
-    TypeConvert, TypeIndex, VMOffsets, WasmCompositeType, WasmFuncType, WasmHeapTopType,
+    ModuleTranslation, ModuleTypesBuilder, PtrSize, Table, TableIndex, TableStyle, TagIndex,
+    Tunables, TypeConvert, TypeIndex, VMOffsets, WasmCompositeType, WasmFuncType, WasmHeapTopType,
     WasmHeapType, WasmRefType, WasmResult, WasmValType,
 };
 use wasmtime_environ::{FUNCREF_INIT_BIT, FUNCREF_MASK};
@@ -84,7 +85,7 @@ wasmtime_environ::foreach_builtin_function!(declare_function_signatures);
 
 /// The `FuncEnvironment` implementation for use by the `ModuleEnvironment`.
 pub struct FuncEnvironment<'module_environment> {
-    isa: &'module_environment (dyn TargetIsa + 'module_environment),
+    pub(crate) isa: &'module_environment (dyn TargetIsa + 'module_environment),
     pub(crate) module: &'module_environment Module,
     pub(crate) types: &'module_environment ModuleTypesBuilder,
     wasm_func_ty: &'module_environment WasmFuncType,
@@ -118,7 +119,7 @@ pub struct FuncEnvironment<'module_environment> {
     /// Offsets to struct fields accessed by JIT code.
     pub(crate) offsets: VMOffsets<u8>,
 
-    tunables: &'module_environment Tunables,
+    pub(crate) tunables: &'module_environment Tunables,
 
     /// A function-local variable which stores the cached value of the amount of
     /// fuel remaining to execute. If used this is modified frequently so it's
@@ -158,6 +159,13 @@ pub struct FuncEnvironment<'module_environment> {
     /// always present even if this is a "leaf" function, as we have to call
     /// into the host to trap when signal handlers are disabled.
     pub(crate) stack_limit_at_function_entry: Option<ir::GlobalValue>,
+
+    /// The Wasm-level frame state recorded for core dumps, if enabled.
+    pub(crate) frame_state: Option<FrameState>,
+
+    /// Whether the code being translated is within a `try_table`, in which
+    /// case calls that may throw are made exception landing pads.
+    in_try_table: bool,
 }
 
 impl<'module_environment> FuncEnvironment<'module_environment> {
@@ -207,6 +215,17 @@ impl<'module_environment> FuncEnvironment<'module_environment> {
             translation,
 
             stack_limit_at_function_entry: None,
+            frame_state: None,
+            in_try_table: false,
+        }
+    }
+
+    /// Make the given call an exception landing pad if it is within a
+    /// `try_table`, so that an exception thrown by the callee resumes
+    /// execution just after it.
+    pub(crate) fn mark_exception_landing_pad(&self, builder: &mut FunctionBuilder, inst: ir::Inst) {
+        if self.in_try_table {
+            builder.func.dfg.set_exception_landing_pad(inst);
         }
     }
 
@@ -699,6 +718,22 @@ impl<'module_environment> FuncEnvironment<'module_environment> {
         self.module.memory_plans[index].memory
     }
 
+    /// Get the signature of the given tag, whose parameters are the types of
+    /// its exceptions' payload values.
+    pub(crate) fn tag_signature(&self, index: TagIndex) -> &WasmFuncType {
+        let ty = self.module.tags[index].signature.unwrap_module_type_index();
+        self.types[ty].unwrap_func()
+    }
+
+    /// Get the function type of the given continuation type, whose parameters
+    /// are the values its continuations are resumed with and whose results
+    /// are the values they eventually return.
+    pub(crate) fn cont_signature(&self, index: TypeIndex) -> &WasmFuncType {
+        let ty = self.module.types[index];
+        let func_ty = self.types[ty].unwrap_cont().func_type();
+        self.types[func_ty.unwrap_module_type_index()].unwrap_func()
+    }
+
     /// Get the Table for the given index.
     fn table(&self, index: TableIndex) -> Table {
         self.module.table_plans[index].table
@@ -1466,7 +1501,12 @@ impl<'a, 'func, 'module_env> Call<'a, 'func, 'module_env> {
             | WasmHeapType::ConcreteArray(_)
             | WasmHeapType::Struct
             | WasmHeapType::ConcreteStruct(_)
-            | WasmHeapType::None => {
+            | WasmHeapType::None
+            | WasmHeapType::Exn
+            | WasmHeapType::NoExn
+            | WasmHeapType::Cont
+            | WasmHeapType::ConcreteCont(_)
+            | WasmHeapType::NoCont => {
                 unreachable!()
             }
         }
@@ -1619,6 +1659,7 @@ impl<'a, 'func, 'module_env> Call<'a, 'func, 'module_env> {
             self.builder.ins().return_call(callee, args)
         } else {
             let inst = self.builder.ins().call(callee, args);
+            self.env.mark_exception_landing_pad(self.builder, inst);
             let results: SmallVec<[_; 4]> = self
                 .builder
                 .func
@@ -1651,6 +1692,7 @@ impl<'a, 'func, 'module_env> Call<'a, 'func, 'module_env> {
                 .return_call_indirect(sig_ref, func_addr, args)
         } else {
             let inst = self.builder.ins().call_indirect(sig_ref, func_addr, args);
+            self.env.mark_exception_landing_pad(self.builder, inst);
             let results: SmallVec<[_; 4]> = self
                 .builder
                 .func
@@ -1688,7 +1730,10 @@ impl<'module_environment> TargetEnvironment for FuncEnvironment<'module_environm
     fn reference_type(&self, wasm_ty: WasmHeapType) -> (ir::Type, bool) {
         let ty = crate::reference_type(wasm_ty, self.pointer_type());
         let needs_stack_map = match wasm_ty.top() {
-            WasmHeapTopType::Extern | WasmHeapTopType::Any => true,
+            WasmHeapTopType::Extern
+            | WasmHeapTopType::Any
+            | WasmHeapTopType::Exn
+            | WasmHeapTopType::Cont => true,
             WasmHeapTopType::Func => false,
         };
         (ty, needs_stack_map)
@@ -1722,12 +1767,12 @@ impl<'module_environment> crate::translate::FuncEnvironment
             return false;
         }
 
-        self.wasm_func_ty.params()[index - 2].is_vmgcref_type_and_not_i31()
+        crate::needs_stack_map(self.wasm_func_ty.params()[index - 2])
     }
 
     fn sig_ref_result_needs_stack_map(&self, sig_ref: ir::SigRef, index: usize) -> bool {
         let wasm_func_ty = self.sig_ref_to_ty[sig_ref].as_ref().unwrap();
-        wasm_func_ty.returns()[index].is_vmgcref_type_and_not_i31()
+        crate::needs_stack_map(wasm_func_ty.returns()[index])
     }
 
     fn func_ref_result_needs_stack_map(
@@ -1738,7 +1783,7 @@ impl<'module_environment> crate::translate::FuncEnvironment
     ) -> bool {
         let sig_ref = func.dfg.ext_funcs[func_ref].signature;
         let wasm_func_ty = self.sig_ref_to_ty[sig_ref].as_ref().unwrap();
-        wasm_func_ty.returns()[index].is_vmgcref_type_and_not_i31()
+        crate::needs_stack_map(wasm_func_ty.returns()[index])
     }
 
     fn after_locals(&mut self, num_locals: usize) {
@@ -1788,7 +1833,7 @@ impl<'module_environment> crate::translate::FuncEnvironment
         let heap_ty = table.ref_type.heap_type;
         match heap_ty.top() {
             // GC-managed types.
-            WasmHeapTopType::Any | WasmHeapTopType::Extern => {
+            WasmHeapTopType::Any | WasmHeapTopType::Extern | WasmHeapTopType::Exn => {
                 let (src, flags) = table_data.prepare_table_addr(self, builder, index);
                 gc::gc_compiler(self)?.translate_read_gc_reference(
                     self,
@@ -1810,6 +1855,9 @@ impl<'module_environment> crate::translate::FuncEnvironment
                         lazy_init,
                     )),
             },
+
+            // Tables of continuations are rejected during translation.
+            WasmHeapTopType::Cont => unreachable!(),
         }
     }
 
@@ -1827,7 +1875,7 @@ impl<'module_environment> crate::translate::FuncEnvironment
         let heap_ty = table.ref_type.heap_type;
         match heap_ty.top() {
             // GC-managed types.
-            WasmHeapTopType::Any | WasmHeapTopType::Extern => {
+            WasmHeapTopType::Any | WasmHeapTopType::Extern | WasmHeapTopType::Exn => {
                 let (dst, flags) = table_data.prepare_table_addr(self, builder, index);
                 gc::gc_compiler(self)?.translate_write_gc_reference(
                     self,
@@ -1862,6 +1910,9 @@ impl<'module_environment> crate::translate::FuncEnvironment
                     }
                 }
             }
+
+            // Tables of continuations are rejected during translation.
+            WasmHeapTopType::Cont => unreachable!(),
         }
     }
 
@@ -2228,6 +2279,142 @@ impl<'module_environment> crate::translate::FuncEnvironment
         gc::translate_ref_test(self, builder, ref_ty, gc_ref)
     }
 
+    fn set_in_try_table(&mut self, in_try_table: bool) {
+        self.in_try_table = in_try_table;
+    }
+
+    fn translate_throw(
+        &mut self,
+        builder: &mut FunctionBuilder<'_>,
+        tag_index: TagIndex,
+        args: &[ir::Value],
+    ) -> WasmResult<()> {
+        gc::translate_throw(self, builder, tag_index, args)
+    }
+
+    fn translate_throw_ref(
+        &mut self,
+        builder: &mut FunctionBuilder<'_>,
+        exn: ir::Value,
+    ) -> WasmResult<()> {
+        gc::translate_throw_ref(self, builder, exn)
+    }
+
+    fn translate_has_pending_exception(
+        &mut self,
+        builder: &mut FunctionBuilder<'_>,
+    ) -> WasmResult<ir::Value> {
+        gc::translate_has_pending_exception(self, builder)
+    }
+
+    fn translate_pending_exception_matches(
+        &mut self,
+        builder: &mut FunctionBuilder<'_>,
+        tag_index: TagIndex,
+    ) -> WasmResult<ir::Value> {
+        gc::translate_pending_exception_matches(self, builder, tag_index)
+    }
+
+    fn translate_take_pending_exception(
+        &mut self,
+        builder: &mut FunctionBuilder<'_>,
+    ) -> WasmResult<ir::Value> {
+        gc::translate_take_pending_exception(self, builder)
+    }
+
+    fn translate_exception_payload(
+        &mut self,
+        builder: &mut FunctionBuilder<'_>,
+        tag_index: TagIndex,
+        exn: ir::Value,
+    ) -> WasmResult<SmallVec<[ir::Value; 4]>> {
+        gc::translate_exception_payload(self, builder, tag_index, exn)
+    }
+
+    fn tag_arity(&self, tag_index: TagIndex) -> usize {
+        self.tag_signature(tag_index).params().len()
+    }
+
+    fn translate_cont_new(
+        &mut self,
+        builder: &mut FunctionBuilder<'_>,
+        func: ir::Value,
+    ) -> WasmResult<ir::Value> {
+        stack_switching::translate_cont_new(self, builder, func)
+    }
+
+    fn translate_cont_bind(
+        &mut self,
+        builder: &mut FunctionBuilder<'_>,
+        argument_type_index: TypeIndex,
+        cont: ir::Value,
+        args: &[ir::Value],
+    ) -> WasmResult<ir::Value> {
+        stack_switching::translate_cont_bind(self, builder, argument_type_index, cont, args)
+    }
+
+    fn translate_resume(
+        &mut self,
+        builder: &mut FunctionBuilder<'_>,
+        cont_type_index: TypeIndex,
+        throw: Option<TagIndex>,
+        cont: ir::Value,
+        args: &[ir::Value],
+        handlers: &[wasmparser::Handle],
+    ) -> WasmResult<(ir::Value, ir::Value)> {
+        stack_switching::translate_resume(
+            self,
+            builder,
+            cont_type_index,
+            throw,
+            cont,
+            args,
+            handlers,
+        )
+    }
+
+    fn translate_resume_results(
+        &mut self,
+        builder: &mut FunctionBuilder<'_>,
+        cont_type_index: TypeIndex,
+        buffer: ir::Value,
+    ) -> WasmResult<SmallVec<[ir::Value; 4]>> {
+        stack_switching::translate_resume_results(self, builder, cont_type_index, buffer)
+    }
+
+    fn translate_resume_payload(
+        &mut self,
+        builder: &mut FunctionBuilder<'_>,
+        tag_index: TagIndex,
+        buffer: ir::Value,
+    ) -> WasmResult<SmallVec<[ir::Value; 4]>> {
+        stack_switching::translate_resume_payload(self, builder, tag_index, buffer)
+    }
+
+    fn translate_suspend(
+        &mut self,
+        builder: &mut FunctionBuilder<'_>,
+        tag_index: TagIndex,
+        args: &[ir::Value],
+    ) -> WasmResult<SmallVec<[ir::Value; 4]>> {
+        stack_switching::translate_suspend(self, builder, tag_index, args)
+    }
+
+    fn translate_switch(
+        &mut self,
+        builder: &mut FunctionBuilder<'_>,
+        cont_type_index: TypeIndex,
+        tag_index: TagIndex,
+        cont: ir::Value,
+        args: &[ir::Value],
+    ) -> WasmResult<SmallVec<[ir::Value; 4]>> {
+        stack_switching::translate_switch(self, builder, cont_type_index, tag_index, cont, args)
+    }
+
+    fn cont_arity(&self, cont_type_index: TypeIndex) -> usize {
+        self.cont_signature(cont_type_index).params().len()
+    }
+
     fn translate_ref_null(
         &mut self,
         mut pos: cranelift_codegen::cursor::FuncCursor,
@@ -2236,7 +2423,10 @@ impl<'module_environment> crate::translate::FuncEnvironment
         Ok(match ht.top() {
             WasmHeapTopType::Func => pos.ins().iconst(self.pointer_type(), 0),
             // NB: null GC references don't need to be in stack maps.
-            WasmHeapTopType::Any | WasmHeapTopType::Extern => pos.ins().iconst(types::I32, 0),
+            WasmHeapTopType::Any | WasmHeapTopType::Extern | WasmHeapTopType::Exn => {
+                pos.ins().iconst(types::I32, 0)
+            }
+            WasmHeapTopType::Cont => pos.ins().iconst(types::I64, 0),
         })
     }
 
@@ -3091,6 +3281,39 @@ impl<'module_environment> crate::translate::FuncEnvironment
         Ok(())
     }
 
+    fn records_frame_state(&self) -> bool {
+        // Frame state is located relative to the frame pointer, assuming that
+        // it points just above the function's clobbers and stack slots.
+        self.tunables.record_frame_state
+            && matches!(
+                self.isa.triple().architecture,
+                target_lexicon::Architecture::X86_64
+                    | target_lexicon::Architecture::Aarch64(_)
+                    | target_lexicon::Architecture::Riscv64(_)
+            )
+    }
+
+    fn declare_frame_state_locals(
+        &mut self,
+        builder: &mut FunctionBuilder,
+        locals: Vec<WasmValType>,
+    ) {
+        self.frame_state = Some(FrameState::new(builder.func, locals));
+    }
+
+    fn before_translate_operator_with_types(
+        &mut self,
+        op: &Operator,
+        builder: &mut FunctionBuilder,
+        state: &FuncTranslationState,
+        operand_types: &[Option<WasmValType>],
+    ) -> WasmResult<()> {
+        if let Some(frame_state) = &mut self.frame_state {
+            frame_state.before_operator(op, builder, &state.stack, operand_types);
+        }
+        Ok(())
+    }
+
     fn before_translate_operator(
         &mut self,
         op: &Operator,
@@ -3140,6 +3363,15 @@ impl<'module_environment> crate::translate::FuncEnvironment
             self.conditionally_trap(builder, overflow, ir::TrapCode::STACK_OVERFLOW);
         }
 
+        // Record the initial frame state once we know there's room for it.
+        if self.frame_state.is_some() {
+            let vmctx = self.vmctx_val(&mut builder.cursor());
+            self.frame_state
+                .as_mut()
+                .unwrap()
+                .function_entry(builder, vmctx);
+        }
+
         // If the `vmruntime_limits_ptr` variable will get used then we initialize
         // it here.
         if self.tunables.consume_fuel || self.tunables.epoch_interruption {
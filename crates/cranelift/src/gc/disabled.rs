@@ -4,7 +4,8 @@ use super::GcCompiler;
 use crate::func_environ::FuncEnvironment;
 use cranelift_codegen::ir;
 use cranelift_frontend::FunctionBuilder;
-use wasmtime_environ::{wasm_unsupported, TypeIndex, WasmRefType, WasmResult};
+use smallvec::SmallVec;
+use wasmtime_environ::{wasm_unsupported, TagIndex, TypeIndex, WasmRefType, WasmResult};
 
 fn disabled<T>() -> WasmResult<T> {
     Err(wasm_unsupported!(
@@ -173,3 +174,51 @@ pub fn translate_ref_test(
 ) -> WasmResult<ir::Value> {
     disabled()
 }
+
+pub fn translate_throw(
+    _func_env: &mut FuncEnvironment<'_>,
+    _builder: &mut FunctionBuilder<'_>,
+    _tag_index: TagIndex,
+    _args: &[ir::Value],
+) -> WasmResult<()> {
+    disabled()
+}
+
+pub fn translate_throw_ref(
+    _func_env: &mut FuncEnvironment<'_>,
+    _builder: &mut FunctionBuilder<'_>,
+    _exn: ir::Value,
+) -> WasmResult<()> {
+    disabled()
+}
+
+pub fn translate_has_pending_exception(
+    _func_env: &mut FuncEnvironment<'_>,
+    _builder: &mut FunctionBuilder<'_>,
+) -> WasmResult<ir::Value> {
+    disabled()
+}
+
+pub fn translate_pending_exception_matches(
+    _func_env: &mut FuncEnvironment<'_>,
+    _builder: &mut FunctionBuilder<'_>,
+    _tag_index: TagIndex,
+) -> WasmResult<ir::Value> {
+    disabled()
+}
+
+pub fn translate_take_pending_exception(
+    _func_env: &mut FuncEnvironment<'_>,
+    _builder: &mut FunctionBuilder<'_>,
+) -> WasmResult<ir::Value> {
+    disabled()
+}
+
+pub fn translate_exception_payload(
+    _func_env: &mut FuncEnvironment<'_>,
+    _builder: &mut FunctionBuilder<'_>,
+    _tag_index: TagIndex,
+    _exn: ir::Value,
+) -> WasmResult<SmallVec<[ir::Value; 4]>> {
+    disabled()
+}
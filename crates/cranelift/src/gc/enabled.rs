@@ -9,17 +9,28 @@ use cranelift_codegen::{
 };
 use cranelift_entity::packed_option::ReservedValue;
 use cranelift_frontend::FunctionBuilder;
+use smallvec::SmallVec;
 use wasmtime_environ::{
-    GcArrayLayout, GcLayout, GcStructLayout, ModuleInternedTypeIndex, PtrSize, TypeIndex, VMGcKind,
-    WasmCompositeType, WasmHeapTopType, WasmHeapType, WasmRefType, WasmResult, WasmStorageType,
-    WasmValType, I31_DISCRIMINANT, NON_NULL_NON_I31_MASK,
+    wasm_unsupported, Collector, GcArrayLayout, GcLayout, GcStructLayout, ModuleInternedTypeIndex,
+    PtrSize, TagIndex, TypeIndex, VMGcKind, WasmCompositeType, WasmHeapTopType, WasmHeapType,
+    WasmRefType, WasmResult, WasmStorageType, WasmValType, I31_DISCRIMINANT, NON_NULL_NON_I31_MASK,
 };
 
 mod drc;
-
-/// Get the default GC compiler.
-pub fn gc_compiler(_func_env: &FuncEnvironment<'_>) -> WasmResult<Box<dyn GcCompiler>> {
-    Ok(Box::new(drc::DrcCompiler::default()))
+mod mark_sweep;
+mod null;
+
+/// Get the GC compiler for the collector configured in this function's
+/// tunables.
+pub fn gc_compiler(func_env: &FuncEnvironment<'_>) -> WasmResult<Box<dyn GcCompiler>> {
+    match func_env.tunables.collector {
+        Some(Collector::DeferredReferenceCounting) => Ok(Box::new(drc::DrcCompiler::default())),
+        Some(Collector::MarkSweep) => Ok(Box::new(mark_sweep::MarkSweepCompiler::default())),
+        Some(Collector::Null) => Ok(Box::new(null::NullCompiler::default())),
+        None => Err(wasm_unsupported!(
+            "support for GC types disabled at configuration time"
+        )),
+    }
 }
 
 fn unbarriered_load_gc_ref(
@@ -48,6 +59,95 @@ fn unbarriered_store_gc_ref(
     Ok(())
 }
 
+/// Emit CLIF to compute an array object's total size, given the dynamic length
+/// in its initialization.
+///
+/// Traps if the size overflows.
+fn emit_array_size(
+    builder: &mut FunctionBuilder<'_>,
+    array_layout: &GcArrayLayout,
+    init: ArrayInit<'_>,
+) -> ir::Value {
+    let base_size = builder
+        .ins()
+        .iconst(ir::types::I32, i64::from(array_layout.base_size));
+    let len = match init {
+        ArrayInit::Fill { elem: _, len } => len,
+        ArrayInit::Elems(elems) => builder
+            .ins()
+            .iconst(ir::types::I32, i64::try_from(elems.len()).unwrap()),
+    };
+
+    // `elems_size = len * elem_size`
+    //
+    // Check for multiplication overflow and trap if it occurs, since that
+    // means Wasm is attempting to allocate an array that is larger than our
+    // implementation limits. (Note: there is no standard implementation
+    // limit for array length beyond `u32::MAX`.)
+    //
+    // We implement this check by encoding our logically-32-bit operands as
+    // i64 values, doing a 64-bit multiplication, and then checking the high
+    // 32 bits of the multiplication's result. If the high 32 bits are not
+    // all zeros, then the multiplication overflowed.
+    let len = builder.ins().uextend(ir::types::I64, len);
+    let elems_size_64 = builder
+        .ins()
+        .imul_imm(len, i64::from(array_layout.elem_size));
+    let high_bits = builder.ins().ushr_imm(elems_size_64, 32);
+    builder
+        .ins()
+        .trapnz(high_bits, crate::TRAP_ALLOCATION_TOO_LARGE);
+    let elems_size = builder.ins().ireduce(ir::types::I32, elems_size_64);
+
+    // And if adding the base size and elements size overflows, then the
+    // allocation is too large.
+    let size =
+        builder
+            .ins()
+            .uadd_overflow_trap(base_size, elems_size, crate::TRAP_ALLOCATION_TOO_LARGE);
+
+    // NB: No need to check that the array's size can fit within the unused bits
+    // of a `VMGcKind`, even though our collectors store the object's size in
+    // the kind's unused bits. The `gc_alloc_raw` libcall will call the
+    // collector's `GcHeap::alloc_raw` which will perform that check itself.
+
+    size
+}
+
+/// Emit CLIF to call the `gc_raw_alloc` libcall.
+///
+/// It is the caller's responsibility to ensure that `size` fits within the
+/// `VMGcKind`'s unused bits.
+fn emit_gc_raw_alloc(
+    func_env: &mut FuncEnvironment<'_>,
+    builder: &mut FunctionBuilder<'_>,
+    kind: VMGcKind,
+    ty: ModuleInternedTypeIndex,
+    size: ir::Value,
+    align: u32,
+) -> ir::Value {
+    let gc_alloc_raw_builtin = func_env.builtin_functions.gc_alloc_raw(builder.func);
+    let vmctx = func_env.vmctx_val(&mut builder.cursor());
+
+    let kind = builder
+        .ins()
+        .iconst(ir::types::I32, i64::from(kind.as_u32()));
+
+    let ty = builder.ins().iconst(ir::types::I32, i64::from(ty.as_u32()));
+
+    assert!(align.is_power_of_two());
+    let align = builder.ins().iconst(ir::types::I32, i64::from(align));
+
+    let call_inst = builder
+        .ins()
+        .call(gc_alloc_raw_builtin, &[vmctx, kind, ty, size, align]);
+
+    let gc_ref = builder.func.dfg.first_result(call_inst);
+    builder.declare_value_needs_stack_map(gc_ref);
+
+    gc_ref
+}
+
 enum Extension {
     Sign,
     Zero,
@@ -84,8 +184,10 @@ fn read_field_at_addr(
             WasmValType::F64 => builder.ins().load(ir::types::F64, flags, addr, 0),
             WasmValType::V128 => builder.ins().load(ir::types::I128, flags, addr, 0),
             WasmValType::Ref(r) => match r.heap_type.top() {
-                WasmHeapTopType::Any | WasmHeapTopType::Extern => gc_compiler(func_env)?
-                    .translate_read_gc_reference(func_env, builder, r, addr, flags)?,
+                WasmHeapTopType::Any | WasmHeapTopType::Extern | WasmHeapTopType::Exn => {
+                    gc_compiler(func_env)?
+                        .translate_read_gc_reference(func_env, builder, r, addr, flags)?
+                }
                 WasmHeapTopType::Func => {
                     let expected_ty = match r.heap_type {
                         WasmHeapType::Func => ModuleInternedTypeIndex::reserved_value(),
@@ -120,6 +222,9 @@ fn read_field_at_addr(
                         .call(get_interned_func_ref, &[vmctx, func_ref_id, expected_ty]);
                     builder.func.dfg.first_result(call_inst)
                 }
+                WasmHeapTopType::Cont => {
+                    unreachable!("continuations are rejected as struct and array fields")
+                }
             },
         },
     };
@@ -864,6 +969,14 @@ pub fn translate_ref_test(
         return Ok(result);
     }
 
+    // Continuation references don't carry any runtime type information to test
+    // against a concrete continuation type.
+    if let WasmHeapType::ConcreteCont(_) = ref_ty.heap_type {
+        return Err(wasm_unsupported!(
+            "`ref.test` and `ref.cast` to concrete continuation types"
+        ));
+    }
+
     // `i31ref`s are a little interesting because they don't point to GC
     // objects; we test the bit pattern of the reference itself.
     if ref_ty.heap_type == WasmHeapType::I31 {
@@ -981,7 +1094,14 @@ pub fn translate_ref_test(
         | WasmHeapType::NoExtern
         | WasmHeapType::Func
         | WasmHeapType::NoFunc
-        | WasmHeapType::I31 => unreachable!("handled top, bottom, and i31 types above"),
+        | WasmHeapType::I31
+        | WasmHeapType::Exn
+        | WasmHeapType::NoExn
+        | WasmHeapType::Cont
+        | WasmHeapType::NoCont
+        | WasmHeapType::ConcreteCont(_) => {
+            unreachable!("handled top, bottom, i31, and continuation types above")
+        }
 
         // For these abstract but non-top and non-bottom types, we check the
         // `VMGcKind` that is in the object's header.
@@ -1093,6 +1213,185 @@ fn uextend_i32_to_pointer_type(
     }
 }
 
+/// Spill the given values into a stack-allocated `ValRaw` array, returning a
+/// pointer to it, for passing to the exception-related libcalls.
+fn spill_values_to_stack(
+    func_env: &mut FuncEnvironment<'_>,
+    builder: &mut FunctionBuilder<'_>,
+    len: usize,
+) -> ir::Value {
+    let value_size = core::mem::size_of::<u128>();
+    let slot = builder.func.create_sized_stack_slot(ir::StackSlotData::new(
+        ir::StackSlotKind::ExplicitSlot,
+        u32::try_from(value_size * len).unwrap(),
+        4,
+    ));
+    builder.ins().stack_addr(func_env.pointer_type(), slot, 0)
+}
+
+/// Memory flags for accessing the `ValRaw` arrays that carry exception
+/// payloads to and from the runtime.
+///
+/// Like the array calling convention, these are always little-endian.
+fn val_raw_flags() -> ir::MemFlags {
+    ir::MemFlags::new()
+        .with_notrap()
+        .with_endianness(ir::Endianness::Little)
+}
+
+/// Check that exceptions with the given tag can be allocated.
+///
+/// Continuation references can't be stored in the GC heap, where the runtime
+/// could not tell whether they are still reachable.
+pub fn check_exception_payload(
+    func_env: &mut FuncEnvironment<'_>,
+    tag_index: TagIndex,
+) -> WasmResult<()> {
+    if func_env
+        .tag_signature(tag_index)
+        .params()
+        .iter()
+        .any(|ty| ty.is_cont_ref())
+    {
+        return Err(wasm_unsupported!(
+            "exceptions with continuation references in their payload"
+        ));
+    }
+    Ok(())
+}
+
+pub fn translate_throw(
+    func_env: &mut FuncEnvironment<'_>,
+    builder: &mut FunctionBuilder<'_>,
+    tag_index: TagIndex,
+    args: &[ir::Value],
+) -> WasmResult<()> {
+    // Make sure that a collector is available to allocate the exception.
+    gc_compiler(func_env)?;
+    check_exception_payload(func_env, tag_index)?;
+
+    let values = spill_values_to_stack(func_env, builder, args.len());
+    for (i, arg) in args.iter().copied().enumerate() {
+        crate::unbarriered_store_type_at_offset(
+            &mut builder.cursor(),
+            val_raw_flags(),
+            values,
+            i32::try_from(i * core::mem::size_of::<u128>()).unwrap(),
+            arg,
+        );
+    }
+
+    let throw = func_env.builtin_functions.throw(builder.func);
+    let vmctx = func_env.vmctx_val(&mut builder.cursor());
+    let tag = builder
+        .ins()
+        .iconst(ir::types::I32, i64::from(tag_index.as_u32()));
+    let call_inst = builder.ins().call(throw, &[vmctx, tag, values]);
+    func_env.mark_exception_landing_pad(builder, call_inst);
+    Ok(())
+}
+
+pub fn translate_throw_ref(
+    func_env: &mut FuncEnvironment<'_>,
+    builder: &mut FunctionBuilder<'_>,
+    exn: ir::Value,
+) -> WasmResult<()> {
+    let throw_ref = func_env.builtin_functions.throw_ref(builder.func);
+    let vmctx = func_env.vmctx_val(&mut builder.cursor());
+    let call_inst = builder.ins().call(throw_ref, &[vmctx, exn]);
+    func_env.mark_exception_landing_pad(builder, call_inst);
+    Ok(())
+}
+
+pub fn translate_has_pending_exception(
+    func_env: &mut FuncEnvironment<'_>,
+    builder: &mut FunctionBuilder<'_>,
+) -> WasmResult<ir::Value> {
+    let pointer_type = func_env.pointer_type();
+    let vmctx = func_env.vmctx_val(&mut builder.cursor());
+    let limits = builder.ins().load(
+        pointer_type,
+        ir::MemFlags::trusted().with_readonly(),
+        vmctx,
+        i32::from(func_env.offsets.ptr.vmctx_runtime_limits()),
+    );
+    Ok(builder.ins().load(
+        ir::types::I32,
+        ir::MemFlags::trusted(),
+        limits,
+        i32::from(func_env.offsets.ptr.vmruntime_limits_pending_exception()),
+    ))
+}
+
+pub fn translate_pending_exception_matches(
+    func_env: &mut FuncEnvironment<'_>,
+    builder: &mut FunctionBuilder<'_>,
+    tag_index: TagIndex,
+) -> WasmResult<ir::Value> {
+    let matches = func_env
+        .builtin_functions
+        .pending_exception_matches(builder.func);
+    let vmctx = func_env.vmctx_val(&mut builder.cursor());
+    let tag = builder
+        .ins()
+        .iconst(ir::types::I32, i64::from(tag_index.as_u32()));
+    let call_inst = builder.ins().call(matches, &[vmctx, tag]);
+    Ok(builder.func.dfg.first_result(call_inst))
+}
+
+pub fn translate_take_pending_exception(
+    func_env: &mut FuncEnvironment<'_>,
+    builder: &mut FunctionBuilder<'_>,
+) -> WasmResult<ir::Value> {
+    let take = func_env
+        .builtin_functions
+        .take_pending_exception(builder.func);
+    let vmctx = func_env.vmctx_val(&mut builder.cursor());
+    let call_inst = builder.ins().call(take, &[vmctx]);
+    let exn = builder.func.dfg.first_result(call_inst);
+    builder.declare_value_needs_stack_map(exn);
+    Ok(exn)
+}
+
+pub fn translate_exception_payload(
+    func_env: &mut FuncEnvironment<'_>,
+    builder: &mut FunctionBuilder<'_>,
+    tag_index: TagIndex,
+    exn: ir::Value,
+) -> WasmResult<SmallVec<[ir::Value; 4]>> {
+    let types: SmallVec<[WasmValType; 4]> = func_env
+        .tag_signature(tag_index)
+        .params()
+        .iter()
+        .copied()
+        .collect();
+    if types.is_empty() {
+        return Ok(SmallVec::new());
+    }
+
+    let values = spill_values_to_stack(func_env, builder, types.len());
+    let payload = func_env.builtin_functions.exception_payload(builder.func);
+    let vmctx = func_env.vmctx_val(&mut builder.cursor());
+    builder.ins().call(payload, &[vmctx, exn, values]);
+
+    let mut results = SmallVec::new();
+    for (i, ty) in types.into_iter().enumerate() {
+        let val = crate::unbarriered_load_type_at_offset(
+            func_env.isa,
+            &mut builder.cursor(),
+            ty,
+            val_raw_flags(),
+            values,
+            i32::try_from(i * core::mem::size_of::<u128>()).unwrap(),
+        );
+        if ty.is_vmgcref_type_and_not_i31() {
+            builder.declare_value_needs_stack_map(val);
+        }
+        results.push(val);
+    }
+    Ok(results)
+}
+
 impl FuncEnvironment<'_> {
     fn gc_layout(&mut self, type_index: ModuleInternedTypeIndex) -> &GcLayout {
         // Lazily compute and cache the layout.
@@ -1247,14 +1546,20 @@ impl FuncEnvironment<'_> {
             | WasmHeapType::None => false,
 
             // Wrong type hierarchy: cannot be an i31.
-            WasmHeapType::Extern | WasmHeapType::NoExtern => false,
+            WasmHeapType::Extern
+            | WasmHeapType::NoExtern
+            | WasmHeapType::Exn
+            | WasmHeapType::NoExn => false,
 
             // Wrong type hierarchy, and also funcrefs are not GC-managed
             // types. Should have been caught by the assertion at the start of
             // the function.
-            WasmHeapType::Func | WasmHeapType::ConcreteFunc(_) | WasmHeapType::NoFunc => {
-                unreachable!()
-            }
+            WasmHeapType::Func
+            | WasmHeapType::ConcreteFunc(_)
+            | WasmHeapType::NoFunc
+            | WasmHeapType::Cont
+            | WasmHeapType::ConcreteCont(_)
+            | WasmHeapType::NoCont => unreachable!(),
         };
 
         match (ty.nullable, might_be_i31) {
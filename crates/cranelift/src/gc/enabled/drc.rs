@@ -2,8 +2,8 @@
 //! barriers.
 
 use super::{
-    emit_array_fill_impl, uextend_i32_to_pointer_type, unbarriered_load_gc_ref,
-    unbarriered_store_gc_ref, write_func_ref_at_addr, BoundsCheck, Offset,
+    emit_array_fill_impl, emit_array_size, emit_gc_raw_alloc, uextend_i32_to_pointer_type,
+    unbarriered_load_gc_ref, unbarriered_store_gc_ref, write_func_ref_at_addr, BoundsCheck, Offset,
 };
 use crate::gc::{gc_compiler, ArrayInit};
 use crate::translate::TargetEnvironment;
@@ -13,9 +13,8 @@ use cranelift_codegen::ir::{self, InstBuilder};
 use cranelift_frontend::FunctionBuilder;
 use smallvec::SmallVec;
 use wasmtime_environ::{
-    drc::DrcTypeLayouts, GcArrayLayout, GcTypeLayouts, ModuleInternedTypeIndex, PtrSize, TypeIndex,
-    VMGcKind, WasmCompositeType, WasmHeapTopType, WasmHeapType, WasmRefType, WasmResult,
-    WasmStorageType, WasmValType,
+    drc::DrcTypeLayouts, GcTypeLayouts, PtrSize, TypeIndex, VMGcKind, WasmCompositeType,
+    WasmHeapTopType, WasmHeapType, WasmRefType, WasmResult, WasmStorageType, WasmValType,
 };
 
 #[derive(Default)]
@@ -258,96 +257,6 @@ impl DrcCompiler {
     }
 }
 
-/// Emit CLIF to compute an array object's total size, given the dynamic length
-/// in its initialization.
-///
-/// Traps if the size overflows.
-fn emit_array_size(
-    builder: &mut FunctionBuilder<'_>,
-    array_layout: &GcArrayLayout,
-    init: ArrayInit<'_>,
-) -> ir::Value {
-    let base_size = builder
-        .ins()
-        .iconst(ir::types::I32, i64::from(array_layout.base_size));
-    let len = match init {
-        ArrayInit::Fill { elem: _, len } => len,
-        ArrayInit::Elems(elems) => builder
-            .ins()
-            .iconst(ir::types::I32, i64::try_from(elems.len()).unwrap()),
-    };
-
-    // `elems_size = len * elem_size`
-    //
-    // Check for multiplication overflow and trap if it occurs, since that
-    // means Wasm is attempting to allocate an array that is larger than our
-    // implementation limits. (Note: there is no standard implementation
-    // limit for array length beyond `u32::MAX`.)
-    //
-    // We implement this check by encoding our logically-32-bit operands as
-    // i64 values, doing a 64-bit multiplication, and then checking the high
-    // 32 bits of the multiplication's result. If the high 32 bits are not
-    // all zeros, then the multiplication overflowed.
-    let len = builder.ins().uextend(ir::types::I64, len);
-    let elems_size_64 = builder
-        .ins()
-        .imul_imm(len, i64::from(array_layout.elem_size));
-    let high_bits = builder.ins().ushr_imm(elems_size_64, 32);
-    builder
-        .ins()
-        .trapnz(high_bits, crate::TRAP_ALLOCATION_TOO_LARGE);
-    let elems_size = builder.ins().ireduce(ir::types::I32, elems_size_64);
-
-    // And if adding the base size and elements size overflows, then the
-    // allocation is too large.
-    let size =
-        builder
-            .ins()
-            .uadd_overflow_trap(base_size, elems_size, crate::TRAP_ALLOCATION_TOO_LARGE);
-
-    // NB: No need to check that the array's size can fit within the unused bits
-    // of a `VMGcKind`, even though the DRC collector stores the object's size
-    // in the kind's unused bits. The `gc_alloc_raw` libcall will call
-    // `<DrcCollector as GcRuntime>::alloc_raw` which will perform that check
-    // itself.
-
-    size
-}
-
-/// Emit CLIF to call the `gc_raw_alloc` libcall.
-///
-/// It is the caller's responsibility to ensure that `size` fits within the
-/// `VMGcKind`'s unused bits.
-fn emit_gc_raw_alloc(
-    func_env: &mut FuncEnvironment<'_>,
-    builder: &mut FunctionBuilder<'_>,
-    kind: VMGcKind,
-    ty: ModuleInternedTypeIndex,
-    size: ir::Value,
-    align: u32,
-) -> ir::Value {
-    let gc_alloc_raw_builtin = func_env.builtin_functions.gc_alloc_raw(builder.func);
-    let vmctx = func_env.vmctx_val(&mut builder.cursor());
-
-    let kind = builder
-        .ins()
-        .iconst(ir::types::I32, i64::from(kind.as_u32()));
-
-    let ty = builder.ins().iconst(ir::types::I32, i64::from(ty.as_u32()));
-
-    assert!(align.is_power_of_two());
-    let align = builder.ins().iconst(ir::types::I32, i64::from(align));
-
-    let call_inst = builder
-        .ins()
-        .call(gc_alloc_raw_builtin, &[vmctx, kind, ty, size, align]);
-
-    let gc_ref = builder.func.dfg.first_result(call_inst);
-    builder.declare_value_needs_stack_map(gc_ref);
-
-    gc_ref
-}
-
 impl GcCompiler for DrcCompiler {
     fn layouts(&self) -> &dyn GcTypeLayouts {
         &self.layouts
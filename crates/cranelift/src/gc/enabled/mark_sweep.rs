@@ -0,0 +1,250 @@
+//! Compiler for the mark-and-sweep collector.
+//!
+//! The mark-and-sweep collector is non-moving and discovers liveness by
+//! tracing from roots, so neither reads nor writes of GC references need
+//! barriers.
+
+use super::{
+    emit_array_fill_impl, emit_array_size, emit_gc_raw_alloc, uextend_i32_to_pointer_type,
+    unbarriered_load_gc_ref, unbarriered_store_gc_ref, write_field_at_addr, BoundsCheck, Offset,
+};
+use crate::gc::{gc_compiler, ArrayInit};
+use crate::translate::TargetEnvironment;
+use crate::{func_environ::FuncEnvironment, gc::GcCompiler, TRAP_INTERNAL_ASSERT};
+use cranelift_codegen::ir::{self, InstBuilder};
+use cranelift_frontend::FunctionBuilder;
+use smallvec::SmallVec;
+use wasmtime_environ::{
+    mark_sweep::MarkSweepTypeLayouts, GcTypeLayouts, TypeIndex, VMGcKind, WasmCompositeType,
+    WasmHeapType, WasmRefType, WasmResult,
+};
+
+#[derive(Default)]
+pub struct MarkSweepCompiler {
+    layouts: MarkSweepTypeLayouts,
+}
+
+impl GcCompiler for MarkSweepCompiler {
+    fn layouts(&self) -> &dyn GcTypeLayouts {
+        &self.layouts
+    }
+
+    fn alloc_array(
+        &mut self,
+        func_env: &mut FuncEnvironment<'_>,
+        builder: &mut FunctionBuilder<'_>,
+        array_type_index: TypeIndex,
+        init: super::ArrayInit<'_>,
+    ) -> WasmResult<ir::Value> {
+        let interned_type_index = func_env.module.types[array_type_index];
+
+        let len_offset = gc_compiler(func_env)?.layouts().array_length_field_offset();
+        let array_layout = func_env.array_layout(interned_type_index);
+        let base_size = array_layout.base_size;
+        let elem_size = array_layout.elem_size;
+        let align = array_layout.align;
+        let len_to_elems_delta = base_size.checked_sub(len_offset).unwrap();
+
+        // First, compute the array's total size from its base size, element
+        // size, and length.
+        let size = emit_array_size(builder, array_layout, init);
+
+        // Second, now that we have the array object's total size, call the
+        // `gc_alloc_raw` builtin libcall to allocate the array.
+        let array_ref = emit_gc_raw_alloc(
+            func_env,
+            builder,
+            VMGcKind::ArrayRef,
+            interned_type_index,
+            size,
+            align,
+        );
+
+        // Write the array's length into the appropriate slot.
+        let len_addr = func_env.prepare_gc_ref_access(
+            builder,
+            array_ref,
+            Offset::Static(len_offset),
+            BoundsCheck::Object(size),
+        );
+        let len = match init {
+            ArrayInit::Fill { len, .. } => len,
+            ArrayInit::Elems(e) => {
+                let len = u32::try_from(e.len()).unwrap();
+                builder.ins().iconst(ir::types::I32, i64::from(len))
+            }
+        };
+        builder
+            .ins()
+            .store(ir::MemFlags::trusted(), len, len_addr, 0);
+
+        // Compute the address of the first element in the array.
+        let len_to_elems_delta = builder
+            .ins()
+            .iconst(ir::types::I64, i64::from(len_to_elems_delta));
+        let mut elem_addr = builder.ins().iadd(len_addr, len_to_elems_delta);
+
+        // Finally, initialize each of the newly-allocated array's elements.
+        // There are no barriers, so initializing a field is the same as
+        // writing to it.
+        let array_ty = func_env.types[interned_type_index]
+            .composite_type
+            .unwrap_array();
+        let elem_ty = array_ty.0.element_type;
+
+        let pointer_type = func_env.pointer_type();
+        let elem_size = builder.ins().iconst(pointer_type, i64::from(elem_size));
+
+        match init {
+            ArrayInit::Elems(elems) => {
+                for val in elems {
+                    write_field_at_addr(func_env, builder, elem_ty, elem_addr, *val)?;
+                    elem_addr = builder.ins().iadd(elem_addr, elem_size);
+                }
+            }
+            ArrayInit::Fill { elem, len: _ } => {
+                // Compute the end address of the elements.
+                let base_size = builder.ins().iconst(pointer_type, i64::from(base_size));
+                let array_addr = builder.ins().isub(elem_addr, base_size);
+                let size = uextend_i32_to_pointer_type(builder, pointer_type, size);
+                let elems_end = builder.ins().iadd(array_addr, size);
+
+                emit_array_fill_impl(
+                    func_env,
+                    builder,
+                    elem_addr,
+                    elem_size,
+                    elems_end,
+                    |func_env, builder, elem_addr| {
+                        write_field_at_addr(func_env, builder, elem_ty, elem_addr, elem)
+                    },
+                )?;
+            }
+        }
+
+        Ok(array_ref)
+    }
+
+    fn alloc_struct(
+        &mut self,
+        func_env: &mut FuncEnvironment<'_>,
+        builder: &mut FunctionBuilder<'_>,
+        struct_type_index: TypeIndex,
+        field_vals: &[ir::Value],
+    ) -> WasmResult<ir::Value> {
+        // First, call the `gc_alloc_raw` builtin libcall to allocate the
+        // struct.
+        let interned_type_index = func_env.module.types[struct_type_index];
+
+        let struct_layout = func_env.struct_layout(interned_type_index);
+
+        // Copy some stuff out of the struct layout to avoid borrowing issues.
+        let struct_size = struct_layout.size;
+        let struct_align = struct_layout.align;
+        let field_offsets: SmallVec<[_; 8]> = struct_layout.fields.iter().copied().collect();
+        assert_eq!(field_vals.len(), field_offsets.len());
+
+        assert_eq!(VMGcKind::MASK & struct_size, 0);
+        assert_eq!(VMGcKind::UNUSED_MASK & struct_size, struct_size);
+        let struct_size_val = builder.ins().iconst(ir::types::I32, i64::from(struct_size));
+
+        let struct_ref = emit_gc_raw_alloc(
+            func_env,
+            builder,
+            VMGcKind::StructRef,
+            interned_type_index,
+            struct_size_val,
+            struct_align,
+        );
+
+        // Second, initialize each of the newly-allocated struct's fields.
+        let struct_ty = match &func_env.types[interned_type_index].composite_type {
+            WasmCompositeType::Struct(s) => s,
+            _ => unreachable!(),
+        };
+        let field_types: SmallVec<[_; 8]> = struct_ty.fields.iter().cloned().collect();
+        assert_eq!(field_vals.len(), field_types.len());
+
+        for ((ty, val), offset) in field_types.into_iter().zip(field_vals).zip(field_offsets) {
+            let size_of_access =
+                wasmtime_environ::byte_size_of_wasm_ty_in_gc_heap(&ty.element_type);
+            assert!(offset + size_of_access <= struct_size);
+
+            let field_addr = func_env.prepare_gc_ref_access(
+                builder,
+                struct_ref,
+                Offset::Static(offset),
+                BoundsCheck::Object(struct_size_val),
+            );
+
+            write_field_at_addr(func_env, builder, ty.element_type, field_addr, *val)?;
+        }
+
+        Ok(struct_ref)
+    }
+
+    fn translate_read_gc_reference(
+        &mut self,
+        func_env: &mut FuncEnvironment<'_>,
+        builder: &mut FunctionBuilder,
+        ty: WasmRefType,
+        src: ir::Value,
+        flags: ir::MemFlags,
+    ) -> WasmResult<ir::Value> {
+        assert!(ty.is_vmgcref_type());
+
+        let (reference_type, needs_stack_map) = func_env.reference_type(ty.heap_type);
+        debug_assert!(needs_stack_map);
+
+        // Special case for references to uninhabited bottom types: the
+        // reference must either be nullable and we can just eagerly return
+        // null, or we are in dynamically unreachable code and should just trap.
+        if let WasmHeapType::None = ty.heap_type {
+            let null = builder.ins().iconst(reference_type, 0);
+            if !ty.nullable {
+                // NB: Don't use an unconditional trap instruction, since that
+                // is a block terminator, and we still need to integrate with
+                // the rest of the surrounding code.
+                let zero = builder.ins().iconst(ir::types::I32, 0);
+                builder.ins().trapz(zero, TRAP_INTERNAL_ASSERT);
+            }
+            return Ok(null);
+        };
+
+        unbarriered_load_gc_ref(builder, ty.heap_type, src, flags)
+    }
+
+    fn translate_write_gc_reference(
+        &mut self,
+        func_env: &mut FuncEnvironment<'_>,
+        builder: &mut FunctionBuilder,
+        ty: WasmRefType,
+        dst: ir::Value,
+        new_val: ir::Value,
+        flags: ir::MemFlags,
+    ) -> WasmResult<()> {
+        assert!(ty.is_vmgcref_type());
+
+        let (ref_ty, needs_stack_map) = func_env.reference_type(ty.heap_type);
+        debug_assert!(needs_stack_map);
+
+        // Special case for references to uninhabited bottom types: either the
+        // reference must either be nullable and we can just eagerly store null
+        // into `dst`, or we are in unreachable code and should just trap.
+        if let WasmHeapType::None = ty.heap_type {
+            if ty.nullable {
+                let null = builder.ins().iconst(ref_ty, 0);
+                builder.ins().store(flags, null, dst, 0);
+            } else {
+                // NB: Don't use an unconditional trap instruction, since that
+                // is a block terminator, and we still need to integrate with
+                // the rest of the surrounding code.
+                let zero = builder.ins().iconst(ir::types::I32, 0);
+                builder.ins().trapz(zero, TRAP_INTERNAL_ASSERT);
+            }
+            return Ok(());
+        };
+
+        unbarriered_store_gc_ref(builder, ty.heap_type, dst, new_val, flags)
+    }
+}
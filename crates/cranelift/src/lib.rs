@@ -29,8 +29,10 @@ pub use compiled_function::*;
 mod builder;
 mod compiler;
 mod debug;
+mod frame_state;
 mod func_environ;
 mod gc;
+mod stack_switching;
 mod translate;
 
 const TRAP_INTERNAL_ASSERT: TrapCode = TrapCode::unwrap_user(1);
@@ -47,6 +49,8 @@ pub const TRAP_NULL_REFERENCE: TrapCode =
     TrapCode::unwrap_user(Trap::NullReference as u8 + TRAP_OFFSET);
 pub const TRAP_ALLOCATION_TOO_LARGE: TrapCode =
     TrapCode::unwrap_user(Trap::AllocationTooLarge as u8 + TRAP_OFFSET);
+pub const TRAP_GC_HEAP_OUT_OF_MEMORY: TrapCode =
+    TrapCode::unwrap_user(Trap::GcHeapOutOfMemory as u8 + TRAP_OFFSET);
 pub const TRAP_ARRAY_OUT_OF_BOUNDS: TrapCode =
     TrapCode::unwrap_user(Trap::ArrayOutOfBounds as u8 + TRAP_OFFSET);
 pub const TRAP_UNREACHABLE: TrapCode =
@@ -188,10 +192,22 @@ fn wasm_call_signature(
 fn reference_type(wasm_ht: WasmHeapType, pointer_type: ir::Type) -> ir::Type {
     match wasm_ht.top() {
         WasmHeapTopType::Func => pointer_type,
-        WasmHeapTopType::Any | WasmHeapTopType::Extern => ir::types::I32,
+        WasmHeapTopType::Any | WasmHeapTopType::Extern | WasmHeapTopType::Exn => ir::types::I32,
+        // Continuation references are handles into the store's table of
+        // continuations, which are the same size on all targets.
+        WasmHeapTopType::Cont => ir::types::I64,
     }
 }
 
+/// Does a value of the given type need to be included in stack maps?
+///
+/// The collector needs to find every GC reference that is live across a call,
+/// and the runtime needs to find every live continuation reference to tell
+/// which continuations are still reachable.
+fn needs_stack_map(ty: WasmValType) -> bool {
+    ty.is_vmgcref_type_and_not_i31() || ty.is_cont_ref()
+}
+
 // List of namespaces which are processed in `mach_reloc_to_reloc` below.
 
 /// Namespace corresponding to wasm functions, the index is the index of the
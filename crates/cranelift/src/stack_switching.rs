@@ -0,0 +1,17 @@
+//! Compilation of the stack-switching proposal's instructions.
+//!
+//! Like the `gc` module, this module's interface is implemented twice: once
+//! when the `stack-switching` cargo feature is enabled and once when it is
+//! disabled, so that a single `cfg` selects between the two.
+
+#[cfg(feature = "stack-switching")]
+mod enabled;
+#[cfg(feature = "stack-switching")]
+use enabled as imp;
+
+#[cfg(not(feature = "stack-switching"))]
+mod disabled;
+#[cfg(not(feature = "stack-switching"))]
+use disabled as imp;
+
+pub use imp::*;
@@ -0,0 +1,82 @@
+//! Stack-switching compilation when the `stack-switching` feature is disabled.
+
+use crate::func_environ::FuncEnvironment;
+use cranelift_codegen::ir;
+use cranelift_frontend::FunctionBuilder;
+use smallvec::SmallVec;
+use wasmtime_environ::{wasm_unsupported, TagIndex, TypeIndex, WasmResult};
+
+fn disabled<T>() -> WasmResult<T> {
+    Err(wasm_unsupported!(
+        "support for stack switching disabled at compile time because the \
+         `stack-switching` cargo feature was not enabled"
+    ))
+}
+
+pub fn translate_cont_new(
+    _func_env: &mut FuncEnvironment<'_>,
+    _builder: &mut FunctionBuilder<'_>,
+    _func: ir::Value,
+) -> WasmResult<ir::Value> {
+    disabled()
+}
+
+pub fn translate_cont_bind(
+    _func_env: &mut FuncEnvironment<'_>,
+    _builder: &mut FunctionBuilder<'_>,
+    _argument_type_index: TypeIndex,
+    _cont: ir::Value,
+    _args: &[ir::Value],
+) -> WasmResult<ir::Value> {
+    disabled()
+}
+
+pub fn translate_resume(
+    _func_env: &mut FuncEnvironment<'_>,
+    _builder: &mut FunctionBuilder<'_>,
+    _cont_type_index: TypeIndex,
+    _throw: Option<TagIndex>,
+    _cont: ir::Value,
+    _args: &[ir::Value],
+    _handlers: &[wasmparser::Handle],
+) -> WasmResult<(ir::Value, ir::Value)> {
+    disabled()
+}
+
+pub fn translate_resume_results(
+    _func_env: &mut FuncEnvironment<'_>,
+    _builder: &mut FunctionBuilder<'_>,
+    _cont_type_index: TypeIndex,
+    _buffer: ir::Value,
+) -> WasmResult<SmallVec<[ir::Value; 4]>> {
+    disabled()
+}
+
+pub fn translate_resume_payload(
+    _func_env: &mut FuncEnvironment<'_>,
+    _builder: &mut FunctionBuilder<'_>,
+    _tag_index: TagIndex,
+    _buffer: ir::Value,
+) -> WasmResult<SmallVec<[ir::Value; 4]>> {
+    disabled()
+}
+
+pub fn translate_suspend(
+    _func_env: &mut FuncEnvironment<'_>,
+    _builder: &mut FunctionBuilder<'_>,
+    _tag_index: TagIndex,
+    _args: &[ir::Value],
+) -> WasmResult<SmallVec<[ir::Value; 4]>> {
+    disabled()
+}
+
+pub fn translate_switch(
+    _func_env: &mut FuncEnvironment<'_>,
+    _builder: &mut FunctionBuilder<'_>,
+    _cont_type_index: TypeIndex,
+    _tag_index: TagIndex,
+    _cont: ir::Value,
+    _args: &[ir::Value],
+) -> WasmResult<SmallVec<[ir::Value; 4]>> {
+    disabled()
+}
@@ -0,0 +1,329 @@
+//! Compilation of the stack-switching instructions into calls to the runtime's
+//! continuation builtins.
+//!
+//! Values are passed to and from the builtins through stack-allocated arrays
+//! of `ValRaw`s, in the same way as the array calling convention.
+
+use crate::func_environ::FuncEnvironment;
+use cranelift_codegen::ir::{self, InstBuilder};
+use cranelift_frontend::FunctionBuilder;
+use smallvec::SmallVec;
+use wasmtime_environ::{
+    wasm_unsupported, TagIndex, TypeIndex, WasmHeapType, WasmRefType, WasmResult, WasmValType,
+    RESUME_HANDLER_SWITCH,
+};
+
+const VAL_RAW_SIZE: usize = core::mem::size_of::<u128>();
+
+/// Memory flags for accessing the `ValRaw` arrays passed to the builtins.
+///
+/// Like the array calling convention, these are always little-endian.
+fn val_raw_flags() -> ir::MemFlags {
+    ir::MemFlags::new()
+        .with_notrap()
+        .with_endianness(ir::Endianness::Little)
+}
+
+/// Allocate a stack slot for an array of `len` `ValRaw`s and initialize its
+/// first elements with `values`.
+fn values_buffer(
+    func_env: &mut FuncEnvironment<'_>,
+    builder: &mut FunctionBuilder<'_>,
+    len: usize,
+    values: &[ir::Value],
+) -> ir::Value {
+    debug_assert!(values.len() <= len);
+    let slot = builder.func.create_sized_stack_slot(ir::StackSlotData::new(
+        ir::StackSlotKind::ExplicitSlot,
+        u32::try_from(VAL_RAW_SIZE * len).unwrap(),
+        4,
+    ));
+    let buffer = builder.ins().stack_addr(func_env.pointer_type(), slot, 0);
+    for (i, value) in values.iter().copied().enumerate() {
+        crate::unbarriered_store_type_at_offset(
+            &mut builder.cursor(),
+            val_raw_flags(),
+            buffer,
+            i32::try_from(i * VAL_RAW_SIZE).unwrap(),
+            value,
+        );
+    }
+    buffer
+}
+
+/// Load values of the given types from the start of a `ValRaw` array.
+fn load_values(
+    func_env: &mut FuncEnvironment<'_>,
+    builder: &mut FunctionBuilder<'_>,
+    buffer: ir::Value,
+    types: impl IntoIterator<Item = WasmValType>,
+) -> SmallVec<[ir::Value; 4]> {
+    let mut values = SmallVec::new();
+    for (i, ty) in types.into_iter().enumerate() {
+        let val = crate::unbarriered_load_type_at_offset(
+            func_env.isa,
+            &mut builder.cursor(),
+            ty,
+            val_raw_flags(),
+            buffer,
+            i32::try_from(i * VAL_RAW_SIZE).unwrap(),
+        );
+        if crate::needs_stack_map(ty) {
+            builder.declare_value_needs_stack_map(val);
+        }
+        values.push(val);
+    }
+    values
+}
+
+fn i32_const(builder: &mut FunctionBuilder<'_>, value: usize) -> ir::Value {
+    let value = i64::try_from(value).unwrap();
+    builder.ins().iconst(ir::types::I32, value)
+}
+
+pub fn translate_cont_new(
+    func_env: &mut FuncEnvironment<'_>,
+    builder: &mut FunctionBuilder<'_>,
+    func: ir::Value,
+) -> WasmResult<ir::Value> {
+    let cont_new = func_env.builtin_functions.cont_new(builder.func);
+    let vmctx = func_env.vmctx_val(&mut builder.cursor());
+    let call_inst = builder.ins().call(cont_new, &[vmctx, func]);
+    let cont = builder.func.dfg.first_result(call_inst);
+    builder.declare_value_needs_stack_map(cont);
+    Ok(cont)
+}
+
+pub fn translate_cont_bind(
+    func_env: &mut FuncEnvironment<'_>,
+    builder: &mut FunctionBuilder<'_>,
+    argument_type_index: TypeIndex,
+    cont: ir::Value,
+    args: &[ir::Value],
+) -> WasmResult<ir::Value> {
+    // Bound values are held by the runtime until the continuation is next
+    // resumed, where they are not visible to the collector.
+    let sig = func_env.cont_signature(argument_type_index);
+    if sig.params()[..args.len()]
+        .iter()
+        .any(|ty| ty.is_vmgcref_type_and_not_i31())
+    {
+        return Err(wasm_unsupported!(
+            "binding GC references to continuations with `cont.bind`"
+        ));
+    }
+
+    let buffer = values_buffer(func_env, builder, args.len(), args);
+    let cont_bind = func_env.builtin_functions.cont_bind(builder.func);
+    let vmctx = func_env.vmctx_val(&mut builder.cursor());
+    let len = i32_const(builder, args.len());
+    let call_inst = builder.ins().call(cont_bind, &[vmctx, cont, buffer, len]);
+    let cont = builder.func.dfg.first_result(call_inst);
+    builder.declare_value_needs_stack_map(cont);
+    Ok(cont)
+}
+
+pub fn translate_resume(
+    func_env: &mut FuncEnvironment<'_>,
+    builder: &mut FunctionBuilder<'_>,
+    cont_type_index: TypeIndex,
+    throw: Option<TagIndex>,
+    cont: ir::Value,
+    args: &[ir::Value],
+    handlers: &[wasmparser::Handle],
+) -> WasmResult<(ir::Value, ir::Value)> {
+    // The buffer holds the arguments on the way in, and on the way out either
+    // the continuation's results or a handler's payload plus the suspended
+    // continuation.
+    let sig = func_env.cont_signature(cont_type_index);
+    let mut len = args.len().max(sig.returns().len());
+    let mut table = SmallVec::<[u32; 4]>::new();
+    for handler in handlers {
+        match *handler {
+            wasmparser::Handle::OnLabel { tag, .. } => {
+                let arity = func_env
+                    .tag_signature(TagIndex::from_u32(tag))
+                    .params()
+                    .len();
+                len = len.max(arity + 1);
+                table.push(tag);
+            }
+            wasmparser::Handle::OnSwitch { tag } => table.push(tag | RESUME_HANDLER_SWITCH),
+        }
+    }
+    let buffer = values_buffer(func_env, builder, len, args);
+
+    let pointer_type = func_env.pointer_type();
+    let handlers = if table.is_empty() {
+        builder.ins().iconst(pointer_type, 0)
+    } else {
+        let slot = builder.func.create_sized_stack_slot(ir::StackSlotData::new(
+            ir::StackSlotKind::ExplicitSlot,
+            u32::try_from(table.len() * core::mem::size_of::<u32>()).unwrap(),
+            2,
+        ));
+        for (i, handler) in table.iter().enumerate() {
+            let handler = builder.ins().iconst(ir::types::I32, i64::from(*handler));
+            let offset = i32::try_from(i * core::mem::size_of::<u32>()).unwrap();
+            builder.ins().stack_store(handler, slot, offset);
+        }
+        builder.ins().stack_addr(pointer_type, slot, 0)
+    };
+    let handlers_len = i32_const(builder, table.len());
+
+    let vmctx = func_env.vmctx_val(&mut builder.cursor());
+    let call_inst = match throw {
+        None => {
+            let resume = func_env.builtin_functions.resume(builder.func);
+            let len = i32_const(builder, args.len());
+            builder
+                .ins()
+                .call(resume, &[vmctx, cont, buffer, len, handlers, handlers_len])
+        }
+        Some(tag_index) => {
+            let resume_throw = resume_throw(func_env, builder, tag_index)?;
+            let tag = builder
+                .ins()
+                .iconst(ir::types::I32, i64::from(tag_index.as_u32()));
+            builder.ins().call(
+                resume_throw,
+                &[vmctx, cont, tag, buffer, handlers, handlers_len],
+            )
+        }
+    };
+    func_env.mark_exception_landing_pad(builder, call_inst);
+    Ok((builder.func.dfg.first_result(call_inst), buffer))
+}
+
+#[cfg(feature = "gc")]
+fn resume_throw(
+    func_env: &mut FuncEnvironment<'_>,
+    builder: &mut FunctionBuilder<'_>,
+    tag_index: TagIndex,
+) -> WasmResult<ir::FuncRef> {
+    crate::gc::check_exception_payload(func_env, tag_index)?;
+    Ok(func_env.builtin_functions.resume_throw(builder.func))
+}
+
+#[cfg(not(feature = "gc"))]
+fn resume_throw(
+    _func_env: &mut FuncEnvironment<'_>,
+    _builder: &mut FunctionBuilder<'_>,
+    _tag_index: TagIndex,
+) -> WasmResult<ir::FuncRef> {
+    Err(wasm_unsupported!(
+        "`resume_throw` requires exceptions, which are disabled at compile time \
+         because the `gc` cargo feature was not enabled"
+    ))
+}
+
+pub fn translate_resume_results(
+    func_env: &mut FuncEnvironment<'_>,
+    builder: &mut FunctionBuilder<'_>,
+    cont_type_index: TypeIndex,
+    buffer: ir::Value,
+) -> WasmResult<SmallVec<[ir::Value; 4]>> {
+    let types: SmallVec<[WasmValType; 4]> = func_env
+        .cont_signature(cont_type_index)
+        .returns()
+        .iter()
+        .copied()
+        .collect();
+    Ok(load_values(func_env, builder, buffer, types))
+}
+
+pub fn translate_resume_payload(
+    func_env: &mut FuncEnvironment<'_>,
+    builder: &mut FunctionBuilder<'_>,
+    tag_index: TagIndex,
+    buffer: ir::Value,
+) -> WasmResult<SmallVec<[ir::Value; 4]>> {
+    // The suspended continuation follows the payload. Its exact type doesn't
+    // matter here since all continuation references are `i64` handles.
+    let cont = WasmValType::Ref(WasmRefType {
+        nullable: false,
+        heap_type: WasmHeapType::Cont,
+    });
+    let types: SmallVec<[WasmValType; 4]> = func_env
+        .tag_signature(tag_index)
+        .params()
+        .iter()
+        .copied()
+        .chain([cont])
+        .collect();
+    Ok(load_values(func_env, builder, buffer, types))
+}
+
+pub fn translate_suspend(
+    func_env: &mut FuncEnvironment<'_>,
+    builder: &mut FunctionBuilder<'_>,
+    tag_index: TagIndex,
+    args: &[ir::Value],
+) -> WasmResult<SmallVec<[ir::Value; 4]>> {
+    let results: SmallVec<[WasmValType; 4]> = func_env
+        .tag_signature(tag_index)
+        .returns()
+        .iter()
+        .copied()
+        .collect();
+    let buffer = values_buffer(func_env, builder, args.len().max(results.len()), args);
+
+    let suspend = func_env.builtin_functions.suspend(builder.func);
+    let vmctx = func_env.vmctx_val(&mut builder.cursor());
+    let tag = builder
+        .ins()
+        .iconst(ir::types::I32, i64::from(tag_index.as_u32()));
+    let len = i32_const(builder, args.len());
+    let call_inst = builder.ins().call(suspend, &[vmctx, tag, buffer, len]);
+    func_env.mark_exception_landing_pad(builder, call_inst);
+
+    Ok(load_values(func_env, builder, buffer, results))
+}
+
+pub fn translate_switch(
+    func_env: &mut FuncEnvironment<'_>,
+    builder: &mut FunctionBuilder<'_>,
+    cont_type_index: TypeIndex,
+    tag_index: TagIndex,
+    cont: ir::Value,
+    args: &[ir::Value],
+) -> WasmResult<SmallVec<[ir::Value; 4]>> {
+    // The last parameter of the target continuation's type is the
+    // continuation that the current one is suspended as, whose parameters are
+    // the values that we are resumed with.
+    let sig = func_env.cont_signature(cont_type_index);
+    let last = *sig
+        .params()
+        .last()
+        .expect("`switch` targets take the suspended continuation");
+    let suspended_type = match last {
+        WasmValType::Ref(r) => match r.heap_type {
+            wasmtime_environ::WasmHeapType::ConcreteCont(idx) => idx.unwrap_module_type_index(),
+            _ => {
+                return Err(wasm_unsupported!(
+                    "`switch` to an abstract continuation type"
+                ))
+            }
+        },
+        _ => unreachable!("validated to be a continuation reference"),
+    };
+    let func_ty = func_env.types[suspended_type].unwrap_cont().func_type();
+    let results: SmallVec<[WasmValType; 4]> = func_env.types[func_ty.unwrap_module_type_index()]
+        .unwrap_func()
+        .params()
+        .iter()
+        .copied()
+        .collect();
+    let buffer = values_buffer(func_env, builder, args.len().max(results.len()), args);
+
+    let switch = func_env.builtin_functions.switch(builder.func);
+    let vmctx = func_env.vmctx_val(&mut builder.cursor());
+    let tag = builder
+        .ins()
+        .iconst(ir::types::I32, i64::from(tag_index.as_u32()));
+    let len = i32_const(builder, args.len());
+    let call_inst = builder.ins().call(switch, &[vmctx, tag, cont, buffer, len]);
+    func_env.mark_exception_landing_pad(builder, call_inst);
+
+    Ok(load_values(func_env, builder, buffer, results))
+}
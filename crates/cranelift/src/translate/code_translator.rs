@@ -91,10 +91,10 @@ use itertools::Itertools;
 use smallvec::SmallVec;
 use std::collections::{hash_map, HashMap};
 use std::vec::Vec;
-use wasmparser::{FuncValidator, MemArg, Operator, WasmModuleResources};
+use wasmparser::{Catch, FuncValidator, Handle, MemArg, Operator, WasmModuleResources};
 use wasmtime_environ::{
     wasm_unsupported, DataIndex, ElemIndex, FuncIndex, GlobalIndex, MemoryIndex, TableIndex,
-    TypeIndex, WasmRefType, WasmResult,
+    TagIndex, TypeIndex, WasmRefType, WasmResult,
 };
 
 /// Given a `Reachability<T>`, unwrap the inner `T` or, when unreachable, set
@@ -228,10 +228,11 @@ pub fn translate_operator<FE: FuncEnvironment + ?Sized>(
             }
             state.push1(builder.ins().select(cond, arg1, arg2));
         }
-        Operator::TypedSelect { ty: _ } => {
-            // We ignore the explicit type parameter as it is only needed for
+        Operator::TypedSelect { ty } => {
+            // The explicit type parameter is otherwise only needed for
             // validation, which we require to have been performed before
-            // translation.
+            // translation, but references selected here still need to be
+            // included in stack maps.
             let (mut arg1, mut arg2, cond) = state.pop3();
             if builder.func.dfg.value_type(arg1).is_vector() {
                 arg1 = optionally_bitcast_vector(arg1, I8X16, builder);
@@ -239,7 +240,15 @@ pub fn translate_operator<FE: FuncEnvironment + ?Sized>(
             if builder.func.dfg.value_type(arg2).is_vector() {
                 arg2 = optionally_bitcast_vector(arg2, I8X16, builder);
             }
-            state.push1(builder.ins().select(cond, arg1, arg2));
+            let val = builder.ins().select(cond, arg1, arg2);
+            if let wasmparser::ValType::Ref(rt) = ty {
+                let hty = environ.convert_heap_type(rt.heap_type());
+                let (_, needs_stack_map) = environ.reference_type(hty);
+                if needs_stack_map {
+                    builder.declare_value_needs_stack_map(val);
+                }
+            }
+            state.push1(val);
         }
         Operator::Nop => {
             // We do nothing
@@ -423,6 +432,7 @@ pub fn translate_operator<FE: FuncEnvironment + ?Sized>(
         }
         Operator::End => {
             let frame = state.control_stack.pop().unwrap();
+            pop_exception_handlers(builder, state, environ);
             let next_block = frame.following_code();
             let return_count = frame.num_return_values();
             let return_args = state.peekn_mut(return_count);
@@ -588,10 +598,52 @@ pub fn translate_operator<FE: FuncEnvironment + ?Sized>(
             state.popn(return_count);
             state.reachable = false;
         }
-        /********************************** Exception handing **********************************/
+        /********************************** Exception handing **********************************
+         * Exceptions are thrown by making them pending in the `VMRuntimeLimits` and then
+         * unwinding the stack to the innermost call that is an exception landing pad. Every call
+         * that may throw within a `try_table` is made a landing pad and is followed by a check
+         * for a pending exception, which branches to the innermost `try_table`'s dispatch block.
+         * Calls outside of any `try_table` need no check, since the runtime unwinds straight
+         * past their frames.
+         *
+         * A dispatch block tests the pending exception against each of its `try_table`'s catch
+         * clauses in order, branching to the first matching clause's label, or to the next
+         * outer dispatch block if no clause matches.
+         ***********************************************************************************/
+        Operator::TryTable { try_table } => {
+            let (params, results) = blocktype_params_results(validator, try_table.ty)?;
+            let next = block_with_params(builder, results.clone(), environ)?;
+            let dispatch = builder.create_block();
+            builder.set_cold_block(dispatch);
+            let body = builder.create_block();
+            builder.ins().jump(body, &[]);
+            builder.seal_block(body);
+
+            // Note that the dispatch block is emitted before pushing this
+            // `try_table`'s frame, since catch clause labels are relative to
+            // the enclosing block.
+            builder.switch_to_block(dispatch);
+            translate_exception_dispatch(&try_table.catches, builder, state, environ)?;
+
+            builder.switch_to_block(body);
+            state.push_block(next, params.len(), results.len());
+            state.handlers.push((state.control_stack.len(), dispatch));
+            environ.set_in_try_table(true);
+        }
+        Operator::Throw { tag_index } => {
+            let tag_index = TagIndex::from_u32(*tag_index);
+            let arity = environ.tag_arity(tag_index);
+            environ.translate_throw(builder, tag_index, state.peekn(arity))?;
+            state.popn(arity);
+            translate_after_throw(builder, state);
+        }
+        Operator::ThrowRef => {
+            let exn = state.pop1();
+            environ.translate_throw_ref(builder, exn)?;
+            translate_after_throw(builder, state);
+        }
         Operator::Try { .. }
         | Operator::Catch { .. }
-        | Operator::Throw { .. }
         | Operator::Rethrow { .. }
         | Operator::Delegate { .. }
         | Operator::CatchAll => {
@@ -2579,12 +2631,6 @@ pub fn translate_operator<FE: FuncEnvironment + ?Sized>(
             state.push1(val);
         }
 
-        Operator::TryTable { .. } | Operator::ThrowRef => {
-            return Err(wasm_unsupported!(
-                "exception operators are not yet implemented"
-            ));
-        }
-
         Operator::ArrayNew { array_type_index } => {
             let array_type_index = TypeIndex::from_u32(*array_type_index);
             let (elem, len) = state.pop2();
@@ -2902,15 +2948,113 @@ pub fn translate_operator<FE: FuncEnvironment + ?Sized>(
             ));
         }
 
-        Operator::ContNew { .. }
-        | Operator::ContBind { .. }
-        | Operator::Suspend { .. }
-        | Operator::Resume { .. }
-        | Operator::ResumeThrow { .. }
-        | Operator::Switch { .. } => {
-            return Err(wasm_unsupported!(
-                "stack-switching operators are not yet implemented"
-            ));
+        /********************************** Stack switching ************************************
+         * Continuations run on fibers managed by the runtime, so each of these instructions is
+         * a call to a builtin. Values are exchanged with continuations through buffers of
+         * `ValRaw`s, and `resume` dispatches on the builtin's result code to branch to the
+         * label of the handler that the continuation suspended to, if any.
+         ***********************************************************************************/
+        Operator::ContNew { cont_type_index: _ } => {
+            let func = state.pop1();
+            let cont = environ.translate_cont_new(builder, func)?;
+            state.push1(cont);
+        }
+        Operator::ContBind {
+            argument_index,
+            result_index,
+        } => {
+            let argument_index = TypeIndex::from_u32(*argument_index);
+            let arity = environ.cont_arity(argument_index)
+                - environ.cont_arity(TypeIndex::from_u32(*result_index));
+            let cont = state.pop1();
+            let bound =
+                environ.translate_cont_bind(builder, argument_index, cont, state.peekn(arity))?;
+            state.popn(arity);
+            state.push1(bound);
+        }
+        Operator::Resume {
+            cont_type_index,
+            resume_table,
+        } => {
+            let cont_type_index = TypeIndex::from_u32(*cont_type_index);
+            let arity = environ.cont_arity(cont_type_index);
+            let cont = state.pop1();
+            let (code, buffer) = environ.translate_resume(
+                builder,
+                cont_type_index,
+                None,
+                cont,
+                state.peekn(arity),
+                &resume_table.handlers,
+            )?;
+            state.popn(arity);
+            translate_resume_dispatch(
+                cont_type_index,
+                &resume_table.handlers,
+                code,
+                buffer,
+                builder,
+                state,
+                environ,
+            )?;
+        }
+        Operator::ResumeThrow {
+            cont_type_index,
+            tag_index,
+            resume_table,
+        } => {
+            let cont_type_index = TypeIndex::from_u32(*cont_type_index);
+            let tag_index = TagIndex::from_u32(*tag_index);
+            let arity = environ.tag_arity(tag_index);
+            let cont = state.pop1();
+            let (code, buffer) = environ.translate_resume(
+                builder,
+                cont_type_index,
+                Some(tag_index),
+                cont,
+                state.peekn(arity),
+                &resume_table.handlers,
+            )?;
+            state.popn(arity);
+            translate_resume_dispatch(
+                cont_type_index,
+                &resume_table.handlers,
+                code,
+                buffer,
+                builder,
+                state,
+                environ,
+            )?;
+        }
+        Operator::Suspend { tag_index } => {
+            let tag_index = TagIndex::from_u32(*tag_index);
+            let arity = environ.tag_arity(tag_index);
+            let results = environ.translate_suspend(builder, tag_index, state.peekn(arity))?;
+            state.popn(arity);
+            // The continuation may be resumed with `resume_throw`.
+            translate_pending_exception_check(builder, state, environ)?;
+            state.pushn(&results);
+        }
+        Operator::Switch {
+            cont_type_index,
+            tag_index,
+        } => {
+            let cont_type_index = TypeIndex::from_u32(*cont_type_index);
+            let tag_index = TagIndex::from_u32(*tag_index);
+            // The target's last parameter is the current continuation, which
+            // is supplied by the runtime.
+            let arity = environ.cont_arity(cont_type_index) - 1;
+            let cont = state.pop1();
+            let results = environ.translate_switch(
+                builder,
+                cont_type_index,
+                tag_index,
+                cont,
+                state.peekn(arity),
+            )?;
+            state.popn(arity);
+            translate_pending_exception_check(builder, state, environ)?;
+            state.pushn(&results);
         }
 
         Operator::I64MulWideS => {
@@ -2975,7 +3119,9 @@ fn translate_unreachable_operator<FE: FuncEnvironment + ?Sized>(
                 blockty,
             );
         }
-        Operator::Loop { blockty: _ } | Operator::Block { blockty: _ } => {
+        Operator::Loop { blockty: _ }
+        | Operator::Block { blockty: _ }
+        | Operator::TryTable { try_table: _ } => {
             state.push_block(ir::Block::reserved_value(), 0, 0);
         }
         Operator::Else => {
@@ -3034,9 +3180,9 @@ fn translate_unreachable_operator<FE: FuncEnvironment + ?Sized>(
             }
         }
         Operator::End => {
+            let frame = state.control_stack.pop().unwrap();
+            pop_exception_handlers(builder, state, environ);
             let stack = &mut state.stack;
-            let control_stack = &mut state.control_stack;
-            let frame = control_stack.pop().unwrap();
 
             // Pop unused parameters from stack.
             frame.truncate_value_stack_to_original_size(stack);
@@ -3936,6 +4082,185 @@ fn canonicalise_v128_values<'a>(
     tmp_canonicalised.as_slice()
 }
 
+/// Finish translating a `throw` or `throw_ref`, after the runtime has been
+/// called to throw the exception.
+///
+/// The runtime only returns here if the throw is within a `try_table`, in
+/// which case the exception is dispatched to its catch clauses.
+fn translate_after_throw(builder: &mut FunctionBuilder, state: &mut FuncTranslationState) {
+    match state.handlers.last() {
+        Some(&(_, dispatch)) => {
+            builder.ins().jump(dispatch, &[]);
+        }
+        None => {
+            builder.ins().trap(crate::TRAP_INTERNAL_ASSERT);
+        }
+    }
+    state.reachable = false;
+}
+
+/// Seal the dispatch blocks of the `try_table`s whose scope ended when the
+/// last control stack frame was popped.
+///
+/// Every throw and call that can branch to a dispatch block is within its
+/// `try_table`, so all of the dispatch block's predecessors are known by now.
+fn pop_exception_handlers<FE: FuncEnvironment + ?Sized>(
+    builder: &mut FunctionBuilder,
+    state: &mut FuncTranslationState,
+    environ: &mut FE,
+) {
+    while let Some((depth, dispatch)) = state.handlers.last().copied() {
+        if depth <= state.control_stack.len() {
+            break;
+        }
+        state.handlers.pop();
+        builder.seal_block(dispatch);
+        environ.set_in_try_table(!state.handlers.is_empty());
+    }
+}
+
+/// Emit the code for a `try_table`'s dispatch block, which branches to the
+/// first of the given catch clauses that matches the pending exception.
+///
+/// The control stack must not yet contain the `try_table`'s own frame.
+fn translate_exception_dispatch<FE: FuncEnvironment + ?Sized>(
+    catches: &[Catch],
+    builder: &mut FunctionBuilder,
+    state: &mut FuncTranslationState,
+    environ: &mut FE,
+) -> WasmResult<()> {
+    for catch in catches {
+        let (tag, label, with_ref) = match *catch {
+            Catch::One { tag, label } => (Some(tag), label, false),
+            Catch::OneRef { tag, label } => (Some(tag), label, true),
+            Catch::All { label } => (None, label, false),
+            Catch::AllRef { label } => (None, label, true),
+        };
+
+        let mut args = SmallVec::<[Value; 4]>::new();
+        let no_match = match tag {
+            Some(tag) => {
+                let tag = TagIndex::from_u32(tag);
+                let matches = environ.translate_pending_exception_matches(builder, tag)?;
+                let caught = builder.create_block();
+                let no_match = builder.create_block();
+                builder.ins().brif(matches, caught, &[], no_match, &[]);
+                builder.seal_block(caught);
+                builder.seal_block(no_match);
+
+                builder.switch_to_block(caught);
+                let exn = environ.translate_take_pending_exception(builder)?;
+                args.extend(environ.translate_exception_payload(builder, tag, exn)?);
+                if with_ref {
+                    args.push(exn);
+                }
+                Some(no_match)
+            }
+            None => {
+                let exn = environ.translate_take_pending_exception(builder)?;
+                if with_ref {
+                    args.push(exn);
+                }
+                None
+            }
+        };
+
+        let i = state.control_stack.len() - 1 - (label as usize);
+        let frame = &mut state.control_stack[i];
+        frame.set_branched_to_exit();
+        canonicalise_then_jump(builder, frame.br_destination(), &args);
+
+        match no_match {
+            Some(block) => builder.switch_to_block(block),
+            // A `catch_all` clause catches everything, so any later clauses
+            // are dead.
+            None => return Ok(()),
+        }
+    }
+
+    // None of the clauses matched, so keep unwinding: either to the enclosing
+    // `try_table` within this function, or by rethrowing the exception to
+    // unwind past this frame.
+    match state.handlers.last() {
+        Some(&(_, dispatch)) => {
+            builder.ins().jump(dispatch, &[]);
+        }
+        None => {
+            let exn = environ.translate_take_pending_exception(builder)?;
+            environ.translate_throw_ref(builder, exn)?;
+            builder.ins().trap(crate::TRAP_INTERNAL_ASSERT);
+        }
+    }
+    Ok(())
+}
+
+/// Dispatch on the result code of a `resume` or `resume_throw`, branching to
+/// the label of the handler that the continuation suspended to or else pushing
+/// the continuation's results.
+fn translate_resume_dispatch<FE: FuncEnvironment + ?Sized>(
+    cont_type_index: TypeIndex,
+    handlers: &[Handle],
+    code: Value,
+    buffer: Value,
+    builder: &mut FunctionBuilder,
+    state: &mut FuncTranslationState,
+    environ: &mut FE,
+) -> WasmResult<()> {
+    // The continuation may have thrown an exception that it did not catch.
+    translate_pending_exception_check(builder, state, environ)?;
+
+    for (i, handler) in handlers.iter().enumerate() {
+        // `switch` handlers are dealt with entirely by the runtime.
+        let Handle::OnLabel { tag, label } = *handler else {
+            continue;
+        };
+        let is_match = builder
+            .ins()
+            .icmp_imm(IntCC::Equal, code, i64::try_from(i + 1).unwrap());
+        let suspended = builder.create_block();
+        let no_match = builder.create_block();
+        builder.ins().brif(is_match, suspended, &[], no_match, &[]);
+        builder.seal_block(suspended);
+        builder.seal_block(no_match);
+
+        builder.switch_to_block(suspended);
+        let args = environ.translate_resume_payload(builder, TagIndex::from_u32(tag), buffer)?;
+        let i = state.control_stack.len() - 1 - (label as usize);
+        let frame = &mut state.control_stack[i];
+        frame.set_branched_to_exit();
+        canonicalise_then_jump(builder, frame.br_destination(), &args);
+
+        builder.switch_to_block(no_match);
+    }
+
+    let results = environ.translate_resume_results(builder, cont_type_index, buffer)?;
+    state.pushn(&results);
+    Ok(())
+}
+
+/// After a call that may throw, branch to the innermost `try_table`'s dispatch
+/// block if the callee threw an exception that it did not catch.
+///
+/// Outside of any `try_table` there is nothing to do, since the runtime
+/// unwinds straight past this function's frame.
+pub(crate) fn translate_pending_exception_check<FE: FuncEnvironment + ?Sized>(
+    builder: &mut FunctionBuilder,
+    state: &mut FuncTranslationState,
+    environ: &mut FE,
+) -> WasmResult<()> {
+    let Some(&(_, dispatch)) = state.handlers.last() else {
+        return Ok(());
+    };
+    let pending = environ.translate_has_pending_exception(builder)?;
+    let continuation = builder.create_block();
+    builder
+        .ins()
+        .brif(pending, dispatch, &[], continuation, &[]);
+    builder.seal_block(continuation);
+    builder.switch_to_block(continuation);
+    Ok(())
+}
+
 /// Generate a `jump` instruction, but first cast all 128-bit vector values to I8X16 if they
 /// don't have that type.  This is done in somewhat roundabout way so as to ensure that we
 /// almost never have to do any heap allocation.
@@ -17,8 +17,8 @@ use cranelift_frontend::FunctionBuilder;
 use smallvec::SmallVec;
 use wasmparser::{Operator, WasmFeatures};
 use wasmtime_environ::{
-    DataIndex, ElemIndex, FuncIndex, GlobalIndex, MemoryIndex, TableIndex, TypeConvert, TypeIndex,
-    WasmHeapType, WasmRefType, WasmResult,
+    DataIndex, ElemIndex, FuncIndex, GlobalIndex, MemoryIndex, TableIndex, TagIndex, TypeConvert,
+    TypeIndex, WasmHeapType, WasmRefType, WasmResult, WasmValType,
 };
 
 /// The value of a WebAssembly global variable.
@@ -711,6 +711,157 @@ pub trait FuncEnvironment: TargetEnvironment {
         gc_ref: ir::Value,
     ) -> WasmResult<ir::Value>;
 
+    /// Set whether the code being translated is within a `try_table`.
+    ///
+    /// While it is, calls that may throw, including those to the runtime
+    /// for `throw`, `throw_ref`, and the stack switching instructions, must
+    /// be made exception landing pads (see
+    /// `DataFlowGraph::set_exception_landing_pad`). The runtime then resumes
+    /// execution just after such a call when an exception is thrown within
+    /// it, and the translator checks for a pending exception there.
+    fn set_in_try_table(&mut self, in_try_table: bool);
+
+    /// Translate a `throw` instruction.
+    ///
+    /// This makes the new exception pending and unwinds the stack to the
+    /// innermost call that is an exception landing pad, so it only returns
+    /// to the following code if it is within a `try_table`.
+    fn translate_throw(
+        &mut self,
+        builder: &mut FunctionBuilder<'_>,
+        tag_index: TagIndex,
+        args: &[ir::Value],
+    ) -> WasmResult<()>;
+
+    /// Translate a `throw_ref` instruction.
+    ///
+    /// Like `translate_throw`, this makes the given exception pending and
+    /// unwinds the stack.
+    fn translate_throw_ref(
+        &mut self,
+        builder: &mut FunctionBuilder<'_>,
+        exn: ir::Value,
+    ) -> WasmResult<()>;
+
+    /// Emit code to check whether an exception is pending, returning a
+    /// non-zero `i32` if so.
+    ///
+    /// This is checked after every call within a `try_table`, since
+    /// execution resumes there when the callee throws an exception that it
+    /// does not catch.
+    fn translate_has_pending_exception(
+        &mut self,
+        builder: &mut FunctionBuilder<'_>,
+    ) -> WasmResult<ir::Value>;
+
+    /// Emit code to check whether the pending exception was thrown with the
+    /// given tag, returning a non-zero `i32` if so.
+    fn translate_pending_exception_matches(
+        &mut self,
+        builder: &mut FunctionBuilder<'_>,
+        tag_index: TagIndex,
+    ) -> WasmResult<ir::Value>;
+
+    /// Emit code to take the pending exception, returning it as an `exnref`.
+    fn translate_take_pending_exception(
+        &mut self,
+        builder: &mut FunctionBuilder<'_>,
+    ) -> WasmResult<ir::Value>;
+
+    /// Emit code to read the payload values out of the given exception, which
+    /// was thrown with the given tag.
+    fn translate_exception_payload(
+        &mut self,
+        builder: &mut FunctionBuilder<'_>,
+        tag_index: TagIndex,
+        exn: ir::Value,
+    ) -> WasmResult<SmallVec<[ir::Value; 4]>>;
+
+    /// Get the number of payload values carried by exceptions with the given
+    /// tag.
+    fn tag_arity(&self, tag_index: TagIndex) -> usize;
+
+    /// Translate a `cont.new` instruction, creating a continuation of the given
+    /// function reference.
+    fn translate_cont_new(
+        &mut self,
+        builder: &mut FunctionBuilder<'_>,
+        func: ir::Value,
+    ) -> WasmResult<ir::Value>;
+
+    /// Translate a `cont.bind` instruction, binding `args` as the first
+    /// arguments of the given continuation, which has the continuation type
+    /// `argument_type_index`.
+    fn translate_cont_bind(
+        &mut self,
+        builder: &mut FunctionBuilder<'_>,
+        argument_type_index: TypeIndex,
+        cont: ir::Value,
+        args: &[ir::Value],
+    ) -> WasmResult<ir::Value>;
+
+    /// Translate a `resume` instruction, or a `resume_throw` instruction when
+    /// `throw` is given, in which case `args` are the exception's payload.
+    ///
+    /// Returns the resumption's result code along with the buffer holding the
+    /// values it produced: zero if the continuation returned, in which case its
+    /// results are read with `translate_resume_results`, or `n + 1` if it
+    /// suspended to the `n`th handler, in which case the payload is read with
+    /// `translate_resume_payload`. The translator is responsible for
+    /// dispatching on the result code.
+    fn translate_resume(
+        &mut self,
+        builder: &mut FunctionBuilder<'_>,
+        cont_type_index: TypeIndex,
+        throw: Option<TagIndex>,
+        cont: ir::Value,
+        args: &[ir::Value],
+        handlers: &[wasmparser::Handle],
+    ) -> WasmResult<(ir::Value, ir::Value)>;
+
+    /// Read the results of a continuation that returned from the buffer
+    /// returned by `translate_resume`.
+    fn translate_resume_results(
+        &mut self,
+        builder: &mut FunctionBuilder<'_>,
+        cont_type_index: TypeIndex,
+        buffer: ir::Value,
+    ) -> WasmResult<SmallVec<[ir::Value; 4]>>;
+
+    /// Read the payload of a suspension with the given tag, followed by the
+    /// suspended continuation, from the buffer returned by
+    /// `translate_resume`.
+    fn translate_resume_payload(
+        &mut self,
+        builder: &mut FunctionBuilder<'_>,
+        tag_index: TagIndex,
+        buffer: ir::Value,
+    ) -> WasmResult<SmallVec<[ir::Value; 4]>>;
+
+    /// Translate a `suspend` instruction, returning the values that the
+    /// current continuation is resumed with.
+    fn translate_suspend(
+        &mut self,
+        builder: &mut FunctionBuilder<'_>,
+        tag_index: TagIndex,
+        args: &[ir::Value],
+    ) -> WasmResult<SmallVec<[ir::Value; 4]>>;
+
+    /// Translate a `switch` instruction, returning the values that the current
+    /// continuation is resumed with.
+    fn translate_switch(
+        &mut self,
+        builder: &mut FunctionBuilder<'_>,
+        cont_type_index: TypeIndex,
+        tag_index: TagIndex,
+        cont: ir::Value,
+        args: &[ir::Value],
+    ) -> WasmResult<SmallVec<[ir::Value; 4]>>;
+
+    /// Get the number of values that the given continuation type's
+    /// continuations take as arguments.
+    fn cont_arity(&self, cont_type_index: TypeIndex) -> usize;
+
     /// Emit code at the beginning of every wasm loop.
     ///
     /// This can be used to insert explicit interrupt or safepoint checking at
@@ -731,6 +882,35 @@ pub trait FuncEnvironment: TargetEnvironment {
         Ok(())
     }
 
+    /// Whether the values of the function's locals and operand stack should be
+    /// recorded in its frame. If so, `declare_frame_state_locals` and
+    /// `before_translate_operator_with_types` are called during translation.
+    fn records_frame_state(&self) -> bool {
+        false
+    }
+
+    /// Optional callback, invoked after the locals for a function have been
+    /// parsed, with the types of all of the function's locals.
+    fn declare_frame_state_locals(
+        &mut self,
+        _builder: &mut FunctionBuilder,
+        _locals: Vec<WasmValType>,
+    ) {
+    }
+
+    /// Optional callback, invoked before `before_translate_operator` when the
+    /// operator is reachable, with the types of the values on the operand
+    /// stack, bottom-most first. Types are `None` if they are unknown.
+    fn before_translate_operator_with_types(
+        &mut self,
+        _op: &Operator,
+        _builder: &mut FunctionBuilder,
+        _state: &FuncTranslationState,
+        _operand_types: &[Option<WasmValType>],
+    ) -> WasmResult<()> {
+        Ok(())
+    }
+
     /// Optional callback for the `FunctionEnvironment` performing this translation to maintain
     /// internal state or finalize custom state for the operator that was translated
     fn after_translate_operator(
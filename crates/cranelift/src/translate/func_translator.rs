@@ -4,7 +4,9 @@
 //! function to Cranelift IR guided by a `FuncEnvironment` which provides information about the
 //! WebAssembly module and the runtime environment.
 
-use crate::translate::code_translator::{bitcast_wasm_returns, translate_operator};
+use crate::translate::code_translator::{
+    bitcast_wasm_returns, translate_operator, translate_pending_exception_check,
+};
 use crate::translate::environ::FuncEnvironment;
 use crate::translate::state::FuncTranslationState;
 use crate::translate::translation_utils::get_vmctx_value_label;
@@ -12,7 +14,7 @@ use cranelift_codegen::entity::EntityRef;
 use cranelift_codegen::ir::{self, Block, InstBuilder, ValueLabel};
 use cranelift_codegen::timing;
 use cranelift_frontend::{FunctionBuilder, FunctionBuilderContext, Variable};
-use wasmparser::{BinaryReader, FuncValidator, FunctionBody, WasmModuleResources};
+use wasmparser::{BinaryReader, FuncValidator, FunctionBody, Operator, WasmModuleResources};
 use wasmtime_environ::WasmResult;
 
 /// WebAssembly to Cranelift IR function translator.
@@ -156,6 +158,13 @@ fn parse_local_decls<FE: FuncEnvironment + ?Sized>(
 
     environ.after_locals(next_local);
 
+    if environ.records_frame_state() {
+        let locals = (0..validator.len_locals())
+            .map(|i| environ.convert_valtype(validator.get_local_type(i).unwrap()))
+            .collect();
+        environ.declare_frame_state_locals(builder, locals);
+    }
+
     Ok(())
 }
 
@@ -242,14 +251,42 @@ fn parse_function_body<FE: FuncEnvironment + ?Sized>(
     debug_assert_eq!(state.control_stack.len(), 1, "State not initialized");
 
     environ.before_translate_function(builder, state)?;
+    let mut operand_types = Vec::new();
     while !reader.eof() {
         let pos = reader.original_position();
         builder.set_srcloc(cur_srcloc(&reader));
         let op = reader.read_operator()?;
+        if state.reachable && environ.records_frame_state() {
+            // Note that this is done before the validator sees `op`, so these
+            // are the types of the operands as `op` starts executing.
+            operand_types.clear();
+            operand_types.extend((0..validator.operand_stack_height() as usize).rev().map(
+                |depth| {
+                    validator
+                        .get_operand_type(depth)
+                        .flatten()
+                        .map(|ty| environ.convert_valtype(ty))
+                },
+            ));
+            environ.before_translate_operator_with_types(&op, builder, state, &operand_types)?;
+        }
         validator.op(pos, &op)?;
         environ.before_translate_operator(&op, builder, state)?;
         translate_operator(validator, &op, builder, state, environ)?;
         environ.after_translate_operator(&op, builder, state)?;
+
+        // Within a `try_table`, a callee may have thrown an exception that it
+        // did not catch. Note that this is checked after the environment has
+        // observed the call's return, so that e.g. fuel is reloaded on both
+        // paths.
+        if state.reachable
+            && matches!(
+                op,
+                Operator::Call { .. } | Operator::CallIndirect { .. } | Operator::CallRef { .. }
+            )
+        {
+            translate_pending_exception_check(builder, state, environ)?;
+        }
     }
     environ.after_translate_function(builder, state)?;
     let pos = reader.original_position();
@@ -239,6 +239,13 @@ pub struct FuncTranslationState {
     // `FuncEnvironment::make_direct_func()`.
     // Stores both the function reference and the number of WebAssembly arguments
     functions: HashMap<FuncIndex, (ir::FuncRef, usize)>,
+
+    /// A stack of the exception handlers for the active `try_table`s.
+    ///
+    /// Each entry is the length of the control stack just after the
+    /// `try_table`'s frame was pushed, along with the block that dispatches a
+    /// pending exception to the `try_table`'s catch clauses.
+    pub(crate) handlers: Vec<(usize, Block)>,
 }
 
 // Public methods that are exposed to non- API consumers.
@@ -261,6 +268,7 @@ impl FuncTranslationState {
             memory_to_heap: HashMap::new(),
             signatures: HashMap::new(),
             functions: HashMap::new(),
+            handlers: Vec::new(),
         }
     }
 
@@ -272,6 +280,7 @@ impl FuncTranslationState {
         self.memory_to_heap.clear();
         self.signatures.clear();
         self.functions.clear();
+        debug_assert!(self.handlers.is_empty());
     }
 
     /// Initialize the state for compiling a function with the given signature.
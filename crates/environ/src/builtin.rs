@@ -196,6 +196,86 @@ macro_rules! foreach_builtin_function {
             #[cfg(feature = "gc")]
             table_fill_gc_ref(vmctx: vmctx, table: i32, dst: i64, val: reference, len: i64);
 
+            // Throw a new exception with the given tag. The exception's payload
+            // values have been written into the `values` array of `ValRaw`s.
+            #[cfg(feature = "gc")]
+            throw(vmctx: vmctx, tag: i32, values: pointer);
+
+            // Builtin implementation of the `throw_ref` instruction.
+            #[cfg(feature = "gc")]
+            throw_ref(vmctx: vmctx, exn: reference);
+
+            // Returns whether the pending exception was thrown with the given
+            // tag.
+            #[cfg(feature = "gc")]
+            pending_exception_matches(vmctx: vmctx, tag: i32) -> i32;
+
+            // Take the pending exception, so that it is no longer pending, and
+            // return it.
+            #[cfg(feature = "gc")]
+            take_pending_exception(vmctx: vmctx) -> reference;
+
+            // Write the given exception's payload values into the `values`
+            // array of `ValRaw`s.
+            #[cfg(feature = "gc")]
+            exception_payload(vmctx: vmctx, exn: reference, values: pointer);
+
+            // Builtin implementation of the `cont.new` instruction.
+            #[cfg(feature = "stack-switching")]
+            cont_new(vmctx: vmctx, func: pointer) -> i64;
+
+            // Builtin implementation of the `cont.bind` instruction. The bound
+            // values have been written into the `values` array of `ValRaw`s.
+            #[cfg(feature = "stack-switching")]
+            cont_bind(vmctx: vmctx, cont: i64, values: pointer, len: i32) -> i64;
+
+            // Builtin implementation of the `resume` instruction.
+            //
+            // The `len` arguments to the continuation are read from `values`
+            // and `handlers` is an array of `handlers_len` handler clauses,
+            // encoded as described by `RESUME_HANDLER_SWITCH`. Returns zero
+            // when the continuation returned, in which case its results have
+            // been written to `values`. Otherwise returns `n + 1` when the
+            // continuation suspended to the `n`th handler clause, in which case
+            // the tag's payload followed by the suspended continuation have
+            // been written to `values`.
+            #[cfg(feature = "stack-switching")]
+            resume(
+                vmctx: vmctx,
+                cont: i64,
+                values: pointer,
+                len: i32,
+                handlers: pointer,
+                handlers_len: i32
+            ) -> i32;
+
+            // Builtin implementation of the `resume_throw` instruction, which
+            // throws an exception with the given tag and payload `values` at
+            // the continuation's suspension point. Returns the same as
+            // `resume`.
+            #[cfg(all(feature = "stack-switching", feature = "gc"))]
+            resume_throw(
+                vmctx: vmctx,
+                cont: i64,
+                tag: i32,
+                values: pointer,
+                handlers: pointer,
+                handlers_len: i32
+            ) -> i32;
+
+            // Builtin implementation of the `suspend` instruction. The tag's
+            // `len` payload values are read from `values` and the values that
+            // the continuation is resumed with are written back into it.
+            #[cfg(feature = "stack-switching")]
+            suspend(vmctx: vmctx, tag: i32, values: pointer, len: i32);
+
+            // Builtin implementation of the `switch` instruction. The `len`
+            // arguments for the target continuation are read from `values`
+            // and the values that the current continuation is resumed with are
+            // written back into it.
+            #[cfg(feature = "stack-switching")]
+            switch(vmctx: vmctx, tag: i32, cont: i64, values: pointer, len: i32);
+
             // Raises an unconditional trap.
             trap(vmctx: vmctx, code: u8);
 
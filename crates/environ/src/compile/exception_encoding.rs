@@ -0,0 +1,77 @@
+use crate::obj::ELF_WASMTIME_EXCEPTIONS;
+use crate::prelude::*;
+use crate::ExceptionLandingPad;
+use object::write::{Object, StandardSegment};
+use object::{LittleEndian, SectionKind, U32Bytes};
+use std::ops::Range;
+
+/// A helper structure to build the custom-encoded section of a wasmtime
+/// compilation image which encodes exception landing pads.
+///
+/// This structure is incrementally fed the results of compiling individual
+/// functions and handles all the encoding internally, allowing usage of
+/// `lookup_exception_landing_pad` with the resulting section.
+#[derive(Default)]
+pub struct ExceptionEncodingBuilder {
+    return_offsets: Vec<U32Bytes<LittleEndian>>,
+    landing_pads: Vec<U32Bytes<LittleEndian>>,
+    frame_sizes: Vec<U32Bytes<LittleEndian>>,
+    last_offset: u32,
+}
+
+impl ExceptionEncodingBuilder {
+    /// Appends the exception landing pads of a function into this section.
+    ///
+    /// The `func` offsets are specified relative to the text section itself,
+    /// and the `landing_pads` offsets are specified relative to the start of
+    /// `func`.
+    ///
+    /// This is required to be called in-order for increasing ranges of `func`
+    /// to ensure the final arrays are properly sorted. Additionally
+    /// `landing_pads` must be sorted.
+    pub fn push(&mut self, func: Range<u64>, landing_pads: &[ExceptionLandingPad]) {
+        let func_start = u32::try_from(func.start).unwrap();
+        let func_end = u32::try_from(func.end).unwrap();
+        assert!(func_start >= self.last_offset);
+
+        self.return_offsets.reserve(landing_pads.len());
+        self.landing_pads.reserve(landing_pads.len());
+        self.frame_sizes.reserve(landing_pads.len());
+        for info in landing_pads {
+            let pos = func_start + info.return_offset;
+            assert!(pos >= self.last_offset);
+            self.return_offsets.push(U32Bytes::new(LittleEndian, pos));
+            self.landing_pads.push(U32Bytes::new(
+                LittleEndian,
+                func_start + info.landing_pad_offset,
+            ));
+            self.frame_sizes
+                .push(U32Bytes::new(LittleEndian, info.frame_size));
+            self.last_offset = pos;
+        }
+
+        self.last_offset = func_end;
+    }
+
+    /// Encodes this section into the object provided.
+    ///
+    /// Nothing is emitted if no function had any landing pads.
+    pub fn append_to(self, obj: &mut Object) {
+        if self.return_offsets.is_empty() {
+            return;
+        }
+        let section = obj.add_section(
+            obj.segment_name(StandardSegment::Data).to_vec(),
+            ELF_WASMTIME_EXCEPTIONS.as_bytes().to_vec(),
+            SectionKind::ReadOnlyData,
+        );
+
+        // NB: this matches the encoding expected by
+        // `lookup_exception_landing_pad`.
+        let amt = u32::try_from(self.return_offsets.len()).unwrap();
+        obj.append_section_data(section, &amt.to_le_bytes(), 1);
+        obj.append_section_data(section, object::bytes_of_slice(&self.return_offsets), 1);
+        obj.append_section_data(section, object::bytes_of_slice(&self.landing_pads), 1);
+        obj.append_section_data(section, object::bytes_of_slice(&self.frame_sizes), 1);
+    }
+}
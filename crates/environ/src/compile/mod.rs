@@ -17,12 +17,14 @@ use std::path;
 use std::sync::Arc;
 
 mod address_map;
+mod exception_encoding;
 mod module_artifacts;
 mod module_environ;
 mod module_types;
 mod trap_encoding;
 
 pub use self::address_map::*;
+pub use self::exception_encoding::*;
 pub use self::module_artifacts::*;
 pub use self::module_environ::*;
 pub use self::module_types::*;
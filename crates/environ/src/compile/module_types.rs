@@ -1,7 +1,7 @@
 use crate::{
-    EngineOrModuleTypeIndex, EntityRef, Module, ModuleInternedRecGroupIndex,
+    wasm_unsupported, EngineOrModuleTypeIndex, EntityRef, Module, ModuleInternedRecGroupIndex,
     ModuleInternedTypeIndex, ModuleTypes, TypeConvert, TypeIndex, WasmCompositeType, WasmFuncType,
-    WasmHeapType, WasmResult, WasmSubType,
+    WasmHeapTopType, WasmHeapType, WasmResult, WasmStorageType, WasmSubType, WasmValType,
 };
 use std::{borrow::Cow, collections::HashMap, ops::Index};
 use wasmparser::{UnpackedIndex, Validator, ValidatorId};
@@ -114,6 +114,25 @@ impl ModuleTypesBuilder {
             let wasm_ty = WasmparserTypeConverter::new(self, module)
                 .with_rec_group(validator_types, rec_group_id)
                 .convert_sub_type(ty);
+
+            // Continuations live outside of the GC heap and are not traced by
+            // collectors, so they cannot be stored inside GC objects yet.
+            let fields = match &wasm_ty.composite_type {
+                WasmCompositeType::Array(a) => core::slice::from_ref(&a.0),
+                WasmCompositeType::Struct(s) => &s.fields[..],
+                WasmCompositeType::Func(_) | WasmCompositeType::Cont(_) => &[],
+            };
+            if fields.iter().any(|f| match f.element_type {
+                WasmStorageType::Val(WasmValType::Ref(r)) => {
+                    r.heap_type.top() == WasmHeapTopType::Cont
+                }
+                _ => false,
+            }) {
+                return Err(wasm_unsupported!(
+                    "continuation references as struct or array fields"
+                ));
+            }
+
             self.wasm_sub_type_in_rec_group(id, wasm_ty);
         }
 
@@ -390,6 +409,7 @@ impl TypeConvert for WasmparserTypeConverter<'_> {
                         WasmCompositeType::Array(_) => WasmHeapType::ConcreteArray(index),
                         WasmCompositeType::Func(_) => WasmHeapType::ConcreteFunc(index),
                         WasmCompositeType::Struct(_) => WasmHeapType::ConcreteStruct(index),
+                        WasmCompositeType::Cont(_) => WasmHeapType::ConcreteCont(index),
                     }
                 } else if let Some((wasmparser_types, _)) = self.rec_group_context.as_ref() {
                     let wasmparser_ty = &wasmparser_types[id].composite_type;
@@ -405,7 +425,7 @@ impl TypeConvert for WasmparserTypeConverter<'_> {
                             WasmHeapType::ConcreteStruct(index)
                         }
                         wasmparser::CompositeInnerType::Cont(_) => {
-                            panic!("unimplemented continuation types")
+                            WasmHeapType::ConcreteCont(index)
                         }
                     }
                 } else {
@@ -428,6 +448,7 @@ impl TypeConvert for WasmparserTypeConverter<'_> {
                         WasmCompositeType::Array(_) => WasmHeapType::ConcreteArray(index),
                         WasmCompositeType::Func(_) => WasmHeapType::ConcreteFunc(index),
                         WasmCompositeType::Struct(_) => WasmHeapType::ConcreteStruct(index),
+                        WasmCompositeType::Cont(_) => WasmHeapType::ConcreteCont(index),
                     }
                 } else if let Some((parser_types, rec_group)) = self.rec_group_context.as_ref() {
                     let rec_group_index = interned.index() - self.types.types.len_types();
@@ -448,7 +469,7 @@ impl TypeConvert for WasmparserTypeConverter<'_> {
                             WasmHeapType::ConcreteStruct(index)
                         }
                         wasmparser::CompositeInnerType::Cont(_) => {
-                            panic!("unimplemented continuation types")
+                            WasmHeapType::ConcreteCont(index)
                         }
                     }
                 } else {
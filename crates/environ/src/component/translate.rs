@@ -608,7 +608,7 @@ impl<'a, 'data> Translator<'a, 'data> {
                             self.instantiate_module(index, &args)
                         }
                         wasmparser::Instance::FromExports(exports) => {
-                            self.instantiate_module_from_exports(&exports)
+                            self.instantiate_module_from_exports(&exports)?
                         }
                     };
                     self.result.initializers.push(init);
@@ -685,7 +685,7 @@ impl<'a, 'data> Translator<'a, 'data> {
                             name,
                         } => {
                             let instance = ModuleInstanceIndex::from_u32(instance_index);
-                            self.alias_module_instance_export(kind, instance, name)
+                            self.alias_module_instance_export(kind, instance, name)?
                         }
                     };
                     self.result.initializers.push(init);
@@ -734,7 +734,7 @@ impl<'a, 'data> Translator<'a, 'data> {
     fn instantiate_module_from_exports(
         &mut self,
         exports: &[wasmparser::Export<'data>],
-    ) -> LocalInitializer<'data> {
+    ) -> Result<LocalInitializer<'data>> {
         let mut map = HashMap::with_capacity(exports.len());
         for export in exports {
             let idx = match export.kind {
@@ -754,13 +754,13 @@ impl<'a, 'data> Translator<'a, 'data> {
                     let index = GlobalIndex::from_u32(export.index);
                     EntityIndex::Global(index)
                 }
-
-                // doesn't get past validation
-                wasmparser::ExternalKind::Tag => unimplemented!("wasm exceptions"),
+                wasmparser::ExternalKind::Tag => {
+                    bail!("core wasm tags in synthetic core instances are not supported")
+                }
             };
             map.insert(export.name, idx);
         }
-        LocalInitializer::ModuleSynthetic(map)
+        Ok(LocalInitializer::ModuleSynthetic(map))
     }
 
     fn instantiate_component(
@@ -832,16 +832,16 @@ impl<'a, 'data> Translator<'a, 'data> {
         kind: wasmparser::ExternalKind,
         instance: ModuleInstanceIndex,
         name: &'data str,
-    ) -> LocalInitializer<'data> {
-        match kind {
+    ) -> Result<LocalInitializer<'data>> {
+        Ok(match kind {
             wasmparser::ExternalKind::Func => LocalInitializer::AliasExportFunc(instance, name),
             wasmparser::ExternalKind::Memory => LocalInitializer::AliasExportMemory(instance, name),
             wasmparser::ExternalKind::Table => LocalInitializer::AliasExportTable(instance, name),
             wasmparser::ExternalKind::Global => LocalInitializer::AliasExportGlobal(instance, name),
             wasmparser::ExternalKind::Tag => {
-                unimplemented!("wasm exceptions");
+                bail!("aliasing core wasm tags in components is not supported")
             }
-        }
+        })
     }
 
     fn alias_component_outer(
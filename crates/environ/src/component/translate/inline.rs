@@ -691,7 +691,7 @@ impl<'a> Inliner<'a> {
                         for (module, name, _ty) in self.nested_modules[*idx].module.imports() {
                             let instance = args[module];
                             defs.push(
-                                self.core_def_of_module_instance_export(frame, instance, name),
+                                self.core_def_of_module_instance_export(frame, instance, name)?,
                             );
                         }
                         instance_module = InstanceModule::Static(*idx);
@@ -702,7 +702,7 @@ impl<'a> Inliner<'a> {
                         for ((module, name), _) in types[*ty].imports.iter() {
                             let instance = args[module.as_str()];
                             let def =
-                                self.core_def_of_module_instance_export(frame, instance, name);
+                                self.core_def_of_module_instance_export(frame, instance, name)?;
                             defs.entry(module.to_string())
                                 .or_insert(IndexMap::new())
                                 .insert(name.to_string(), def);
@@ -799,12 +799,12 @@ impl<'a> Inliner<'a> {
             AliasExportFunc(instance, name) => {
                 frame
                     .funcs
-                    .push(self.core_def_of_module_instance_export(frame, *instance, *name));
+                    .push(self.core_def_of_module_instance_export(frame, *instance, *name)?);
             }
 
             AliasExportTable(instance, name) => {
                 frame.tables.push(
-                    match self.core_def_of_module_instance_export(frame, *instance, *name) {
+                    match self.core_def_of_module_instance_export(frame, *instance, *name)? {
                         dfg::CoreDef::Export(e) => e,
                         _ => unreachable!(),
                     },
@@ -813,7 +813,7 @@ impl<'a> Inliner<'a> {
 
             AliasExportGlobal(instance, name) => {
                 frame.globals.push(
-                    match self.core_def_of_module_instance_export(frame, *instance, *name) {
+                    match self.core_def_of_module_instance_export(frame, *instance, *name)? {
                         dfg::CoreDef::Export(e) => e,
                         _ => unreachable!(),
                     },
@@ -822,7 +822,7 @@ impl<'a> Inliner<'a> {
 
             AliasExportMemory(instance, name) => {
                 frame.memories.push(
-                    match self.core_def_of_module_instance_export(frame, *instance, *name) {
+                    match self.core_def_of_module_instance_export(frame, *instance, *name)? {
                         dfg::CoreDef::Export(e) => e,
                         _ => unreachable!(),
                     },
@@ -913,8 +913,8 @@ impl<'a> Inliner<'a> {
         frame: &InlinerFrame<'a>,
         instance: ModuleInstanceIndex,
         name: &'a str,
-    ) -> dfg::CoreDef {
-        match &frame.module_instances[instance] {
+    ) -> Result<dfg::CoreDef> {
+        Ok(match &frame.module_instances[instance] {
             // Instantiations of a statically known module means that we can
             // refer to the exported item by a precise index, skipping name
             // lookups at runtime.
@@ -944,8 +944,11 @@ impl<'a> Inliner<'a> {
                 EntityIndex::Table(i) => frame.tables[i].clone().into(),
                 EntityIndex::Global(i) => frame.globals[i].clone().into(),
                 EntityIndex::Memory(i) => frame.memories[i].clone().into(),
+                EntityIndex::Tag(_) => {
+                    bail!("core wasm tags in synthetic core instances are not supported")
+                }
             },
-        }
+        })
     }
 
     /// Translates a `LocalCanonicalOptions` which indexes into the `frame`
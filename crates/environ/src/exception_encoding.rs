@@ -0,0 +1,54 @@
+use object::{Bytes, LittleEndian, U32Bytes};
+
+/// Information about a call's exception landing pad.
+#[derive(Debug, PartialEq, Eq, Clone)]
+pub struct ExceptionLandingPad {
+    /// The offset of the call's return address in native code.
+    ///
+    /// This is relative to the beginning of the function when building the
+    /// `ELF_WASMTIME_EXCEPTIONS` section, and to the beginning of the text
+    /// section when looked up.
+    pub return_offset: u32,
+
+    /// The offset at which execution resumes when the stack is unwound to the
+    /// call, relative to the same base as `return_offset`.
+    pub landing_pad_offset: u32,
+
+    /// The distance from the frame pointer down to the stack pointer at the
+    /// landing pad.
+    pub frame_size: u32,
+}
+
+/// Decodes the provided exception section and attempts to find the landing pad
+/// of the call whose return address is at `return_offset`.
+///
+/// The `section` provided is expected to have been built by
+/// `ExceptionEncodingBuilder`. Additionally the `return_offset` should be a
+/// relative offset within the text section of the compilation image.
+pub fn lookup_exception_landing_pad(
+    section: &[u8],
+    return_offset: usize,
+) -> Option<ExceptionLandingPad> {
+    let mut section = Bytes(section);
+    // NB: this matches the encoding written by `append_to` in
+    // `ExceptionEncodingBuilder`.
+    let count = section.read::<U32Bytes<LittleEndian>>().ok()?;
+    let count = usize::try_from(count.get(LittleEndian)).ok()?;
+    let (return_offsets, rest) =
+        object::slice_from_bytes::<U32Bytes<LittleEndian>>(section.0, count).ok()?;
+    let (landing_pads, rest) =
+        object::slice_from_bytes::<U32Bytes<LittleEndian>>(rest, count).ok()?;
+    let (frame_sizes, _) = object::slice_from_bytes::<U32Bytes<LittleEndian>>(rest, count).ok()?;
+
+    // Like trap offsets, return addresses are precise, so look for an exact
+    // match.
+    let return_offset = u32::try_from(return_offset).ok()?;
+    let index = return_offsets
+        .binary_search_by_key(&return_offset, |val| val.get(LittleEndian))
+        .ok()?;
+    Some(ExceptionLandingPad {
+        return_offset,
+        landing_pad_offset: landing_pads.get(index)?.get(LittleEndian),
+        frame_size: frame_sizes.get(index)?.get(LittleEndian),
+    })
+}
@@ -0,0 +1,62 @@
+//! Metadata describing where a compiled function keeps its Wasm-level frame
+//! state, i.e. its locals and operand stack, so that it can be recovered from
+//! a native stack frame when capturing a core dump.
+
+use crate::prelude::*;
+use crate::WasmValType;
+use serde_derive::{Deserialize, Serialize};
+
+/// Tag of an operand stack entry whose value was not recorded.
+pub const FRAME_STATE_TAG_MISSING: u32 = 0;
+/// Tag of an `i32` operand stack entry.
+pub const FRAME_STATE_TAG_I32: u32 = 1;
+/// Tag of an `i64` operand stack entry.
+pub const FRAME_STATE_TAG_I64: u32 = 2;
+/// Tag of an `f32` operand stack entry.
+pub const FRAME_STATE_TAG_F32: u32 = 3;
+/// Tag of an `f64` operand stack entry.
+pub const FRAME_STATE_TAG_F64: u32 = 4;
+
+/// The size, in bytes, of each entry in a recorded operand stack.
+///
+/// Each entry is a `u32` tag (one of the `FRAME_STATE_TAG_*` constants),
+/// followed by four bytes of padding and then the entry's value in the low
+/// bytes of a native-endian `u64`.
+pub const FRAME_STATE_OPERAND_SIZE: u32 = 16;
+
+/// Where a compiled function stores its Wasm-level frame state.
+///
+/// All offsets are relative to the function's frame pointer.
+#[derive(Serialize, Deserialize, Debug, Clone, PartialEq, Eq)]
+pub struct FrameStateInfo {
+    /// Offset of the slot holding this frame's `vmctx` pointer.
+    pub vmctx_offset: i32,
+    /// The function's locals, including its parameters, in order.
+    pub locals: Box<[FrameStateLocal]>,
+    /// The function's operand stack, if it is recorded.
+    pub operand_stack: Option<FrameStateOperandStack>,
+}
+
+/// A single local of a function described by [`FrameStateInfo`].
+#[derive(Serialize, Deserialize, Debug, Clone, PartialEq, Eq)]
+pub struct FrameStateLocal {
+    /// The type of this local.
+    pub ty: WasmValType,
+    /// Offset of the slot holding this local's value, if it is recorded.
+    ///
+    /// Only numeric locals are recorded. Their values are stored in the low
+    /// bytes of a native-endian 8-byte slot.
+    pub offset: Option<i32>,
+}
+
+/// The recorded operand stack of a function described by [`FrameStateInfo`].
+#[derive(Serialize, Deserialize, Debug, Clone, PartialEq, Eq)]
+pub struct FrameStateOperandStack {
+    /// Offset of the `u32` holding the current depth of the operand stack.
+    pub depth_offset: i32,
+    /// Offset of the bottom-most operand stack entry. Entries are
+    /// [`FRAME_STATE_OPERAND_SIZE`] bytes each and grow upwards.
+    pub entries_offset: i32,
+    /// The maximum number of entries that are ever recorded.
+    pub max_depth: u32,
+}
@@ -12,9 +12,17 @@
 #[cfg(feature = "gc")]
 pub mod drc;
 
+#[cfg(feature = "gc")]
+pub mod mark_sweep;
+
+#[cfg(feature = "gc")]
+pub mod null;
+
 use crate::prelude::*;
 use crate::{WasmArrayType, WasmCompositeType, WasmStorageType, WasmStructType, WasmValType};
 use core::alloc::Layout;
+use core::fmt;
+use serde_derive::{Deserialize, Serialize};
 
 /// Discriminant to check whether GC reference is an `i31ref` or not.
 pub const I31_DISCRIMINANT: u64 = 1;
@@ -49,6 +57,117 @@ pub fn byte_size_of_wasm_ty_in_gc_heap(ty: &WasmStorageType) -> u32 {
     }
 }
 
+/// Align `offset` up to `bytes`, updating `max_align` if `align` is the
+/// new maximum alignment, and returning the aligned offset.
+#[cfg(feature = "gc")]
+fn align_up(offset: &mut u32, max_align: &mut u32, align: u32) -> u32 {
+    debug_assert!(max_align.is_power_of_two());
+    debug_assert!(align.is_power_of_two());
+    *offset = offset.checked_add(align - 1).unwrap() & !(align - 1);
+    *max_align = core::cmp::max(*max_align, align);
+    *offset
+}
+
+/// Define a new field of size and alignment `bytes`, updating the object's
+/// total `size` and `align` as necessary. The offset of the new field is
+/// returned.
+#[cfg(feature = "gc")]
+fn field(size: &mut u32, align: &mut u32, bytes: u32) -> u32 {
+    let offset = align_up(size, align, bytes);
+    *size += bytes;
+    offset
+}
+
+/// Common code to define a GC array's layout, given the size and alignment of
+/// the collector's GC header and its expected offset of the array length
+/// field.
+#[cfg(feature = "gc")]
+fn common_array_layout(
+    ty: &WasmArrayType,
+    header_size: u32,
+    header_align: u32,
+    expected_array_length_offset: u32,
+) -> GcArrayLayout {
+    let mut size = header_size;
+    let mut align = header_align;
+
+    let length_field_offset = field(&mut size, &mut align, 4);
+    debug_assert_eq!(length_field_offset, expected_array_length_offset);
+
+    let elem_size = byte_size_of_wasm_ty_in_gc_heap(&ty.0.element_type);
+    let elems_offset = align_up(&mut size, &mut align, elem_size);
+    debug_assert_eq!(elems_offset, size);
+
+    GcArrayLayout {
+        base_size: size,
+        align,
+        elem_size,
+    }
+}
+
+/// Common code to define a GC struct's layout, given the size and alignment of
+/// the collector's GC header.
+#[cfg(feature = "gc")]
+fn common_struct_layout(
+    ty: &WasmStructType,
+    header_size: u32,
+    header_align: u32,
+) -> GcStructLayout {
+    // Process each field, aligning it to its natural alignment.
+    //
+    // We don't try and do any fancy field reordering to minimize padding
+    // (yet?) because (a) the toolchain probably already did that and (b)
+    // we're just doing the simple thing first. We can come back and improve
+    // things here if we find that (a) isn't actually holding true in
+    // practice.
+    let mut size = header_size;
+    let mut align = header_align;
+
+    let fields = ty
+        .fields
+        .iter()
+        .map(|f| {
+            let field_size = byte_size_of_wasm_ty_in_gc_heap(&f.element_type);
+            field(&mut size, &mut align, field_size)
+        })
+        .collect();
+
+    // Ensure that the final size is a multiple of the alignment, for
+    // simplicity.
+    align_up(&mut size, &mut 16, align);
+
+    GcStructLayout {
+        size,
+        align,
+        fields,
+    }
+}
+
+/// Which garbage collector implementation compiled code and the runtime use.
+///
+/// This is decided at `Config` time and recorded in `Tunables` because the
+/// collector determines both object layouts and the barriers that are emitted
+/// into compiled code.
+#[derive(Clone, Copy, Debug, Hash, PartialEq, Eq, Serialize, Deserialize)]
+pub enum Collector {
+    /// The deferred reference-counting collector.
+    DeferredReferenceCounting,
+    /// The tracing, non-moving mark-and-sweep collector.
+    MarkSweep,
+    /// The bump-allocating collector that never collects.
+    Null,
+}
+
+impl fmt::Display for Collector {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        match self {
+            Collector::DeferredReferenceCounting => write!(f, "deferred reference-counting"),
+            Collector::MarkSweep => write!(f, "mark-and-sweep"),
+            Collector::Null => write!(f, "null"),
+        }
+    }
+}
+
 /// A trait for getting the layout of a Wasm GC struct or array inside a
 /// particular collector.
 pub trait GcTypeLayouts {
@@ -60,13 +179,13 @@ pub trait GcTypeLayouts {
 
     /// Get this collector's layout for the given composite type.
     ///
-    /// Returns `None` if the type is a function type, as functions are not
-    /// managed by the GC.
+    /// Returns `None` if the type is a function or continuation type, as
+    /// functions and continuations are not managed by the GC.
     fn gc_layout(&self, ty: &WasmCompositeType) -> Option<GcLayout> {
         match ty {
             WasmCompositeType::Array(ty) => Some(self.array_layout(ty).into()),
             WasmCompositeType::Struct(ty) => Some(self.struct_layout(ty).into()),
-            WasmCompositeType::Func(_) => None,
+            WasmCompositeType::Func(_) | WasmCompositeType::Cont(_) => None,
         }
     }
 
@@ -0,0 +1,33 @@
+//! Layout of Wasm GC objects in the mark-and-sweep collector.
+
+use super::*;
+
+/// The size of the header for GC objects in the mark-and-sweep collector.
+///
+/// The mark-and-sweep collector keeps its mark bits in a side table, so its
+/// header is just the collector-agnostic `VMGcHeader`.
+pub const HEADER_SIZE: u32 = VM_GC_HEADER_SIZE;
+
+/// The align of the header for GC objects in the mark-and-sweep collector.
+pub const HEADER_ALIGN: u32 = VM_GC_HEADER_ALIGN;
+
+/// The offset of the length field in a `VMMarkSweepArrayHeader`.
+pub const ARRAY_LENGTH_OFFSET: u32 = HEADER_SIZE;
+
+/// The layout of Wasm GC objects in the mark-and-sweep collector.
+#[derive(Default)]
+pub struct MarkSweepTypeLayouts;
+
+impl GcTypeLayouts for MarkSweepTypeLayouts {
+    fn array_length_field_offset(&self) -> u32 {
+        ARRAY_LENGTH_OFFSET
+    }
+
+    fn array_layout(&self, ty: &WasmArrayType) -> GcArrayLayout {
+        common_array_layout(ty, HEADER_SIZE, HEADER_ALIGN, ARRAY_LENGTH_OFFSET)
+    }
+
+    fn struct_layout(&self, ty: &WasmStructType) -> GcStructLayout {
+        common_struct_layout(ty, HEADER_SIZE, HEADER_ALIGN)
+    }
+}
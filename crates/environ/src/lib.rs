@@ -18,6 +18,8 @@ mod address_map;
 mod builtin;
 mod demangling;
 mod error;
+mod exception_encoding;
+mod frame_state;
 mod gc;
 mod module;
 mod module_artifacts;
@@ -35,6 +37,8 @@ pub use crate::address_map::*;
 pub use crate::builtin::*;
 pub use crate::demangling::*;
 pub use crate::error::*;
+pub use crate::exception_encoding::*;
+pub use crate::frame_state::*;
 pub use crate::gc::*;
 pub use crate::module::*;
 pub use crate::module_artifacts::*;
@@ -3,7 +3,8 @@
 
 use crate::prelude::*;
 use crate::{
-    DefinedFuncIndex, FilePos, FuncIndex, Module, ModuleInternedTypeIndex, PrimaryMap, StackMap,
+    DefinedFuncIndex, FilePos, FrameStateInfo, FuncIndex, Module, ModuleInternedTypeIndex,
+    PrimaryMap, StackMap,
 };
 use core::fmt;
 use core::ops::Range;
@@ -29,6 +30,7 @@ pub struct CompiledFunctionInfo {
 pub struct WasmFunctionInfo {
     pub start_srcloc: FilePos,
     pub stack_maps: Box<[StackMapInformation]>,
+    pub frame_state: Option<FrameStateInfo>,
 }
 
 /// Description of where a function is located in the text section of a
@@ -71,6 +71,28 @@ pub const ELF_WASMTIME_ADDRMAP: &str = ".wasmtime.addrmap";
 /// to the 32-bit encodings for offsets this doesn't support images >=4gb.
 pub const ELF_WASMTIME_TRAPS: &str = ".wasmtime.traps";
 
+/// A custom binary-encoded section of wasmtime compilation artifacts which
+/// encodes the exception landing pads of calls within `try_table`s.
+///
+/// When a Wasm exception is thrown, the runtime walks the stack looking for a
+/// frame whose return address is listed in this section, and then resumes
+/// execution at that call's landing pad. The encoding is similar to that of
+/// `ELF_WASMTIME_TRAPS`:
+///
+/// * First the section has a 32-bit little endian integer indicating how many
+///   landing pads are in the section.
+/// * Next is a sorted array, of the same length as read before, of 32-bit
+///   little-endian integers. These are the offsets of the calls' return
+///   addresses within the text section of the compilation image.
+/// * Then is an array of the same length of 32-bit little-endian offsets of
+///   the landing pads within the text section.
+/// * Finally is an array of the same length of 32-bit little-endian frame
+///   sizes, which are the distance from the frame pointer down to the stack
+///   pointer at each landing pad.
+///
+/// This section is decoded by `lookup_exception_landing_pad`.
+pub const ELF_WASMTIME_EXCEPTIONS: &str = ".wasmtime.exceptions";
+
 /// A custom section which consists of just 1 byte which is either 0 or 1 as to
 /// whether BTI is enabled.
 pub const ELF_WASM_BTI: &str = ".wasmtime.bti";
@@ -9,14 +9,21 @@ use serde_derive::{Deserialize, Serialize};
 #[derive(Debug, Serialize, Deserialize)]
 pub struct StackMap {
     bits: CompoundBitSet,
+    /// The frame offsets of live continuation references, which are `u64`
+    /// handles rather than `u32` GC references.
+    cont_bits: CompoundBitSet,
     frame_size: u32,
 }
 
 impl StackMap {
     /// Creates a new `StackMap`, typically from a preexisting
     /// `binemit::StackMap`.
-    pub fn new(frame_size: u32, bits: CompoundBitSet) -> StackMap {
-        StackMap { bits, frame_size }
+    pub fn new(frame_size: u32, bits: CompoundBitSet, cont_bits: CompoundBitSet) -> StackMap {
+        StackMap {
+            bits,
+            cont_bits,
+            frame_size,
+        }
     }
 
     /// Returns the byte size of this stack map's frame.
@@ -57,4 +64,24 @@ impl StackMap {
             ptr_to_gc_ref.cast::<u32>()
         })
     }
+
+    /// Given the stack pointer, get a reference to each live continuation
+    /// reference in the stack frame.
+    ///
+    /// # Safety
+    ///
+    /// The `sp` must be the stack pointer at the code offset that this stack
+    /// map is associated with.
+    pub unsafe fn live_cont_refs(&self, sp: *mut usize) -> impl Iterator<Item = *mut u64> + '_ {
+        self.cont_bits.iter().map(move |i| {
+            log::trace!("Live continuation ref in frame at frame offset {i:#x}");
+            let ptr_to_cont_ref = sp.byte_add(i);
+            assert!({
+                let delta = ptr_to_cont_ref as usize - sp as usize;
+                let frame_size = usize::try_from(self.frame_size).unwrap();
+                delta < frame_size
+            });
+            ptr_to_cont_ref.cast::<u64>()
+        })
+    }
 }
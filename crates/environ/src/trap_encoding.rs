@@ -88,6 +88,17 @@ pub enum Trap {
     /// would have violated the reentrance rules of the component model,
     /// triggering a trap instead.
     CannotEnterComponent,
+
+    /// The GC heap was exhausted and a collection could not free up enough
+    /// space to satisfy an allocation.
+    GcHeapOutOfMemory,
+
+    /// A continuation was suspended with a tag that none of the enclosing
+    /// `resume` instructions handle.
+    UnhandledTag,
+
+    /// Attempted to resume a continuation that was already resumed.
+    ContinuationAlreadyConsumed,
     // if adding a variant here be sure to update the `check!` macro below
 }
 
@@ -124,6 +135,9 @@ impl Trap {
             AllocationTooLarge
             CastFailure
             CannotEnterComponent
+            GcHeapOutOfMemory
+            UnhandledTag
+            ContinuationAlreadyConsumed
         }
 
         None
@@ -154,6 +168,9 @@ impl fmt::Display for Trap {
             AllocationTooLarge => "allocation size too large",
             CastFailure => "cast failure",
             CannotEnterComponent => "cannot enter component instance",
+            GcHeapOutOfMemory => "GC heap out of memory",
+            UnhandledTag => "unhandled tag",
+            ContinuationAlreadyConsumed => "continuation already consumed",
         };
         write!(f, "wasm trap: {desc}")
     }
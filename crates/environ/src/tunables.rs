@@ -1,3 +1,4 @@
+use crate::Collector;
 use anyhow::{anyhow, bail, Result};
 use serde_derive::{Deserialize, Serialize};
 use target_lexicon::{PointerWidth, Triple};
@@ -52,6 +53,11 @@ pub struct Tunables {
     /// offsets in the original file is generated.
     pub generate_address_map: bool,
 
+    /// Whether or not compiled code records the values of its Wasm locals and
+    /// operand stack in its frames so that they can be recovered when a core
+    /// dump is captured.
+    pub record_frame_state: bool,
+
     /// Flag for the component module whether adapter modules have debug
     /// assertions baked into them.
     pub debug_adapter_modules: bool,
@@ -66,6 +72,10 @@ pub struct Tunables {
     /// Whether or not the host will be using native signals (e.g. SIGILL,
     /// SIGSEGV, etc) to implement traps.
     pub signals_based_traps: bool,
+
+    /// The garbage collector implementation to use, or `None` if GC types are
+    /// not enabled.
+    pub collector: Option<Collector>,
 }
 
 impl Tunables {
@@ -114,10 +124,12 @@ impl Tunables {
             guard_before_linear_memory: true,
             table_lazy_init: true,
             generate_address_map: true,
+            record_frame_state: false,
             debug_adapter_modules: false,
             relaxed_simd_deterministic: false,
             winch_callable: false,
             signals_based_traps: true,
+            collector: None,
         }
     }
 
@@ -208,6 +208,15 @@ impl WasmValType {
         }
     }
 
+    /// Is this a continuation reference type?
+    #[inline]
+    pub fn is_cont_ref(&self) -> bool {
+        match self {
+            WasmValType::Ref(r) => r.heap_type.top() == WasmHeapTopType::Cont,
+            _ => false,
+        }
+    }
+
     fn trampoline_type(&self) -> Self {
         match self {
             WasmValType::Ref(r) => WasmValType::Ref(WasmRefType {
@@ -425,6 +434,15 @@ pub enum WasmHeapType {
     Struct,
     ConcreteStruct(EngineOrModuleTypeIndex),
     None,
+
+    // Exception types.
+    Exn,
+    NoExn,
+
+    // Continuation types.
+    Cont,
+    ConcreteCont(EngineOrModuleTypeIndex),
+    NoCont,
 }
 
 impl From<WasmHeapTopType> for WasmHeapType {
@@ -434,6 +452,8 @@ impl From<WasmHeapTopType> for WasmHeapType {
             WasmHeapTopType::Extern => Self::Extern,
             WasmHeapTopType::Any => Self::Any,
             WasmHeapTopType::Func => Self::Func,
+            WasmHeapTopType::Exn => Self::Exn,
+            WasmHeapTopType::Cont => Self::Cont,
         }
     }
 }
@@ -445,6 +465,8 @@ impl From<WasmHeapBottomType> for WasmHeapType {
             WasmHeapBottomType::NoExtern => Self::NoExtern,
             WasmHeapBottomType::None => Self::None,
             WasmHeapBottomType::NoFunc => Self::NoFunc,
+            WasmHeapBottomType::NoExn => Self::NoExn,
+            WasmHeapBottomType::NoCont => Self::NoCont,
         }
     }
 }
@@ -465,6 +487,11 @@ impl fmt::Display for WasmHeapType {
             Self::Struct => write!(f, "struct"),
             Self::ConcreteStruct(i) => write!(f, "struct {i}"),
             Self::None => write!(f, "none"),
+            Self::Exn => write!(f, "exn"),
+            Self::NoExn => write!(f, "noexn"),
+            Self::Cont => write!(f, "cont"),
+            Self::ConcreteCont(i) => write!(f, "cont {i}"),
+            Self::NoCont => write!(f, "nocont"),
         }
     }
 }
@@ -478,6 +505,7 @@ impl TypeTrace for WasmHeapType {
             Self::ConcreteArray(i) => func(i),
             Self::ConcreteFunc(i) => func(i),
             Self::ConcreteStruct(i) => func(i),
+            Self::ConcreteCont(i) => func(i),
             _ => Ok(()),
         }
     }
@@ -490,6 +518,7 @@ impl TypeTrace for WasmHeapType {
             Self::ConcreteArray(i) => func(i),
             Self::ConcreteFunc(i) => func(i),
             Self::ConcreteStruct(i) => func(i),
+            Self::ConcreteCont(i) => func(i),
             _ => Ok(()),
         }
     }
@@ -500,12 +529,12 @@ impl WasmHeapType {
     #[inline]
     pub fn is_vmgcref_type(&self) -> bool {
         match self.top() {
-            // All `t <: (ref null any)` and `t <: (ref null extern)` are
-            // represented as `VMGcRef`s.
-            WasmHeapTopType::Any | WasmHeapTopType::Extern => true,
+            // All `t <: (ref null any)`, `t <: (ref null extern)`, and
+            // `t <: (ref null exn)` are represented as `VMGcRef`s.
+            WasmHeapTopType::Any | WasmHeapTopType::Extern | WasmHeapTopType::Exn => true,
 
-            // All `t <: (ref null func)` are not.
-            WasmHeapTopType::Func => false,
+            // All `t <: (ref null func)` and `t <: (ref null cont)` are not.
+            WasmHeapTopType::Func | WasmHeapTopType::Cont => false,
         }
     }
 
@@ -543,6 +572,12 @@ impl WasmHeapType {
             | WasmHeapType::Struct
             | WasmHeapType::ConcreteStruct(_)
             | WasmHeapType::None => WasmHeapTopType::Any,
+
+            WasmHeapType::Exn | WasmHeapType::NoExn => WasmHeapTopType::Exn,
+
+            WasmHeapType::Cont | WasmHeapType::ConcreteCont(_) | WasmHeapType::NoCont => {
+                WasmHeapTopType::Cont
+            }
         }
     }
 
@@ -570,6 +605,12 @@ impl WasmHeapType {
             | WasmHeapType::Struct
             | WasmHeapType::ConcreteStruct(_)
             | WasmHeapType::None => WasmHeapBottomType::None,
+
+            WasmHeapType::Exn | WasmHeapType::NoExn => WasmHeapBottomType::NoExn,
+
+            WasmHeapType::Cont | WasmHeapType::ConcreteCont(_) | WasmHeapType::NoCont => {
+                WasmHeapBottomType::NoCont
+            }
         }
     }
 }
@@ -583,6 +624,10 @@ pub enum WasmHeapTopType {
     Any,
     /// The common supertype of all function references.
     Func,
+    /// The common supertype of all exception references.
+    Exn,
+    /// The common supertype of all continuation references.
+    Cont,
 }
 
 /// A bottom heap type.
@@ -594,6 +639,10 @@ pub enum WasmHeapBottomType {
     None,
     /// The common subtype of all function references.
     NoFunc,
+    /// The common subtype of all exception references.
+    NoExn,
+    /// The common subtype of all continuation references.
+    NoCont,
 }
 
 /// WebAssembly function type -- equivalent of `wasmparser`'s FuncType.
@@ -885,13 +934,66 @@ impl TypeTrace for WasmStructType {
     }
 }
 
-/// A function, array, or struct type.
+/// A concrete continuation type.
+///
+/// Continuation types are defined in terms of the function type of the
+/// continuation's computation: the function's parameters are the values
+/// needed to resume the continuation and its results are the values the
+/// continuation returns once it is finished.
+#[derive(Debug, Clone, Eq, PartialEq, Hash, Serialize, Deserialize)]
+pub struct WasmContType(EngineOrModuleTypeIndex);
+
+impl fmt::Display for WasmContType {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        write!(f, "(cont {})", self.0)
+    }
+}
+
+impl TypeTrace for WasmContType {
+    fn trace<F, E>(&self, func: &mut F) -> Result<(), E>
+    where
+        F: FnMut(EngineOrModuleTypeIndex) -> Result<(), E>,
+    {
+        func(self.0)
+    }
+
+    fn trace_mut<F, E>(&mut self, func: &mut F) -> Result<(), E>
+    where
+        F: FnMut(&mut EngineOrModuleTypeIndex) -> Result<(), E>,
+    {
+        func(&mut self.0)
+    }
+}
+
+impl WasmContType {
+    /// Create a new continuation type whose computation has the given
+    /// function type.
+    pub fn new(func_type: EngineOrModuleTypeIndex) -> Self {
+        WasmContType(func_type)
+    }
+
+    /// Get the index of this continuation's function type.
+    pub fn func_type(&self) -> EngineOrModuleTypeIndex {
+        self.0
+    }
+}
+
+/// The bit set in an encoded `resume` handler clause for `(on $tag switch)`
+/// clauses.
+///
+/// Handler clauses are passed to the `resume` builtins as an array of `u32`s
+/// where each entry is the clause's tag index, with this bit set if the
+/// clause handles `switch` rather than `suspend`.
+pub const RESUME_HANDLER_SWITCH: u32 = 1 << 31;
+
+/// A function, array, struct, or continuation type.
 #[derive(Debug, Clone, Eq, PartialEq, Hash, Serialize, Deserialize)]
 #[allow(missing_docs)]
 pub enum WasmCompositeType {
     Array(WasmArrayType),
     Func(WasmFuncType),
     Struct(WasmStructType),
+    Cont(WasmContType),
 }
 
 impl fmt::Display for WasmCompositeType {
@@ -900,6 +1002,7 @@ impl fmt::Display for WasmCompositeType {
             WasmCompositeType::Array(ty) => fmt::Display::fmt(ty, f),
             WasmCompositeType::Func(ty) => fmt::Display::fmt(ty, f),
             WasmCompositeType::Struct(ty) => fmt::Display::fmt(ty, f),
+            WasmCompositeType::Cont(ty) => fmt::Display::fmt(ty, f),
         }
     }
 }
@@ -959,6 +1062,24 @@ impl WasmCompositeType {
     pub fn unwrap_struct(&self) -> &WasmStructType {
         self.as_struct().unwrap()
     }
+
+    #[inline]
+    pub fn is_cont(&self) -> bool {
+        matches!(self, Self::Cont(_))
+    }
+
+    #[inline]
+    pub fn as_cont(&self) -> Option<&WasmContType> {
+        match self {
+            WasmCompositeType::Cont(f) => Some(f),
+            _ => None,
+        }
+    }
+
+    #[inline]
+    pub fn unwrap_cont(&self) -> &WasmContType {
+        self.as_cont().unwrap()
+    }
 }
 
 impl TypeTrace for WasmCompositeType {
@@ -970,6 +1091,7 @@ impl TypeTrace for WasmCompositeType {
             WasmCompositeType::Array(a) => a.trace(func),
             WasmCompositeType::Func(f) => f.trace(func),
             WasmCompositeType::Struct(a) => a.trace(func),
+            WasmCompositeType::Cont(c) => c.trace(func),
         }
     }
 
@@ -981,6 +1103,7 @@ impl TypeTrace for WasmCompositeType {
             WasmCompositeType::Array(a) => a.trace_mut(func),
             WasmCompositeType::Func(f) => f.trace_mut(func),
             WasmCompositeType::Struct(a) => a.trace_mut(func),
+            WasmCompositeType::Cont(c) => c.trace_mut(func),
         }
     }
 }
@@ -1062,6 +1185,21 @@ impl WasmSubType {
     pub fn unwrap_struct(&self) -> &WasmStructType {
         self.composite_type.unwrap_struct()
     }
+
+    #[inline]
+    pub fn is_cont(&self) -> bool {
+        self.composite_type.is_cont()
+    }
+
+    #[inline]
+    pub fn as_cont(&self) -> Option<&WasmContType> {
+        self.composite_type.as_cont()
+    }
+
+    #[inline]
+    pub fn unwrap_cont(&self) -> &WasmContType {
+        self.composite_type.unwrap_cont()
+    }
 }
 
 impl TypeTrace for WasmSubType {
@@ -1154,6 +1292,11 @@ entity_impl!(OwnedMemoryIndex);
 pub struct DefinedGlobalIndex(u32);
 entity_impl!(DefinedGlobalIndex);
 
+/// Index type of a defined tag inside the WebAssembly module.
+#[derive(Copy, Clone, PartialEq, Eq, Hash, PartialOrd, Ord, Debug, Serialize, Deserialize)]
+pub struct DefinedTagIndex(u32);
+entity_impl!(DefinedTagIndex);
+
 /// Index type of a table (imported or defined) inside the WebAssembly module.
 #[derive(Copy, Clone, PartialEq, Eq, Hash, PartialOrd, Ord, Debug, Serialize, Deserialize)]
 pub struct TableIndex(u32);
@@ -1278,6 +1421,8 @@ pub enum EntityIndex {
     Memory(MemoryIndex),
     /// Global index.
     Global(GlobalIndex),
+    /// Tag index.
+    Tag(TagIndex),
 }
 
 impl From<FuncIndex> for EntityIndex {
@@ -1304,6 +1449,12 @@ impl From<GlobalIndex> for EntityIndex {
     }
 }
 
+impl From<TagIndex> for EntityIndex {
+    fn from(idx: TagIndex) -> EntityIndex {
+        EntityIndex::Tag(idx)
+    }
+}
+
 /// A type of an item in a wasm module where an item is typically something that
 /// can be exported.
 #[allow(missing_docs)]
@@ -1331,7 +1482,8 @@ impl TypeTrace for EntityType {
             Self::Global(g) => g.trace(func),
             Self::Table(t) => t.trace(func),
             Self::Function(idx) => func(*idx),
-            Self::Memory(_) | Self::Tag(_) => Ok(()),
+            Self::Tag(t) => t.trace(func),
+            Self::Memory(_) => Ok(()),
         }
     }
 
@@ -1343,7 +1495,8 @@ impl TypeTrace for EntityType {
             Self::Global(g) => g.trace_mut(func),
             Self::Table(t) => t.trace_mut(func),
             Self::Function(idx) => func(idx),
-            Self::Memory(_) | Self::Tag(_) => Ok(()),
+            Self::Tag(t) => t.trace_mut(func),
+            Self::Memory(_) => Ok(()),
         }
     }
 }
@@ -1795,20 +1948,27 @@ impl From<wasmparser::MemoryType> for Memory {
     }
 }
 
-/// WebAssembly event.
+/// WebAssembly exception tag.
 #[derive(Debug, Clone, Copy, Hash, Eq, PartialEq, Serialize, Deserialize)]
 pub struct Tag {
-    /// The event signature type.
-    pub ty: TypeIndex,
+    /// The tag's signature: a function type whose parameters are the types of
+    /// the exception's payload and whose results are empty.
+    pub signature: EngineOrModuleTypeIndex,
 }
 
-impl From<wasmparser::TagType> for Tag {
-    fn from(ty: wasmparser::TagType) -> Tag {
-        match ty.kind {
-            wasmparser::TagKind::Exception => Tag {
-                ty: TypeIndex::from_u32(ty.func_type_idx),
-            },
-        }
+impl TypeTrace for Tag {
+    fn trace<F, E>(&self, func: &mut F) -> Result<(), E>
+    where
+        F: FnMut(EngineOrModuleTypeIndex) -> Result<(), E>,
+    {
+        func(self.signature)
+    }
+
+    fn trace_mut<F, E>(&mut self, func: &mut F) -> Result<(), E>
+    where
+        F: FnMut(&mut EngineOrModuleTypeIndex) -> Result<(), E>,
+    {
+        func(&mut self.signature)
     }
 }
 
@@ -1816,11 +1976,17 @@ impl From<wasmparser::TagType> for Tag {
 #[allow(missing_docs)]
 pub trait TypeConvert {
     /// Converts a wasmparser table type into a wasmtime type
-    fn convert_global_type(&self, ty: &wasmparser::GlobalType) -> Global {
-        Global {
-            wasm_ty: self.convert_valtype(ty.content_type),
-            mutability: ty.mutable,
+    fn convert_global_type(&self, ty: &wasmparser::GlobalType) -> WasmResult<Global> {
+        let wasm_ty = self.convert_valtype(ty.content_type);
+        if let WasmValType::Ref(r) = wasm_ty {
+            if r.heap_type.top() == WasmHeapTopType::Cont {
+                return Err(wasm_unsupported!("globals of continuation references"));
+            }
         }
+        Ok(Global {
+            wasm_ty,
+            mutability: ty.mutable,
+        })
     }
 
     /// Converts a wasmparser table type into a wasmtime type
@@ -1833,10 +1999,14 @@ pub trait TypeConvert {
             min: ty.initial.try_into().unwrap(),
             max: ty.maximum.map(|i| i.try_into().unwrap()),
         };
+        let ref_type = self.convert_ref_type(ty.element_type);
+        if ref_type.heap_type.top() == WasmHeapTopType::Cont {
+            return Err(wasm_unsupported!("tables of continuation references"));
+        }
         Ok(Table {
             idx_type,
             limits,
-            ref_type: self.convert_ref_type(ty.element_type),
+            ref_type,
         })
     }
 
@@ -1860,8 +2030,8 @@ pub trait TypeConvert {
             wasmparser::CompositeInnerType::Struct(s) => {
                 WasmCompositeType::Struct(self.convert_struct_type(s))
             }
-            wasmparser::CompositeInnerType::Cont(_) => {
-                unimplemented!("continuation types")
+            wasmparser::CompositeInnerType::Cont(c) => {
+                WasmCompositeType::Cont(self.convert_cont_type(c))
             }
         }
     }
@@ -1876,6 +2046,10 @@ pub trait TypeConvert {
         }
     }
 
+    fn convert_cont_type(&self, ty: &wasmparser::ContType) -> WasmContType {
+        WasmContType::new(self.lookup_type_index(ty.0.unpack()))
+    }
+
     fn convert_array_type(&self, ty: &wasmparser::ArrayType) -> WasmArrayType {
         WasmArrayType(self.convert_field_type(&ty.0))
     }
@@ -1945,13 +2119,10 @@ pub trait TypeConvert {
                 wasmparser::AbstractHeapType::Array => WasmHeapType::Array,
                 wasmparser::AbstractHeapType::Struct => WasmHeapType::Struct,
                 wasmparser::AbstractHeapType::None => WasmHeapType::None,
-
-                wasmparser::AbstractHeapType::Exn
-                | wasmparser::AbstractHeapType::NoExn
-                | wasmparser::AbstractHeapType::Cont
-                | wasmparser::AbstractHeapType::NoCont => {
-                    unimplemented!("unsupported heap type {ty:?}");
-                }
+                wasmparser::AbstractHeapType::Exn => WasmHeapType::Exn,
+                wasmparser::AbstractHeapType::NoExn => WasmHeapType::NoExn,
+                wasmparser::AbstractHeapType::Cont => WasmHeapType::Cont,
+                wasmparser::AbstractHeapType::NoCont => WasmHeapType::NoCont,
             },
             _ => unimplemented!("unsupported heap type {ty:?}"),
         }
@@ -123,6 +123,9 @@ fn build_and_generate_tests() {
 
 // Build the WASI Preview 1 adapter, and get the binary:
 fn build_adapter(out_dir: &PathBuf, name: &str, features: &[&str]) -> Vec<u8> {
+    if let Ok(dir) = std::env::var("PREBUILT_ADAPTERS") {
+        return fs::read(format!("{dir}/wasi_snapshot_preview1.{name}.wasm")).unwrap();
+    }
     println!("cargo:rerun-if-changed=../../wasi-preview1-component-adapter");
     let mut cmd = cargo();
     cmd.arg("build")
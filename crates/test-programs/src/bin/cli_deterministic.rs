@@ -0,0 +1,46 @@
+use std::time::{Duration, Instant, SystemTime};
+use test_programs::wasi::cli::{terminal_stderr, terminal_stdin, terminal_stdout};
+use test_programs::wasi::random;
+
+fn main() {
+    // Entries are listed in the order the host returns them.
+    let names = std::fs::read_dir(".")
+        .unwrap()
+        .map(|entry| entry.unwrap().file_name().into_string().unwrap())
+        .collect::<Vec<_>>();
+    println!("entries: {names:?}");
+
+    let metadata = std::fs::metadata("b.txt").unwrap();
+    for (name, time) in [
+        ("accessed", metadata.accessed()),
+        ("modified", metadata.modified()),
+    ] {
+        let time = time
+            .unwrap()
+            .duration_since(SystemTime::UNIX_EPOCH)
+            .unwrap();
+        println!("{name}: {time:?}");
+    }
+
+    let start = Instant::now();
+    let now = SystemTime::now()
+        .duration_since(SystemTime::UNIX_EPOCH)
+        .unwrap();
+    println!("wall clock: {now:?}");
+    std::thread::sleep(Duration::from_secs(1));
+    println!("slept: {:?}", start.elapsed());
+
+    println!("random: {:#x}", random::random::get_random_u64());
+    println!(
+        "insecure: {:#x}",
+        random::insecure::get_insecure_random_u64()
+    );
+    println!("seed: {:x?}", random::insecure_seed::insecure_seed());
+
+    println!(
+        "terminals: {} {} {}",
+        terminal_stdin::get_terminal_stdin().is_some(),
+        terminal_stdout::get_terminal_stdout().is_some(),
+        terminal_stderr::get_terminal_stderr().is_some(),
+    );
+}
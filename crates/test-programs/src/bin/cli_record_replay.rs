@@ -0,0 +1,37 @@
+use std::io::Read;
+use test_programs::wasi::clocks::{monotonic_clock, wall_clock};
+use test_programs::wasi::http::types::{Method, Scheme};
+use test_programs::wasi::random;
+
+fn main() {
+    let mut stdin = String::new();
+    std::io::stdin().read_to_string(&mut stdin).unwrap();
+    println!("stdin: {stdin:?}");
+
+    let now = wall_clock::now();
+    println!("wall clock: {}.{:09}", now.seconds, now.nanoseconds);
+    println!("monotonic clock: {}", monotonic_clock::now());
+    println!("random: {:#x}", random::random::get_random_u64());
+
+    let contents = std::fs::read_to_string("bar.txt").unwrap();
+    println!("bar.txt: {contents:?}");
+
+    let addr = std::env::var("HTTP_SERVER").unwrap();
+    let res = test_programs::http::request(
+        Method::Get,
+        Scheme::Http,
+        &addr,
+        "/",
+        None,
+        None,
+        None,
+        None,
+        None,
+    )
+    .unwrap();
+    println!(
+        "http: {} {:?}",
+        res.status,
+        String::from_utf8(res.body).unwrap()
+    );
+}
@@ -13,12 +13,17 @@ impl proxy::exports::wasi::http::incoming_handler::Guest for T {
         let scheme = request.scheme();
 
         assert_eq!(authority.as_deref(), Some("localhost"));
-        assert!(
-            matches!(scheme, Some(Scheme::Http)),
-            "bad scheme: {scheme:?}",
-        );
+        let scheme = match scheme {
+            Some(Scheme::Http) => "http",
+            Some(Scheme::Https) => "https",
+            _ => panic!("bad scheme: {scheme:?}"),
+        };
 
-        let resp = OutgoingResponse::new(Fields::new());
+        let headers = Fields::new();
+        headers
+            .set(&"scheme".to_string(), &[scheme.as_bytes().to_vec()])
+            .unwrap();
+        let resp = OutgoingResponse::new(headers);
         ResponseOutparam::set(outparam, Ok(resp));
     }
 }
@@ -0,0 +1,63 @@
+use test_programs::proxy;
+use test_programs::wasi::{
+    http::types::{Fields, IncomingRequest, OutgoingBody, OutgoingResponse, ResponseOutparam},
+    keyvalue::store::{self, Bucket},
+};
+
+mod bindings {
+    wit_bindgen::generate!({
+        path: "../wasi-keyvalue/wit",
+        world: "wasi:keyvalue/watch-service",
+        with: {
+            "wasi:keyvalue/store@0.2.0-draft2": test_programs::wasi::keyvalue::store,
+            "wasi:keyvalue/atomics@0.2.0-draft2": test_programs::wasi::keyvalue::atomics,
+            "wasi:keyvalue/batch@0.2.0-draft2": test_programs::wasi::keyvalue::batch,
+        },
+    });
+}
+
+struct T;
+
+proxy::export!(T);
+bindings::export!(T with_types_in bindings);
+
+// `POST` requests set `greeting` to the request's path, and other requests
+// respond with the value that the watcher last saw `greeting` set to.
+impl proxy::exports::wasi::http::incoming_handler::Guest for T {
+    fn handle(req: IncomingRequest, outparam: ResponseOutparam) {
+        let bucket = store::open("").unwrap();
+        let data = match req.method() {
+            test_programs::wasi::http::types::Method::Post => {
+                let path = req.path_with_query().unwrap();
+                bucket.set("greeting", path.as_bytes()).unwrap();
+                Vec::new()
+            }
+            _ => bucket.get("seen:greeting").unwrap().unwrap_or_default(),
+        };
+
+        let fields = Fields::new();
+        let resp = OutgoingResponse::new(fields);
+        let body = resp.body().expect("outgoing response");
+
+        ResponseOutparam::set(outparam, Ok(resp));
+
+        let out = body.write().expect("outgoing stream");
+        out.blocking_write_and_flush(&data)
+            .expect("writing response");
+
+        drop(out);
+        OutgoingBody::finish(body, None).expect("outgoing-body.finish");
+    }
+}
+
+impl bindings::exports::wasi::keyvalue::watcher::Guest for T {
+    fn on_set(bucket: Bucket, key: String, value: Vec<u8>) {
+        if key == "greeting" {
+            bucket.set("seen:greeting", &value).unwrap();
+        }
+    }
+
+    fn on_delete(_bucket: Bucket, _key: String) {}
+}
+
+fn main() {}
@@ -0,0 +1,23 @@
+use test_programs::wasi::keyvalue::{atomics, store};
+
+fn main() {
+    let bucket = store::open("").unwrap();
+
+    let cas = atomics::Cas::new(&bucket, "cas_key").unwrap();
+    assert_eq!(cas.current().unwrap(), None);
+    atomics::swap(cas, b"1").unwrap();
+    assert_eq!(bucket.get("cas_key").unwrap(), Some(b"1".to_vec()));
+
+    // A swap based on an outdated value fails and hands back an operation
+    // that's been updated to the latest value.
+    let stale = atomics::Cas::new(&bucket, "cas_key").unwrap();
+    assert_eq!(stale.current().unwrap(), Some(b"1".to_vec()));
+    bucket.set("cas_key", b"2").unwrap();
+    let retry = match atomics::swap(stale, b"3") {
+        Err(atomics::CasError::CasFailed(cas)) => cas,
+        other => panic!("unexpected swap result: {other:?}"),
+    };
+    assert_eq!(retry.current().unwrap(), Some(b"2".to_vec()));
+    atomics::swap(retry, b"3").unwrap();
+    assert_eq!(bucket.get("cas_key").unwrap(), Some(b"3".to_vec()));
+}
@@ -0,0 +1,35 @@
+use test_programs::wasi::keyvalue::store::Bucket;
+
+mod bindings {
+    wit_bindgen::generate!({
+        path: "../wasi-keyvalue/wit",
+        world: "wasi:keyvalue/watch-service",
+        with: {
+            "wasi:keyvalue/store@0.2.0-draft2": test_programs::wasi::keyvalue::store,
+            "wasi:keyvalue/atomics@0.2.0-draft2": test_programs::wasi::keyvalue::atomics,
+            "wasi:keyvalue/batch@0.2.0-draft2": test_programs::wasi::keyvalue::batch,
+        },
+    });
+}
+
+struct T;
+
+bindings::export!(T with_types_in bindings);
+
+// Records every change to a key `k` as `seen:k`, ignoring the changes made
+// by the recording itself.
+impl bindings::exports::wasi::keyvalue::watcher::Guest for T {
+    fn on_set(bucket: Bucket, key: String, value: Vec<u8>) {
+        if !key.starts_with("seen:") {
+            bucket.set(&format!("seen:{key}"), &value).unwrap();
+        }
+    }
+
+    fn on_delete(bucket: Bucket, key: String) {
+        if !key.starts_with("seen:") {
+            bucket.set(&format!("seen:{key}"), b"deleted").unwrap();
+        }
+    }
+}
+
+fn main() {}
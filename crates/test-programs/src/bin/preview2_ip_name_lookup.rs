@@ -6,8 +6,9 @@ fn main() {
     resolve("localhost").unwrap();
     resolve("example.com").unwrap();
 
-    // NB: this is an actual real resolution, so it might time out, might cause
-    // issues, etc. This result is ignored to prevent flaky failures in CI.
+    // NB: this may be an actual real resolution, so it might time out, might
+    // cause issues, etc. This result is ignored to prevent flaky failures in
+    // CI.
     let _ = resolve("münchen.de");
 
     // Valid IP addresses
@@ -11,7 +11,7 @@ wit_bindgen::generate!({
             include wasi:cli/imports@0.2.2;
             include wasi:http/imports@0.2.2;
             include wasi:config/imports@0.2.0-draft;
-            include wasi:keyvalue/imports@0.2.0-draft;
+            include wasi:keyvalue/imports@0.2.0-draft2;
         }
     ",
     path: [
@@ -0,0 +1,151 @@
+use crate::{ConfigProvider, Error};
+use anyhow::{bail, Context, Result};
+use std::collections::HashMap;
+use std::fs;
+use std::path::{Path, PathBuf};
+use std::sync::Mutex;
+use std::time::SystemTime;
+
+/// A [`ConfigProvider`] for the variables in a TOML or JSON file.
+///
+/// The format is chosen by the extension of the file: `.json` files are read
+/// as JSON and all others as TOML. The top level of the file must be a table
+/// (or object) and nested tables are flattened by joining keys with dots, so
+///
+/// ```toml
+/// log_level = "info"
+///
+/// [database]
+/// url = "postgres://localhost"
+/// pool_size = 4
+/// ```
+///
+/// provides the variables `log_level`, `database.url` and `database.pool_size`.
+/// Strings are provided as they are; other values, including arrays, are
+/// provided in their TOML or JSON syntax.
+///
+/// The file is reloaded whenever its modification time or size changes, which
+/// is checked on every call. If a changed file can't be read or parsed, the
+/// variables it last contained continue to be provided until it can.
+pub struct FileProvider {
+    path: PathBuf,
+    state: Mutex<FileState>,
+}
+
+struct FileState {
+    /// The modification time and size of the file when it was last loaded.
+    version: (Option<SystemTime>, u64),
+    vars: HashMap<String, String>,
+}
+
+impl FileProvider {
+    /// Loads the variables in the file at `path`.
+    pub fn open(path: impl Into<PathBuf>) -> Result<Self> {
+        let path = path.into();
+        let version = version(&path)?;
+        let vars = load(&path)?;
+        Ok(Self {
+            path,
+            state: Mutex::new(FileState { version, vars }),
+        })
+    }
+
+    /// Reloads the file if it changed since it was last loaded.
+    fn refresh(&self) -> std::sync::MutexGuard<'_, FileState> {
+        let mut state = self.state.lock().unwrap();
+        match version(&self.path) {
+            Ok(version) if version == state.version => {}
+            Ok(version) => match load(&self.path) {
+                Ok(vars) => *state = FileState { version, vars },
+                Err(e) => tracing::warn!("failed to reload config file: {e:?}"),
+            },
+            Err(e) => tracing::warn!("failed to reload config file: {e:?}"),
+        }
+        state
+    }
+}
+
+impl ConfigProvider for FileProvider {
+    fn get(&self, key: &str) -> Result<Option<String>, Error> {
+        Ok(self.refresh().vars.get(key).cloned())
+    }
+
+    fn get_all(&self) -> Result<Vec<(String, String)>, Error> {
+        Ok(self
+            .refresh()
+            .vars
+            .iter()
+            .map(|(k, v)| (k.clone(), v.clone()))
+            .collect())
+    }
+}
+
+fn version(path: &Path) -> Result<(Option<SystemTime>, u64)> {
+    let metadata =
+        fs::metadata(path).with_context(|| format!("failed to read `{}`", path.display()))?;
+    Ok((metadata.modified().ok(), metadata.len()))
+}
+
+fn load(path: &Path) -> Result<HashMap<String, String>> {
+    let contents =
+        fs::read_to_string(path).with_context(|| format!("failed to read `{}`", path.display()))?;
+    let mut vars = HashMap::new();
+    if path.extension().is_some_and(|ext| ext == "json") {
+        let value: serde_json::Value = serde_json::from_str(&contents)
+            .with_context(|| format!("failed to parse `{}`", path.display()))?;
+        let serde_json::Value::Object(object) = value else {
+            bail!("`{}` does not contain a JSON object", path.display());
+        };
+        flatten_json(String::new(), object, &mut vars);
+    } else {
+        let table: toml::Table = contents
+            .parse()
+            .with_context(|| format!("failed to parse `{}`", path.display()))?;
+        flatten_toml(String::new(), table, &mut vars);
+    }
+    Ok(vars)
+}
+
+/// Joins `key` onto the dotted path `prefix` of a nested table.
+fn join(prefix: &str, key: String) -> String {
+    if prefix.is_empty() {
+        key
+    } else {
+        format!("{prefix}.{key}")
+    }
+}
+
+fn flatten_toml(prefix: String, table: toml::Table, vars: &mut HashMap<String, String>) {
+    for (key, value) in table {
+        let key = join(&prefix, key);
+        match value {
+            toml::Value::Table(table) => flatten_toml(key, table, vars),
+            toml::Value::String(s) => {
+                vars.insert(key, s);
+            }
+            value => {
+                vars.insert(key, value.to_string());
+            }
+        }
+    }
+}
+
+fn flatten_json(
+    prefix: String,
+    object: serde_json::Map<String, serde_json::Value>,
+    vars: &mut HashMap<String, String>,
+) {
+    for (key, value) in object {
+        let key = join(&prefix, key);
+        match value {
+            serde_json::Value::Object(object) => flatten_json(key, object, vars),
+            serde_json::Value::String(s) => {
+                vars.insert(key, s);
+            }
+            serde_json::Value::Null => {}
+            value => {
+                vars.insert(key, value.to_string());
+            }
+        }
+    }
+}
@@ -59,6 +59,12 @@
 //! }
 //! ```
 //!
+//! Variables can also be read at call time from a [`ConfigProvider`], such as
+//! an [`EnvProvider`] for the host's environment, a [`FileProvider`] which
+//! reloads a TOML or JSON file when it changes, or a [`LayeredProvider`]
+//! combining several of them; pass a provider to [`WasiConfig::new`] in place of
+//! the [`WasiConfigVariables`].
+//!
 //! [wasi-config]: https://github.com/WebAssembly/wasi-config
 //! [wasi:cli]: https://docs.rs/wasmtime-wasi/latest
 //! [wasi:http]: https://docs.rs/wasmtime-wasi-http/latest
@@ -77,6 +83,13 @@ mod gen_ {
 }
 use self::gen_::wasi::config::store as generated;
 
+mod file;
+mod provider;
+
+pub use self::file::FileProvider;
+pub use self::generated::Error;
+pub use self::provider::{ConfigProvider, EnvProvider, LayeredProvider};
+
 /// Capture the state necessary for use in the `wasi-config` API implementation.
 #[derive(Default)]
 pub struct WasiConfigVariables(HashMap<String, String>);
@@ -106,7 +119,7 @@ impl WasiConfigVariables {
 
 /// A wrapper capturing the needed internal `wasi-config` state.
 pub struct WasiConfig<'a> {
-    vars: &'a WasiConfigVariables,
+    vars: &'a dyn ConfigProvider,
 }
 
 impl<'a> From<&'a WasiConfigVariables> for WasiConfig<'a> {
@@ -115,25 +128,27 @@ impl<'a> From<&'a WasiConfigVariables> for WasiConfig<'a> {
     }
 }
 
+impl<'a> From<&'a dyn ConfigProvider> for WasiConfig<'a> {
+    fn from(vars: &'a dyn ConfigProvider) -> Self {
+        Self { vars }
+    }
+}
+
 impl<'a> WasiConfig<'a> {
-    /// Create a new view into the `wasi-config` state.
-    pub fn new(vars: &'a WasiConfigVariables) -> Self {
+    /// Create a new view into the `wasi-config` state, which is provided by
+    /// `vars`.
+    pub fn new(vars: &'a dyn ConfigProvider) -> Self {
         Self { vars }
     }
 }
 
 impl generated::Host for WasiConfig<'_> {
     fn get(&mut self, key: String) -> Result<Result<Option<String>, generated::Error>> {
-        Ok(Ok(self.vars.0.get(&key).map(|s| s.to_owned())))
+        Ok(self.vars.get(&key))
     }
 
     fn get_all(&mut self) -> Result<Result<Vec<(String, String)>, generated::Error>> {
-        Ok(Ok(self
-            .vars
-            .0
-            .iter()
-            .map(|(k, v)| (k.to_string(), v.to_string()))
-            .collect()))
+        Ok(self.vars.get_all())
     }
 }
 
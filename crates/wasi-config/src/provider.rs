@@ -0,0 +1,126 @@
+use crate::{Error, WasiConfigVariables};
+use std::collections::HashMap;
+use std::sync::Arc;
+
+/// A source of configuration variables for `wasi:config`.
+///
+/// Providers are queried every time a guest calls `get` or `get-all`, so they
+/// may change the values they return over the lifetime of a store. The same
+/// provider may be shared by the stores of many guests through an [`Arc`].
+pub trait ConfigProvider: Send + Sync {
+    /// Returns the value of the variable `key`, if it's set.
+    fn get(&self, key: &str) -> Result<Option<String>, Error>;
+
+    /// Returns all variables that are set.
+    fn get_all(&self) -> Result<Vec<(String, String)>, Error>;
+}
+
+impl<P: ConfigProvider + ?Sized> ConfigProvider for Arc<P> {
+    fn get(&self, key: &str) -> Result<Option<String>, Error> {
+        (**self).get(key)
+    }
+
+    fn get_all(&self) -> Result<Vec<(String, String)>, Error> {
+        (**self).get_all()
+    }
+}
+
+impl<P: ConfigProvider + ?Sized> ConfigProvider for Box<P> {
+    fn get(&self, key: &str) -> Result<Option<String>, Error> {
+        (**self).get(key)
+    }
+
+    fn get_all(&self) -> Result<Vec<(String, String)>, Error> {
+        (**self).get_all()
+    }
+}
+
+impl ConfigProvider for WasiConfigVariables {
+    fn get(&self, key: &str) -> Result<Option<String>, Error> {
+        Ok(self.0.get(key).cloned())
+    }
+
+    fn get_all(&self) -> Result<Vec<(String, String)>, Error> {
+        Ok(self
+            .0
+            .iter()
+            .map(|(k, v)| (k.to_string(), v.to_string()))
+            .collect())
+    }
+}
+
+/// A [`ConfigProvider`] for the host's environment variables whose names start
+/// with a prefix.
+///
+/// Variables are named without the prefix, so with the prefix `APP_` the
+/// environment variable `APP_DATABASE_URL` is provided as `DATABASE_URL`. The
+/// environment is read on every call. Variables whose name or value isn't
+/// valid Unicode are ignored.
+#[derive(Clone, Debug)]
+pub struct EnvProvider {
+    prefix: String,
+}
+
+impl EnvProvider {
+    /// Creates a provider for the environment variables starting with
+    /// `prefix`.
+    pub fn new(prefix: impl Into<String>) -> Self {
+        Self {
+            prefix: prefix.into(),
+        }
+    }
+}
+
+impl ConfigProvider for EnvProvider {
+    fn get(&self, key: &str) -> Result<Option<String>, Error> {
+        Ok(std::env::var(format!("{}{key}", self.prefix)).ok())
+    }
+
+    fn get_all(&self) -> Result<Vec<(String, String)>, Error> {
+        Ok(std::env::vars()
+            .filter_map(|(k, v)| Some((k.strip_prefix(&self.prefix)?.to_string(), v)))
+            .collect())
+    }
+}
+
+/// A [`ConfigProvider`] which combines several others.
+///
+/// Layers are consulted in the order they were added and the first one to
+/// provide a variable determines its value, so earlier layers override later
+/// ones. An error from any layer consulted is returned to the guest.
+#[derive(Default)]
+pub struct LayeredProvider {
+    layers: Vec<Box<dyn ConfigProvider>>,
+}
+
+impl LayeredProvider {
+    /// Creates a provider with no layers, which provides no variables.
+    pub fn new() -> Self {
+        Self::default()
+    }
+
+    /// Adds `layer` below all the layers added so far.
+    pub fn layer(mut self, layer: impl ConfigProvider + 'static) -> Self {
+        self.layers.push(Box::new(layer));
+        self
+    }
+}
+
+impl ConfigProvider for LayeredProvider {
+    fn get(&self, key: &str) -> Result<Option<String>, Error> {
+        for layer in &self.layers {
+            if let Some(value) = layer.get(key)? {
+                return Ok(Some(value));
+            }
+        }
+        Ok(None)
+    }
+
+    fn get_all(&self) -> Result<Vec<(String, String)>, Error> {
+        let mut all = HashMap::new();
+        for layer in self.layers.iter().rev() {
+            all.extend(layer.get_all()?);
+        }
+        Ok(all.into_iter().collect())
+    }
+}
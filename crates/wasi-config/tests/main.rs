@@ -5,12 +5,14 @@ use wasmtime::{
     Store,
 };
 use wasmtime_wasi::{add_to_linker_async, bindings::Command, WasiCtx, WasiCtxBuilder, WasiView};
-use wasmtime_wasi_config::{WasiConfig, WasiConfigVariables};
+use wasmtime_wasi_config::{
+    ConfigProvider, EnvProvider, FileProvider, LayeredProvider, WasiConfig, WasiConfigVariables,
+};
 
 struct Ctx {
     table: ResourceTable,
     wasi_ctx: WasiCtx,
-    wasi_config_vars: WasiConfigVariables,
+    wasi_config_vars: Box<dyn ConfigProvider>,
 }
 
 impl WasiView for Ctx {
@@ -33,7 +35,7 @@ async fn run_wasi(path: &str, ctx: Ctx) -> Result<()> {
     let mut linker = Linker::new(&engine);
     add_to_linker_async(&mut linker)?;
     wasmtime_wasi_config::add_to_linker(&mut linker, |h: &mut Ctx| {
-        WasiConfig::from(&h.wasi_config_vars)
+        WasiConfig::new(&*h.wasi_config_vars)
     })?;
 
     let command = Command::instantiate_async(&mut store, &component, &linker).await?;
@@ -60,8 +62,97 @@ async fn config_get() -> Result<()> {
         Ctx {
             table: ResourceTable::new(),
             wasi_ctx: WasiCtxBuilder::new().build(),
-            wasi_config_vars: WasiConfigVariables::from_iter(vec![("hello", "world")]),
+            wasi_config_vars: Box::new(WasiConfigVariables::from_iter(vec![("hello", "world")])),
         },
     )
     .await
 }
+
+#[tokio::test(flavor = "multi_thread")]
+async fn config_get_from_file() -> Result<()> {
+    let dir = tempfile::tempdir()?;
+    let path = dir.path().join("config.toml");
+    std::fs::write(&path, "hello = \"world\"\n")?;
+    run_wasi(
+        CONFIG_GET_COMPONENT,
+        Ctx {
+            table: ResourceTable::new(),
+            wasi_ctx: WasiCtxBuilder::new().build(),
+            wasi_config_vars: Box::new(FileProvider::open(&path)?),
+        },
+    )
+    .await
+}
+
+#[test]
+fn file_provider() -> Result<()> {
+    let dir = tempfile::tempdir()?;
+
+    let path = dir.path().join("config.toml");
+    std::fs::write(
+        &path,
+        "log_level = \"info\"\n[database]\nurl = \"postgres://localhost\"\npool_size = 4\n",
+    )?;
+    let provider = FileProvider::open(&path)?;
+    assert_eq!(provider.get("log_level")?.as_deref(), Some("info"));
+    assert_eq!(
+        provider.get("database.url")?.as_deref(),
+        Some("postgres://localhost")
+    );
+    assert_eq!(provider.get("database.pool_size")?.as_deref(), Some("4"));
+    assert_eq!(provider.get("database")?, None);
+    assert_eq!(provider.get_all()?.len(), 3);
+
+    // Changes to the file are picked up by the next call.
+    std::fs::write(&path, "log_level = \"debug\"\n")?;
+    assert_eq!(provider.get("log_level")?.as_deref(), Some("debug"));
+    assert_eq!(provider.get("database.url")?, None);
+
+    // A file that fails to parse leaves the previous variables in place.
+    std::fs::write(&path, "log_level = ")?;
+    assert_eq!(provider.get("log_level")?.as_deref(), Some("debug"));
+
+    let path = dir.path().join("config.json");
+    std::fs::write(
+        &path,
+        r#"{"a": {"b": "c", "d": [1, 2]}, "e": true, "f": null}"#,
+    )?;
+    let provider = FileProvider::open(&path)?;
+    let mut all = provider.get_all()?;
+    all.sort();
+    assert_eq!(
+        all,
+        [
+            ("a.b".to_string(), "c".to_string()),
+            ("a.d".to_string(), "[1,2]".to_string()),
+            ("e".to_string(), "true".to_string()),
+        ]
+    );
+
+    std::fs::write(&path, "[]")?;
+    assert!(FileProvider::open(&path).is_err());
+    assert!(FileProvider::open(dir.path().join("missing.toml")).is_err());
+    Ok(())
+}
+
+#[test]
+fn layered_provider() -> Result<()> {
+    std::env::set_var("WASI_CONFIG_TEST_hello", "env");
+    std::env::set_var("WASI_CONFIG_TEST_only_env", "env");
+    let provider = LayeredProvider::new()
+        .layer(WasiConfigVariables::from_iter(vec![("hello", "vars")]))
+        .layer(EnvProvider::new("WASI_CONFIG_TEST_"));
+    assert_eq!(provider.get("hello")?.as_deref(), Some("vars"));
+    assert_eq!(provider.get("only_env")?.as_deref(), Some("env"));
+    assert_eq!(provider.get("missing")?, None);
+    let mut all = provider.get_all()?;
+    all.sort();
+    assert_eq!(
+        all,
+        [
+            ("hello".to_string(), "vars".to_string()),
+            ("only_env".to_string(), "env".to_string()),
+        ]
+    );
+    Ok(())
+}
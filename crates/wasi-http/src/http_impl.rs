@@ -89,10 +89,15 @@ where
                 .boxed()
         });
 
-        let request = builder
+        let mut request = builder
             .body(body)
             .map_err(|err| internal_error(err.to_string()))?;
 
+        let policy = self.ctx().outgoing_policy().clone();
+        policy.apply(&mut request, use_tls)?;
+
+        let pool = self.ctx().connection_pool().cloned();
+        let tls = self.ctx().tls_config().clone();
         let future = self.send_request(
             request,
             OutgoingRequestConfig {
@@ -100,6 +105,9 @@ where
                 connect_timeout,
                 first_byte_timeout,
                 between_bytes_timeout,
+                pool,
+                tls,
+                policy,
             },
         )?;
 
@@ -0,0 +1,432 @@
+//! Restrictions on the outgoing requests that guests may make.
+
+use crate::bindings::http::types::ErrorCode;
+use crate::body::HyperOutgoingBody;
+use anyhow::{bail, Context, Result};
+use bytes::Bytes;
+use http_body::{Body, Frame, SizeHint};
+use http_body_util::BodyExt;
+use hyper::header::{HeaderName, HeaderValue};
+use std::net::{IpAddr, SocketAddr};
+use std::pin::Pin;
+use std::sync::Arc;
+use std::task::{Context as TaskContext, Poll};
+use tokio::io::{AsyncReadExt, AsyncWriteExt};
+use tokio::net::TcpStream;
+
+/// A policy for the outgoing requests of guests.
+///
+/// The default policy allows every request and changes nothing about it.
+/// Otherwise requests are checked against the policy's rules before a
+/// connection is made, and those which aren't allowed fail with
+/// `error-code.HTTP-request-denied`. A request is allowed if it matches none
+/// of the rules added with [`OutgoingPolicyBuilder::deny`] and, if any were
+/// added with [`OutgoingPolicyBuilder::allow`], at least one of those.
+///
+/// Rules are one of:
+///
+/// * A host name, such as `example.com`, or `*.example.com` for all of its
+///   subdomains, or `*` for all hosts.
+/// * An IP address, such as `10.0.0.1`, or a range of them in CIDR notation,
+///   such as `10.0.0.0/8`.
+///
+/// Host names and single IP addresses may be followed by a port, such as
+/// `example.com:443` or `[::1]:8080`, to only match requests to that port.
+///
+/// Address rules are checked against both the IP addresses that requests are
+/// made to directly and the addresses that host names resolve to, and
+/// connections are only made to resolved addresses that are allowed. That
+/// resolution is done by [`default_send_request`](crate::types::default_send_request),
+/// so implementations of
+/// [`WasiHttpView::send_request`](crate::WasiHttpView::send_request) which
+/// don't defer to it must check addresses with
+/// [`OutgoingPolicy::is_addr_allowed`] themselves. Host names aren't resolved
+/// when requests go through a [proxy](OutgoingPolicyBuilder::proxy), in which
+/// case address rules only apply to IP addresses used directly.
+///
+/// Cloning a policy is cheap.
+#[derive(Clone, Debug, Default)]
+pub struct OutgoingPolicy {
+    inner: Arc<Inner>,
+}
+
+#[derive(Clone, Debug, Default)]
+struct Inner {
+    allow: Vec<Rule>,
+    deny: Vec<Rule>,
+    https_only: bool,
+    headers: Vec<(HeaderName, HeaderValue)>,
+    max_request_body_size: Option<u64>,
+    proxy: Option<String>,
+}
+
+#[derive(Clone, Debug)]
+enum Rule {
+    Host {
+        pattern: String,
+        port: Option<u16>,
+    },
+    Addr {
+        net: IpAddr,
+        prefix: u8,
+        port: Option<u16>,
+    },
+}
+
+impl Rule {
+    fn parse(rule: &str) -> Result<Rule> {
+        let invalid = || format!("invalid host rule `{rule}`");
+        if let Some((addr, prefix)) = rule.split_once('/') {
+            let net = addr.parse::<IpAddr>().with_context(invalid)?;
+            let prefix = prefix.parse::<u8>().with_context(invalid)?;
+            if prefix > max_prefix(net) {
+                bail!("{}: prefix is too long", invalid());
+            }
+            return Ok(Rule::Addr {
+                net,
+                prefix,
+                port: None,
+            });
+        }
+        if let Ok(net) = rule.parse::<IpAddr>() {
+            return Ok(Rule::Addr {
+                net,
+                prefix: max_prefix(net),
+                port: None,
+            });
+        }
+        if let Ok(addr) = rule.parse::<SocketAddr>() {
+            return Ok(Rule::Addr {
+                net: addr.ip(),
+                prefix: max_prefix(addr.ip()),
+                port: Some(addr.port()),
+            });
+        }
+        let (host, port) = match rule.rsplit_once(':') {
+            Some((host, port)) => (host, Some(port.parse::<u16>().with_context(invalid)?)),
+            None => (rule, None),
+        };
+        let name = host.strip_prefix("*.").unwrap_or(host);
+        if host.is_empty()
+            || (host != "*"
+                && !name
+                    .chars()
+                    .all(|c| c.is_ascii_alphanumeric() || c == '-' || c == '.'))
+        {
+            bail!("{}: not a host name or IP address", invalid());
+        }
+        Ok(Rule::Host {
+            pattern: normalize_host(host),
+            port,
+        })
+    }
+
+    /// Returns whether this rule matches requests to `host` on `port`,
+    /// without resolving `host`.
+    fn matches_host(&self, host: &str, port: u16) -> bool {
+        match self {
+            Rule::Host {
+                pattern,
+                port: rule_port,
+            } => {
+                if rule_port.is_some_and(|p| p != port) {
+                    return false;
+                }
+                match pattern.strip_prefix("*") {
+                    Some("") => true,
+                    Some(suffix) => host.ends_with(suffix),
+                    None => host == pattern,
+                }
+            }
+            Rule::Addr { .. } => match host.parse::<IpAddr>() {
+                Ok(addr) => self.matches_addr(SocketAddr::new(addr, port)),
+                Err(_) => false,
+            },
+        }
+    }
+
+    fn matches_addr(&self, addr: SocketAddr) -> bool {
+        let Rule::Addr { net, prefix, port } = self else {
+            return false;
+        };
+        if port.is_some_and(|p| p != addr.port()) {
+            return false;
+        }
+        match (net, addr.ip().to_canonical()) {
+            (IpAddr::V4(net), IpAddr::V4(addr)) => {
+                let mask = u32::MAX.checked_shl(32 - u32::from(*prefix)).unwrap_or(0);
+                u32::from(*net) & mask == u32::from(addr) & mask
+            }
+            (IpAddr::V6(net), IpAddr::V6(addr)) => {
+                let mask = u128::MAX.checked_shl(128 - u32::from(*prefix)).unwrap_or(0);
+                u128::from(*net) & mask == u128::from(addr) & mask
+            }
+            _ => false,
+        }
+    }
+}
+
+fn max_prefix(addr: IpAddr) -> u8 {
+    match addr {
+        IpAddr::V4(_) => 32,
+        IpAddr::V6(_) => 128,
+    }
+}
+
+fn normalize_host(host: &str) -> String {
+    host.trim_start_matches('[')
+        .trim_end_matches(']')
+        .trim_end_matches('.')
+        .to_ascii_lowercase()
+}
+
+/// Whether a request may be made according to its host name alone.
+#[derive(Clone, Copy, Debug, PartialEq)]
+pub(crate) enum HostDecision {
+    Allowed,
+    Denied,
+    /// The request is only allowed if the host resolves to allowed addresses.
+    DependsOnAddr,
+}
+
+impl OutgoingPolicy {
+    /// Returns a builder for a policy.
+    pub fn builder() -> OutgoingPolicyBuilder {
+        OutgoingPolicyBuilder {
+            inner: Inner::default(),
+        }
+    }
+
+    /// Returns the HTTP proxy that requests are tunneled through, if any.
+    pub fn proxy(&self) -> Option<&str> {
+        self.inner.proxy.as_deref()
+    }
+
+    pub(crate) fn check_host(&self, host: &str, port: u16) -> HostDecision {
+        let host = normalize_host(host);
+        if self.inner.deny.iter().any(|r| r.matches_host(&host, port)) {
+            return HostDecision::Denied;
+        }
+        if self.inner.allow.is_empty()
+            || self.inner.allow.iter().any(|r| r.matches_host(&host, port))
+        {
+            return HostDecision::Allowed;
+        }
+        let has_addr_rules = self
+            .inner
+            .allow
+            .iter()
+            .any(|r| matches!(r, Rule::Addr { .. }));
+        if has_addr_rules && host.parse::<IpAddr>().is_err() {
+            HostDecision::DependsOnAddr
+        } else {
+            HostDecision::Denied
+        }
+    }
+
+    /// Returns whether a connection may be made to `addr` for a request to
+    /// `host`, which resolved to it.
+    pub fn is_addr_allowed(&self, host: &str, addr: SocketAddr) -> bool {
+        let host_decision = self.check_host(host, addr.port());
+        host_decision != HostDecision::Denied
+            && !self.inner.deny.iter().any(|r| r.matches_addr(addr))
+            && (host_decision == HostDecision::Allowed
+                || self.inner.allow.iter().any(|r| r.matches_addr(addr)))
+    }
+
+    /// Checks `request` against this policy and applies the changes it makes
+    /// to requests, such as adding headers.
+    ///
+    /// This is done for every request that guests make before it's passed to
+    /// [`WasiHttpView::send_request`](crate::WasiHttpView::send_request).
+    pub fn apply(
+        &self,
+        request: &mut hyper::Request<HyperOutgoingBody>,
+        use_tls: bool,
+    ) -> Result<(), ErrorCode> {
+        let inner = &self.inner;
+        if inner.https_only && !use_tls {
+            return Err(ErrorCode::HttpRequestDenied);
+        }
+        let uri = request.uri();
+        let host = uri.host().ok_or(ErrorCode::HttpRequestUriInvalid)?;
+        let port = uri.port_u16().unwrap_or(if use_tls { 443 } else { 80 });
+        match self.check_host(host, port) {
+            HostDecision::Allowed => {}
+            HostDecision::Denied => return Err(ErrorCode::HttpRequestDenied),
+            HostDecision::DependsOnAddr if inner.proxy.is_some() => {
+                return Err(ErrorCode::HttpRequestDenied)
+            }
+            HostDecision::DependsOnAddr => {}
+        }
+
+        for (name, value) in &inner.headers {
+            request.headers_mut().insert(name.clone(), value.clone());
+        }
+
+        if let Some(max) = inner.max_request_body_size {
+            let content_length = request
+                .headers()
+                .get(hyper::header::CONTENT_LENGTH)
+                .and_then(|v| v.to_str().ok())
+                .and_then(|v| v.parse::<u64>().ok());
+            if let Some(len) = content_length.filter(|len| *len > max) {
+                return Err(ErrorCode::HttpRequestBodySize(Some(len)));
+            }
+            let body = std::mem::replace(
+                request.body_mut(),
+                http_body_util::Empty::new()
+                    .map_err(|never| match never {})
+                    .boxed(),
+            );
+            *request.body_mut() = LimitedBody {
+                body,
+                remaining: max,
+            }
+            .boxed();
+        }
+        Ok(())
+    }
+}
+
+/// A builder for an [`OutgoingPolicy`].
+#[derive(Clone)]
+pub struct OutgoingPolicyBuilder {
+    inner: Inner,
+}
+
+impl OutgoingPolicyBuilder {
+    /// Only allows requests which match `rule`, or any other rule added with
+    /// this method.
+    ///
+    /// See [`OutgoingPolicy`] for the syntax of rules.
+    pub fn allow(&mut self, rule: &str) -> Result<&mut Self> {
+        self.inner.allow.push(Rule::parse(rule)?);
+        Ok(self)
+    }
+
+    /// Denies requests which match `rule`.
+    ///
+    /// See [`OutgoingPolicy`] for the syntax of rules.
+    pub fn deny(&mut self, rule: &str) -> Result<&mut Self> {
+        self.inner.deny.push(Rule::parse(rule)?);
+        Ok(self)
+    }
+
+    /// Sets whether to deny requests which don't use HTTPS.
+    pub fn https_only(&mut self, enable: bool) -> &mut Self {
+        self.inner.https_only = enable;
+        self
+    }
+
+    /// Sets the header `name` to `value` on every request, replacing any value
+    /// that the guest gave it.
+    pub fn header(&mut self, name: &str, value: &str) -> Result<&mut Self> {
+        let name = HeaderName::from_bytes(name.as_bytes())
+            .with_context(|| format!("invalid header name `{name}`"))?;
+        let value = HeaderValue::from_str(value)
+            .with_context(|| format!("invalid value for header `{name}`"))?;
+        self.inner.headers.push((name, value));
+        Ok(self)
+    }
+
+    /// Fails requests whose bodies are larger than `size` bytes with
+    /// `error-code.HTTP-request-body-size`.
+    pub fn max_request_body_size(&mut self, size: u64) -> &mut Self {
+        self.inner.max_request_body_size = Some(size);
+        self
+    }
+
+    /// Tunnels all requests through the HTTP proxy at `authority`, which is a
+    /// host and port such as `proxy.internal:3128`, using `CONNECT` requests.
+    pub fn proxy(&mut self, authority: &str) -> Result<&mut Self> {
+        let uri = authority
+            .parse::<http::uri::Authority>()
+            .with_context(|| format!("invalid proxy `{authority}`"))?;
+        if uri.port().is_none() {
+            bail!("proxy `{authority}` has no port");
+        }
+        self.inner.proxy = Some(authority.to_string());
+        Ok(self)
+    }
+
+    /// Builds the policy.
+    pub fn build(&self) -> OutgoingPolicy {
+        OutgoingPolicy {
+            inner: Arc::new(self.inner.clone()),
+        }
+    }
+}
+
+/// A request body which fails once more than `remaining` bytes are read from
+/// it.
+struct LimitedBody {
+    body: HyperOutgoingBody,
+    remaining: u64,
+}
+
+impl Body for LimitedBody {
+    type Data = Bytes;
+    type Error = ErrorCode;
+
+    fn poll_frame(
+        mut self: Pin<&mut Self>,
+        cx: &mut TaskContext<'_>,
+    ) -> Poll<Option<Result<Frame<Bytes>, ErrorCode>>> {
+        let frame = match Pin::new(&mut self.body).poll_frame(cx) {
+            Poll::Ready(Some(Ok(frame))) => frame,
+            other => return other,
+        };
+        if let Some(data) = frame.data_ref() {
+            let len = data.len() as u64;
+            match self.remaining.checked_sub(len) {
+                Some(remaining) => self.remaining = remaining,
+                None => return Poll::Ready(Some(Err(ErrorCode::HttpRequestBodySize(None)))),
+            }
+        }
+        Poll::Ready(Some(Ok(frame)))
+    }
+
+    fn is_end_stream(&self) -> bool {
+        self.body.is_end_stream()
+    }
+
+    fn size_hint(&self) -> SizeHint {
+        self.body.size_hint()
+    }
+}
+
+/// Opens a tunnel to `authority` through the HTTP proxy that `stream` is
+/// connected to.
+pub(crate) async fn tunnel(mut stream: TcpStream, authority: &str) -> Result<TcpStream, ErrorCode> {
+    let request = format!("CONNECT {authority} HTTP/1.1\r\nHost: {authority}\r\n\r\n");
+    stream
+        .write_all(request.as_bytes())
+        .await
+        .map_err(|_| ErrorCode::ConnectionTerminated)?;
+
+    // Read the response a byte at a time so as to not consume anything that
+    // the server sends through the tunnel.
+    let mut response = Vec::new();
+    while !response.ends_with(b"\r\n\r\n") {
+        if response.len() >= 8192 {
+            return Err(ErrorCode::HttpProtocolError);
+        }
+        let byte = stream
+            .read_u8()
+            .await
+            .map_err(|_| ErrorCode::ConnectionTerminated)?;
+        response.push(byte);
+    }
+    let status = std::str::from_utf8(&response)
+        .ok()
+        .and_then(|response| response.strip_prefix("HTTP/1."))
+        .and_then(|response| response.get(2..5))
+        .and_then(|status| status.parse::<u16>().ok())
+        .ok_or(ErrorCode::HttpProtocolError)?;
+    if !(200..300).contains(&status) {
+        tracing::warn!("proxy refused to connect to {authority} with status {status}");
+        return Err(ErrorCode::ConnectionRefused);
+    }
+    Ok(stream)
+}
@@ -0,0 +1,212 @@
+//! Reuse of connections between outgoing requests.
+
+use crate::body::HyperOutgoingBody;
+use crate::policy::OutgoingPolicy;
+use crate::tls::TlsConfig;
+use hyper::client::conn::{http1, http2};
+use std::collections::HashMap;
+use std::future::Future;
+use std::sync::{Arc, Mutex};
+use std::time::{Duration, Instant};
+
+/// Configuration for a [`ConnectionPool`].
+#[derive(Clone, Debug)]
+pub struct ConnectionPoolConfig {
+    /// The maximum number of idle HTTP/1 connections to keep per authority.
+    pub max_idle_per_authority: usize,
+    /// How long a connection may stay idle before it's closed.
+    pub idle_timeout: Duration,
+    /// Whether to offer HTTP/2 to servers when connecting over TLS.
+    ///
+    /// Servers that accept it are sent all requests to their authority over a
+    /// single multiplexed connection.
+    pub http2: bool,
+}
+
+impl Default for ConnectionPoolConfig {
+    fn default() -> Self {
+        Self {
+            max_idle_per_authority: 16,
+            idle_timeout: Duration::from_secs(90),
+            http2: true,
+        }
+    }
+}
+
+/// A pool of connections which outgoing requests to the same authority reuse
+/// instead of connecting anew each time.
+///
+/// Cloning a pool is cheap and the clone shares its connections with the
+/// original, so a pool may be shared by the [`WasiHttpCtx`] of many stores.
+/// Requests only reuse connections opened with the same [`TlsConfig`] and
+/// through the same proxy though, so contexts which configure those
+/// differently don't share connections.
+///
+/// [`WasiHttpCtx`]: crate::WasiHttpCtx
+#[derive(Clone, Debug)]
+pub struct ConnectionPool {
+    inner: Arc<Inner>,
+}
+
+#[derive(Debug)]
+struct Inner {
+    config: ConnectionPoolConfig,
+    idle: Mutex<HashMap<Key, Vec<Idle>>>,
+}
+
+/// Connections are only shared between requests with the same scheme,
+/// authority, TLS configuration and proxy.
+#[derive(Clone, Debug, PartialEq, Eq, Hash)]
+pub(crate) struct Key {
+    use_tls: bool,
+    authority: String,
+    tls: Option<u64>,
+    proxy: Option<String>,
+}
+
+impl Key {
+    /// Returns the key of connections for requests to `authority`.
+    pub(crate) fn new(
+        use_tls: bool,
+        authority: &str,
+        tls: &TlsConfig,
+        policy: &OutgoingPolicy,
+    ) -> Self {
+        Self {
+            use_tls,
+            authority: authority.to_string(),
+            tls: use_tls.then(|| tls.id()),
+            proxy: policy.proxy().map(str::to_string),
+        }
+    }
+}
+
+#[derive(Debug)]
+struct Idle {
+    sender: Sender,
+    since: Instant,
+}
+
+/// The sending half of an open connection.
+#[derive(Debug)]
+pub(crate) enum Sender {
+    Http1(http1::SendRequest<HyperOutgoingBody>),
+    Http2(http2::SendRequest<HyperOutgoingBody>),
+}
+
+impl Sender {
+    fn is_closed(&self) -> bool {
+        match self {
+            Sender::Http1(sender) => sender.is_closed(),
+            Sender::Http2(sender) => sender.is_closed(),
+        }
+    }
+}
+
+impl ConnectionPool {
+    /// Creates a new, empty pool.
+    pub fn new(config: ConnectionPoolConfig) -> Self {
+        Self {
+            inner: Arc::new(Inner {
+                config,
+                idle: Mutex::new(HashMap::new()),
+            }),
+        }
+    }
+
+    /// Returns the configuration of this pool.
+    pub fn config(&self) -> &ConnectionPoolConfig {
+        &self.inner.config
+    }
+
+    /// Takes a connection for `key` out of the pool, if there's one ready
+    /// for another request.
+    ///
+    /// HTTP/2 connections stay in the pool since they can be shared.
+    pub(crate) fn checkout(&self, key: &Key) -> Option<Sender> {
+        let mut idle = self.inner.idle.lock().unwrap();
+        let conns = idle.get_mut(key)?;
+        let now = Instant::now();
+        conns.retain(|conn| {
+            !conn.sender.is_closed() && now - conn.since < self.inner.config.idle_timeout
+        });
+        let sender = match conns.last_mut() {
+            Some(Idle {
+                sender: Sender::Http2(sender),
+                since,
+            }) => {
+                *since = now;
+                Some(Sender::Http2(sender.clone()))
+            }
+            Some(Idle {
+                sender: Sender::Http1(_),
+                ..
+            }) => conns.pop().map(|conn| conn.sender),
+            None => None,
+        };
+        if conns.is_empty() {
+            idle.remove(key);
+        }
+        sender
+    }
+
+    /// Returns a connection for `key` to the pool once it's ready for
+    /// another request.
+    ///
+    /// For HTTP/1 that's once the response to the previous request has been
+    /// received in full, whereas HTTP/2 connections are ready right away.
+    pub(crate) fn checkin(&self, key: Key, sender: Sender) {
+        let pool = self.clone();
+        match sender {
+            Sender::Http1(mut sender) => {
+                spawn(async move {
+                    if sender.ready().await.is_ok() {
+                        pool.insert(key, Sender::Http1(sender));
+                    }
+                });
+            }
+            Sender::Http2(sender) => pool.insert(key, Sender::Http2(sender)),
+        }
+    }
+
+    fn insert(&self, key: Key, sender: Sender) {
+        let mut idle = self.inner.idle.lock().unwrap();
+        let now = Instant::now();
+        idle.retain(|_, conns| {
+            conns.retain(|conn| {
+                !conn.sender.is_closed() && now - conn.since < self.inner.config.idle_timeout
+            });
+            !conns.is_empty()
+        });
+        let conns = idle.entry(key).or_default();
+        let has_room = match sender {
+            Sender::Http1(_) => conns.len() < self.inner.config.max_idle_per_authority,
+            Sender::Http2(_) => conns.is_empty(),
+        };
+        if has_room {
+            conns.push(Idle { sender, since: now });
+        }
+    }
+}
+
+/// Spawns a task which isn't tied to the lifetime of any particular request.
+///
+/// Pooled connections are closed once every [`Sender`] for them is dropped,
+/// which also ends these tasks.
+pub(crate) fn spawn(future: impl Future<Output = ()> + Send + 'static) {
+    let _ = tokio::task::spawn(future);
+}
+
+/// Runs the background tasks of HTTP/2 connections.
+#[derive(Clone)]
+pub(crate) struct TokioExecutor;
+
+impl<F> hyper::rt::Executor<F> for TokioExecutor
+where
+    F: Future + Send + 'static,
+    F::Output: Send + 'static,
+{
+    fn execute(&self, future: F) {
+        let _ = tokio::task::spawn(future);
+    }
+}
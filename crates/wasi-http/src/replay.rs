@@ -0,0 +1,394 @@
+//! Sending outgoing requests to recorded responses instead of the network.
+//!
+//! A [`ReplayTransport`] answers the outgoing requests of guests from a
+//! [`Recording`] of earlier exchanges, which makes it possible to test
+//! components that make HTTP requests without network access. It can also
+//! capture such a recording from live traffic.
+//!
+//! Recordings are stored as JSON in a subset of the [HAR] format, so that
+//! requests and responses can also be written by hand or exported from other
+//! tools:
+//!
+//! ```json
+//! {
+//!   "log": {
+//!     "entries": [
+//!       {
+//!         "request": {
+//!           "method": "POST",
+//!           "url": "http://localhost:8080/post",
+//!           "headers": [{ "name": "content-type", "value": "text/plain" }],
+//!           "postData": { "text": "hello" }
+//!         },
+//!         "response": {
+//!           "status": 200,
+//!           "headers": [{ "name": "content-type", "value": "text/plain" }],
+//!           "content": { "text": "aGVsbG8=", "encoding": "base64" }
+//!         }
+//!       }
+//!     ]
+//!   }
+//! }
+//! ```
+//!
+//! [HAR]: https://w3c.github.io/web-performance/specs/HAR/Overview.html
+
+use crate::bindings::http::types::ErrorCode;
+use crate::body::HyperOutgoingBody;
+use crate::types::{
+    default_send_request_handler, HostFutureIncomingResponse, IncomingResponse,
+    OutgoingRequestConfig,
+};
+use anyhow::{bail, Context, Result};
+use base64::Engine;
+use bytes::Bytes;
+use http_body_util::{BodyExt, Full};
+use serde_derive::{Deserialize, Serialize};
+use std::path::{Path, PathBuf};
+use std::sync::{Arc, Mutex};
+
+/// A list of recorded exchanges of requests and responses.
+#[derive(Clone, Debug, Default, Serialize, Deserialize)]
+pub struct Recording {
+    /// The recorded exchanges, in the order they happened.
+    pub entries: Vec<Entry>,
+}
+
+/// The top level of a HAR file, which wraps the [`Recording`].
+#[derive(Serialize, Deserialize)]
+struct Har {
+    log: Recording,
+}
+
+impl Recording {
+    /// Parses a recording from JSON.
+    pub fn from_json(json: &[u8]) -> Result<Recording> {
+        let har: Har = serde_json::from_slice(json)?;
+        Ok(har.log)
+    }
+
+    /// Reads a recording from the JSON file at `path`.
+    pub fn from_file(path: impl AsRef<Path>) -> Result<Recording> {
+        let path = path.as_ref();
+        let json =
+            std::fs::read(path).with_context(|| format!("failed to read `{}`", path.display()))?;
+        Recording::from_json(&json)
+            .with_context(|| format!("failed to parse recording `{}`", path.display()))
+    }
+
+    /// Serializes this recording to JSON.
+    pub fn to_json(&self) -> Vec<u8> {
+        let har = Har { log: self.clone() };
+        serde_json::to_vec_pretty(&har).expect("recordings can always be serialized")
+    }
+}
+
+/// A request and the response that it got.
+#[derive(Clone, Debug, Serialize, Deserialize)]
+pub struct Entry {
+    /// The request that was sent.
+    pub request: RecordedRequest,
+    /// The response that was received for it.
+    pub response: RecordedResponse,
+}
+
+/// A recorded request.
+#[derive(Clone, Debug, Serialize, Deserialize)]
+pub struct RecordedRequest {
+    /// The method of the request, such as `GET`.
+    pub method: String,
+    /// The absolute URL of the request, including its query string.
+    pub url: String,
+    /// The headers of the request.
+    #[serde(default)]
+    pub headers: Vec<Header>,
+    /// The body of the request.
+    ///
+    /// When replaying, requests only match this one if their body is the
+    /// same, unless this is `None`.
+    #[serde(default, rename = "postData", skip_serializing_if = "Option::is_none")]
+    pub post_data: Option<Content>,
+}
+
+/// A recorded response.
+#[derive(Clone, Debug, Serialize, Deserialize)]
+pub struct RecordedResponse {
+    /// The status code of the response.
+    pub status: u16,
+    /// The headers of the response.
+    #[serde(default)]
+    pub headers: Vec<Header>,
+    /// The body of the response.
+    #[serde(default)]
+    pub content: Content,
+}
+
+/// A header of a recorded request or response.
+#[derive(Clone, Debug, Serialize, Deserialize)]
+pub struct Header {
+    /// The name of the header.
+    pub name: String,
+    /// The value of the header.
+    pub value: String,
+}
+
+/// The body of a recorded request or response.
+#[derive(Clone, Debug, Default, Serialize, Deserialize)]
+pub struct Content {
+    /// The body itself, encoded according to `encoding`.
+    #[serde(default)]
+    pub text: String,
+    /// How `text` is encoded: `None` for UTF-8 text, or `base64`.
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub encoding: Option<String>,
+}
+
+impl Content {
+    /// Encodes `bytes` as text if they're valid UTF-8, or as base64
+    /// otherwise.
+    pub fn from_bytes(bytes: &[u8]) -> Content {
+        match std::str::from_utf8(bytes) {
+            Ok(text) => Content {
+                text: text.to_string(),
+                encoding: None,
+            },
+            Err(_) => Content {
+                text: base64::engine::general_purpose::STANDARD.encode(bytes),
+                encoding: Some("base64".to_string()),
+            },
+        }
+    }
+
+    /// Decodes the body.
+    pub fn to_bytes(&self) -> Result<Vec<u8>> {
+        match self.encoding.as_deref() {
+            None => Ok(self.text.clone().into_bytes()),
+            Some("base64") => Ok(base64::engine::general_purpose::STANDARD.decode(&self.text)?),
+            Some(encoding) => bail!("unsupported content encoding `{encoding}`"),
+        }
+    }
+}
+
+/// Sends outgoing requests to recorded responses, or records the responses
+/// that they get from the network.
+///
+/// Call [`ReplayTransport::send_request`] from
+/// [`WasiHttpView::send_request`](crate::WasiHttpView::send_request) to use
+/// this for the requests of guests.
+///
+/// When replaying, each request is answered with the response of the first
+/// entry that has the same method and URL (and body, if recorded) and hasn't
+/// been used yet, or else the last such entry. Requests without a matching
+/// entry fail with `error-code.internal-error`.
+///
+/// Cloning a transport is cheap and the clone shares its recording.
+#[derive(Clone)]
+pub struct ReplayTransport {
+    state: Arc<Mutex<State>>,
+}
+
+struct State {
+    recording: Recording,
+    /// Which entries of `recording` have been replayed.
+    used: Vec<bool>,
+    /// Where to save the recording to, when recording.
+    record_to: Option<Sink>,
+}
+
+enum Sink {
+    /// Rewrite this file with the whole recording.
+    File(PathBuf),
+    /// Pass each new entry to this callback.
+    Callback(Box<dyn Fn(&Entry) -> Result<()> + Send + Sync>),
+}
+
+impl ReplayTransport {
+    /// Creates a transport which answers requests from `recording`.
+    pub fn replay(recording: Recording) -> ReplayTransport {
+        ReplayTransport {
+            state: Arc::new(Mutex::new(State {
+                used: vec![false; recording.entries.len()],
+                recording,
+                record_to: None,
+            })),
+        }
+    }
+
+    /// Creates a transport which answers requests from the recording in the
+    /// JSON file at `path`.
+    pub fn replay_file(path: impl AsRef<Path>) -> Result<ReplayTransport> {
+        Ok(ReplayTransport::replay(Recording::from_file(path)?))
+    }
+
+    /// Creates a transport which sends requests over the network with
+    /// [`default_send_request_handler`] and records them, along with their
+    /// responses, in a JSON file at `path`.
+    ///
+    /// The file is rewritten after every request. Bodies of responses are
+    /// read in full before they're returned.
+    pub fn record(path: impl Into<PathBuf>) -> ReplayTransport {
+        ReplayTransport::record_to(Sink::File(path.into()))
+    }
+
+    /// Creates a transport which sends requests over the network like
+    /// [`ReplayTransport::record`], but passes each exchange to `save` as
+    /// it's recorded instead of writing a file.
+    ///
+    /// Requests fail with `error-code.internal-error` if `save` fails.
+    pub fn record_with(
+        save: impl Fn(&Entry) -> Result<()> + Send + Sync + 'static,
+    ) -> ReplayTransport {
+        ReplayTransport::record_to(Sink::Callback(Box::new(save)))
+    }
+
+    fn record_to(sink: Sink) -> ReplayTransport {
+        ReplayTransport {
+            state: Arc::new(Mutex::new(State {
+                recording: Recording::default(),
+                used: Vec::new(),
+                record_to: Some(sink),
+            })),
+        }
+    }
+
+    /// Returns the recording that this transport replays or has recorded.
+    pub fn recording(&self) -> Recording {
+        self.state.lock().unwrap().recording.clone()
+    }
+
+    /// Sends `request`, returning a future for its response.
+    pub fn send_request(
+        &self,
+        request: hyper::Request<HyperOutgoingBody>,
+        config: OutgoingRequestConfig,
+    ) -> HostFutureIncomingResponse {
+        let transport = self.clone();
+        let handle = wasmtime_wasi::runtime::spawn(async move {
+            Ok(transport.send_request_handler(request, config).await)
+        });
+        HostFutureIncomingResponse::pending(handle)
+    }
+
+    /// The underlying implementation of [`ReplayTransport::send_request`].
+    pub async fn send_request_handler(
+        &self,
+        request: hyper::Request<HyperOutgoingBody>,
+        config: OutgoingRequestConfig,
+    ) -> Result<IncomingResponse, ErrorCode> {
+        let (parts, body) = request.into_parts();
+        let body = body.collect().await?.to_bytes();
+        let recorded = RecordedRequest {
+            method: parts.method.to_string(),
+            url: parts.uri.to_string(),
+            headers: record_headers(&parts.headers),
+            post_data: (!body.is_empty()).then(|| Content::from_bytes(&body)),
+        };
+        let between_bytes_timeout = config.between_bytes_timeout;
+
+        let response = if self.state.lock().unwrap().record_to.is_some() {
+            let request = hyper::Request::from_parts(
+                parts,
+                Full::new(body).map_err(|never| match never {}).boxed(),
+            );
+            let response = default_send_request_handler(request, config).await?;
+            let (parts, body) = response.resp.into_parts();
+            let body = body.collect().await?.to_bytes();
+            let response = RecordedResponse {
+                status: parts.status.as_u16(),
+                headers: record_headers(&parts.headers),
+                content: Content::from_bytes(&body),
+            };
+            self.save(Entry {
+                request: recorded,
+                response: response.clone(),
+            })?;
+            response
+        } else {
+            self.find(&recorded, &body).ok_or_else(|| {
+                tracing::warn!(
+                    "no recorded response for {} {}",
+                    recorded.method,
+                    recorded.url
+                );
+                ErrorCode::InternalError(Some(format!(
+                    "no recorded response for {} {}",
+                    recorded.method, recorded.url
+                )))
+            })?
+        };
+
+        let internal_error = |e: anyhow::Error| ErrorCode::InternalError(Some(e.to_string()));
+        let mut builder = hyper::Response::builder().status(response.status);
+        for header in &response.headers {
+            builder = builder.header(&header.name, &header.value);
+        }
+        let body = response.content.to_bytes().map_err(internal_error)?;
+        let resp = builder
+            .body(
+                Full::new(Bytes::from(body))
+                    .map_err(|never| match never {})
+                    .boxed(),
+            )
+            .map_err(|e| internal_error(e.into()))?;
+        Ok(IncomingResponse {
+            resp,
+            worker: None,
+            between_bytes_timeout,
+        })
+    }
+
+    /// Finds the response to replay for `request`, whose body is `body`.
+    fn find(&self, request: &RecordedRequest, body: &[u8]) -> Option<RecordedResponse> {
+        let mut state = self.state.lock().unwrap();
+        let State {
+            recording, used, ..
+        } = &mut *state;
+        let matches = |entry: &Entry| {
+            entry.request.method.eq_ignore_ascii_case(&request.method)
+                && entry.request.url == request.url
+                && entry
+                    .request
+                    .post_data
+                    .as_ref()
+                    .map_or(true, |content| content.to_bytes().is_ok_and(|b| b == body))
+        };
+        let index = recording
+            .entries
+            .iter()
+            .zip(used.iter())
+            .position(|(entry, used)| !used && matches(entry))
+            .or_else(|| recording.entries.iter().rposition(matches))?;
+        used[index] = true;
+        Some(recording.entries[index].response.clone())
+    }
+
+    /// Adds `entry` to the recording and saves it.
+    fn save(&self, entry: Entry) -> Result<(), ErrorCode> {
+        let mut state = self.state.lock().unwrap();
+        state.recording.entries.push(entry);
+        match state.record_to.as_ref().unwrap() {
+            Sink::File(path) => std::fs::write(path, state.recording.to_json()).map_err(|e| {
+                ErrorCode::InternalError(Some(format!(
+                    "failed to save recording to `{}`: {e}",
+                    path.display()
+                )))
+            }),
+            Sink::Callback(save) => {
+                let entry = state.recording.entries.last().unwrap();
+                save(entry).map_err(|e| {
+                    ErrorCode::InternalError(Some(format!("failed to save recording: {e:#}")))
+                })
+            }
+        }
+    }
+}
+
+fn record_headers(headers: &hyper::HeaderMap) -> Vec<Header> {
+    headers
+        .iter()
+        .map(|(name, value)| Header {
+            name: name.to_string(),
+            value: String::from_utf8_lossy(value.as_bytes()).into_owned(),
+        })
+        .collect()
+}
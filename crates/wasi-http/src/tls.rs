@@ -0,0 +1,184 @@
+//! Configuration of TLS for outgoing requests.
+
+use anyhow::Result;
+
+#[cfg(not(any(target_arch = "riscv64", target_arch = "s390x")))]
+use anyhow::{anyhow, bail, Context};
+#[cfg(not(any(target_arch = "riscv64", target_arch = "s390x")))]
+use std::sync::atomic::{AtomicU64, Ordering};
+#[cfg(not(any(target_arch = "riscv64", target_arch = "s390x")))]
+use std::sync::{Arc, OnceLock};
+
+/// How outgoing requests authenticate servers, and themselves, over TLS.
+///
+/// The default configuration trusts the Mozilla root certificates of the
+/// `webpki-roots` crate and presents no client certificate. Use
+/// [`TlsConfig::builder`] to trust other certificate authorities or to present
+/// a client certificate, or [`TlsConfig::from_rustls`] for full control.
+///
+/// Cloning a configuration is cheap.
+#[derive(Clone)]
+pub struct TlsConfig {
+    id: u64,
+    #[cfg(not(any(target_arch = "riscv64", target_arch = "s390x")))]
+    http1: Arc<rustls::ClientConfig>,
+    #[cfg(not(any(target_arch = "riscv64", target_arch = "s390x")))]
+    http2: Arc<rustls::ClientConfig>,
+}
+
+impl std::fmt::Debug for TlsConfig {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        f.debug_struct("TlsConfig").finish_non_exhaustive()
+    }
+}
+
+impl TlsConfig {
+    /// Returns a builder for a configuration.
+    pub fn builder() -> TlsConfigBuilder {
+        TlsConfigBuilder {
+            webpki_roots: true,
+            ca_pems: Vec::new(),
+            client_auth_pem: None,
+        }
+    }
+
+    /// Creates a configuration which uses `config` for all connections.
+    ///
+    /// The ALPN protocols of `config` are replaced when connecting, according
+    /// to whether HTTP/2 is enabled for the connection.
+    #[cfg(not(any(target_arch = "riscv64", target_arch = "s390x")))]
+    pub fn from_rustls(config: rustls::ClientConfig) -> Self {
+        let mut http1 = config.clone();
+        http1.alpn_protocols = Vec::new();
+        let mut http2 = config;
+        http2.alpn_protocols = vec![b"h2".to_vec(), b"http/1.1".to_vec()];
+        static NEXT_ID: AtomicU64 = AtomicU64::new(0);
+        Self {
+            id: NEXT_ID.fetch_add(1, Ordering::Relaxed),
+            http1: Arc::new(http1),
+            http2: Arc::new(http2),
+        }
+    }
+
+    /// Returns an identifier shared only by clones of this configuration,
+    /// which tells apart the connections made with it.
+    pub(crate) fn id(&self) -> u64 {
+        self.id
+    }
+
+    /// Returns the rustls configuration for a connection, with `h2` offered
+    /// through ALPN if `http2` is set.
+    #[cfg(not(any(target_arch = "riscv64", target_arch = "s390x")))]
+    pub(crate) fn client_config(&self, http2: bool) -> Arc<rustls::ClientConfig> {
+        if http2 {
+            self.http2.clone()
+        } else {
+            self.http1.clone()
+        }
+    }
+}
+
+impl Default for TlsConfig {
+    fn default() -> Self {
+        #[cfg(not(any(target_arch = "riscv64", target_arch = "s390x")))]
+        {
+            static DEFAULT: OnceLock<TlsConfig> = OnceLock::new();
+            DEFAULT
+                .get_or_init(|| {
+                    TlsConfig::builder()
+                        .build()
+                        .expect("the default configuration is valid")
+                })
+                .clone()
+        }
+
+        #[cfg(any(target_arch = "riscv64", target_arch = "s390x"))]
+        {
+            Self { id: 0 }
+        }
+    }
+}
+
+/// A builder for a [`TlsConfig`].
+#[derive(Clone)]
+pub struct TlsConfigBuilder {
+    webpki_roots: bool,
+    ca_pems: Vec<Vec<u8>>,
+    client_auth_pem: Option<(Vec<u8>, Vec<u8>)>,
+}
+
+impl TlsConfigBuilder {
+    /// Sets whether to trust the Mozilla root certificates of the
+    /// `webpki-roots` crate, which is the default.
+    pub fn webpki_roots(&mut self, enable: bool) -> &mut Self {
+        self.webpki_roots = enable;
+        self
+    }
+
+    /// Trusts the PEM-encoded certificate authorities in `pem` in addition to
+    /// any others.
+    pub fn add_ca_pem(&mut self, pem: impl Into<Vec<u8>>) -> &mut Self {
+        self.ca_pems.push(pem.into());
+        self
+    }
+
+    /// Presents the PEM-encoded certificate chain `cert` to servers which ask
+    /// for a client certificate, along with the PEM-encoded private key `key`
+    /// for it.
+    pub fn client_auth_pem(
+        &mut self,
+        cert: impl Into<Vec<u8>>,
+        key: impl Into<Vec<u8>>,
+    ) -> &mut Self {
+        self.client_auth_pem = Some((cert.into(), key.into()));
+        self
+    }
+
+    /// Builds the configuration, failing if any of the certificates or keys
+    /// given to this builder are invalid.
+    pub fn build(&self) -> Result<TlsConfig> {
+        #[cfg(any(target_arch = "riscv64", target_arch = "s390x"))]
+        {
+            anyhow::bail!("TLS is not supported on this architecture")
+        }
+
+        #[cfg(not(any(target_arch = "riscv64", target_arch = "s390x")))]
+        {
+            let mut roots = rustls::RootCertStore::empty();
+            if self.webpki_roots {
+                roots.extend(webpki_roots::TLS_SERVER_ROOTS.iter().cloned());
+            }
+            for pem in &self.ca_pems {
+                let certs = rustls_pemfile::certs(&mut &pem[..])
+                    .collect::<Result<Vec<_>, _>>()
+                    .context("failed to read CA certificates")?;
+                if certs.is_empty() {
+                    bail!("no CA certificates found");
+                }
+                for cert in certs {
+                    roots.add(cert).context("invalid CA certificate")?;
+                }
+            }
+
+            let builder = rustls::ClientConfig::builder().with_root_certificates(roots);
+            let config = match &self.client_auth_pem {
+                Some((cert, key)) => {
+                    let certs = rustls_pemfile::certs(&mut &cert[..])
+                        .collect::<Result<Vec<_>, _>>()
+                        .context("failed to read client certificates")?;
+                    if certs.is_empty() {
+                        bail!("no client certificates found");
+                    }
+                    let key = rustls_pemfile::private_key(&mut &key[..])
+                        .context("failed to read client private key")?
+                        .ok_or_else(|| anyhow!("no client private key found"))?;
+                    builder
+                        .with_client_auth_cert(certs, key)
+                        .context("invalid client certificate or key")?
+                }
+                None => builder.with_no_client_auth(),
+            };
+            Ok(TlsConfig::from_rustls(config))
+        }
+    }
+}
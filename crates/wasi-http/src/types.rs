@@ -2,6 +2,9 @@
 //! implementation of the wasi-http API.
 
 use crate::io::TokioIo;
+use crate::policy::{self, OutgoingPolicy};
+use crate::pool::{self, ConnectionPool, ConnectionPoolConfig, Key, Sender};
+use crate::tls::TlsConfig;
 use crate::{
     bindings::http::types::{self, Method, Scheme},
     body::{HostIncomingBody, HyperIncomingBody, HyperOutgoingBody},
@@ -14,6 +17,8 @@ use http_body_util::BodyExt;
 use hyper::body::Body;
 use hyper::header::HeaderName;
 use std::any::Any;
+use std::future::Future;
+use std::pin::Pin;
 use std::time::Duration;
 use tokio::net::TcpStream;
 use tokio::time::timeout;
@@ -21,15 +26,62 @@ use wasmtime::component::{Resource, ResourceTable};
 use wasmtime_wasi::{runtime::AbortOnDropJoinHandle, Subscribe};
 
 /// Capture the state necessary for use in the wasi-http API implementation.
-#[derive(Debug)]
+///
+/// Cloning a context shares its [`ConnectionPool`] with the clone.
+#[derive(Clone, Debug)]
 pub struct WasiHttpCtx {
-    _priv: (),
+    pool: Option<ConnectionPool>,
+    tls: TlsConfig,
+    policy: OutgoingPolicy,
 }
 
 impl WasiHttpCtx {
     /// Create a new context.
+    ///
+    /// The context gets its own [`ConnectionPool`] with the default
+    /// configuration.
     pub fn new() -> Self {
-        Self { _priv: () }
+        Self {
+            pool: Some(ConnectionPool::new(ConnectionPoolConfig::default())),
+            tls: TlsConfig::default(),
+            policy: OutgoingPolicy::default(),
+        }
+    }
+
+    /// Sets the pool of connections that outgoing requests reuse, or `None`
+    /// to open a new connection for every request.
+    ///
+    /// Passing a clone of the same pool to the contexts of many stores lets
+    /// their requests share connections.
+    pub fn set_connection_pool(&mut self, pool: Option<ConnectionPool>) {
+        self.pool = pool;
+    }
+
+    /// Returns the pool of connections that outgoing requests reuse, if any.
+    pub fn connection_pool(&self) -> Option<&ConnectionPool> {
+        self.pool.as_ref()
+    }
+
+    /// Sets how outgoing requests over HTTPS authenticate servers and
+    /// themselves.
+    pub fn set_tls_config(&mut self, tls: TlsConfig) {
+        self.tls = tls;
+    }
+
+    /// Returns how outgoing requests over HTTPS authenticate servers and
+    /// themselves.
+    pub fn tls_config(&self) -> &TlsConfig {
+        &self.tls
+    }
+
+    /// Sets the policy that outgoing requests are checked against.
+    pub fn set_outgoing_policy(&mut self, policy: OutgoingPolicy) {
+        self.policy = policy;
+    }
+
+    /// Returns the policy that outgoing requests are checked against.
+    pub fn outgoing_policy(&self) -> &OutgoingPolicy {
+        &self.policy
     }
 }
 
@@ -281,6 +333,14 @@ pub struct OutgoingRequestConfig {
     pub first_byte_timeout: Duration,
     /// The timeout between chunks of a streaming body
     pub between_bytes_timeout: Duration,
+    /// The pool to take a connection from and return it to afterwards, or
+    /// `None` to open a new connection just for this request.
+    pub pool: Option<ConnectionPool>,
+    /// The TLS configuration to connect with, if `use_tls` is set.
+    pub tls: TlsConfig,
+    /// The policy that the request was checked against, which also decides
+    /// which addresses may be connected to and whether to go through a proxy.
+    pub policy: OutgoingPolicy,
 }
 
 /// The default implementation of how an outgoing request is sent.
@@ -308,6 +368,9 @@ pub async fn default_send_request_handler(
         connect_timeout,
         first_byte_timeout,
         between_bytes_timeout,
+        pool,
+        tls,
+        policy,
     }: OutgoingRequestConfig,
 ) -> Result<IncomingResponse, types::ErrorCode> {
     let authority = if let Some(authority) = request.uri().authority() {
@@ -320,28 +383,100 @@ pub async fn default_send_request_handler(
     } else {
         return Err(types::ErrorCode::HttpRequestUriInvalid);
     };
-    let tcp_stream = timeout(connect_timeout, TcpStream::connect(&authority))
-        .await
-        .map_err(|_| types::ErrorCode::ConnectionTimeout)?
-        .map_err(|e| match e.kind() {
-            std::io::ErrorKind::AddrNotAvailable => {
-                dns_error("address not available".to_string(), 0)
-            }
 
-            _ => {
-                if e.to_string()
-                    .starts_with("failed to lookup address information")
-                {
-                    dns_error("address not available".to_string(), 0)
-                } else {
-                    types::ErrorCode::ConnectionRefused
-                }
+    let key = Key::new(use_tls, &authority, &tls, &policy);
+    let pooled = pool.as_ref().and_then(|pool| pool.checkout(&key));
+    let (sender, worker) = match pooled {
+        Some(sender) => (sender, None),
+        None => {
+            let http2 = pool.as_ref().is_some_and(|pool| pool.config().http2);
+            let (sender, conn) = connect(
+                &authority,
+                use_tls.then_some(&tls),
+                http2,
+                &policy,
+                connect_timeout,
+            )
+            .await?;
+            // Pooled connections outlive the request that opened them, so
+            // they can't be torn down along with its response.
+            let worker = if pool.is_some() {
+                pool::spawn(conn);
+                None
+            } else {
+                Some(wasmtime_wasi::runtime::spawn(conn))
+            };
+            (sender, worker)
+        }
+    };
+
+    let resp = match sender {
+        Sender::Http1(mut sender) => {
+            // at this point, the request contains the scheme and the authority, but
+            // the http packet should only include those if addressing a proxy, so
+            // remove them here, since SendRequest::send_request does not do it for us
+            *request.uri_mut() = http::Uri::builder()
+                .path_and_query(
+                    request
+                        .uri()
+                        .path_and_query()
+                        .map(|p| p.as_str())
+                        .unwrap_or("/"),
+                )
+                .build()
+                .expect("comes from valid request");
+
+            let resp = timeout(first_byte_timeout, sender.send_request(request)).await;
+            if let Some(pool) = &pool {
+                pool.checkin(key, Sender::Http1(sender));
+            }
+            resp
+        }
+        Sender::Http2(mut sender) => {
+            // HTTP/2 takes the authority from the URI, which is kept as is,
+            // instead of from a `Host` header.
+            request.headers_mut().remove(hyper::header::HOST);
+            if let Some(pool) = &pool {
+                pool.checkin(key, Sender::Http2(sender.clone()));
             }
-        })?;
+            timeout(first_byte_timeout, sender.send_request(request)).await
+        }
+    };
+    let resp = resp
+        .map_err(|_| types::ErrorCode::ConnectionReadTimeout)?
+        .map_err(hyper_request_error)?
+        .map(|body| body.map_err(hyper_request_error).boxed());
+
+    Ok(IncomingResponse {
+        resp,
+        worker,
+        between_bytes_timeout,
+    })
+}
+
+/// The future which drives a connection until it's closed.
+type Connection = Pin<Box<dyn Future<Output = ()> + Send>>;
 
-    let (mut sender, worker) = if use_tls {
+/// Opens a connection to `authority`, returning a sender for requests on it
+/// and the future which drives it.
+///
+/// TLS is used if `tls` is given, and HTTP/2 is used if `http2` is set and
+/// the server agrees to it during the TLS handshake.
+async fn connect(
+    authority: &str,
+    tls: Option<&TlsConfig>,
+    http2: bool,
+    policy: &OutgoingPolicy,
+    connect_timeout: Duration,
+) -> Result<(Sender, Connection), types::ErrorCode> {
+    let tcp_stream = timeout(connect_timeout, open_tcp(authority, policy))
+        .await
+        .map_err(|_| types::ErrorCode::ConnectionTimeout)??;
+
+    if let Some(tls) = tls {
         #[cfg(any(target_arch = "riscv64", target_arch = "s390x"))]
         {
+            let _ = (tls, http2);
             return Err(crate::bindings::http::types::ErrorCode::InternalError(
                 Some("unsupported architecture for SSL".to_string()),
             ));
@@ -352,15 +487,9 @@ pub async fn default_send_request_handler(
             use rustls::pki_types::ServerName;
 
             // derived from https://github.com/rustls/rustls/blob/main/examples/src/bin/simpleclient.rs
-            let root_cert_store = rustls::RootCertStore {
-                roots: webpki_roots::TLS_SERVER_ROOTS.into(),
-            };
-            let config = rustls::ClientConfig::builder()
-                .with_root_certificates(root_cert_store)
-                .with_no_client_auth();
-            let connector = tokio_rustls::TlsConnector::from(std::sync::Arc::new(config));
+            let connector = tokio_rustls::TlsConnector::from(tls.client_config(http2));
             let mut parts = authority.split(":");
-            let host = parts.next().unwrap_or(&authority);
+            let host = parts.next().unwrap_or(authority);
             let domain = ServerName::try_from(host)
                 .map_err(|e| {
                     tracing::warn!("dns lookup error: {e:?}");
@@ -369,76 +498,114 @@ pub async fn default_send_request_handler(
                 .to_owned();
             let stream = connector.connect(domain, tcp_stream).await.map_err(|e| {
                 tracing::warn!("tls protocol error: {e:?}");
-                types::ErrorCode::TlsProtocolError
+                tls_error(e)
             })?;
-            let stream = TokioIo::new(stream);
+            let negotiated_h2 = stream.get_ref().1.alpn_protocol() == Some(b"h2");
+            handshake(TokioIo::new(stream), negotiated_h2, connect_timeout).await
+        }
+    } else {
+        // Without TLS there's no way to negotiate HTTP/2, so stick to HTTP/1.
+        handshake(TokioIo::new(tcp_stream), false, connect_timeout).await
+    }
+}
 
-            let (sender, conn) = timeout(
-                connect_timeout,
-                hyper::client::conn::http1::handshake(stream),
-            )
-            .await
-            .map_err(|_| types::ErrorCode::ConnectionTimeout)?
-            .map_err(hyper_request_error)?;
+/// Opens a TCP connection for requests to `authority`, either to one of the
+/// addresses it resolves to that `policy` allows or through its proxy.
+async fn open_tcp(authority: &str, policy: &OutgoingPolicy) -> Result<TcpStream, types::ErrorCode> {
+    if let Some(proxy) = policy.proxy() {
+        let stream = TcpStream::connect(proxy).await.map_err(connect_error)?;
+        return policy::tunnel(stream, authority).await;
+    }
+
+    let addrs = tokio::net::lookup_host(authority)
+        .await
+        .map_err(connect_error)?
+        .collect::<Vec<_>>();
+    let host = authority
+        .rsplit_once(':')
+        .map_or(authority, |(host, _)| host);
+    let allowed = addrs
+        .iter()
+        .copied()
+        .filter(|addr| policy.is_addr_allowed(host, *addr))
+        .collect::<Vec<_>>();
+    if allowed.is_empty() && !addrs.is_empty() {
+        return Err(types::ErrorCode::HttpRequestDenied);
+    }
+    TcpStream::connect(&allowed[..])
+        .await
+        .map_err(connect_error)
+}
 
-            let worker = wasmtime_wasi::runtime::spawn(async move {
-                match conn.await {
-                    Ok(()) => {}
-                    // TODO: shouldn't throw away this error and ideally should
-                    // surface somewhere.
-                    Err(e) => tracing::warn!("dropping error {e}"),
-                }
-            });
+fn connect_error(e: std::io::Error) -> types::ErrorCode {
+    match e.kind() {
+        std::io::ErrorKind::AddrNotAvailable => dns_error("address not available".to_string(), 0),
 
-            (sender, worker)
+        _ => {
+            if e.to_string()
+                .starts_with("failed to lookup address information")
+            {
+                dns_error("address not available".to_string(), 0)
+            } else {
+                types::ErrorCode::ConnectionRefused
+            }
         }
-    } else {
-        let tcp_stream = TokioIo::new(tcp_stream);
+    }
+}
+
+/// Translates an error from the TLS handshake into the error reported to the
+/// guest.
+#[cfg(not(any(target_arch = "riscv64", target_arch = "s390x")))]
+fn tls_error(e: std::io::Error) -> types::ErrorCode {
+    match e.get_ref().and_then(|e| e.downcast_ref::<rustls::Error>()) {
+        Some(rustls::Error::InvalidCertificate(_)) => types::ErrorCode::TlsCertificateError,
+        Some(rustls::Error::AlertReceived(alert)) => {
+            types::ErrorCode::TlsAlertReceived(types::TlsAlertReceivedPayload {
+                alert_id: Some(alert.get_u8()),
+                alert_message: Some(format!("{alert:?}")),
+            })
+        }
+        _ => types::ErrorCode::TlsProtocolError,
+    }
+}
+
+/// Performs the HTTP handshake over an established connection.
+async fn handshake<T>(
+    io: T,
+    http2: bool,
+    connect_timeout: Duration,
+) -> Result<(Sender, Connection), types::ErrorCode>
+where
+    T: hyper::rt::Read + hyper::rt::Write + Unpin + Send + 'static,
+{
+    // TODO: shouldn't throw away connection errors and ideally should
+    // surface them somewhere.
+    if http2 {
         let (sender, conn) = timeout(
             connect_timeout,
-            // TODO: we should plumb the builder through the http context, and use it here
-            hyper::client::conn::http1::handshake(tcp_stream),
+            hyper::client::conn::http2::handshake(pool::TokioExecutor, io),
         )
         .await
         .map_err(|_| types::ErrorCode::ConnectionTimeout)?
         .map_err(hyper_request_error)?;
-
-        let worker = wasmtime_wasi::runtime::spawn(async move {
-            match conn.await {
-                Ok(()) => {}
-                // TODO: same as above, shouldn't throw this error away.
-                Err(e) => tracing::warn!("dropping error {e}"),
+        let conn = async move {
+            if let Err(e) = conn.await {
+                tracing::warn!("dropping error {e}");
             }
-        });
-
-        (sender, worker)
-    };
-
-    // at this point, the request contains the scheme and the authority, but
-    // the http packet should only include those if addressing a proxy, so
-    // remove them here, since SendRequest::send_request does not do it for us
-    *request.uri_mut() = http::Uri::builder()
-        .path_and_query(
-            request
-                .uri()
-                .path_and_query()
-                .map(|p| p.as_str())
-                .unwrap_or("/"),
-        )
-        .build()
-        .expect("comes from valid request");
-
-    let resp = timeout(first_byte_timeout, sender.send_request(request))
-        .await
-        .map_err(|_| types::ErrorCode::ConnectionReadTimeout)?
-        .map_err(hyper_request_error)?
-        .map(|body| body.map_err(hyper_request_error).boxed());
-
-    Ok(IncomingResponse {
-        resp,
-        worker: Some(worker),
-        between_bytes_timeout,
-    })
+        };
+        Ok((Sender::Http2(sender), Box::pin(conn)))
+    } else {
+        let (sender, conn) = timeout(connect_timeout, hyper::client::conn::http1::handshake(io))
+            .await
+            .map_err(|_| types::ErrorCode::ConnectionTimeout)?
+            .map_err(hyper_request_error)?;
+        let conn = async move {
+            if let Err(e) = conn.await {
+                tracing::warn!("dropping error {e}");
+            }
+        };
+        Ok((Sender::Http1(sender), Box::pin(conn)))
+    }
 }
 
 impl From<http::Method> for types::Method {
@@ -553,6 +553,557 @@ async fn wasi_http_without_port() -> Result<()> {
     Ok(())
 }
 
+#[test_log::test(tokio::test)]
+async fn wasi_http_connection_pool() -> Result<()> {
+    use std::sync::atomic::{AtomicUsize, Ordering};
+    use std::time::Duration;
+    use wasmtime_wasi_http::pool::{ConnectionPool, ConnectionPoolConfig};
+
+    let listener = tokio::net::TcpListener::bind((Ipv4Addr::new(127, 0, 0, 1), 0)).await?;
+    let addr = listener.local_addr()?;
+    let connections = Arc::new(AtomicUsize::new(0));
+    let server = task::spawn({
+        let connections = connections.clone();
+        async move {
+            loop {
+                let (stream, _) = listener.accept().await?;
+                connections.fetch_add(1, Ordering::SeqCst);
+                task::spawn(http1::Builder::new().keep_alive(true).serve_connection(
+                    TokioIo::new(stream),
+                    service_fn(|_| async {
+                        Ok::<_, hyper::Error>(hyper::Response::new(body::full(Bytes::from_static(
+                            b"hello",
+                        ))))
+                    }),
+                ));
+            }
+            #[allow(unreachable_code)]
+            Ok::<_, anyhow::Error>(())
+        }
+    });
+
+    let pool = ConnectionPool::new(ConnectionPoolConfig::default());
+    for _ in 0..3 {
+        let request = hyper::Request::get(format!("http://{addr}/"))
+            .body(Empty::new().map_err(|_| unreachable!()).boxed())?;
+        let response = types::default_send_request_handler(
+            request,
+            OutgoingRequestConfig {
+                use_tls: false,
+                connect_timeout: Duration::from_secs(5),
+                first_byte_timeout: Duration::from_secs(5),
+                between_bytes_timeout: Duration::from_secs(5),
+                pool: Some(pool.clone()),
+                tls: Default::default(),
+                policy: Default::default(),
+            },
+        )
+        .await
+        .map_err(|e| anyhow!("request failed: {e:?}"))?;
+        let body = response.resp.into_body().collect().await?.to_bytes();
+        assert_eq!(body, "hello");
+
+        // Connections are returned to the pool in the background once the
+        // response has been read, so give that a moment to happen.
+        tokio::time::sleep(Duration::from_millis(100)).await;
+    }
+
+    assert_eq!(connections.load(Ordering::SeqCst), 1);
+
+    // Requests through a proxy don't reuse the direct connection, so this
+    // one fails to connect to a proxy which isn't listening.
+    let proxy = tokio::net::TcpListener::bind((Ipv4Addr::new(127, 0, 0, 1), 0))
+        .await?
+        .local_addr()?;
+    let request = hyper::Request::get(format!("http://{addr}/"))
+        .body(Empty::new().map_err(|_| unreachable!()).boxed())?;
+    let result = types::default_send_request_handler(
+        request,
+        OutgoingRequestConfig {
+            use_tls: false,
+            connect_timeout: Duration::from_secs(5),
+            first_byte_timeout: Duration::from_secs(5),
+            between_bytes_timeout: Duration::from_secs(5),
+            pool: Some(pool.clone()),
+            tls: Default::default(),
+            policy: wasmtime_wasi_http::policy::OutgoingPolicy::builder()
+                .proxy(&proxy.to_string())?
+                .build(),
+        },
+    )
+    .await;
+    assert!(
+        matches!(result, Err(ErrorCode::ConnectionRefused)),
+        "{:?}",
+        result.map(|_| ())
+    );
+    assert_eq!(connections.load(Ordering::SeqCst), 1);
+
+    server.abort();
+    Ok(())
+}
+
+/// Serves requests over TLS with the certificates in `tests/all/tls`,
+/// requiring clients to present a certificate if `client_auth` is set.
+#[cfg(not(any(target_arch = "riscv64", target_arch = "s390x")))]
+async fn tls_server(client_auth: bool) -> Result<(std::net::SocketAddr, task::JoinHandle<()>)> {
+    use tokio_rustls::rustls::{self, server::WebPkiClientVerifier};
+
+    let certs = rustls_pemfile::certs(&mut &include_bytes!("tls/server.pem")[..])
+        .collect::<Result<Vec<_>, _>>()?;
+    let key = rustls_pemfile::private_key(&mut &include_bytes!("tls/server.key")[..])?
+        .context("no private key")?;
+    let builder = if client_auth {
+        let mut roots = rustls::RootCertStore::empty();
+        for cert in rustls_pemfile::certs(&mut &include_bytes!("tls/ca.pem")[..]) {
+            roots.add(cert?)?;
+        }
+        rustls::ServerConfig::builder()
+            .with_client_cert_verifier(WebPkiClientVerifier::builder(Arc::new(roots)).build()?)
+    } else {
+        rustls::ServerConfig::builder().with_no_client_auth()
+    };
+    let acceptor = tokio_rustls::TlsAcceptor::from(Arc::new(builder.with_single_cert(certs, key)?));
+
+    let listener = tokio::net::TcpListener::bind((Ipv4Addr::new(127, 0, 0, 1), 0)).await?;
+    let addr = listener.local_addr()?;
+    let server = task::spawn(async move {
+        while let Ok((stream, _)) = listener.accept().await {
+            let acceptor = acceptor.clone();
+            task::spawn(async move {
+                let Ok(stream) = acceptor.accept(stream).await else {
+                    return;
+                };
+                let _ = http1::Builder::new()
+                    .serve_connection(
+                        TokioIo::new(stream),
+                        service_fn(|_| async {
+                            Ok::<_, hyper::Error>(hyper::Response::new(body::full(
+                                Bytes::from_static(b"hello"),
+                            )))
+                        }),
+                    )
+                    .await;
+            });
+        }
+    });
+    Ok((addr, server))
+}
+
+#[cfg(not(any(target_arch = "riscv64", target_arch = "s390x")))]
+async fn send_tls_request(
+    addr: std::net::SocketAddr,
+    tls: wasmtime_wasi_http::tls::TlsConfig,
+    pool: Option<wasmtime_wasi_http::pool::ConnectionPool>,
+) -> Result<Bytes, ErrorCode> {
+    use std::time::Duration;
+
+    let request = hyper::Request::get(format!("https://localhost:{}/", addr.port()))
+        .body(Empty::new().map_err(|_| unreachable!()).boxed())
+        .unwrap();
+    let response = types::default_send_request_handler(
+        request,
+        OutgoingRequestConfig {
+            use_tls: true,
+            connect_timeout: Duration::from_secs(5),
+            first_byte_timeout: Duration::from_secs(5),
+            between_bytes_timeout: Duration::from_secs(5),
+            pool,
+            tls,
+            policy: Default::default(),
+        },
+    )
+    .await?;
+    Ok(response.resp.into_body().collect().await?.to_bytes())
+}
+
+#[test_log::test(tokio::test)]
+// test uses TLS but riscv/s390x don't support that yet
+#[cfg_attr(any(target_arch = "riscv64", target_arch = "s390x"), ignore)]
+async fn wasi_http_tls_ca_bundle() -> Result<()> {
+    #[cfg(not(any(target_arch = "riscv64", target_arch = "s390x")))]
+    {
+        use wasmtime_wasi_http::tls::TlsConfig;
+
+        let (addr, server) = tls_server(false).await?;
+
+        // The server's certificate isn't signed by any of the default roots.
+        let result = send_tls_request(addr, TlsConfig::default(), None).await;
+        assert!(
+            matches!(result, Err(ErrorCode::TlsCertificateError)),
+            "{result:?}"
+        );
+
+        let tls = TlsConfig::builder()
+            .add_ca_pem(&include_bytes!("tls/ca.pem")[..])
+            .build()?;
+        let body = send_tls_request(addr, tls, None)
+            .await
+            .map_err(|e| anyhow!("{e:?}"))?;
+        assert_eq!(body, "hello");
+
+        server.abort();
+    }
+    Ok(())
+}
+
+#[test_log::test(tokio::test)]
+// test uses TLS but riscv/s390x don't support that yet
+#[cfg_attr(any(target_arch = "riscv64", target_arch = "s390x"), ignore)]
+async fn wasi_http_tls_client_auth() -> Result<()> {
+    #[cfg(not(any(target_arch = "riscv64", target_arch = "s390x")))]
+    {
+        use wasmtime_wasi_http::tls::TlsConfig;
+
+        let (addr, server) = tls_server(true).await?;
+
+        let tls = TlsConfig::builder()
+            .add_ca_pem(&include_bytes!("tls/ca.pem")[..])
+            .build()?;
+        let result = send_tls_request(addr, tls, None).await;
+        assert!(result.is_err(), "{result:?}");
+
+        let tls = TlsConfig::builder()
+            .add_ca_pem(&include_bytes!("tls/ca.pem")[..])
+            .client_auth_pem(
+                &include_bytes!("tls/client.pem")[..],
+                &include_bytes!("tls/client.key")[..],
+            )
+            .build()?;
+        let body = send_tls_request(addr, tls, None)
+            .await
+            .map_err(|e| anyhow!("{e:?}"))?;
+        assert_eq!(body, "hello");
+
+        server.abort();
+    }
+    Ok(())
+}
+
+#[test_log::test(tokio::test)]
+// test uses TLS but riscv/s390x don't support that yet
+#[cfg_attr(any(target_arch = "riscv64", target_arch = "s390x"), ignore)]
+async fn wasi_http_connection_pool_tls() -> Result<()> {
+    #[cfg(not(any(target_arch = "riscv64", target_arch = "s390x")))]
+    {
+        use std::time::Duration;
+        use wasmtime_wasi_http::pool::{ConnectionPool, ConnectionPoolConfig};
+        use wasmtime_wasi_http::tls::TlsConfig;
+
+        let (addr, server) = tls_server(true).await?;
+        let pool = ConnectionPool::new(ConnectionPoolConfig::default());
+
+        let tls = TlsConfig::builder()
+            .add_ca_pem(&include_bytes!("tls/ca.pem")[..])
+            .client_auth_pem(
+                &include_bytes!("tls/client.pem")[..],
+                &include_bytes!("tls/client.key")[..],
+            )
+            .build()?;
+        let body = send_tls_request(addr, tls.clone(), Some(pool.clone()))
+            .await
+            .map_err(|e| anyhow!("{e:?}"))?;
+        assert_eq!(body, "hello");
+        tokio::time::sleep(Duration::from_millis(100)).await;
+
+        // A configuration without the client certificate can't reuse the
+        // connection authenticated with it.
+        let without_cert = TlsConfig::builder()
+            .add_ca_pem(&include_bytes!("tls/ca.pem")[..])
+            .build()?;
+        let result = send_tls_request(addr, without_cert, Some(pool.clone())).await;
+        assert!(result.is_err(), "{result:?}");
+
+        let body = send_tls_request(addr, tls, Some(pool))
+            .await
+            .map_err(|e| anyhow!("{e:?}"))?;
+        assert_eq!(body, "hello");
+
+        server.abort();
+    }
+    Ok(())
+}
+
+/// Sends a GET request with `body` to `addr` after checking it against
+/// `policy`, returning the response body.
+async fn send_with_policy(
+    addr: std::net::SocketAddr,
+    policy: wasmtime_wasi_http::policy::OutgoingPolicy,
+    body: &'static str,
+) -> Result<Bytes, ErrorCode> {
+    use std::time::Duration;
+
+    let mut request = hyper::Request::get(format!("http://localhost:{}/", addr.port()))
+        .body(
+            body::full(Bytes::from_static(body.as_bytes()))
+                .map_err(|_| unreachable!())
+                .boxed(),
+        )
+        .unwrap();
+    policy.apply(&mut request, false)?;
+    let response = types::default_send_request_handler(
+        request,
+        OutgoingRequestConfig {
+            use_tls: false,
+            connect_timeout: Duration::from_secs(5),
+            first_byte_timeout: Duration::from_secs(5),
+            between_bytes_timeout: Duration::from_secs(5),
+            pool: None,
+            tls: Default::default(),
+            policy,
+        },
+    )
+    .await?;
+    Ok(response.resp.into_body().collect().await?.to_bytes())
+}
+
+#[test_log::test(tokio::test)]
+async fn wasi_http_outgoing_policy() -> Result<()> {
+    use wasmtime_wasi_http::policy::OutgoingPolicy;
+
+    // Responds with the `x-token` header of requests and the size of their
+    // body.
+    let listener = tokio::net::TcpListener::bind((Ipv4Addr::new(127, 0, 0, 1), 0)).await?;
+    let addr = listener.local_addr()?;
+    let server = task::spawn(async move {
+        while let Ok((stream, _)) = listener.accept().await {
+            task::spawn(http1::Builder::new().serve_connection(
+                TokioIo::new(stream),
+                service_fn(
+                    |request: hyper::Request<hyper::body::Incoming>| async move {
+                        let token = request
+                            .headers()
+                            .get("x-token")
+                            .map(|v| v.to_str().unwrap().to_string())
+                            .unwrap_or_default();
+                        let size = request.into_body().collect().await?.to_bytes().len();
+                        Ok::<_, hyper::Error>(hyper::Response::new(body::full(Bytes::from(
+                            format!("{token} {size}"),
+                        ))))
+                    },
+                ),
+            ));
+        }
+    });
+
+    let body = send_with_policy(addr, OutgoingPolicy::default(), "hi")
+        .await
+        .map_err(|e| anyhow!("{e:?}"))?;
+    assert_eq!(body, " 2");
+
+    for (allow, deny) in [
+        (None, Some("localhost")),
+        (None, Some("127.0.0.0/8")),
+        (Some("example.com"), None),
+        (Some("10.0.0.0/8"), None),
+        (Some(&*format!("localhost:{}", addr.port() + 1)), None),
+    ] {
+        let mut policy = OutgoingPolicy::builder();
+        if let Some(rule) = allow {
+            policy.allow(rule)?;
+        }
+        if let Some(rule) = deny {
+            policy.deny(rule)?;
+        }
+        let result = send_with_policy(addr, policy.build(), "hi").await;
+        assert!(
+            matches!(result, Err(ErrorCode::HttpRequestDenied)),
+            "allow {allow:?} deny {deny:?}: {result:?}"
+        );
+    }
+
+    let policy = OutgoingPolicy::builder()
+        .allow("127.0.0.0/8")?
+        .deny("10.0.0.0/8")?
+        .header("x-token", "secret")?
+        .max_request_body_size(4)
+        .build();
+    let body = send_with_policy(addr, policy.clone(), "hi")
+        .await
+        .map_err(|e| anyhow!("{e:?}"))?;
+    assert_eq!(body, "secret 2");
+    let result = send_with_policy(addr, policy, "too long").await;
+    assert!(
+        matches!(result, Err(ErrorCode::HttpRequestBodySize(_))),
+        "{result:?}"
+    );
+
+    let result = send_with_policy(
+        addr,
+        OutgoingPolicy::builder().https_only(true).build(),
+        "hi",
+    )
+    .await;
+    assert!(
+        matches!(result, Err(ErrorCode::HttpRequestDenied)),
+        "{result:?}"
+    );
+
+    server.abort();
+    Ok(())
+}
+
+#[test_log::test(tokio::test)]
+async fn wasi_http_outgoing_proxy() -> Result<()> {
+    use tokio::io::{AsyncReadExt, AsyncWriteExt};
+    use wasmtime_wasi_http::policy::OutgoingPolicy;
+
+    let listener = tokio::net::TcpListener::bind((Ipv4Addr::new(127, 0, 0, 1), 0)).await?;
+    let addr = listener.local_addr()?;
+    let server = task::spawn(async move {
+        while let Ok((stream, _)) = listener.accept().await {
+            task::spawn(http1::Builder::new().serve_connection(
+                TokioIo::new(stream),
+                service_fn(|_| async {
+                    Ok::<_, hyper::Error>(hyper::Response::new(body::full(Bytes::from_static(
+                        b"hello",
+                    ))))
+                }),
+            ));
+        }
+    });
+
+    // A proxy which records the targets of `CONNECT` requests and tunnels
+    // them to the server above.
+    let proxy_listener = tokio::net::TcpListener::bind((Ipv4Addr::new(127, 0, 0, 1), 0)).await?;
+    let proxy_addr = proxy_listener.local_addr()?;
+    let (targets_tx, mut targets) = tokio::sync::mpsc::unbounded_channel();
+    let proxy = task::spawn(async move {
+        while let Ok((mut client, _)) = proxy_listener.accept().await {
+            let mut request = Vec::new();
+            while !request.ends_with(b"\r\n\r\n") {
+                request.push(client.read_u8().await.unwrap());
+            }
+            let request = String::from_utf8(request).unwrap();
+            targets_tx
+                .send(request.lines().next().unwrap().to_string())
+                .unwrap();
+            client
+                .write_all(b"HTTP/1.1 200 Connection established\r\n\r\n")
+                .await
+                .unwrap();
+            let mut upstream = tokio::net::TcpStream::connect(addr).await.unwrap();
+            task::spawn(async move {
+                let _ = tokio::io::copy_bidirectional(&mut client, &mut upstream).await;
+            });
+        }
+    });
+
+    let policy = OutgoingPolicy::builder()
+        .proxy(&proxy_addr.to_string())?
+        .build();
+    let body = send_with_policy(addr, policy, "")
+        .await
+        .map_err(|e| anyhow!("{e:?}"))?;
+    assert_eq!(body, "hello");
+    assert_eq!(
+        targets.recv().await.unwrap(),
+        format!("CONNECT localhost:{} HTTP/1.1", addr.port())
+    );
+
+    proxy.abort();
+    server.abort();
+    Ok(())
+}
+
+#[test_log::test(tokio::test)]
+async fn wasi_http_record_and_replay() -> Result<()> {
+    use std::time::Duration;
+    use wasmtime_wasi_http::replay::{Recording, ReplayTransport};
+
+    let listener = tokio::net::TcpListener::bind((Ipv4Addr::new(127, 0, 0, 1), 0)).await?;
+    let addr = listener.local_addr()?;
+    let server = task::spawn(async move {
+        while let Ok((stream, _)) = listener.accept().await {
+            task::spawn(http1::Builder::new().serve_connection(
+                TokioIo::new(stream),
+                service_fn(
+                    |request: hyper::Request<hyper::body::Incoming>| async move {
+                        let path = request.uri().path().to_string();
+                        let body = request.into_body().collect().await?.to_bytes();
+                        let mut response = hyper::Response::new(body::full(Bytes::from(format!(
+                            "{path} {}",
+                            String::from_utf8_lossy(&body)
+                        ))));
+                        response
+                            .headers_mut()
+                            .insert("x-path", path.parse().unwrap());
+                        Ok::<_, hyper::Error>(response)
+                    },
+                ),
+            ));
+        }
+    });
+
+    let send = |transport: ReplayTransport, path: &'static str, body: &'static str| async move {
+        let request = hyper::Request::post(format!("http://localhost:{}{path}", addr.port()))
+            .body(
+                body::full(Bytes::from_static(body.as_bytes()))
+                    .map_err(|_| unreachable!())
+                    .boxed(),
+            )
+            .unwrap();
+        let config = OutgoingRequestConfig {
+            use_tls: false,
+            connect_timeout: Duration::from_secs(5),
+            first_byte_timeout: Duration::from_secs(5),
+            between_bytes_timeout: Duration::from_secs(5),
+            pool: None,
+            tls: Default::default(),
+            policy: Default::default(),
+        };
+        let response = transport.send_request_handler(request, config).await?;
+        let header = response.resp.headers()["x-path"]
+            .to_str()
+            .unwrap()
+            .to_string();
+        let body = response.resp.into_body().collect().await?.to_bytes();
+        Ok::<_, ErrorCode>((header, body))
+    };
+
+    let dir = tempfile::tempdir()?;
+    let path = dir.path().join("recording.har");
+    let recorder = ReplayTransport::record(&path);
+    for (path, body) in [("/a", "1"), ("/b", "2"), ("/a", "3")] {
+        let (header, response) = send(recorder.clone(), path, body)
+            .await
+            .map_err(|e| anyhow!("{e:?}"))?;
+        assert_eq!(header, path);
+        assert_eq!(response, format!("{path} {body}"));
+    }
+    server.abort();
+
+    let recording = Recording::from_file(&path)?;
+    assert_eq!(recording.entries.len(), 3);
+    assert_eq!(recording.entries[1].response.content.text, "/b 2");
+
+    // The server is gone, so responses can only come from the recording.
+    let replayer = ReplayTransport::replay_file(&path)?;
+    for (path, body) in [("/a", "3"), ("/b", "2"), ("/a", "1")] {
+        let (header, response) = send(replayer.clone(), path, body)
+            .await
+            .map_err(|e| anyhow!("{e:?}"))?;
+        assert_eq!(header, path);
+        assert_eq!(response, format!("{path} {body}"));
+    }
+    let result = send(replayer.clone(), "/a", "4").await;
+    assert!(
+        matches!(result, Err(ErrorCode::InternalError(_))),
+        "{result:?}"
+    );
+
+    // Without a recorded body, requests match on the method and URL alone.
+    let mut recording = Recording::from_file(&path)?;
+    recording.entries[1].request.post_data = None;
+    let replayer = ReplayTransport::replay(recording);
+    let (_, response) = send(replayer, "/b", "5")
+        .await
+        .map_err(|e| anyhow!("{e:?}"))?;
+    assert_eq!(response, "/b 2");
+
+    Ok(())
+}
+
 mod body {
     use http_body_util::{combinators::BoxBody, BodyExt, Empty, Full};
     use hyper::body::Bytes;
@@ -0,0 +1,141 @@
+use crate::Error;
+use std::collections::HashMap;
+use std::sync::Mutex;
+
+/// A storage backend for the buckets of a `wasi:keyvalue` store.
+///
+/// Backends are registered under an identifier with
+/// [`WasiKeyValueCtxBuilder::backend`](crate::WasiKeyValueCtxBuilder::backend)
+/// and back every bucket that a guest opens with that identifier. The same
+/// backend may be registered with the contexts of several stores, in which
+/// case each store observes the writes of the others.
+pub trait KeyValueBackend: Send + Sync {
+    /// Returns the value associated with `key`, if any.
+    fn get(&self, key: &str) -> Result<Option<Vec<u8>>, Error>;
+
+    /// Sets the value associated with `key`, replacing any previous value.
+    fn set(&self, key: &str, value: Vec<u8>) -> Result<(), Error>;
+
+    /// Removes `key` and its value, doing nothing if `key` doesn't exist.
+    fn delete(&self, key: &str) -> Result<(), Error>;
+
+    /// Returns whether `key` is associated with a value.
+    fn exists(&self, key: &str) -> Result<bool, Error> {
+        Ok(self.get(key)?.is_some())
+    }
+
+    /// Returns all keys that are associated with a value.
+    fn keys(&self) -> Result<Vec<String>, Error>;
+
+    /// Atomically adds `delta` to the value associated with `key` and returns
+    /// the new value.
+    ///
+    /// Values are stored as decimal strings and a missing key is treated as
+    /// zero.
+    fn increment(&self, key: &str, delta: u64) -> Result<u64, Error>;
+
+    /// Atomically sets the value associated with `key` to `value` if its
+    /// current value is `current`, where `None` means that `key` must not
+    /// exist.
+    ///
+    /// Returns whether the value was set.
+    fn compare_and_swap(
+        &self,
+        key: &str,
+        current: Option<&[u8]>,
+        value: Vec<u8>,
+    ) -> Result<bool, Error>;
+}
+
+/// Computes the result of [`KeyValueBackend::increment`] for the current
+/// `value` of a key.
+pub(crate) fn increment_value(value: Option<&[u8]>, delta: u64) -> Result<u64, Error> {
+    let current = match value {
+        Some(value) => std::str::from_utf8(value)
+            .map_err(|e| Error::Other(e.to_string()))?
+            .parse::<u64>()
+            .map_err(|e| Error::Other(e.to_string()))?,
+        None => 0,
+    };
+    current
+        .checked_add(delta)
+        .ok_or_else(|| Error::Other("integer overflow".to_string()))
+}
+
+/// A [`KeyValueBackend`] which keeps its data in memory.
+///
+/// Wrap this in an [`Arc`](std::sync::Arc) and register it with multiple
+/// contexts to share its data between stores. The data is lost once the
+/// backend is dropped.
+#[derive(Default)]
+pub struct InMemoryBackend {
+    data: Mutex<HashMap<String, Vec<u8>>>,
+}
+
+impl InMemoryBackend {
+    /// Creates a new, empty backend.
+    pub fn new() -> Self {
+        Default::default()
+    }
+
+    /// Creates a new backend populated with `data`.
+    pub fn with_data<I, K, V>(data: I) -> Self
+    where
+        I: IntoIterator<Item = (K, V)>,
+        K: Into<String>,
+        V: Into<Vec<u8>>,
+    {
+        Self {
+            data: Mutex::new(
+                data.into_iter()
+                    .map(|(k, v)| (k.into(), v.into()))
+                    .collect(),
+            ),
+        }
+    }
+}
+
+impl KeyValueBackend for InMemoryBackend {
+    fn get(&self, key: &str) -> Result<Option<Vec<u8>>, Error> {
+        Ok(self.data.lock().unwrap().get(key).cloned())
+    }
+
+    fn set(&self, key: &str, value: Vec<u8>) -> Result<(), Error> {
+        self.data.lock().unwrap().insert(key.to_string(), value);
+        Ok(())
+    }
+
+    fn delete(&self, key: &str) -> Result<(), Error> {
+        self.data.lock().unwrap().remove(key);
+        Ok(())
+    }
+
+    fn exists(&self, key: &str) -> Result<bool, Error> {
+        Ok(self.data.lock().unwrap().contains_key(key))
+    }
+
+    fn keys(&self) -> Result<Vec<String>, Error> {
+        Ok(self.data.lock().unwrap().keys().cloned().collect())
+    }
+
+    fn increment(&self, key: &str, delta: u64) -> Result<u64, Error> {
+        let mut data = self.data.lock().unwrap();
+        let value = increment_value(data.get(key).map(|v| &v[..]), delta)?;
+        data.insert(key.to_string(), value.to_string().into_bytes());
+        Ok(value)
+    }
+
+    fn compare_and_swap(
+        &self,
+        key: &str,
+        current: Option<&[u8]>,
+        value: Vec<u8>,
+    ) -> Result<bool, Error> {
+        let mut data = self.data.lock().unwrap();
+        if data.get(key).map(|v| &v[..]) != current {
+            return Ok(false);
+        }
+        data.insert(key.to_string(), value);
+        Ok(true)
+    }
+}
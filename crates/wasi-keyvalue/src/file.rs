@@ -0,0 +1,272 @@
+use crate::backend::{increment_value, KeyValueBackend};
+use crate::Error;
+use anyhow::{bail, Context, Result};
+use std::collections::HashMap;
+use std::ffi::OsString;
+use std::fs::{self, File, OpenOptions};
+use std::io::{Read, Seek, SeekFrom, Write};
+use std::path::{Path, PathBuf};
+use std::sync::Mutex;
+
+/// Identifies a file as a log written by [`FileBackend`], including the
+/// version of its format.
+const MAGIC: &[u8; 8] = b"wasmkv\0\x01";
+
+const RECORD_SET: u8 = 0;
+const RECORD_DELETE: u8 = 1;
+
+/// Don't bother compacting logs with less garbage than this.
+const COMPACT_THRESHOLD: u64 = 1 << 20;
+
+/// A [`KeyValueBackend`] which persists its data to a file.
+///
+/// Every write is appended to a log in the file and flushed to disk before
+/// it's acknowledged, so data survives restarts of the host. A write that was
+/// torn by a crash is discarded the next time the file is opened. The log is
+/// compacted when opened and whenever most of it is made up of overwritten or
+/// deleted values.
+///
+/// The whole data set is also kept in memory. A file must not be opened by
+/// more than one backend at a time, whether in the same process or not.
+pub struct FileBackend {
+    state: Mutex<FileState>,
+}
+
+struct FileState {
+    path: PathBuf,
+    file: File,
+    data: HashMap<String, Vec<u8>>,
+    /// The size of the log, in bytes.
+    log_len: u64,
+    /// The number of bytes of the log that hold the records of `data`.
+    live_len: u64,
+}
+
+impl FileBackend {
+    /// Opens the backend stored at `path`, creating the file if it doesn't
+    /// exist yet.
+    pub fn open(path: impl AsRef<Path>) -> Result<Self> {
+        let path = path.as_ref();
+        let mut file = OpenOptions::new()
+            .read(true)
+            .write(true)
+            .create(true)
+            .truncate(false)
+            .open(path)
+            .with_context(|| format!("failed to open `{}`", path.display()))?;
+        let mut contents = Vec::new();
+        file.read_to_end(&mut contents)
+            .with_context(|| format!("failed to read `{}`", path.display()))?;
+
+        let mut data = HashMap::new();
+        let log_len = if contents.is_empty() {
+            file.write_all(MAGIC)?;
+            file.sync_data()?;
+            MAGIC.len()
+        } else {
+            if !contents.starts_with(MAGIC) {
+                bail!("`{}` is not a key-value store", path.display());
+            }
+            let valid_len = replay(&contents[MAGIC.len()..], &mut data)? + MAGIC.len();
+            if valid_len < contents.len() {
+                file.set_len(valid_len as u64)?;
+                file.seek(SeekFrom::Start(valid_len as u64))?;
+                file.sync_data()?;
+            }
+            valid_len
+        };
+
+        let mut state = FileState {
+            path: path.to_path_buf(),
+            file,
+            live_len: data.iter().map(|(k, v)| record_len(k, Some(v))).sum(),
+            data,
+            log_len: log_len as u64,
+        };
+        state
+            .compact()
+            .with_context(|| format!("failed to compact `{}`", path.display()))?;
+        Ok(Self {
+            state: Mutex::new(state),
+        })
+    }
+}
+
+/// Applies the records in `log` to `data`, returning the length of the
+/// complete records.
+fn replay(mut log: &[u8], data: &mut HashMap<String, Vec<u8>>) -> Result<usize> {
+    let total = log.len();
+    loop {
+        let start = total - log.len();
+        let Some((&tag, rest)) = log.split_first() else {
+            return Ok(start);
+        };
+        let Some((key, rest)) = read_bytes(rest) else {
+            return Ok(start);
+        };
+        let key = std::str::from_utf8(key).context("key-value store is corrupt")?;
+        match tag {
+            RECORD_SET => {
+                let Some((value, rest)) = read_bytes(rest) else {
+                    return Ok(start);
+                };
+                data.insert(key.to_string(), value.to_vec());
+                log = rest;
+            }
+            RECORD_DELETE => {
+                data.remove(key);
+                log = rest;
+            }
+            _ => bail!("key-value store is corrupt"),
+        }
+    }
+}
+
+/// Splits a length-prefixed byte string off the front of `bytes`.
+fn read_bytes(bytes: &[u8]) -> Option<(&[u8], &[u8])> {
+    let (len, rest) = bytes.split_first_chunk::<4>()?;
+    let len = u32::from_le_bytes(*len) as usize;
+    (rest.len() >= len).then(|| rest.split_at(len))
+}
+
+fn write_bytes(record: &mut Vec<u8>, bytes: &[u8]) -> Result<(), Error> {
+    let len = u32::try_from(bytes.len())
+        .map_err(|_| Error::Other("key or value is too large".to_string()))?;
+    record.extend_from_slice(&len.to_le_bytes());
+    record.extend_from_slice(bytes);
+    Ok(())
+}
+
+/// Encodes setting `key` to `value`, or deleting it if `value` is `None`.
+fn encode(record: &mut Vec<u8>, key: &str, value: Option<&[u8]>) -> Result<(), Error> {
+    match value {
+        Some(value) => {
+            record.push(RECORD_SET);
+            write_bytes(record, key.as_bytes())?;
+            write_bytes(record, value)?;
+        }
+        None => {
+            record.push(RECORD_DELETE);
+            write_bytes(record, key.as_bytes())?;
+        }
+    }
+    Ok(())
+}
+
+fn record_len(key: &str, value: Option<&[u8]>) -> u64 {
+    let value_len = value.map_or(0, |v| 4 + v.len());
+    (1 + 4 + key.len() + value_len) as u64
+}
+
+impl FileState {
+    /// Durably records setting `key` to `value`, or deleting it if `value` is
+    /// `None`, and then applies the change to `data`.
+    fn write(&mut self, key: &str, value: Option<Vec<u8>>) -> Result<(), Error> {
+        let mut record = Vec::new();
+        encode(&mut record, key, value.as_deref())?;
+        if let Err(e) = self
+            .file
+            .write_all(&record)
+            .and_then(|()| self.file.sync_data())
+        {
+            // Don't leave a partial record behind for later records to be
+            // appended to.
+            let _ = self.file.set_len(self.log_len);
+            let _ = self.file.seek(SeekFrom::Start(self.log_len));
+            return Err(Error::Other(e.to_string()));
+        }
+        self.log_len += record.len() as u64;
+
+        let old = match value {
+            Some(value) => {
+                self.live_len += record.len() as u64;
+                self.data.insert(key.to_string(), value)
+            }
+            None => self.data.remove(key),
+        };
+        if let Some(old) = old {
+            self.live_len -= record_len(key, Some(&old));
+        }
+
+        let garbage = self.log_len - MAGIC.len() as u64 - self.live_len;
+        if garbage > COMPACT_THRESHOLD && garbage > self.live_len {
+            // The write itself has already been persisted, so a failure to
+            // compact just leaves the log to grow until the next attempt.
+            let _ = self.compact();
+        }
+        Ok(())
+    }
+
+    /// Rewrites the log to only contain the records of `data`.
+    fn compact(&mut self) -> Result<()> {
+        let mut log = MAGIC.to_vec();
+        for (key, value) in &self.data {
+            encode(&mut log, key, Some(value))?;
+        }
+        if log.len() as u64 == self.log_len {
+            return Ok(());
+        }
+
+        let mut tmp_path = OsString::from(&self.path);
+        tmp_path.push(".compact");
+        let tmp_path = PathBuf::from(tmp_path);
+        let mut file = File::create(&tmp_path)?;
+        file.write_all(&log)?;
+        file.sync_all()?;
+        fs::rename(&tmp_path, &self.path)?;
+
+        // Keep appending to the new log, whose handle is left positioned at
+        // its end.
+        self.file = file;
+        self.log_len = log.len() as u64;
+        self.live_len = self.log_len - MAGIC.len() as u64;
+        Ok(())
+    }
+}
+
+impl KeyValueBackend for FileBackend {
+    fn get(&self, key: &str) -> Result<Option<Vec<u8>>, Error> {
+        Ok(self.state.lock().unwrap().data.get(key).cloned())
+    }
+
+    fn set(&self, key: &str, value: Vec<u8>) -> Result<(), Error> {
+        self.state.lock().unwrap().write(key, Some(value))
+    }
+
+    fn delete(&self, key: &str) -> Result<(), Error> {
+        let mut state = self.state.lock().unwrap();
+        if !state.data.contains_key(key) {
+            return Ok(());
+        }
+        state.write(key, None)
+    }
+
+    fn exists(&self, key: &str) -> Result<bool, Error> {
+        Ok(self.state.lock().unwrap().data.contains_key(key))
+    }
+
+    fn keys(&self) -> Result<Vec<String>, Error> {
+        Ok(self.state.lock().unwrap().data.keys().cloned().collect())
+    }
+
+    fn increment(&self, key: &str, delta: u64) -> Result<u64, Error> {
+        let mut state = self.state.lock().unwrap();
+        let value = increment_value(state.data.get(key).map(|v| &v[..]), delta)?;
+        state.write(key, Some(value.to_string().into_bytes()))?;
+        Ok(value)
+    }
+
+    fn compare_and_swap(
+        &self,
+        key: &str,
+        current: Option<&[u8]>,
+        value: Vec<u8>,
+    ) -> Result<bool, Error> {
+        let mut state = self.state.lock().unwrap();
+        if state.data.get(key).map(|v| &v[..]) != current {
+            return Ok(false);
+        }
+        state.write(key, Some(value))?;
+        Ok(true)
+    }
+}
@@ -4,8 +4,16 @@
 //! API. With this crate, the runtime can run components that call APIs in
 //! [wasi-keyvalue] and provide components with access to key-value storages.
 //!
-//! Currently supported storage backends:
-//! * In-Memory (empty identifier)
+//! Stores are provided by implementations of [`KeyValueBackend`], which are
+//! registered under the identifier that guests use to open them. This crate
+//! provides the following backends:
+//! * [`InMemoryBackend`], which is also used for the empty identifier unless
+//!   another backend is registered for it
+//! * [`FileBackend`], which persists its data to a file
+//!
+//! Changes that guests make to stores are published as [`KeyValueEvent`]s,
+//! which can be delivered to components exporting the `wasi:keyvalue/watcher`
+//! interface with [`KeyValueEvent::deliver`].
 //!
 //! # Examples
 //!
@@ -72,6 +80,7 @@ mod generated {
         trappable_imports: true,
         with: {
             "wasi:keyvalue/store/bucket": crate::Bucket,
+            "wasi:keyvalue/atomics/cas": crate::Cas,
         },
         trappable_error_type: {
             "wasi:keyvalue/store/error" => crate::Error,
@@ -79,18 +88,80 @@ mod generated {
     });
 }
 
+mod backend;
+mod file;
+mod watch;
+
+pub use self::backend::{InMemoryBackend, KeyValueBackend};
+pub use self::file::FileBackend;
+pub use self::watch::{KeyValueEvent, Subscription, WatchService, WatchServicePre};
+
 use self::generated::wasi::keyvalue;
 use anyhow::Result;
-use std::collections::HashMap;
+use std::collections::{BTreeMap, HashMap};
+use std::fmt;
+use std::sync::{Arc, Mutex, Weak};
+use tokio::sync::broadcast;
 use wasmtime::component::{Resource, ResourceTable, ResourceTableError};
 
-#[doc(hidden)]
+/// The number of events that a subscriber may fall behind by before it starts
+/// missing them.
+const EVENT_CAPACITY: usize = 1024;
+
+/// The channels over which the changes to each backend are published, keyed by
+/// the address of the backend.
+///
+/// Channels are shared by every context that the backend is registered with,
+/// including contexts built separately, and live as long as one of them.
+static CHANNELS: Mutex<BTreeMap<usize, Weak<broadcast::Sender<Change>>>> =
+    Mutex::new(BTreeMap::new());
+
+/// A change published on the channel of a backend, which subscribers tag with
+/// the identifier they know the backend by.
+#[derive(Clone)]
+struct Change {
+    key: String,
+    value: Option<Vec<u8>>,
+}
+
+/// Returns the channel over which the changes to `backend` are published.
+fn channel(backend: &Arc<dyn KeyValueBackend>) -> Arc<broadcast::Sender<Change>> {
+    let addr = Arc::as_ptr(backend).cast::<()>() as usize;
+    let mut channels = CHANNELS.lock().unwrap();
+    // Contexts hold on to their backends, so a channel that is still alive
+    // belongs to this backend rather than one freed from the same address.
+    if let Some(channel) = channels.get(&addr).and_then(Weak::upgrade) {
+        return channel;
+    }
+    channels.retain(|_, channel| channel.strong_count() > 0);
+    let channel = Arc::new(broadcast::channel(EVENT_CAPACITY).0);
+    channels.insert(addr, Arc::downgrade(&channel));
+    channel
+}
+
+/// An error returned by a `wasi:keyvalue` operation.
+#[derive(Debug)]
 pub enum Error {
+    /// The store identifier doesn't name a registered backend.
     NoSuchStore,
+    /// Access to the requested store or data was denied.
     AccessDenied,
+    /// Some implementation-specific error occurred.
     Other(String),
 }
 
+impl fmt::Display for Error {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        match self {
+            Error::NoSuchStore => f.write_str("no such store"),
+            Error::AccessDenied => f.write_str("access denied"),
+            Error::Other(e) => f.write_str(e),
+        }
+    }
+}
+
+impl std::error::Error for Error {}
+
 impl From<ResourceTableError> for Error {
     fn from(err: ResourceTableError) -> Self {
         Self::Other(err.to_string())
@@ -98,14 +169,23 @@ impl From<ResourceTableError> for Error {
 }
 
 #[doc(hidden)]
+#[derive(Clone)]
 pub struct Bucket {
-    in_memory_data: HashMap<String, Vec<u8>>,
+    identifier: String,
+    backend: Arc<dyn KeyValueBackend>,
+}
+
+#[doc(hidden)]
+pub struct Cas {
+    bucket: Bucket,
+    key: String,
+    current: Option<Vec<u8>>,
 }
 
 /// Builder-style structure used to create a [`WasiKeyValueCtx`].
 #[derive(Default)]
 pub struct WasiKeyValueCtxBuilder {
-    in_memory_data: HashMap<String, Vec<u8>>,
+    backends: HashMap<String, Arc<dyn KeyValueBackend>>,
 }
 
 impl WasiKeyValueCtxBuilder {
@@ -115,30 +195,55 @@ impl WasiKeyValueCtxBuilder {
     }
 
     /// Preset data for the In-Memory provider.
-    pub fn in_memory_data<I, K, V>(mut self, data: I) -> Self
+    ///
+    /// This registers a new [`InMemoryBackend`] holding `data` for the empty
+    /// identifier.
+    pub fn in_memory_data<I, K, V>(self, data: I) -> Self
     where
         I: IntoIterator<Item = (K, V)>,
         K: Into<String>,
         V: Into<Vec<u8>>,
     {
-        self.in_memory_data = data
-            .into_iter()
-            .map(|(k, v)| (k.into(), v.into()))
-            .collect();
+        self.backend("", Arc::new(InMemoryBackend::with_data(data)))
+    }
+
+    /// Registers `backend` to back the store named `identifier`.
+    ///
+    /// Any backend previously registered for `identifier` is replaced.
+    pub fn backend(
+        mut self,
+        identifier: impl Into<String>,
+        backend: Arc<dyn KeyValueBackend>,
+    ) -> Self {
+        self.backends.insert(identifier.into(), backend);
         self
     }
 
     /// Uses the configured context so far to construct the final [`WasiKeyValueCtx`].
-    pub fn build(self) -> WasiKeyValueCtx {
+    pub fn build(mut self) -> WasiKeyValueCtx {
+        self.backends
+            .entry(String::new())
+            .or_insert_with(|| Arc::new(InMemoryBackend::new()));
+        let events = self
+            .backends
+            .iter()
+            .map(|(identifier, backend)| (identifier.clone(), channel(backend)))
+            .collect();
         WasiKeyValueCtx {
-            in_memory_data: self.in_memory_data,
+            backends: self.backends,
+            events,
         }
     }
 }
 
 /// Capture the state necessary for use in the `wasi-keyvalue` API implementation.
+///
+/// Cloning a context is cheap and the clone shares its backends and events
+/// with the original.
+#[derive(Clone)]
 pub struct WasiKeyValueCtx {
-    in_memory_data: HashMap<String, Vec<u8>>,
+    backends: HashMap<String, Arc<dyn KeyValueBackend>>,
+    events: HashMap<String, Arc<broadcast::Sender<Change>>>,
 }
 
 impl WasiKeyValueCtx {
@@ -146,6 +251,35 @@ impl WasiKeyValueCtx {
     pub fn builder() -> WasiKeyValueCtxBuilder {
         WasiKeyValueCtxBuilder::new()
     }
+
+    /// Subscribes to the changes that guests make to the backends of this
+    /// context, through this context or any other context sharing them.
+    ///
+    /// Events are tagged with the identifier that this context registered
+    /// the changed backend under. Changes made while there are no subscribers
+    /// aren't recorded, and a subscriber which falls too far behind misses
+    /// the oldest events.
+    pub fn subscribe(&self) -> Subscription {
+        Subscription::new(
+            self.events
+                .iter()
+                .map(|(identifier, events)| (identifier.clone(), events.subscribe()))
+                .collect(),
+        )
+    }
+
+    fn publish(&self, bucket: &Bucket, key: &str, value: Option<&[u8]>) {
+        let Some(events) = self.events.get(&bucket.identifier) else {
+            return;
+        };
+        if events.receiver_count() == 0 {
+            return;
+        }
+        let _ = events.send(Change {
+            key: key.to_string(),
+            value: value.map(|v| v.to_vec()),
+        });
+    }
 }
 
 /// A wrapper capturing the needed internal `wasi-keyvalue` state.
@@ -163,11 +297,12 @@ impl<'a> WasiKeyValue<'a> {
 
 impl keyvalue::store::Host for WasiKeyValue<'_> {
     fn open(&mut self, identifier: String) -> Result<Resource<Bucket>, Error> {
-        match identifier.as_str() {
-            "" => Ok(self.table.push(Bucket {
-                in_memory_data: self.ctx.in_memory_data.clone(),
+        match self.ctx.backends.get(&identifier) {
+            Some(backend) => Ok(self.table.push(Bucket {
+                identifier,
+                backend: backend.clone(),
             })?),
-            _ => Err(Error::NoSuchStore),
+            None => Err(Error::NoSuchStore),
         }
     }
 
@@ -182,25 +317,27 @@ impl keyvalue::store::Host for WasiKeyValue<'_> {
 
 impl keyvalue::store::HostBucket for WasiKeyValue<'_> {
     fn get(&mut self, bucket: Resource<Bucket>, key: String) -> Result<Option<Vec<u8>>, Error> {
-        let bucket = self.table.get_mut(&bucket)?;
-        Ok(bucket.in_memory_data.get(&key).cloned())
+        let bucket = self.table.get(&bucket)?;
+        bucket.backend.get(&key)
     }
 
     fn set(&mut self, bucket: Resource<Bucket>, key: String, value: Vec<u8>) -> Result<(), Error> {
-        let bucket = self.table.get_mut(&bucket)?;
-        bucket.in_memory_data.insert(key, value);
+        let bucket = self.table.get(&bucket)?;
+        bucket.backend.set(&key, value.clone())?;
+        self.ctx.publish(bucket, &key, Some(&value));
         Ok(())
     }
 
     fn delete(&mut self, bucket: Resource<Bucket>, key: String) -> Result<(), Error> {
-        let bucket = self.table.get_mut(&bucket)?;
-        bucket.in_memory_data.remove(&key);
+        let bucket = self.table.get(&bucket)?;
+        bucket.backend.delete(&key)?;
+        self.ctx.publish(bucket, &key, None);
         Ok(())
     }
 
     fn exists(&mut self, bucket: Resource<Bucket>, key: String) -> Result<bool, Error> {
-        let bucket = self.table.get_mut(&bucket)?;
-        Ok(bucket.in_memory_data.contains_key(&key))
+        let bucket = self.table.get(&bucket)?;
+        bucket.backend.exists(&key)
     }
 
     fn list_keys(
@@ -208,12 +345,12 @@ impl keyvalue::store::HostBucket for WasiKeyValue<'_> {
         bucket: Resource<Bucket>,
         cursor: Option<u64>,
     ) -> Result<keyvalue::store::KeyResponse, Error> {
-        let bucket = self.table.get_mut(&bucket)?;
-        let keys: Vec<String> = bucket.in_memory_data.keys().cloned().collect();
-        let cursor = cursor.unwrap_or(0) as usize;
-        let keys_slice = &keys[cursor..];
+        let bucket = self.table.get(&bucket)?;
+        let mut keys = bucket.backend.keys()?;
+        keys.sort_unstable();
+        let cursor = usize::try_from(cursor.unwrap_or(0)).unwrap_or(usize::MAX);
         Ok(keyvalue::store::KeyResponse {
-            keys: keys_slice.to_vec(),
+            keys: keys.into_iter().skip(cursor).collect(),
             cursor: None,
         })
     }
@@ -231,18 +368,69 @@ impl keyvalue::atomics::Host for WasiKeyValue<'_> {
         key: String,
         delta: u64,
     ) -> Result<u64, Error> {
-        let bucket = self.table.get_mut(&bucket)?;
-        let value = bucket
-            .in_memory_data
-            .entry(key.clone())
-            .or_insert("0".to_string().into_bytes());
-        let current_value = String::from_utf8(value.clone())
-            .map_err(|e| Error::Other(e.to_string()))?
-            .parse::<u64>()
-            .map_err(|e| Error::Other(e.to_string()))?;
-        let new_value = current_value + delta;
-        *value = new_value.to_string().into_bytes();
-        Ok(new_value)
+        let bucket = self.table.get(&bucket)?;
+        let value = bucket.backend.increment(&key, delta)?;
+        self.ctx
+            .publish(bucket, &key, Some(value.to_string().as_bytes()));
+        Ok(value)
+    }
+
+    fn swap(
+        &mut self,
+        cas: Resource<Cas>,
+        value: Vec<u8>,
+    ) -> Result<Result<(), keyvalue::atomics::CasError>> {
+        let cas_ref = self.table.get_mut(&cas)?;
+        let swapped = match cas_ref.bucket.backend.compare_and_swap(
+            &cas_ref.key,
+            cas_ref.current.as_deref(),
+            value.clone(),
+        ) {
+            Ok(swapped) => swapped,
+            Err(e) => {
+                self.table.delete(cas)?;
+                let e = keyvalue::store::Host::convert_error(self, e)?;
+                return Ok(Err(keyvalue::atomics::CasError::StoreError(e)));
+            }
+        };
+        if !swapped {
+            // Hand the operation back, updated to the latest value, so that
+            // the guest can retry it.
+            match cas_ref.bucket.backend.get(&cas_ref.key) {
+                Ok(current) => cas_ref.current = current,
+                Err(e) => {
+                    self.table.delete(cas)?;
+                    let e = keyvalue::store::Host::convert_error(self, e)?;
+                    return Ok(Err(keyvalue::atomics::CasError::StoreError(e)));
+                }
+            }
+            return Ok(Err(keyvalue::atomics::CasError::CasFailed(cas)));
+        }
+        let cas = self.table.delete(cas)?;
+        self.ctx.publish(&cas.bucket, &cas.key, Some(&value));
+        Ok(Ok(()))
+    }
+}
+
+impl keyvalue::atomics::HostCas for WasiKeyValue<'_> {
+    fn new(&mut self, bucket: Resource<Bucket>, key: String) -> Result<Resource<Cas>, Error> {
+        let bucket = self.table.get(&bucket)?.clone();
+        let current = bucket.backend.get(&key)?;
+        Ok(self.table.push(Cas {
+            bucket,
+            key,
+            current,
+        })?)
+    }
+
+    fn current(&mut self, cas: Resource<Cas>) -> Result<Option<Vec<u8>>, Error> {
+        let cas = self.table.get(&cas)?;
+        Ok(cas.current.clone())
+    }
+
+    fn drop(&mut self, cas: Resource<Cas>) -> Result<()> {
+        self.table.delete(cas)?;
+        Ok(())
     }
 }
 
@@ -252,16 +440,10 @@ impl keyvalue::batch::Host for WasiKeyValue<'_> {
         bucket: Resource<Bucket>,
         keys: Vec<String>,
     ) -> Result<Vec<Option<(String, Vec<u8>)>>, Error> {
-        let bucket = self.table.get_mut(&bucket)?;
-        Ok(keys
-            .into_iter()
-            .map(|key| {
-                bucket
-                    .in_memory_data
-                    .get(&key)
-                    .map(|value| (key.clone(), value.clone()))
-            })
-            .collect())
+        let bucket = self.table.get(&bucket)?;
+        keys.into_iter()
+            .map(|key| Ok(bucket.backend.get(&key)?.map(|value| (key, value))))
+            .collect()
     }
 
     fn set_many(
@@ -269,17 +451,19 @@ impl keyvalue::batch::Host for WasiKeyValue<'_> {
         bucket: Resource<Bucket>,
         key_values: Vec<(String, Vec<u8>)>,
     ) -> Result<(), Error> {
-        let bucket = self.table.get_mut(&bucket)?;
+        let bucket = self.table.get(&bucket)?;
         for (key, value) in key_values {
-            bucket.in_memory_data.insert(key, value);
+            bucket.backend.set(&key, value.clone())?;
+            self.ctx.publish(bucket, &key, Some(&value));
         }
         Ok(())
     }
 
     fn delete_many(&mut self, bucket: Resource<Bucket>, keys: Vec<String>) -> Result<(), Error> {
-        let bucket = self.table.get_mut(&bucket)?;
+        let bucket = self.table.get(&bucket)?;
         for key in keys {
-            bucket.in_memory_data.remove(&key);
+            bucket.backend.delete(&key)?;
+            self.ctx.publish(bucket, &key, None);
         }
         Ok(())
     }
@@ -0,0 +1,150 @@
+use crate::{Bucket, Change, WasiKeyValue};
+use anyhow::Result;
+use std::future::{poll_fn, Future};
+use std::task::Poll;
+use tokio::sync::broadcast::{
+    self,
+    error::{RecvError, TryRecvError},
+};
+use wasmtime::AsContextMut;
+
+#[allow(missing_docs)]
+mod generated {
+    wasmtime::component::bindgen!({
+        path: "wit",
+        world: "wasi:keyvalue/watch-service",
+        async: {
+            only_imports: [],
+        },
+        with: {
+            "wasi:keyvalue/store": crate::generated::wasi::keyvalue::store,
+            "wasi:keyvalue/atomics": crate::generated::wasi::keyvalue::atomics,
+            "wasi:keyvalue/batch": crate::generated::wasi::keyvalue::batch,
+        },
+    });
+}
+
+pub use self::generated::{WatchService, WatchServicePre};
+
+/// A change to a key in a `wasi:keyvalue` store.
+///
+/// Events are published for changes that guests make through a
+/// [`WasiKeyValueCtx`](crate::WasiKeyValueCtx) and can be received with
+/// [`WasiKeyValueCtx::subscribe`](crate::WasiKeyValueCtx::subscribe).
+#[derive(Clone, Debug)]
+pub struct KeyValueEvent {
+    /// The identifier of the store that was changed.
+    pub identifier: String,
+    /// The key that was changed.
+    pub key: String,
+    /// The new value of the key, or `None` if it was deleted.
+    pub value: Option<Vec<u8>>,
+}
+
+/// A subscription to the changes made to the stores of a
+/// [`WasiKeyValueCtx`](crate::WasiKeyValueCtx), created with
+/// [`WasiKeyValueCtx::subscribe`](crate::WasiKeyValueCtx::subscribe).
+pub struct Subscription {
+    receivers: Vec<(String, broadcast::Receiver<Change>)>,
+}
+
+impl Subscription {
+    pub(crate) fn new(receivers: Vec<(String, broadcast::Receiver<Change>)>) -> Self {
+        Self { receivers }
+    }
+
+    /// Waits for the next change to any of the subscribed stores.
+    ///
+    /// Returns [`RecvError::Lagged`] if the subscription fell too far behind
+    /// and missed changes, and [`RecvError::Closed`] once every context
+    /// sharing the stores has been dropped.
+    pub async fn recv(&mut self) -> Result<KeyValueEvent, RecvError> {
+        let mut recvs = self
+            .receivers
+            .iter_mut()
+            .map(|(identifier, events)| {
+                Some(Box::pin(async move { (&*identifier, events.recv().await) }))
+            })
+            .collect::<Vec<_>>();
+        poll_fn(|cx| {
+            let mut open = false;
+            for slot in recvs.iter_mut() {
+                let Some(recv) = slot else { continue };
+                match recv.as_mut().poll(cx) {
+                    Poll::Ready((_, Err(RecvError::Closed))) => *slot = None,
+                    Poll::Ready((identifier, result)) => {
+                        return Poll::Ready(result.map(|change| change.into_event(identifier)))
+                    }
+                    Poll::Pending => open = true,
+                }
+            }
+            if open {
+                Poll::Pending
+            } else {
+                Poll::Ready(Err(RecvError::Closed))
+            }
+        })
+        .await
+    }
+
+    /// Returns the next change to any of the subscribed stores if one has
+    /// already been made, without waiting.
+    pub fn try_recv(&mut self) -> Result<KeyValueEvent, TryRecvError> {
+        let mut result = Err(TryRecvError::Closed);
+        for (identifier, events) in self.receivers.iter_mut() {
+            match events.try_recv() {
+                Ok(change) => return Ok(change.into_event(identifier)),
+                Err(TryRecvError::Closed) => {}
+                Err(TryRecvError::Empty) => result = Err(TryRecvError::Empty),
+                Err(e @ TryRecvError::Lagged(_)) => return Err(e),
+            }
+        }
+        result
+    }
+}
+
+impl Change {
+    fn into_event(self, identifier: &str) -> KeyValueEvent {
+        KeyValueEvent {
+            identifier: identifier.to_string(),
+            key: self.key,
+            value: self.value,
+        }
+    }
+}
+
+impl KeyValueEvent {
+    /// Delivers this event to the `wasi:keyvalue/watcher` handlers exported by
+    /// `service`.
+    ///
+    /// The handler is given a bucket for the changed store, which is opened
+    /// with the context returned by `f`. Events for stores which that context
+    /// doesn't have are ignored.
+    pub async fn deliver<T: Send>(
+        &self,
+        mut store: impl AsContextMut<Data = T>,
+        service: &WatchService,
+        f: impl Fn(&mut T) -> WasiKeyValue<'_>,
+    ) -> Result<()> {
+        let mut store = store.as_context_mut();
+        let bucket = {
+            let view = f(store.data_mut());
+            let Some(backend) = view.ctx.backends.get(&self.identifier) else {
+                return Ok(());
+            };
+            view.table.push(Bucket {
+                identifier: self.identifier.clone(),
+                backend: backend.clone(),
+            })?
+        };
+        let watcher = service.wasi_keyvalue_watcher();
+        match &self.value {
+            Some(value) => {
+                watcher
+                    .call_on_set(&mut store, bucket, &self.key, value)
+                    .await
+            }
+            None => watcher.call_on_delete(&mut store, bucket, &self.key).await,
+        }
+    }
+}
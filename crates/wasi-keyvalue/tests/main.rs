@@ -1,11 +1,17 @@
 use anyhow::{anyhow, Result};
-use test_programs_artifacts::{foreach_keyvalue, KEYVALUE_MAIN_COMPONENT};
+use std::sync::Arc;
+use test_programs_artifacts::{
+    foreach_keyvalue, KEYVALUE_CAS_COMPONENT, KEYVALUE_MAIN_COMPONENT, KEYVALUE_WATCHER_COMPONENT,
+};
 use wasmtime::{
     component::{Component, Linker, ResourceTable},
-    Store,
+    Engine, Store,
 };
 use wasmtime_wasi::{bindings::Command, WasiCtx, WasiCtxBuilder, WasiView};
-use wasmtime_wasi_keyvalue::{WasiKeyValue, WasiKeyValueCtx, WasiKeyValueCtxBuilder};
+use wasmtime_wasi_keyvalue::{
+    FileBackend, InMemoryBackend, KeyValueBackend, WasiKeyValue, WasiKeyValueCtx,
+    WasiKeyValueCtxBuilder, WatchService,
+};
 
 struct Ctx {
     table: ResourceTable,
@@ -23,18 +29,22 @@ impl WasiView for Ctx {
     }
 }
 
+fn linker(engine: &Engine) -> Result<Linker<Ctx>> {
+    let mut linker = Linker::new(engine);
+    wasmtime_wasi::add_to_linker_async(&mut linker)?;
+    wasmtime_wasi_keyvalue::add_to_linker(&mut linker, |h: &mut Ctx| {
+        WasiKeyValue::new(&h.wasi_keyvalue_ctx, &mut h.table)
+    })?;
+    Ok(linker)
+}
+
 async fn run_wasi(path: &str, ctx: Ctx) -> Result<()> {
     let engine = test_programs_artifacts::engine(|config| {
         config.async_support(true);
     });
     let mut store = Store::new(&engine, ctx);
     let component = Component::from_file(&engine, path)?;
-
-    let mut linker = Linker::new(&engine);
-    wasmtime_wasi::add_to_linker_async(&mut linker)?;
-    wasmtime_wasi_keyvalue::add_to_linker(&mut linker, |h: &mut Ctx| {
-        WasiKeyValue::new(&h.wasi_keyvalue_ctx, &mut h.table)
-    })?;
+    let linker = linker(&engine)?;
 
     let command = Command::instantiate_async(&mut store, &component, &linker).await?;
     command
@@ -67,3 +77,177 @@ async fn keyvalue_main() -> Result<()> {
     )
     .await
 }
+
+#[tokio::test(flavor = "multi_thread")]
+async fn keyvalue_cas() -> Result<()> {
+    run_wasi(
+        KEYVALUE_CAS_COMPONENT,
+        Ctx {
+            table: ResourceTable::new(),
+            wasi_ctx: WasiCtxBuilder::new().inherit_stderr().build(),
+            wasi_keyvalue_ctx: WasiKeyValueCtxBuilder::new().build(),
+        },
+    )
+    .await
+}
+
+#[tokio::test(flavor = "multi_thread")]
+async fn keyvalue_watcher() -> Result<()> {
+    let backend = Arc::new(InMemoryBackend::with_data([("atomics_key", "5")]));
+    let kv = WasiKeyValueCtxBuilder::new()
+        .backend("", backend.clone())
+        .build();
+    let mut events = kv.subscribe();
+    let ctx = || Ctx {
+        table: ResourceTable::new(),
+        wasi_ctx: WasiCtxBuilder::new().inherit_stderr().build(),
+        wasi_keyvalue_ctx: kv.clone(),
+    };
+
+    run_wasi(KEYVALUE_MAIN_COMPONENT, ctx()).await?;
+
+    let engine = test_programs_artifacts::engine(|config| {
+        config.async_support(true);
+    });
+    let mut store = Store::new(&engine, ctx());
+    let component = Component::from_file(&engine, KEYVALUE_WATCHER_COMPONENT)?;
+    let watcher =
+        WatchService::instantiate_async(&mut store, &component, &linker(&engine)?).await?;
+
+    // Deliver the changes made by the other instance, leaving behind the ones
+    // the watcher makes itself.
+    let mut delivered = 0;
+    while let Ok(event) = events.try_recv() {
+        if event.key.starts_with("seen:") {
+            continue;
+        }
+        event
+            .deliver(&mut store, &watcher, |h: &mut Ctx| {
+                WasiKeyValue::new(&h.wasi_keyvalue_ctx, &mut h.table)
+            })
+            .await?;
+        delivered += 1;
+    }
+    assert_eq!(delivered, 8);
+
+    let seen = |key: &str| backend.get(&format!("seen:{key}")).unwrap();
+    assert_eq!(seen("atomics_key").as_deref(), Some(&b"6"[..]));
+    assert_eq!(seen("hello").as_deref(), Some(&b"deleted"[..]));
+    assert_eq!(seen("a1").as_deref(), Some(&b"deleted"[..]));
+    assert_eq!(seen("b1").as_deref(), Some(&b"v1"[..]));
+    Ok(())
+}
+
+#[tokio::test(flavor = "multi_thread")]
+async fn keyvalue_events_shared_backend() -> Result<()> {
+    let backend: Arc<dyn KeyValueBackend> =
+        Arc::new(InMemoryBackend::with_data([("atomics_key", "5")]));
+    let writer = WasiKeyValueCtxBuilder::new()
+        .backend("", backend.clone())
+        .build();
+    let watcher = WasiKeyValueCtxBuilder::new()
+        .backend("shared", backend.clone())
+        .build();
+    let unrelated = WasiKeyValueCtxBuilder::new().build();
+    let mut events = watcher.subscribe();
+    let mut unrelated_events = unrelated.subscribe();
+
+    run_wasi(
+        KEYVALUE_MAIN_COMPONENT,
+        Ctx {
+            table: ResourceTable::new(),
+            wasi_ctx: WasiCtxBuilder::new().inherit_stderr().build(),
+            wasi_keyvalue_ctx: writer,
+        },
+    )
+    .await?;
+
+    // Changes made through one context reach the subscribers of a separately
+    // built context sharing the backend, under that context's identifier.
+    let mut received = 0;
+    while let Ok(event) = events.try_recv() {
+        assert_eq!(event.identifier, "shared");
+        received += 1;
+    }
+    assert_eq!(received, 8);
+    assert!(unrelated_events.try_recv().is_err());
+    Ok(())
+}
+
+#[tokio::test(flavor = "multi_thread")]
+async fn keyvalue_main_shared_backend() -> Result<()> {
+    let backend = Arc::new(InMemoryBackend::with_data([("atomics_key", "5")]));
+    run_wasi(
+        KEYVALUE_MAIN_COMPONENT,
+        Ctx {
+            table: ResourceTable::new(),
+            wasi_ctx: WasiCtxBuilder::new().inherit_stderr().build(),
+            wasi_keyvalue_ctx: WasiKeyValueCtxBuilder::new()
+                .backend("", backend.clone())
+                .build(),
+        },
+    )
+    .await?;
+
+    // The guest's writes are visible to anyone else sharing the backend.
+    assert_eq!(backend.get("atomics_key")?.as_deref(), Some(&b"6"[..]));
+    assert_eq!(backend.get("b1")?.as_deref(), Some(&b"v1"[..]));
+    assert!(!backend.exists("hello")?);
+    Ok(())
+}
+
+#[tokio::test(flavor = "multi_thread")]
+async fn keyvalue_main_file_backend() -> Result<()> {
+    let dir = tempfile::tempdir()?;
+    let path = dir.path().join("store.kv");
+    FileBackend::open(&path)?.set("atomics_key", b"5".to_vec())?;
+
+    run_wasi(
+        KEYVALUE_MAIN_COMPONENT,
+        Ctx {
+            table: ResourceTable::new(),
+            wasi_ctx: WasiCtxBuilder::new().inherit_stderr().build(),
+            wasi_keyvalue_ctx: WasiKeyValueCtxBuilder::new()
+                .backend("", Arc::new(FileBackend::open(&path)?))
+                .build(),
+        },
+    )
+    .await?;
+
+    let backend = FileBackend::open(&path)?;
+    let mut keys = backend.keys()?;
+    keys.sort();
+    assert_eq!(keys, ["atomics_key", "b1"]);
+    assert_eq!(backend.get("atomics_key")?.as_deref(), Some(&b"6"[..]));
+    assert_eq!(backend.get("b1")?.as_deref(), Some(&b"v1"[..]));
+    Ok(())
+}
+
+#[test]
+fn file_backend_discards_torn_writes() -> Result<()> {
+    let dir = tempfile::tempdir()?;
+    let path = dir.path().join("store.kv");
+    let backend = FileBackend::open(&path)?;
+    backend.set("a", b"1".to_vec())?;
+    backend.set("b", b"2".to_vec())?;
+    drop(backend);
+
+    // Chop off the end of the last record as if the host crashed while
+    // writing it.
+    let len = std::fs::metadata(&path)?.len();
+    std::fs::OpenOptions::new()
+        .write(true)
+        .open(&path)?
+        .set_len(len - 1)?;
+
+    let backend = FileBackend::open(&path)?;
+    assert_eq!(backend.get("a")?.as_deref(), Some(&b"1"[..]));
+    assert!(!backend.exists("b")?);
+    backend.set("c", b"3".to_vec())?;
+    drop(backend);
+
+    let backend = FileBackend::open(&path)?;
+    assert_eq!(backend.get("a")?.as_deref(), Some(&b"1"[..]));
+    assert_eq!(backend.get("c")?.as_deref(), Some(&b"3"[..]));
+    Ok(())
+}
@@ -6,6 +6,8 @@
 pub mod onnx;
 #[cfg(feature = "openvino")]
 pub mod openvino;
+#[cfg(feature = "tract")]
+pub mod tract;
 #[cfg(all(feature = "winml", target_os = "windows"))]
 pub mod winml;
 
@@ -13,6 +15,8 @@ pub mod winml;
 use self::onnx::OnnxBackend;
 #[cfg(feature = "openvino")]
 use self::openvino::OpenvinoBackend;
+#[cfg(feature = "tract")]
+use self::tract::TractBackend;
 #[cfg(all(feature = "winml", target_os = "windows"))]
 use self::winml::WinMLBackend;
 
@@ -25,6 +29,9 @@ use thiserror::Error;
 use wiggle::GuestError;
 
 /// Return a list of all available backend frameworks.
+///
+/// Both the `onnx` and `tract` backends load ONNX graphs; when both are
+/// enabled, only the former is listed (see [`by_name`]).
 pub fn list() -> Vec<Backend> {
     let mut backends = vec![];
     #[cfg(feature = "openvino")]
@@ -39,9 +46,27 @@ pub fn list() -> Vec<Backend> {
     {
         backends.push(Backend::from(OnnxBackend::default()));
     }
+    #[cfg(all(feature = "tract", not(feature = "onnx")))]
+    {
+        backends.push(Backend::from(TractBackend::default()));
+    }
     backends
 }
 
+/// Return the backend with the given name, if it is available.
+///
+/// Backends are named after their graph encoding (e.g., `openvino`), except
+/// for `tract`, which is named after the library so that it can be selected
+/// over the `onnx` backend.
+pub fn by_name(name: &str) -> Option<Backend> {
+    #[cfg(feature = "tract")]
+    if name.eq_ignore_ascii_case("tract") {
+        return Some(Backend::from(TractBackend::default()));
+    }
+    let encoding: GraphEncoding = name.parse().ok()?;
+    list().into_iter().find(|b| b.encoding() == encoding)
+}
+
 /// A [Backend] contains the necessary state to load [Graph]s.
 pub trait BackendInner: Send + Sync {
     fn encoding(&self) -> GraphEncoding;
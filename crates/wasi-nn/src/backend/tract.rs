@@ -0,0 +1,310 @@
+//! Implements a `wasi-nn` [`BackendInner`] for ONNX models using the pure-Rust
+//! `tract` inference engine; unlike the other backends, this one requires no
+//! native libraries and runs on the CPU of any platform.
+
+use super::{BackendError, BackendExecutionContext, BackendFromDir, BackendGraph, BackendInner};
+use crate::backend::{read, Id};
+use crate::wit::types::{ExecutionTarget, GraphEncoding, Tensor, TensorType};
+use crate::{ExecutionContext, Graph};
+use anyhow::{anyhow, Context};
+use std::path::Path;
+use std::sync::Arc;
+use tract_onnx::prelude::{
+    tvec, DatumType, Framework, InferenceModelExt, TValue, TypedFact, TypedModel,
+    TypedRunnableModel,
+};
+
+#[derive(Default)]
+pub struct TractBackend();
+
+impl BackendInner for TractBackend {
+    fn encoding(&self) -> GraphEncoding {
+        GraphEncoding::Onnx
+    }
+
+    fn load(&mut self, builders: &[&[u8]], target: ExecutionTarget) -> Result<Graph, BackendError> {
+        if builders.len() != 1 {
+            return Err(BackendError::InvalidNumberOfBuilders(1, builders.len()).into());
+        }
+        if target != ExecutionTarget::Cpu {
+            return Err(BackendError::BackendAccess(anyhow!(
+                "tract only supports the CPU execution target, not {target:?}"
+            )));
+        }
+
+        let model = tract_onnx::onnx()
+            .model_for_read(&mut &builders[0][..])
+            .context("failed to parse ONNX model")?
+            .into_optimized()
+            .context("failed to optimize ONNX model")?;
+
+        // Record the names, types and dimensions of the inputs and outputs
+        // before the model is turned into an execution plan; they are needed
+        // for looking up tensors by name and for validating inputs.
+        let mut inputs = vec![];
+        for (i, outlet) in model.input_outlets()?.iter().enumerate() {
+            let name = model.node(outlet.node).name.clone();
+            inputs.push(Shape::from_fact(name, model.input_fact(i)?)?);
+        }
+        let mut outputs = vec![];
+        for outlet in model.output_outlets()? {
+            let name = model
+                .outlet_label(*outlet)
+                .unwrap_or(&model.node(outlet.node).name)
+                .to_string();
+            outputs.push(Shape::from_fact(name, model.outlet_fact(*outlet)?)?);
+        }
+
+        let plan = model
+            .into_runnable()
+            .context("failed to create tract execution plan")?;
+        let box_: Box<dyn BackendGraph> = Box::new(TractGraph {
+            plan: Arc::new(plan),
+            inputs: inputs.into(),
+            outputs: outputs.into(),
+        });
+        Ok(box_.into())
+    }
+
+    fn as_dir_loadable<'a>(&'a mut self) -> Option<&'a mut dyn BackendFromDir> {
+        Some(self)
+    }
+}
+
+impl BackendFromDir for TractBackend {
+    fn load_from_dir(
+        &mut self,
+        path: &Path,
+        target: ExecutionTarget,
+    ) -> Result<Graph, BackendError> {
+        let model = read(&path.join("model.onnx"))?;
+        self.load(&[&model], target)
+    }
+}
+
+struct TractGraph {
+    plan: Arc<TypedRunnableModel<TypedModel>>,
+    inputs: Arc<[Shape]>,
+    outputs: Arc<[Shape]>,
+}
+
+impl BackendGraph for TractGraph {
+    fn init_execution_context(&self) -> Result<ExecutionContext, BackendError> {
+        let box_: Box<dyn BackendExecutionContext> = Box::new(TractExecutionContext {
+            plan: self.plan.clone(),
+            shapes: (self.inputs.clone(), self.outputs.clone()),
+            inputs: vec![None; self.inputs.len()],
+            outputs: vec![None; self.outputs.len()],
+        });
+        Ok(box_.into())
+    }
+}
+
+struct TractExecutionContext {
+    plan: Arc<TypedRunnableModel<TypedModel>>,
+    shapes: (Arc<[Shape]>, Arc<[Shape]>),
+    inputs: Vec<Option<Tensor>>,
+    outputs: Vec<Option<Tensor>>,
+}
+
+impl BackendExecutionContext for TractExecutionContext {
+    fn set_input(&mut self, id: Id, tensor: &Tensor) -> Result<(), BackendError> {
+        let index = find(id, &self.shapes.0)?;
+        self.shapes.0[index].matches(tensor)?;
+        // Hold the tensor data on the context until `compute` is called.
+        self.inputs[index].replace(tensor.clone());
+        Ok(())
+    }
+
+    fn compute(&mut self) -> Result<(), BackendError> {
+        let mut inputs = tvec![];
+        for (shape, tensor) in self.shapes.0.iter().zip(&self.inputs) {
+            let tensor = tensor.as_ref().ok_or_else(|| {
+                BackendError::BackendAccess(anyhow!("missing input tensor: {}", shape.name))
+            })?;
+            inputs.push(to_tract_tensor(tensor)?);
+        }
+        let outputs = self
+            .plan
+            .run(inputs)
+            .context("failed to run tract execution plan")?;
+        for (slot, output) in self.outputs.iter_mut().zip(outputs) {
+            slot.replace(from_tract_tensor(&output)?);
+        }
+        Ok(())
+    }
+
+    fn get_output(&mut self, id: Id) -> Result<Tensor, BackendError> {
+        let index = find(id, &self.shapes.1)?;
+        match &self.outputs[index] {
+            Some(tensor) => Ok(tensor.clone()),
+            None => Err(BackendError::BackendAccess(anyhow!(
+                "missing output tensor: {}; has `compute` been called?",
+                self.shapes.1[index].name
+            ))),
+        }
+    }
+}
+
+/// Find the internal index of a tensor by [`Id`].
+fn find(id: Id, shapes: &[Shape]) -> Result<usize, BackendError> {
+    match id {
+        Id::Index(i) => {
+            let i = i as usize;
+            if i < shapes.len() {
+                Ok(i)
+            } else {
+                Err(BackendError::BackendAccess(anyhow!(
+                    "incorrect tensor index: {i} >= {}",
+                    shapes.len()
+                )))
+            }
+        }
+        Id::Name(n) => shapes
+            .iter()
+            .position(|s| s.name == n)
+            .ok_or_else(|| BackendError::BackendAccess(anyhow!("unknown tensor name: {n}"))),
+    }
+}
+
+/// Describes a model input or output; dimensions that are only known once the
+/// model runs (e.g., the batch size) are `None`.
+struct Shape {
+    name: String,
+    dimensions: Vec<Option<u32>>,
+    ty: TensorType,
+}
+
+impl Shape {
+    fn from_fact(name: String, fact: &TypedFact) -> Result<Self, BackendError> {
+        let dimensions = fact
+            .shape
+            .iter()
+            .map(|d| d.to_i64().ok().and_then(|d| u32::try_from(d).ok()))
+            .collect();
+        let ty = convert_datum_type(fact.datum_type)?;
+        Ok(Self {
+            name,
+            dimensions,
+            ty,
+        })
+    }
+
+    fn matches(&self, tensor: &Tensor) -> anyhow::Result<()> {
+        let dimensions_match = self.dimensions.len() == tensor.dimensions.len()
+            && self
+                .dimensions
+                .iter()
+                .zip(&tensor.dimensions)
+                .all(|(expected, actual)| expected.map_or(true, |d| d == *actual));
+        if !dimensions_match {
+            return Err(anyhow!(
+                "input tensor dimensions do not match model: {:?} != {:?}",
+                self.dimensions,
+                tensor.dimensions
+            ));
+        }
+        if self.ty != tensor.ty {
+            return Err(anyhow!(
+                "input tensor type does not match model: {:?} != {:?}",
+                self.ty,
+                tensor.ty
+            ));
+        }
+        Ok(())
+    }
+}
+
+fn convert_datum_type(ty: DatumType) -> Result<TensorType, BackendError> {
+    match ty {
+        DatumType::F16 => Ok(TensorType::Fp16),
+        DatumType::F32 => Ok(TensorType::Fp32),
+        DatumType::F64 => Ok(TensorType::Fp64),
+        DatumType::U8 => Ok(TensorType::U8),
+        DatumType::I32 => Ok(TensorType::I32),
+        DatumType::I64 => Ok(TensorType::I64),
+        _ => Err(BackendError::UnsupportedTensorType(format!("{ty:?}"))),
+    }
+}
+
+fn convert_tensor_type(ty: TensorType) -> Result<DatumType, BackendError> {
+    match ty {
+        TensorType::Fp16 => Ok(DatumType::F16),
+        TensorType::Fp32 => Ok(DatumType::F32),
+        TensorType::Fp64 => Ok(DatumType::F64),
+        TensorType::U8 => Ok(DatumType::U8),
+        TensorType::I32 => Ok(DatumType::I32),
+        TensorType::I64 => Ok(DatumType::I64),
+        TensorType::Bf16 => Err(BackendError::UnsupportedTensorType(format!("{ty:?}"))),
+    }
+}
+
+fn to_tract_tensor(tensor: &Tensor) -> Result<TValue, BackendError> {
+    let ty = convert_tensor_type(tensor.ty)?;
+    let shape = tensor
+        .dimensions
+        .iter()
+        .map(|d| *d as usize)
+        .collect::<Vec<_>>();
+    // The dimensions come from the guest, so a product which overflows must
+    // not be allowed to wrap around to the length of the data.
+    let expected_len = shape
+        .iter()
+        .try_fold(ty.size_of(), |len, d| len.checked_mul(*d))
+        .ok_or_else(|| {
+            BackendError::BackendAccess(anyhow!(
+                "input tensor dimensions {:?} are too large",
+                tensor.dimensions
+            ))
+        })?;
+    if tensor.data.len() != expected_len {
+        return Err(BackendError::BackendAccess(anyhow!(
+            "input tensor has {} bytes of data but its dimensions {:?} require {expected_len}",
+            tensor.data.len(),
+            tensor.dimensions
+        )));
+    }
+    // SAFETY: all of the supported datum types are plain numbers for which any
+    // bit pattern is valid and the length of the data was checked above.
+    let tensor = unsafe { tract_onnx::prelude::Tensor::from_raw_dt(ty, &shape, &tensor.data) }?;
+    Ok(tensor.into())
+}
+
+fn from_tract_tensor(tensor: &tract_onnx::prelude::Tensor) -> Result<Tensor, BackendError> {
+    let ty = convert_datum_type(tensor.datum_type())?;
+    let dimensions = tensor
+        .shape()
+        .iter()
+        .map(|d| u32::try_from(*d).context("unable to convert dimension to u32"))
+        .collect::<anyhow::Result<_>>()?;
+    // SAFETY: the datum types accepted by `convert_datum_type` are plain
+    // numbers, so their in-memory representation is the tensor data.
+    let data = unsafe { tensor.as_bytes() }.to_vec();
+    Ok(Tensor {
+        dimensions,
+        ty,
+        data,
+    })
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn tensor_size_overflow() {
+        // 4 bytes * 2^31 * 2^31 * 4 is 2^66, which wraps around to 0.
+        let tensor = Tensor {
+            dimensions: vec![1 << 31, 1 << 31, 4],
+            ty: TensorType::Fp32,
+            data: Vec::new(),
+        };
+        match to_tract_tensor(&tensor) {
+            Err(BackendError::BackendAccess(e)) => {
+                assert!(e.to_string().contains("too large"), "{e}")
+            }
+            Err(e) => panic!("unexpected error: {e:?}"),
+            Ok(_) => panic!("overflowing dimensions were accepted"),
+        }
+    }
+}
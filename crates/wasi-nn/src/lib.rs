@@ -1,4 +1,5 @@
 pub mod backend;
+pub mod limits;
 mod registry;
 pub mod wit;
 pub mod witx;
@@ -10,18 +11,15 @@ use std::path::Path;
 use std::sync::Arc;
 
 /// Construct an in-memory registry from the available backends and a list of
-/// `(<backend name>, <graph directory>)`. This assumes graphs can be loaded
-/// from a local directory, which is a safe assumption currently for the current
-/// model types.
+/// `(<backend name>, <graph directory>)` (see [`backend::by_name`]). This
+/// assumes graphs can be loaded from a local directory, which is a safe
+/// assumption currently for the current model types.
 pub fn preload(preload_graphs: &[(String, String)]) -> anyhow::Result<(Vec<Backend>, Registry)> {
-    let mut backends = backend::list();
+    let backends = backend::list();
     let mut registry = InMemoryRegistry::new();
     for (kind, path) in preload_graphs {
-        let kind_ = kind.parse()?;
-        let backend = backends
-            .iter_mut()
-            .find(|b| b.encoding() == kind_)
-            .ok_or(anyhow!("unsupported backend: {}", kind))?
+        let mut backend = backend::by_name(kind).ok_or(anyhow!("unsupported backend: {}", kind))?;
+        let backend = backend
             .as_dir_loadable()
             .ok_or(anyhow!("{} does not support directory loading", kind))?;
         registry.load(backend, Path::new(path))?;
@@ -0,0 +1,336 @@
+//! Limit the resources a guest may consume through `wasi-nn`.
+//!
+//! Graphs, tensors and inferences live in host memory and run on host threads,
+//! so they are not accounted for by a store's [`ResourceLimiter`] or fuel. A
+//! [`Limits`] caps what each store may load and a [`ComputeBudget`] lets
+//! embedders charge the time spent in inference to whatever budget they
+//! maintain. Violations are reported to the guest as `too-large` `wasi-nn`
+//! errors, except for an exhausted budget, which is reported as `timeout` by
+//! the WIT interface and as `busy` by the witx one, which has no `timeout`
+//! error code. Tensors created by the guest through the WIT interface's tensor
+//! constructor can't fail with an error code, so oversized ones trap instead.
+//!
+//! [`ResourceLimiter`]: wasmtime::ResourceLimiter
+
+use crate::backend::BackendError;
+use std::collections::HashMap;
+use std::time::{Duration, Instant};
+use thiserror::Error;
+
+/// Per-store limits on `wasi-nn` resources; `None` means unlimited, which is
+/// the default.
+#[derive(Clone, Debug, Default)]
+pub struct Limits {
+    /// The maximum total size, in bytes, of the graph builders of all graphs
+    /// that the guest has loaded and not yet dropped. Graphs retrieved by name
+    /// from the registry are not counted.
+    pub max_graph_bytes: Option<usize>,
+    /// The maximum size, in bytes, of the data of any tensor passed to or
+    /// returned from an inference.
+    pub max_tensor_bytes: Option<usize>,
+    /// The maximum number of execution contexts that may exist at once.
+    pub max_execution_contexts: Option<usize>,
+}
+
+/// A hook for charging the time spent computing inferences to an
+/// embedder-defined budget, e.g., to a store's fuel.
+///
+/// Host calls cannot access the [`Store`](wasmtime::Store), so an embedder
+/// metering with fuel would typically accumulate the charged time here and
+/// convert it to fuel when control returns to the host.
+pub trait ComputeBudget: Send + Sync {
+    /// Called before every inference; returning an error fails the inference
+    /// without running it.
+    fn start(&mut self) -> anyhow::Result<()> {
+        Ok(())
+    }
+
+    /// Called after every inference, successful or not, with the time it took;
+    /// returning an error fails the inference.
+    fn charge(&mut self, elapsed: Duration) -> anyhow::Result<()>;
+}
+
+/// Errors returned when a guest exceeds its [`Limits`] or [`ComputeBudget`].
+#[derive(Debug, Error)]
+pub enum LimitError {
+    #[error("loading {requested} bytes of graphs would exceed the limit of {limit} bytes")]
+    GraphBytes { requested: usize, limit: usize },
+    #[error("a tensor of {size} bytes exceeds the limit of {limit} bytes")]
+    TensorBytes { size: usize, limit: usize },
+    #[error("cannot create more than {0} execution contexts")]
+    ExecutionContexts(usize),
+    #[error("compute budget exhausted")]
+    Budget(#[source] anyhow::Error),
+}
+
+/// Errors from [`Accounting::compute`]: either the backend failed or the
+/// budget refused the inference.
+#[derive(Debug, Error)]
+pub(crate) enum ComputeError {
+    #[error(transparent)]
+    Backend(#[from] BackendError),
+    #[error(transparent)]
+    Limit(#[from] LimitError),
+}
+
+/// Tracks the `wasi-nn` resources used by one store against its [`Limits`].
+#[derive(Default)]
+pub(crate) struct Accounting {
+    limits: Limits,
+    budget: Option<Box<dyn ComputeBudget>>,
+    /// The sizes of the live graphs loaded by the guest, by handle.
+    graphs: HashMap<u32, usize>,
+    graph_bytes: usize,
+    execution_contexts: usize,
+}
+
+impl Accounting {
+    pub(crate) fn set_limits(&mut self, limits: Limits) {
+        self.limits = limits;
+    }
+
+    pub(crate) fn set_compute_budget(&mut self, budget: Box<dyn ComputeBudget>) {
+        self.budget = Some(budget);
+    }
+
+    /// Check that a graph of `bytes` can be loaded; this should happen before
+    /// the backend starts loading it.
+    pub(crate) fn check_graph(&self, bytes: usize) -> Result<(), LimitError> {
+        match self.limits.max_graph_bytes {
+            Some(limit) if self.graph_bytes.saturating_add(bytes) > limit => {
+                Err(LimitError::GraphBytes {
+                    requested: bytes,
+                    limit,
+                })
+            }
+            _ => Ok(()),
+        }
+    }
+
+    /// Record that the graph with handle `id` and of `bytes` was loaded.
+    pub(crate) fn add_graph(&mut self, id: u32, bytes: usize) {
+        self.graphs.insert(id, bytes);
+        self.graph_bytes += bytes;
+    }
+
+    /// Record that the graph with handle `id` was dropped.
+    pub(crate) fn remove_graph(&mut self, id: u32) {
+        if let Some(bytes) = self.graphs.remove(&id) {
+            self.graph_bytes -= bytes;
+        }
+    }
+
+    /// Check that another execution context can be created.
+    pub(crate) fn check_execution_context(&self) -> Result<(), LimitError> {
+        match self.limits.max_execution_contexts {
+            Some(limit) if self.execution_contexts >= limit => {
+                Err(LimitError::ExecutionContexts(limit))
+            }
+            _ => Ok(()),
+        }
+    }
+
+    pub(crate) fn add_execution_context(&mut self) {
+        self.execution_contexts += 1;
+    }
+
+    pub(crate) fn remove_execution_context(&mut self) {
+        self.execution_contexts = self.execution_contexts.saturating_sub(1);
+    }
+
+    /// Check that a tensor with `size` bytes of data is allowed.
+    pub(crate) fn check_tensor(&self, size: usize) -> Result<(), LimitError> {
+        match self.limits.max_tensor_bytes {
+            Some(limit) if size > limit => Err(LimitError::TensorBytes { size, limit }),
+            _ => Ok(()),
+        }
+    }
+
+    /// Run an inference, charging the time it takes to the budget.
+    pub(crate) fn compute(
+        &mut self,
+        compute: impl FnOnce() -> Result<(), BackendError>,
+    ) -> Result<(), ComputeError> {
+        let Some(budget) = &mut self.budget else {
+            return Ok(compute()?);
+        };
+        budget.start().map_err(LimitError::Budget)?;
+        let start = Instant::now();
+        let result = compute();
+        budget.charge(start.elapsed()).map_err(LimitError::Budget)?;
+        Ok(result?)
+    }
+}
+
+/// A backend and limits for testing that the host bindings enforce limits.
+#[cfg(test)]
+pub(crate) mod stub {
+    use super::{ComputeBudget, Limits};
+    use crate::backend::{
+        BackendError, BackendExecutionContext, BackendFromDir, BackendGraph, BackendInner, Id,
+    };
+    use crate::wit::{ExecutionTarget, GraphEncoding, TensorType};
+    use crate::{ExecutionContext, Graph, Tensor};
+    use std::time::Duration;
+
+    /// The limit on graph and tensor sizes set by [`limits`].
+    pub(crate) const MAX_BYTES: usize = 16;
+
+    /// Limits allowing graphs and tensors of [`MAX_BYTES`] and a single
+    /// execution context.
+    pub(crate) fn limits() -> Limits {
+        Limits {
+            max_graph_bytes: Some(MAX_BYTES),
+            max_tensor_bytes: Some(MAX_BYTES),
+            max_execution_contexts: Some(1),
+        }
+    }
+
+    /// A budget which refuses every inference.
+    pub(crate) struct Exhausted;
+
+    impl ComputeBudget for Exhausted {
+        fn start(&mut self) -> anyhow::Result<()> {
+            anyhow::bail!("no time left")
+        }
+
+        fn charge(&mut self, _: Duration) -> anyhow::Result<()> {
+            Ok(())
+        }
+    }
+
+    /// A backend for the `autodetect` encoding whose inferences do nothing and
+    /// produce outputs larger than [`MAX_BYTES`].
+    pub(crate) struct StubBackend;
+
+    impl BackendInner for StubBackend {
+        fn encoding(&self) -> GraphEncoding {
+            GraphEncoding::Autodetect
+        }
+
+        fn load(&mut self, _: &[&[u8]], _: ExecutionTarget) -> Result<Graph, BackendError> {
+            let graph: Box<dyn BackendGraph> = Box::new(StubGraph);
+            Ok(graph.into())
+        }
+
+        fn as_dir_loadable(&mut self) -> Option<&mut dyn BackendFromDir> {
+            None
+        }
+    }
+
+    struct StubGraph;
+
+    impl BackendGraph for StubGraph {
+        fn init_execution_context(&self) -> Result<ExecutionContext, BackendError> {
+            let context: Box<dyn BackendExecutionContext> = Box::new(StubExecutionContext);
+            Ok(context.into())
+        }
+    }
+
+    struct StubExecutionContext;
+
+    impl BackendExecutionContext for StubExecutionContext {
+        fn set_input(&mut self, _: Id, _: &Tensor) -> Result<(), BackendError> {
+            Ok(())
+        }
+
+        fn compute(&mut self) -> Result<(), BackendError> {
+            Ok(())
+        }
+
+        fn get_output(&mut self, _: Id) -> Result<Tensor, BackendError> {
+            Ok(Tensor {
+                dimensions: vec![MAX_BYTES as u32 + 1],
+                ty: TensorType::U8,
+                data: vec![0; MAX_BYTES + 1],
+            })
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn graph_bytes() {
+        let mut accounting = Accounting::default();
+        accounting.set_limits(Limits {
+            max_graph_bytes: Some(100),
+            ..Limits::default()
+        });
+        assert!(accounting.check_graph(60).is_ok());
+        accounting.add_graph(0, 60);
+        assert!(accounting.check_graph(40).is_ok());
+        assert!(matches!(
+            accounting.check_graph(41),
+            Err(LimitError::GraphBytes { requested: 41, .. })
+        ));
+        accounting.remove_graph(0);
+        assert!(accounting.check_graph(100).is_ok());
+        // Graphs which were never counted are ignored.
+        accounting.remove_graph(1);
+        assert!(accounting.check_graph(100).is_ok());
+    }
+
+    #[test]
+    fn execution_contexts() {
+        let mut accounting = Accounting::default();
+        accounting.set_limits(Limits {
+            max_execution_contexts: Some(1),
+            ..Limits::default()
+        });
+        assert!(accounting.check_execution_context().is_ok());
+        accounting.add_execution_context();
+        assert!(accounting.check_execution_context().is_err());
+        accounting.remove_execution_context();
+        assert!(accounting.check_execution_context().is_ok());
+    }
+
+    #[test]
+    fn tensor_bytes() {
+        let mut accounting = Accounting::default();
+        assert!(accounting.check_tensor(usize::MAX).is_ok());
+        accounting.set_limits(Limits {
+            max_tensor_bytes: Some(16),
+            ..Limits::default()
+        });
+        assert!(accounting.check_tensor(16).is_ok());
+        assert!(accounting.check_tensor(17).is_err());
+    }
+
+    #[test]
+    fn compute_budget() {
+        struct Calls(u32);
+        impl ComputeBudget for Calls {
+            fn start(&mut self) -> anyhow::Result<()> {
+                if self.0 == 0 {
+                    anyhow::bail!("no calls left");
+                }
+                Ok(())
+            }
+            fn charge(&mut self, _: Duration) -> anyhow::Result<()> {
+                self.0 -= 1;
+                Ok(())
+            }
+        }
+
+        let mut accounting = Accounting::default();
+        accounting.set_compute_budget(Box::new(Calls(1)));
+        let mut computed = 0;
+        assert!(accounting
+            .compute(|| {
+                computed += 1;
+                Ok(())
+            })
+            .is_ok());
+        assert!(matches!(
+            accounting.compute(|| {
+                computed += 1;
+                Ok(())
+            }),
+            Err(ComputeError::Limit(LimitError::Budget(_)))
+        ));
+        assert_eq!(computed, 1);
+    }
+}
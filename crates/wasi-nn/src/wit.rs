@@ -16,6 +16,7 @@
 //! [`types`]: crate::wit::types
 
 use crate::backend::Id;
+use crate::limits::{Accounting, ComputeBudget, ComputeError, LimitError, Limits};
 use crate::{Backend, Registry};
 use anyhow::anyhow;
 use std::collections::HashMap;
@@ -27,13 +28,28 @@ use wasmtime::component::{Resource, ResourceTable};
 pub struct WasiNnCtx {
     pub(crate) backends: HashMap<GraphEncoding, Backend>,
     pub(crate) registry: Registry,
+    pub(crate) accounting: Accounting,
 }
 
 impl WasiNnCtx {
     /// Make a new context from the default state.
     pub fn new(backends: impl IntoIterator<Item = Backend>, registry: Registry) -> Self {
         let backends = backends.into_iter().map(|b| (b.encoding(), b)).collect();
-        Self { backends, registry }
+        Self {
+            backends,
+            registry,
+            accounting: Accounting::default(),
+        }
+    }
+
+    /// Limit the resources the guest may use through this context.
+    pub fn set_limits(&mut self, limits: Limits) {
+        self.accounting.set_limits(limits);
+    }
+
+    /// Charge the time spent computing inferences to `budget`.
+    pub fn set_compute_budget(&mut self, budget: impl ComputeBudget + 'static) {
+        self.accounting.set_compute_budget(Box::new(budget));
     }
 }
 
@@ -169,11 +185,16 @@ impl gen::graph::Host for WasiNnView<'_> {
         target: ExecutionTarget,
     ) -> wasmtime::Result<Result<Resource<Graph>, Resource<Error>>> {
         tracing::debug!("load {encoding:?} {target:?}");
+        let bytes = builders.iter().map(|b| b.len()).sum();
+        if let Err(error) = self.ctx.accounting.check_graph(bytes) {
+            bail!(self, ErrorCode::TooLarge, error);
+        }
         if let Some(backend) = self.ctx.backends.get_mut(&encoding) {
             let slices = builders.iter().map(|s| s.as_slice()).collect::<Vec<_>>();
             match backend.load(&slices, target.into()) {
                 Ok(graph) => {
                     let graph = self.table.push(graph)?;
+                    self.ctx.accounting.add_graph(graph.rep(), bytes);
                     Ok(Ok(graph))
                 }
                 Err(error) => {
@@ -217,10 +238,14 @@ impl gen::graph::HostGraph for WasiNnView<'_> {
     ) -> wasmtime::Result<Result<Resource<GraphExecutionContext>, Resource<Error>>> {
         use core::result::Result::*;
         tracing::debug!("initialize execution context");
+        if let Err(error) = self.ctx.accounting.check_execution_context() {
+            bail!(self, ErrorCode::TooLarge, error);
+        }
         let graph = self.table.get(&graph)?;
         match graph.init_execution_context() {
             Ok(exec_context) => {
                 let exec_context = self.table.push(exec_context)?;
+                self.ctx.accounting.add_execution_context();
                 Ok(Ok(exec_context))
             }
             Err(error) => {
@@ -230,6 +255,7 @@ impl gen::graph::HostGraph for WasiNnView<'_> {
     }
 
     fn drop(&mut self, graph: Resource<Graph>) -> wasmtime::Result<()> {
+        self.ctx.accounting.remove_graph(graph.rep());
         self.table.delete(graph)?;
         Ok(())
     }
@@ -244,6 +270,9 @@ impl gen::inference::HostGraphExecutionContext for WasiNnView<'_> {
     ) -> wasmtime::Result<Result<(), Resource<Error>>> {
         let tensor = self.table.get(&tensor)?;
         tracing::debug!("set input {name:?}: {tensor:?}");
+        if let Err(error) = self.ctx.accounting.check_tensor(tensor.data.len()) {
+            bail!(self, ErrorCode::TooLarge, error);
+        }
         let tensor = tensor.clone(); // TODO: avoid copying the tensor
         let exec_context = self.table.get_mut(&exec_context)?;
         if let Err(error) = exec_context.set_input(Id::Name(name), &tensor) {
@@ -259,8 +288,11 @@ impl gen::inference::HostGraphExecutionContext for WasiNnView<'_> {
     ) -> wasmtime::Result<Result<(), Resource<Error>>> {
         let exec_context = &mut self.table.get_mut(&exec_context)?;
         tracing::debug!("compute");
-        match exec_context.compute() {
+        match self.ctx.accounting.compute(|| exec_context.compute()) {
             Ok(()) => Ok(Ok(())),
+            Err(ComputeError::Limit(error @ LimitError::Budget(_))) => {
+                bail!(self, ErrorCode::Timeout, error);
+            }
             Err(error) => {
                 bail!(self, ErrorCode::RuntimeError, error);
             }
@@ -276,6 +308,9 @@ impl gen::inference::HostGraphExecutionContext for WasiNnView<'_> {
         tracing::debug!("get output {name:?}");
         match exec_context.get_output(Id::Name(name)) {
             Ok(tensor) => {
+                if let Err(error) = self.ctx.accounting.check_tensor(tensor.data.len()) {
+                    bail!(self, ErrorCode::TooLarge, error);
+                }
                 let tensor = self.table.push(tensor)?;
                 Ok(Ok(tensor))
             }
@@ -286,6 +321,7 @@ impl gen::inference::HostGraphExecutionContext for WasiNnView<'_> {
     }
 
     fn drop(&mut self, exec_context: Resource<GraphExecutionContext>) -> wasmtime::Result<()> {
+        self.ctx.accounting.remove_execution_context();
         self.table.delete(exec_context)?;
         Ok(())
     }
@@ -298,6 +334,8 @@ impl gen::tensor::HostTensor for WasiNnView<'_> {
         ty: TensorType,
         data: TensorData,
     ) -> wasmtime::Result<Resource<Tensor>> {
+        // Constructors can't return a `wasi-nn` error, so trap instead.
+        self.ctx.accounting.check_tensor(data.len())?;
         let tensor = Tensor {
             dimensions,
             ty,
@@ -410,3 +448,72 @@ impl fmt::Display for GraphEncodingParseError {
     }
 }
 impl std::error::Error for GraphEncodingParseError {}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::limits::stub::{self, StubBackend, MAX_BYTES};
+    use crate::InMemoryRegistry;
+    use gen::errors::{ErrorCode, HostError};
+    use gen::graph::{Host, HostGraph};
+    use gen::inference::HostGraphExecutionContext;
+    use gen::tensor::HostTensor;
+
+    /// Returns the code of the `wasi-nn` error that `result` failed with.
+    fn code<T: std::fmt::Debug>(
+        view: &mut WasiNnView<'_>,
+        result: Result<T, Resource<Error>>,
+    ) -> ErrorCode {
+        view.code(result.expect_err("expected a wasi-nn error"))
+            .unwrap()
+    }
+
+    #[test]
+    fn limits() -> wasmtime::Result<()> {
+        let mut table = ResourceTable::new();
+        let mut ctx = WasiNnCtx::new([Backend::from(StubBackend)], InMemoryRegistry::new().into());
+        ctx.set_limits(stub::limits());
+        ctx.set_compute_budget(stub::Exhausted);
+        let mut view = WasiNnView::new(&mut table, &mut ctx);
+        let target = ExecutionTarget::Cpu;
+
+        let result = view.load(
+            vec![vec![0; MAX_BYTES + 1]],
+            GraphEncoding::Autodetect,
+            target,
+        )?;
+        assert_eq!(code(&mut view, result), ErrorCode::TooLarge);
+        let graph = view
+            .load(vec![vec![0; MAX_BYTES]], GraphEncoding::Autodetect, target)?
+            .unwrap();
+
+        let exec = view
+            .init_execution_context(Resource::new_borrow(graph.rep()))?
+            .unwrap();
+        let result = view.init_execution_context(Resource::new_borrow(graph.rep()))?;
+        assert_eq!(code(&mut view, result), ErrorCode::TooLarge);
+
+        // Oversized tensors can't be reported as errors by the constructor.
+        assert!(HostTensor::new(
+            &mut view,
+            vec![MAX_BYTES as u32 + 1],
+            TensorType::U8,
+            vec![0; MAX_BYTES + 1]
+        )
+        .is_err());
+        let tensor = view.table.push(Tensor {
+            dimensions: vec![MAX_BYTES as u32 + 1],
+            ty: TensorType::U8,
+            data: vec![0; MAX_BYTES + 1],
+        })?;
+        let result = view.set_input(Resource::new_borrow(exec.rep()), "input".into(), tensor)?;
+        assert_eq!(code(&mut view, result), ErrorCode::TooLarge);
+
+        let result = view.compute(Resource::new_borrow(exec.rep()))?;
+        assert_eq!(code(&mut view, result), ErrorCode::Timeout);
+
+        let result = view.get_output(Resource::new_borrow(exec.rep()), "output".into())?;
+        assert_eq!(code(&mut view, result), ErrorCode::TooLarge);
+        Ok(())
+    }
+}
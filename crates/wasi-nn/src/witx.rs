@@ -15,6 +15,7 @@
 
 use crate::backend::BackendError;
 use crate::backend::Id;
+use crate::limits::{Accounting, ComputeBudget, ComputeError, LimitError, Limits};
 use crate::wit::GraphEncoding;
 use crate::{Backend, ExecutionContext, Graph, Registry};
 use std::collections::HashMap;
@@ -35,6 +36,7 @@ pub struct WasiNnCtx {
     pub(crate) registry: Registry,
     pub(crate) graphs: Table<GraphId, Graph>,
     pub(crate) executions: Table<GraphExecutionContextId, ExecutionContext>,
+    pub(crate) accounting: Accounting,
 }
 
 impl WasiNnCtx {
@@ -46,8 +48,22 @@ impl WasiNnCtx {
             registry,
             graphs: Table::default(),
             executions: Table::default(),
+            accounting: Accounting::default(),
         }
     }
+
+    /// Limit the resources the guest may use through this context.
+    ///
+    /// This ABI has no way to drop graphs or execution contexts, so every
+    /// graph loaded and execution context created counts towards the limits.
+    pub fn set_limits(&mut self, limits: Limits) {
+        self.accounting.set_limits(limits);
+    }
+
+    /// Charge the time spent computing inferences to `budget`.
+    pub fn set_compute_budget(&mut self, budget: impl ComputeBudget + 'static) {
+        self.accounting.set_compute_budget(Box::new(budget));
+    }
 }
 
 /// Record handle entries in a table.
@@ -118,6 +134,10 @@ mod gen {
                 WasiNnError::GuestError(_) => unimplemented!("guest error conversion"),
                 WasiNnError::UsageError(_) => Ok(types::NnErrno::UnsupportedOperation),
                 WasiNnError::NotEnoughMemory(_) => Ok(types::NnErrno::TooLarge),
+                // The closest witx equivalent of the WIT interface's
+                // `timeout`.
+                WasiNnError::LimitError(LimitError::Budget(_)) => Ok(types::NnErrno::Busy),
+                WasiNnError::LimitError(_) => Ok(types::NnErrno::TooLarge),
             }
         }
     }
@@ -132,6 +152,7 @@ impl gen::wasi_ephemeral_nn::WasiEphemeralNn for WasiNnCtx {
         encoding: gen::types::GraphEncoding,
         target: gen::types::ExecutionTarget,
     ) -> Result<gen::types::Graph> {
+        let bytes;
         let graph = if let Some(backend) = self.backends.get_mut(&encoding.into()) {
             // Retrieve all of the "builder lists" from the Wasm memory (see
             // $graph_builder_array) as slices for a backend to operate on.
@@ -144,12 +165,15 @@ impl gen::wasi_ephemeral_nn::WasiEphemeralNn for WasiNnCtx {
                 );
                 slices.push(slice);
             }
-            let slice_refs = slices.iter().map(|s| s.as_ref()).collect::<Vec<_>>();
+            let slice_refs: Vec<&[u8]> = slices.iter().map(|s| s.as_ref()).collect();
+            bytes = slice_refs.iter().map(|s| s.len()).sum();
+            self.accounting.check_graph(bytes)?;
             backend.load(&slice_refs, target.into())?
         } else {
             return Err(UsageError::InvalidEncoding(encoding.into()).into());
         };
         let graph_id = self.graphs.insert(graph);
+        self.accounting.add_graph(graph_id, bytes);
         Ok(graph_id.into())
     }
 
@@ -173,12 +197,14 @@ impl gen::wasi_ephemeral_nn::WasiEphemeralNn for WasiNnCtx {
         graph_id: gen::types::Graph,
     ) -> Result<gen::types::GraphExecutionContext> {
         let exec_context = if let Some(graph) = self.graphs.get_mut(graph_id.into()) {
+            self.accounting.check_execution_context()?;
             graph.init_execution_context()?
         } else {
             return Err(UsageError::InvalidGraphHandle.into());
         };
 
         let exec_context_id = self.executions.insert(exec_context);
+        self.accounting.add_execution_context();
         Ok(exec_context_id.into())
     }
 
@@ -190,6 +216,7 @@ impl gen::wasi_ephemeral_nn::WasiEphemeralNn for WasiNnCtx {
         tensor: &gen::types::Tensor,
     ) -> Result<()> {
         if let Some(exec_context) = self.executions.get_mut(exec_context_id.into()) {
+            self.accounting.check_tensor(tensor.data.len() as usize)?;
             let tensor = crate::wit::types::Tensor {
                 dimensions: memory.to_vec(tensor.dimensions)?,
                 ty: tensor.type_.into(),
@@ -207,7 +234,7 @@ impl gen::wasi_ephemeral_nn::WasiEphemeralNn for WasiNnCtx {
         exec_context_id: gen::types::GraphExecutionContext,
     ) -> Result<()> {
         if let Some(exec_context) = self.executions.get_mut(exec_context_id.into()) {
-            Ok(exec_context.compute()?)
+            Ok(self.accounting.compute(|| exec_context.compute())?)
         } else {
             Err(UsageError::InvalidExecutionContextHandle.into())
         }
@@ -223,6 +250,7 @@ impl gen::wasi_ephemeral_nn::WasiEphemeralNn for WasiNnCtx {
     ) -> Result<u32> {
         if let Some(exec_context) = self.executions.get_mut(exec_context_id.into()) {
             let tensor = exec_context.get_output(Id::Index(index))?;
+            self.accounting.check_tensor(tensor.data.len())?;
             let destination = memory
                 .as_slice_mut(out_buffer.as_array(out_buffer_max_size))?
                 .expect(
@@ -290,6 +318,17 @@ pub enum WasiNnError {
     UsageError(#[from] UsageError),
     #[error("not enough memory: requested {0} bytes")]
     NotEnoughMemory(usize),
+    #[error("limit exceeded")]
+    LimitError(#[from] LimitError),
+}
+
+impl From<ComputeError> for WasiNnError {
+    fn from(error: ComputeError) -> Self {
+        match error {
+            ComputeError::Backend(e) => e.into(),
+            ComputeError::Limit(e) => e.into(),
+        }
+    }
 }
 
 #[derive(Debug, Error)]
@@ -303,3 +342,105 @@ pub enum UsageError {
     #[error("No graph found with name: {0}")]
     NotFound(String),
 }
+
+#[cfg(test)]
+mod tests {
+    use super::gen::{types::NnErrno, wasi_ephemeral_nn as abi};
+    use super::*;
+    use crate::limits::stub::{self, StubBackend, MAX_BYTES};
+    use crate::InMemoryRegistry;
+
+    fn write_u32(memory: &mut [u8], offset: usize, value: u32) {
+        memory[offset..offset + 4].copy_from_slice(&value.to_le_bytes());
+    }
+
+    fn read_u32(memory: &[u8], offset: usize) -> i32 {
+        u32::from_le_bytes(memory[offset..offset + 4].try_into().unwrap()) as i32
+    }
+
+    #[test]
+    fn limits() -> anyhow::Result<()> {
+        let mut ctx = WasiNnCtx::new([Backend::from(StubBackend)], InMemoryRegistry::new().into());
+        ctx.set_limits(stub::limits());
+        ctx.set_compute_budget(stub::Exhausted);
+        let mut memory = vec![0; 256];
+        let autodetect = gen::types::GraphEncoding::Autodetect as i32;
+        let cpu = gen::types::ExecutionTarget::Cpu as i32;
+        let too_large = NnErrno::TooLarge as i32;
+
+        // A graph builder array at 0 with one builder of data at 64, and the
+        // returned handles at 8 and 12.
+        write_u32(&mut memory, 0, 64);
+        write_u32(&mut memory, 4, MAX_BYTES as u32 + 1);
+        let errno = abi::load(
+            &mut ctx,
+            &mut GuestMemory::Unshared(&mut memory),
+            0,
+            1,
+            autodetect,
+            cpu,
+            8,
+        )?;
+        assert_eq!(errno, too_large);
+        write_u32(&mut memory, 4, MAX_BYTES as u32);
+        let errno = abi::load(
+            &mut ctx,
+            &mut GuestMemory::Unshared(&mut memory),
+            0,
+            1,
+            autodetect,
+            cpu,
+            8,
+        )?;
+        assert_eq!(errno, NnErrno::Success as i32);
+        let graph = read_u32(&memory, 8);
+
+        let errno = abi::init_execution_context(
+            &mut ctx,
+            &mut GuestMemory::Unshared(&mut memory),
+            graph,
+            12,
+        )?;
+        assert_eq!(errno, NnErrno::Success as i32);
+        let exec = read_u32(&memory, 12);
+        let errno = abi::init_execution_context(
+            &mut ctx,
+            &mut GuestMemory::Unshared(&mut memory),
+            graph,
+            12,
+        )?;
+        assert_eq!(errno, too_large);
+
+        // A tensor at 16 with its dimensions at 48 and its data at 64.
+        write_u32(&mut memory, 16, 48);
+        write_u32(&mut memory, 20, 1);
+        memory[24] = gen::types::TensorType::U8 as u8;
+        write_u32(&mut memory, 28, 64);
+        write_u32(&mut memory, 32, MAX_BYTES as u32 + 1);
+        write_u32(&mut memory, 48, MAX_BYTES as u32 + 1);
+        let errno = abi::set_input(
+            &mut ctx,
+            &mut GuestMemory::Unshared(&mut memory),
+            exec,
+            0,
+            16,
+        )?;
+        assert_eq!(errno, too_large);
+
+        // The witx ABI has no `timeout` error code.
+        let errno = abi::compute(&mut ctx, &mut GuestMemory::Unshared(&mut memory), exec)?;
+        assert_eq!(errno, NnErrno::Busy as i32);
+
+        let errno = abi::get_output(
+            &mut ctx,
+            &mut GuestMemory::Unshared(&mut memory),
+            exec,
+            0,
+            128,
+            128,
+            40,
+        )?;
+        assert_eq!(errno, too_large);
+        Ok(())
+    }
+}
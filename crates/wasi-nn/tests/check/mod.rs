@@ -13,7 +13,7 @@ use std::{
     sync::Mutex,
 };
 
-#[cfg(any(feature = "onnx", feature = "winml"))]
+#[cfg(any(feature = "onnx", feature = "tract", feature = "winml"))]
 pub mod onnx;
 #[cfg(feature = "openvino")]
 pub mod openvino;
@@ -83,6 +83,9 @@ fn check_test_program(name: &str) -> (fn() -> Result<()>, IgnoreCheck) {
         "nn_witx_image_classification_onnx" => {
             (nn_witx_image_classification_onnx, IgnoreCheck::for_onnx())
         }
+        "nn_witx_image_classification_tract" => {
+            (nn_witx_image_classification_tract, IgnoreCheck::for_tract())
+        }
         "nn_witx_image_classification_winml_named" => (
             nn_witx_image_classification_winml_named,
             IgnoreCheck::for_winml(),
@@ -99,6 +102,9 @@ fn check_test_program(name: &str) -> (fn() -> Result<()>, IgnoreCheck) {
         "nn_wit_image_classification_onnx" => {
             (nn_wit_image_classification_onnx, IgnoreCheck::for_onnx())
         }
+        "nn_wit_image_classification_tract" => {
+            (nn_wit_image_classification_tract, IgnoreCheck::for_tract())
+        }
         "nn_wit_image_classification_winml_named" => (
             nn_wit_image_classification_winml_named,
             IgnoreCheck::for_winml(),
@@ -132,6 +138,17 @@ fn nn_witx_image_classification_onnx() -> Result<()> {
     anyhow::bail!("this test requires the `onnx` feature")
 }
 
+#[cfg(feature = "tract")]
+fn nn_witx_image_classification_tract() -> Result<()> {
+    check::onnx::are_artifacts_available()?;
+    let backend = Backend::from(backend::tract::TractBackend::default());
+    exec::witx::run(NN_WITX_IMAGE_CLASSIFICATION_TRACT, backend, false)
+}
+#[cfg(not(feature = "tract"))]
+fn nn_witx_image_classification_tract() -> Result<()> {
+    anyhow::bail!("this test requires the `tract` feature")
+}
+
 #[cfg(all(feature = "winml", target_os = "windows"))]
 fn nn_witx_image_classification_winml_named() -> Result<()> {
     check::winml::is_available()?;
@@ -177,6 +194,17 @@ fn nn_wit_image_classification_onnx() -> Result<()> {
     anyhow::bail!("this test requires the `onnx` feature")
 }
 
+#[cfg(feature = "tract")]
+fn nn_wit_image_classification_tract() -> Result<()> {
+    check::onnx::are_artifacts_available()?;
+    let backend = Backend::from(backend::tract::TractBackend::default());
+    exec::wit::run(NN_WIT_IMAGE_CLASSIFICATION_TRACT_COMPONENT, backend, false)
+}
+#[cfg(not(feature = "tract"))]
+fn nn_wit_image_classification_tract() -> Result<()> {
+    anyhow::bail!("this test requires the `tract` feature")
+}
+
 #[cfg(all(feature = "winml", target_os = "windows"))]
 fn nn_wit_image_classification_winml_named() -> Result<()> {
     check::winml::is_available()?;
@@ -251,6 +279,15 @@ impl IgnoreCheck {
         Ignore("requires the `onnx` feature".into())
     }
 
+    fn for_tract() -> Self {
+        use IgnoreCheck::*;
+        if cfg!(feature = "tract") {
+            Run
+        } else {
+            Ignore("requires the `tract` feature".into())
+        }
+    }
+
     fn for_winml() -> IgnoreCheck {
         use IgnoreCheck::*;
         #[cfg(all(feature = "winml", target_os = "windows"))]
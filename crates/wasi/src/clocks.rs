@@ -1,5 +1,8 @@
 pub mod host;
+mod virtual_clock;
+
 use cap_std::time::Duration;
+pub(crate) use virtual_clock::VirtualClock;
 
 pub trait HostWallClock: Send {
     fn resolution(&self) -> Duration;
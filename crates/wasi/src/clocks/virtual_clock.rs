@@ -0,0 +1,163 @@
+use super::{HostMonotonicClock, HostWallClock};
+use cap_std::time::Duration;
+use std::collections::BTreeMap;
+use std::sync::atomic::{AtomicU64, Ordering};
+use std::sync::{Arc, Mutex};
+use tokio::sync::Notify;
+
+/// A clock which only moves forward when the guest waits on it.
+///
+/// All readings of the clock are in nanoseconds since it was created, which
+/// is both the monotonic instant and, for the wall clock, the Unix epoch.
+/// Rather than sleeping, a pending deadline jumps the clock forward once it's
+/// the earliest deadline being waited on, so a run's view of time doesn't
+/// depend on how fast the host happens to execute it.
+#[derive(Clone, Default)]
+pub(crate) struct VirtualClock {
+    inner: Arc<Inner>,
+}
+
+#[derive(Default)]
+struct Inner {
+    now: AtomicU64,
+    /// Deadlines currently being waited on, along with how many waiters each
+    /// one has.
+    sleepers: Mutex<BTreeMap<u64, usize>>,
+    /// Notified whenever `now` or `sleepers` changes.
+    changed: Notify,
+}
+
+impl VirtualClock {
+    pub(crate) fn new() -> Self {
+        Self::default()
+    }
+
+    fn nanos(&self) -> u64 {
+        self.inner.now.load(Ordering::SeqCst)
+    }
+
+    /// Waits until this clock reaches `when`, advancing it there if nothing
+    /// else is waiting on an earlier deadline.
+    pub(crate) async fn sleep_until(&self, when: u64) {
+        if self.nanos() >= when {
+            return;
+        }
+        let _sleeper = Sleeper::new(self, when);
+
+        // Give anything being polled alongside this deadline which is already
+        // ready, like a stream with buffered data, a chance to win before
+        // time moves.
+        tokio::task::yield_now().await;
+
+        loop {
+            let changed = self.inner.changed.notified();
+            if self.nanos() >= when {
+                return;
+            }
+            let earliest = self.inner.sleepers.lock().unwrap().keys().next().copied();
+            if earliest == Some(when) {
+                self.inner.now.fetch_max(when, Ordering::SeqCst);
+                self.inner.changed.notify_waiters();
+                return;
+            }
+            changed.await;
+        }
+    }
+}
+
+impl HostMonotonicClock for VirtualClock {
+    fn resolution(&self) -> u64 {
+        1
+    }
+
+    fn now(&self) -> u64 {
+        self.nanos()
+    }
+}
+
+impl HostWallClock for VirtualClock {
+    fn resolution(&self) -> Duration {
+        Duration::from_nanos(1)
+    }
+
+    fn now(&self) -> Duration {
+        Duration::from_nanos(self.nanos())
+    }
+}
+
+/// Registration of a pending deadline, removed when the waiting future
+/// completes or is dropped.
+struct Sleeper<'a> {
+    clock: &'a VirtualClock,
+    when: u64,
+}
+
+impl<'a> Sleeper<'a> {
+    fn new(clock: &'a VirtualClock, when: u64) -> Self {
+        *clock
+            .inner
+            .sleepers
+            .lock()
+            .unwrap()
+            .entry(when)
+            .or_insert(0) += 1;
+        Sleeper { clock, when }
+    }
+}
+
+impl Drop for Sleeper<'_> {
+    fn drop(&mut self) {
+        let mut sleepers = self.clock.inner.sleepers.lock().unwrap();
+        if let Some(count) = sleepers.get_mut(&self.when) {
+            *count -= 1;
+            if *count == 0 {
+                sleepers.remove(&self.when);
+            }
+        }
+        drop(sleepers);
+        self.clock.inner.changed.notify_waiters();
+    }
+}
+
+#[cfg(test)]
+mod test {
+    use super::*;
+    use crate::runtime::in_tokio;
+
+    #[test]
+    fn advances_to_earliest_deadline() {
+        let clock = VirtualClock::new();
+        assert_eq!(HostMonotonicClock::now(&clock), 0);
+
+        in_tokio(async {
+            let first = clock.sleep_until(100);
+            let second = clock.sleep_until(50);
+            tokio::select! {
+                _ = first => panic!("the later deadline fired first"),
+                _ = second => {}
+            }
+            assert_eq!(HostMonotonicClock::now(&clock), 50);
+
+            clock.sleep_until(100).await;
+            assert_eq!(HostMonotonicClock::now(&clock), 100);
+
+            // Deadlines in the past don't move the clock.
+            clock.sleep_until(10).await;
+            assert_eq!(HostMonotonicClock::now(&clock), 100);
+        });
+        assert_eq!(HostWallClock::now(&clock), Duration::from_nanos(100));
+    }
+
+    #[test]
+    fn ready_futures_win() {
+        let clock = VirtualClock::new();
+        in_tokio(async {
+            tokio::select! {
+                biased;
+                _ = clock.sleep_until(1_000) => panic!("the clock advanced"),
+                _ = std::future::ready(()) => {}
+            }
+        });
+        assert_eq!(HostMonotonicClock::now(&clock), 0);
+    }
+}
@@ -1,13 +1,13 @@
 use crate::{
     clocks::{
         host::{monotonic_clock, wall_clock},
-        HostMonotonicClock, HostWallClock,
+        HostMonotonicClock, HostWallClock, VirtualClock,
     },
     filesystem::{Dir, OpenMode},
-    network::{SocketAddrCheck, SocketAddrUse},
+    network::{SocketAddrCheck, SocketAddrUse, SocketPolicy, VirtualNetwork},
     pipe, random, stdio,
     stdio::{StdinStream, StdoutStream},
-    DirPerms, FilePerms,
+    DirPerms, FilePerms, HostResolver, SystemResolver, Trace, WasiDir,
 };
 use anyhow::Result;
 use cap_rand::{Rng, RngCore, SeedableRng};
@@ -46,11 +46,16 @@ pub struct WasiCtxBuilder {
     args: Vec<String>,
     preopens: Vec<(Dir, String)>,
     socket_addr_check: SocketAddrCheck,
+    ip_name_resolver: Arc<dyn HostResolver>,
+    virtual_network: Option<VirtualNetwork>,
     random: Box<dyn RngCore + Send>,
     insecure_random: Box<dyn RngCore + Send>,
     insecure_random_seed: u128,
     wall_clock: Box<dyn HostWallClock + Send>,
     monotonic_clock: Box<dyn HostMonotonicClock + Send>,
+    virtual_clock: Option<VirtualClock>,
+    deterministic: bool,
+    trace: Option<Trace>,
     allowed_network_uses: AllowedNetworkUses,
     allow_blocking_current_thread: bool,
     built: bool,
@@ -70,7 +75,8 @@ impl WasiCtxBuilder {
     /// * RNGs are all initialized with random state and suitable generator
     ///   quality to satisfy the requirements of WASI APIs.
     /// * TCP/UDP are allowed but all addresses are denied by default.
-    /// * `wasi:network/ip-name-lookup` is denied by default.
+    /// * `wasi:network/ip-name-lookup` is denied by default, and uses the
+    ///   host's resolver when allowed.
     ///
     /// These defaults can all be updated via the various builder configuration
     /// methods below.
@@ -95,11 +101,16 @@ impl WasiCtxBuilder {
             args: Vec::new(),
             preopens: Vec::new(),
             socket_addr_check: SocketAddrCheck::default(),
+            ip_name_resolver: Arc::new(SystemResolver),
+            virtual_network: None,
             random: random::thread_rng(),
             insecure_random,
             insecure_random_seed,
             wall_clock: wall_clock(),
             monotonic_clock: monotonic_clock(),
+            virtual_clock: None,
+            deterministic: false,
+            trace: None,
             allowed_network_uses: AllowedNetworkUses::default(),
             allow_blocking_current_thread: false,
             built: false,
@@ -332,6 +343,43 @@ impl WasiCtxBuilder {
         file_perms: FilePerms,
     ) -> Result<&mut Self> {
         let dir = cap_std::fs::Dir::open_ambient_dir(host_path.as_ref(), ambient_authority())?;
+        Ok(self.preopened_virtual_dir(dir, guest_path, dir_perms, file_perms))
+    }
+
+    /// Provides a virtual directory to the guest at `guest_path`.
+    ///
+    /// This is the same as [`WasiCtxBuilder::preopened_dir`] except that
+    /// rather than opening a directory on the host the guest is given `dir`,
+    /// any implementation of [`WasiDir`]. This can be used to mount an
+    /// in-memory tree with [`MemoryDir`](crate::MemoryDir), for example, or
+    /// a copy-on-write overlay on top of a host directory with
+    /// [`MemoryDir::overlay`](crate::MemoryDir::overlay).
+    ///
+    /// The `dir_perms` and `file_perms` are enforced on top of whatever the
+    /// directory itself permits.
+    ///
+    /// # Examples
+    ///
+    /// ```
+    /// use wasmtime_wasi::{WasiCtxBuilder, DirPerms, FilePerms, MemoryDir};
+    ///
+    /// # fn main() {}
+    /// # fn foo() -> std::io::Result<()> {
+    /// let dir = MemoryDir::new();
+    /// dir.write_file("config/app.toml", b"verbose = true")?;
+    ///
+    /// let mut wasi = WasiCtxBuilder::new();
+    /// wasi.preopened_virtual_dir(dir, "/etc", DirPerms::READ, FilePerms::READ);
+    /// # Ok(())
+    /// # }
+    /// ```
+    pub fn preopened_virtual_dir(
+        &mut self,
+        dir: impl WasiDir,
+        guest_path: impl AsRef<str>,
+        dir_perms: DirPerms,
+        file_perms: FilePerms,
+    ) -> &mut Self {
         let mut open_mode = OpenMode::empty();
         if dir_perms.contains(DirPerms::READ) {
             open_mode |= OpenMode::READ;
@@ -341,7 +389,7 @@ impl WasiCtxBuilder {
         }
         self.preopens.push((
             Dir::new(
-                dir,
+                Arc::new(dir),
                 dir_perms,
                 file_perms,
                 open_mode,
@@ -349,7 +397,7 @@ impl WasiCtxBuilder {
             ),
             guest_path.as_ref().to_owned(),
         ));
-        Ok(self)
+        self
     }
 
     /// Set the generator for the `wasi:random/random` number generator to the
@@ -398,6 +446,60 @@ impl WasiCtxBuilder {
     /// By default the host's monotonic clock is used.
     pub fn monotonic_clock(&mut self, clock: impl HostMonotonicClock + 'static) -> &mut Self {
         self.monotonic_clock = Box::new(clock);
+        self.virtual_clock = None;
+        self
+    }
+
+    /// Configures this context to make runs of a guest reproducible.
+    ///
+    /// This is a preset which, given the same `seed`, inputs and
+    /// configuration otherwise, makes the observable behavior of WASI the
+    /// same from one run to the next:
+    ///
+    /// * Both clocks are replaced with a virtual clock starting at zero (the
+    ///   Unix epoch for the wall clock) which only advances when the guest
+    ///   waits on it. Sleeping and polling on a deadline jumps the clock to
+    ///   that deadline instead of waiting for real time to pass.
+    /// * `wasi:random/random`, `wasi:random/insecure` and
+    ///   `wasi:random/insecure-seed` are all derived from `seed`.
+    /// * Directory entries are listed sorted by name, and all filesystem
+    ///   timestamps are reported as the Unix epoch.
+    /// * Stdio is never reported as a terminal.
+    ///
+    /// The environment variables, arguments, preopens and network access of
+    /// the context are left as configured, so they need to be fixed by the
+    /// embedder as well. For reproducible floating-point results the engine
+    /// should also have NaN canonicalization and
+    /// [`Config::relaxed_simd_deterministic`] enabled.
+    ///
+    /// Clocks and generators configured after this method will replace the
+    /// deterministic ones.
+    ///
+    /// [`Config::relaxed_simd_deterministic`]: wasmtime::Config::relaxed_simd_deterministic
+    pub fn deterministic(&mut self, seed: u64) -> &mut Self {
+        let clock = VirtualClock::new();
+        self.wall_clock = Box::new(clock.clone());
+        self.monotonic_clock = Box::new(clock.clone());
+        self.virtual_clock = Some(clock);
+
+        // `StdRng` is used for everything here as, unlike `SmallRng`, its
+        // output doesn't vary across platforms.
+        let mut rng = cap_rand::rngs::StdRng::seed_from_u64(seed);
+        self.insecure_random_seed = rng.gen();
+        self.insecure_random = Box::new(cap_rand::rngs::StdRng::from_rng(&mut rng).unwrap());
+        self.random = Box::new(cap_rand::rngs::StdRng::from_rng(&mut rng).unwrap());
+        self.deterministic = true;
+        self
+    }
+
+    /// Records the results of the nondeterministic calls that the guest makes
+    /// into `trace`, or replays them from it.
+    ///
+    /// When replaying, the arguments, environment variables, preopens and
+    /// stdin of this builder are replaced with the recorded ones. See
+    /// [`Trace`] for what's recorded.
+    pub fn trace(&mut self, trace: Trace) -> &mut Self {
+        self.trace = Some(trace);
         self
     }
 
@@ -427,6 +529,18 @@ impl WasiCtxBuilder {
         self
     }
 
+    /// Checks the socket addresses used by the guest against `policy`.
+    ///
+    /// This replaces any check configured with
+    /// [`WasiCtxBuilder::socket_addr_check`] or
+    /// [`WasiCtxBuilder::inherit_network`].
+    pub fn socket_policy(&mut self, policy: SocketPolicy) -> &mut Self {
+        self.socket_addr_check(move |addr, reason| {
+            let allowed = policy.check(addr, reason);
+            Box::pin(async move { allowed })
+        })
+    }
+
     /// Allow usage of `wasi:sockets/ip-name-lookup`
     ///
     /// By default this is disabled.
@@ -435,6 +549,28 @@ impl WasiCtxBuilder {
         self
     }
 
+    /// Configures the resolver used by `wasi:sockets/ip-name-lookup`.
+    ///
+    /// By default names are resolved with the host's resolver, see
+    /// [`SystemResolver`]. Note that lookups still have to be allowed with
+    /// [`WasiCtxBuilder::allow_ip_name_lookup`].
+    pub fn ip_name_resolver(&mut self, resolver: impl HostResolver + 'static) -> &mut Self {
+        self.ip_name_resolver = Arc::new(resolver);
+        self
+    }
+
+    /// Creates all TCP and UDP sockets on `network` instead of the host's
+    /// network.
+    ///
+    /// Sockets on a [`VirtualNetwork`] can only reach other sockets on the
+    /// same network, which may be shared with other contexts. Addresses are
+    /// still checked with [`WasiCtxBuilder::socket_addr_check`], so to allow
+    /// all of them use [`WasiCtxBuilder::inherit_network`].
+    pub fn virtual_network(&mut self, network: VirtualNetwork) -> &mut Self {
+        self.virtual_network = Some(network);
+        self
+    }
+
     /// Allow usage of UDP.
     ///
     /// This is enabled by default, but can be disabled if UDP should be blanket
@@ -474,18 +610,23 @@ impl WasiCtxBuilder {
             args,
             preopens,
             socket_addr_check,
+            ip_name_resolver,
+            virtual_network,
             random,
             insecure_random,
             insecure_random_seed,
             wall_clock,
             monotonic_clock,
+            virtual_clock,
+            deterministic,
+            trace,
             allowed_network_uses,
             allow_blocking_current_thread,
             built: _,
         } = mem::replace(self, Self::new());
         self.built = true;
 
-        WasiCtx {
+        let mut ctx = WasiCtx {
             stdin,
             stdout,
             stderr,
@@ -493,14 +634,23 @@ impl WasiCtxBuilder {
             args,
             preopens,
             socket_addr_check,
+            ip_name_resolver,
+            virtual_network,
             random,
             insecure_random,
             insecure_random_seed,
             wall_clock,
             monotonic_clock,
+            virtual_clock,
+            deterministic,
+            trace,
             allowed_network_uses,
             allow_blocking_current_thread,
+        };
+        if let Some(trace) = ctx.trace.clone() {
+            trace.install(&mut ctx);
         }
+        ctx
     }
 
     /// Builds a WASIp1 context instead of a [`WasiCtx`].
@@ -645,6 +795,9 @@ pub struct WasiCtx {
     pub(crate) insecure_random_seed: u128,
     pub(crate) wall_clock: Box<dyn HostWallClock + Send>,
     pub(crate) monotonic_clock: Box<dyn HostMonotonicClock + Send>,
+    pub(crate) virtual_clock: Option<VirtualClock>,
+    pub(crate) deterministic: bool,
+    pub(crate) trace: Option<Trace>,
     pub(crate) env: Vec<(String, String)>,
     pub(crate) args: Vec<String>,
     pub(crate) preopens: Vec<(Dir, String)>,
@@ -652,6 +805,8 @@ pub struct WasiCtx {
     pub(crate) stdout: Box<dyn StdoutStream>,
     pub(crate) stderr: Box<dyn StdoutStream>,
     pub(crate) socket_addr_check: SocketAddrCheck,
+    pub(crate) ip_name_resolver: Arc<dyn HostResolver>,
+    pub(crate) virtual_network: Option<VirtualNetwork>,
     pub(crate) allowed_network_uses: AllowedNetworkUses,
     pub(crate) allow_blocking_current_thread: bool,
 }
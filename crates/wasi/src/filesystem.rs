@@ -1,13 +1,24 @@
 use crate::bindings::filesystem::types;
 use crate::runtime::{spawn_blocking, AbortOnDropJoinHandle};
+use crate::SystemTimeSpec;
 use crate::{
     HostInputStream, HostOutputStream, StreamError, StreamResult, Subscribe, TrappableError,
 };
 use anyhow::anyhow;
 use bytes::{Bytes, BytesMut};
+use std::any::Any;
 use std::io;
 use std::mem;
+use std::path::PathBuf;
 use std::sync::Arc;
+use std::time::SystemTime;
+
+#[cfg(feature = "archive")]
+mod archive;
+mod host;
+mod memory;
+
+pub use self::memory::MemoryDir;
 
 pub type FsResult<T> = Result<T, FsError>;
 
@@ -21,6 +32,14 @@ impl From<wasmtime::component::ResourceTableError> for FsError {
 
 impl From<io::Error> for FsError {
     fn from(error: io::Error) -> Self {
+        // Errors of a trace that's being recorded or replayed aren't errors of
+        // the filesystem, so they trap instead.
+        if error
+            .get_ref()
+            .is_some_and(|e| e.is::<crate::trace::TraceError>())
+        {
+            return FsError::trap(error);
+        }
         types::ErrorCode::from(error).into()
     }
 }
@@ -76,16 +95,197 @@ bitflags::bitflags! {
     }
 }
 
+/// A directory of a filesystem which can be made available to guests.
+///
+/// This is implemented for [`cap_std::fs::Dir`], which is what
+/// [`WasiCtxBuilder::preopened_dir`](crate::WasiCtxBuilder::preopened_dir)
+/// uses, and for [`MemoryDir`]. Other implementations can be preopened with
+/// [`WasiCtxBuilder::preopened_virtual_dir`](crate::WasiCtxBuilder::preopened_virtual_dir).
+///
+/// Paths are relative to this directory, use `/` as their separator and must
+/// not escape this directory, whether through `..` or symbolic links.
+/// Permissions configured with [`DirPerms`] and [`FilePerms`] are checked
+/// before any of these methods are called.
+///
+/// All methods may block; they are only called from threads which are allowed
+/// to. Errors are converted to `wasi:filesystem` error codes, so errors created
+/// from an [`ErrorCode`](types::ErrorCode) are reported as that code.
+pub trait WasiDir: Send + Sync + 'static {
+    /// Opens the file or directory at `path`.
+    fn open_at(&self, path: &str, options: &OpenOptions) -> io::Result<Opened>;
+
+    /// Returns the metadata of this directory.
+    fn metadata(&self) -> io::Result<Metadata>;
+
+    /// Returns the metadata of the object at `path`, or of the symbolic link
+    /// itself if `path` names one and `follow_symlinks` is false.
+    fn metadata_at(&self, path: &str, follow_symlinks: bool) -> io::Result<Metadata>;
+
+    /// Returns the entries of this directory, excluding `.` and `..`.
+    fn read_dir(&self) -> io::Result<Vec<io::Result<types::DirectoryEntry>>>;
+
+    /// Creates a directory at `path`.
+    fn create_dir_at(&self, path: &str) -> io::Result<()>;
+
+    /// Removes the empty directory at `path`.
+    fn remove_dir_at(&self, path: &str) -> io::Result<()>;
+
+    /// Removes the file or symbolic link at `path`.
+    fn unlink_file_at(&self, path: &str) -> io::Result<()>;
+
+    /// Renames `old_path` to `new_path` in `new_dir`.
+    ///
+    /// Implementations only need to support `new_dir`s of their own type and
+    /// may fail with `cross-device` otherwise.
+    fn rename_at(&self, old_path: &str, new_dir: &dyn WasiDir, new_path: &str) -> io::Result<()>;
+
+    /// Creates a hard link at `new_path` in `new_dir` to the file at
+    /// `old_path`.
+    ///
+    /// Implementations only need to support `new_dir`s of their own type and
+    /// may fail with `cross-device` otherwise.
+    fn link_at(&self, old_path: &str, new_dir: &dyn WasiDir, new_path: &str) -> io::Result<()>;
+
+    /// Creates a symbolic link at `path` pointing to `target`.
+    fn symlink_at(&self, target: &str, path: &str) -> io::Result<()>;
+
+    /// Returns the target of the symbolic link at `path`.
+    fn read_link_at(&self, path: &str) -> io::Result<PathBuf>;
+
+    /// Sets the timestamps of this directory; `None` leaves one unchanged.
+    fn set_times(
+        &self,
+        atime: Option<SystemTimeSpec>,
+        mtime: Option<SystemTimeSpec>,
+    ) -> io::Result<()>;
+
+    /// Sets the timestamps of the object at `path`, or of the symbolic link
+    /// itself if `path` names one and `follow_symlinks` is false.
+    fn set_times_at(
+        &self,
+        path: &str,
+        atime: Option<SystemTimeSpec>,
+        mtime: Option<SystemTimeSpec>,
+        follow_symlinks: bool,
+    ) -> io::Result<()>;
+
+    /// Flushes changes to this directory to storage, or only those needed to
+    /// read its contents back if `data_only` is set.
+    fn sync(&self, data_only: bool) -> io::Result<()> {
+        let _ = data_only;
+        Ok(())
+    }
+
+    /// Returns `self`, so that [`WasiDir::rename_at`] and
+    /// [`WasiDir::link_at`] can downcast their `new_dir`.
+    fn as_any(&self) -> &dyn Any;
+}
+
+/// A file of a filesystem which can be made available to guests, as opened by
+/// [`WasiDir::open_at`].
+///
+/// As with [`WasiDir`], all methods may block and permissions are checked
+/// before they're called.
+pub trait WasiFile: Send + Sync + 'static {
+    /// Reads from the file at `offset` into `buf`, returning how many bytes
+    /// were read; `0` means the end of the file was reached.
+    fn read_at(&self, buf: &mut [u8], offset: u64) -> io::Result<usize>;
+
+    /// Writes `buf` to the file at `offset`, returning how many bytes were
+    /// written.
+    fn write_at(&self, buf: &[u8], offset: u64) -> io::Result<usize>;
+
+    /// Writes `buf` to the end of the file, returning how many bytes were
+    /// written.
+    fn append(&self, buf: &[u8]) -> io::Result<usize>;
+
+    /// Returns the metadata of the file.
+    fn metadata(&self) -> io::Result<Metadata>;
+
+    /// Truncates or extends the file to `size` bytes.
+    fn set_len(&self, size: u64) -> io::Result<()>;
+
+    /// Sets the timestamps of the file; `None` leaves one unchanged.
+    fn set_times(
+        &self,
+        atime: Option<SystemTimeSpec>,
+        mtime: Option<SystemTimeSpec>,
+    ) -> io::Result<()>;
+
+    /// Flushes changes to the file to storage, or only those needed to read
+    /// its contents back if `data_only` is set.
+    fn sync(&self, data_only: bool) -> io::Result<()> {
+        let _ = data_only;
+        Ok(())
+    }
+
+    /// Advises the implementation how a range of the file will be accessed.
+    fn advise(&self, offset: u64, len: u64, advice: types::Advice) -> io::Result<()> {
+        let _ = (offset, len, advice);
+        Ok(())
+    }
+}
+
+/// How [`WasiDir::open_at`] should open a file or directory.
+#[derive(Copy, Clone, Debug, Default, PartialEq, Eq)]
+pub struct OpenOptions {
+    /// Open for reading.
+    pub read: bool,
+    /// Open for writing. Directories can't be opened for writing.
+    pub write: bool,
+    /// Create a file if nothing exists at the path.
+    pub create: bool,
+    /// Fail with `exist` if something exists at the path; only used along
+    /// with `create`.
+    pub exclusive: bool,
+    /// Truncate the file to zero length.
+    pub truncate: bool,
+    /// Fail with `not-directory` if the path doesn't name a directory.
+    pub directory: bool,
+    /// Follow a symbolic link at the end of the path instead of failing with
+    /// `loop`.
+    pub follow_symlinks: bool,
+}
+
+/// A file or directory opened by [`WasiDir::open_at`].
+pub enum Opened {
+    /// A directory was opened.
+    Dir(Arc<dyn WasiDir>),
+    /// A file was opened.
+    File(Arc<dyn WasiFile>),
+}
+
+/// The metadata of a file, directory or symbolic link.
+#[derive(Clone, Debug)]
+pub struct Metadata {
+    /// The type of the object.
+    pub file_type: types::DescriptorType,
+    /// The size of the object, in bytes.
+    pub len: u64,
+    /// The number of hard links to the object.
+    pub nlink: u64,
+    /// The device, or filesystem, containing the object.
+    pub dev: u64,
+    /// The number of the object, which is unique within its `dev`.
+    pub ino: u64,
+    /// When the object was last accessed, if known.
+    pub accessed: Option<SystemTime>,
+    /// When the object's data was last modified, if known.
+    pub modified: Option<SystemTime>,
+    /// When the object's status was last changed, if known.
+    pub created: Option<SystemTime>,
+}
+
 #[derive(Clone)]
 pub struct File {
-    /// The operating system File this struct is mediating access to.
+    /// The file this struct is mediating access to.
     ///
     /// Wrapped in an Arc because the same underlying file is used for
     /// implementing the stream types. A copy is also needed for
     /// [`spawn_blocking`].
     ///
     /// [`spawn_blocking`]: Self::spawn_blocking
-    pub file: Arc<cap_std::fs::File>,
+    pub file: Arc<dyn WasiFile>,
     /// Permissions to enforce on access to the file. These permissions are
     /// specified by a user of the `crate::WasiCtxBuilder`, and are
     /// enforced prior to any enforced by the underlying operating system.
@@ -101,13 +301,13 @@ pub struct File {
 
 impl File {
     pub fn new(
-        file: cap_std::fs::File,
+        file: Arc<dyn WasiFile>,
         perms: FilePerms,
         open_mode: OpenMode,
         allow_blocking_current_thread: bool,
     ) -> Self {
         Self {
-            file: Arc::new(file),
+            file,
             perms,
             open_mode,
             allow_blocking_current_thread,
@@ -130,7 +330,7 @@ impl File {
     /// - [Implement opt-in for enabling WASI to block the current thread](https://github.com/bytecodealliance/wasmtime/pull/8190)
     pub(crate) async fn run_blocking<F, R>(&self, body: F) -> R
     where
-        F: FnOnce(&cap_std::fs::File) -> R + Send + 'static,
+        F: FnOnce(&dyn WasiFile) -> R + Send + 'static,
         R: Send + 'static,
     {
         match self.as_blocking_file() {
@@ -141,19 +341,19 @@ impl File {
 
     pub(crate) fn spawn_blocking<F, R>(&self, body: F) -> AbortOnDropJoinHandle<R>
     where
-        F: FnOnce(&cap_std::fs::File) -> R + Send + 'static,
+        F: FnOnce(&dyn WasiFile) -> R + Send + 'static,
         R: Send + 'static,
     {
         let f = self.file.clone();
-        spawn_blocking(move || body(&f))
+        spawn_blocking(move || body(&*f))
     }
 
     /// Returns `Some` when the current thread is allowed to block in filesystem
     /// operations, and otherwise returns `None` to indicate that
     /// `spawn_blocking` must be used.
-    pub(crate) fn as_blocking_file(&self) -> Option<&cap_std::fs::File> {
+    pub(crate) fn as_blocking_file(&self) -> Option<&dyn WasiFile> {
         if self.allow_blocking_current_thread {
-            Some(&self.file)
+            Some(&*self.file)
         } else {
             None
         }
@@ -179,13 +379,12 @@ bitflags::bitflags! {
 
 #[derive(Clone)]
 pub struct Dir {
-    /// The operating system file descriptor this struct is mediating access
-    /// to.
+    /// The directory this struct is mediating access to.
     ///
     /// Wrapped in an Arc because a copy is needed for [`spawn_blocking`].
     ///
     /// [`spawn_blocking`]: Self::spawn_blocking
-    pub dir: Arc<cap_std::fs::Dir>,
+    pub dir: Arc<dyn WasiDir>,
     /// Permissions to enforce on access to this directory. These permissions
     /// are specified by a user of the `crate::WasiCtxBuilder`, and
     /// are enforced prior to any enforced by the underlying operating system.
@@ -206,14 +405,14 @@ pub struct Dir {
 
 impl Dir {
     pub fn new(
-        dir: cap_std::fs::Dir,
+        dir: Arc<dyn WasiDir>,
         perms: DirPerms,
         file_perms: FilePerms,
         open_mode: OpenMode,
         allow_blocking_current_thread: bool,
     ) -> Self {
         Dir {
-            dir: Arc::new(dir),
+            dir,
             perms,
             file_perms,
             open_mode,
@@ -237,14 +436,14 @@ impl Dir {
     /// - [Implement opt-in for enabling WASI to block the current thread](https://github.com/bytecodealliance/wasmtime/pull/8190)
     pub(crate) async fn run_blocking<F, R>(&self, body: F) -> R
     where
-        F: FnOnce(&cap_std::fs::Dir) -> R + Send + 'static,
+        F: FnOnce(&dyn WasiDir) -> R + Send + 'static,
         R: Send + 'static,
     {
         if self.allow_blocking_current_thread {
-            body(&self.dir)
+            body(&*self.dir)
         } else {
             let d = self.dir.clone();
-            spawn_blocking(move || body(&d)).await
+            spawn_blocking(move || body(&*d)).await
         }
     }
 }
@@ -270,9 +469,7 @@ impl FileInputStream {
         }
     }
 
-    fn blocking_read(file: &cap_std::fs::File, offset: u64, size: usize) -> ReadState {
-        use system_interface::fs::FileIoExt;
-
+    fn blocking_read(file: &dyn WasiFile, offset: u64, size: usize) -> ReadState {
         let mut buf = BytesMut::zeroed(size);
         loop {
             match file.read_at(&mut buf, offset) {
@@ -426,12 +623,10 @@ impl FileOutputStream {
     }
 
     fn blocking_write(
-        file: &cap_std::fs::File,
+        file: &dyn WasiFile,
         mut buf: Bytes,
         mode: FileOutputMode,
     ) -> io::Result<usize> {
-        use system_interface::fs::FileIoExt;
-
         match mode {
             FileOutputMode::Position(mut p) => {
                 let mut total = 0;
@@ -0,0 +1,462 @@
+//! Building read-only in-memory filesystems out of tar and zip archives.
+
+use super::{MemoryDir, WasiDir};
+use crate::SystemTimeSpec;
+use std::io::{self, BufRead, BufReader, Read};
+use std::time::{Duration, SystemTime};
+
+/// The magic number at the start of gzip streams.
+const GZIP_MAGIC: [u8; 2] = [0x1f, 0x8b];
+
+impl MemoryDir {
+    /// Creates a read-only filesystem with the contents of the tar archive
+    /// read from `reader`, and returns its root directory.
+    ///
+    /// The archive may be compressed with gzip, which is detected
+    /// automatically. Regular files, directories, symbolic links and hard
+    /// links are extracted and other kinds of entries are ignored. Leading `/`
+    /// and `./` components of entry paths are stripped, and entries with `..`
+    /// components are rejected.
+    pub fn from_tar(reader: impl Read) -> io::Result<MemoryDir> {
+        let mut reader = BufReader::new(reader);
+        if reader.fill_buf()?.starts_with(&GZIP_MAGIC) {
+            read_tar(flate2::read::GzDecoder::new(reader))
+        } else {
+            read_tar(reader)
+        }
+    }
+
+    /// Creates a read-only filesystem with the contents of the zip archive
+    /// read from `reader`, and returns its root directory.
+    ///
+    /// Entries may be stored or compressed with deflate, and symbolic links
+    /// are recognized for archives created on Unix. Encrypted entries and
+    /// zip64 archives aren't supported. Paths are handled as with
+    /// [`MemoryDir::from_tar`]. Modification times aren't preserved, as zip
+    /// archives record them without a time zone.
+    pub fn from_zip(mut reader: impl Read) -> io::Result<MemoryDir> {
+        let mut data = Vec::new();
+        reader.read_to_end(&mut data)?;
+        read_zip(&data)
+    }
+}
+
+fn read_tar(reader: impl Read) -> io::Result<MemoryDir> {
+    use tar::EntryType;
+
+    let dir = MemoryDir::new();
+    // Times are set once everything has been extracted, as adding entries to
+    // a directory updates its modification time.
+    let mut times = Vec::new();
+    for entry in tar::Archive::new(reader).entries()? {
+        let mut entry = entry?;
+        let path = entry.path()?;
+        let path = normalize(path.to_str().ok_or_else(|| invalid("non-UTF-8 path"))?)?;
+        let link_name = match entry.link_name()? {
+            Some(name) => Some(
+                name.to_str()
+                    .ok_or_else(|| invalid("non-UTF-8 link name"))?
+                    .to_string(),
+            ),
+            None => None,
+        };
+        match entry.header().entry_type() {
+            EntryType::Regular | EntryType::Continuous => {
+                let mut contents = Vec::new();
+                entry.read_to_end(&mut contents)?;
+                dir.write_file(&path, contents)?;
+            }
+            EntryType::Directory => {
+                if path.is_empty() {
+                    continue;
+                }
+                dir.create_dir_all(&path)?;
+            }
+            EntryType::Symlink => {
+                let target = link_name.ok_or_else(|| invalid("symlink without a target"))?;
+                create_parent(&dir, &path)?;
+                dir.symlink_at(&target, &path)?;
+            }
+            EntryType::Link => {
+                let target = link_name.ok_or_else(|| invalid("hard link without a target"))?;
+                create_parent(&dir, &path)?;
+                dir.link_at(&normalize(&target)?, &dir, &path)?;
+            }
+            _ => continue,
+        }
+        if let Ok(mtime) = entry.header().mtime() {
+            times.push((path, SystemTime::UNIX_EPOCH + Duration::from_secs(mtime)));
+        }
+    }
+    for (path, mtime) in times {
+        let mtime = SystemTimeSpec::Absolute(cap_std::time::SystemTime::from_std(mtime));
+        if path.is_empty() {
+            dir.set_times(None, Some(mtime))?;
+        } else {
+            dir.set_times_at(&path, None, Some(mtime), false)?;
+        }
+    }
+    dir.freeze();
+    Ok(dir)
+}
+
+/// The signature of the end of central directory record.
+const END_OF_CENTRAL_DIRECTORY: u32 = 0x0605_4b50;
+/// The signature of central directory file headers.
+const CENTRAL_DIRECTORY_HEADER: u32 = 0x0201_4b50;
+/// The signature of local file headers.
+const LOCAL_FILE_HEADER: u32 = 0x0403_4b50;
+
+/// The fixed size of the end of central directory record.
+const END_OF_CENTRAL_DIRECTORY_LEN: usize = 22;
+/// The fixed size of central directory file headers.
+const CENTRAL_DIRECTORY_HEADER_LEN: usize = 46;
+/// The fixed size of local file headers.
+const LOCAL_FILE_HEADER_LEN: usize = 30;
+
+fn read_zip(data: &[u8]) -> io::Result<MemoryDir> {
+    // The end of central directory record is followed by a comment of at most
+    // `u16::MAX` bytes, so search backwards for it from the end.
+    let search_start = data
+        .len()
+        .checked_sub(END_OF_CENTRAL_DIRECTORY_LEN)
+        .ok_or_else(|| invalid("truncated zip archive"))?;
+    let search_end = search_start.saturating_sub(usize::from(u16::MAX));
+    let eocd = (search_end..=search_start)
+        .rev()
+        .find(|&i| u32_at(data, i) == Some(END_OF_CENTRAL_DIRECTORY))
+        .ok_or_else(|| invalid("missing end of central directory record"))?;
+    let count = u16_at(data, eocd + 10).unwrap();
+    let offset = u32_at(data, eocd + 16).unwrap();
+    if count == u16::MAX || offset == u32::MAX {
+        return Err(unsupported("zip64 archives"));
+    }
+
+    let dir = MemoryDir::new();
+    let mut pos = offset as usize;
+    for _ in 0..count {
+        let header = data
+            .get(pos..pos + CENTRAL_DIRECTORY_HEADER_LEN)
+            .ok_or_else(|| invalid("truncated central directory"))?;
+        if u32_at(header, 0) != Some(CENTRAL_DIRECTORY_HEADER) {
+            return Err(invalid("bad central directory header"));
+        }
+        let made_by = u16_at(header, 4).unwrap();
+        let flags = u16_at(header, 8).unwrap();
+        let method = u16_at(header, 10).unwrap();
+        let crc = u32_at(header, 16).unwrap();
+        let compressed_len = u32_at(header, 20).unwrap();
+        let len = u32_at(header, 24).unwrap();
+        let name_len = usize::from(u16_at(header, 28).unwrap());
+        let extra_len = usize::from(u16_at(header, 30).unwrap());
+        let comment_len = usize::from(u16_at(header, 32).unwrap());
+        let attrs = u32_at(header, 38).unwrap();
+        let local_offset = u32_at(header, 42).unwrap();
+        let name_start = pos + CENTRAL_DIRECTORY_HEADER_LEN;
+        let name = data
+            .get(name_start..name_start + name_len)
+            .ok_or_else(|| invalid("truncated central directory"))?;
+        let name = std::str::from_utf8(name).map_err(|_| invalid("non-UTF-8 path"))?;
+        pos = name_start + name_len + extra_len + comment_len;
+
+        if flags & 1 != 0 {
+            return Err(unsupported("encrypted entries"));
+        }
+        if compressed_len == u32::MAX || len == u32::MAX || local_offset == u32::MAX {
+            return Err(unsupported("zip64 archives"));
+        }
+
+        let path = normalize(name)?;
+        if name.ends_with('/') {
+            if !path.is_empty() {
+                dir.create_dir_all(&path)?;
+            }
+            continue;
+        }
+
+        // The local header repeats the name and has its own extra field, which
+        // may differ in length from the one in the central directory.
+        let local = local_offset as usize;
+        let local_header = data
+            .get(local..local + LOCAL_FILE_HEADER_LEN)
+            .ok_or_else(|| invalid("truncated local file header"))?;
+        if u32_at(local_header, 0) != Some(LOCAL_FILE_HEADER) {
+            return Err(invalid("bad local file header"));
+        }
+        let start = local
+            + LOCAL_FILE_HEADER_LEN
+            + usize::from(u16_at(local_header, 26).unwrap())
+            + usize::from(u16_at(local_header, 28).unwrap());
+        let compressed = data
+            .get(start..start + compressed_len as usize)
+            .ok_or_else(|| invalid("truncated entry"))?;
+        let contents = match method {
+            0 => compressed.to_vec(),
+            8 => {
+                // The recorded size can't be trusted until the entry has been
+                // decompressed, so don't let it pick the allocation size and
+                // stop decompressing as soon as the output is known to exceed
+                // it.
+                let mut contents = Vec::with_capacity((len as usize).min(compressed.len() * 4));
+                flate2::read::DeflateDecoder::new(compressed)
+                    .take(u64::from(len) + 1)
+                    .read_to_end(&mut contents)?;
+                contents
+            }
+            _ => return Err(unsupported("compression methods other than deflate")),
+        };
+        if contents.len() != len as usize {
+            return Err(invalid("entry size mismatch"));
+        }
+        if crc32fast::hash(&contents) != crc {
+            return Err(invalid("entry checksum mismatch"));
+        }
+
+        // Archives created on Unix keep the mode in the high half of the
+        // external attributes.
+        const UNIX: u16 = 3;
+        const S_IFMT: u32 = 0o170000;
+        const S_IFLNK: u32 = 0o120000;
+        if made_by >> 8 == UNIX && (attrs >> 16) & S_IFMT == S_IFLNK {
+            let target = String::from_utf8(contents).map_err(|_| invalid("non-UTF-8 link"))?;
+            create_parent(&dir, &path)?;
+            dir.symlink_at(&target, &path)?;
+        } else {
+            dir.write_file(&path, contents)?;
+        }
+    }
+    dir.freeze();
+    Ok(dir)
+}
+
+/// Strips the leading `/` and `.` components of the path of an entry of an
+/// archive and rejects paths that would escape the archive.
+fn normalize(path: &str) -> io::Result<String> {
+    let mut components = Vec::new();
+    for component in path.split('/') {
+        match component {
+            "" | "." => {}
+            ".." => return Err(invalid("path with `..` components")),
+            _ => components.push(component),
+        }
+    }
+    Ok(components.join("/"))
+}
+
+/// Creates the parent directories of `path`, which archives aren't required
+/// to have entries for.
+fn create_parent(dir: &MemoryDir, path: &str) -> io::Result<()> {
+    match path.rsplit_once('/') {
+        Some((parent, _)) => dir.create_dir_all(parent),
+        None => Ok(()),
+    }
+}
+
+fn u16_at(data: &[u8], offset: usize) -> Option<u16> {
+    let bytes = data.get(offset..offset + 2)?;
+    Some(u16::from_le_bytes(bytes.try_into().unwrap()))
+}
+
+fn u32_at(data: &[u8], offset: usize) -> Option<u32> {
+    let bytes = data.get(offset..offset + 4)?;
+    Some(u32::from_le_bytes(bytes.try_into().unwrap()))
+}
+
+fn invalid(msg: &str) -> io::Error {
+    io::Error::new(
+        io::ErrorKind::InvalidData,
+        format!("invalid archive: {msg}"),
+    )
+}
+
+fn unsupported(what: &str) -> io::Error {
+    io::Error::new(
+        io::ErrorKind::Unsupported,
+        format!("{what} are not supported"),
+    )
+}
+
+#[cfg(test)]
+mod test {
+    use super::*;
+
+    fn tar() -> Vec<u8> {
+        let mut builder = tar::Builder::new(Vec::new());
+        let mut header = tar::Header::new_gnu();
+        header.set_entry_type(tar::EntryType::Directory);
+        header.set_mode(0o755);
+        header.set_mtime(1_000_000);
+        header.set_size(0);
+        builder
+            .append_data(&mut header, "./etc/", io::empty())
+            .unwrap();
+
+        let mut header = tar::Header::new_gnu();
+        header.set_mode(0o644);
+        header.set_mtime(2_000_000);
+        header.set_size(5);
+        builder
+            .append_data(&mut header, "etc/hosts", &b"hello"[..])
+            .unwrap();
+
+        let mut header = tar::Header::new_gnu();
+        header.set_entry_type(tar::EntryType::Symlink);
+        header.set_size(0);
+        builder
+            .append_link(&mut header, "usr/hosts", "../etc/hosts")
+            .unwrap();
+        builder.into_inner().unwrap()
+    }
+
+    #[test]
+    fn tar_archive() {
+        let dir = MemoryDir::from_tar(&tar()[..]).unwrap();
+        assert_eq!(dir.read_file("etc/hosts").unwrap(), b"hello");
+        assert_eq!(dir.read_file("usr/hosts").unwrap(), b"hello");
+        let meta = dir.metadata_at("etc/hosts", false).unwrap();
+        assert_eq!(
+            meta.modified,
+            Some(SystemTime::UNIX_EPOCH + Duration::from_secs(2_000_000))
+        );
+        let meta = dir.metadata_at("etc", false).unwrap();
+        assert_eq!(
+            meta.modified,
+            Some(SystemTime::UNIX_EPOCH + Duration::from_secs(1_000_000))
+        );
+        assert!(dir.write_file("etc/hosts", "bye").is_err());
+    }
+
+    #[test]
+    fn gzipped_tar_archive() {
+        use flate2::write::GzEncoder;
+        use std::io::Write;
+
+        let mut encoder = GzEncoder::new(Vec::new(), flate2::Compression::default());
+        encoder.write_all(&tar()).unwrap();
+        let archive = encoder.finish().unwrap();
+        let dir = MemoryDir::from_tar(&archive[..]).unwrap();
+        assert_eq!(dir.read_file("etc/hosts").unwrap(), b"hello");
+    }
+
+    #[test]
+    fn tar_escape() {
+        let mut builder = tar::Builder::new(Vec::new());
+        let mut header = tar::Header::new_gnu();
+        header.set_size(0);
+        // `append_data` refuses `..` so write the path into the header
+        // directly.
+        header.as_old_mut().name[..9].copy_from_slice(b"../secret");
+        header.set_cksum();
+        builder.append(&header, io::empty()).unwrap();
+        let archive = builder.into_inner().unwrap();
+        let err = MemoryDir::from_tar(&archive[..]).err().unwrap();
+        assert_eq!(err.kind(), io::ErrorKind::InvalidData);
+    }
+
+    /// Builds a zip archive by hand out of `(name, method, contents, unix
+    /// mode)` entries.
+    fn zip(entries: &[(&str, u16, &[u8], u32)]) -> Vec<u8> {
+        let mut out = Vec::new();
+        let mut central = Vec::new();
+        for (name, method, contents, mode) in entries {
+            let data = match method {
+                0 => contents.to_vec(),
+                8 => {
+                    use flate2::write::DeflateEncoder;
+                    use std::io::Write;
+                    let mut encoder =
+                        DeflateEncoder::new(Vec::new(), flate2::Compression::default());
+                    encoder.write_all(contents).unwrap();
+                    encoder.finish().unwrap()
+                }
+                _ => unreachable!(),
+            };
+            let crc = crc32fast::hash(contents);
+            let offset = out.len() as u32;
+            out.extend_from_slice(&LOCAL_FILE_HEADER.to_le_bytes());
+            out.extend_from_slice(&[20, 0, 0, 0]);
+            out.extend_from_slice(&method.to_le_bytes());
+            out.extend_from_slice(&[0; 4]);
+            out.extend_from_slice(&crc.to_le_bytes());
+            out.extend_from_slice(&(data.len() as u32).to_le_bytes());
+            out.extend_from_slice(&(contents.len() as u32).to_le_bytes());
+            out.extend_from_slice(&(name.len() as u16).to_le_bytes());
+            out.extend_from_slice(&0u16.to_le_bytes());
+            out.extend_from_slice(name.as_bytes());
+            out.extend_from_slice(&data);
+
+            central.extend_from_slice(&CENTRAL_DIRECTORY_HEADER.to_le_bytes());
+            central.extend_from_slice(&(3u16 << 8 | 20).to_le_bytes());
+            central.extend_from_slice(&[20, 0, 0, 0]);
+            central.extend_from_slice(&method.to_le_bytes());
+            central.extend_from_slice(&[0; 4]);
+            central.extend_from_slice(&crc.to_le_bytes());
+            central.extend_from_slice(&(data.len() as u32).to_le_bytes());
+            central.extend_from_slice(&(contents.len() as u32).to_le_bytes());
+            central.extend_from_slice(&(name.len() as u16).to_le_bytes());
+            central.extend_from_slice(&[0; 8]);
+            central.extend_from_slice(&(mode << 16).to_le_bytes());
+            central.extend_from_slice(&offset.to_le_bytes());
+            central.extend_from_slice(name.as_bytes());
+        }
+        let central_offset = out.len() as u32;
+        out.extend_from_slice(&central);
+        out.extend_from_slice(&END_OF_CENTRAL_DIRECTORY.to_le_bytes());
+        out.extend_from_slice(&[0; 4]);
+        out.extend_from_slice(&(entries.len() as u16).to_le_bytes());
+        out.extend_from_slice(&(entries.len() as u16).to_le_bytes());
+        out.extend_from_slice(&(central.len() as u32).to_le_bytes());
+        out.extend_from_slice(&central_offset.to_le_bytes());
+        out.extend_from_slice(&0u16.to_le_bytes());
+        out
+    }
+
+    #[test]
+    fn zip_archive() {
+        let archive = zip(&[
+            ("data/", 0, b"", 0o040755),
+            ("data/stored.txt", 0, b"stored", 0o100644),
+            ("data/deflated.txt", 8, &[b'x'; 1000], 0o100644),
+            ("link", 0, b"data/stored.txt", 0o120777),
+        ]);
+        let dir = MemoryDir::from_zip(&archive[..]).unwrap();
+        assert_eq!(dir.read_file("data/stored.txt").unwrap(), b"stored");
+        assert_eq!(dir.read_file("data/deflated.txt").unwrap(), [b'x'; 1000]);
+        assert_eq!(dir.read_file("link").unwrap(), b"stored");
+        assert!(dir.create_dir_all("more").is_err());
+
+        let err = MemoryDir::from_zip(&archive[..20]).err().unwrap();
+        assert_eq!(err.kind(), io::ErrorKind::InvalidData);
+    }
+
+    /// Returns the offset of the first central directory header of `archive`.
+    fn central_directory(archive: &[u8]) -> usize {
+        u32_at(archive, archive.len() - 6).unwrap() as usize
+    }
+
+    #[test]
+    fn zip_checksum_mismatch() {
+        for method in [0, 8] {
+            let mut archive = zip(&[("file", method, b"contents", 0o100644)]);
+            let crc = central_directory(&archive) + 16;
+            archive[crc] ^= 1;
+            let err = MemoryDir::from_zip(&archive[..]).err().unwrap();
+            assert_eq!(err.kind(), io::ErrorKind::InvalidData);
+            assert_eq!(err.to_string(), "invalid archive: entry checksum mismatch");
+        }
+    }
+
+    #[test]
+    fn zip_size_mismatch() {
+        // A deflated entry which expands to more than its recorded size, and
+        // one which claims to be far larger than it is.
+        for len in [10, u32::MAX - 1] {
+            let mut archive = zip(&[("file", 8, &[0; 1 << 20], 0o100644)]);
+            let size = central_directory(&archive) + 24;
+            archive[size..size + 4].copy_from_slice(&len.to_le_bytes());
+            let err = MemoryDir::from_zip(&archive[..]).err().unwrap();
+            assert_eq!(err.kind(), io::ErrorKind::InvalidData);
+            assert_eq!(err.to_string(), "invalid archive: entry size mismatch");
+        }
+    }
+}
@@ -0,0 +1,276 @@
+//! The implementation of [`WasiDir`] and [`WasiFile`] for host directories
+//! and files, using `cap-std`.
+
+use super::{Metadata, OpenOptions, Opened, WasiDir, WasiFile};
+use crate::bindings::filesystem::types::{self, ErrorCode};
+use crate::SystemTimeSpec;
+use cap_fs_ext::{DirExt, MetadataExt};
+use std::any::Any;
+use std::io;
+use std::path::{Component, PathBuf};
+use std::sync::Arc;
+
+impl WasiDir for cap_std::fs::Dir {
+    fn open_at(&self, path: &str, options: &OpenOptions) -> io::Result<Opened> {
+        use cap_fs_ext::{FollowSymlinks, OpenOptionsFollowExt, OpenOptionsMaybeDirExt};
+        use system_interface::fs::{FdFlags, GetSetFdFlags};
+
+        let mut opts = cap_std::fs::OpenOptions::new();
+        opts.maybe_dir(true);
+        if options.create {
+            if options.exclusive {
+                opts.create_new(true);
+            } else {
+                opts.create(true);
+            }
+        }
+        opts.read(options.read)
+            .write(options.write)
+            .truncate(options.truncate);
+        if options.follow_symlinks {
+            opts.follow(FollowSymlinks::Yes);
+        } else {
+            opts.follow(FollowSymlinks::No);
+        }
+
+        let mut opened = self.open_with(path, &opts)?;
+        if opened.metadata()?.is_dir() {
+            Ok(Opened::Dir(Arc::new(cap_std::fs::Dir::from_std_file(
+                opened.into_std(),
+            ))))
+        } else if options.directory {
+            Err(ErrorCode::NotDirectory.into())
+        } else {
+            // FIXME cap-std needs a nonblocking open option so that files reads and writes
+            // are nonblocking. Instead we set it after opening here:
+            let set_fd_flags = opened.new_set_fd_flags(FdFlags::NONBLOCK)?;
+            opened.set_fd_flags(set_fd_flags)?;
+            Ok(Opened::File(Arc::new(opened)))
+        }
+    }
+
+    fn metadata(&self) -> io::Result<Metadata> {
+        Ok(metadata_from(self.dir_metadata()?))
+    }
+
+    fn metadata_at(&self, path: &str, follow_symlinks: bool) -> io::Result<Metadata> {
+        let meta = if follow_symlinks {
+            cap_std::fs::Dir::metadata(self, path)?
+        } else {
+            self.symlink_metadata(path)?
+        };
+        Ok(metadata_from(meta))
+    }
+
+    fn read_dir(&self) -> io::Result<Vec<io::Result<types::DirectoryEntry>>> {
+        // Both `entries` and `metadata` perform syscalls, which is why they are
+        // done here, rather than delay calculating the metadata for entries
+        // when they're demanded later.
+        let entries = self.entries()?.map(|entry| {
+            let entry = entry?;
+            let meta = entry.metadata()?;
+            let type_ = descriptortype_from(meta.file_type());
+            let name = entry
+                .file_name()
+                .into_string()
+                .map_err(|_| ErrorCode::IllegalByteSequence)?;
+            Ok(types::DirectoryEntry { type_, name })
+        });
+
+        // On windows, filter out files like `C:\DumpStack.log.tmp` which we
+        // can't get full metadata for.
+        #[cfg(windows)]
+        let entries = entries.filter(|entry| {
+            use windows_sys::Win32::Foundation::{ERROR_ACCESS_DENIED, ERROR_SHARING_VIOLATION};
+            if let Err(err) = entry {
+                if err.raw_os_error() == Some(ERROR_SHARING_VIOLATION as i32)
+                    || err.raw_os_error() == Some(ERROR_ACCESS_DENIED as i32)
+                {
+                    return false;
+                }
+            }
+            true
+        });
+
+        Ok(entries.collect())
+    }
+
+    fn create_dir_at(&self, path: &str) -> io::Result<()> {
+        self.create_dir(path)
+    }
+
+    fn remove_dir_at(&self, path: &str) -> io::Result<()> {
+        self.remove_dir(path)
+    }
+
+    fn unlink_file_at(&self, path: &str) -> io::Result<()> {
+        self.remove_file_or_symlink(path)
+    }
+
+    fn rename_at(&self, old_path: &str, new_dir: &dyn WasiDir, new_path: &str) -> io::Result<()> {
+        let new_dir = host_dir(new_dir)?;
+        self.rename(old_path, new_dir, new_path)
+    }
+
+    fn link_at(&self, old_path: &str, new_dir: &dyn WasiDir, new_path: &str) -> io::Result<()> {
+        let new_dir = host_dir(new_dir)?;
+        self.hard_link(old_path, new_dir, new_path)
+    }
+
+    fn symlink_at(&self, target: &str, path: &str) -> io::Result<()> {
+        // On windows, Dir.symlink is provided by DirExt
+        self.symlink(target, path)
+    }
+
+    fn read_link_at(&self, path: &str) -> io::Result<PathBuf> {
+        self.read_link(path)
+    }
+
+    fn set_times(
+        &self,
+        atime: Option<SystemTimeSpec>,
+        mtime: Option<SystemTimeSpec>,
+    ) -> io::Result<()> {
+        fs_set_times::SetTimes::set_times(
+            self,
+            atime.map(SystemTimeSpec::into_std),
+            mtime.map(SystemTimeSpec::into_std),
+        )
+    }
+
+    fn set_times_at(
+        &self,
+        path: &str,
+        atime: Option<SystemTimeSpec>,
+        mtime: Option<SystemTimeSpec>,
+        follow_symlinks: bool,
+    ) -> io::Result<()> {
+        if follow_symlinks {
+            DirExt::set_times(self, path, atime, mtime)
+        } else {
+            self.set_symlink_times(path, atime, mtime)
+        }
+    }
+
+    fn sync(&self, data_only: bool) -> io::Result<()> {
+        let dir = self.open(Component::CurDir)?;
+        if data_only {
+            dir.sync_data()
+        } else {
+            dir.sync_all()
+        }
+    }
+
+    fn as_any(&self) -> &dyn Any {
+        self
+    }
+}
+
+impl WasiFile for cap_std::fs::File {
+    fn read_at(&self, buf: &mut [u8], offset: u64) -> io::Result<usize> {
+        system_interface::fs::FileIoExt::read_at(self, buf, offset)
+    }
+
+    fn write_at(&self, buf: &[u8], offset: u64) -> io::Result<usize> {
+        system_interface::fs::FileIoExt::write_at(self, buf, offset)
+    }
+
+    fn append(&self, buf: &[u8]) -> io::Result<usize> {
+        system_interface::fs::FileIoExt::append(self, buf)
+    }
+
+    fn metadata(&self) -> io::Result<Metadata> {
+        Ok(metadata_from(cap_std::fs::File::metadata(self)?))
+    }
+
+    fn set_len(&self, size: u64) -> io::Result<()> {
+        cap_std::fs::File::set_len(self, size)
+    }
+
+    fn set_times(
+        &self,
+        atime: Option<SystemTimeSpec>,
+        mtime: Option<SystemTimeSpec>,
+    ) -> io::Result<()> {
+        fs_set_times::SetTimes::set_times(
+            self,
+            atime.map(SystemTimeSpec::into_std),
+            mtime.map(SystemTimeSpec::into_std),
+        )
+    }
+
+    fn sync(&self, data_only: bool) -> io::Result<()> {
+        let result = if data_only {
+            self.sync_data()
+        } else {
+            self.sync_all()
+        };
+        match result {
+            Ok(()) => Ok(()),
+            // On windows, `sync_data` uses `FileFlushBuffers` which fails with
+            // `ERROR_ACCESS_DENIED` if the file is not upen for writing. Ignore
+            // this error, for POSIX compatibility.
+            #[cfg(windows)]
+            Err(e)
+                if e.raw_os_error()
+                    == Some(windows_sys::Win32::Foundation::ERROR_ACCESS_DENIED as _) =>
+            {
+                Ok(())
+            }
+            Err(e) => Err(e),
+        }
+    }
+
+    fn advise(&self, offset: u64, len: u64, advice: types::Advice) -> io::Result<()> {
+        use system_interface::fs::{Advice as A, FileIoExt};
+        use types::Advice;
+
+        let advice = match advice {
+            Advice::Normal => A::Normal,
+            Advice::Sequential => A::Sequential,
+            Advice::Random => A::Random,
+            Advice::WillNeed => A::WillNeed,
+            Advice::DontNeed => A::DontNeed,
+            Advice::NoReuse => A::NoReuse,
+        };
+        FileIoExt::advise(self, offset, len, advice)
+    }
+}
+
+/// Host directories can only be renamed or linked between each other.
+fn host_dir(dir: &dyn WasiDir) -> io::Result<&cap_std::fs::Dir> {
+    dir.as_any()
+        .downcast_ref()
+        .ok_or_else(|| ErrorCode::CrossDevice.into())
+}
+
+fn metadata_from(meta: cap_std::fs::Metadata) -> Metadata {
+    Metadata {
+        file_type: descriptortype_from(meta.file_type()),
+        len: meta.len(),
+        nlink: meta.nlink(),
+        dev: meta.dev(),
+        ino: meta.ino(),
+        accessed: meta.accessed().map(|t| t.into_std()).ok(),
+        modified: meta.modified().map(|t| t.into_std()).ok(),
+        created: meta.created().map(|t| t.into_std()).ok(),
+    }
+}
+
+fn descriptortype_from(ft: cap_std::fs::FileType) -> types::DescriptorType {
+    use cap_fs_ext::FileTypeExt;
+    use types::DescriptorType;
+    if ft.is_dir() {
+        DescriptorType::Directory
+    } else if ft.is_symlink() {
+        DescriptorType::SymbolicLink
+    } else if ft.is_block_device() {
+        DescriptorType::BlockDevice
+    } else if ft.is_char_device() {
+        DescriptorType::CharacterDevice
+    } else if ft.is_file() {
+        DescriptorType::RegularFile
+    } else {
+        DescriptorType::Unknown
+    }
+}
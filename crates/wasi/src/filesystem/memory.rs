@@ -0,0 +1,1272 @@
+//! A filesystem kept in memory, which may be layered over another directory.
+
+use super::{Metadata, OpenOptions, Opened, WasiDir, WasiFile};
+use crate::bindings::filesystem::types::{self, DescriptorType, ErrorCode};
+use crate::SystemTimeSpec;
+use std::any::Any;
+use std::collections::{BTreeMap, BTreeSet, HashMap, VecDeque};
+use std::io;
+use std::path::PathBuf;
+use std::sync::atomic::{AtomicBool, AtomicU64, Ordering};
+use std::sync::{Arc, Mutex, MutexGuard};
+use std::time::SystemTime;
+
+/// The most symbolic links that are followed while resolving a path, as on
+/// Linux.
+const MAX_SYMLINKS: usize = 40;
+
+/// The device number of the next in-memory filesystem, so that objects of
+/// different filesystems are never the same object.
+///
+/// These start high up to stay clear of the device numbers of the host.
+static NEXT_DEV: AtomicU64 = AtomicU64::new(1 << 62);
+
+/// A directory of a filesystem kept in memory.
+///
+/// A filesystem is created empty with [`MemoryDir::new`], or layered over
+/// another directory with [`MemoryDir::overlay`], and the handle returned is
+/// its root directory. The filesystem is shared by all handles to its
+/// directories, including those opened by guests, and lives as long as any of
+/// them. Cloning a handle is cheap.
+///
+/// Files, directories, symbolic links and hard links are supported. Symbolic
+/// links can't point outside of the directory they're resolved from, just as
+/// with host directories.
+///
+/// The file data kept in memory is unlimited by default. Hosts running
+/// untrusted guests should bound it with [`MemoryDir::set_max_size`], as
+/// otherwise a guest can grow a file as far as the host's memory allows.
+#[derive(Clone)]
+pub struct MemoryDir {
+    fs: Arc<Filesystem>,
+    ino: u64,
+}
+
+struct Filesystem {
+    dev: u64,
+    read_only: AtomicBool,
+    usage: Arc<Usage>,
+    tree: Mutex<Tree>,
+}
+
+/// The number of bytes of file data that a filesystem keeps in memory, and
+/// the most that it may keep.
+struct Usage {
+    used: AtomicU64,
+    max: AtomicU64,
+}
+
+/// All the objects of a filesystem, by their inode number.
+struct Tree {
+    nodes: HashMap<u64, Node>,
+    next_ino: u64,
+    usage: Arc<Usage>,
+}
+
+enum Node {
+    Dir(DirNode),
+    File(Arc<Mutex<FileNode>>),
+    Symlink(SymlinkNode),
+}
+
+struct DirNode {
+    entries: BTreeMap<String, u64>,
+    /// The directory that this one is layered over, whose entries are also
+    /// entries of this one unless they're in `whiteouts`.
+    lower: Option<Arc<dyn WasiDir>>,
+    /// The entries of `lower` which were removed from this directory.
+    whiteouts: BTreeSet<String>,
+    times: Times,
+}
+
+struct FileNode {
+    ino: u64,
+    nlink: u64,
+    contents: Contents,
+    times: Times,
+    usage: Arc<Usage>,
+}
+
+enum Contents {
+    Data(Vec<u8>),
+    /// The contents are those of the file `name` in the lower directory
+    /// `dir`, until they're first modified.
+    Lower {
+        dir: Arc<dyn WasiDir>,
+        name: String,
+        file: Option<Arc<dyn WasiFile>>,
+    },
+}
+
+struct SymlinkNode {
+    target: String,
+    times: Times,
+}
+
+#[derive(Copy, Clone)]
+struct Times {
+    accessed: SystemTime,
+    modified: SystemTime,
+    changed: SystemTime,
+}
+
+impl MemoryDir {
+    /// Creates an empty filesystem and returns its root directory.
+    pub fn new() -> MemoryDir {
+        MemoryDir::with_root(None)
+    }
+
+    /// Creates a filesystem layered over `lower` and returns its root
+    /// directory.
+    ///
+    /// The filesystem starts out with the contents of `lower` and changes to
+    /// it are kept in memory: files are copied into memory when they're first
+    /// modified and removed entries are hidden, so `lower` itself is never
+    /// modified. Entries of `lower` that haven't been modified reflect changes
+    /// made to it by others.
+    pub fn overlay(lower: impl WasiDir) -> MemoryDir {
+        MemoryDir::with_root(Some(Arc::new(lower)))
+    }
+
+    fn with_root(lower: Option<Arc<dyn WasiDir>>) -> MemoryDir {
+        let usage = Arc::new(Usage {
+            used: AtomicU64::new(0),
+            max: AtomicU64::new(u64::MAX),
+        });
+        let mut tree = Tree {
+            nodes: HashMap::new(),
+            next_ino: 1,
+            usage: usage.clone(),
+        };
+        let ino = tree.insert(|_| {
+            Node::Dir(DirNode {
+                entries: BTreeMap::new(),
+                lower,
+                whiteouts: BTreeSet::new(),
+                times: Times::now(),
+            })
+        });
+        MemoryDir {
+            fs: Arc::new(Filesystem {
+                dev: NEXT_DEV.fetch_add(1, Ordering::Relaxed),
+                read_only: AtomicBool::new(false),
+                usage,
+                tree: Mutex::new(tree),
+            }),
+            ino,
+        }
+    }
+
+    /// Makes the whole filesystem read-only, so that any attempt to modify it
+    /// fails with `read-only`.
+    pub fn freeze(&self) {
+        self.fs.read_only.store(true, Ordering::Relaxed);
+    }
+
+    /// Limits the file data that the whole filesystem keeps in memory to
+    /// `bytes`, beyond which writes fail with `insufficient-space`.
+    ///
+    /// Files of the lower directory of an overlay only count once they're
+    /// copied into memory. Data already kept in memory stays even if it's
+    /// over the new limit.
+    pub fn set_max_size(&self, bytes: u64) {
+        self.fs.usage.max.store(bytes, Ordering::Relaxed);
+    }
+
+    /// Creates the directory at `path` along with any missing parents.
+    pub fn create_dir_all(&self, path: &str) -> io::Result<()> {
+        self.fs.check_writable()?;
+        let mut tree = self.fs.lock();
+        tree.create_dir_all(self.ino, path)?;
+        Ok(())
+    }
+
+    /// Writes `contents` to the file at `path`, replacing any existing
+    /// contents and creating the file and any missing parent directories.
+    pub fn write_file(&self, path: &str, contents: impl Into<Vec<u8>>) -> io::Result<()> {
+        self.fs.check_writable()?;
+        let mut tree = self.fs.lock();
+        let (dir, name) = split_parent(path)?;
+        let dir = tree.create_dir_all(self.ino, &dir)?;
+        let file = match tree.child(dir, &name)? {
+            Some(ino) => tree.file(ino)?,
+            None => {
+                let ino = tree.new_file();
+                tree.attach(dir, name, ino)?;
+                tree.file(ino)?
+            }
+        };
+        let mut file = file.lock().unwrap();
+        file.set_data(contents.into())?;
+        file.times.modify();
+        Ok(())
+    }
+
+    /// Reads the contents of the file at `path`.
+    pub fn read_file(&self, path: &str) -> io::Result<Vec<u8>> {
+        let mut tree = self.fs.lock();
+        let ino = tree.resolve(self.ino, path, true)?;
+        let file = tree.file(ino)?;
+        drop(tree);
+        let mut file = file.lock().unwrap();
+        file.read_to_end()
+    }
+
+    /// Checks that `dir` is part of the same filesystem as `self`.
+    fn same_fs<'a>(&self, dir: &'a dyn WasiDir) -> io::Result<&'a MemoryDir> {
+        match dir.as_any().downcast_ref::<MemoryDir>() {
+            Some(dir) if Arc::ptr_eq(&self.fs, &dir.fs) => Ok(dir),
+            _ => Err(ErrorCode::CrossDevice.into()),
+        }
+    }
+}
+
+impl Default for MemoryDir {
+    fn default() -> MemoryDir {
+        MemoryDir::new()
+    }
+}
+
+impl WasiDir for MemoryDir {
+    fn open_at(&self, path: &str, options: &OpenOptions) -> io::Result<Opened> {
+        let mut tree = self.fs.lock();
+        let ino = match tree.resolve(self.ino, path, options.follow_symlinks) {
+            Ok(_) if options.create && options.exclusive => {
+                return Err(ErrorCode::Exist.into());
+            }
+            Ok(ino) => ino,
+            Err(e) if options.create && ErrorCode::from(&e) == ErrorCode::NoEntry => {
+                self.fs.check_writable()?;
+                let (dir, name) = tree.resolve_parent(self.ino, path)?;
+                // A dangling symbolic link isn't replaced.
+                if tree.child(dir, &name)?.is_some() {
+                    return Err(e);
+                }
+                let ino = tree.new_file();
+                tree.attach(dir, name, ino)?;
+                ino
+            }
+            Err(e) => return Err(e),
+        };
+        match &tree.nodes[&ino] {
+            Node::Dir(_) => {
+                if options.write || options.truncate {
+                    return Err(ErrorCode::IsDirectory.into());
+                }
+                Ok(Opened::Dir(Arc::new(MemoryDir {
+                    fs: self.fs.clone(),
+                    ino,
+                })))
+            }
+            Node::File(file) => {
+                if options.directory {
+                    return Err(ErrorCode::NotDirectory.into());
+                }
+                if options.write || options.truncate {
+                    self.fs.check_writable()?;
+                }
+                if options.truncate {
+                    let mut file = file.lock().unwrap();
+                    file.set_data(Vec::new())?;
+                    file.times.modify();
+                }
+                Ok(Opened::File(Arc::new(MemoryFile {
+                    fs: self.fs.clone(),
+                    file: file.clone(),
+                    writable: options.write,
+                })))
+            }
+            Node::Symlink(_) => Err(ErrorCode::Loop.into()),
+        }
+    }
+
+    fn metadata(&self) -> io::Result<Metadata> {
+        self.fs.lock().metadata(self.fs.dev, self.ino)
+    }
+
+    fn metadata_at(&self, path: &str, follow_symlinks: bool) -> io::Result<Metadata> {
+        let mut tree = self.fs.lock();
+        let ino = tree.resolve(self.ino, path, follow_symlinks)?;
+        tree.metadata(self.fs.dev, ino)
+    }
+
+    fn read_dir(&self) -> io::Result<Vec<io::Result<types::DirectoryEntry>>> {
+        let tree = self.fs.lock();
+        Ok(tree
+            .entries(self.ino)?
+            .into_iter()
+            .map(|(name, type_)| Ok(types::DirectoryEntry { type_, name }))
+            .collect())
+    }
+
+    fn create_dir_at(&self, path: &str) -> io::Result<()> {
+        self.fs.check_writable()?;
+        let mut tree = self.fs.lock();
+        let (dir, name) = tree.resolve_parent(self.ino, path)?;
+        if tree.child(dir, &name)?.is_some() {
+            return Err(ErrorCode::Exist.into());
+        }
+        let ino = tree.insert(|_| Node::Dir(DirNode::new()));
+        tree.attach(dir, name, ino)
+    }
+
+    fn remove_dir_at(&self, path: &str) -> io::Result<()> {
+        self.fs.check_writable()?;
+        let mut tree = self.fs.lock();
+        let (dir, name) = tree.resolve_parent(self.ino, path)?;
+        let ino = tree.child(dir, &name)?.ok_or(ErrorCode::NoEntry)?;
+        if !tree.entries(ino)?.is_empty() {
+            return Err(ErrorCode::NotEmpty.into());
+        }
+        tree.unlink(dir, &name)
+    }
+
+    fn unlink_file_at(&self, path: &str) -> io::Result<()> {
+        self.fs.check_writable()?;
+        let mut tree = self.fs.lock();
+        let (dir, name) = tree.resolve_parent(self.ino, path)?;
+        let ino = tree.child(dir, &name)?.ok_or(ErrorCode::NoEntry)?;
+        if let Node::Dir(_) = tree.nodes[&ino] {
+            return Err(ErrorCode::IsDirectory.into());
+        }
+        tree.unlink(dir, &name)
+    }
+
+    fn rename_at(&self, old_path: &str, new_dir: &dyn WasiDir, new_path: &str) -> io::Result<()> {
+        let new_dir = self.same_fs(new_dir)?;
+        self.fs.check_writable()?;
+        let mut tree = self.fs.lock();
+        let (src_dir, src_name) = tree.resolve_parent(self.ino, old_path)?;
+        let src = tree.child(src_dir, &src_name)?.ok_or(ErrorCode::NoEntry)?;
+        let (dst_dir, dst_name) = tree.resolve_parent(new_dir.ino, new_path)?;
+        let src_is_dir = matches!(tree.nodes[&src], Node::Dir(_));
+        if src_is_dir && tree.contains(src, dst_dir) {
+            return Err(ErrorCode::Invalid.into());
+        }
+        if let Some(dst) = tree.child(dst_dir, &dst_name)? {
+            if dst == src {
+                return Ok(());
+            }
+            match (src_is_dir, &tree.nodes[&dst]) {
+                (true, Node::Dir(_)) => {
+                    if !tree.entries(dst)?.is_empty() {
+                        return Err(ErrorCode::NotEmpty.into());
+                    }
+                }
+                (true, _) => return Err(ErrorCode::NotDirectory.into()),
+                (false, Node::Dir(_)) => return Err(ErrorCode::IsDirectory.into()),
+                (false, _) => {}
+            }
+            tree.unlink(dst_dir, &dst_name)?;
+        }
+        tree.detach(src_dir, &src_name)?;
+        tree.attach(dst_dir, dst_name, src)
+    }
+
+    fn link_at(&self, old_path: &str, new_dir: &dyn WasiDir, new_path: &str) -> io::Result<()> {
+        let new_dir = self.same_fs(new_dir)?;
+        self.fs.check_writable()?;
+        let mut tree = self.fs.lock();
+        let src = tree.resolve(self.ino, old_path, false)?;
+        let Node::File(file) = &tree.nodes[&src] else {
+            return Err(ErrorCode::NotPermitted.into());
+        };
+        let file = file.clone();
+        let (dir, name) = tree.resolve_parent(new_dir.ino, new_path)?;
+        if tree.child(dir, &name)?.is_some() {
+            return Err(ErrorCode::Exist.into());
+        }
+        tree.attach(dir, name, src)?;
+        let mut file = file.lock().unwrap();
+        file.nlink += 1;
+        file.times.change();
+        Ok(())
+    }
+
+    fn symlink_at(&self, target: &str, path: &str) -> io::Result<()> {
+        self.fs.check_writable()?;
+        let mut tree = self.fs.lock();
+        let (dir, name) = tree.resolve_parent(self.ino, path)?;
+        if tree.child(dir, &name)?.is_some() {
+            return Err(ErrorCode::Exist.into());
+        }
+        let ino = tree.insert(|_| {
+            Node::Symlink(SymlinkNode {
+                target: target.to_string(),
+                times: Times::now(),
+            })
+        });
+        tree.attach(dir, name, ino)
+    }
+
+    fn read_link_at(&self, path: &str) -> io::Result<PathBuf> {
+        let mut tree = self.fs.lock();
+        let ino = tree.resolve(self.ino, path, false)?;
+        match &tree.nodes[&ino] {
+            Node::Symlink(link) => Ok(PathBuf::from(&link.target)),
+            _ => Err(ErrorCode::Invalid.into()),
+        }
+    }
+
+    fn set_times(
+        &self,
+        atime: Option<SystemTimeSpec>,
+        mtime: Option<SystemTimeSpec>,
+    ) -> io::Result<()> {
+        self.fs.check_writable()?;
+        let mut tree = self.fs.lock();
+        tree.set_times(self.ino, atime, mtime)
+    }
+
+    fn set_times_at(
+        &self,
+        path: &str,
+        atime: Option<SystemTimeSpec>,
+        mtime: Option<SystemTimeSpec>,
+        follow_symlinks: bool,
+    ) -> io::Result<()> {
+        self.fs.check_writable()?;
+        let mut tree = self.fs.lock();
+        let ino = tree.resolve(self.ino, path, follow_symlinks)?;
+        tree.set_times(ino, atime, mtime)
+    }
+
+    fn as_any(&self) -> &dyn Any {
+        self
+    }
+}
+
+impl Filesystem {
+    fn lock(&self) -> MutexGuard<'_, Tree> {
+        self.tree.lock().unwrap()
+    }
+
+    fn check_writable(&self) -> io::Result<()> {
+        if self.read_only.load(Ordering::Relaxed) {
+            Err(ErrorCode::ReadOnly.into())
+        } else {
+            Ok(())
+        }
+    }
+}
+
+impl Usage {
+    /// Accounts for `len` more bytes, failing with `insufficient-space` if
+    /// that would exceed the maximum.
+    fn reserve(&self, len: u64) -> io::Result<()> {
+        let max = self.max.load(Ordering::Relaxed);
+        match self
+            .used
+            .fetch_update(Ordering::Relaxed, Ordering::Relaxed, |used| {
+                used.checked_add(len).filter(|used| *used <= max)
+            }) {
+            Ok(_) => Ok(()),
+            Err(_) => Err(ErrorCode::InsufficientSpace.into()),
+        }
+    }
+
+    /// Accounts for `len` fewer bytes.
+    fn release(&self, len: u64) {
+        self.used.fetch_sub(len, Ordering::Relaxed);
+    }
+}
+
+impl Tree {
+    /// Adds the node created by `node`, which is passed its inode number.
+    fn insert(&mut self, node: impl FnOnce(u64) -> Node) -> u64 {
+        let ino = self.next_ino;
+        self.next_ino += 1;
+        self.nodes.insert(ino, node(ino));
+        ino
+    }
+
+    /// Adds a new, empty file.
+    fn new_file(&mut self) -> u64 {
+        let usage = self.usage.clone();
+        self.insert(|ino| Node::File(Arc::new(Mutex::new(FileNode::new(ino, usage)))))
+    }
+
+    fn dir(&self, ino: u64) -> io::Result<&DirNode> {
+        match self.nodes.get(&ino) {
+            Some(Node::Dir(dir)) => Ok(dir),
+            Some(_) => Err(ErrorCode::NotDirectory.into()),
+            // The directory was removed while a handle to it was open.
+            None => Err(ErrorCode::NoEntry.into()),
+        }
+    }
+
+    fn dir_mut(&mut self, ino: u64) -> io::Result<&mut DirNode> {
+        match self.nodes.get_mut(&ino) {
+            Some(Node::Dir(dir)) => Ok(dir),
+            Some(_) => Err(ErrorCode::NotDirectory.into()),
+            None => Err(ErrorCode::NoEntry.into()),
+        }
+    }
+
+    fn file(&self, ino: u64) -> io::Result<Arc<Mutex<FileNode>>> {
+        match &self.nodes[&ino] {
+            Node::File(file) => Ok(file.clone()),
+            Node::Dir(_) => Err(ErrorCode::IsDirectory.into()),
+            Node::Symlink(_) => Err(ErrorCode::Loop.into()),
+        }
+    }
+
+    /// Looks up the entry `name` of the directory `dir`, bringing it into
+    /// the tree first if it's an entry of the lower directory.
+    fn child(&mut self, dir: u64, name: &str) -> io::Result<Option<u64>> {
+        let node = self.dir(dir)?;
+        if let Some(ino) = node.entries.get(name) {
+            return Ok(Some(*ino));
+        }
+        let lower = match &node.lower {
+            Some(lower) if !node.whiteouts.contains(name) => lower.clone(),
+            _ => return Ok(None),
+        };
+        let meta = match lower.metadata_at(name, false) {
+            Ok(meta) => meta,
+            Err(e) if ErrorCode::from(&e) == ErrorCode::NoEntry => return Ok(None),
+            Err(e) => return Err(e),
+        };
+        let times = Times::from_metadata(&meta);
+        let node = match meta.file_type {
+            DescriptorType::Directory => {
+                let options = OpenOptions {
+                    read: true,
+                    directory: true,
+                    ..OpenOptions::default()
+                };
+                let Opened::Dir(lower) = lower.open_at(name, &options)? else {
+                    return Err(ErrorCode::NotDirectory.into());
+                };
+                Node::Dir(DirNode {
+                    lower: Some(lower),
+                    times,
+                    ..DirNode::new()
+                })
+            }
+            DescriptorType::SymbolicLink => {
+                let target = lower
+                    .read_link_at(name)?
+                    .into_os_string()
+                    .into_string()
+                    .map_err(|_| ErrorCode::IllegalByteSequence)?;
+                Node::Symlink(SymlinkNode { target, times })
+            }
+            _ => {
+                let contents = Contents::Lower {
+                    dir: lower,
+                    name: name.to_string(),
+                    file: None,
+                };
+                // This is the inode number that `insert` assigns below.
+                let ino = self.next_ino;
+                Node::File(Arc::new(Mutex::new(FileNode {
+                    ino,
+                    nlink: 1,
+                    contents,
+                    times,
+                    usage: self.usage.clone(),
+                })))
+            }
+        };
+        let ino = self.insert(|_| node);
+        self.dir_mut(dir)?.entries.insert(name.to_string(), ino);
+        Ok(Some(ino))
+    }
+
+    /// Resolves `path` relative to the directory `start`.
+    fn resolve(&mut self, start: u64, path: &str, follow_symlinks: bool) -> io::Result<u64> {
+        self.walk(start, components(path)?, follow_symlinks)
+    }
+
+    /// Resolves the directory containing the last component of `path`,
+    /// relative to the directory `start`, and returns it along with that
+    /// component.
+    fn resolve_parent(&mut self, start: u64, path: &str) -> io::Result<(u64, String)> {
+        let mut components = components(path)?;
+        let name = components.pop_back().ok_or(ErrorCode::NoEntry)?;
+        if name == "." || name == ".." {
+            return Err(ErrorCode::Invalid.into());
+        }
+        let dir = self.walk(start, components, true)?;
+        self.dir(dir)?;
+        Ok((dir, name))
+    }
+
+    fn walk(
+        &mut self,
+        start: u64,
+        mut components: VecDeque<String>,
+        follow_symlinks: bool,
+    ) -> io::Result<u64> {
+        self.dir(start)?;
+        // The directories that lead from `start` to the current one, for
+        // resolving `..`.
+        let mut dirs = vec![start];
+        let mut symlinks = 0;
+        while let Some(component) = components.pop_front() {
+            let dir = *dirs.last().unwrap();
+            match component.as_str() {
+                "." => {}
+                ".." => {
+                    if dirs.len() == 1 {
+                        return Err(ErrorCode::NotPermitted.into());
+                    }
+                    dirs.pop();
+                }
+                name => {
+                    let ino = self.child(dir, name)?.ok_or(ErrorCode::NoEntry)?;
+                    match &self.nodes[&ino] {
+                        Node::Dir(_) => dirs.push(ino),
+                        Node::Symlink(link) if follow_symlinks || !components.is_empty() => {
+                            symlinks += 1;
+                            if symlinks > MAX_SYMLINKS {
+                                return Err(ErrorCode::Loop.into());
+                            }
+                            for component in self::components(&link.target)?.into_iter().rev() {
+                                components.push_front(component);
+                            }
+                        }
+                        _ if components.is_empty() => return Ok(ino),
+                        _ => return Err(ErrorCode::NotDirectory.into()),
+                    }
+                }
+            }
+        }
+        Ok(*dirs.last().unwrap())
+    }
+
+    /// Creates the directory `path` relative to `start` along with any
+    /// missing parents.
+    ///
+    /// Symbolic links aren't followed and `..` components are rejected.
+    fn create_dir_all(&mut self, start: u64, path: &str) -> io::Result<u64> {
+        let mut dir = start;
+        for component in components(path)? {
+            match component.as_str() {
+                "." => continue,
+                ".." => return Err(ErrorCode::Invalid.into()),
+                _ => {}
+            }
+            dir = match self.child(dir, &component)? {
+                Some(ino) => ino,
+                None => {
+                    let ino = self.insert(|_| Node::Dir(DirNode::new()));
+                    self.attach(dir, component, ino)?;
+                    ino
+                }
+            };
+            self.dir(dir)?;
+        }
+        Ok(dir)
+    }
+
+    /// Returns whether `ino` is the directory `dir` or one of its
+    /// descendants.
+    fn contains(&self, dir: u64, ino: u64) -> bool {
+        if dir == ino {
+            return true;
+        }
+        match self.nodes.get(&dir) {
+            Some(Node::Dir(node)) => node
+                .entries
+                .values()
+                .any(|child| self.contains(*child, ino)),
+            _ => false,
+        }
+    }
+
+    /// Returns the names and types of the entries of the directory `dir`,
+    /// including those of its lower directory.
+    fn entries(&self, dir: u64) -> io::Result<BTreeMap<String, DescriptorType>> {
+        let node = self.dir(dir)?;
+        let mut entries = BTreeMap::new();
+        if let Some(lower) = &node.lower {
+            for entry in lower.read_dir()? {
+                let entry = entry?;
+                if !node.whiteouts.contains(&entry.name) {
+                    entries.insert(entry.name, entry.type_);
+                }
+            }
+        }
+        for (name, ino) in &node.entries {
+            let type_ = match &self.nodes[ino] {
+                Node::Dir(_) => DescriptorType::Directory,
+                Node::File(_) => DescriptorType::RegularFile,
+                Node::Symlink(_) => DescriptorType::SymbolicLink,
+            };
+            entries.insert(name.clone(), type_);
+        }
+        Ok(entries)
+    }
+
+    /// Adds `ino` to the directory `dir` as `name`.
+    fn attach(&mut self, dir: u64, name: String, ino: u64) -> io::Result<()> {
+        let node = self.dir_mut(dir)?;
+        node.whiteouts.remove(&name);
+        node.entries.insert(name, ino);
+        node.times.modify();
+        Ok(())
+    }
+
+    /// Removes the entry `name` from the directory `dir`, without removing
+    /// the node it refers to.
+    fn detach(&mut self, dir: u64, name: &str) -> io::Result<u64> {
+        let node = self.dir_mut(dir)?;
+        let ino = node.entries.remove(name).ok_or(ErrorCode::NoEntry)?;
+        if node.lower.is_some() {
+            node.whiteouts.insert(name.to_string());
+        }
+        node.times.modify();
+        Ok(ino)
+    }
+
+    /// Removes the entry `name` from the directory `dir`, along with the node
+    /// it refers to if that was its last link.
+    fn unlink(&mut self, dir: u64, name: &str) -> io::Result<()> {
+        let ino = self.detach(dir, name)?;
+        if let Node::File(file) = &self.nodes[&ino] {
+            let mut file = file.lock().unwrap();
+            file.nlink -= 1;
+            file.times.change();
+            if file.nlink > 0 {
+                return Ok(());
+            }
+        }
+        // Open files keep their contents until they're closed.
+        self.nodes.remove(&ino);
+        Ok(())
+    }
+
+    fn metadata(&self, dev: u64, ino: u64) -> io::Result<Metadata> {
+        let (file_type, len, nlink, times) = match self.nodes.get(&ino) {
+            Some(Node::Dir(dir)) => (DescriptorType::Directory, 0, 1, dir.times),
+            Some(Node::File(file)) => {
+                let file = file.lock().unwrap();
+                (
+                    DescriptorType::RegularFile,
+                    file.len()?,
+                    file.nlink,
+                    file.times,
+                )
+            }
+            Some(Node::Symlink(link)) => (
+                DescriptorType::SymbolicLink,
+                link.target.len() as u64,
+                1,
+                link.times,
+            ),
+            None => return Err(ErrorCode::NoEntry.into()),
+        };
+        Ok(Metadata {
+            file_type,
+            len,
+            nlink,
+            dev,
+            ino,
+            accessed: Some(times.accessed),
+            modified: Some(times.modified),
+            created: Some(times.changed),
+        })
+    }
+
+    fn set_times(
+        &mut self,
+        ino: u64,
+        atime: Option<SystemTimeSpec>,
+        mtime: Option<SystemTimeSpec>,
+    ) -> io::Result<()> {
+        match self.nodes.get_mut(&ino) {
+            Some(Node::Dir(dir)) => dir.times.set(atime, mtime),
+            Some(Node::File(file)) => file.lock().unwrap().times.set(atime, mtime),
+            Some(Node::Symlink(link)) => link.times.set(atime, mtime),
+            None => return Err(ErrorCode::NoEntry.into()),
+        }
+        Ok(())
+    }
+}
+
+impl DirNode {
+    fn new() -> DirNode {
+        DirNode {
+            entries: BTreeMap::new(),
+            lower: None,
+            whiteouts: BTreeSet::new(),
+            times: Times::now(),
+        }
+    }
+}
+
+impl FileNode {
+    fn new(ino: u64, usage: Arc<Usage>) -> FileNode {
+        FileNode {
+            ino,
+            nlink: 1,
+            contents: Contents::Data(Vec::new()),
+            times: Times::now(),
+            usage,
+        }
+    }
+
+    fn len(&self) -> io::Result<u64> {
+        match &self.contents {
+            Contents::Data(data) => Ok(data.len() as u64),
+            Contents::Lower { dir, name, .. } => Ok(dir.metadata_at(name, false)?.len),
+        }
+    }
+
+    fn read_to_end(&mut self) -> io::Result<Vec<u8>> {
+        let mut data = Vec::new();
+        let mut buf = vec![0; 64 * 1024];
+        loop {
+            match self.read_at(&mut buf, data.len() as u64)? {
+                0 => return Ok(data),
+                n => data.extend_from_slice(&buf[..n]),
+            }
+        }
+    }
+
+    /// Returns the data of the file, copying it out of the lower directory
+    /// if it hasn't been already.
+    fn data(&mut self) -> io::Result<&mut Vec<u8>> {
+        if let Contents::Lower { .. } = &self.contents {
+            let data = self.read_to_end()?;
+            self.set_data(data)?;
+        }
+        match &mut self.contents {
+            Contents::Data(data) => Ok(data),
+            Contents::Lower { .. } => unreachable!(),
+        }
+    }
+
+    /// Replaces the contents of the file with `data`, if the filesystem has
+    /// room for it.
+    fn set_data(&mut self, data: Vec<u8>) -> io::Result<()> {
+        self.usage.reserve(data.len() as u64)?;
+        if let Contents::Data(old) = &self.contents {
+            self.usage.release(old.len() as u64);
+        }
+        self.contents = Contents::Data(data);
+        Ok(())
+    }
+
+    /// Grows or shrinks the data of the file to `len` bytes, filling it with
+    /// zeros, if the filesystem and the host have room for it.
+    fn resize(&mut self, len: usize) -> io::Result<()> {
+        let usage = self.usage.clone();
+        let data = self.data()?;
+        if let Some(additional) = len.checked_sub(data.len()) {
+            usage.reserve(additional as u64)?;
+            if data.try_reserve_exact(additional).is_err() {
+                usage.release(additional as u64);
+                return Err(ErrorCode::InsufficientSpace.into());
+            }
+        } else {
+            usage.release((data.len() - len) as u64);
+        }
+        data.resize(len, 0);
+        Ok(())
+    }
+
+    fn read_at(&mut self, buf: &mut [u8], offset: u64) -> io::Result<usize> {
+        match &mut self.contents {
+            Contents::Data(data) => {
+                let start = usize::try_from(offset)
+                    .unwrap_or(usize::MAX)
+                    .min(data.len());
+                let len = buf.len().min(data.len() - start);
+                buf[..len].copy_from_slice(&data[start..][..len]);
+                Ok(len)
+            }
+            Contents::Lower { dir, name, file } => {
+                let file = match file {
+                    Some(file) => file,
+                    None => {
+                        let options = OpenOptions {
+                            read: true,
+                            ..OpenOptions::default()
+                        };
+                        match dir.open_at(name, &options)? {
+                            Opened::File(opened) => file.insert(opened),
+                            Opened::Dir(_) => return Err(ErrorCode::IsDirectory.into()),
+                        }
+                    }
+                };
+                file.read_at(buf, offset)
+            }
+        }
+    }
+
+    fn write_at(&mut self, buf: &[u8], offset: u64) -> io::Result<usize> {
+        let offset = usize::try_from(offset).map_err(|_| ErrorCode::FileTooLarge)?;
+        let end = offset
+            .checked_add(buf.len())
+            .ok_or(ErrorCode::FileTooLarge)?;
+        if self.data()?.len() < end {
+            self.resize(end)?;
+        }
+        self.data()?[offset..end].copy_from_slice(buf);
+        self.times.modify();
+        Ok(buf.len())
+    }
+}
+
+impl Drop for FileNode {
+    fn drop(&mut self) {
+        if let Contents::Data(data) = &self.contents {
+            self.usage.release(data.len() as u64);
+        }
+    }
+}
+
+impl Times {
+    fn now() -> Times {
+        let now = SystemTime::now();
+        Times {
+            accessed: now,
+            modified: now,
+            changed: now,
+        }
+    }
+
+    fn from_metadata(meta: &Metadata) -> Times {
+        let now = SystemTime::now();
+        Times {
+            accessed: meta.accessed.unwrap_or(now),
+            modified: meta.modified.unwrap_or(now),
+            changed: meta.created.unwrap_or(now),
+        }
+    }
+
+    fn modify(&mut self) {
+        let now = SystemTime::now();
+        self.modified = now;
+        self.changed = now;
+    }
+
+    fn change(&mut self) {
+        self.changed = SystemTime::now();
+    }
+
+    fn set(&mut self, atime: Option<SystemTimeSpec>, mtime: Option<SystemTimeSpec>) {
+        let resolve = |spec| match spec {
+            SystemTimeSpec::SymbolicNow => SystemTime::now(),
+            SystemTimeSpec::Absolute(time) => time.into_std(),
+        };
+        if let Some(atime) = atime {
+            self.accessed = resolve(atime);
+        }
+        if let Some(mtime) = mtime {
+            self.modified = resolve(mtime);
+        }
+        self.change();
+    }
+}
+
+/// A file of a [`MemoryDir`].
+struct MemoryFile {
+    fs: Arc<Filesystem>,
+    file: Arc<Mutex<FileNode>>,
+    /// Whether the file was opened for writing.
+    writable: bool,
+}
+
+impl MemoryFile {
+    fn check_writable(&self) -> io::Result<()> {
+        if !self.writable {
+            return Err(ErrorCode::BadDescriptor.into());
+        }
+        self.fs.check_writable()
+    }
+}
+
+impl WasiFile for MemoryFile {
+    fn read_at(&self, buf: &mut [u8], offset: u64) -> io::Result<usize> {
+        self.file.lock().unwrap().read_at(buf, offset)
+    }
+
+    fn write_at(&self, buf: &[u8], offset: u64) -> io::Result<usize> {
+        self.check_writable()?;
+        self.file.lock().unwrap().write_at(buf, offset)
+    }
+
+    fn append(&self, buf: &[u8]) -> io::Result<usize> {
+        self.check_writable()?;
+        let mut file = self.file.lock().unwrap();
+        let len = file.data()?.len() as u64;
+        file.write_at(buf, len)
+    }
+
+    fn metadata(&self) -> io::Result<Metadata> {
+        let file = self.file.lock().unwrap();
+        Ok(Metadata {
+            file_type: DescriptorType::RegularFile,
+            len: file.len()?,
+            nlink: file.nlink,
+            dev: self.fs.dev,
+            ino: file.ino,
+            accessed: Some(file.times.accessed),
+            modified: Some(file.times.modified),
+            created: Some(file.times.changed),
+        })
+    }
+
+    fn set_len(&self, size: u64) -> io::Result<()> {
+        self.check_writable()?;
+        let size = usize::try_from(size).map_err(|_| ErrorCode::FileTooLarge)?;
+        let mut file = self.file.lock().unwrap();
+        file.resize(size)?;
+        file.times.modify();
+        Ok(())
+    }
+
+    fn set_times(
+        &self,
+        atime: Option<SystemTimeSpec>,
+        mtime: Option<SystemTimeSpec>,
+    ) -> io::Result<()> {
+        self.fs.check_writable()?;
+        self.file.lock().unwrap().times.set(atime, mtime);
+        Ok(())
+    }
+}
+
+/// Splits `path` into its components, which must be relative.
+fn components(path: &str) -> io::Result<VecDeque<String>> {
+    if path.is_empty() {
+        return Err(ErrorCode::NoEntry.into());
+    }
+    if path.starts_with('/') {
+        return Err(ErrorCode::NotPermitted.into());
+    }
+    Ok(path
+        .split('/')
+        .filter(|c| !c.is_empty())
+        .map(|c| c.to_string())
+        .collect())
+}
+
+/// Splits `path` into the path of its parent directory and its last
+/// component.
+fn split_parent(path: &str) -> io::Result<(String, String)> {
+    let path = path.trim_end_matches('/');
+    let (dir, name) = match path.rsplit_once('/') {
+        Some((dir, name)) => (dir, name),
+        None => (".", path),
+    };
+    if name.is_empty() || name == "." || name == ".." {
+        return Err(ErrorCode::Invalid.into());
+    }
+    let dir = if dir.is_empty() { "." } else { dir };
+    Ok((dir.to_string(), name.to_string()))
+}
+
+#[cfg(test)]
+mod test {
+    use super::*;
+
+    fn open(dir: &dyn WasiDir, path: &str, options: OpenOptions) -> io::Result<Arc<dyn WasiFile>> {
+        match dir.open_at(path, &options)? {
+            Opened::File(file) => Ok(file),
+            Opened::Dir(_) => panic!("`{path}` is a directory"),
+        }
+    }
+
+    fn error(result: io::Result<impl Sized>) -> ErrorCode {
+        ErrorCode::from(&result.err().expect("expected an error"))
+    }
+
+    #[test]
+    fn files_and_dirs() {
+        let root = MemoryDir::new();
+        root.create_dir_at("a").unwrap();
+        root.write_file("a/b/c.txt", "hello").unwrap();
+        assert_eq!(root.read_file("a/b/c.txt").unwrap(), b"hello");
+        assert_eq!(root.read_file("a/./b/../b/c.txt").unwrap(), b"hello");
+
+        let options = OpenOptions {
+            read: true,
+            write: true,
+            create: true,
+            ..OpenOptions::default()
+        };
+        let file = open(&root, "a/d.txt", options).unwrap();
+        assert_eq!(file.write_at(b"world", 2).unwrap(), 5);
+        assert_eq!(file.append(b"!").unwrap(), 1);
+        assert_eq!(root.read_file("a/d.txt").unwrap(), b"\0\0world!");
+        file.set_len(3).unwrap();
+        let mut buf = [0; 8];
+        assert_eq!(file.read_at(&mut buf, 1).unwrap(), 2);
+        assert_eq!(file.read_at(&mut buf, 3).unwrap(), 0);
+
+        let names = |dir: &dyn WasiDir| {
+            dir.read_dir()
+                .unwrap()
+                .into_iter()
+                .map(|e| e.unwrap().name)
+                .collect::<Vec<_>>()
+        };
+        assert_eq!(names(&root), ["a"]);
+        let Opened::Dir(a) = root.open_at("a", &OpenOptions::default()).unwrap() else {
+            panic!()
+        };
+        assert_eq!(names(&*a), ["b", "d.txt"]);
+
+        assert_eq!(error(root.create_dir_at("a")), ErrorCode::Exist);
+        assert_eq!(error(root.remove_dir_at("a")), ErrorCode::NotEmpty);
+        assert_eq!(error(root.unlink_file_at("a")), ErrorCode::IsDirectory);
+        assert_eq!(error(root.read_file("a/d.txt/e")), ErrorCode::NotDirectory);
+        assert_eq!(error(a.metadata_at("..", true)), ErrorCode::NotPermitted);
+        assert_eq!(error(root.metadata_at("/a", true)), ErrorCode::NotPermitted);
+
+        a.unlink_file_at("d.txt").unwrap();
+        // The file stays open after it's removed.
+        assert_eq!(file.metadata().unwrap().nlink, 0);
+        assert_eq!(file.read_at(&mut buf, 0).unwrap(), 3);
+        assert_eq!(error(root.read_file("a/d.txt")), ErrorCode::NoEntry);
+    }
+
+    #[test]
+    fn links() {
+        let root = MemoryDir::new();
+        root.write_file("dir/file", "data").unwrap();
+        root.symlink_at("dir/file", "link").unwrap();
+        root.symlink_at("../..", "dir/escape").unwrap();
+        root.symlink_at("loop", "loop").unwrap();
+        assert_eq!(root.read_file("link").unwrap(), b"data");
+        assert_eq!(
+            root.read_link_at("link").unwrap(),
+            PathBuf::from("dir/file")
+        );
+        assert_eq!(
+            root.metadata_at("link", false).unwrap().file_type,
+            DescriptorType::SymbolicLink
+        );
+        assert_eq!(
+            error(root.metadata_at("dir/escape", true)),
+            ErrorCode::NotPermitted
+        );
+        assert_eq!(error(root.metadata_at("loop", true)), ErrorCode::Loop);
+
+        root.link_at("dir/file", &root, "hard").unwrap();
+        let meta = root.metadata_at("hard", false).unwrap();
+        assert_eq!(meta.nlink, 2);
+        assert_eq!(meta.ino, root.metadata_at("dir/file", false).unwrap().ino);
+
+        root.rename_at("hard", &root, "dir/renamed").unwrap();
+        assert_eq!(root.read_file("dir/renamed").unwrap(), b"data");
+        assert_eq!(
+            error(root.rename_at("dir", &root, "dir/sub")),
+            ErrorCode::Invalid
+        );
+        assert_eq!(
+            error(root.rename_at("link", &MemoryDir::new(), "link")),
+            ErrorCode::CrossDevice
+        );
+    }
+
+    #[test]
+    fn read_only() {
+        let root = MemoryDir::new();
+        root.write_file("file", "data").unwrap();
+        root.freeze();
+        assert_eq!(error(root.write_file("file", "")), ErrorCode::ReadOnly);
+        assert_eq!(error(root.create_dir_at("dir")), ErrorCode::ReadOnly);
+        let options = OpenOptions {
+            write: true,
+            ..OpenOptions::default()
+        };
+        assert_eq!(error(open(&root, "file", options)), ErrorCode::ReadOnly);
+        assert_eq!(root.read_file("file").unwrap(), b"data");
+    }
+
+    #[test]
+    fn max_size() {
+        let root = MemoryDir::new();
+        root.set_max_size(10);
+        root.write_file("a", "hello").unwrap();
+        assert_eq!(
+            error(root.write_file("b", "too long")),
+            ErrorCode::InsufficientSpace
+        );
+
+        let options = OpenOptions {
+            write: true,
+            create: true,
+            ..OpenOptions::default()
+        };
+        let file = open(&root, "c", options).unwrap();
+        assert_eq!(error(file.set_len(1 << 50)), ErrorCode::InsufficientSpace);
+        assert_eq!(
+            error(file.write_at(b"x", 1 << 50)),
+            ErrorCode::InsufficientSpace
+        );
+        assert_eq!(
+            error(file.write_at(b"x", u64::MAX)),
+            ErrorCode::FileTooLarge
+        );
+        file.set_len(5).unwrap();
+        assert_eq!(error(file.append(b"x")), ErrorCode::InsufficientSpace);
+
+        // Shrinking, replacing and removing files frees up space.
+        file.set_len(2).unwrap();
+        root.write_file("a", "abc").unwrap();
+        assert_eq!(file.append(b"vwxyz").unwrap(), 5);
+        assert_eq!(error(file.append(b"x")), ErrorCode::InsufficientSpace);
+        root.unlink_file_at("a").unwrap();
+        drop(file);
+        root.unlink_file_at("c").unwrap();
+        root.write_file("d", "0123456789").unwrap();
+
+        // Without a limit, sizes that the host can't allocate fail too.
+        let root = MemoryDir::new();
+        let file = open(&root, "file", options).unwrap();
+        assert_eq!(error(file.set_len(1 << 50)), ErrorCode::InsufficientSpace);
+
+        // Files of the lower directory count once they're copied up.
+        let lower = MemoryDir::new();
+        lower.write_file("file", "lower").unwrap();
+        let root = MemoryDir::overlay(lower);
+        root.set_max_size(6);
+        let file = open(&root, "file", options).unwrap();
+        assert_eq!(error(file.append(b"xy")), ErrorCode::InsufficientSpace);
+        assert_eq!(file.append(b"x").unwrap(), 1);
+    }
+
+    #[test]
+    fn overlay() {
+        let lower = MemoryDir::new();
+        lower.write_file("dir/file", "lower").unwrap();
+        lower.write_file("dir/other", "other").unwrap();
+        let root = MemoryDir::overlay(lower.clone());
+        assert_eq!(root.read_file("dir/file").unwrap(), b"lower");
+
+        let options = OpenOptions {
+            write: true,
+            ..OpenOptions::default()
+        };
+        let file = open(&root, "dir/file", options).unwrap();
+        file.write_at(b"upper", 0).unwrap();
+        assert_eq!(root.read_file("dir/file").unwrap(), b"upper");
+        assert_eq!(lower.read_file("dir/file").unwrap(), b"lower");
+
+        root.unlink_file_at("dir/other").unwrap();
+        root.write_file("dir/new", "new").unwrap();
+        assert_eq!(error(root.read_file("dir/other")), ErrorCode::NoEntry);
+        assert_eq!(lower.read_file("dir/other").unwrap(), b"other");
+        let Opened::Dir(dir) = root.open_at("dir", &OpenOptions::default()).unwrap() else {
+            panic!()
+        };
+        let names = dir
+            .read_dir()
+            .unwrap()
+            .into_iter()
+            .map(|e| e.unwrap().name)
+            .collect::<Vec<_>>();
+        assert_eq!(names, ["file", "new"]);
+
+        // Entries removed from the overlay can be recreated.
+        root.write_file("dir/other", "again").unwrap();
+        assert_eq!(root.read_file("dir/other").unwrap(), b"again");
+    }
+}
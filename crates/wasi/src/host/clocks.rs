@@ -4,7 +4,9 @@ use crate::bindings::{
     clocks::monotonic_clock::{self, Duration as WasiDuration, Instant},
     clocks::wall_clock::{self, Datetime},
 };
+use crate::clocks::{HostMonotonicClock, VirtualClock};
 use crate::poll::{subscribe, Subscribe};
+use crate::trace::{traced_call, Event};
 use crate::{Pollable, WasiImpl, WasiView};
 use cap_std::time::SystemTime;
 use std::time::Duration;
@@ -29,18 +31,24 @@ where
     T: WasiView,
 {
     fn now(&mut self) -> anyhow::Result<Datetime> {
-        let now = self.ctx().wall_clock.now();
-        Ok(Datetime {
-            seconds: now.as_secs(),
-            nanoseconds: now.subsec_nanos(),
+        let ctx = self.ctx();
+        traced_call(&ctx.trace, Event::WallClockNow, || {
+            let now = ctx.wall_clock.now();
+            Datetime {
+                seconds: now.as_secs(),
+                nanoseconds: now.subsec_nanos(),
+            }
         })
     }
 
     fn resolution(&mut self) -> anyhow::Result<Datetime> {
-        let res = self.ctx().wall_clock.resolution();
-        Ok(Datetime {
-            seconds: res.as_secs(),
-            nanoseconds: res.subsec_nanos(),
+        let ctx = self.ctx();
+        traced_call(&ctx.trace, Event::WallClockResolution, || {
+            let res = ctx.wall_clock.resolution();
+            Datetime {
+                seconds: res.as_secs(),
+                nanoseconds: res.subsec_nanos(),
+            }
         })
     }
 }
@@ -64,19 +72,55 @@ fn subscribe_to_duration(
     subscribe(table, sleep)
 }
 
+fn subscribe_to_virtual_instant(
+    table: &mut wasmtime::component::ResourceTable,
+    clock: VirtualClock,
+    when: Option<Instant>,
+) -> anyhow::Result<Resource<Pollable>> {
+    let sleep = match when {
+        Some(when) if when <= clock.now() => table.push(Deadline::Past)?,
+        Some(when) => table.push(Deadline::Virtual(clock, when))?,
+        None => table.push(Deadline::Never)?,
+    };
+    subscribe(table, sleep)
+}
+
+impl<T> WasiImpl<T>
+where
+    T: WasiView,
+{
+    /// Whether a trace is being replayed, in which case the times that the
+    /// guest waits until have all passed already.
+    fn replaying(&mut self) -> bool {
+        self.ctx().trace.as_ref().is_some_and(|t| t.is_replay())
+    }
+}
+
 impl<T> monotonic_clock::Host for WasiImpl<T>
 where
     T: WasiView,
 {
     fn now(&mut self) -> anyhow::Result<Instant> {
-        Ok(self.ctx().monotonic_clock.now())
+        let ctx = self.ctx();
+        traced_call(&ctx.trace, Event::MonotonicClockNow, || {
+            ctx.monotonic_clock.now()
+        })
     }
 
     fn resolution(&mut self) -> anyhow::Result<Instant> {
-        Ok(self.ctx().monotonic_clock.resolution())
+        let ctx = self.ctx();
+        traced_call(&ctx.trace, Event::MonotonicClockResolution, || {
+            ctx.monotonic_clock.resolution()
+        })
     }
 
     fn subscribe_instant(&mut self, when: Instant) -> anyhow::Result<Resource<Pollable>> {
+        if self.replaying() {
+            return subscribe_to_duration(&mut self.table(), Duration::ZERO);
+        }
+        if let Some(clock) = self.ctx().virtual_clock.clone() {
+            return subscribe_to_virtual_instant(&mut self.table(), clock, Some(when));
+        }
         let clock_now = self.ctx().monotonic_clock.now();
         let duration = if when > clock_now {
             Duration::from_nanos(when - clock_now)
@@ -87,6 +131,13 @@ where
     }
 
     fn subscribe_duration(&mut self, duration: WasiDuration) -> anyhow::Result<Resource<Pollable>> {
+        if self.replaying() {
+            return subscribe_to_duration(&mut self.table(), Duration::ZERO);
+        }
+        if let Some(clock) = self.ctx().virtual_clock.clone() {
+            let when = clock.now().checked_add(duration);
+            return subscribe_to_virtual_instant(&mut self.table(), clock, when);
+        }
         subscribe_to_duration(&mut self.table(), Duration::from_nanos(duration))
     }
 }
@@ -94,6 +145,7 @@ where
 enum Deadline {
     Past,
     Instant(tokio::time::Instant),
+    Virtual(VirtualClock, Instant),
     Never,
 }
 
@@ -103,6 +155,7 @@ impl Subscribe for Deadline {
         match self {
             Deadline::Past => {}
             Deadline::Instant(instant) => tokio::time::sleep_until(*instant).await,
+            Deadline::Virtual(clock, when) => clock.sleep_until(*when).await,
             Deadline::Never => std::future::pending().await,
         }
     }
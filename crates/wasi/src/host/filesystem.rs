@@ -5,9 +5,10 @@ use crate::bindings::filesystem::types::{
 };
 use crate::bindings::io::streams::{InputStream, OutputStream};
 use crate::filesystem::{
-    Descriptor, Dir, File, FileInputStream, FileOutputStream, OpenMode, ReaddirIterator,
+    Descriptor, Dir, File, FileInputStream, FileOutputStream, Metadata, OpenMode, OpenOptions,
+    Opened, ReaddirIterator,
 };
-use crate::{DirPerms, FilePerms, FsError, FsResult, WasiImpl, WasiView};
+use crate::{DirPerms, FilePerms, FsError, FsResult, SystemTimeSpec, WasiImpl, WasiView};
 use anyhow::Context;
 use wasmtime::component::Resource;
 
@@ -69,18 +70,6 @@ where
         len: types::Filesize,
         advice: types::Advice,
     ) -> FsResult<()> {
-        use system_interface::fs::{Advice as A, FileIoExt};
-        use types::Advice;
-
-        let advice = match advice {
-            Advice::Normal => A::Normal,
-            Advice::Sequential => A::Sequential,
-            Advice::Random => A::Random,
-            Advice::WillNeed => A::WillNeed,
-            Advice::DontNeed => A::DontNeed,
-            Advice::NoReuse => A::NoReuse,
-        };
-
         let f = self.table().get(&fd)?.file()?;
         f.run_blocking(move |f| f.advise(offset, len, advice))
             .await?;
@@ -91,55 +80,24 @@ where
         let descriptor = self.table().get(&fd)?;
 
         match descriptor {
-            Descriptor::File(f) => {
-                match f.run_blocking(|f| f.sync_data()).await {
-                    Ok(()) => Ok(()),
-                    // On windows, `sync_data` uses `FileFlushBuffers` which fails with
-                    // `ERROR_ACCESS_DENIED` if the file is not upen for writing. Ignore
-                    // this error, for POSIX compatibility.
-                    #[cfg(windows)]
-                    Err(e)
-                        if e.raw_os_error()
-                            == Some(windows_sys::Win32::Foundation::ERROR_ACCESS_DENIED as _) =>
-                    {
-                        Ok(())
-                    }
-                    Err(e) => Err(e.into()),
-                }
-            }
-            Descriptor::Dir(d) => {
-                d.run_blocking(|d| Ok(d.open(std::path::Component::CurDir)?.sync_data()?))
-                    .await
-            }
+            Descriptor::File(f) => f.run_blocking(|f| f.sync(true)).await?,
+            Descriptor::Dir(d) => d.run_blocking(|d| d.sync(true)).await?,
         }
+        Ok(())
     }
 
     async fn get_flags(
         &mut self,
         fd: Resource<types::Descriptor>,
     ) -> FsResult<types::DescriptorFlags> {
-        use system_interface::fs::{FdFlags, GetSetFdFlags};
         use types::DescriptorFlags;
 
-        fn get_from_fdflags(flags: FdFlags) -> DescriptorFlags {
-            let mut out = DescriptorFlags::empty();
-            if flags.contains(FdFlags::DSYNC) {
-                out |= DescriptorFlags::REQUESTED_WRITE_SYNC;
-            }
-            if flags.contains(FdFlags::RSYNC) {
-                out |= DescriptorFlags::DATA_INTEGRITY_SYNC;
-            }
-            if flags.contains(FdFlags::SYNC) {
-                out |= DescriptorFlags::FILE_INTEGRITY_SYNC;
-            }
-            out
-        }
-
+        // Descriptors are never opened with any of the sync flags, as they
+        // aren't supported by `open_at`, so only the open mode is reflected.
         let descriptor = self.table().get(&fd)?;
         match descriptor {
             Descriptor::File(f) => {
-                let flags = f.run_blocking(|f| f.get_fd_flags()).await?;
-                let mut flags = get_from_fdflags(flags);
+                let mut flags = DescriptorFlags::empty();
                 if f.open_mode.contains(OpenMode::READ) {
                     flags |= DescriptorFlags::READ;
                 }
@@ -149,8 +107,7 @@ where
                 Ok(flags)
             }
             Descriptor::Dir(d) => {
-                let flags = d.run_blocking(|d| d.get_fd_flags()).await?;
-                let mut flags = get_from_fdflags(flags);
+                let mut flags = DescriptorFlags::empty();
                 if d.open_mode.contains(OpenMode::READ) {
                     flags |= DescriptorFlags::READ;
                 }
@@ -171,7 +128,7 @@ where
         match descriptor {
             Descriptor::File(f) => {
                 let meta = f.run_blocking(|f| f.metadata()).await?;
-                Ok(descriptortype_from(meta.file_type()))
+                Ok(meta.file_type)
             }
             Descriptor::Dir(_) => Ok(types::DescriptorType::Directory),
         }
@@ -196,8 +153,6 @@ where
         atim: types::NewTimestamp,
         mtim: types::NewTimestamp,
     ) -> FsResult<()> {
-        use fs_set_times::SetTimes;
-
         let descriptor = self.table().get(&fd)?;
         match descriptor {
             Descriptor::File(f) => {
@@ -227,9 +182,6 @@ where
         len: types::Filesize,
         offset: types::Filesize,
     ) -> FsResult<(Vec<u8>, bool)> {
-        use std::io::IoSliceMut;
-        use system_interface::fs::FileIoExt;
-
         let table = self.table();
 
         let f = table.get(&fd)?.file()?;
@@ -240,7 +192,7 @@ where
         let (mut buffer, r) = f
             .run_blocking(move |f| {
                 let mut buffer = vec![0; len.try_into().unwrap_or(usize::MAX)];
-                let r = f.read_vectored_at(&mut [IoSliceMut::new(&mut buffer)], offset);
+                let r = f.read_at(&mut buffer, offset);
                 (buffer, r)
             })
             .await;
@@ -265,18 +217,13 @@ where
         buf: Vec<u8>,
         offset: types::Filesize,
     ) -> FsResult<types::Filesize> {
-        use std::io::IoSlice;
-        use system_interface::fs::FileIoExt;
-
         let table = self.table();
         let f = table.get(&fd)?.file()?;
         if !f.perms.contains(FilePerms::WRITE) {
             return Err(ErrorCode::NotPermitted.into());
         }
 
-        let bytes_written = f
-            .run_blocking(move |f| f.write_vectored_at(&[IoSlice::new(&buf)], offset))
-            .await?;
+        let bytes_written = f.run_blocking(move |f| f.write_at(&buf, offset)).await?;
 
         Ok(types::Filesize::try_from(bytes_written).expect("usize fits in Filesize"))
     }
@@ -285,64 +232,25 @@ where
         &mut self,
         fd: Resource<types::Descriptor>,
     ) -> FsResult<Resource<types::DirectoryEntryStream>> {
+        let deterministic = self.ctx().deterministic;
         let table = self.table();
         let d = table.get(&fd)?.dir()?;
         if !d.perms.contains(DirPerms::READ) {
             return Err(ErrorCode::NotPermitted.into());
         }
 
-        enum ReaddirError {
-            Io(std::io::Error),
-            IllegalSequence,
+        let mut entries = d.run_blocking(|d| d.read_dir()).await?;
+        if deterministic {
+            // Sort by name rather than returning the host's order, with any
+            // errors left at the end.
+            entries.sort_by(|a, b| match (a, b) {
+                (Ok(a), Ok(b)) => a.name.cmp(&b.name),
+                (Ok(_), Err(_)) => std::cmp::Ordering::Less,
+                (Err(_), Ok(_)) => std::cmp::Ordering::Greater,
+                (Err(_), Err(_)) => std::cmp::Ordering::Equal,
+            });
         }
-        impl From<std::io::Error> for ReaddirError {
-            fn from(e: std::io::Error) -> ReaddirError {
-                ReaddirError::Io(e)
-            }
-        }
-
-        let entries = d
-            .run_blocking(|d| {
-                // Both `entries` and `metadata` perform syscalls, which is why they are done
-                // within this `block` call, rather than delay calculating the metadata
-                // for entries when they're demanded later in the iterator chain.
-                Ok::<_, std::io::Error>(
-                    d.entries()?
-                        .map(|entry| {
-                            let entry = entry?;
-                            let meta = entry.metadata()?;
-                            let type_ = descriptortype_from(meta.file_type());
-                            let name = entry
-                                .file_name()
-                                .into_string()
-                                .map_err(|_| ReaddirError::IllegalSequence)?;
-                            Ok(types::DirectoryEntry { type_, name })
-                        })
-                        .collect::<Vec<Result<types::DirectoryEntry, ReaddirError>>>(),
-                )
-            })
-            .await?
-            .into_iter();
-
-        // On windows, filter out files like `C:\DumpStack.log.tmp` which we
-        // can't get full metadata for.
-        #[cfg(windows)]
-        let entries = entries.filter(|entry| {
-            use windows_sys::Win32::Foundation::{ERROR_ACCESS_DENIED, ERROR_SHARING_VIOLATION};
-            if let Err(ReaddirError::Io(err)) = entry {
-                if err.raw_os_error() == Some(ERROR_SHARING_VIOLATION as i32)
-                    || err.raw_os_error() == Some(ERROR_ACCESS_DENIED as i32)
-                {
-                    return false;
-                }
-            }
-            true
-        });
-        let entries = entries.map(|r| match r {
-            Ok(r) => Ok(r),
-            Err(ReaddirError::Io(e)) => Err(e.into()),
-            Err(ReaddirError::IllegalSequence) => Err(ErrorCode::IllegalByteSequence.into()),
-        });
+        let entries = entries.into_iter().map(|r| r.map_err(FsError::from));
         Ok(table.push(ReaddirIterator::new(entries))?)
     }
 
@@ -350,27 +258,10 @@ where
         let descriptor = self.table().get(&fd)?;
 
         match descriptor {
-            Descriptor::File(f) => {
-                match f.run_blocking(|f| f.sync_all()).await {
-                    Ok(()) => Ok(()),
-                    // On windows, `sync_data` uses `FileFlushBuffers` which fails with
-                    // `ERROR_ACCESS_DENIED` if the file is not upen for writing. Ignore
-                    // this error, for POSIX compatibility.
-                    #[cfg(windows)]
-                    Err(e)
-                        if e.raw_os_error()
-                            == Some(windows_sys::Win32::Foundation::ERROR_ACCESS_DENIED as _) =>
-                    {
-                        Ok(())
-                    }
-                    Err(e) => Err(e.into()),
-                }
-            }
-            Descriptor::Dir(d) => {
-                d.run_blocking(|d| Ok(d.open(std::path::Component::CurDir)?.sync_all()?))
-                    .await
-            }
+            Descriptor::File(f) => f.run_blocking(|f| f.sync(false)).await?,
+            Descriptor::Dir(d) => d.run_blocking(|d| d.sync(false)).await?,
         }
+        Ok(())
     }
 
     async fn create_directory_at(
@@ -383,22 +274,23 @@ where
         if !d.perms.contains(DirPerms::MUTATE) {
             return Err(ErrorCode::NotPermitted.into());
         }
-        d.run_blocking(move |d| d.create_dir(&path)).await?;
+        d.run_blocking(move |d| d.create_dir_at(&path)).await?;
         Ok(())
     }
 
     async fn stat(&mut self, fd: Resource<types::Descriptor>) -> FsResult<types::DescriptorStat> {
+        let deterministic = self.ctx().deterministic;
         let descriptor = self.table().get(&fd)?;
         match descriptor {
             Descriptor::File(f) => {
                 // No permissions check on stat: if opened, allowed to stat it
                 let meta = f.run_blocking(|f| f.metadata()).await?;
-                Ok(descriptorstat_from(meta))
+                Ok(descriptorstat_from(meta, deterministic))
             }
             Descriptor::Dir(d) => {
                 // No permissions check on stat: if opened, allowed to stat it
-                let meta = d.run_blocking(|d| d.dir_metadata()).await?;
-                Ok(descriptorstat_from(meta))
+                let meta = d.run_blocking(|d| d.metadata()).await?;
+                Ok(descriptorstat_from(meta, deterministic))
             }
         }
     }
@@ -409,18 +301,18 @@ where
         path_flags: types::PathFlags,
         path: String,
     ) -> FsResult<types::DescriptorStat> {
+        let deterministic = self.ctx().deterministic;
         let table = self.table();
         let d = table.get(&fd)?.dir()?;
         if !d.perms.contains(DirPerms::READ) {
             return Err(ErrorCode::NotPermitted.into());
         }
 
-        let meta = if symlink_follow(path_flags) {
-            d.run_blocking(move |d| d.metadata(&path)).await?
-        } else {
-            d.run_blocking(move |d| d.symlink_metadata(&path)).await?
-        };
-        Ok(descriptorstat_from(meta))
+        let follow = symlink_follow(path_flags);
+        let meta = d
+            .run_blocking(move |d| d.metadata_at(&path, follow))
+            .await?;
+        Ok(descriptorstat_from(meta, deterministic))
     }
 
     async fn set_times_at(
@@ -431,8 +323,6 @@ where
         atim: types::NewTimestamp,
         mtim: types::NewTimestamp,
     ) -> FsResult<()> {
-        use cap_fs_ext::DirExt;
-
         let table = self.table();
         let d = table.get(&fd)?.dir()?;
         if !d.perms.contains(DirPerms::MUTATE) {
@@ -440,25 +330,9 @@ where
         }
         let atim = systemtimespec_from(atim)?;
         let mtim = systemtimespec_from(mtim)?;
-        if symlink_follow(path_flags) {
-            d.run_blocking(move |d| {
-                d.set_times(
-                    &path,
-                    atim.map(cap_fs_ext::SystemTimeSpec::from_std),
-                    mtim.map(cap_fs_ext::SystemTimeSpec::from_std),
-                )
-            })
+        let follow = symlink_follow(path_flags);
+        d.run_blocking(move |d| d.set_times_at(&path, atim, mtim, follow))
             .await?;
-        } else {
-            d.run_blocking(move |d| {
-                d.set_symlink_times(
-                    &path,
-                    atim.map(cap_fs_ext::SystemTimeSpec::from_std),
-                    mtim.map(cap_fs_ext::SystemTimeSpec::from_std),
-                )
-            })
-            .await?;
-        }
         Ok(())
     }
 
@@ -485,7 +359,7 @@ where
         }
         let new_dir_handle = std::sync::Arc::clone(&new_dir.dir);
         old_dir
-            .run_blocking(move |d| d.hard_link(&old_path, &new_dir_handle, &new_path))
+            .run_blocking(move |d| d.link_at(&old_path, &*new_dir_handle, &new_path))
             .await?;
         Ok(())
     }
@@ -498,8 +372,6 @@ where
         oflags: types::OpenFlags,
         flags: types::DescriptorFlags,
     ) -> FsResult<Resource<types::Descriptor>> {
-        use cap_fs_ext::{FollowSymlinks, OpenOptionsFollowExt, OpenOptionsMaybeDirExt};
-        use system_interface::fs::{FdFlags, GetSetFdFlags};
         use types::{DescriptorFlags, OpenFlags};
 
         let allow_blocking_current_thread = self.ctx().allow_blocking_current_thread;
@@ -522,44 +394,38 @@ where
         let mut create = false;
         // Track open mode, for permission check and recording in created descriptor:
         let mut open_mode = OpenMode::empty();
-        // Construct the OpenOptions to give the OS:
-        let mut opts = cap_std::fs::OpenOptions::new();
-        opts.maybe_dir(true);
+        // Construct the OpenOptions to give the filesystem:
+        let mut opts = OpenOptions::default();
 
         if oflags.contains(OpenFlags::CREATE) {
-            if oflags.contains(OpenFlags::EXCLUSIVE) {
-                opts.create_new(true);
-            } else {
-                opts.create(true);
-            }
+            opts.create = true;
+            opts.exclusive = oflags.contains(OpenFlags::EXCLUSIVE);
             create = true;
-            opts.write(true);
+            opts.write = true;
             open_mode |= OpenMode::WRITE;
         }
 
         if oflags.contains(OpenFlags::TRUNCATE) {
-            opts.truncate(true).write(true);
+            opts.truncate = true;
+            opts.write = true;
         }
         if flags.contains(DescriptorFlags::READ) {
-            opts.read(true);
+            opts.read = true;
             open_mode |= OpenMode::READ;
         }
         if flags.contains(DescriptorFlags::WRITE) {
-            opts.write(true);
+            opts.write = true;
             open_mode |= OpenMode::WRITE;
         } else {
             // If not opened write, open read. This way the OS lets us open
             // the file, but we can use perms to reject use of the file later.
-            opts.read(true);
+            opts.read = true;
             open_mode |= OpenMode::READ;
         }
-        if symlink_follow(path_flags) {
-            opts.follow(FollowSymlinks::Yes);
-        } else {
-            opts.follow(FollowSymlinks::No);
-        }
+        opts.follow_symlinks = symlink_follow(path_flags);
+        opts.directory = oflags.contains(OpenFlags::DIRECTORY);
 
-        // These flags are not yet supported in cap-std:
+        // These flags are not yet supported:
         if flags.contains(DescriptorFlags::FILE_INTEGRITY_SYNC)
             || flags.contains(DescriptorFlags::DATA_INTEGRITY_SYNC)
             || flags.contains(DescriptorFlags::REQUESTED_WRITE_SYNC)
@@ -585,36 +451,10 @@ where
             Err(ErrorCode::NotPermitted)?;
         }
 
-        // Represents each possible outcome from the spawn_blocking operation.
-        // This makes sure we don't have to give spawn_blocking any way to
-        // manipulate the table.
-        enum OpenResult {
-            Dir(cap_std::fs::Dir),
-            File(cap_std::fs::File),
-            NotDir,
-        }
-
-        let opened = d
-            .run_blocking::<_, std::io::Result<OpenResult>>(move |d| {
-                let mut opened = d.open_with(&path, &opts)?;
-                if opened.metadata()?.is_dir() {
-                    Ok(OpenResult::Dir(cap_std::fs::Dir::from_std_file(
-                        opened.into_std(),
-                    )))
-                } else if oflags.contains(OpenFlags::DIRECTORY) {
-                    Ok(OpenResult::NotDir)
-                } else {
-                    // FIXME cap-std needs a nonblocking open option so that files reads and writes
-                    // are nonblocking. Instead we set it after opening here:
-                    let set_fd_flags = opened.new_set_fd_flags(FdFlags::NONBLOCK)?;
-                    opened.set_fd_flags(set_fd_flags)?;
-                    Ok(OpenResult::File(opened))
-                }
-            })
-            .await?;
+        let opened = d.run_blocking(move |d| d.open_at(&path, &opts)).await?;
 
         match opened {
-            OpenResult::Dir(dir) => Ok(table.push(Descriptor::Dir(Dir::new(
+            Opened::Dir(dir) => Ok(table.push(Descriptor::Dir(Dir::new(
                 dir,
                 d.perms,
                 d.file_perms,
@@ -622,14 +462,12 @@ where
                 allow_blocking_current_thread,
             )))?),
 
-            OpenResult::File(file) => Ok(table.push(Descriptor::File(File::new(
+            Opened::File(file) => Ok(table.push(Descriptor::File(File::new(
                 file,
                 d.file_perms,
                 open_mode,
                 allow_blocking_current_thread,
             )))?),
-
-            OpenResult::NotDir => Err(ErrorCode::NotDirectory.into()),
         }
     }
 
@@ -656,7 +494,7 @@ where
         if !d.perms.contains(DirPerms::READ) {
             return Err(ErrorCode::NotPermitted.into());
         }
-        let link = d.run_blocking(move |d| d.read_link(&path)).await?;
+        let link = d.run_blocking(move |d| d.read_link_at(&path)).await?;
         Ok(link
             .into_os_string()
             .into_string()
@@ -673,7 +511,7 @@ where
         if !d.perms.contains(DirPerms::MUTATE) {
             return Err(ErrorCode::NotPermitted.into());
         }
-        Ok(d.run_blocking(move |d| d.remove_dir(&path)).await?)
+        Ok(d.run_blocking(move |d| d.remove_dir_at(&path)).await?)
     }
 
     async fn rename_at(
@@ -694,7 +532,7 @@ where
         }
         let new_dir_handle = std::sync::Arc::clone(&new_dir.dir);
         Ok(old_dir
-            .run_blocking(move |d| d.rename(&old_path, &new_dir_handle, &new_path))
+            .run_blocking(move |d| d.rename_at(&old_path, &*new_dir_handle, &new_path))
             .await?)
     }
 
@@ -704,16 +542,12 @@ where
         src_path: String,
         dest_path: String,
     ) -> FsResult<()> {
-        // On windows, Dir.symlink is provided by DirExt
-        #[cfg(windows)]
-        use cap_fs_ext::DirExt;
-
         let table = self.table();
         let d = table.get(&fd)?.dir()?;
         if !d.perms.contains(DirPerms::MUTATE) {
             return Err(ErrorCode::NotPermitted.into());
         }
-        Ok(d.run_blocking(move |d| d.symlink(&src_path, &dest_path))
+        Ok(d.run_blocking(move |d| d.symlink_at(&src_path, &dest_path))
             .await?)
     }
 
@@ -722,15 +556,12 @@ where
         fd: Resource<types::Descriptor>,
         path: String,
     ) -> FsResult<()> {
-        use cap_fs_ext::DirExt;
-
         let table = self.table();
         let d = table.get(&fd)?.dir()?;
         if !d.perms.contains(DirPerms::MUTATE) {
             return Err(ErrorCode::NotPermitted.into());
         }
-        Ok(d.run_blocking(move |d| d.remove_file_or_symlink(&path))
-            .await?)
+        Ok(d.run_blocking(move |d| d.unlink_file_at(&path)).await?)
     }
 
     fn read_via_stream(
@@ -802,12 +633,11 @@ where
         a: Resource<types::Descriptor>,
         b: Resource<types::Descriptor>,
     ) -> anyhow::Result<bool> {
-        use cap_fs_ext::MetadataExt;
         let descriptor_a = self.table().get(&a)?;
         let meta_a = get_descriptor_metadata(descriptor_a).await?;
         let descriptor_b = self.table().get(&b)?;
         let meta_b = get_descriptor_metadata(descriptor_b).await?;
-        if meta_a.dev() == meta_b.dev() && meta_a.ino() == meta_b.ino() {
+        if meta_a.dev == meta_b.dev && meta_a.ino == meta_b.ino {
             // MetadataHashValue does not derive eq, so use a pair of
             // comparisons to check equality:
             debug_assert_eq!(
@@ -841,14 +671,9 @@ where
         let table = self.table();
         let d = table.get(&fd)?.dir()?;
         // No permissions check on metadata: if dir opened, allowed to stat it
+        let follow = symlink_follow(path_flags);
         let meta = d
-            .run_blocking(move |d| {
-                if symlink_follow(path_flags) {
-                    d.metadata(path)
-                } else {
-                    d.symlink_metadata(path)
-                }
-            })
+            .run_blocking(move |d| d.metadata_at(&path, follow))
             .await?;
         Ok(calculate_metadata_hash(&meta))
     }
@@ -874,7 +699,7 @@ where
     }
 }
 
-async fn get_descriptor_metadata(fd: &types::Descriptor) -> FsResult<cap_std::fs::Metadata> {
+async fn get_descriptor_metadata(fd: &types::Descriptor) -> FsResult<Metadata> {
     match fd {
         Descriptor::File(f) => {
             // No permissions check on metadata: if opened, allowed to stat it
@@ -882,21 +707,20 @@ async fn get_descriptor_metadata(fd: &types::Descriptor) -> FsResult<cap_std::fs
         }
         Descriptor::Dir(d) => {
             // No permissions check on metadata: if opened, allowed to stat it
-            Ok(d.run_blocking(|d| d.dir_metadata()).await?)
+            Ok(d.run_blocking(|d| d.metadata()).await?)
         }
     }
 }
 
-fn calculate_metadata_hash(meta: &cap_std::fs::Metadata) -> types::MetadataHashValue {
-    use cap_fs_ext::MetadataExt;
+fn calculate_metadata_hash(meta: &Metadata) -> types::MetadataHashValue {
     // Without incurring any deps, std provides us with a 64 bit hash
     // function:
     use std::hash::Hasher;
     // Note that this means that the metadata hash (which becomes a preview1 ino) may
     // change when a different rustc release is used to build this host implementation:
     let mut hasher = std::collections::hash_map::DefaultHasher::new();
-    hasher.write_u64(meta.dev());
-    hasher.write_u64(meta.ino());
+    hasher.write_u64(meta.dev);
+    hasher.write_u64(meta.ino);
     let lower = hasher.finish();
     // MetadataHashValue has a pair of 64-bit members for representing a
     // single 128-bit number. However, we only have 64 bits of entropy. To
@@ -989,6 +813,10 @@ impl From<std::io::Error> for ErrorCode {
 
 impl<'a> From<&'a std::io::Error> for ErrorCode {
     fn from(err: &'a std::io::Error) -> ErrorCode {
+        // Filesystems other than the host's report errors as error codes.
+        if let Some(code) = err.get_ref().and_then(|e| e.downcast_ref::<ErrorCode>()) {
+            return *code;
+        }
         match from_raw_os_error(err.raw_os_error()) {
             Some(errno) => errno,
             None => {
@@ -1005,6 +833,22 @@ impl<'a> From<&'a std::io::Error> for ErrorCode {
     }
 }
 
+impl From<ErrorCode> for std::io::Error {
+    fn from(code: ErrorCode) -> std::io::Error {
+        use std::io::ErrorKind;
+        let kind = match code {
+            ErrorCode::NoEntry => ErrorKind::NotFound,
+            ErrorCode::Access | ErrorCode::NotPermitted => ErrorKind::PermissionDenied,
+            ErrorCode::Exist => ErrorKind::AlreadyExists,
+            ErrorCode::Invalid => ErrorKind::InvalidInput,
+            ErrorCode::WouldBlock => ErrorKind::WouldBlock,
+            ErrorCode::Interrupted => ErrorKind::Interrupted,
+            _ => ErrorKind::Other,
+        };
+        std::io::Error::new(kind, code)
+    }
+}
+
 impl From<cap_rand::Error> for ErrorCode {
     fn from(err: cap_rand::Error) -> ErrorCode {
         // I picked Error::Io as a 'reasonable default', FIXME dan is this ok?
@@ -1018,31 +862,14 @@ impl From<std::num::TryFromIntError> for ErrorCode {
     }
 }
 
-fn descriptortype_from(ft: cap_std::fs::FileType) -> types::DescriptorType {
-    use cap_fs_ext::FileTypeExt;
-    use types::DescriptorType;
-    if ft.is_dir() {
-        DescriptorType::Directory
-    } else if ft.is_symlink() {
-        DescriptorType::SymbolicLink
-    } else if ft.is_block_device() {
-        DescriptorType::BlockDevice
-    } else if ft.is_char_device() {
-        DescriptorType::CharacterDevice
-    } else if ft.is_file() {
-        DescriptorType::RegularFile
-    } else {
-        DescriptorType::Unknown
-    }
-}
-
-fn systemtimespec_from(t: types::NewTimestamp) -> FsResult<Option<fs_set_times::SystemTimeSpec>> {
-    use fs_set_times::SystemTimeSpec;
+fn systemtimespec_from(t: types::NewTimestamp) -> FsResult<Option<SystemTimeSpec>> {
     use types::NewTimestamp;
     match t {
         NewTimestamp::NoChange => Ok(None),
         NewTimestamp::Now => Ok(Some(SystemTimeSpec::SymbolicNow)),
-        NewTimestamp::Timestamp(st) => Ok(Some(SystemTimeSpec::Absolute(systemtime_from(st)?))),
+        NewTimestamp::Timestamp(st) => Ok(Some(SystemTimeSpec::Absolute(
+            cap_std::time::SystemTime::from_std(systemtime_from(st)?),
+        ))),
     }
 }
 
@@ -1058,15 +885,30 @@ fn datetime_from(t: std::time::SystemTime) -> wall_clock::Datetime {
     wall_clock::Datetime::try_from(cap_std::time::SystemTime::from_std(t)).unwrap()
 }
 
-fn descriptorstat_from(meta: cap_std::fs::Metadata) -> types::DescriptorStat {
-    use cap_fs_ext::MetadataExt;
+/// Converts `meta` to a `descriptor-stat`, reporting every timestamp as the
+/// Unix epoch when `frozen_times` is set.
+fn descriptorstat_from(meta: Metadata, frozen_times: bool) -> types::DescriptorStat {
+    if frozen_times {
+        let epoch = Some(wall_clock::Datetime {
+            seconds: 0,
+            nanoseconds: 0,
+        });
+        return types::DescriptorStat {
+            type_: meta.file_type,
+            link_count: meta.nlink,
+            size: meta.len,
+            data_access_timestamp: epoch,
+            data_modification_timestamp: epoch,
+            status_change_timestamp: epoch,
+        };
+    }
     types::DescriptorStat {
-        type_: descriptortype_from(meta.file_type()),
-        link_count: meta.nlink(),
-        size: meta.len(),
-        data_access_timestamp: meta.accessed().map(|t| datetime_from(t.into_std())).ok(),
-        data_modification_timestamp: meta.modified().map(|t| datetime_from(t.into_std())).ok(),
-        status_change_timestamp: meta.created().map(|t| datetime_from(t.into_std())).ok(),
+        type_: meta.file_type,
+        link_count: meta.nlink,
+        size: meta.len,
+        data_access_timestamp: meta.accessed.map(datetime_from),
+        data_modification_timestamp: meta.modified.map(datetime_from),
+        status_change_timestamp: meta.created.map(datetime_from),
     }
 }
 
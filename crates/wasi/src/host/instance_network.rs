@@ -11,6 +11,7 @@ where
         let network = Network {
             socket_addr_check: self.ctx().socket_addr_check.clone(),
             allow_ip_name_lookup: self.ctx().allowed_network_uses.ip_name_lookup,
+            resolver: self.ctx().ip_name_resolver.clone(),
         };
         let network = self.table().push(network)?;
         Ok(network)
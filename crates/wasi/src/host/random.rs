@@ -1,4 +1,5 @@
 use crate::bindings::random::{insecure, insecure_seed, random};
+use crate::trace::{traced_call, Event};
 use crate::{WasiImpl, WasiView};
 use cap_rand::{distributions::Standard, Rng};
 
@@ -7,14 +8,18 @@ where
     T: WasiView,
 {
     fn get_random_bytes(&mut self, len: u64) -> anyhow::Result<Vec<u8>> {
-        Ok((&mut self.ctx().random)
-            .sample_iter(Standard)
-            .take(len as usize)
-            .collect())
+        let ctx = self.ctx();
+        traced_call(&ctx.trace, Event::RandomBytes, || {
+            (&mut ctx.random)
+                .sample_iter(Standard)
+                .take(len as usize)
+                .collect()
+        })
     }
 
     fn get_random_u64(&mut self) -> anyhow::Result<u64> {
-        Ok(self.ctx().random.sample(Standard))
+        let ctx = self.ctx();
+        traced_call(&ctx.trace, Event::RandomU64, || ctx.random.sample(Standard))
     }
 }
 
@@ -23,14 +28,20 @@ where
     T: WasiView,
 {
     fn get_insecure_random_bytes(&mut self, len: u64) -> anyhow::Result<Vec<u8>> {
-        Ok((&mut self.ctx().insecure_random)
-            .sample_iter(Standard)
-            .take(len as usize)
-            .collect())
+        let ctx = self.ctx();
+        traced_call(&ctx.trace, Event::InsecureRandomBytes, || {
+            (&mut ctx.insecure_random)
+                .sample_iter(Standard)
+                .take(len as usize)
+                .collect()
+        })
     }
 
     fn get_insecure_random_u64(&mut self) -> anyhow::Result<u64> {
-        Ok(self.ctx().insecure_random.sample(Standard))
+        let ctx = self.ctx();
+        traced_call(&ctx.trace, Event::InsecureRandomU64, || {
+            ctx.insecure_random.sample(Standard)
+        })
     }
 }
 
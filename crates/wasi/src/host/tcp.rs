@@ -180,7 +180,7 @@ where
         value: bool,
     ) -> SocketResult<()> {
         let table = self.table();
-        let socket = table.get(&this)?;
+        let socket = table.get_mut(&this)?;
         socket.set_keep_alive_enabled(value)
     }
 
@@ -213,7 +213,7 @@ where
         value: u64,
     ) -> SocketResult<()> {
         let table = self.table();
-        let socket = table.get(&this)?;
+        let socket = table.get_mut(&this)?;
         socket.set_keep_alive_interval(Duration::from_nanos(value))
     }
 
@@ -229,7 +229,7 @@ where
         value: u32,
     ) -> SocketResult<()> {
         let table = self.table();
-        let socket = table.get(&this)?;
+        let socket = table.get_mut(&this)?;
         socket.set_keep_alive_count(value)
     }
 
@@ -5,7 +5,7 @@ use crate::{
         sockets::network::{ErrorCode, IpAddressFamily, IpSocketAddress, Network},
         sockets::udp,
     },
-    udp::{IncomingDatagramStream, OutgoingDatagramStream, SendState, UdpState},
+    udp::{IncomingDatagramStream, OutgoingDatagramStream, SendState, UdpInner, UdpState},
     Subscribe,
 };
 use crate::{Pollable, SocketError, SocketResult, WasiImpl, WasiView};
@@ -59,18 +59,23 @@ where
         {
             check.check(local_address, SocketAddrUse::UdpBind).await?;
 
-            // Perform the OS bind call.
-            util::udp_bind(socket.udp_socket(), &local_address).map_err(|error| match error {
-                // From https://pubs.opengroup.org/onlinepubs/9699919799/functions/bind.html:
-                // > [EAFNOSUPPORT] The specified address is not a valid address for the address family of the specified socket
-                //
-                // The most common reasons for this error should have already
-                // been handled by our own validation slightly higher up in this
-                // function. This error mapping is here just in case there is
-                // an edge case we didn't catch.
-                Errno::AFNOSUPPORT => ErrorCode::InvalidArgument,
-                _ => ErrorCode::from(error),
-            })?;
+            match &socket.inner {
+                // Perform the OS bind call.
+                UdpInner::Host(inner) => {
+                    util::udp_bind(&**inner, &local_address).map_err(|error| match error {
+                        // From https://pubs.opengroup.org/onlinepubs/9699919799/functions/bind.html:
+                        // > [EAFNOSUPPORT] The specified address is not a valid address for the address family of the specified socket
+                        //
+                        // The most common reasons for this error should have already
+                        // been handled by our own validation slightly higher up in this
+                        // function. This error mapping is here just in case there is
+                        // an edge case we didn't catch.
+                        Errno::AFNOSUPPORT => ErrorCode::InvalidArgument,
+                        _ => ErrorCode::from(error),
+                    })?
+                }
+                UdpInner::Virtual(inner) => inner.bind(local_address)?,
+            }
         }
 
         let socket = table.get_mut(&this)?;
@@ -126,7 +131,10 @@ where
 
         // Step #1: Disconnect
         if let UdpState::Connected = socket.udp_state {
-            util::udp_disconnect(socket.udp_socket())?;
+            match &socket.inner {
+                UdpInner::Host(inner) => util::udp_disconnect(&**inner)?,
+                UdpInner::Virtual(inner) => inner.disconnect(),
+            }
             socket.udp_state = UdpState::Bound;
         }
 
@@ -139,18 +147,21 @@ where
             util::validate_address_family(&connect_addr, &socket.family)?;
             check.check(connect_addr, SocketAddrUse::UdpConnect).await?;
 
-            rustix::net::connect(socket.udp_socket(), &connect_addr).map_err(
-                |error| match error {
-                    Errno::AFNOSUPPORT => ErrorCode::InvalidArgument, // See `bind` implementation.
-                    Errno::INPROGRESS => {
-                        tracing::debug!(
-                            "UDP connect returned EINPROGRESS, which should never happen"
-                        );
-                        ErrorCode::Unknown
-                    }
-                    _ => ErrorCode::from(error),
-                },
-            )?;
+            match &socket.inner {
+                UdpInner::Host(inner) => {
+                    rustix::net::connect(&**inner, &connect_addr).map_err(|error| match error {
+                        Errno::AFNOSUPPORT => ErrorCode::InvalidArgument, // See `bind` implementation.
+                        Errno::INPROGRESS => {
+                            tracing::debug!(
+                                "UDP connect returned EINPROGRESS, which should never happen"
+                            );
+                            ErrorCode::Unknown
+                        }
+                        _ => ErrorCode::from(error),
+                    })?
+                }
+                UdpInner::Virtual(inner) => inner.connect(connect_addr)?,
+            }
             socket.udp_state = UdpState::Connected;
         }
 
@@ -182,10 +193,12 @@ where
             _ => {}
         }
 
-        let addr = socket
-            .udp_socket()
-            .as_socketlike_view::<std::net::UdpSocket>()
-            .local_addr()?;
+        let addr = match &socket.inner {
+            UdpInner::Host(inner) => inner
+                .as_socketlike_view::<std::net::UdpSocket>()
+                .local_addr()?,
+            UdpInner::Virtual(inner) => inner.local_address()?,
+        };
         Ok(addr.into())
     }
 
@@ -198,10 +211,12 @@ where
             _ => return Err(ErrorCode::InvalidState.into()),
         }
 
-        let addr = socket
-            .udp_socket()
-            .as_socketlike_view::<std::net::UdpSocket>()
-            .peer_addr()?;
+        let addr = match &socket.inner {
+            UdpInner::Host(inner) => inner
+                .as_socketlike_view::<std::net::UdpSocket>()
+                .peer_addr()?,
+            UdpInner::Virtual(inner) => inner.remote_address()?,
+        };
         Ok(addr.into())
     }
 
@@ -222,9 +237,12 @@ where
         let table = self.table();
         let socket = table.get(&this)?;
 
-        let ttl = match socket.family {
-            SocketAddressFamily::Ipv4 => util::get_ip_ttl(socket.udp_socket())?,
-            SocketAddressFamily::Ipv6 => util::get_ipv6_unicast_hops(socket.udp_socket())?,
+        let ttl = match (&socket.inner, socket.family) {
+            (UdpInner::Host(inner), SocketAddressFamily::Ipv4) => util::get_ip_ttl(&**inner)?,
+            (UdpInner::Host(inner), SocketAddressFamily::Ipv6) => {
+                util::get_ipv6_unicast_hops(&**inner)?
+            }
+            (UdpInner::Virtual(inner), _) => inner.hop_limit()?,
         };
 
         Ok(ttl)
@@ -238,9 +256,14 @@ where
         let table = self.table();
         let socket = table.get(&this)?;
 
-        match socket.family {
-            SocketAddressFamily::Ipv4 => util::set_ip_ttl(socket.udp_socket(), value)?,
-            SocketAddressFamily::Ipv6 => util::set_ipv6_unicast_hops(socket.udp_socket(), value)?,
+        match (&socket.inner, socket.family) {
+            (UdpInner::Host(inner), SocketAddressFamily::Ipv4) => {
+                util::set_ip_ttl(&**inner, value)?
+            }
+            (UdpInner::Host(inner), SocketAddressFamily::Ipv6) => {
+                util::set_ipv6_unicast_hops(&**inner, value)?
+            }
+            (UdpInner::Virtual(inner), _) => inner.set_hop_limit(value)?,
         }
 
         Ok(())
@@ -250,7 +273,10 @@ where
         let table = self.table();
         let socket = table.get(&this)?;
 
-        let value = util::get_socket_recv_buffer_size(socket.udp_socket())?;
+        let value = match &socket.inner {
+            UdpInner::Host(inner) => util::get_socket_recv_buffer_size(&**inner)?,
+            UdpInner::Virtual(inner) => inner.receive_buffer_size()?,
+        };
         Ok(value as u64)
     }
 
@@ -263,7 +289,10 @@ where
         let socket = table.get(&this)?;
         let value = value.try_into().unwrap_or(usize::MAX);
 
-        util::set_socket_recv_buffer_size(socket.udp_socket(), value)?;
+        match &socket.inner {
+            UdpInner::Host(inner) => util::set_socket_recv_buffer_size(&**inner, value)?,
+            UdpInner::Virtual(inner) => inner.set_receive_buffer_size(value)?,
+        }
         Ok(())
     }
 
@@ -271,7 +300,10 @@ where
         let table = self.table();
         let socket = table.get(&this)?;
 
-        let value = util::get_socket_send_buffer_size(socket.udp_socket())?;
+        let value = match &socket.inner {
+            UdpInner::Host(inner) => util::get_socket_send_buffer_size(&**inner)?,
+            UdpInner::Virtual(inner) => inner.send_buffer_size()?,
+        };
         Ok(value as u64)
     }
 
@@ -284,7 +316,10 @@ where
         let socket = table.get(&this)?;
         let value = value.try_into().unwrap_or(usize::MAX);
 
-        util::set_socket_send_buffer_size(socket.udp_socket(), value)?;
+        match &socket.inner {
+            UdpInner::Host(inner) => util::set_socket_send_buffer_size(&**inner, value)?,
+            UdpInner::Virtual(inner) => inner.set_send_buffer_size(value)?,
+        }
         Ok(())
     }
 
@@ -317,9 +352,18 @@ where
         fn recv_one(
             stream: &IncomingDatagramStream,
         ) -> SocketResult<Option<udp::IncomingDatagram>> {
-            let mut buf = [0; MAX_UDP_DATAGRAM_SIZE];
-            let (size, received_addr) = stream.inner.try_recv_from(&mut buf)?;
-            debug_assert!(size <= buf.len());
+            let (data, received_addr) = match &stream.inner {
+                UdpInner::Host(inner) => {
+                    let mut buf = [0; MAX_UDP_DATAGRAM_SIZE];
+                    let (size, received_addr) = inner.try_recv_from(&mut buf)?;
+                    debug_assert!(size <= buf.len());
+                    (buf[..size].into(), received_addr)
+                }
+                UdpInner::Virtual(inner) => {
+                    let (data, received_addr) = inner.try_recv_from()?;
+                    (data.into(), received_addr)
+                }
+            };
 
             match stream.remote_address {
                 Some(connected_addr) if connected_addr != received_addr => {
@@ -330,7 +374,7 @@ where
             }
 
             Ok(Some(udp::IncomingDatagram {
-                data: buf[..size].into(),
+                data,
                 remote_address: received_addr.into(),
             }))
         }
@@ -390,11 +434,16 @@ where
 #[async_trait]
 impl Subscribe for IncomingDatagramStream {
     async fn ready(&mut self) {
-        // FIXME: Add `Interest::ERROR` when we update to tokio 1.32.
-        self.inner
-            .ready(Interest::READABLE)
-            .await
-            .expect("failed to await UDP socket readiness");
+        match &self.inner {
+            UdpInner::Host(inner) => {
+                // FIXME: Add `Interest::ERROR` when we update to tokio 1.32.
+                inner
+                    .ready(Interest::READABLE)
+                    .await
+                    .expect("failed to await UDP socket readiness");
+            }
+            UdpInner::Virtual(inner) => inner.readable().await,
+        }
     }
 }
 
@@ -454,10 +503,14 @@ where
             util::validate_remote_address(&addr)?;
             util::validate_address_family(&addr, &stream.family)?;
 
-            if stream.remote_address == Some(addr) {
-                stream.inner.try_send(&datagram.data)?;
-            } else {
-                stream.inner.try_send_to(&datagram.data, addr)?;
+            match &stream.inner {
+                UdpInner::Host(inner) if stream.remote_address == Some(addr) => {
+                    inner.try_send(&datagram.data)?;
+                }
+                UdpInner::Host(inner) => {
+                    inner.try_send_to(&datagram.data, addr)?;
+                }
+                UdpInner::Virtual(inner) => inner.send_to(&datagram.data, addr)?,
             }
 
             Ok(())
@@ -533,11 +586,14 @@ impl Subscribe for OutgoingDatagramStream {
         match self.send_state {
             SendState::Idle | SendState::Permitted(_) => {}
             SendState::Waiting => {
-                // FIXME: Add `Interest::ERROR` when we update to tokio 1.32.
-                self.inner
-                    .ready(Interest::WRITABLE)
-                    .await
-                    .expect("failed to await UDP socket readiness");
+                // Sockets on a virtual network never have to wait for sending.
+                if let UdpInner::Host(inner) = &self.inner {
+                    // FIXME: Add `Interest::ERROR` when we update to tokio 1.32.
+                    inner
+                        .ready(Interest::WRITABLE)
+                        .await
+                        .expect("failed to await UDP socket readiness");
+                }
                 self.send_state = SendState::Idle;
             }
         }
@@ -11,7 +11,10 @@ where
         &mut self,
         address_family: IpAddressFamily,
     ) -> SocketResult<Resource<UdpSocket>> {
-        let socket = UdpSocket::new(address_family.into())?;
+        let socket = match &self.ctx().virtual_network {
+            Some(network) => UdpSocket::new_virtual(network, address_family.into()),
+            None => UdpSocket::new(address_family.into())?,
+        };
         let socket = self.table().push(socket)?;
         Ok(socket)
     }
@@ -2,18 +2,171 @@ use crate::bindings::sockets::ip_name_lookup::{Host, HostResolveAddressStream};
 use crate::bindings::sockets::network::{ErrorCode, IpAddress, Network};
 use crate::host::network::util;
 use crate::poll::{subscribe, Pollable, Subscribe};
-use crate::runtime::{spawn_blocking, AbortOnDropJoinHandle};
-use crate::{SocketError, WasiImpl, WasiView};
-use anyhow::Result;
+use crate::runtime::{spawn, spawn_blocking, AbortOnDropJoinHandle};
+use crate::{SocketError, SocketResult, WasiImpl, WasiView};
+use anyhow::{bail, Result};
+use std::collections::HashMap;
 use std::mem;
-use std::net::{Ipv6Addr, ToSocketAddrs};
+use std::net::{IpAddr, Ipv6Addr, ToSocketAddrs};
 use std::pin::Pin;
 use std::str::FromStr;
+use std::sync::Arc;
 use std::vec;
 use wasmtime::component::Resource;
 
 use super::network::{from_ipv4_addr, from_ipv6_addr};
 
+/// A resolver of domain names for `wasi:sockets/ip-name-lookup`.
+///
+/// Resolvers are only consulted for domain names: guests looking up IP
+/// addresses get them back as they are. The addresses returned by a resolver
+/// are handed to the guest, which is still subject to the
+/// [`WasiCtxBuilder::socket_addr_check`] when binding or connecting to them.
+///
+/// [`WasiCtxBuilder::socket_addr_check`]: crate::WasiCtxBuilder::socket_addr_check
+#[async_trait::async_trait]
+pub trait HostResolver: Send + Sync {
+    /// Resolves the domain `name` to its addresses.
+    ///
+    /// The name is in lowercase ASCII, with internationalized names converted
+    /// to punycode. Names that don't exist should fail with
+    /// `name-unresolvable`, and names that the guest isn't allowed to look up
+    /// with `permanent-resolver-failure`.
+    async fn resolve(&self, name: &str) -> SocketResult<Vec<IpAddr>>;
+}
+
+/// Resolves names with the resolver of the host's operating system.
+///
+/// This is the default resolver of [`WasiCtxBuilder`](crate::WasiCtxBuilder).
+pub struct SystemResolver;
+
+#[async_trait::async_trait]
+impl HostResolver for SystemResolver {
+    async fn resolve(&self, name: &str) -> SocketResult<Vec<IpAddr>> {
+        let name = name.to_string();
+        spawn_blocking(move || {
+            // For now use the standard library to perform actual resolution through
+            // the usage of the `ToSocketAddrs` trait. This is only
+            // resolving names, not ports, so force the port to be 0.
+            let addresses = (name.as_str(), 0)
+                .to_socket_addrs()
+                .map_err(|_| ErrorCode::NameUnresolvable)? // If/when we use `getaddrinfo` directly, map the error properly.
+                .map(|addr| addr.ip())
+                .collect();
+            Ok(addresses)
+        })
+        .await
+    }
+}
+
+/// Resolves names from a fixed table, in the style of `/etc/hosts`.
+///
+/// Names missing from the table fail with `name-unresolvable`, unless a
+/// [fallback](HostsResolver::fallback) is configured to resolve them instead.
+/// This can be used to run guests without any access to the network, or to
+/// override the addresses of a few names.
+///
+/// ```
+/// use std::net::Ipv4Addr;
+/// use wasmtime_wasi::{HostsResolver, SystemResolver, WasiCtxBuilder};
+///
+/// # fn main() -> anyhow::Result<()> {
+/// let mut resolver = HostsResolver::parse(
+///     "127.0.0.1 localhost
+///      ::1       localhost
+///      10.0.0.2  db.internal cache.internal  # services of the embedder",
+/// )?;
+/// resolver
+///     .insert("api.internal", Ipv4Addr::new(10, 0, 0, 3).into())
+///     .fallback(SystemResolver);
+///
+/// let mut wasi = WasiCtxBuilder::new();
+/// wasi.allow_ip_name_lookup(true).ip_name_resolver(resolver);
+/// # Ok(())
+/// # }
+/// ```
+#[derive(Default, Clone)]
+pub struct HostsResolver {
+    hosts: HashMap<String, Vec<IpAddr>>,
+    fallback: Option<Arc<dyn HostResolver>>,
+}
+
+impl HostsResolver {
+    /// Creates a resolver without any names.
+    pub fn new() -> HostsResolver {
+        HostsResolver::default()
+    }
+
+    /// Creates a resolver with the names of `hosts`, which is in the format
+    /// of `/etc/hosts`.
+    ///
+    /// Each line has an IP address followed by the names that resolve to it,
+    /// separated by whitespace, and `#` starts a comment.
+    pub fn parse(hosts: &str) -> Result<HostsResolver> {
+        let mut resolver = HostsResolver::new();
+        for (i, line) in hosts.lines().enumerate() {
+            let line = match line.split_once('#') {
+                Some((line, _comment)) => line,
+                None => line,
+            };
+            let mut fields = line.split_whitespace();
+            let Some(addr) = fields.next() else {
+                continue;
+            };
+            let Ok(addr) = addr.parse::<IpAddr>() else {
+                bail!("invalid IP address `{addr}` on line {}", i + 1);
+            };
+            let mut names = fields.peekable();
+            if names.peek().is_none() {
+                bail!("no names for `{addr}` on line {}", i + 1);
+            }
+            for name in names {
+                resolver.insert(name, addr);
+            }
+        }
+        Ok(resolver)
+    }
+
+    /// Adds `addr` to the addresses that `name` resolves to.
+    ///
+    /// Names match regardless of case and of a trailing `.`.
+    pub fn insert(&mut self, name: &str, addr: IpAddr) -> &mut Self {
+        let addrs = self.hosts.entry(normalize(name)).or_default();
+        if !addrs.contains(&addr) {
+            addrs.push(addr);
+        }
+        self
+    }
+
+    /// Resolves names missing from this resolver with `resolver`.
+    pub fn fallback(&mut self, resolver: impl HostResolver + 'static) -> &mut Self {
+        self.fallback = Some(Arc::new(resolver));
+        self
+    }
+}
+
+#[async_trait::async_trait]
+impl HostResolver for HostsResolver {
+    async fn resolve(&self, name: &str) -> SocketResult<Vec<IpAddr>> {
+        match self.hosts.get(&normalize(name)) {
+            Some(addrs) => Ok(addrs.clone()),
+            None => match &self.fallback {
+                Some(fallback) => fallback.resolve(name).await,
+                None => Err(ErrorCode::NameUnresolvable.into()),
+            },
+        }
+    }
+}
+
+/// Converts `name` to the form given to resolvers.
+fn normalize(name: &str) -> String {
+    let name = name.strip_suffix('.').unwrap_or(name);
+    match url::Host::parse(name) {
+        Ok(url::Host::Domain(domain)) => domain,
+        _ => name.to_ascii_lowercase(),
+    }
+}
+
 pub enum ResolveAddressStream {
     Waiting(AbortOnDropJoinHandle<Result<Vec<IpAddress>, SocketError>>),
     Done(Result<vec::IntoIter<IpAddress>, SocketError>),
@@ -37,8 +190,27 @@ where
             return Err(ErrorCode::PermanentResolverFailure.into());
         }
 
-        let task = spawn_blocking(move || blocking_resolve(&host));
-        let resource = self.table().push(ResolveAddressStream::Waiting(task))?;
+        let stream = match host {
+            url::Host::Ipv4(v4addr) => {
+                let addresses = vec![IpAddress::Ipv4(from_ipv4_addr(v4addr))];
+                ResolveAddressStream::Done(Ok(addresses.into_iter()))
+            }
+            url::Host::Ipv6(v6addr) => {
+                let addresses = vec![IpAddress::Ipv6(from_ipv6_addr(v6addr))];
+                ResolveAddressStream::Done(Ok(addresses.into_iter()))
+            }
+            url::Host::Domain(domain) => {
+                let resolver = network.resolver.clone();
+                ResolveAddressStream::Waiting(spawn(async move {
+                    let addresses = resolver.resolve(&domain).await?;
+                    Ok(addresses
+                        .iter()
+                        .map(|addr| util::to_canonical(addr).into())
+                        .collect())
+                }))
+            }
+        };
+        let resource = self.table().push(stream)?;
         Ok(resource)
     }
 }
@@ -112,21 +284,71 @@ fn parse(name: &str) -> Result<url::Host, SocketError> {
     }
 }
 
-fn blocking_resolve(host: &url::Host) -> Result<Vec<IpAddress>, SocketError> {
-    match host {
-        url::Host::Ipv4(v4addr) => Ok(vec![IpAddress::Ipv4(from_ipv4_addr(*v4addr))]),
-        url::Host::Ipv6(v6addr) => Ok(vec![IpAddress::Ipv6(from_ipv6_addr(*v6addr))]),
-        url::Host::Domain(domain) => {
-            // For now use the standard library to perform actual resolution through
-            // the usage of the `ToSocketAddrs` trait. This is only
-            // resolving names, not ports, so force the port to be 0.
-            let addresses = (domain.as_str(), 0)
-                .to_socket_addrs()
-                .map_err(|_| ErrorCode::NameUnresolvable)? // If/when we use `getaddrinfo` directly, map the error properly.
-                .map(|addr| util::to_canonical(&addr.ip()).into())
-                .collect();
+#[cfg(test)]
+mod test {
+    use super::*;
+    use crate::runtime::in_tokio;
+    use std::net::{Ipv4Addr, Ipv6Addr};
 
-            Ok(addresses)
-        }
+    fn resolve(resolver: &dyn HostResolver, name: &str) -> Result<Vec<IpAddr>, ErrorCode> {
+        in_tokio(resolver.resolve(name)).map_err(|e| e.downcast().unwrap())
+    }
+
+    #[test]
+    fn hosts() {
+        let resolver = HostsResolver::parse(
+            "# comment
+             127.0.0.1 localhost  # trailing comment
+
+             ::1 localhost Ip6-Localhost
+             10.0.0.1 xn--mnchen-3ya.de",
+        )
+        .unwrap();
+        let localhost = vec![
+            IpAddr::from(Ipv4Addr::LOCALHOST),
+            IpAddr::from(Ipv6Addr::LOCALHOST),
+        ];
+        assert_eq!(resolve(&resolver, "localhost").unwrap(), localhost);
+        assert_eq!(resolve(&resolver, "localhost.").unwrap(), localhost);
+        assert_eq!(
+            resolve(&resolver, "ip6-localhost").unwrap(),
+            [IpAddr::from(Ipv6Addr::LOCALHOST)]
+        );
+        assert_eq!(
+            resolve(&resolver, "münchen.de").unwrap(),
+            [IpAddr::from(Ipv4Addr::new(10, 0, 0, 1))]
+        );
+        assert_eq!(
+            resolve(&resolver, "example.com").unwrap_err(),
+            ErrorCode::NameUnresolvable
+        );
+
+        assert!(HostsResolver::parse("localhost 127.0.0.1").is_err());
+        assert!(HostsResolver::parse("127.0.0.1").is_err());
+    }
+
+    #[test]
+    fn fallback() {
+        let mut inner = HostsResolver::new();
+        inner
+            .insert("example.com", Ipv4Addr::new(192, 0, 2, 1).into())
+            .insert("example.net", Ipv4Addr::new(192, 0, 2, 2).into());
+        let mut resolver = HostsResolver::new();
+        resolver
+            .insert("example.com", Ipv4Addr::new(192, 0, 2, 3).into())
+            .insert("EXAMPLE.com", Ipv4Addr::new(192, 0, 2, 3).into())
+            .fallback(inner);
+        assert_eq!(
+            resolve(&resolver, "example.com").unwrap(),
+            [IpAddr::from(Ipv4Addr::new(192, 0, 2, 3))]
+        );
+        assert_eq!(
+            resolve(&resolver, "example.net").unwrap(),
+            [IpAddr::from(Ipv4Addr::new(192, 0, 2, 2))]
+        );
+        assert_eq!(
+            resolve(&resolver, "example.org").unwrap_err(),
+            ErrorCode::NameUnresolvable
+        );
     }
 }
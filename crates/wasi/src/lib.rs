@@ -203,14 +203,22 @@ pub mod runtime;
 mod stdio;
 mod stream;
 mod tcp;
+mod trace;
 mod udp;
 mod write_stream;
 
 pub use self::clocks::{HostMonotonicClock, HostWallClock};
 pub use self::ctx::{WasiCtx, WasiCtxBuilder, WasiImpl, WasiView};
 pub use self::error::{I32Exit, TrappableError};
-pub use self::filesystem::{DirPerms, FileInputStream, FilePerms, FsError, FsResult};
-pub use self::network::{Network, SocketAddrUse, SocketError, SocketResult};
+pub use self::filesystem::{
+    DirPerms, FileInputStream, FilePerms, FsError, FsResult, MemoryDir, Metadata, OpenOptions,
+    Opened, WasiDir, WasiFile,
+};
+pub use self::ip_name_lookup::{HostResolver, HostsResolver, SystemResolver};
+pub use self::network::{
+    Network, SocketAddrUse, SocketError, SocketPolicy, SocketPolicyBuilder, SocketResult,
+    VirtualNetwork,
+};
 pub use self::poll::{subscribe, ClosureFuture, MakeFuture, Pollable, PollableFuture, Subscribe};
 pub use self::random::{thread_rng, Deterministic};
 pub use self::stdio::{
@@ -220,6 +228,7 @@ pub use self::stdio::{
 pub use self::stream::{
     HostInputStream, HostOutputStream, InputStream, OutputStream, StreamError, StreamResult,
 };
+pub use self::trace::Trace;
 #[doc(no_inline)]
 pub use async_trait::async_trait;
 #[doc(no_inline)]
@@ -1,13 +1,21 @@
 use crate::bindings::sockets::network::{ErrorCode, Ipv4Address, Ipv6Address};
-use crate::TrappableError;
+use crate::{HostResolver, TrappableError};
 use std::future::Future;
 use std::net::SocketAddr;
 use std::pin::Pin;
 use std::sync::Arc;
 
+mod policy;
+mod virtual_network;
+
+pub use self::policy::{SocketPolicy, SocketPolicyBuilder};
+pub use self::virtual_network::VirtualNetwork;
+pub(crate) use self::virtual_network::{VirtualTcpSocket, VirtualUdpSocket};
+
 pub struct Network {
     pub socket_addr_check: SocketAddrCheck,
     pub allow_ip_name_lookup: bool,
+    pub resolver: Arc<dyn HostResolver>,
 }
 
 impl Network {
@@ -0,0 +1,375 @@
+//! Declarative restrictions on the socket addresses that guests may use.
+
+use super::SocketAddrUse;
+use anyhow::{bail, Context, Result};
+use std::net::{IpAddr, SocketAddr};
+use std::ops::RangeInclusive;
+use std::sync::Arc;
+
+/// A policy for the socket addresses that guests may bind and connect to.
+///
+/// An address is allowed for a use if it matches a rule added with
+/// [`SocketPolicyBuilder::allow`] and none added with
+/// [`SocketPolicyBuilder::deny`], so the default policy allows nothing. A
+/// policy is used by a [`WasiCtx`](crate::WasiCtx) through
+/// [`WasiCtxBuilder::socket_policy`](crate::WasiCtxBuilder::socket_policy).
+///
+/// Rules have the form `USES=ADDRS:PORTS`, where:
+///
+/// * `USES` is what the address is used for, one of `tcp-bind`,
+///   `tcp-connect`, `udp-bind`, `udp-connect` or `udp-send`, for sending
+///   datagrams on unconnected UDP sockets, or a group of those: `bind`,
+///   `connect`, which includes `udp-send`, `tcp`, `udp` or `*` for all uses.
+///   `USES=` may be left out to match all uses.
+/// * `ADDRS` is an IP address, such as `10.0.0.1`, a range of them in CIDR
+///   notation, such as `10.0.0.0/8`, or `*` for all addresses. IPv6
+///   addresses must be in brackets when followed by ports, such as
+///   `[::1]:8080`.
+/// * `PORTS` is a port, such as `443`, a range of them, such as
+///   `8000-8999`, or `*` for all ports. `:PORTS` may be left out to match
+///   all ports. Note that binding to port 0 lets the host choose the port.
+///
+/// For example `connect=10.0.0.0/8:443` allows connecting to port 443 of the
+/// `10.0.0.0/8` network and `bind=127.0.0.1:*` allows binding to any port of
+/// the loopback address.
+///
+/// Cloning a policy is cheap.
+#[derive(Clone, Default)]
+pub struct SocketPolicy {
+    inner: Arc<Inner>,
+}
+
+#[derive(Clone, Default)]
+struct Inner {
+    allow: Vec<Rule>,
+    deny: Vec<Rule>,
+    on_denied: Option<Arc<dyn Fn(SocketAddr, SocketAddrUse) + Send + Sync>>,
+}
+
+#[derive(Clone, Debug, PartialEq)]
+struct Rule {
+    uses: Uses,
+    addrs: Addrs,
+    ports: RangeInclusive<u16>,
+}
+
+/// A set of [`SocketAddrUse`]s, as a bit for each.
+#[derive(Clone, Copy, Debug, PartialEq)]
+struct Uses(u8);
+
+impl Uses {
+    const TCP_BIND: Uses = Uses(1 << 0);
+    const TCP_CONNECT: Uses = Uses(1 << 1);
+    const UDP_BIND: Uses = Uses(1 << 2);
+    const UDP_CONNECT: Uses = Uses(1 << 3);
+    const UDP_SEND: Uses = Uses(1 << 4);
+    const ALL: Uses = Uses(0b11111);
+
+    fn parse(uses: &str) -> Option<Uses> {
+        let bits = match uses {
+            "tcp-bind" => Uses::TCP_BIND.0,
+            "tcp-connect" => Uses::TCP_CONNECT.0,
+            "udp-bind" => Uses::UDP_BIND.0,
+            "udp-connect" => Uses::UDP_CONNECT.0,
+            "udp-send" => Uses::UDP_SEND.0,
+            "bind" => Uses::TCP_BIND.0 | Uses::UDP_BIND.0,
+            "connect" => Uses::TCP_CONNECT.0 | Uses::UDP_CONNECT.0 | Uses::UDP_SEND.0,
+            "tcp" => Uses::TCP_BIND.0 | Uses::TCP_CONNECT.0,
+            "udp" => Uses::UDP_BIND.0 | Uses::UDP_CONNECT.0 | Uses::UDP_SEND.0,
+            "*" => Uses::ALL.0,
+            _ => return None,
+        };
+        Some(Uses(bits))
+    }
+
+    fn contains(&self, reason: SocketAddrUse) -> bool {
+        let bit = match reason {
+            SocketAddrUse::TcpBind => Uses::TCP_BIND,
+            SocketAddrUse::TcpConnect => Uses::TCP_CONNECT,
+            SocketAddrUse::UdpBind => Uses::UDP_BIND,
+            SocketAddrUse::UdpConnect => Uses::UDP_CONNECT,
+            SocketAddrUse::UdpOutgoingDatagram => Uses::UDP_SEND,
+        };
+        self.0 & bit.0 != 0
+    }
+}
+
+#[derive(Clone, Debug, PartialEq)]
+enum Addrs {
+    Any,
+    Net { net: IpAddr, prefix: u8 },
+}
+
+impl Rule {
+    fn parse(rule: &str) -> Result<Rule> {
+        let invalid = || format!("invalid socket rule `{rule}`");
+        let (uses, target) = match rule.split_once('=') {
+            Some((uses, target)) => (
+                Uses::parse(uses)
+                    .with_context(|| format!("unknown socket address use `{uses}`"))
+                    .with_context(invalid)?,
+                target,
+            ),
+            None => (Uses::ALL, rule),
+        };
+
+        // Split off the ports, keeping in mind that IPv6 addresses are full of
+        // colons unless they're in brackets.
+        let (addrs, ports) = if let Some(rest) = target.strip_prefix('[') {
+            let (addrs, rest) = rest
+                .split_once(']')
+                .with_context(|| "missing `]`")
+                .with_context(invalid)?;
+            match rest {
+                "" => (addrs, None),
+                _ => match rest.strip_prefix(':') {
+                    Some(ports) => (addrs, Some(ports)),
+                    None => bail!("{}: expected `:` after `]`", invalid()),
+                },
+            }
+        } else if target.matches(':').count() > 1 {
+            (target, None)
+        } else {
+            match target.split_once(':') {
+                Some((addrs, ports)) => (addrs, Some(ports)),
+                None => (target, None),
+            }
+        };
+
+        let addrs = match addrs {
+            "*" => Addrs::Any,
+            _ => {
+                let (net, prefix) = match addrs.split_once('/') {
+                    Some((net, prefix)) => (net, Some(prefix)),
+                    None => (addrs, None),
+                };
+                let net = net.parse::<IpAddr>().with_context(invalid)?;
+                let max = match net {
+                    IpAddr::V4(_) => 32,
+                    IpAddr::V6(_) => 128,
+                };
+                let prefix = match prefix {
+                    Some(prefix) => prefix.parse::<u8>().with_context(invalid)?,
+                    None => max,
+                };
+                if prefix > max {
+                    bail!("{}: prefix is too long", invalid());
+                }
+                Addrs::Net { net, prefix }
+            }
+        };
+
+        let ports = match ports {
+            None | Some("*") => 0..=u16::MAX,
+            Some(ports) => {
+                let (start, end) = ports.split_once('-').unwrap_or((ports, ports));
+                let start = start.parse::<u16>().with_context(invalid)?;
+                let end = end.parse::<u16>().with_context(invalid)?;
+                if start > end {
+                    bail!("{}: empty port range", invalid());
+                }
+                start..=end
+            }
+        };
+
+        Ok(Rule { uses, addrs, ports })
+    }
+
+    fn matches(&self, addr: SocketAddr, reason: SocketAddrUse) -> bool {
+        if !self.uses.contains(reason) || !self.ports.contains(&addr.port()) {
+            return false;
+        }
+        let (net, prefix) = match &self.addrs {
+            Addrs::Any => return true,
+            Addrs::Net { net, prefix } => (net, u32::from(*prefix)),
+        };
+        match (net, addr.ip().to_canonical()) {
+            (IpAddr::V4(net), IpAddr::V4(addr)) => {
+                let mask = u32::MAX.checked_shl(32 - prefix).unwrap_or(0);
+                u32::from(*net) & mask == u32::from(addr) & mask
+            }
+            (IpAddr::V6(net), IpAddr::V6(addr)) => {
+                let mask = u128::MAX.checked_shl(128 - prefix).unwrap_or(0);
+                u128::from(*net) & mask == u128::from(addr) & mask
+            }
+            _ => false,
+        }
+    }
+}
+
+impl SocketPolicy {
+    /// Returns a builder for a policy.
+    pub fn builder() -> SocketPolicyBuilder {
+        SocketPolicyBuilder {
+            inner: Inner::default(),
+        }
+    }
+
+    /// Returns whether `addr` may be used for `reason`.
+    ///
+    /// This calls the [`SocketPolicyBuilder::on_denied`] callback if it
+    /// isn't.
+    pub fn check(&self, addr: SocketAddr, reason: SocketAddrUse) -> bool {
+        let inner = &self.inner;
+        let allowed = inner.allow.iter().any(|r| r.matches(addr, reason))
+            && !inner.deny.iter().any(|r| r.matches(addr, reason));
+        if !allowed {
+            if let Some(on_denied) = &inner.on_denied {
+                on_denied(addr, reason);
+            }
+        }
+        allowed
+    }
+}
+
+/// A builder for a [`SocketPolicy`].
+#[derive(Clone)]
+pub struct SocketPolicyBuilder {
+    inner: Inner,
+}
+
+impl SocketPolicyBuilder {
+    /// Allows addresses which match `rule`, unless they're denied.
+    ///
+    /// See [`SocketPolicy`] for the syntax of rules.
+    pub fn allow(&mut self, rule: &str) -> Result<&mut Self> {
+        self.inner.allow.push(Rule::parse(rule)?);
+        Ok(self)
+    }
+
+    /// Denies addresses which match `rule`.
+    ///
+    /// See [`SocketPolicy`] for the syntax of rules.
+    pub fn deny(&mut self, rule: &str) -> Result<&mut Self> {
+        self.inner.deny.push(Rule::parse(rule)?);
+        Ok(self)
+    }
+
+    /// Adds the rules of `policy`, which has a rule on each line preceded by
+    /// `allow` or `deny`, such as `allow connect=10.0.0.0/8:443`.
+    ///
+    /// Empty lines are ignored and `#` starts a comment.
+    pub fn rules(&mut self, policy: &str) -> Result<&mut Self> {
+        for (i, line) in policy.lines().enumerate() {
+            let line = match line.split_once('#') {
+                Some((line, _comment)) => line,
+                None => line,
+            };
+            let line = line.trim();
+            if line.is_empty() {
+                continue;
+            }
+            let result = match line.split_once(char::is_whitespace) {
+                Some(("allow", rule)) => self.allow(rule.trim()),
+                Some(("deny", rule)) => self.deny(rule.trim()),
+                _ => Err(anyhow::anyhow!("expected `allow` or `deny`")),
+            };
+            result.with_context(|| format!("invalid socket policy on line {}", i + 1))?;
+        }
+        Ok(self)
+    }
+
+    /// Calls `on_denied` with every address that the policy denies, along
+    /// with what it was going to be used for, such as to log it.
+    pub fn on_denied(
+        &mut self,
+        on_denied: impl Fn(SocketAddr, SocketAddrUse) + Send + Sync + 'static,
+    ) -> &mut Self {
+        self.inner.on_denied = Some(Arc::new(on_denied));
+        self
+    }
+
+    /// Builds the policy.
+    pub fn build(&self) -> SocketPolicy {
+        SocketPolicy {
+            inner: Arc::new(self.inner.clone()),
+        }
+    }
+}
+
+#[cfg(test)]
+mod test {
+    use super::*;
+    use std::sync::atomic::{AtomicUsize, Ordering};
+
+    fn addr(addr: &str) -> SocketAddr {
+        addr.parse().unwrap()
+    }
+
+    #[test]
+    fn parse() {
+        let rule = Rule::parse("connect=10.0.0.0/8:443").unwrap();
+        assert_eq!(
+            rule,
+            Rule {
+                uses: Uses(0b11010),
+                addrs: Addrs::Net {
+                    net: "10.0.0.0".parse().unwrap(),
+                    prefix: 8
+                },
+                ports: 443..=443,
+            }
+        );
+        let rule = Rule::parse("[2001:db8::/32]:8000-8999").unwrap();
+        assert_eq!(rule.uses, Uses::ALL);
+        assert_eq!(rule.ports, 8000..=8999);
+        let rule = Rule::parse("udp-send=::1").unwrap();
+        assert_eq!(rule.uses, Uses::UDP_SEND);
+        assert_eq!(rule.ports, 0..=u16::MAX);
+        let rule = Rule::parse("*:*").unwrap();
+        assert_eq!(rule.addrs, Addrs::Any);
+
+        for rule in [
+            "listen=*",
+            "10.0.0.0/33",
+            "10.0.0.1:99999",
+            "10.0.0.1:9-1",
+            "[::1",
+            "[::1]443",
+            "example.com:80",
+        ] {
+            assert!(Rule::parse(rule).is_err(), "{rule}");
+        }
+    }
+
+    #[test]
+    fn check() {
+        let denied = Arc::new(AtomicUsize::new(0));
+        let policy = SocketPolicy::builder()
+            .rules(
+                "# Only talk to the internal network over TLS.
+                 allow connect=10.0.0.0/8:443
+                 deny  connect=10.0.0.1   # except for the gateway
+                 allow bind=[::1]:0",
+            )
+            .unwrap()
+            .on_denied({
+                let denied = denied.clone();
+                move |_, _| {
+                    denied.fetch_add(1, Ordering::Relaxed);
+                }
+            })
+            .build();
+        use SocketAddrUse::*;
+        assert!(policy.check(addr("10.1.2.3:443"), TcpConnect));
+        assert!(policy.check(addr("10.1.2.3:443"), UdpOutgoingDatagram));
+        assert!(policy.check(addr("[::ffff:10.1.2.3]:443"), TcpConnect));
+        assert!(policy.check(addr("[::1]:0"), TcpBind));
+        assert_eq!(denied.load(Ordering::Relaxed), 0);
+
+        assert!(!policy.check(addr("10.1.2.3:80"), TcpConnect));
+        assert!(!policy.check(addr("10.1.2.3:443"), TcpBind));
+        assert!(!policy.check(addr("11.0.0.1:443"), TcpConnect));
+        assert!(!policy.check(addr("10.0.0.1:443"), TcpConnect));
+        assert!(!policy.check(addr("[::1]:80"), UdpBind));
+        assert_eq!(denied.load(Ordering::Relaxed), 5);
+
+        assert!(!SocketPolicy::default().check(addr("127.0.0.1:80"), TcpConnect));
+        assert!(SocketPolicy::builder()
+            .rules("bad rule")
+            .err()
+            .unwrap()
+            .to_string()
+            .contains("line 1"));
+    }
+}
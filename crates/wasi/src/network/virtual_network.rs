@@ -0,0 +1,1051 @@
+//! An in-process, loopback-only network for `wasi:sockets`.
+//!
+//! Sockets created by a [`WasiCtx`](crate::WasiCtx) with a [`VirtualNetwork`]
+//! never touch the host's network stack. TCP connections are pairs of
+//! in-memory byte pipes and UDP datagrams are delivered straight into the
+//! receiving socket's queue.
+
+use crate::bindings::sockets::network::ErrorCode;
+use crate::host::network::util;
+use crate::network::SocketAddressFamily;
+use crate::{
+    HostInputStream, HostOutputStream, InputStream, OutputStream, SocketResult, StreamError,
+    Subscribe,
+};
+use bytes::{Bytes, BytesMut};
+use rustix::io::Errno;
+use std::collections::{BTreeMap, BTreeSet, VecDeque};
+use std::io;
+use std::mem;
+use std::net::{Shutdown, SocketAddr};
+use std::sync::{Arc, Mutex, MutexGuard};
+use std::time::Duration;
+use tokio::sync::Notify;
+
+/// The first port handed out to sockets binding to port 0, which is the start
+/// of the IANA dynamic port range.
+const EPHEMERAL_PORT_START: u16 = 49152;
+
+/// The number of bytes buffered in one direction of a TCP connection before
+/// the writer has to wait for the reader.
+const PIPE_CAPACITY: usize = 64 * 1024;
+
+/// The number of datagrams queued on a UDP socket before further datagrams
+/// are dropped.
+const MAILBOX_CAPACITY: usize = 1024;
+
+/// An in-process network which lets the sockets of multiple
+/// [`WasiCtx`](crate::WasiCtx)s talk to each other without using the host's
+/// network stack.
+///
+/// Only loopback addresses exist on a virtual network: sockets can bind to
+/// `127.0.0.0/8`, `::1` or the unspecified address, and can only reach other
+/// sockets on the same network. Everything happens in memory, so ports are
+/// handed out in a deterministic order, connections are accepted in the order
+/// they were made and datagrams are received in the order they were sent.
+///
+/// Cloning a `VirtualNetwork` is cheap and all clones refer to the same
+/// network. A network is used by a context through
+/// [`WasiCtxBuilder::virtual_network`](crate::WasiCtxBuilder::virtual_network).
+/// Note that socket addresses are still subject to the context's socket
+/// address check, which denies all addresses by default.
+///
+/// ```
+/// use wasmtime_wasi::{VirtualNetwork, WasiCtxBuilder};
+///
+/// let network = VirtualNetwork::new();
+///
+/// let server = WasiCtxBuilder::new()
+///     .virtual_network(network.clone())
+///     .inherit_network()
+///     .build();
+/// let client = WasiCtxBuilder::new()
+///     .virtual_network(network)
+///     .inherit_network()
+///     .build();
+/// ```
+#[derive(Clone, Default)]
+pub struct VirtualNetwork {
+    state: Arc<Mutex<State>>,
+}
+
+impl VirtualNetwork {
+    /// Creates a new network without any bound sockets.
+    pub fn new() -> Self {
+        Self::default()
+    }
+
+    fn state(&self) -> MutexGuard<'_, State> {
+        self.state.lock().unwrap()
+    }
+
+    /// Reserves `addr` for a socket, picking a free port if its port is 0.
+    fn bind(&self, protocol: Protocol, addr: SocketAddr) -> io::Result<Binding> {
+        let mut addr = normalize(addr);
+        if !addr.ip().is_unspecified() && !addr.ip().is_loopback() {
+            return Err(Errno::ADDRNOTAVAIL.into());
+        }
+
+        let mut state = self.state();
+        if addr.port() == 0 {
+            addr.set_port(state.ephemeral_port(protocol, addr)?);
+        } else if state.in_use(protocol, addr) {
+            return Err(Errno::ADDRINUSE.into());
+        }
+        state.bound.insert((protocol, addr));
+
+        Ok(Binding {
+            network: self.clone(),
+            protocol,
+            addr,
+        })
+    }
+
+    /// Opens a connection to the TCP socket listening on `remote_address`.
+    fn connect(&self, remote_address: SocketAddr) -> io::Result<Connection> {
+        let remote_address = normalize(remote_address);
+        if !remote_address.ip().is_loopback() {
+            return Err(Errno::NETUNREACH.into());
+        }
+        let Some(listener) = lookup(&self.state().listeners, remote_address) else {
+            return Err(Errno::CONNREFUSED.into());
+        };
+
+        let binding = self.bind(Protocol::Tcp, SocketAddr::new(remote_address.ip(), 0))?;
+        let client_to_server = Arc::new(Pipe::default());
+        let server_to_client = Arc::new(Pipe::default());
+        let server = Connection {
+            local_address: remote_address,
+            remote_address: binding.addr,
+            receive: client_to_server.clone(),
+            send: server_to_client.clone(),
+            _binding: None,
+        };
+        let client = Connection {
+            local_address: binding.addr,
+            remote_address,
+            receive: server_to_client,
+            send: client_to_server,
+            _binding: Some(binding),
+        };
+
+        listener.push(server)?;
+        Ok(client)
+    }
+}
+
+struct State {
+    next_port: u16,
+    bound: BTreeSet<(Protocol, SocketAddr)>,
+    listeners: BTreeMap<SocketAddr, Arc<Listener>>,
+    mailboxes: BTreeMap<SocketAddr, Arc<Mailbox>>,
+}
+
+impl Default for State {
+    fn default() -> Self {
+        Self {
+            next_port: EPHEMERAL_PORT_START,
+            bound: BTreeSet::new(),
+            listeners: BTreeMap::new(),
+            mailboxes: BTreeMap::new(),
+        }
+    }
+}
+
+impl State {
+    /// Whether binding `addr` would conflict with an existing binding.
+    fn in_use(&self, protocol: Protocol, addr: SocketAddr) -> bool {
+        self.bound.iter().any(|&(p, bound)| {
+            p == protocol
+                && bound.port() == addr.port()
+                && bound.is_ipv4() == addr.is_ipv4()
+                && (bound.ip() == addr.ip()
+                    || bound.ip().is_unspecified()
+                    || addr.ip().is_unspecified())
+        })
+    }
+
+    fn ephemeral_port(&mut self, protocol: Protocol, addr: SocketAddr) -> io::Result<u16> {
+        for _ in EPHEMERAL_PORT_START..=u16::MAX {
+            let port = self.next_port;
+            self.next_port = port.checked_add(1).unwrap_or(EPHEMERAL_PORT_START);
+            if !self.in_use(protocol, SocketAddr::new(addr.ip(), port)) {
+                return Ok(port);
+            }
+        }
+        Err(Errno::ADDRINUSE.into())
+    }
+}
+
+#[derive(Clone, Copy, PartialEq, Eq, PartialOrd, Ord)]
+enum Protocol {
+    Tcp,
+    Udp,
+}
+
+/// Strips the IPv6 flow info and scope id, which play no part in matching
+/// addresses on a virtual network.
+fn normalize(addr: SocketAddr) -> SocketAddr {
+    SocketAddr::new(addr.ip(), addr.port())
+}
+
+/// Finds the socket bound to `addr`, or to the unspecified address of the
+/// same family and port.
+fn lookup<T: Clone>(sockets: &BTreeMap<SocketAddr, T>, addr: SocketAddr) -> Option<T> {
+    let wildcard = match addr {
+        SocketAddr::V4(_) => SocketAddr::new(std::net::Ipv4Addr::UNSPECIFIED.into(), addr.port()),
+        SocketAddr::V6(_) => SocketAddr::new(std::net::Ipv6Addr::UNSPECIFIED.into(), addr.port()),
+    };
+    sockets
+        .get(&addr)
+        .or_else(|| sockets.get(&wildcard))
+        .cloned()
+}
+
+/// A bound address, which is released again when this is dropped.
+struct Binding {
+    network: VirtualNetwork,
+    protocol: Protocol,
+    addr: SocketAddr,
+}
+
+impl Drop for Binding {
+    fn drop(&mut self) {
+        let mut state = self.network.state();
+        state.bound.remove(&(self.protocol, self.addr));
+        let listener = match self.protocol {
+            Protocol::Tcp => state.listeners.remove(&self.addr),
+            Protocol::Udp => {
+                state.mailboxes.remove(&self.addr);
+                None
+            }
+        };
+        // Dropping a listener closes its pending connections, which is done
+        // after releasing the lock.
+        drop(state);
+        drop(listener);
+    }
+}
+
+/// One direction of a TCP connection.
+#[derive(Default)]
+struct Pipe {
+    state: Mutex<PipeState>,
+    changed: Notify,
+}
+
+#[derive(Default)]
+struct PipeState {
+    buffer: BytesMut,
+    reader_closed: bool,
+    writer_closed: bool,
+}
+
+impl PipeState {
+    fn is_closed(&self) -> bool {
+        self.reader_closed || self.writer_closed
+    }
+}
+
+impl Pipe {
+    fn lock(&self) -> MutexGuard<'_, PipeState> {
+        self.state.lock().unwrap()
+    }
+
+    fn update(&self, f: impl FnOnce(&mut PipeState)) {
+        f(&mut self.lock());
+        self.changed.notify_waiters();
+    }
+
+    async fn wait_until(&self, ready: fn(&PipeState) -> bool) {
+        loop {
+            let changed = self.changed.notified();
+            if ready(&self.lock()) {
+                return;
+            }
+            changed.await;
+        }
+    }
+}
+
+struct PipeReader(Arc<Pipe>);
+
+#[async_trait::async_trait]
+impl HostInputStream for PipeReader {
+    fn read(&mut self, size: usize) -> Result<Bytes, StreamError> {
+        let mut state = self.0.lock();
+        if state.reader_closed {
+            return Err(StreamError::Closed);
+        }
+        if state.buffer.is_empty() {
+            return match state.writer_closed {
+                true => Err(StreamError::Closed),
+                false => Ok(Bytes::new()),
+            };
+        }
+
+        let size = size.min(state.buffer.len());
+        let bytes = state.buffer.split_to(size).freeze();
+        drop(state);
+        self.0.changed.notify_waiters();
+        Ok(bytes)
+    }
+}
+
+#[async_trait::async_trait]
+impl Subscribe for PipeReader {
+    async fn ready(&mut self) {
+        self.0
+            .wait_until(|state| state.is_closed() || !state.buffer.is_empty())
+            .await
+    }
+}
+
+struct PipeWriter(Arc<Pipe>);
+
+#[async_trait::async_trait]
+impl HostOutputStream for PipeWriter {
+    fn write(&mut self, bytes: Bytes) -> Result<(), StreamError> {
+        let mut state = self.0.lock();
+        if state.is_closed() {
+            return Err(StreamError::Closed);
+        }
+        state.buffer.extend_from_slice(&bytes);
+        drop(state);
+        self.0.changed.notify_waiters();
+        Ok(())
+    }
+
+    fn flush(&mut self) -> Result<(), StreamError> {
+        // Written bytes are immediately visible to the reader.
+        match self.0.lock().is_closed() {
+            true => Err(StreamError::Closed),
+            false => Ok(()),
+        }
+    }
+
+    fn check_write(&mut self) -> Result<usize, StreamError> {
+        let state = self.0.lock();
+        if state.is_closed() {
+            return Err(StreamError::Closed);
+        }
+        Ok(PIPE_CAPACITY.saturating_sub(state.buffer.len()))
+    }
+}
+
+#[async_trait::async_trait]
+impl Subscribe for PipeWriter {
+    async fn ready(&mut self) {
+        self.0
+            .wait_until(|state| state.is_closed() || state.buffer.len() < PIPE_CAPACITY)
+            .await
+    }
+}
+
+impl Drop for PipeWriter {
+    fn drop(&mut self) {
+        // The output stream can't be recreated, so signal the end of the
+        // stream to the peer.
+        self.0.update(|state| state.writer_closed = true);
+    }
+}
+
+/// One end of an established TCP connection.
+struct Connection {
+    local_address: SocketAddr,
+    remote_address: SocketAddr,
+    receive: Arc<Pipe>,
+    send: Arc<Pipe>,
+
+    /// The ephemeral port of the connecting end.
+    _binding: Option<Binding>,
+}
+
+impl Connection {
+    fn streams(&self) -> (InputStream, OutputStream) {
+        (
+            Box::new(PipeReader(self.receive.clone())),
+            Box::new(PipeWriter(self.send.clone())),
+        )
+    }
+
+    fn shutdown(&self, how: Shutdown) {
+        if let Shutdown::Both | Shutdown::Read = how {
+            self.receive.update(|state| state.reader_closed = true);
+        }
+        if let Shutdown::Both | Shutdown::Write = how {
+            self.send.update(|state| state.writer_closed = true);
+        }
+    }
+}
+
+impl Drop for Connection {
+    fn drop(&mut self) {
+        self.shutdown(Shutdown::Both);
+    }
+}
+
+/// The queue of connections waiting to be accepted by a listening socket.
+struct Listener {
+    state: Mutex<ListenerState>,
+    changed: Notify,
+}
+
+struct ListenerState {
+    pending: VecDeque<Connection>,
+    backlog: usize,
+}
+
+impl Listener {
+    fn new(backlog: u32) -> Self {
+        Self {
+            state: Mutex::new(ListenerState {
+                pending: VecDeque::new(),
+                backlog: backlog.try_into().unwrap_or(usize::MAX),
+            }),
+            changed: Notify::new(),
+        }
+    }
+
+    fn lock(&self) -> MutexGuard<'_, ListenerState> {
+        self.state.lock().unwrap()
+    }
+
+    fn push(&self, connection: Connection) -> io::Result<()> {
+        let mut state = self.lock();
+        if state.pending.len() >= state.backlog {
+            return Err(Errno::CONNREFUSED.into());
+        }
+        state.pending.push_back(connection);
+        drop(state);
+        self.changed.notify_waiters();
+        Ok(())
+    }
+
+    fn set_backlog(&self, backlog: u32) {
+        self.lock().backlog = backlog.try_into().unwrap_or(usize::MAX);
+    }
+
+    async fn ready(&self) {
+        loop {
+            let changed = self.changed.notified();
+            if !self.lock().pending.is_empty() {
+                return;
+            }
+            changed.await;
+        }
+    }
+}
+
+/// The socket options of a virtual TCP socket, which have no effect other
+/// than being reported back.
+#[derive(Clone)]
+struct TcpOptions {
+    keep_alive_enabled: bool,
+    keep_alive_idle_time: Duration,
+    keep_alive_interval: Duration,
+    keep_alive_count: u32,
+    hop_limit: u8,
+    receive_buffer_size: usize,
+    send_buffer_size: usize,
+}
+
+impl Default for TcpOptions {
+    fn default() -> Self {
+        // The defaults of Linux.
+        Self {
+            keep_alive_enabled: false,
+            keep_alive_idle_time: Duration::from_secs(7200),
+            keep_alive_interval: Duration::from_secs(75),
+            keep_alive_count: 9,
+            hop_limit: 64,
+            receive_buffer_size: PIPE_CAPACITY,
+            send_buffer_size: PIPE_CAPACITY,
+        }
+    }
+}
+
+/// The state of a TCP socket on a virtual network, mirroring `TcpState`.
+enum VirtualTcpState {
+    Default,
+    BindStarted(Binding),
+    Bound(Binding),
+    ListenStarted(Binding),
+    Listening {
+        _binding: Binding,
+        listener: Arc<Listener>,
+    },
+    /// Connections are established immediately, so there is no equivalent
+    /// of `TcpState::Connecting`.
+    ConnectReady(io::Result<Connection>),
+    Connected(Connection),
+    Closed,
+}
+
+/// A TCP socket on a [`VirtualNetwork`].
+pub(crate) struct VirtualTcpSocket {
+    network: VirtualNetwork,
+    family: SocketAddressFamily,
+    state: VirtualTcpState,
+    options: TcpOptions,
+}
+
+impl VirtualTcpSocket {
+    pub fn new(network: &VirtualNetwork, family: SocketAddressFamily) -> Self {
+        Self {
+            network: network.clone(),
+            family,
+            state: VirtualTcpState::Default,
+            options: TcpOptions::default(),
+        }
+    }
+
+    pub fn start_bind(&mut self, local_address: SocketAddr) -> io::Result<()> {
+        match self.state {
+            VirtualTcpState::Default => {}
+            VirtualTcpState::BindStarted(..) => return Err(Errno::ALREADY.into()),
+            _ => return Err(Errno::ISCONN.into()),
+        }
+
+        util::validate_unicast(&local_address)?;
+        util::validate_address_family(&local_address, &self.family)?;
+
+        let binding = self.network.bind(Protocol::Tcp, local_address)?;
+        self.state = VirtualTcpState::BindStarted(binding);
+        Ok(())
+    }
+
+    pub fn finish_bind(&mut self) -> SocketResult<()> {
+        match mem::replace(&mut self.state, VirtualTcpState::Closed) {
+            VirtualTcpState::BindStarted(binding) => {
+                self.state = VirtualTcpState::Bound(binding);
+                Ok(())
+            }
+            previous_state => {
+                self.state = previous_state;
+                Err(ErrorCode::NotInProgress.into())
+            }
+        }
+    }
+
+    pub fn start_connect(&mut self, remote_address: SocketAddr) -> SocketResult<()> {
+        match self.state {
+            VirtualTcpState::Default => {}
+            VirtualTcpState::ConnectReady(..) => return Err(ErrorCode::ConcurrencyConflict.into()),
+            _ => return Err(ErrorCode::InvalidState.into()),
+        }
+
+        util::validate_unicast(&remote_address)?;
+        util::validate_remote_address(&remote_address)?;
+        util::validate_address_family(&remote_address, &self.family)?;
+
+        self.state = VirtualTcpState::ConnectReady(self.network.connect(remote_address));
+        Ok(())
+    }
+
+    pub fn finish_connect(&mut self) -> SocketResult<(InputStream, OutputStream)> {
+        match mem::replace(&mut self.state, VirtualTcpState::Closed) {
+            VirtualTcpState::ConnectReady(Ok(connection)) => {
+                let streams = connection.streams();
+                self.state = VirtualTcpState::Connected(connection);
+                Ok(streams)
+            }
+            VirtualTcpState::ConnectReady(Err(err)) => Err(err.into()),
+            previous_state => {
+                self.state = previous_state;
+                Err(ErrorCode::NotInProgress.into())
+            }
+        }
+    }
+
+    pub fn start_listen(&mut self) -> SocketResult<()> {
+        match mem::replace(&mut self.state, VirtualTcpState::Closed) {
+            VirtualTcpState::Bound(binding) => {
+                self.state = VirtualTcpState::ListenStarted(binding);
+                Ok(())
+            }
+            VirtualTcpState::ListenStarted(binding) => {
+                self.state = VirtualTcpState::ListenStarted(binding);
+                Err(ErrorCode::ConcurrencyConflict.into())
+            }
+            previous_state => {
+                self.state = previous_state;
+                Err(ErrorCode::InvalidState.into())
+            }
+        }
+    }
+
+    pub fn finish_listen(&mut self, backlog: u32) -> SocketResult<()> {
+        match mem::replace(&mut self.state, VirtualTcpState::Closed) {
+            VirtualTcpState::ListenStarted(binding) => {
+                let listener = Arc::new(Listener::new(backlog));
+                self.network
+                    .state()
+                    .listeners
+                    .insert(binding.addr, listener.clone());
+                self.state = VirtualTcpState::Listening {
+                    _binding: binding,
+                    listener,
+                };
+                Ok(())
+            }
+            previous_state => {
+                self.state = previous_state;
+                Err(ErrorCode::NotInProgress.into())
+            }
+        }
+    }
+
+    pub fn accept(&mut self) -> SocketResult<(Self, InputStream, OutputStream)> {
+        let VirtualTcpState::Listening { listener, .. } = &self.state else {
+            return Err(ErrorCode::InvalidState.into());
+        };
+        let Some(connection) = listener.lock().pending.pop_front() else {
+            return Err(ErrorCode::WouldBlock.into());
+        };
+
+        let (input, output) = connection.streams();
+        let socket = Self {
+            network: self.network.clone(),
+            family: self.family,
+            state: VirtualTcpState::Connected(connection),
+            // Like on most platforms, accepted sockets inherit the options
+            // of the listener.
+            options: self.options.clone(),
+        };
+        Ok((socket, input, output))
+    }
+
+    pub fn local_address(&self) -> SocketResult<SocketAddr> {
+        match &self.state {
+            VirtualTcpState::BindStarted(..) => Err(ErrorCode::ConcurrencyConflict.into()),
+            VirtualTcpState::Bound(binding)
+            | VirtualTcpState::ListenStarted(binding)
+            | VirtualTcpState::Listening {
+                _binding: binding, ..
+            } => Ok(binding.addr),
+            VirtualTcpState::Connected(connection) => Ok(connection.local_address),
+            _ => Err(ErrorCode::InvalidState.into()),
+        }
+    }
+
+    pub fn remote_address(&self) -> SocketResult<SocketAddr> {
+        match &self.state {
+            VirtualTcpState::Connected(connection) => Ok(connection.remote_address),
+            VirtualTcpState::ConnectReady(..) => Err(ErrorCode::ConcurrencyConflict.into()),
+            _ => Err(ErrorCode::InvalidState.into()),
+        }
+    }
+
+    pub fn is_listening(&self) -> bool {
+        matches!(self.state, VirtualTcpState::Listening { .. })
+    }
+
+    pub fn set_listen_backlog_size(&self, value: u32) -> SocketResult<()> {
+        match &self.state {
+            VirtualTcpState::Default | VirtualTcpState::Bound(..) => {}
+            VirtualTcpState::Listening { listener, .. } => listener.set_backlog(value),
+            _ => return Err(ErrorCode::InvalidState.into()),
+        }
+        Ok(())
+    }
+
+    pub fn keep_alive_enabled(&self) -> SocketResult<bool> {
+        Ok(self.options.keep_alive_enabled)
+    }
+
+    pub fn set_keep_alive_enabled(&mut self, value: bool) -> SocketResult<()> {
+        self.options.keep_alive_enabled = value;
+        Ok(())
+    }
+
+    pub fn keep_alive_idle_time(&self) -> SocketResult<Duration> {
+        Ok(self.options.keep_alive_idle_time)
+    }
+
+    pub fn set_keep_alive_idle_time(&mut self, value: Duration) -> SocketResult<()> {
+        self.options.keep_alive_idle_time = non_zero(value, Duration::ZERO)?;
+        Ok(())
+    }
+
+    pub fn keep_alive_interval(&self) -> SocketResult<Duration> {
+        Ok(self.options.keep_alive_interval)
+    }
+
+    pub fn set_keep_alive_interval(&mut self, value: Duration) -> SocketResult<()> {
+        self.options.keep_alive_interval = non_zero(value, Duration::ZERO)?;
+        Ok(())
+    }
+
+    pub fn keep_alive_count(&self) -> SocketResult<u32> {
+        Ok(self.options.keep_alive_count)
+    }
+
+    pub fn set_keep_alive_count(&mut self, value: u32) -> SocketResult<()> {
+        self.options.keep_alive_count = non_zero(value, 0)?;
+        Ok(())
+    }
+
+    pub fn hop_limit(&self) -> SocketResult<u8> {
+        Ok(self.options.hop_limit)
+    }
+
+    pub fn set_hop_limit(&mut self, value: u8) -> SocketResult<()> {
+        self.options.hop_limit = non_zero(value, 0)?;
+        Ok(())
+    }
+
+    pub fn receive_buffer_size(&self) -> SocketResult<usize> {
+        Ok(self.options.receive_buffer_size)
+    }
+
+    pub fn set_receive_buffer_size(&mut self, value: usize) -> SocketResult<()> {
+        self.options.receive_buffer_size = non_zero(value, 0)?;
+        Ok(())
+    }
+
+    pub fn send_buffer_size(&self) -> SocketResult<usize> {
+        Ok(self.options.send_buffer_size)
+    }
+
+    pub fn set_send_buffer_size(&mut self, value: usize) -> SocketResult<()> {
+        self.options.send_buffer_size = non_zero(value, 0)?;
+        Ok(())
+    }
+
+    pub fn shutdown(&self, how: Shutdown) -> SocketResult<()> {
+        let VirtualTcpState::Connected(connection) = &self.state else {
+            return Err(ErrorCode::InvalidState.into());
+        };
+        connection.shutdown(how);
+        Ok(())
+    }
+
+    pub async fn ready(&mut self) {
+        if let VirtualTcpState::Listening { listener, .. } = &self.state {
+            listener.ready().await;
+        }
+    }
+}
+
+/// WIT: "If the provided value is 0, an `invalid-argument` error is returned."
+fn non_zero<T: PartialEq>(value: T, zero: T) -> SocketResult<T> {
+    if value == zero {
+        return Err(ErrorCode::InvalidArgument.into());
+    }
+    Ok(value)
+}
+
+/// The queue of datagrams received by a UDP socket.
+#[derive(Default)]
+struct Mailbox {
+    datagrams: Mutex<VecDeque<(Bytes, SocketAddr)>>,
+    changed: Notify,
+}
+
+impl Mailbox {
+    fn lock(&self) -> MutexGuard<'_, VecDeque<(Bytes, SocketAddr)>> {
+        self.datagrams.lock().unwrap()
+    }
+}
+
+/// A UDP socket on a [`VirtualNetwork`].
+///
+/// This is shared between a `UdpSocket` and its datagram streams, so all
+/// state is behind a lock.
+pub(crate) struct VirtualUdpSocket {
+    network: VirtualNetwork,
+    mailbox: Arc<Mailbox>,
+    state: Mutex<UdpEndpoint>,
+}
+
+struct UdpEndpoint {
+    binding: Option<Binding>,
+    remote_address: Option<SocketAddr>,
+    hop_limit: u8,
+    receive_buffer_size: usize,
+    send_buffer_size: usize,
+}
+
+impl VirtualUdpSocket {
+    pub fn new(network: &VirtualNetwork) -> Self {
+        Self {
+            network: network.clone(),
+            mailbox: Arc::new(Mailbox::default()),
+            state: Mutex::new(UdpEndpoint {
+                binding: None,
+                remote_address: None,
+                hop_limit: 64,
+                receive_buffer_size: PIPE_CAPACITY,
+                send_buffer_size: PIPE_CAPACITY,
+            }),
+        }
+    }
+
+    fn lock(&self) -> MutexGuard<'_, UdpEndpoint> {
+        self.state.lock().unwrap()
+    }
+
+    pub fn bind(&self, local_address: SocketAddr) -> io::Result<()> {
+        let binding = self.network.bind(Protocol::Udp, local_address)?;
+        self.network
+            .state()
+            .mailboxes
+            .insert(binding.addr, self.mailbox.clone());
+        self.lock().binding = Some(binding);
+        Ok(())
+    }
+
+    pub fn connect(&self, remote_address: SocketAddr) -> io::Result<()> {
+        if !remote_address.ip().is_loopback() {
+            return Err(Errno::NETUNREACH.into());
+        }
+        self.lock().remote_address = Some(normalize(remote_address));
+        Ok(())
+    }
+
+    pub fn disconnect(&self) {
+        self.lock().remote_address = None;
+    }
+
+    pub fn local_address(&self) -> io::Result<SocketAddr> {
+        let state = self.lock();
+        let Some(binding) = &state.binding else {
+            return Err(Errno::INVAL.into());
+        };
+
+        // Like on a real network, connecting a socket bound to the unspecified
+        // address fixes its local address.
+        let mut addr = binding.addr;
+        if let (true, Some(remote_address)) = (addr.ip().is_unspecified(), state.remote_address) {
+            addr.set_ip(remote_address.ip());
+        }
+        Ok(addr)
+    }
+
+    pub fn remote_address(&self) -> io::Result<SocketAddr> {
+        self.lock()
+            .remote_address
+            .ok_or_else(|| Errno::NOTCONN.into())
+    }
+
+    pub fn hop_limit(&self) -> SocketResult<u8> {
+        Ok(self.lock().hop_limit)
+    }
+
+    pub fn set_hop_limit(&self, value: u8) -> SocketResult<()> {
+        self.lock().hop_limit = non_zero(value, 0)?;
+        Ok(())
+    }
+
+    pub fn receive_buffer_size(&self) -> SocketResult<usize> {
+        Ok(self.lock().receive_buffer_size)
+    }
+
+    pub fn set_receive_buffer_size(&self, value: usize) -> SocketResult<()> {
+        self.lock().receive_buffer_size = non_zero(value, 0)?;
+        Ok(())
+    }
+
+    pub fn send_buffer_size(&self) -> SocketResult<usize> {
+        Ok(self.lock().send_buffer_size)
+    }
+
+    pub fn set_send_buffer_size(&self, value: usize) -> SocketResult<()> {
+        self.lock().send_buffer_size = non_zero(value, 0)?;
+        Ok(())
+    }
+
+    /// Takes the oldest datagram from the queue, along with its sender.
+    pub fn try_recv_from(&self) -> io::Result<(Bytes, SocketAddr)> {
+        self.mailbox
+            .lock()
+            .pop_front()
+            .ok_or_else(|| Errno::WOULDBLOCK.into())
+    }
+
+    /// Delivers a datagram to the socket bound to `remote_address`.
+    ///
+    /// Like on a real network, datagrams are silently dropped if nothing is
+    /// bound to the address or the receiver's queue is full.
+    pub fn send_to(&self, data: &[u8], remote_address: SocketAddr) -> io::Result<()> {
+        let remote_address = normalize(remote_address);
+        if !remote_address.ip().is_loopback() {
+            return Err(Errno::NETUNREACH.into());
+        }
+
+        // Sockets bound to the unspecified address send from the loopback
+        // address the datagram is sent to.
+        let mut source = self.local_address()?;
+        if source.ip().is_unspecified() {
+            source.set_ip(remote_address.ip());
+        }
+
+        let Some(mailbox) = lookup(&self.network.state().mailboxes, remote_address) else {
+            return Ok(());
+        };
+        let mut datagrams = mailbox.lock();
+        if datagrams.len() < MAILBOX_CAPACITY {
+            datagrams.push_back((Bytes::copy_from_slice(data), source));
+        }
+        drop(datagrams);
+        mailbox.changed.notify_waiters();
+        Ok(())
+    }
+
+    /// Waits until a datagram can be received.
+    pub async fn readable(&self) {
+        loop {
+            let changed = self.mailbox.changed.notified();
+            if !self.mailbox.lock().is_empty() {
+                return;
+            }
+            changed.await;
+        }
+    }
+}
+
+#[cfg(test)]
+mod test {
+    use super::*;
+    use crate::runtime::in_tokio;
+
+    fn addr(addr: &str) -> SocketAddr {
+        addr.parse().unwrap()
+    }
+
+    fn listen(network: &VirtualNetwork, address: &str) -> VirtualTcpSocket {
+        let mut socket = VirtualTcpSocket::new(network, SocketAddressFamily::Ipv4);
+        socket.start_bind(addr(address)).unwrap();
+        socket.finish_bind().unwrap();
+        socket.start_listen().unwrap();
+        socket.finish_listen(2).unwrap();
+        socket
+    }
+
+    fn connect(
+        network: &VirtualNetwork,
+        address: &str,
+    ) -> SocketResult<(VirtualTcpSocket, InputStream, OutputStream)> {
+        let mut socket = VirtualTcpSocket::new(network, SocketAddressFamily::Ipv4);
+        socket.start_connect(addr(address))?;
+        let (input, output) = socket.finish_connect()?;
+        Ok((socket, input, output))
+    }
+
+    fn error_code(err: crate::SocketError) -> ErrorCode {
+        err.downcast().unwrap()
+    }
+
+    #[test]
+    fn tcp() {
+        let network = VirtualNetwork::new();
+        let mut server = listen(&network, "127.0.0.1:8080");
+
+        let (client, mut client_in, mut client_out) = connect(&network, "127.0.0.1:8080").unwrap();
+        assert_eq!(client.local_address().unwrap(), addr("127.0.0.1:49152"));
+        assert_eq!(client.remote_address().unwrap(), addr("127.0.0.1:8080"));
+
+        let (accepted, mut server_in, mut server_out) = server.accept().unwrap();
+        assert_eq!(accepted.local_address().unwrap(), addr("127.0.0.1:8080"));
+        assert_eq!(accepted.remote_address().unwrap(), addr("127.0.0.1:49152"));
+        assert!(matches!(
+            error_code(server.accept().err().unwrap()),
+            ErrorCode::WouldBlock
+        ));
+
+        client_out.write(Bytes::from_static(b"ping")).unwrap();
+        assert_eq!(server_in.read(2).unwrap(), "pi");
+        assert_eq!(server_in.read(10).unwrap(), "ng");
+        assert!(server_in.read(10).unwrap().is_empty());
+
+        server_out.write(Bytes::from_static(b"pong")).unwrap();
+        drop(server_out);
+        assert_eq!(in_tokio(client_in.blocking_read(10)).unwrap(), "pong");
+        assert!(matches!(client_in.read(10), Err(StreamError::Closed)));
+
+        // The client's port is released once it is closed.
+        drop((client, client_in, client_out));
+        assert!(network.state().bound.iter().all(|(_, a)| a.port() == 8080));
+    }
+
+    #[test]
+    fn tcp_errors() {
+        let network = VirtualNetwork::new();
+
+        assert!(matches!(
+            error_code(connect(&network, "127.0.0.1:8080").err().unwrap()),
+            ErrorCode::ConnectionRefused
+        ));
+        assert!(matches!(
+            error_code(connect(&network, "192.0.2.1:8080").err().unwrap()),
+            ErrorCode::RemoteUnreachable
+        ));
+
+        let mut socket = VirtualTcpSocket::new(&network, SocketAddressFamily::Ipv4);
+        let err = socket.start_bind(addr("192.0.2.1:8080")).unwrap_err();
+        assert!(matches!(
+            ErrorCode::from(err),
+            ErrorCode::AddressNotBindable
+        ));
+
+        // The unspecified address conflicts with all loopback addresses.
+        let _server = listen(&network, "0.0.0.0:8080");
+        let err = socket.start_bind(addr("127.0.0.2:8080")).unwrap_err();
+        assert!(matches!(ErrorCode::from(err), ErrorCode::AddressInUse));
+
+        // Connections beyond the backlog are refused.
+        let _first = connect(&network, "127.0.0.2:8080").unwrap();
+        let _second = connect(&network, "127.0.0.2:8080").unwrap();
+        assert!(matches!(
+            error_code(connect(&network, "127.0.0.2:8080").err().unwrap()),
+            ErrorCode::ConnectionRefused
+        ));
+    }
+
+    #[test]
+    fn tcp_listener_closed() {
+        let network = VirtualNetwork::new();
+        let server = listen(&network, "127.0.0.1:8080");
+        let (_client, mut input, mut output) = connect(&network, "127.0.0.1:8080").unwrap();
+
+        drop(server);
+        assert!(matches!(input.read(10), Err(StreamError::Closed)));
+        assert!(matches!(output.check_write(), Err(StreamError::Closed)));
+        assert!(matches!(
+            error_code(connect(&network, "127.0.0.1:8080").err().unwrap()),
+            ErrorCode::ConnectionRefused
+        ));
+    }
+
+    #[test]
+    fn udp() {
+        let network = VirtualNetwork::new();
+        let a = VirtualUdpSocket::new(&network);
+        a.bind(addr("0.0.0.0:0")).unwrap();
+        let b = VirtualUdpSocket::new(&network);
+        b.bind(addr("127.0.0.1:5353")).unwrap();
+        assert_eq!(a.local_address().unwrap(), addr("0.0.0.0:49152"));
+        a.connect(addr("127.0.0.1:5353")).unwrap();
+        assert_eq!(a.local_address().unwrap(), addr("127.0.0.1:49152"));
+        a.disconnect();
+
+        for data in [&b"one"[..], b"two", b"three"] {
+            a.send_to(data, addr("127.0.0.1:5353")).unwrap();
+        }
+        in_tokio(b.readable());
+        for data in ["one", "two", "three"] {
+            assert_eq!(
+                b.try_recv_from().unwrap(),
+                (Bytes::from(data), addr("127.0.0.1:49152"))
+            );
+        }
+        assert!(b.try_recv_from().is_err());
+
+        // Datagrams to unbound ports are dropped.
+        b.send_to(b"lost", addr("127.0.0.1:1")).unwrap();
+        drop(a);
+        b.send_to(b"lost", addr("127.0.0.1:49152")).unwrap();
+        assert_eq!(network.state().mailboxes.len(), 1);
+    }
+}
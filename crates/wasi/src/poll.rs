@@ -1,3 +1,4 @@
+use crate::trace::{Event, CALLS};
 use crate::{bindings::io::poll, WasiImpl, WasiView};
 use anyhow::{anyhow, Result};
 use std::any::Any;
@@ -128,6 +129,7 @@ where
             return Err(anyhow!("empty poll list"));
         }
 
+        let trace = self.ctx().trace.clone();
         let table = self.table();
 
         let mut table_futures: HashMap<u32, (MakeFuture, Vec<ReadylistIndex>)> = HashMap::new();
@@ -174,7 +176,23 @@ where
             }
         }
 
-        Ok(PollList { futures }.await)
+        if let Some(trace) = trace.as_ref().filter(|t| t.is_replay()) {
+            // Wait for the pollables that were ready when this was recorded,
+            // rather than for whichever are ready first now.
+            let ready: Vec<u32> = trace.next(CALLS, Event::Poll)?;
+            for (future, indices) in futures {
+                if indices.iter().any(|i| ready.contains(i)) {
+                    future.await;
+                }
+            }
+            return Ok(ready);
+        }
+
+        let ready = PollList { futures }.await;
+        if let Some(trace) = &trace {
+            trace.push(CALLS, Event::Poll, &ready)?;
+        }
+        Ok(ready)
     }
 }
 
@@ -191,14 +209,23 @@ where
         Ok(())
     }
     async fn ready(&mut self, pollable: Resource<Pollable>) -> Result<bool> {
+        let trace = self.ctx().trace.clone();
         let table = self.table();
         let pollable = table.get(&pollable)?;
         let ready = (pollable.make_future)(table.get_any_mut(pollable.index)?);
+        if let Some(trace) = trace.as_ref().filter(|t| t.is_replay()) {
+            let recorded: bool = trace.next(CALLS, Event::Ready)?;
+            if recorded {
+                ready.await;
+            }
+            return Ok(recorded);
+        }
         futures::pin_mut!(ready);
-        Ok(matches!(
-            futures::future::poll_immediate(ready).await,
-            Some(())
-        ))
+        let ready = matches!(futures::future::poll_immediate(ready).await, Some(()));
+        if let Some(trace) = &trace {
+            trace.push(CALLS, Event::Ready, &ready)?;
+        }
+        Ok(ready)
     }
     fn drop(&mut self, pollable: Resource<Pollable>) -> Result<()> {
         let pollable = self.table().delete(pollable)?;
@@ -73,7 +73,8 @@ use crate::bindings::{
     io::streams,
 };
 use crate::{
-    FsError, IsATTY, ResourceTable, StreamError, StreamResult, WasiCtx, WasiImpl, WasiView,
+    FsError, IsATTY, ResourceTable, StreamError, StreamResult, WasiCtx, WasiFile, WasiImpl,
+    WasiView,
 };
 use anyhow::{bail, Context};
 use std::collections::{BTreeMap, HashSet};
@@ -82,7 +83,6 @@ use std::ops::{Deref, DerefMut};
 use std::slice;
 use std::sync::atomic::{AtomicU64, Ordering};
 use std::sync::Arc;
-use system_interface::fs::FileIoExt;
 use wasmtime::component::Resource;
 use wiggle::tracing::instrument;
 use wiggle::{GuestError, GuestMemory, GuestPtr, GuestType};
@@ -597,7 +597,7 @@ impl WasiP1Ctx {
                 let f = self.table().get(&fd)?.file()?;
                 let buf = first_non_empty_ciovec(memory, ciovs)?;
 
-                let do_write = move |f: &cap_std::fs::File, buf: &[u8]| match (append, write) {
+                let do_write = move |f: &dyn WasiFile, buf: &[u8]| match (append, write) {
                     // Note that this is implementing Linux semantics of
                     // `pwrite` where the offset is ignored if the file was
                     // opened in append mode.
@@ -2279,6 +2279,8 @@ impl wasi_snapshot_preview1::WasiSnapshotPreview1 for WasiP1Ctx {
                     .flags
                     .contains(types::Subclockflags::SUBSCRIPTION_CLOCK_ABSTIME)
                     && self.ctx().allow_blocking_current_thread
+                    && self.ctx().virtual_clock.is_none()
+                    && self.ctx().trace.is_none()
                 {
                     std::thread::sleep(std::time::Duration::from_nanos(clocksub.timeout));
                     memory.write(
@@ -467,7 +467,7 @@ where
     T: WasiView,
 {
     fn get_terminal_stdin(&mut self) -> anyhow::Result<Option<Resource<TerminalInput>>> {
-        if self.ctx().stdin.isatty() {
+        if !self.ctx().deterministic && self.ctx().stdin.isatty() {
             let fd = self.table().push(TerminalInput)?;
             Ok(Some(fd))
         } else {
@@ -480,7 +480,7 @@ where
     T: WasiView,
 {
     fn get_terminal_stdout(&mut self) -> anyhow::Result<Option<Resource<TerminalOutput>>> {
-        if self.ctx().stdout.isatty() {
+        if !self.ctx().deterministic && self.ctx().stdout.isatty() {
             let fd = self.table().push(TerminalOutput)?;
             Ok(Some(fd))
         } else {
@@ -493,7 +493,7 @@ where
     T: WasiView,
 {
     fn get_terminal_stderr(&mut self) -> anyhow::Result<Option<Resource<TerminalOutput>>> {
-        if self.ctx().stderr.isatty() {
+        if !self.ctx().deterministic && self.ctx().stderr.isatty() {
             let fd = self.table().push(TerminalOutput)?;
             Ok(Some(fd))
         } else {
@@ -1,6 +1,6 @@
 use crate::bindings::sockets::tcp::ErrorCode;
 use crate::host::network;
-use crate::network::SocketAddressFamily;
+use crate::network::{SocketAddressFamily, VirtualNetwork, VirtualTcpSocket};
 use crate::runtime::{with_ambient_tokio_runtime, AbortOnDropJoinHandle};
 use crate::{
     HostInputStream, HostOutputStream, InputStream, OutputStream, SocketError, SocketResult,
@@ -64,6 +64,9 @@ enum TcpState {
     },
 
     Closed,
+
+    /// A socket on a [`VirtualNetwork`], which has its own state machine.
+    Virtual(VirtualTcpSocket),
 }
 
 impl std::fmt::Debug for TcpState {
@@ -81,6 +84,7 @@ impl std::fmt::Debug for TcpState {
             Self::ConnectReady(_) => f.debug_tuple("ConnectReady").finish(),
             Self::Connected { .. } => f.debug_tuple("Connected").finish(),
             Self::Closed => write!(f, "Closed"),
+            Self::Virtual(_) => f.debug_tuple("Virtual").finish(),
         }
     }
 }
@@ -128,6 +132,18 @@ impl TcpSocket {
         })
     }
 
+    /// Create a new socket in the given family on a [`VirtualNetwork`].
+    pub fn new_virtual(network: &VirtualNetwork, family: AddressFamily) -> io::Result<Self> {
+        let family = match family {
+            AddressFamily::Ipv4 => SocketAddressFamily::Ipv4,
+            AddressFamily::Ipv6 => SocketAddressFamily::Ipv6,
+        };
+        Self::from_state(
+            TcpState::Virtual(VirtualTcpSocket::new(network, family)),
+            family,
+        )
+    }
+
     /// Create a `TcpSocket` from an existing socket.
     fn from_state(state: TcpState, family: SocketAddressFamily) -> io::Result<Self> {
         Ok(Self {
@@ -163,13 +179,18 @@ impl TcpSocket {
             | TcpState::ListenStarted(..)
             | TcpState::Connecting(..)
             | TcpState::ConnectReady(..)
-            | TcpState::Closed => Err(ErrorCode::InvalidState.into()),
+            | TcpState::Closed
+            | TcpState::Virtual(..) => Err(ErrorCode::InvalidState.into()),
         }
     }
 }
 
 impl TcpSocket {
     pub fn start_bind(&mut self, local_address: SocketAddr) -> io::Result<()> {
+        if let TcpState::Virtual(socket) = &mut self.tcp_state {
+            return socket.start_bind(local_address);
+        }
+
         let tokio_socket = match &self.tcp_state {
             TcpState::Default(socket) => socket,
             TcpState::BindStarted(..) => return Err(Errno::ALREADY.into()),
@@ -223,6 +244,10 @@ impl TcpSocket {
     }
 
     pub fn finish_bind(&mut self) -> SocketResult<()> {
+        if let TcpState::Virtual(socket) = &mut self.tcp_state {
+            return socket.finish_bind();
+        }
+
         match std::mem::replace(&mut self.tcp_state, TcpState::Closed) {
             TcpState::BindStarted(socket) => {
                 self.tcp_state = TcpState::Bound(socket);
@@ -237,6 +262,10 @@ impl TcpSocket {
     }
 
     pub fn start_connect(&mut self, remote_address: SocketAddr) -> SocketResult<()> {
+        if let TcpState::Virtual(socket) = &mut self.tcp_state {
+            return socket.start_connect(remote_address);
+        }
+
         match self.tcp_state {
             TcpState::Default(..) => {}
 
@@ -264,6 +293,10 @@ impl TcpSocket {
     }
 
     pub fn finish_connect(&mut self) -> SocketResult<(InputStream, OutputStream)> {
+        if let TcpState::Virtual(socket) = &mut self.tcp_state {
+            return socket.finish_connect();
+        }
+
         let previous_state = std::mem::replace(&mut self.tcp_state, TcpState::Closed);
         let result = match previous_state {
             TcpState::ConnectReady(result) => result,
@@ -305,6 +338,10 @@ impl TcpSocket {
     }
 
     pub fn start_listen(&mut self) -> SocketResult<()> {
+        if let TcpState::Virtual(socket) = &mut self.tcp_state {
+            return socket.start_listen();
+        }
+
         match std::mem::replace(&mut self.tcp_state, TcpState::Closed) {
             TcpState::Bound(tokio_socket) => {
                 self.tcp_state = TcpState::ListenStarted(tokio_socket);
@@ -322,6 +359,10 @@ impl TcpSocket {
     }
 
     pub fn finish_listen(&mut self) -> SocketResult<()> {
+        if let TcpState::Virtual(socket) = &mut self.tcp_state {
+            return socket.finish_listen(self.listen_backlog_size);
+        }
+
         let tokio_socket = match std::mem::replace(&mut self.tcp_state, TcpState::Closed) {
             TcpState::ListenStarted(tokio_socket) => tokio_socket,
             previous_state => {
@@ -361,6 +402,12 @@ impl TcpSocket {
     }
 
     pub fn accept(&mut self) -> SocketResult<(Self, InputStream, OutputStream)> {
+        if let TcpState::Virtual(socket) = &mut self.tcp_state {
+            let (socket, input, output) = socket.accept()?;
+            let tcp_socket = TcpSocket::from_state(TcpState::Virtual(socket), self.family)?;
+            return Ok((tcp_socket, input, output));
+        }
+
         let TcpState::Listening {
             listener,
             pending_accept,
@@ -460,6 +507,10 @@ impl TcpSocket {
     }
 
     pub fn local_address(&self) -> SocketResult<SocketAddr> {
+        if let TcpState::Virtual(socket) = &self.tcp_state {
+            return socket.local_address();
+        }
+
         let view = match self.tcp_state {
             TcpState::Default(..) => return Err(ErrorCode::InvalidState.into()),
             TcpState::BindStarted(..) => return Err(ErrorCode::ConcurrencyConflict.into()),
@@ -470,6 +521,10 @@ impl TcpSocket {
     }
 
     pub fn remote_address(&self) -> SocketResult<SocketAddr> {
+        if let TcpState::Virtual(socket) = &self.tcp_state {
+            return socket.remote_address();
+        }
+
         let view = match self.tcp_state {
             TcpState::Connected { .. } => self.as_std_view()?,
             TcpState::Connecting(..) | TcpState::ConnectReady(..) => {
@@ -482,7 +537,11 @@ impl TcpSocket {
     }
 
     pub fn is_listening(&self) -> bool {
-        matches!(self.tcp_state, TcpState::Listening { .. })
+        match &self.tcp_state {
+            TcpState::Listening { .. } => true,
+            TcpState::Virtual(socket) => socket.is_listening(),
+            _ => false,
+        }
     }
 
     pub fn address_family(&self) -> SocketAddressFamily {
@@ -511,6 +570,7 @@ impl TcpSocket {
                 rustix::net::listen(&listener, value.try_into().unwrap())
                     .map_err(|_| ErrorCode::NotSupported)?;
             }
+            TcpState::Virtual(socket) => socket.set_listen_backlog_size(value)?,
             _ => return Err(ErrorCode::InvalidState.into()),
         }
         self.listen_backlog_size = value;
@@ -519,21 +579,37 @@ impl TcpSocket {
     }
 
     pub fn keep_alive_enabled(&self) -> SocketResult<bool> {
+        if let TcpState::Virtual(socket) = &self.tcp_state {
+            return socket.keep_alive_enabled();
+        }
+
         let view = &*self.as_std_view()?;
         Ok(sockopt::get_socket_keepalive(view)?)
     }
 
-    pub fn set_keep_alive_enabled(&self, value: bool) -> SocketResult<()> {
+    pub fn set_keep_alive_enabled(&mut self, value: bool) -> SocketResult<()> {
+        if let TcpState::Virtual(socket) = &mut self.tcp_state {
+            return socket.set_keep_alive_enabled(value);
+        }
+
         let view = &*self.as_std_view()?;
         Ok(sockopt::set_socket_keepalive(view, value)?)
     }
 
     pub fn keep_alive_idle_time(&self) -> SocketResult<std::time::Duration> {
+        if let TcpState::Virtual(socket) = &self.tcp_state {
+            return socket.keep_alive_idle_time();
+        }
+
         let view = &*self.as_std_view()?;
         Ok(sockopt::get_tcp_keepidle(view)?)
     }
 
     pub fn set_keep_alive_idle_time(&mut self, duration: std::time::Duration) -> SocketResult<()> {
+        if let TcpState::Virtual(socket) = &mut self.tcp_state {
+            return socket.set_keep_alive_idle_time(duration);
+        }
+
         {
             let view = &*self.as_std_view()?;
             network::util::set_tcp_keepidle(view, duration)?;
@@ -548,26 +624,46 @@ impl TcpSocket {
     }
 
     pub fn keep_alive_interval(&self) -> SocketResult<std::time::Duration> {
+        if let TcpState::Virtual(socket) = &self.tcp_state {
+            return socket.keep_alive_interval();
+        }
+
         let view = &*self.as_std_view()?;
         Ok(sockopt::get_tcp_keepintvl(view)?)
     }
 
-    pub fn set_keep_alive_interval(&self, duration: std::time::Duration) -> SocketResult<()> {
+    pub fn set_keep_alive_interval(&mut self, duration: std::time::Duration) -> SocketResult<()> {
+        if let TcpState::Virtual(socket) = &mut self.tcp_state {
+            return socket.set_keep_alive_interval(duration);
+        }
+
         let view = &*self.as_std_view()?;
         Ok(network::util::set_tcp_keepintvl(view, duration)?)
     }
 
     pub fn keep_alive_count(&self) -> SocketResult<u32> {
+        if let TcpState::Virtual(socket) = &self.tcp_state {
+            return socket.keep_alive_count();
+        }
+
         let view = &*self.as_std_view()?;
         Ok(sockopt::get_tcp_keepcnt(view)?)
     }
 
-    pub fn set_keep_alive_count(&self, value: u32) -> SocketResult<()> {
+    pub fn set_keep_alive_count(&mut self, value: u32) -> SocketResult<()> {
+        if let TcpState::Virtual(socket) = &mut self.tcp_state {
+            return socket.set_keep_alive_count(value);
+        }
+
         let view = &*self.as_std_view()?;
         Ok(network::util::set_tcp_keepcnt(view, value)?)
     }
 
     pub fn hop_limit(&self) -> SocketResult<u8> {
+        if let TcpState::Virtual(socket) = &self.tcp_state {
+            return socket.hop_limit();
+        }
+
         let view = &*self.as_std_view()?;
 
         let ttl = match self.family {
@@ -579,6 +675,10 @@ impl TcpSocket {
     }
 
     pub fn set_hop_limit(&mut self, value: u8) -> SocketResult<()> {
+        if let TcpState::Virtual(socket) = &mut self.tcp_state {
+            return socket.set_hop_limit(value);
+        }
+
         {
             let view = &*self.as_std_view()?;
 
@@ -597,12 +697,20 @@ impl TcpSocket {
     }
 
     pub fn receive_buffer_size(&self) -> SocketResult<usize> {
+        if let TcpState::Virtual(socket) = &self.tcp_state {
+            return socket.receive_buffer_size();
+        }
+
         let view = &*self.as_std_view()?;
 
         Ok(network::util::get_socket_recv_buffer_size(view)?)
     }
 
     pub fn set_receive_buffer_size(&mut self, value: usize) -> SocketResult<()> {
+        if let TcpState::Virtual(socket) = &mut self.tcp_state {
+            return socket.set_receive_buffer_size(value);
+        }
+
         {
             let view = &*self.as_std_view()?;
 
@@ -618,12 +726,20 @@ impl TcpSocket {
     }
 
     pub fn send_buffer_size(&self) -> SocketResult<usize> {
+        if let TcpState::Virtual(socket) = &self.tcp_state {
+            return socket.send_buffer_size();
+        }
+
         let view = &*self.as_std_view()?;
 
         Ok(network::util::get_socket_send_buffer_size(view)?)
     }
 
     pub fn set_send_buffer_size(&mut self, value: usize) -> SocketResult<()> {
+        if let TcpState::Virtual(socket) = &mut self.tcp_state {
+            return socket.set_send_buffer_size(value);
+        }
+
         {
             let view = &*self.as_std_view()?;
 
@@ -639,6 +755,10 @@ impl TcpSocket {
     }
 
     pub fn shutdown(&self, how: Shutdown) -> SocketResult<()> {
+        if let TcpState::Virtual(socket) = &self.tcp_state {
+            return socket.shutdown(how);
+        }
+
         let TcpState::Connected { reader, writer, .. } = &self.tcp_state else {
             return Err(ErrorCode::InvalidState.into());
         };
@@ -671,6 +791,7 @@ impl Subscribe for TcpSocket {
             TcpState::Connecting(future) => {
                 self.tcp_state = TcpState::ConnectReady(future.as_mut().await);
             }
+            TcpState::Virtual(socket) => socket.ready().await,
             TcpState::Listening {
                 listener,
                 pending_accept,
@@ -0,0 +1,744 @@
+//! Recording the nondeterministic results of the WASI calls made by a guest,
+//! and replaying them later.
+
+mod codec;
+mod fs;
+
+pub(crate) use self::codec::Traced;
+use self::fs::{RecordingDir, ReplayDir};
+use crate::filesystem::{Dir, DirPerms, FilePerms, OpenMode};
+use crate::network::SocketResult;
+use crate::pipe::ClosedInputStream;
+use crate::stdio::{StdinStream, StdoutStream};
+use crate::{
+    HostInputStream, HostOutputStream, HostResolver, SocketError, StreamError, StreamResult,
+    Subscribe, WasiCtx,
+};
+use anyhow::{anyhow, bail, Context, Result};
+use bytes::Bytes;
+use std::collections::{HashMap, VecDeque};
+use std::io::{self, Read, Write};
+use std::mem;
+use std::net::IpAddr;
+use std::path::Path;
+use std::sync::{Arc, Mutex};
+
+/// A trace of the nondeterministic results of the WASI calls made by a guest,
+/// which is either being recorded or replayed.
+///
+/// A trace is installed in a context with
+/// [`WasiCtxBuilder::trace`](crate::WasiCtxBuilder::trace). When recording,
+/// the context works as usual but also writes the following to the trace:
+///
+/// * the arguments, environment variables and preopened directories,
+/// * the readings of clocks and the output of all random number generators,
+/// * what's read from stdin, and whether stdio is a terminal,
+/// * which pollables `wasi:io/poll` reports as ready,
+/// * the results of all calls on the files and directories under preopened
+///   directories, including the data read from files,
+/// * the addresses that `wasi:sockets/ip-name-lookup` resolves names to.
+///
+/// When replaying, all of those come from the trace instead: there's no need
+/// for the original directories or stdin, and timers are ready as soon as
+/// the guest waits on them. Output to stdout and stderr is written as usual,
+/// so a replay shows the same output as the original run. If the guest makes
+/// a call which wasn't recorded, for example because it's a different guest,
+/// that call and all calls after it trap.
+///
+/// Sockets other than name lookups aren't recorded. Embedders can record the
+/// results of their own interfaces alongside with
+/// [`Trace::record_custom`].
+///
+/// A trace should only be installed in a single context, and cloning a trace
+/// is cheap as the clone shares its state.
+#[derive(Clone)]
+pub struct Trace {
+    replay: bool,
+    state: Arc<Mutex<State>>,
+}
+
+/// Identifies the file a trace is stored in, and its version.
+const MAGIC: &[u8; 8] = b"wasitrc\x01";
+
+/// The channel of the calls made on the thread of the guest, which happen in
+/// the same order every time the guest is run.
+pub(crate) const CALLS: u64 = 0;
+/// The channel of the setup of the context.
+const SETUP: u64 = 1;
+/// The channel of name lookups, which are made concurrently.
+const RESOLVER: u64 = 2;
+/// The channel of the records of embedders.
+const CUSTOM: u64 = u64::MAX;
+/// The first channel handed out by [`Trace::new_channel`].
+const FIRST_DYNAMIC_CHANNEL: u64 = 16;
+
+struct State {
+    mode: Mode,
+    next_channel: u64,
+    /// The first error that recording or replaying ran into, after which the
+    /// trace can't be used any more.
+    error: Option<String>,
+}
+
+enum Mode {
+    Record(Box<dyn Write + Send>),
+    Replay {
+        /// The events of each channel, in the order they were recorded.
+        channels: HashMap<u64, VecDeque<(u8, Vec<u8>)>>,
+        /// The records of embedders, by name.
+        custom: HashMap<String, Vec<Vec<u8>>>,
+    },
+}
+
+macro_rules! events {
+    ($($event:ident)*) => {
+        /// The calls whose results are recorded in a trace.
+        #[derive(Copy, Clone, Debug, PartialEq, Eq)]
+        pub(crate) enum Event {
+            $($event,)*
+        }
+
+        impl Event {
+            const ALL: &'static [Event] = &[$(Event::$event),*];
+        }
+    };
+}
+
+events! {
+    Setup
+    WallClockNow WallClockResolution MonotonicClockNow MonotonicClockResolution
+    RandomBytes RandomU64 InsecureRandomBytes InsecureRandomU64
+    StdinRead Poll Ready Resolve
+    Open Metadata MetadataAt ReadDir CreateDir RemoveDir UnlinkFile Rename Link Symlink
+    ReadLink SetTimes SetTimesAt Sync Read Write Append SetLen Advise
+}
+
+impl Trace {
+    /// Creates a trace which records into `output`.
+    pub fn record(mut output: impl Write + Send + 'static) -> Result<Trace> {
+        output.write_all(MAGIC).context("failed to write trace")?;
+        Ok(Trace {
+            replay: false,
+            state: Arc::new(Mutex::new(State {
+                mode: Mode::Record(Box::new(output)),
+                next_channel: FIRST_DYNAMIC_CHANNEL,
+                error: None,
+            })),
+        })
+    }
+
+    /// Creates a trace which records into a new file at `path`.
+    ///
+    /// Writes to the file are buffered, see [`Trace::flush`].
+    pub fn record_file(path: impl AsRef<Path>) -> Result<Trace> {
+        let path = path.as_ref();
+        let file = std::fs::File::create(path)
+            .with_context(|| format!("failed to create trace `{}`", path.display()))?;
+        Trace::record(io::BufWriter::new(file))
+    }
+
+    /// Creates a trace which replays the trace recorded in `input`.
+    pub fn replay(mut input: impl Read) -> Result<Trace> {
+        let mut bytes = Vec::new();
+        input.read_to_end(&mut bytes)?;
+        let Some(mut input) = bytes.strip_prefix(MAGIC) else {
+            bail!("not a trace recorded by this version of wasmtime-wasi");
+        };
+
+        let mut channels = HashMap::<u64, VecDeque<_>>::new();
+        let mut custom = HashMap::<String, Vec<_>>::new();
+        while !input.is_empty() {
+            let channel = u64::decode(&mut input)?;
+            let event = u8::decode(&mut input)?;
+            let payload = Vec::<u8>::decode(&mut input)?;
+            if channel == CUSTOM {
+                let (name, data) = <(String, Vec<u8>)>::decode(&mut &payload[..])?;
+                custom.entry(name).or_default().push(data);
+            } else {
+                channels
+                    .entry(channel)
+                    .or_default()
+                    .push_back((event, payload));
+            }
+        }
+
+        Ok(Trace {
+            replay: true,
+            state: Arc::new(Mutex::new(State {
+                mode: Mode::Replay { channels, custom },
+                next_channel: FIRST_DYNAMIC_CHANNEL,
+                error: None,
+            })),
+        })
+    }
+
+    /// Creates a trace which replays the trace recorded in the file at
+    /// `path`.
+    pub fn replay_file(path: impl AsRef<Path>) -> Result<Trace> {
+        let path = path.as_ref();
+        let file = std::fs::File::open(path)
+            .with_context(|| format!("failed to open trace `{}`", path.display()))?;
+        Trace::replay(io::BufReader::new(file))
+            .with_context(|| format!("failed to read trace `{}`", path.display()))
+    }
+
+    /// Returns whether this trace is being replayed, rather than recorded.
+    pub fn is_replay(&self) -> bool {
+        self.replay
+    }
+
+    /// Flushes what's been recorded so far to the output of this trace.
+    ///
+    /// The output is also flushed when the last clone of this trace is
+    /// dropped.
+    pub fn flush(&self) -> Result<()> {
+        let mut state = self.state.lock().unwrap();
+        if let Mode::Record(output) = &mut state.mode {
+            output.flush().context("failed to write trace")?;
+        }
+        Ok(())
+    }
+
+    /// Records `data` under `name`, for interfaces provided by the embedder.
+    ///
+    /// All data recorded under a name is returned by
+    /// [`Trace::custom_records`] when replaying.
+    pub fn record_custom(&self, name: &str, data: &[u8]) -> Result<()> {
+        self.push(CUSTOM, Event::Setup, &(name.to_string(), data.to_vec()))
+    }
+
+    /// Returns all data recorded with [`Trace::record_custom`] under `name`,
+    /// in the order it was recorded.
+    ///
+    /// This is empty when recording.
+    pub fn custom_records(&self, name: &str) -> Vec<Vec<u8>> {
+        match &self.state.lock().unwrap().mode {
+            Mode::Replay { custom, .. } => custom.get(name).cloned().unwrap_or_default(),
+            Mode::Record(_) => Vec::new(),
+        }
+    }
+
+    /// Returns a new channel, for calls which may be made concurrently with
+    /// those on other channels.
+    pub(crate) fn new_channel(&self) -> u64 {
+        let mut state = self.state.lock().unwrap();
+        let channel = state.next_channel;
+        state.next_channel += 1;
+        channel
+    }
+
+    /// Records `value` as the result of `event` on `channel`.
+    pub(crate) fn push<T: Traced>(&self, channel: u64, event: Event, value: &T) -> Result<()> {
+        let mut payload = Vec::new();
+        value.encode(&mut payload);
+        let mut frame = Vec::with_capacity(payload.len() + 17);
+        channel.encode(&mut frame);
+        (event as u8).encode(&mut frame);
+        payload.encode(&mut frame);
+
+        let mut state = self.state.lock().unwrap();
+        if let Some(error) = &state.error {
+            bail!("{error}");
+        }
+        let Mode::Record(output) = &mut state.mode else {
+            bail!("cannot record into a trace that's being replayed");
+        };
+        if let Err(e) = output.write_all(&frame) {
+            let error = format!("failed to write trace: {e}");
+            state.error = Some(error.clone());
+            bail!(error);
+        }
+        Ok(())
+    }
+
+    /// Replays the next result on `channel`, which must be that of `event`.
+    pub(crate) fn next<T: Traced>(&self, channel: u64, event: Event) -> Result<T> {
+        let mut state = self.state.lock().unwrap();
+        if let Some(error) = &state.error {
+            bail!("{error}");
+        }
+        let Mode::Replay { channels, .. } = &mut state.mode else {
+            bail!("cannot replay a trace that's being recorded");
+        };
+        let result = match channels.get_mut(&channel).and_then(|c| c.pop_front()) {
+            None => Err(anyhow!(
+                "trace diverged: the guest made a `{event:?}` call which wasn't recorded"
+            )),
+            Some((recorded, _)) if recorded != event as u8 => {
+                let recorded = match Event::ALL.get(usize::from(recorded)) {
+                    Some(recorded) => format!("{recorded:?}"),
+                    None => recorded.to_string(),
+                };
+                Err(anyhow!(
+                    "trace diverged: the guest made a `{event:?}` call where a \
+                     `{recorded}` call was recorded"
+                ))
+            }
+            Some((_, payload)) => {
+                let mut input = &payload[..];
+                T::decode(&mut input).and_then(|value| match input.is_empty() {
+                    true => Ok(value),
+                    false => Err(anyhow!("malformed `{event:?}` event in trace")),
+                })
+            }
+        };
+        if let Err(e) = &result {
+            state.error = Some(e.to_string());
+        }
+        result
+    }
+
+    /// Returns the result of `call` when recording, after recording it, or
+    /// the recorded result when replaying.
+    pub(crate) fn traced<T: Traced>(
+        &self,
+        channel: u64,
+        event: Event,
+        call: impl FnOnce() -> T,
+    ) -> Result<T> {
+        if self.replay {
+            self.next(channel, event)
+        } else {
+            let value = call();
+            self.push(channel, event, &value)?;
+            Ok(value)
+        }
+    }
+
+    /// Sets up `ctx` to record or replay this trace.
+    ///
+    /// Errors are remembered and reported by the first call that the guest
+    /// makes.
+    pub(crate) fn install(&self, ctx: &mut WasiCtx) {
+        if self.replay {
+            self.install_replay(ctx);
+        } else {
+            self.install_record(ctx);
+        }
+    }
+
+    fn install_record(&self, ctx: &mut WasiCtx) {
+        let mut preopens = Vec::new();
+        for (dir, guest_path) in ctx.preopens.iter_mut() {
+            let channel = self.new_channel();
+            dir.dir = Arc::new(RecordingDir::new(dir.dir.clone(), self.clone(), channel));
+            preopens.push(Preopen {
+                guest_path: guest_path.clone(),
+                channel,
+                perms: dir.perms,
+                file_perms: dir.file_perms,
+                open_mode: dir.open_mode,
+            });
+        }
+        let setup = Setup {
+            args: ctx.args.clone(),
+            env: ctx.env.clone(),
+            insecure_random_seed: ctx.insecure_random_seed,
+            terminals: (
+                ctx.stdin.isatty(),
+                (ctx.stdout.isatty(), ctx.stderr.isatty()),
+            ),
+            preopens,
+        };
+        let _ = self.push(SETUP, Event::Setup, &setup);
+
+        let stdin = mem::replace(&mut ctx.stdin, Box::new(ClosedInputStream));
+        ctx.stdin = Box::new(RecordingStdin {
+            inner: stdin,
+            trace: self.clone(),
+        });
+        ctx.ip_name_resolver = Arc::new(RecordingResolver {
+            inner: ctx.ip_name_resolver.clone(),
+            trace: self.clone(),
+        });
+    }
+
+    fn install_replay(&self, ctx: &mut WasiCtx) {
+        ctx.preopens.clear();
+        let setup = match self.next::<Setup>(SETUP, Event::Setup) {
+            Ok(setup) => setup,
+            Err(_) => return,
+        };
+        ctx.args = setup.args;
+        ctx.env = setup.env;
+        ctx.insecure_random_seed = setup.insecure_random_seed;
+        for preopen in setup.preopens {
+            let dir = Dir::new(
+                Arc::new(ReplayDir::new(self.clone(), preopen.channel)),
+                preopen.perms,
+                preopen.file_perms,
+                preopen.open_mode,
+                ctx.allow_blocking_current_thread,
+            );
+            ctx.preopens.push((dir, preopen.guest_path));
+        }
+
+        let (stdin, (stdout, stderr)) = setup.terminals;
+        ctx.stdin = Box::new(ReplayStdin {
+            trace: self.clone(),
+            isatty: stdin,
+        });
+        let inner = mem::replace(&mut ctx.stdout, Box::new(crate::pipe::SinkOutputStream));
+        ctx.stdout = Box::new(Terminal {
+            inner,
+            isatty: stdout,
+        });
+        let inner = mem::replace(&mut ctx.stderr, Box::new(crate::pipe::SinkOutputStream));
+        ctx.stderr = Box::new(Terminal {
+            inner,
+            isatty: stderr,
+        });
+        ctx.ip_name_resolver = Arc::new(ReplayResolver {
+            trace: self.clone(),
+            lookups: Mutex::new(None),
+        });
+    }
+}
+
+/// Returns the result of `call`, or records or replays it on the channel of
+/// the calls made on the thread of the guest if there's a `trace`.
+pub(crate) fn traced_call<T: Traced>(
+    trace: &Option<Trace>,
+    event: Event,
+    call: impl FnOnce() -> T,
+) -> Result<T> {
+    match trace {
+        Some(trace) => trace.traced(CALLS, event, call),
+        None => Ok(call()),
+    }
+}
+
+/// Converts the errors of traces to I/O errors, which trap when they're
+/// converted to `wasi:filesystem` errors.
+pub(crate) fn io_result<T>(result: Result<T>) -> io::Result<T> {
+    result.map_err(|e| io::Error::new(io::ErrorKind::Other, TraceError(e.to_string())))
+}
+
+/// An error of a trace, which traps the guest.
+#[derive(Debug)]
+pub(crate) struct TraceError(String);
+
+impl std::fmt::Display for TraceError {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        f.write_str(&self.0)
+    }
+}
+
+impl std::error::Error for TraceError {}
+
+/// The configuration of a context which is recorded when it's built.
+struct Setup {
+    args: Vec<String>,
+    env: Vec<(String, String)>,
+    insecure_random_seed: u128,
+    /// Whether stdin, stdout and stderr are terminals.
+    terminals: (bool, (bool, bool)),
+    preopens: Vec<Preopen>,
+}
+
+struct Preopen {
+    guest_path: String,
+    channel: u64,
+    perms: DirPerms,
+    file_perms: FilePerms,
+    open_mode: OpenMode,
+}
+
+impl Traced for Setup {
+    fn encode(&self, out: &mut Vec<u8>) {
+        self.args.encode(out);
+        self.env.encode(out);
+        self.insecure_random_seed.encode(out);
+        self.terminals.encode(out);
+        self.preopens.len().encode(out);
+        for preopen in &self.preopens {
+            preopen.guest_path.encode(out);
+            preopen.channel.encode(out);
+            preopen.perms.bits().encode(out);
+            preopen.file_perms.bits().encode(out);
+            preopen.open_mode.bits().encode(out);
+        }
+    }
+
+    fn decode(input: &mut &[u8]) -> Result<Self> {
+        Ok(Setup {
+            args: Traced::decode(input)?,
+            env: Traced::decode(input)?,
+            insecure_random_seed: Traced::decode(input)?,
+            terminals: Traced::decode(input)?,
+            preopens: (0..usize::decode(input)?)
+                .map(|_| {
+                    Ok(Preopen {
+                        guest_path: Traced::decode(input)?,
+                        channel: Traced::decode(input)?,
+                        perms: DirPerms::from_bits_truncate(Traced::decode(input)?),
+                        file_perms: FilePerms::from_bits_truncate(Traced::decode(input)?),
+                        open_mode: OpenMode::from_bits_truncate(Traced::decode(input)?),
+                    })
+                })
+                .collect::<Result<_>>()?,
+        })
+    }
+}
+
+/// Records what's read from the stdin of the host.
+struct RecordingStdin {
+    inner: Box<dyn StdinStream>,
+    trace: Trace,
+}
+
+impl StdinStream for RecordingStdin {
+    fn stream(&self) -> Box<dyn HostInputStream> {
+        Box::new(RecordingInputStream {
+            inner: self.inner.stream(),
+            trace: self.trace.clone(),
+        })
+    }
+
+    fn isatty(&self) -> bool {
+        self.inner.isatty()
+    }
+}
+
+struct RecordingInputStream {
+    inner: Box<dyn HostInputStream>,
+    trace: Trace,
+}
+
+#[async_trait::async_trait]
+impl HostInputStream for RecordingInputStream {
+    fn read(&mut self, size: usize) -> StreamResult<Bytes> {
+        self.trace
+            .traced(CALLS, Event::StdinRead, || self.inner.read(size))
+            .unwrap_or_else(|e| Err(StreamError::Trap(e)))
+    }
+
+    async fn cancel(&mut self) {
+        self.inner.cancel().await
+    }
+}
+
+#[async_trait::async_trait]
+impl Subscribe for RecordingInputStream {
+    async fn ready(&mut self) {
+        self.inner.ready().await
+    }
+}
+
+/// Replays what was read from stdin.
+struct ReplayStdin {
+    trace: Trace,
+    isatty: bool,
+}
+
+impl StdinStream for ReplayStdin {
+    fn stream(&self) -> Box<dyn HostInputStream> {
+        Box::new(ReplayInputStream {
+            trace: self.trace.clone(),
+        })
+    }
+
+    fn isatty(&self) -> bool {
+        self.isatty
+    }
+}
+
+struct ReplayInputStream {
+    trace: Trace,
+}
+
+#[async_trait::async_trait]
+impl HostInputStream for ReplayInputStream {
+    fn read(&mut self, _size: usize) -> StreamResult<Bytes> {
+        self.trace
+            .next(CALLS, Event::StdinRead)
+            .unwrap_or_else(|e| Err(StreamError::Trap(e)))
+    }
+}
+
+#[async_trait::async_trait]
+impl Subscribe for ReplayInputStream {
+    async fn ready(&mut self) {}
+}
+
+/// Reports whether an output stream is a terminal as recorded.
+struct Terminal {
+    inner: Box<dyn StdoutStream>,
+    isatty: bool,
+}
+
+impl StdoutStream for Terminal {
+    fn stream(&self) -> Box<dyn HostOutputStream> {
+        self.inner.stream()
+    }
+
+    fn isatty(&self) -> bool {
+        self.isatty
+    }
+}
+
+type Lookup = (String, SocketResult<Vec<IpAddr>>);
+
+/// Records the names that `inner` resolves and their addresses.
+struct RecordingResolver {
+    inner: Arc<dyn HostResolver>,
+    trace: Trace,
+}
+
+#[async_trait::async_trait]
+impl HostResolver for RecordingResolver {
+    async fn resolve(&self, name: &str) -> SocketResult<Vec<IpAddr>> {
+        let lookup: Lookup = (name.to_string(), self.inner.resolve(name).await);
+        self.trace
+            .push(RESOLVER, Event::Resolve, &lookup)
+            .map_err(SocketError::trap)?;
+        lookup.1
+    }
+}
+
+/// Resolves names to the addresses that were recorded for them.
+///
+/// As lookups happen concurrently, the first recorded lookup of the same name
+/// is used rather than the next one recorded.
+struct ReplayResolver {
+    trace: Trace,
+    lookups: Mutex<Option<Vec<Lookup>>>,
+}
+
+#[async_trait::async_trait]
+impl HostResolver for ReplayResolver {
+    async fn resolve(&self, name: &str) -> SocketResult<Vec<IpAddr>> {
+        let mut lookups = self.lookups.lock().unwrap();
+        let lookups = match &mut *lookups {
+            Some(lookups) => lookups,
+            None => {
+                let mut recorded = Vec::new();
+                let mut state = self.trace.state.lock().unwrap();
+                if let Mode::Replay { channels, .. } = &mut state.mode {
+                    for (_, payload) in channels.remove(&RESOLVER).unwrap_or_default() {
+                        let lookup =
+                            Lookup::decode(&mut &payload[..]).map_err(SocketError::trap)?;
+                        recorded.push(lookup);
+                    }
+                }
+                lookups.insert(recorded)
+            }
+        };
+        match lookups.iter().position(|(n, _)| n == name) {
+            Some(index) => lookups.remove(index).1,
+            None => Err(SocketError::trap(anyhow!(
+                "trace diverged: the guest looked up `{name}` which wasn't recorded"
+            ))),
+        }
+    }
+}
+
+#[cfg(test)]
+mod test {
+    use super::*;
+    use crate::bindings::cli::environment::Host as _;
+    use crate::bindings::cli::stdin::Host as _;
+    use crate::bindings::clocks::monotonic_clock::Host as _;
+    use crate::bindings::filesystem::preopens::Host as _;
+    use crate::bindings::filesystem::types::{
+        DescriptorFlags, HostDescriptor, OpenFlags, PathFlags,
+    };
+    use crate::bindings::io::streams::HostInputStream as _;
+    use crate::bindings::random::random::Host as _;
+    use crate::{pipe::MemoryInputPipe, MemoryDir, WasiCtxBuilder, WasiImpl, WasiView};
+    use wasmtime::component::ResourceTable;
+
+    struct Host {
+        ctx: WasiCtx,
+        table: ResourceTable,
+    }
+
+    impl WasiView for Host {
+        fn ctx(&mut self) -> &mut WasiCtx {
+            &mut self.ctx
+        }
+        fn table(&mut self) -> &mut ResourceTable {
+            &mut self.table
+        }
+    }
+
+    /// Makes some calls whose results are traced, returning those results.
+    async fn run(builder: &mut WasiCtxBuilder) -> Result<Vec<Vec<u8>>> {
+        let mut host = Host {
+            ctx: builder.build(),
+            table: ResourceTable::new(),
+        };
+        let mut wasi = WasiImpl(&mut host);
+        let mut results = Vec::new();
+
+        results.push(wasi.get_arguments()?.concat().into_bytes());
+        results.push(wasi.now()?.to_le_bytes().to_vec());
+        results.push(wasi.get_random_bytes(16)?);
+
+        let stdin = wasi.get_stdin()?;
+        results.push(wasi.blocking_read(stdin, 100).await?);
+
+        let (dir, _) = wasi.get_directories()?.pop().unwrap();
+        let file = wasi
+            .open_at(
+                dir,
+                PathFlags::empty(),
+                "file.txt".to_string(),
+                OpenFlags::empty(),
+                DescriptorFlags::READ,
+            )
+            .await?;
+        results.push(HostDescriptor::read(&mut wasi, file, 100, 0).await?.0);
+        Ok(results)
+    }
+
+    #[test]
+    fn record_and_replay() {
+        crate::runtime::in_tokio(async {
+            let output = Arc::new(Mutex::new(Vec::new()));
+            let trace = Trace::record(SharedBuffer(output.clone())).unwrap();
+
+            let dir = MemoryDir::new();
+            dir.write_file("file.txt", b"contents").unwrap();
+            let mut builder = WasiCtxBuilder::new();
+            builder
+                .arg("recorded")
+                .stdin(MemoryInputPipe::new("input"))
+                .preopened_virtual_dir(dir, "/", DirPerms::all(), FilePerms::all())
+                .trace(trace.clone());
+            let recorded = run(&mut builder).await.unwrap();
+            assert_eq!(recorded[0], b"recorded");
+            assert_eq!(recorded[3], b"input");
+            assert_eq!(recorded[4], b"contents");
+            trace.flush().unwrap();
+
+            // Without any of the original configuration, the same results come
+            // back.
+            let bytes = output.lock().unwrap().clone();
+            let mut builder = WasiCtxBuilder::new();
+            builder.trace(Trace::replay(&bytes[..]).unwrap());
+            assert_eq!(run(&mut builder).await.unwrap(), recorded);
+
+            // Calls which weren't recorded trap.
+            let mut builder = WasiCtxBuilder::new();
+            builder.trace(Trace::replay(&bytes[..]).unwrap());
+            let mut host = Host {
+                ctx: builder.build(),
+                table: ResourceTable::new(),
+            };
+            let err = WasiImpl(&mut host).get_random_u64().unwrap_err();
+            assert!(err.to_string().contains("trace diverged"), "{err}");
+        })
+    }
+
+    struct SharedBuffer(Arc<Mutex<Vec<u8>>>);
+
+    impl Write for SharedBuffer {
+        fn write(&mut self, buf: &[u8]) -> io::Result<usize> {
+            self.0.lock().unwrap().write(buf)
+        }
+        fn flush(&mut self) -> io::Result<()> {
+            Ok(())
+        }
+    }
+}
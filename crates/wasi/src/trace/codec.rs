@@ -0,0 +1,420 @@
+//! The encoding of the values stored in a trace.
+
+use crate::bindings::clocks::wall_clock::Datetime;
+use crate::bindings::filesystem::types::{DescriptorType, DirectoryEntry, ErrorCode};
+use crate::bindings::sockets::network::ErrorCode as SocketErrorCode;
+use crate::filesystem::Metadata;
+use crate::{SocketError, StreamError};
+use anyhow::{anyhow, bail, Result};
+use bytes::Bytes;
+use std::io;
+use std::net::{IpAddr, Ipv4Addr, Ipv6Addr};
+use std::path::PathBuf;
+use std::time::{Duration, SystemTime};
+
+/// A value which can be stored in a trace.
+pub(crate) trait Traced: Sized {
+    fn encode(&self, out: &mut Vec<u8>);
+    fn decode(input: &mut &[u8]) -> Result<Self>;
+}
+
+fn take<'a>(input: &mut &'a [u8], len: usize) -> Result<&'a [u8]> {
+    if input.len() < len {
+        bail!("unexpected end of trace event");
+    }
+    let (head, rest) = input.split_at(len);
+    *input = rest;
+    Ok(head)
+}
+
+macro_rules! traced_ints {
+    ($($ty:ty)*) => {$(
+        impl Traced for $ty {
+            fn encode(&self, out: &mut Vec<u8>) {
+                out.extend_from_slice(&self.to_le_bytes());
+            }
+            fn decode(input: &mut &[u8]) -> Result<Self> {
+                let bytes = take(input, std::mem::size_of::<$ty>())?;
+                Ok(<$ty>::from_le_bytes(bytes.try_into().unwrap()))
+            }
+        }
+    )*};
+}
+
+traced_ints!(u8 u16 u32 u64 u128);
+
+impl Traced for usize {
+    fn encode(&self, out: &mut Vec<u8>) {
+        (*self as u64).encode(out)
+    }
+    fn decode(input: &mut &[u8]) -> Result<Self> {
+        Ok(usize::try_from(u64::decode(input)?)?)
+    }
+}
+
+impl Traced for bool {
+    fn encode(&self, out: &mut Vec<u8>) {
+        u8::from(*self).encode(out)
+    }
+    fn decode(input: &mut &[u8]) -> Result<Self> {
+        match u8::decode(input)? {
+            0 => Ok(false),
+            1 => Ok(true),
+            n => bail!("invalid boolean {n} in trace"),
+        }
+    }
+}
+
+impl Traced for () {
+    fn encode(&self, _out: &mut Vec<u8>) {}
+    fn decode(_input: &mut &[u8]) -> Result<Self> {
+        Ok(())
+    }
+}
+
+impl Traced for Vec<u8> {
+    fn encode(&self, out: &mut Vec<u8>) {
+        self.len().encode(out);
+        out.extend_from_slice(self);
+    }
+    fn decode(input: &mut &[u8]) -> Result<Self> {
+        let len = usize::decode(input)?;
+        Ok(take(input, len)?.to_vec())
+    }
+}
+
+impl Traced for Bytes {
+    fn encode(&self, out: &mut Vec<u8>) {
+        self.len().encode(out);
+        out.extend_from_slice(self);
+    }
+    fn decode(input: &mut &[u8]) -> Result<Self> {
+        Ok(Vec::decode(input)?.into())
+    }
+}
+
+impl Traced for String {
+    fn encode(&self, out: &mut Vec<u8>) {
+        self.len().encode(out);
+        out.extend_from_slice(self.as_bytes());
+    }
+    fn decode(input: &mut &[u8]) -> Result<Self> {
+        Ok(String::from_utf8(Vec::decode(input)?)?)
+    }
+}
+
+impl Traced for PathBuf {
+    fn encode(&self, out: &mut Vec<u8>) {
+        self.to_string_lossy().into_owned().encode(out)
+    }
+    fn decode(input: &mut &[u8]) -> Result<Self> {
+        Ok(PathBuf::from(String::decode(input)?))
+    }
+}
+
+/// Lists other than bytes are stored element by element.
+macro_rules! traced_vecs {
+    ($($ty:ty),*) => {$(
+        impl Traced for Vec<$ty> {
+            fn encode(&self, out: &mut Vec<u8>) {
+                self.len().encode(out);
+                for item in self {
+                    item.encode(out);
+                }
+            }
+            fn decode(input: &mut &[u8]) -> Result<Self> {
+                let len = usize::decode(input)?;
+                (0..len).map(|_| <$ty>::decode(input)).collect()
+            }
+        }
+    )*};
+}
+
+traced_vecs!(
+    u32,
+    String,
+    (String, String),
+    IpAddr,
+    Result<DirectoryEntry, io::Error>
+);
+
+impl<T: Traced> Traced for Option<T> {
+    fn encode(&self, out: &mut Vec<u8>) {
+        match self {
+            None => 0u8.encode(out),
+            Some(value) => {
+                1u8.encode(out);
+                value.encode(out);
+            }
+        }
+    }
+    fn decode(input: &mut &[u8]) -> Result<Self> {
+        match u8::decode(input)? {
+            0 => Ok(None),
+            1 => Ok(Some(T::decode(input)?)),
+            n => bail!("invalid option tag {n} in trace"),
+        }
+    }
+}
+
+impl<T: Traced, E: Traced> Traced for Result<T, E> {
+    fn encode(&self, out: &mut Vec<u8>) {
+        match self {
+            Ok(value) => {
+                0u8.encode(out);
+                value.encode(out);
+            }
+            Err(error) => {
+                1u8.encode(out);
+                error.encode(out);
+            }
+        }
+    }
+    fn decode(input: &mut &[u8]) -> Result<Self> {
+        match u8::decode(input)? {
+            0 => Ok(Ok(T::decode(input)?)),
+            1 => Ok(Err(E::decode(input)?)),
+            n => bail!("invalid result tag {n} in trace"),
+        }
+    }
+}
+
+impl<A: Traced, B: Traced> Traced for (A, B) {
+    fn encode(&self, out: &mut Vec<u8>) {
+        self.0.encode(out);
+        self.1.encode(out);
+    }
+    fn decode(input: &mut &[u8]) -> Result<Self> {
+        Ok((A::decode(input)?, B::decode(input)?))
+    }
+}
+
+impl Traced for Datetime {
+    fn encode(&self, out: &mut Vec<u8>) {
+        self.seconds.encode(out);
+        self.nanoseconds.encode(out);
+    }
+    fn decode(input: &mut &[u8]) -> Result<Self> {
+        Ok(Datetime {
+            seconds: u64::decode(input)?,
+            nanoseconds: u32::decode(input)?,
+        })
+    }
+}
+
+/// Times are stored relative to the Unix epoch, with a sign as they may be
+/// before it.
+impl Traced for SystemTime {
+    fn encode(&self, out: &mut Vec<u8>) {
+        let (before, duration) = match self.duration_since(SystemTime::UNIX_EPOCH) {
+            Ok(duration) => (false, duration),
+            Err(e) => (true, e.duration()),
+        };
+        before.encode(out);
+        duration.as_secs().encode(out);
+        duration.subsec_nanos().encode(out);
+    }
+    fn decode(input: &mut &[u8]) -> Result<Self> {
+        let before = bool::decode(input)?;
+        let duration = Duration::new(u64::decode(input)?, u32::decode(input)?);
+        let time = if before {
+            SystemTime::UNIX_EPOCH.checked_sub(duration)
+        } else {
+            SystemTime::UNIX_EPOCH.checked_add(duration)
+        };
+        time.ok_or_else(|| anyhow!("time in trace is out of range"))
+    }
+}
+
+impl Traced for IpAddr {
+    fn encode(&self, out: &mut Vec<u8>) {
+        match self {
+            IpAddr::V4(addr) => {
+                4u8.encode(out);
+                u32::from(*addr).encode(out);
+            }
+            IpAddr::V6(addr) => {
+                6u8.encode(out);
+                u128::from(*addr).encode(out);
+            }
+        }
+    }
+    fn decode(input: &mut &[u8]) -> Result<Self> {
+        match u8::decode(input)? {
+            4 => Ok(Ipv4Addr::from(u32::decode(input)?).into()),
+            6 => Ok(Ipv6Addr::from(u128::decode(input)?).into()),
+            n => bail!("invalid address family {n} in trace"),
+        }
+    }
+}
+
+/// Defines how an `enum` generated by `bindgen!` is stored, as the index of
+/// its case in the list given.
+macro_rules! traced_enum {
+    ($ty:ty { $($case:ident)* }) => {
+        impl Traced for $ty {
+            fn encode(&self, out: &mut Vec<u8>) {
+                const CASES: &[$ty] = &[$(<$ty>::$case),*];
+                let index = CASES.iter().position(|case| case == self).unwrap();
+                u8::try_from(index).unwrap().encode(out)
+            }
+            fn decode(input: &mut &[u8]) -> Result<Self> {
+                const CASES: &[$ty] = &[$(<$ty>::$case),*];
+                let index = u8::decode(input)?;
+                CASES
+                    .get(usize::from(index))
+                    .copied()
+                    .ok_or_else(|| anyhow!("invalid {} {index} in trace", stringify!($ty)))
+            }
+        }
+    };
+}
+
+traced_enum!(ErrorCode {
+    Access WouldBlock Already BadDescriptor Busy Deadlock Quota Exist FileTooLarge
+    IllegalByteSequence InProgress Interrupted Invalid Io IsDirectory Loop TooManyLinks
+    MessageSize NameTooLong NoDevice NoEntry NoLock InsufficientMemory InsufficientSpace
+    NotDirectory NotEmpty NotRecoverable Unsupported NoTty NoSuchDevice Overflow NotPermitted
+    Pipe ReadOnly InvalidSeek TextFileBusy CrossDevice
+});
+
+traced_enum!(SocketErrorCode {
+    Unknown AccessDenied NotSupported InvalidArgument OutOfMemory Timeout ConcurrencyConflict
+    NotInProgress WouldBlock InvalidState NewSocketLimit AddressNotBindable AddressInUse
+    RemoteUnreachable ConnectionRefused ConnectionReset ConnectionAborted DatagramTooLarge
+    NameUnresolvable TemporaryResolverFailure PermanentResolverFailure
+});
+
+traced_enum!(DescriptorType {
+    Unknown BlockDevice CharacterDevice Directory Fifo SymbolicLink RegularFile Socket
+});
+
+/// Errors of filesystems are stored as the error code that the guest sees.
+impl Traced for io::Error {
+    fn encode(&self, out: &mut Vec<u8>) {
+        ErrorCode::from(self).encode(out)
+    }
+    fn decode(input: &mut &[u8]) -> Result<Self> {
+        Ok(ErrorCode::decode(input)?.into())
+    }
+}
+
+/// Errors which would trap are stored as their message, and trap again when
+/// replayed.
+impl Traced for SocketError {
+    fn encode(&self, out: &mut Vec<u8>) {
+        match self.downcast_ref() {
+            Some(code) => Ok::<SocketErrorCode, String>(*code).encode(out),
+            None => Err::<SocketErrorCode, String>(format!("{self:?}")).encode(out),
+        }
+    }
+    fn decode(input: &mut &[u8]) -> Result<Self> {
+        Ok(match Result::<SocketErrorCode, String>::decode(input)? {
+            Ok(code) => code.into(),
+            Err(message) => SocketError::trap(anyhow!(message)),
+        })
+    }
+}
+
+impl Traced for StreamError {
+    fn encode(&self, out: &mut Vec<u8>) {
+        match self {
+            StreamError::Closed => 0u8.encode(out),
+            StreamError::LastOperationFailed(e) => {
+                1u8.encode(out);
+                format!("{e:?}").encode(out);
+            }
+            StreamError::Trap(e) => {
+                2u8.encode(out);
+                format!("{e:?}").encode(out);
+            }
+        }
+    }
+    fn decode(input: &mut &[u8]) -> Result<Self> {
+        match u8::decode(input)? {
+            0 => Ok(StreamError::Closed),
+            1 => Ok(StreamError::LastOperationFailed(anyhow!(String::decode(
+                input
+            )?))),
+            2 => Ok(StreamError::Trap(anyhow!(String::decode(input)?))),
+            n => bail!("invalid stream error {n} in trace"),
+        }
+    }
+}
+
+impl Traced for Metadata {
+    fn encode(&self, out: &mut Vec<u8>) {
+        self.file_type.encode(out);
+        self.len.encode(out);
+        self.nlink.encode(out);
+        self.dev.encode(out);
+        self.ino.encode(out);
+        self.accessed.encode(out);
+        self.modified.encode(out);
+        self.created.encode(out);
+    }
+    fn decode(input: &mut &[u8]) -> Result<Self> {
+        Ok(Metadata {
+            file_type: Traced::decode(input)?,
+            len: Traced::decode(input)?,
+            nlink: Traced::decode(input)?,
+            dev: Traced::decode(input)?,
+            ino: Traced::decode(input)?,
+            accessed: Traced::decode(input)?,
+            modified: Traced::decode(input)?,
+            created: Traced::decode(input)?,
+        })
+    }
+}
+
+impl Traced for DirectoryEntry {
+    fn encode(&self, out: &mut Vec<u8>) {
+        self.type_.encode(out);
+        self.name.encode(out);
+    }
+    fn decode(input: &mut &[u8]) -> Result<Self> {
+        Ok(DirectoryEntry {
+            type_: Traced::decode(input)?,
+            name: Traced::decode(input)?,
+        })
+    }
+}
+
+#[cfg(test)]
+mod test {
+    use super::*;
+
+    fn round_trip<T: Traced>(value: &T) -> T {
+        let mut bytes = Vec::new();
+        value.encode(&mut bytes);
+        let mut input = &bytes[..];
+        let value = T::decode(&mut input).unwrap();
+        assert!(input.is_empty());
+        value
+    }
+
+    #[test]
+    fn values() {
+        assert_eq!(round_trip(&u128::MAX), u128::MAX);
+        assert_eq!(
+            round_trip(&Ok::<_, Option<String>>(vec![1u8, 2, 3])),
+            Ok(vec![1, 2, 3])
+        );
+        assert_eq!(
+            round_trip(&Err::<u8, _>(Some("error".to_string()))),
+            Err(Some("error".to_string()))
+        );
+        assert_eq!(
+            round_trip(&ErrorCode::CrossDevice) as u8,
+            ErrorCode::CrossDevice as u8
+        );
+        let addresses: Vec<IpAddr> = vec![Ipv4Addr::LOCALHOST.into(), Ipv6Addr::LOCALHOST.into()];
+        assert_eq!(round_trip(&addresses), addresses);
+
+        let before_epoch = SystemTime::UNIX_EPOCH - Duration::new(5, 1);
+        assert_eq!(round_trip(&before_epoch), before_epoch);
+
+        let error = round_trip(&io::Error::from(io::ErrorKind::NotFound));
+        assert_eq!(ErrorCode::from(&error) as u8, ErrorCode::NoEntry as u8);
+    }
+}
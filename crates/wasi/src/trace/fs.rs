@@ -0,0 +1,381 @@
+//! Filesystems which record the results of the calls made on another
+//! filesystem, or replay them without one.
+//!
+//! Each directory and file that's opened gets its own channel in the trace,
+//! since calls on them are made on blocking threads and may race with the
+//! calls on other files.
+
+use super::{io_result, Event, Trace, Traced};
+use crate::bindings::filesystem::types::{self, ErrorCode};
+use crate::filesystem::{Metadata, OpenOptions, Opened, WasiDir, WasiFile};
+use crate::SystemTimeSpec;
+use std::any::Any;
+use std::io;
+use std::path::PathBuf;
+use std::sync::Arc;
+
+/// Records the results of the calls made on `inner`.
+pub(crate) struct RecordingDir {
+    inner: Arc<dyn WasiDir>,
+    trace: Trace,
+    channel: u64,
+}
+
+impl RecordingDir {
+    pub(crate) fn new(inner: Arc<dyn WasiDir>, trace: Trace, channel: u64) -> Self {
+        RecordingDir {
+            inner,
+            trace,
+            channel,
+        }
+    }
+
+    fn record<T>(&self, event: Event, call: impl FnOnce() -> io::Result<T>) -> io::Result<T>
+    where
+        io::Result<T>: Traced,
+    {
+        io_result(self.trace.traced(self.channel, event, call))?
+    }
+}
+
+/// Returns the directory that `dir` records calls on, if it's a
+/// [`RecordingDir`], so that it can be passed to the `inner` directory of
+/// another one.
+fn unwrap_recording(dir: &dyn WasiDir) -> &dyn WasiDir {
+    match dir.as_any().downcast_ref::<RecordingDir>() {
+        Some(dir) => &*dir.inner,
+        None => dir,
+    }
+}
+
+impl WasiDir for RecordingDir {
+    fn open_at(&self, path: &str, options: &OpenOptions) -> io::Result<Opened> {
+        let result = self.inner.open_at(path, options).map(|opened| {
+            let channel = self.trace.new_channel();
+            let trace = self.trace.clone();
+            match opened {
+                Opened::Dir(inner) => (
+                    Opened::Dir(Arc::new(RecordingDir::new(inner, trace, channel))),
+                    (true, channel),
+                ),
+                Opened::File(inner) => (
+                    Opened::File(Arc::new(RecordingFile {
+                        inner,
+                        trace,
+                        channel,
+                    })),
+                    (false, channel),
+                ),
+            }
+        });
+        let recorded = match &result {
+            Ok((_, opened)) => Ok(*opened),
+            Err(e) => Err(ErrorCode::from(e)),
+        };
+        io_result(self.trace.push(self.channel, Event::Open, &recorded))?;
+        result.map(|(opened, _)| opened)
+    }
+
+    fn metadata(&self) -> io::Result<Metadata> {
+        self.record(Event::Metadata, || self.inner.metadata())
+    }
+
+    fn metadata_at(&self, path: &str, follow_symlinks: bool) -> io::Result<Metadata> {
+        self.record(Event::MetadataAt, || {
+            self.inner.metadata_at(path, follow_symlinks)
+        })
+    }
+
+    fn read_dir(&self) -> io::Result<Vec<io::Result<types::DirectoryEntry>>> {
+        self.record(Event::ReadDir, || self.inner.read_dir())
+    }
+
+    fn create_dir_at(&self, path: &str) -> io::Result<()> {
+        self.record(Event::CreateDir, || self.inner.create_dir_at(path))
+    }
+
+    fn remove_dir_at(&self, path: &str) -> io::Result<()> {
+        self.record(Event::RemoveDir, || self.inner.remove_dir_at(path))
+    }
+
+    fn unlink_file_at(&self, path: &str) -> io::Result<()> {
+        self.record(Event::UnlinkFile, || self.inner.unlink_file_at(path))
+    }
+
+    fn rename_at(&self, old_path: &str, new_dir: &dyn WasiDir, new_path: &str) -> io::Result<()> {
+        self.record(Event::Rename, || {
+            let new_dir = unwrap_recording(new_dir);
+            self.inner.rename_at(old_path, new_dir, new_path)
+        })
+    }
+
+    fn link_at(&self, old_path: &str, new_dir: &dyn WasiDir, new_path: &str) -> io::Result<()> {
+        self.record(Event::Link, || {
+            let new_dir = unwrap_recording(new_dir);
+            self.inner.link_at(old_path, new_dir, new_path)
+        })
+    }
+
+    fn symlink_at(&self, target: &str, path: &str) -> io::Result<()> {
+        self.record(Event::Symlink, || self.inner.symlink_at(target, path))
+    }
+
+    fn read_link_at(&self, path: &str) -> io::Result<PathBuf> {
+        self.record(Event::ReadLink, || self.inner.read_link_at(path))
+    }
+
+    fn set_times(
+        &self,
+        atime: Option<SystemTimeSpec>,
+        mtime: Option<SystemTimeSpec>,
+    ) -> io::Result<()> {
+        self.record(Event::SetTimes, || self.inner.set_times(atime, mtime))
+    }
+
+    fn set_times_at(
+        &self,
+        path: &str,
+        atime: Option<SystemTimeSpec>,
+        mtime: Option<SystemTimeSpec>,
+        follow_symlinks: bool,
+    ) -> io::Result<()> {
+        self.record(Event::SetTimesAt, || {
+            self.inner.set_times_at(path, atime, mtime, follow_symlinks)
+        })
+    }
+
+    fn sync(&self, data_only: bool) -> io::Result<()> {
+        self.record(Event::Sync, || self.inner.sync(data_only))
+    }
+
+    fn as_any(&self) -> &dyn Any {
+        self
+    }
+}
+
+/// Records the results of the calls made on `inner`.
+struct RecordingFile {
+    inner: Arc<dyn WasiFile>,
+    trace: Trace,
+    channel: u64,
+}
+
+impl RecordingFile {
+    fn record<T>(&self, event: Event, call: impl FnOnce() -> io::Result<T>) -> io::Result<T>
+    where
+        io::Result<T>: Traced,
+    {
+        io_result(self.trace.traced(self.channel, event, call))?
+    }
+}
+
+impl WasiFile for RecordingFile {
+    fn read_at(&self, buf: &mut [u8], offset: u64) -> io::Result<usize> {
+        let result = self.inner.read_at(buf, offset);
+        let recorded = match &result {
+            Ok(n) => Ok(buf[..*n].to_vec()),
+            Err(e) => Err(ErrorCode::from(e)),
+        };
+        io_result(self.trace.push(self.channel, Event::Read, &recorded))?;
+        result
+    }
+
+    fn write_at(&self, buf: &[u8], offset: u64) -> io::Result<usize> {
+        self.record(Event::Write, || self.inner.write_at(buf, offset))
+    }
+
+    fn append(&self, buf: &[u8]) -> io::Result<usize> {
+        self.record(Event::Append, || self.inner.append(buf))
+    }
+
+    fn metadata(&self) -> io::Result<Metadata> {
+        self.record(Event::Metadata, || self.inner.metadata())
+    }
+
+    fn set_len(&self, size: u64) -> io::Result<()> {
+        self.record(Event::SetLen, || self.inner.set_len(size))
+    }
+
+    fn set_times(
+        &self,
+        atime: Option<SystemTimeSpec>,
+        mtime: Option<SystemTimeSpec>,
+    ) -> io::Result<()> {
+        self.record(Event::SetTimes, || self.inner.set_times(atime, mtime))
+    }
+
+    fn sync(&self, data_only: bool) -> io::Result<()> {
+        self.record(Event::Sync, || self.inner.sync(data_only))
+    }
+
+    fn advise(&self, offset: u64, len: u64, advice: types::Advice) -> io::Result<()> {
+        self.record(Event::Advise, || self.inner.advise(offset, len, advice))
+    }
+}
+
+/// Replays the results that a [`RecordingDir`] recorded, without a
+/// filesystem.
+pub(crate) struct ReplayDir {
+    trace: Trace,
+    channel: u64,
+}
+
+impl ReplayDir {
+    pub(crate) fn new(trace: Trace, channel: u64) -> Self {
+        ReplayDir { trace, channel }
+    }
+
+    fn replay<T>(&self, event: Event) -> io::Result<T>
+    where
+        io::Result<T>: Traced,
+    {
+        io_result(self.trace.next(self.channel, event))?
+    }
+}
+
+impl WasiDir for ReplayDir {
+    fn open_at(&self, _path: &str, _options: &OpenOptions) -> io::Result<Opened> {
+        let recorded: Result<(bool, u64), ErrorCode> =
+            io_result(self.trace.next(self.channel, Event::Open))?;
+        let trace = self.trace.clone();
+        match recorded? {
+            (true, channel) => Ok(Opened::Dir(Arc::new(ReplayDir::new(trace, channel)))),
+            (false, channel) => Ok(Opened::File(Arc::new(ReplayFile { trace, channel }))),
+        }
+    }
+
+    fn metadata(&self) -> io::Result<Metadata> {
+        self.replay(Event::Metadata)
+    }
+
+    fn metadata_at(&self, _path: &str, _follow_symlinks: bool) -> io::Result<Metadata> {
+        self.replay(Event::MetadataAt)
+    }
+
+    fn read_dir(&self) -> io::Result<Vec<io::Result<types::DirectoryEntry>>> {
+        self.replay(Event::ReadDir)
+    }
+
+    fn create_dir_at(&self, _path: &str) -> io::Result<()> {
+        self.replay(Event::CreateDir)
+    }
+
+    fn remove_dir_at(&self, _path: &str) -> io::Result<()> {
+        self.replay(Event::RemoveDir)
+    }
+
+    fn unlink_file_at(&self, _path: &str) -> io::Result<()> {
+        self.replay(Event::UnlinkFile)
+    }
+
+    fn rename_at(
+        &self,
+        _old_path: &str,
+        _new_dir: &dyn WasiDir,
+        _new_path: &str,
+    ) -> io::Result<()> {
+        self.replay(Event::Rename)
+    }
+
+    fn link_at(&self, _old_path: &str, _new_dir: &dyn WasiDir, _new_path: &str) -> io::Result<()> {
+        self.replay(Event::Link)
+    }
+
+    fn symlink_at(&self, _target: &str, _path: &str) -> io::Result<()> {
+        self.replay(Event::Symlink)
+    }
+
+    fn read_link_at(&self, _path: &str) -> io::Result<PathBuf> {
+        self.replay(Event::ReadLink)
+    }
+
+    fn set_times(
+        &self,
+        _atime: Option<SystemTimeSpec>,
+        _mtime: Option<SystemTimeSpec>,
+    ) -> io::Result<()> {
+        self.replay(Event::SetTimes)
+    }
+
+    fn set_times_at(
+        &self,
+        _path: &str,
+        _atime: Option<SystemTimeSpec>,
+        _mtime: Option<SystemTimeSpec>,
+        _follow_symlinks: bool,
+    ) -> io::Result<()> {
+        self.replay(Event::SetTimesAt)
+    }
+
+    fn sync(&self, _data_only: bool) -> io::Result<()> {
+        self.replay(Event::Sync)
+    }
+
+    fn as_any(&self) -> &dyn Any {
+        self
+    }
+}
+
+/// Replays the results that a [`RecordingFile`] recorded.
+struct ReplayFile {
+    trace: Trace,
+    channel: u64,
+}
+
+impl ReplayFile {
+    fn replay<T>(&self, event: Event) -> io::Result<T>
+    where
+        io::Result<T>: Traced,
+    {
+        io_result(self.trace.next(self.channel, event))?
+    }
+}
+
+impl WasiFile for ReplayFile {
+    fn read_at(&self, buf: &mut [u8], _offset: u64) -> io::Result<usize> {
+        let recorded: Result<Vec<u8>, ErrorCode> =
+            io_result(self.trace.next(self.channel, Event::Read))?;
+        let data = recorded?;
+        if data.len() > buf.len() {
+            return io_result(Err(anyhow::anyhow!(
+                "trace diverged: {} bytes were read from a file but only {} were requested",
+                data.len(),
+                buf.len()
+            )));
+        }
+        buf[..data.len()].copy_from_slice(&data);
+        Ok(data.len())
+    }
+
+    fn write_at(&self, _buf: &[u8], _offset: u64) -> io::Result<usize> {
+        self.replay(Event::Write)
+    }
+
+    fn append(&self, _buf: &[u8]) -> io::Result<usize> {
+        self.replay(Event::Append)
+    }
+
+    fn metadata(&self) -> io::Result<Metadata> {
+        self.replay(Event::Metadata)
+    }
+
+    fn set_len(&self, _size: u64) -> io::Result<()> {
+        self.replay(Event::SetLen)
+    }
+
+    fn set_times(
+        &self,
+        _atime: Option<SystemTimeSpec>,
+        _mtime: Option<SystemTimeSpec>,
+    ) -> io::Result<()> {
+        self.replay(Event::SetTimes)
+    }
+
+    fn sync(&self, _data_only: bool) -> io::Result<()> {
+        self.replay(Event::Sync)
+    }
+
+    fn advise(&self, _offset: u64, _len: u64, _advice: types::Advice) -> io::Result<()> {
+        self.replay(Event::Advise)
+    }
+}
@@ -8,7 +8,7 @@ use std::io;
 use std::net::SocketAddr;
 use std::sync::Arc;
 
-use super::network::{SocketAddrCheck, SocketAddressFamily};
+use super::network::{SocketAddrCheck, SocketAddressFamily, VirtualNetwork, VirtualUdpSocket};
 
 /// The state of a UDP socket.
 ///
@@ -29,6 +29,16 @@ pub(crate) enum UdpState {
     Connected,
 }
 
+/// The socket underlying a [`UdpSocket`] and its datagram streams.
+#[derive(Clone)]
+pub(crate) enum UdpInner {
+    /// A socket of the host's network stack.
+    Host(Arc<tokio::net::UdpSocket>),
+
+    /// A socket on a [`VirtualNetwork`].
+    Virtual(Arc<VirtualUdpSocket>),
+}
+
 /// A host UDP socket, plus associated bookkeeping.
 ///
 /// The inner state is wrapped in an Arc because the same underlying socket is
@@ -36,7 +46,7 @@ pub(crate) enum UdpState {
 pub struct UdpSocket {
     /// The part of a `UdpSocket` which is reference-counted so that we
     /// can pass it to async tasks.
-    pub(crate) inner: Arc<tokio::net::UdpSocket>,
+    pub(crate) inner: UdpInner,
 
     /// The current state in the bind/connect progression.
     pub(crate) udp_state: UdpState,
@@ -73,33 +83,50 @@ impl UdpSocket {
         let socket = Self::setup_tokio_udp_socket(fd)?;
 
         Ok(UdpSocket {
-            inner: Arc::new(socket),
+            inner: UdpInner::Host(Arc::new(socket)),
             udp_state: UdpState::Default,
             family: socket_address_family,
             socket_addr_check: None,
         })
     }
 
+    /// Create a new socket in the given family on a [`VirtualNetwork`].
+    pub fn new_virtual(network: &VirtualNetwork, family: AddressFamily) -> Self {
+        UdpSocket {
+            inner: UdpInner::Virtual(Arc::new(VirtualUdpSocket::new(network))),
+            udp_state: UdpState::Default,
+            family: match family {
+                AddressFamily::Ipv4 => SocketAddressFamily::Ipv4,
+                AddressFamily::Ipv6 => SocketAddressFamily::Ipv6,
+            },
+            socket_addr_check: None,
+        }
+    }
+
     fn setup_tokio_udp_socket(fd: rustix::fd::OwnedFd) -> io::Result<tokio::net::UdpSocket> {
         let std_socket =
             unsafe { std::net::UdpSocket::from_raw_socketlike(fd.into_raw_socketlike()) };
         with_ambient_tokio_runtime(|| tokio::net::UdpSocket::try_from(std_socket))
     }
 
-    pub fn udp_socket(&self) -> &tokio::net::UdpSocket {
-        &self.inner
+    /// The host socket, or `None` for sockets on a [`VirtualNetwork`].
+    pub fn udp_socket(&self) -> Option<&tokio::net::UdpSocket> {
+        match &self.inner {
+            UdpInner::Host(socket) => Some(socket),
+            UdpInner::Virtual(_) => None,
+        }
     }
 }
 
 pub struct IncomingDatagramStream {
-    pub(crate) inner: Arc<tokio::net::UdpSocket>,
+    pub(crate) inner: UdpInner,
 
     /// If this has a value, the stream is "connected".
     pub(crate) remote_address: Option<SocketAddr>,
 }
 
 pub struct OutgoingDatagramStream {
-    pub(crate) inner: Arc<tokio::net::UdpSocket>,
+    pub(crate) inner: UdpInner,
 
     /// If this has a value, the stream is "connected".
     pub(crate) remote_address: Option<SocketAddr>,
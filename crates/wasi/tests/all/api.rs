@@ -8,7 +8,8 @@ use wasmtime_wasi::bindings::Command;
 use wasmtime_wasi::{
     add_to_linker_async,
     bindings::{clocks::wall_clock, filesystem::types as filesystem},
-    DirPerms, FilePerms, HostMonotonicClock, HostWallClock, WasiCtx, WasiCtxBuilder, WasiView,
+    DirPerms, FilePerms, HostMonotonicClock, HostWallClock, HostsResolver, WasiCtx, WasiCtxBuilder,
+    WasiView,
 };
 
 struct CommandCtx {
@@ -110,6 +111,110 @@ async fn api_read_only() -> Result<()> {
         .map_err(|()| anyhow::anyhow!("command returned with failing exit status"))
 }
 
+/// Runs the `preview2_ip_name_lookup` program with names resolved from a
+/// fixed table instead of by the system, so without any network access.
+#[test_log::test(tokio::test(flavor = "multi_thread"))]
+async fn api_ip_name_lookup_hosts() -> Result<()> {
+    let resolver = HostsResolver::parse(
+        "127.0.0.1 localhost
+         ::1 localhost
+         192.0.2.1 example.com",
+    )?;
+
+    let table = ResourceTable::new();
+    let wasi = WasiCtxBuilder::new()
+        .inherit_network()
+        .allow_ip_name_lookup(true)
+        .ip_name_resolver(resolver)
+        .build();
+
+    let (mut store, command) = instantiate(
+        PREVIEW2_IP_NAME_LOOKUP_COMPONENT,
+        CommandCtx { table, wasi },
+    )
+    .await?;
+
+    command
+        .wasi_cli_run()
+        .call_run(&mut store)
+        .await?
+        .map_err(|()| anyhow::anyhow!("command returned with failing exit status"))
+}
+
+/// Runs the `api_read_only` program against a zip archive with the same
+/// contents as the directory used by `api_read_only` above.
+#[cfg(feature = "archive")]
+#[test_log::test(tokio::test(flavor = "multi_thread"))]
+async fn api_read_only_zip() -> Result<()> {
+    use flate2::{write::DeflateEncoder, Compression};
+    use wasmtime_wasi::MemoryDir;
+
+    // Builds a zip archive out of `(name, contents)` entries, deflating the
+    // contents of files.
+    fn zip(entries: &[(&str, &[u8])]) -> Result<Vec<u8>> {
+        let mut out = Vec::new();
+        let mut central = Vec::new();
+        for (name, contents) in entries {
+            let (method, data) = if name.ends_with('/') {
+                (0u16, Vec::new())
+            } else {
+                let mut encoder = DeflateEncoder::new(Vec::new(), Compression::default());
+                encoder.write_all(contents)?;
+                (8, encoder.finish()?)
+            };
+            let mut fields = Vec::new();
+            fields.extend_from_slice(&method.to_le_bytes());
+            fields.extend_from_slice(&[0; 4]);
+            fields.extend_from_slice(&crc32fast::hash(contents).to_le_bytes());
+            fields.extend_from_slice(&(data.len() as u32).to_le_bytes());
+            fields.extend_from_slice(&(contents.len() as u32).to_le_bytes());
+            fields.extend_from_slice(&(name.len() as u16).to_le_bytes());
+
+            let offset = out.len() as u32;
+            out.extend_from_slice(&0x0403_4b50u32.to_le_bytes());
+            out.extend_from_slice(&[20, 0, 0, 0]);
+            out.extend_from_slice(&fields);
+            out.extend_from_slice(&[0; 2]);
+            out.extend_from_slice(name.as_bytes());
+            out.extend_from_slice(&data);
+
+            central.extend_from_slice(&0x0201_4b50u32.to_le_bytes());
+            central.extend_from_slice(&[20, 0, 20, 0, 0, 0]);
+            central.extend_from_slice(&fields);
+            central.extend_from_slice(&[0; 12]);
+            central.extend_from_slice(&offset.to_le_bytes());
+            central.extend_from_slice(name.as_bytes());
+        }
+        let central_offset = out.len() as u32;
+        out.extend_from_slice(&central);
+        out.extend_from_slice(&0x0605_4b50u32.to_le_bytes());
+        out.extend_from_slice(&[0; 4]);
+        out.extend_from_slice(&(entries.len() as u16).to_le_bytes());
+        out.extend_from_slice(&(entries.len() as u16).to_le_bytes());
+        out.extend_from_slice(&(central.len() as u32).to_le_bytes());
+        out.extend_from_slice(&central_offset.to_le_bytes());
+        out.extend_from_slice(&[0; 2]);
+        Ok(out)
+    }
+
+    let archive = zip(&[("bar.txt", b"And stood awhile in thought"), ("sub/", b"")])?;
+    let dir = MemoryDir::from_zip(&archive[..])?;
+
+    let table = ResourceTable::new();
+    let wasi = WasiCtxBuilder::new()
+        .preopened_virtual_dir(dir, "/", DirPerms::READ, FilePerms::READ)
+        .build();
+
+    let (mut store, command) =
+        instantiate(API_READ_ONLY_COMPONENT, CommandCtx { table, wasi }).await?;
+
+    command
+        .wasi_cli_run()
+        .call_run(&mut store)
+        .await?
+        .map_err(|()| anyhow::anyhow!("command returned with failing exit status"))
+}
+
 // This is tested in the wasi-http crate, but need to satisfy the `foreach_api!`
 // macro above.
 #[allow(dead_code)]
@@ -3,8 +3,18 @@ use std::path::Path;
 use test_programs_artifacts::*;
 use wasmtime_wasi::add_to_linker_async;
 use wasmtime_wasi::bindings::Command;
+use wasmtime_wasi::VirtualNetwork;
 
 async fn run(path: &str, inherit_stdio: bool) -> Result<()> {
+    run_with(path, |builder| {
+        if inherit_stdio {
+            builder.inherit_stdio();
+        }
+    })
+    .await
+}
+
+async fn run_with(path: &str, configure: impl FnOnce(&mut WasiCtxBuilder)) -> Result<()> {
     let path = Path::new(path);
     let name = path.file_stem().unwrap().to_str().unwrap();
     let engine = test_programs_artifacts::engine(|config| {
@@ -13,11 +23,7 @@ async fn run(path: &str, inherit_stdio: bool) -> Result<()> {
     let mut linker = Linker::new(&engine);
     add_to_linker_async(&mut linker)?;
 
-    let (mut store, _td) = store(&engine, name, |builder| {
-        if inherit_stdio {
-            builder.inherit_stdio();
-        }
-    })?;
+    let (mut store, _td) = store(&engine, name, configure)?;
     let component = Component::from_file(&engine, path)?;
     let command = Command::instantiate_async(&mut store, &component, &linker).await?;
     command
@@ -399,3 +405,22 @@ async fn preview2_file_read_write() {
         .await
         .unwrap()
 }
+
+// The sample applications are both client and server, so they also work on a
+// virtual network.
+#[test_log::test(tokio::test(flavor = "multi_thread"))]
+async fn preview2_tcp_sample_application_virtual_network() {
+    run_with(PREVIEW2_TCP_SAMPLE_APPLICATION_COMPONENT, |builder| {
+        builder.virtual_network(VirtualNetwork::new());
+    })
+    .await
+    .unwrap()
+}
+#[test_log::test(tokio::test(flavor = "multi_thread"))]
+async fn preview2_udp_sample_application_virtual_network() {
+    run_with(PREVIEW2_UDP_SAMPLE_APPLICATION_COMPONENT, |builder| {
+        builder.virtual_network(VirtualNetwork::new());
+    })
+    .await
+    .unwrap()
+}
@@ -141,6 +141,7 @@ pub struct Config {
     pub(crate) coredump_on_trap: bool,
     pub(crate) macos_use_mach_ports: bool,
     pub(crate) detect_host_feature: Option<fn(&str) -> Option<bool>>,
+    pub(crate) collector: Collector,
 }
 
 #[derive(Default, Clone)]
@@ -157,6 +158,7 @@ struct ConfigTunables {
     guard_before_linear_memory: Option<bool>,
     table_lazy_init: Option<bool>,
     generate_address_map: Option<bool>,
+    record_frame_state: Option<bool>,
     debug_adapter_modules: Option<bool>,
     relaxed_simd_deterministic: Option<bool>,
     signals_based_traps: Option<bool>,
@@ -263,6 +265,7 @@ impl Config {
             detect_host_feature: Some(detect_host_feature),
             #[cfg(not(feature = "std"))]
             detect_host_feature: None,
+            collector: Collector::Auto,
         };
         #[cfg(any(feature = "cranelift", feature = "winch"))]
         {
@@ -849,6 +852,88 @@ impl Config {
         self
     }
 
+    /// Configures whether the [WebAssembly exception-handling
+    /// proposal][proposal] will be enabled for compilation.
+    ///
+    /// This feature gates tag definitions and imports, the `exnref` type, and
+    /// the `try_table`, `throw`, and `throw_ref` instructions. Exceptions that
+    /// are not caught within Wasm propagate out of [`Func::call`] as a
+    /// [`ThrownException`] error, after which the exception itself may be
+    /// retrieved with [`Store::take_pending_exception`]. Host functions may
+    /// throw exceptions with [`Caller::throw`].
+    ///
+    /// Exception objects are allocated in the GC heap, so this requires the
+    /// `gc` Cargo feature, and exception objects are managed by the
+    /// configured [`Collector`].
+    ///
+    /// This feature is `false` by default.
+    ///
+    /// **Warning: Wasmtime's implementation of the exception-handling proposal
+    /// is still in progress and generally not ready for primetime.**
+    ///
+    /// [proposal]: https://github.com/WebAssembly/exception-handling
+    /// [`Func::call`]: crate::Func::call
+    /// [`ThrownException`]: crate::ThrownException
+    /// [`Store::take_pending_exception`]: crate::Store::take_pending_exception
+    /// [`Caller::throw`]: crate::Caller::throw
+    #[cfg(feature = "gc")]
+    pub fn wasm_exceptions(&mut self, enable: bool) -> &mut Self {
+        self.wasm_feature(WasmFeatures::EXCEPTIONS, enable);
+        self
+    }
+
+    /// Configures whether the [WebAssembly stack-switching
+    /// proposal][proposal] will be enabled for compilation.
+    ///
+    /// This feature gates continuation types and the `cont.new`, `cont.bind`,
+    /// `resume`, `resume_throw`, `suspend`, and `switch` instructions.
+    ///
+    /// Each continuation executes on its own native stack. These stacks are
+    /// allocated in the same way as the stacks used for async calls, so their
+    /// size is configured with [`Config::async_stack_size`] and they are taken
+    /// from the pooling allocator's stack pool when it is in use. The maximum
+    /// stack usage of Wasm running within a continuation is limited by
+    /// [`Config::max_wasm_stack`], just like on the main stack.
+    ///
+    /// A continuation that is never resumed to completion keeps its stack
+    /// allocated until it becomes unreachable, i.e. until no Wasm frame, no
+    /// other reachable continuation and no value being passed to Wasm holds
+    /// its handle. Unreachable continuations are reclaimed, and their stacks
+    /// unwound and deallocated, when `cont.new` finds many continuations
+    /// alive in the store or when no stack can be allocated for a new
+    /// continuation.
+    ///
+    /// The number of continuations alive at once in a store is limited by
+    /// [`ResourceLimiter::continuations`](crate::ResourceLimiter::continuations),
+    /// which defaults to 10,000 and can be set with
+    /// [`StoreLimitsBuilder::continuations`](crate::StoreLimitsBuilder::continuations).
+    /// `cont.new` traps once that many continuations are still reachable.
+    ///
+    /// This feature is `false` by default.
+    ///
+    /// **Warning: Wasmtime's implementation of the stack-switching proposal
+    /// is still in progress and generally not ready for primetime.**
+    ///
+    /// [proposal]: https://github.com/WebAssembly/stack-switching
+    #[cfg(feature = "stack-switching")]
+    pub fn wasm_stack_switching(&mut self, enable: bool) -> &mut Self {
+        self.wasm_feature(WasmFeatures::STACK_SWITCHING, enable);
+        self
+    }
+
+    /// Configures which garbage collector will be used for Wasm modules.
+    ///
+    /// This method can be used to configure which garbage collector
+    /// implementation is used for Wasm modules. For more documentation, consult
+    /// the [`Collector`] enum and its documentation.
+    ///
+    /// The default value for this is `Collector::Auto`.
+    #[cfg(feature = "gc")]
+    pub fn collector(&mut self, collector: Collector) -> &mut Self {
+        self.collector = collector;
+        self
+    }
+
     /// Configures whether the WebAssembly SIMD proposal will be
     /// enabled for compilation.
     ///
@@ -1702,6 +1787,31 @@ impl Config {
         self
     }
 
+    /// Configures whether compiled code records its instance and the values
+    /// of its locals and operand stack so that they can be included in core
+    /// dumps.
+    ///
+    /// By default this state is not available to core dumps captured with
+    /// [`Config::coredump_on_trap`] because it lives in registers that are
+    /// not preserved at the time of a trap. When this option is enabled
+    /// Wasmtime compiles code to additionally spill it into a known location
+    /// in each frame, and it is then included in
+    /// [`WasmCoreDump`](crate::WasmCoreDump)s.
+    ///
+    /// This option only affects Cranelift, where both locals and the operand
+    /// stack are recorded. This noticeably slows down execution, so it is
+    /// intended for debugging. Winch already keeps its instance and locals in
+    /// its frames so code compiled by Winch always has them, but not its
+    /// operand stack, available to core dumps. Only numeric values are
+    /// recorded; other values show up as missing in core dumps.
+    ///
+    /// This option is disabled by default.
+    #[cfg(feature = "coredump")]
+    pub fn record_frame_state(&mut self, enable: bool) -> &mut Self {
+        self.tunables.record_frame_state = Some(enable);
+        self
+    }
+
     /// Enables memory error checking for wasm programs.
     ///
     /// This option is disabled by default.
@@ -1774,14 +1884,23 @@ impl Config {
     fn compiler_panicking_wasm_features(&self) -> WasmFeatures {
         #[cfg(any(feature = "cranelift", feature = "winch"))]
         match self.compiler_config.strategy {
-            None | Some(Strategy::Cranelift) => WasmFeatures::empty(),
+            None | Some(Strategy::Cranelift) => match self.compiler_target().architecture {
+                // Exceptions unwind the stack straight to the frame that
+                // catches them, which isn't implemented for these targets.
+                target_lexicon::Architecture::S390x
+                | target_lexicon::Architecture::Pulley32
+                | target_lexicon::Architecture::Pulley64 => WasmFeatures::EXCEPTIONS,
+                _ => WasmFeatures::empty(),
+            },
             Some(Strategy::Winch) => {
                 let mut unsupported = WasmFeatures::GC
                     | WasmFeatures::FUNCTION_REFERENCES
                     | WasmFeatures::THREADS
                     | WasmFeatures::RELAXED_SIMD
                     | WasmFeatures::TAIL_CALL
-                    | WasmFeatures::GC_TYPES;
+                    | WasmFeatures::GC_TYPES
+                    | WasmFeatures::EXCEPTIONS
+                    | WasmFeatures::STACK_SWITCHING;
                 match self.compiler_target().architecture {
                     target_lexicon::Architecture::Aarch64(_) => {
                         // no support for simd on aarch64
@@ -1914,8 +2033,15 @@ impl Config {
         {
             bail!("feature 'gc' requires 'function_references' to be enabled");
         }
+        if features.contains(WasmFeatures::EXCEPTIONS)
+            && !features.contains(WasmFeatures::REFERENCE_TYPES)
+        {
+            bail!("feature 'exceptions' requires 'reference_types' to be enabled");
+        }
         #[cfg(feature = "async")]
-        if self.async_support && self.max_wasm_stack > self.async_stack_size {
+        if (self.async_support || features.contains(WasmFeatures::STACK_SWITCHING))
+            && self.max_wasm_stack > self.async_stack_size
+        {
             bail!("max_wasm_stack size cannot exceed the async_stack_size");
         }
         if self.max_wasm_stack == 0 {
@@ -1961,6 +2087,7 @@ impl Config {
             guard_before_linear_memory
             table_lazy_init
             generate_address_map
+            record_frame_state
             debug_adapter_modules
             relaxed_simd_deterministic
             signals_based_traps
@@ -1984,6 +2111,12 @@ impl Config {
             bail!("static memory guard size cannot be smaller than dynamic memory guard size");
         }
 
+        tunables.collector = if features.gc_types() {
+            Some(self.collector.try_not_auto()?.into())
+        } else {
+            None
+        };
+
         Ok((tunables, features))
     }
 
@@ -2026,7 +2159,24 @@ impl Config {
 
     #[cfg(feature = "runtime")]
     pub(crate) fn build_gc_runtime(&self) -> Result<Arc<dyn GcRuntime>> {
-        Ok(Arc::new(crate::runtime::vm::default_gc_runtime()) as Arc<dyn GcRuntime>)
+        #[cfg(feature = "gc")]
+        {
+            use crate::runtime::vm::{DrcCollector, MarkSweepCollector, NullCollector};
+            Ok(match self.collector.try_not_auto()? {
+                Collector::Auto => unreachable!(),
+                Collector::DeferredReferenceCounting => {
+                    Arc::new(DrcCollector::default()) as Arc<dyn GcRuntime>
+                }
+                Collector::MarkSweep => {
+                    Arc::new(MarkSweepCollector::default()) as Arc<dyn GcRuntime>
+                }
+                Collector::Null => Arc::new(NullCollector::default()) as Arc<dyn GcRuntime>,
+            })
+        }
+        #[cfg(not(feature = "gc"))]
+        {
+            Ok(Arc::new(crate::runtime::vm::default_gc_runtime()) as Arc<dyn GcRuntime>)
+        }
     }
 
     #[cfg(feature = "runtime")]
@@ -2331,6 +2481,7 @@ impl fmt::Debug for Config {
         if let Some(enable) = self.tunables.guard_before_linear_memory {
             f.field("guard_before_linear_memory", &enable);
         }
+        f.field("collector", &self.collector);
         f.finish()
     }
 }
@@ -2378,6 +2529,122 @@ impl Strategy {
     }
 }
 
+/// Possible garbage collector implementations for Wasm.
+///
+/// This is used as an argument to the [`Config::collector`] method.
+#[non_exhaustive]
+#[derive(PartialEq, Eq, Clone, Debug, Copy)]
+pub enum Collector {
+    /// An indicator that the garbage collector should be automatically
+    /// selected.
+    ///
+    /// This is generally what you want for most projects and indicates that the
+    /// `wasmtime` crate itself should make the decision about what the best
+    /// collector for a wasm module is.
+    ///
+    /// Currently this always defaults to the deferred reference-counting
+    /// collector, but the default value may change over time.
+    Auto,
+
+    /// The deferred reference-counting collector.
+    ///
+    /// A reference-counting collector, generally trading improved latency for
+    /// worsened throughput. However, to avoid the largest overheads of
+    /// reference counting, it avoids manipulating reference counts for Wasm
+    /// objects on the stack. Instead, it will hold a reference count for an
+    /// over-approximation of all objects that are currently on the stack, trace
+    /// the stack during collection to find the precise set of on-stack roots,
+    /// and decrement the reference count of any object that was in the
+    /// over-approximation but not the precise set. This improves throughput,
+    /// compared to "pure" reference counting, by performing many fewer
+    /// refcount-increment and -decrement operations. The cost is the increased
+    /// latency associated with tracing the stack.
+    ///
+    /// This collector cannot currently collect cycles; they will leak until the
+    /// GC heap's store is dropped.
+    DeferredReferenceCounting,
+
+    /// The mark-and-sweep collector.
+    ///
+    /// A non-moving, tracing collector. During a collection, it marks every
+    /// object that is transitively reachable from the GC roots (including
+    /// on-stack roots found via stack maps) and then sweeps every unmarked
+    /// object back onto the GC heap's free list.
+    ///
+    /// Because it traces the object graph, this collector reclaims cyclic
+    /// garbage. Compiled Wasm code does not need to emit any GC barriers for
+    /// this collector, which improves throughput, at the cost of pausing the
+    /// Wasm program while the whole heap is traced and swept.
+    MarkSweep,
+
+    /// The null collector.
+    ///
+    /// This collector bump-allocates objects and never collects any garbage.
+    /// Once the GC heap is exhausted, further allocations from Wasm trap with
+    /// [`Trap::GcHeapOutOfMemory`][crate::Trap::GcHeapOutOfMemory], and
+    /// allocations from the host fail with a
+    /// [`GcHeapOutOfMemory`][crate::GcHeapOutOfMemory] error.
+    ///
+    /// This is useful for short-lived instances whose GC heap is thrown away
+    /// before it would ever fill up, such as per-request instances on the
+    /// pooling allocator, since it avoids all GC barriers and collection work.
+    /// It also serves as a baseline when benchmarking other collectors.
+    Null,
+}
+
+impl Default for Collector {
+    fn default() -> Collector {
+        Collector::Auto
+    }
+}
+
+impl fmt::Display for Collector {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        match self {
+            Collector::Auto => write!(f, "auto"),
+            Collector::DeferredReferenceCounting => write!(f, "deferred reference-counting"),
+            Collector::MarkSweep => write!(f, "mark-and-sweep"),
+            Collector::Null => write!(f, "null"),
+        }
+    }
+}
+
+impl Collector {
+    fn not_auto(&self) -> Option<Collector> {
+        match self {
+            Collector::Auto => {
+                if cfg!(feature = "gc") {
+                    Some(Collector::DeferredReferenceCounting)
+                } else {
+                    None
+                }
+            }
+            other => Some(*other),
+        }
+    }
+
+    fn try_not_auto(&self) -> Result<Self> {
+        match self.not_auto() {
+            Some(c) => Ok(c),
+            None => bail!(
+                "`Collector::Auto` requires the `gc` cargo feature, which was \
+                 disabled at compile time",
+            ),
+        }
+    }
+}
+
+impl From<Collector> for wasmtime_environ::Collector {
+    fn from(c: Collector) -> Self {
+        match c {
+            Collector::DeferredReferenceCounting => Self::DeferredReferenceCounting,
+            Collector::MarkSweep => Self::MarkSweep,
+            Collector::Null => Self::Null,
+            Collector::Auto => unreachable!(),
+        }
+    }
+}
+
 /// Possible optimization levels for the Cranelift codegen backend.
 #[non_exhaustive]
 #[derive(Copy, Clone, Debug, Serialize, Deserialize, Eq, PartialEq)]
@@ -205,6 +205,7 @@ struct WasmFeatures {
     component_model_more_flags: bool,
     component_model_multiple_returns: bool,
     gc_types: bool,
+    stack_switching: bool,
     wide_arithmetic: bool,
 }
 
@@ -253,7 +254,6 @@ impl Metadata<'_> {
         assert!(!component_model_nested_names);
         assert!(!shared_everything_threads);
         assert!(!legacy_exceptions);
-        assert!(!stack_switching);
 
         Metadata {
             target: engine.compiler().triple().to_string(),
@@ -279,6 +279,7 @@ impl Metadata<'_> {
                 component_model_more_flags,
                 component_model_multiple_returns,
                 gc_types,
+                stack_switching,
                 wide_arithmetic,
             },
         }
@@ -376,6 +377,7 @@ impl Metadata<'_> {
             relaxed_simd_deterministic,
             winch_callable,
             signals_based_traps,
+            collector,
             // This doesn't affect compilation, it's just a runtime setting.
             dynamic_memory_growth_reserve: _,
 
@@ -385,6 +387,11 @@ impl Metadata<'_> {
             // whether it's present or not)
             generate_address_map: _,
 
+            // Similarly frame state is recorded in metadata which is only
+            // consulted when capturing core dumps, and code compiled either
+            // way can run in engines with the opposite setting.
+            record_frame_state: _,
+
             // Just a debugging aid, doesn't affect functionality at all.
             debug_adapter_modules: _,
         } = self.tunables;
@@ -446,10 +453,30 @@ impl Metadata<'_> {
             other.signals_based_traps,
             "Signals-based traps",
         )?;
+        Self::check_collector(collector, other.collector)?;
 
         Ok(())
     }
 
+    fn check_collector(
+        module: Option<wasmtime_environ::Collector>,
+        host: Option<wasmtime_environ::Collector>,
+    ) -> Result<()> {
+        match (module, host) {
+            (None, _) => Ok(()),
+            (Some(module), Some(host)) if module == host => Ok(()),
+            (Some(_), None) => {
+                bail!("Module was compiled with GC however GC is disabled in the host")
+            }
+            (Some(module), Some(host)) => {
+                bail!(
+                    "Module was compiled for the {module} collector but \
+                     the host is configured to use the {host} collector",
+                )
+            }
+        }
+    }
+
     fn check_cfg_bool(
         cfg: bool,
         cfg_str: &str,
@@ -491,6 +518,7 @@ impl Metadata<'_> {
             component_model_more_flags,
             component_model_multiple_returns,
             gc_types,
+            stack_switching,
             wide_arithmetic,
         } = self.features;
 
@@ -546,6 +574,11 @@ impl Metadata<'_> {
             other.contains(F::EXCEPTIONS),
             "WebAssembly exceptions support",
         )?;
+        Self::check_bool(
+            stack_switching,
+            other.contains(F::STACK_SWITCHING),
+            "WebAssembly stack switching support",
+        )?;
         Self::check_bool(
             memory64,
             other.contains(F::MEMORY64),
@@ -7,7 +7,9 @@ use core::ops::Range;
 use object::endian::NativeEndian;
 use object::read::{elf::ElfFile64, Object, ObjectSection};
 use object::ObjectSymbol;
-use wasmtime_environ::{lookup_trap_code, obj, Trap};
+use wasmtime_environ::{
+    lookup_exception_landing_pad, lookup_trap_code, obj, ExceptionLandingPad, Trap,
+};
 use wasmtime_jit_icache_coherence as icache_coherence;
 
 /// Management of executable memory within a `MmapVec`
@@ -28,6 +30,7 @@ pub struct CodeMemory {
     text: Range<usize>,
     unwind: Range<usize>,
     trap_data: Range<usize>,
+    exception_data: Range<usize>,
     wasm_data: Range<usize>,
     address_map_data: Range<usize>,
     func_name_data: Range<usize>,
@@ -66,6 +69,7 @@ impl CodeMemory {
         let mut unwind = 0..0;
         let mut enable_branch_protection = None;
         let mut trap_data = 0..0;
+        let mut exception_data = 0..0;
         let mut wasm_data = 0..0;
         let mut address_map_data = 0..0;
         let mut func_name_data = 0..0;
@@ -122,6 +126,7 @@ impl CodeMemory {
                 obj::ELF_WASM_DATA => wasm_data = range,
                 obj::ELF_WASMTIME_ADDRMAP => address_map_data = range,
                 obj::ELF_WASMTIME_TRAPS => trap_data = range,
+                obj::ELF_WASMTIME_EXCEPTIONS => exception_data = range,
                 obj::ELF_NAME_DATA => func_name_data = range,
                 obj::ELF_WASMTIME_INFO => info_data = range,
                 obj::ELF_WASMTIME_DWARF => dwarf = range,
@@ -138,6 +143,7 @@ impl CodeMemory {
             text,
             unwind,
             trap_data,
+            exception_data,
             address_map_data,
             func_name_data,
             dwarf,
@@ -323,6 +329,13 @@ impl CodeMemory {
     pub fn lookup_trap_code(&self, text_offset: usize) -> Option<Trap> {
         lookup_trap_code(self.trap_data(), text_offset)
     }
+
+    /// Looks up the given return address, as an offset within this module's
+    /// text section, and returns the exception landing pad of the call that
+    /// it returns from, if there is one.
+    pub fn lookup_exception_landing_pad(&self, text_offset: usize) -> Option<ExceptionLandingPad> {
+        lookup_exception_landing_pad(&self.mmap[self.exception_data.clone()], text_offset)
+    }
 }
 
 /// Returns the range of `inner` within `outer`, such that `outer[range]` is the
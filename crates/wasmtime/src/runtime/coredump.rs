@@ -1,10 +1,12 @@
 use crate::hash_map::HashMap;
 use crate::prelude::*;
+use crate::runtime::vm::{CoreDumpFrameState, CoreDumpStack};
 use crate::{
     store::StoreOpaque, AsContextMut, FrameInfo, Global, HeapType, Instance, Memory, Module,
     StoreContextMut, Val, ValType, WasmBacktrace,
 };
 use std::fmt;
+use wasm_encoder::CoreDumpValue;
 
 /// Representation of a core dump of a WebAssembly module
 ///
@@ -18,7 +20,10 @@ use std::fmt;
 ///
 /// Note that some state, such as Wasm locals or values on the operand stack,
 /// may be optimized away by the compiler or otherwise not recovered in the
-/// coredump.
+/// coredump. Locals are recovered for code compiled by Winch, and both locals
+/// and the operand stack are recovered for code compiled with
+/// [`Config::record_frame_state`][crate::Config::record_frame_state]. The
+/// same is true of the instance that each frame belongs to.
 ///
 /// Capturing of wasm coredumps can be configured through the
 /// [`Config::coredump_on_trap`][crate::Config::coredump_on_trap] method.
@@ -35,10 +40,24 @@ pub struct WasmCoreDump {
     memories: Vec<Memory>,
     globals: Vec<Global>,
     backtrace: WasmBacktrace,
+    /// The recovered state of each frame in `backtrace`, if any.
+    frame_states: Vec<Option<FrameState>>,
+}
+
+/// The recovered state of a single frame in a core dump.
+struct FrameState {
+    /// The index of this frame's instance in `WasmCoreDump::instances`.
+    instance: Option<usize>,
+    locals: Vec<Option<Val>>,
+    operand_stack: Vec<Option<Val>>,
 }
 
 impl WasmCoreDump {
-    pub(crate) fn new(store: &mut StoreOpaque, backtrace: WasmBacktrace) -> WasmCoreDump {
+    pub(crate) fn new(
+        store: &mut StoreOpaque,
+        coredump: CoreDumpStack,
+        trap_pc: Option<usize>,
+    ) -> WasmCoreDump {
         let modules: Vec<_> = store.modules().all_modules().cloned().collect();
         let instances: Vec<Instance> = store.all_instances().collect();
         let store_memories: Vec<Memory> = store.all_memories().collect();
@@ -46,6 +65,57 @@ impl WasmCoreDump {
         let mut store_globals: Vec<Global> = vec![];
         store.for_each_global(|_store, global| store_globals.push(global));
 
+        // Frames from other stores are skipped in the backtrace, so skip their
+        // state too.
+        let CoreDumpStack { bt, frame_states } = coredump;
+        let frame_states: Vec<_> = bt
+            .frames()
+            .zip(frame_states)
+            .filter(|(frame, _)| {
+                let pc = WasmBacktrace::pc_to_lookup(frame, trap_pc);
+                store.modules().lookup_frame_info(pc).is_some()
+            })
+            .map(|(_, state)| state)
+            .collect();
+        let backtrace = WasmBacktrace::from_captured(store, bt, trap_pc);
+        debug_assert_eq!(backtrace.frames().len(), frame_states.len());
+
+        // A map from each instance's `VMContext` to its index in `instances`
+        // and the id of its module.
+        let vmctx_to_instance: HashMap<usize, _> = instances
+            .iter()
+            .enumerate()
+            .map(|(i, instance)| {
+                let id = instance.id(store);
+                let vmctx = store.instance(id).vmctx();
+                let module = store.module_for_instance(id).map(|m| m.id());
+                (vmctx as usize, (i, module))
+            })
+            .collect();
+        let frame_states = backtrace
+            .frames()
+            .iter()
+            .zip(frame_states)
+            .map(|(frame, state)| {
+                let CoreDumpFrameState {
+                    vmctx,
+                    locals,
+                    operand_stack,
+                } = state?;
+                // Double check that the recorded instance is an instance of
+                // the frame's module before trusting it.
+                let instance = vmctx
+                    .and_then(|vmctx| vmctx_to_instance.get(&(vmctx.as_ptr() as usize)))
+                    .filter(|(_, module)| *module == Some(frame.module().id()))
+                    .map(|(i, _)| *i);
+                Some(FrameState {
+                    instance,
+                    locals: locals.into_iter().map(val_from_core_dump).collect(),
+                    operand_stack: operand_stack.into_iter().map(val_from_core_dump).collect(),
+                })
+            })
+            .collect();
+
         WasmCoreDump {
             name: String::from("store_name"),
             modules,
@@ -53,6 +123,7 @@ impl WasmCoreDump {
             memories: store_memories,
             globals: store_globals,
             backtrace,
+            frame_states,
         }
     }
 
@@ -64,6 +135,31 @@ impl WasmCoreDump {
         self.backtrace.frames()
     }
 
+    /// The instance that the frame at `index` in [`WasmCoreDump::frames`]
+    /// belongs to, if it could be recovered.
+    pub fn frame_instance(&self, index: usize) -> Option<Instance> {
+        let instance = self.frame_states.get(index)?.as_ref()?.instance?;
+        Some(self.instances[instance])
+    }
+
+    /// The values of the locals, including parameters, of the frame at `index`
+    /// in [`WasmCoreDump::frames`], if they could be recovered.
+    ///
+    /// Values which weren't recorded, such as references, are `None`.
+    pub fn frame_locals(&self, index: usize) -> Option<&[Option<Val>]> {
+        Some(&self.frame_states.get(index)?.as_ref()?.locals)
+    }
+
+    /// The values on the operand stack, bottom-most first, of the frame at
+    /// `index` in [`WasmCoreDump::frames`], if they could be recovered.
+    ///
+    /// Values which weren't recorded, such as references, are `None`. The
+    /// operand stack of code compiled by Winch is never recovered, and is
+    /// always empty.
+    pub fn frame_operand_stack(&self, index: usize) -> Option<&[Option<Val>]> {
+        Some(&self.frame_states.get(index)?.as_ref()?.operand_stack)
+    }
+
     /// All modules instantiated inside the store when the core dump was
     /// created.
     pub fn modules(&self) -> &[Module] {
@@ -186,6 +282,8 @@ impl WasmCoreDump {
 
                         HeapType::Any => wasm_encoder::ValType::Ref(wasm_encoder::RefType::ANYREF),
 
+                        HeapType::Exn => wasm_encoder::ValType::EXNREF,
+
                         ty => unreachable!("not a top type: {ty:?}"),
                     },
                 };
@@ -204,6 +302,12 @@ impl WasmCoreDump {
                     Val::AnyRef(_) => {
                         wasm_encoder::ConstExpr::ref_null(wasm_encoder::HeapType::ANY)
                     }
+                    Val::ExnRef(_) => {
+                        wasm_encoder::ConstExpr::ref_null(wasm_encoder::HeapType::Abstract {
+                            shared: false,
+                            ty: wasm_encoder::AbstractHeapType::Exn,
+                        })
+                    }
                 };
                 globals.global(
                     wasm_encoder::GlobalType {
@@ -236,12 +340,11 @@ impl WasmCoreDump {
             core_dump.section(&modules);
         }
 
-        // TODO: We can't currently recover instances from stack frames. We can
-        // recover module via the frame's PC, but if there are multiple
-        // instances of the same module, we don't know which instance the frame
-        // is associated with. Therefore, we do a best effort job: remember the
-        // last instance of each module and always choose that one. We record
-        // that information here.
+        // Frames only record which instance they belong to when their
+        // function was compiled to record its frame state. For other frames
+        // we do a best effort job: remember the last instance of each module
+        // and always choose that one. We record that information here, along
+        // with each instance's index.
         let mut module_to_instance = HashMap::new();
 
         {
@@ -274,11 +377,16 @@ impl WasmCoreDump {
         {
             let thread_name = "main";
             let mut stack = wasm_encoder::CoreDumpStackSection::new(thread_name);
-            for frame in self.frames() {
-                // This isn't necessarily the right instance if there are
+            for (frame, state) in self.frames().iter().zip(&self.frame_states) {
+                // Note that the instances section above lists instances in the
+                // same order as `self.instances`. Without a recorded instance
+                // this isn't necessarily the right instance if there are
                 // multiple instances of the same module. See comment above
                 // `module_to_instance` for details.
-                let instance = module_to_instance[&frame.module().id()];
+                let instance = match state.as_ref().and_then(|s| s.instance) {
+                    Some(instance) => u32::try_from(instance).unwrap(),
+                    None => module_to_instance[&frame.module().id()],
+                };
 
                 let func = frame.func_index();
 
@@ -287,10 +395,13 @@ impl WasmCoreDump {
                     .and_then(|o| u32::try_from(o).ok())
                     .unwrap_or(0);
 
-                // We can't currently recover locals and the operand stack. We
-                // should eventually be able to do that with Winch though.
-                let locals = [];
-                let operand_stack = [];
+                let (locals, operand_stack) = match state {
+                    Some(state) => (
+                        state.locals.iter().map(val_to_core_dump).collect(),
+                        state.operand_stack.iter().map(val_to_core_dump).collect(),
+                    ),
+                    None => (Vec::new(), Vec::new()),
+                };
 
                 stack.frame(instance, func, offset, locals, operand_stack);
             }
@@ -301,6 +412,26 @@ impl WasmCoreDump {
     }
 }
 
+fn val_from_core_dump(val: CoreDumpValue) -> Option<Val> {
+    match val {
+        CoreDumpValue::Missing => None,
+        CoreDumpValue::I32(x) => Some(Val::I32(x)),
+        CoreDumpValue::I64(x) => Some(Val::I64(x)),
+        CoreDumpValue::F32(x) => Some(Val::F32(x.to_bits())),
+        CoreDumpValue::F64(x) => Some(Val::F64(x.to_bits())),
+    }
+}
+
+fn val_to_core_dump(val: &Option<Val>) -> CoreDumpValue {
+    match val {
+        Some(Val::I32(x)) => CoreDumpValue::I32(*x),
+        Some(Val::I64(x)) => CoreDumpValue::I64(*x),
+        Some(Val::F32(x)) => CoreDumpValue::F32(f32::from_bits(*x)),
+        Some(Val::F64(x)) => CoreDumpValue::F64(f64::from_bits(*x)),
+        _ => CoreDumpValue::Missing,
+    }
+}
+
 impl fmt::Display for WasmCoreDump {
     fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
         writeln!(f, "wasm coredump generated while executing {}:", self.name)?;
@@ -3,8 +3,8 @@ use crate::runtime::vm::{GcRootsList, SendSyncPtr};
 use crate::{
     store::{AutoAssertNoGc, StoreData, StoreOpaque, Stored},
     trampoline::generate_global_export,
-    AnyRef, AsContext, AsContextMut, ExternRef, Func, GlobalType, HeapType, Mutability, Ref,
-    RootedGcRefImpl, Val, ValType,
+    AnyRef, AsContext, AsContextMut, ExnRef, ExternRef, Func, GlobalType, HeapType, Mutability,
+    Ref, RootedGcRefImpl, Val, ValType,
 };
 use core::ptr;
 use core::ptr::NonNull;
@@ -150,6 +150,20 @@ impl Global {
                             .into(),
 
                         HeapType::None => Ref::Any(None),
+
+                        HeapType::Exn => definition
+                            .as_gc_ref()
+                            .map(|r| {
+                                let r = store.unwrap_gc_store_mut().clone_gc_ref(r);
+                                ExnRef::from_cloned_gc_ref(&mut store, r)
+                            })
+                            .into(),
+
+                        HeapType::NoExn => Ref::Exn(None),
+
+                        HeapType::Cont | HeapType::ConcreteCont(_) | HeapType::NoCont => {
+                            unreachable!("globals of continuation references are rejected")
+                        }
                     };
                     debug_assert!(
                         ref_ty.is_nullable() || !reference.is_null(),
@@ -211,6 +225,15 @@ impl Global {
                     let new = new.as_ref();
                     definition.write_gc_ref(store.unwrap_gc_store_mut(), new);
                 }
+                Val::ExnRef(e) => {
+                    let new = match e {
+                        None => None,
+                        #[cfg_attr(not(feature = "gc"), allow(unreachable_patterns))]
+                        Some(e) => Some(e.try_gc_ref(&store)?.unchecked_copy()),
+                    };
+                    let new = new.as_ref();
+                    definition.write_gc_ref(store.unwrap_gc_store_mut(), new);
+                }
             }
         }
         Ok(())
@@ -0,0 +1,143 @@
+use crate::prelude::*;
+use crate::runtime::vm::VMTagDefinition;
+use crate::{
+    store::{StoreData, StoreOpaque, Stored},
+    trampoline::generate_tag_export,
+    AsContext, AsContextMut, FuncType, TagType,
+};
+use wasmtime_environ::EngineOrModuleTypeIndex;
+
+/// A WebAssembly exception `tag`.
+///
+/// Tags identify the exceptions thrown by the `throw` instruction and caught
+/// by `try_table` handlers. Each tag has a [`TagType`] describing the values
+/// carried by its exceptions. Two tags are the same tag only if they refer to
+/// the same underlying definition: instantiating a module twice creates two
+/// distinct sets of tags even though their types are identical.
+///
+/// A [`Tag`] "belongs" to the store that it was originally created within
+/// (either via [`Tag::new`] or via instantiating a [`Module`](crate::Module)).
+/// Operations on a [`Tag`] only work with the store it belongs to, and if
+/// another store is passed in by accident then methods will panic.
+#[derive(Copy, Clone, Debug)]
+#[repr(transparent)] // here for the C API
+pub struct Tag(pub(super) Stored<crate::runtime::vm::ExportTag>);
+
+impl Tag {
+    /// Creates a new host-defined WebAssembly `tag` with the given type.
+    ///
+    /// The returned tag may be provided as an import to a module, used to
+    /// throw exceptions from the host with [`ExnRef::new`](crate::ExnRef::new),
+    /// and compared against the tags of caught exceptions.
+    ///
+    /// # Errors
+    ///
+    /// Returns an error if `ty` is associated with a different engine than
+    /// `store`.
+    ///
+    /// # Examples
+    ///
+    /// ```
+    /// # use wasmtime::*;
+    /// # fn main() -> anyhow::Result<()> {
+    /// let mut config = Config::new();
+    /// config.wasm_exceptions(true);
+    /// let engine = Engine::new(&config)?;
+    /// let mut store = Store::new(&engine, ());
+    ///
+    /// let ty = TagType::new(FuncType::new(&engine, [ValType::I32], []))?;
+    /// let tag = Tag::new(&mut store, &ty)?;
+    ///
+    /// let module = Module::new(
+    ///     &engine,
+    ///     "(module (tag (import \"\" \"tag\") (param i32)))",
+    /// )?;
+    /// let instance = Instance::new(&mut store, &module, &[tag.into()])?;
+    /// // ...
+    /// # Ok(())
+    /// # }
+    /// ```
+    pub fn new(mut store: impl AsContextMut, ty: &TagType) -> Result<Tag> {
+        Tag::_new(store.as_context_mut().0, ty)
+    }
+
+    fn _new(store: &mut StoreOpaque, ty: &TagType) -> Result<Tag> {
+        if !ty.comes_from_same_engine(store.engine()) {
+            bail!("tag type used with wrong engine");
+        }
+        unsafe {
+            let wasmtime_export = generate_tag_export(store, ty);
+            Ok(Tag::from_wasmtime_tag(wasmtime_export, store))
+        }
+    }
+
+    /// Returns the underlying type of this `tag`.
+    ///
+    /// # Panics
+    ///
+    /// Panics if `store` does not own this tag.
+    pub fn ty(&self, store: impl AsContext) -> TagType {
+        self._ty(store.as_context().0)
+    }
+
+    pub(crate) fn _ty(&self, store: &StoreOpaque) -> TagType {
+        let index = store[self.0].tag.signature.unwrap_engine_type_index();
+        let ty = FuncType::from_shared_type_index(store.engine(), index);
+        TagType::new(ty).expect("tag signatures were validated")
+    }
+
+    /// Are `a` and `b` the same tag?
+    ///
+    /// This compares tag identity, not tag types.
+    ///
+    /// # Panics
+    ///
+    /// Panics if either tag is not owned by `store`.
+    pub fn eq(a: &Tag, b: &Tag, store: impl AsContext) -> bool {
+        let store = store.as_context().0;
+        a.definition(store) == b.definition(store)
+    }
+
+    pub(crate) unsafe fn from_wasmtime_tag(
+        wasmtime_export: crate::runtime::vm::ExportTag,
+        store: &mut StoreOpaque,
+    ) -> Tag {
+        Tag(store.store_data_mut().insert(wasmtime_export))
+    }
+
+    /// Create a `Tag` handle for the tag `definition`, e.g. the tag found in
+    /// a thrown exception.
+    ///
+    /// The definition must be owned by `store`, either as a host tag or as a
+    /// tag defined by one of its instances.
+    pub(crate) unsafe fn from_definition(
+        store: &mut StoreOpaque,
+        definition: *mut VMTagDefinition,
+    ) -> Tag {
+        let signature = EngineOrModuleTypeIndex::Engine((*definition).type_index);
+        let export = crate::runtime::vm::ExportTag {
+            definition,
+            vmctx: core::ptr::null_mut(),
+            tag: wasmtime_environ::Tag { signature },
+        };
+        Tag::from_wasmtime_tag(export, store)
+    }
+
+    pub(crate) fn wasmtime_ty<'a>(&self, data: &'a StoreData) -> &'a wasmtime_environ::Tag {
+        &data[self.0].tag
+    }
+
+    pub(crate) fn definition(&self, store: &StoreOpaque) -> *mut VMTagDefinition {
+        store[self.0].definition
+    }
+
+    pub(crate) fn vmimport(&self, store: &StoreOpaque) -> crate::runtime::vm::VMTagImport {
+        crate::runtime::vm::VMTagImport {
+            from: self.definition(store),
+        }
+    }
+
+    pub(crate) fn comes_from_same_store(&self, store: &StoreOpaque) -> bool {
+        store.store_data().contains(self.0)
+    }
+}
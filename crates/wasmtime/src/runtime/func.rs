@@ -19,6 +19,9 @@ use core::pin::Pin;
 use core::ptr::{self, NonNull};
 use wasmtime_environ::VMSharedTypeIndex;
 
+#[cfg(feature = "gc")]
+use crate::{ExnRef, Rooted, ThrownException};
+
 /// A reference to the abstract `nofunc` heap value.
 ///
 /// The are no instances of `(ref nofunc)`: it is an uninhabited type.
@@ -413,7 +416,7 @@ impl Func {
     /// # Panics
     ///
     /// Panics if the given function type is not associated with this store's
-    /// engine.
+    /// engine, or if it mentions continuation references.
     pub fn new<T>(
         store: impl AsContextMut<Data = T>,
         ty: FuncType,
@@ -455,7 +458,7 @@ impl Func {
     /// # Panics
     ///
     /// Panics if the given function type is not associated with this store's
-    /// engine.
+    /// engine, or if it mentions continuation references.
     pub unsafe fn new_unchecked<T>(
         mut store: impl AsContextMut<Data = T>,
         ty: FuncType,
@@ -1187,6 +1190,9 @@ impl Func {
         results: &mut [Val],
     ) -> Result<bool> {
         let (ty, opaque) = self.ty_ref(store.0);
+        if ty.has_cont_refs() {
+            bail!("cannot call functions with continuation references from the host");
+        }
         if ty.params().len() != params.len() {
             bail!(
                 "expected {} arguments, got {}",
@@ -1604,6 +1610,10 @@ pub(crate) fn invoke_wasm_and_catch_traps<T>(
     closure: impl FnMut(*mut VMContext),
 ) -> Result<()> {
     unsafe {
+        // Any exception left pending by a previous call is stale now.
+        #[cfg(feature = "gc")]
+        store.0.clear_pending_exception();
+
         let exit = enter_wasm(store);
 
         if let Err(trap) = store.0.call_hook(CallHook::CallingWasm) {
@@ -1620,6 +1630,9 @@ pub(crate) fn invoke_wasm_and_catch_traps<T>(
         );
         exit_wasm(store, exit);
         store.0.call_hook(CallHook::ReturningFromWasm)?;
+        // Note that an exception that propagates out of Wasm is raised as a
+        // `ThrownException` error, and stays pending so that the caller can
+        // take it from the store.
         result.map_err(|t| crate::trap::from_runtime_box(store.0, t))
     }
 }
@@ -2147,6 +2160,23 @@ impl<T> Caller<'_, T> {
         self.store.gc_async().await;
     }
 
+    /// Throw the exception `exn` into the Wasm code that called this host
+    /// function.
+    ///
+    /// Same as [`Store::throw`](crate::Store::throw).
+    #[cfg(feature = "gc")]
+    pub fn throw<R>(&mut self, exn: Rooted<ExnRef>) -> Result<R, ThrownException> {
+        self.store.throw(exn)
+    }
+
+    /// Take the exception that was thrown by Wasm code and not caught, if any.
+    ///
+    /// Same as [`Store::take_pending_exception`](crate::Store::take_pending_exception).
+    #[cfg(feature = "gc")]
+    pub fn take_pending_exception(&mut self) -> Option<Rooted<ExnRef>> {
+        self.store.take_pending_exception()
+    }
+
     /// Returns the remaining fuel in the store.
     ///
     /// For more information see [`Store::get_fuel`](crate::Store::get_fuel)
@@ -2293,8 +2323,9 @@ impl HostContext {
                 } else {
                     unsafe { AutoAssertNoGc::disabled(caller.store.0) }
                 };
-                let ret = ret.store(&mut store, args)?;
-                Ok(ret)
+                let ret = ret.store(&mut store, args);
+                drop(store);
+                caller.store.0.finish_host_call(ret)
             }
         };
 
@@ -2356,19 +2387,26 @@ impl HostFunc {
     /// # Panics
     ///
     /// Panics if the given function type is not associated with the given
-    /// engine.
+    /// engine, or if it mentions continuation references.
     pub unsafe fn new_unchecked<T>(
         engine: &Engine,
         ty: FuncType,
         func: impl Fn(Caller<'_, T>, &mut [ValRaw]) -> Result<()> + Send + Sync + 'static,
     ) -> Self {
         assert!(ty.comes_from_same_engine(engine));
+        assert!(
+            !ty.has_cont_refs(),
+            "continuation references cannot be passed to or from host functions"
+        );
         let func = move |caller_vmctx, values: &mut [ValRaw]| {
             Caller::<T>::with(caller_vmctx, |mut caller| {
-                caller.store.0.call_hook(CallHook::CallingHost)?;
-                let result = func(caller.sub_caller(), values)?;
-                caller.store.0.call_hook(CallHook::ReturningFromHost)?;
-                Ok(result)
+                let result = caller
+                    .store
+                    .0
+                    .call_hook(CallHook::CallingHost)
+                    .and_then(|()| func(caller.sub_caller(), values))
+                    .and_then(|()| caller.store.0.call_hook(CallHook::ReturningFromHost));
+                caller.store.0.finish_host_call(result)
             })
         };
         let ctx = crate::trampoline::create_array_call_function(&ty, func)
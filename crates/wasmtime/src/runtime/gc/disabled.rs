@@ -11,6 +11,7 @@
 mod anyref;
 mod arrayref;
 mod eqref;
+mod exnref;
 mod externref;
 mod i31;
 mod rooting;
@@ -19,6 +20,7 @@ mod structref;
 pub use anyref::*;
 pub use arrayref::*;
 pub use eqref::*;
+pub use exnref::*;
 pub use externref::*;
 pub use i31::*;
 pub use rooting::*;
@@ -0,0 +1,50 @@
+use crate::runtime::vm::VMGcRef;
+use crate::{
+    store::{AutoAssertNoGc, StoreContextMut},
+    AsContextMut, GcRefImpl, Result, Rooted, Tag, Val,
+};
+
+/// Support for `exnref` disabled at compile time because the `gc` cargo
+/// feature was not enabled.
+pub enum ExnRef {}
+
+impl GcRefImpl for ExnRef {}
+
+impl ExnRef {
+    pub(crate) fn from_cloned_gc_ref(
+        _store: &mut AutoAssertNoGc<'_>,
+        _gc_ref: VMGcRef,
+    ) -> Rooted<Self> {
+        unreachable!()
+    }
+
+    pub fn tag(&self, _store: impl AsContextMut) -> Result<Tag> {
+        match *self {}
+    }
+
+    pub fn fields<'a, T: 'a>(
+        &self,
+        _store: impl Into<StoreContextMut<'a, T>>,
+    ) -> Result<impl ExactSizeIterator<Item = Val> + 'a> {
+        match *self {}
+        Ok([].into_iter())
+    }
+
+    pub fn field(&self, _store: impl AsContextMut, _index: usize) -> Result<Val> {
+        match *self {}
+    }
+
+    pub unsafe fn from_raw(_store: impl AsContextMut, raw: u32) -> Option<Rooted<Self>> {
+        assert_eq!(raw, 0);
+        None
+    }
+
+    pub(crate) fn _from_raw(_store: &mut AutoAssertNoGc<'_>, raw: u32) -> Option<Rooted<Self>> {
+        assert_eq!(raw, 0);
+        None
+    }
+
+    pub unsafe fn to_raw(&self, _store: impl AsContextMut) -> Result<u32> {
+        match *self {}
+    }
+}
@@ -4,6 +4,7 @@
 mod anyref;
 mod arrayref;
 mod eqref;
+mod exnref;
 mod externref;
 mod i31;
 mod rooting;
@@ -12,6 +13,7 @@ mod structref;
 pub use anyref::*;
 pub use arrayref::*;
 pub use eqref::*;
+pub use exnref::*;
 pub use externref::*;
 pub use i31::*;
 pub use rooting::*;
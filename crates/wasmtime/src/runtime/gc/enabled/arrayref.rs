@@ -729,7 +729,12 @@ unsafe impl WasmTy for Rooted<ArrayRef> {
             | HeapType::I31
             | HeapType::Struct
             | HeapType::ConcreteStruct(_)
-            | HeapType::None => bail!(
+            | HeapType::None
+            | HeapType::Exn
+            | HeapType::NoExn
+            | HeapType::Cont
+            | HeapType::ConcreteCont(_)
+            | HeapType::NoCont => bail!(
                 "type mismatch: expected `(ref {ty})`, got `(ref {})`",
                 self._ty(store)?,
             ),
@@ -823,7 +828,12 @@ unsafe impl WasmTy for ManuallyRooted<ArrayRef> {
             | HeapType::I31
             | HeapType::Struct
             | HeapType::ConcreteStruct(_)
-            | HeapType::None => bail!(
+            | HeapType::None
+            | HeapType::Exn
+            | HeapType::NoExn
+            | HeapType::Cont
+            | HeapType::ConcreteCont(_)
+            | HeapType::NoCont => bail!(
                 "type mismatch: expected `(ref {ty})`, got `(ref {})`",
                 self._ty(store)?,
             ),
@@ -0,0 +1,429 @@
+//! Working with Wasm exception objects.
+
+use crate::prelude::*;
+use crate::runtime::vm::{VMGcRef, VMTagDefinition};
+use crate::{
+    store::{AutoAssertNoGc, StoreOpaque},
+    AsContextMut, FuncType, GcRefImpl, GcRootIndex, HeapType, ManuallyRooted, RefType, Rooted,
+    StoreContextMut, StructRef, StructRefPre, Tag, Val, ValRaw, ValType, WasmTy,
+};
+use core::mem;
+use core::mem::MaybeUninit;
+
+/// A reference to a WebAssembly exception.
+///
+/// Exceptions are created by the `throw` instruction, or by the host with
+/// [`ExnRef::new`], and are caught by `try_table` handlers as `exnref`
+/// values. Every exception carries the [`Tag`] it was thrown with and the
+/// payload values described by that tag's type.
+///
+/// When Wasm code throws an exception that is not caught before it returns
+/// to the host, the call fails with a [`ThrownException`][crate::ThrownException]
+/// error and the exception remains pending in the store. It can be retrieved
+/// with [`Store::take_pending_exception`][crate::Store::take_pending_exception].
+///
+/// Like all WebAssembly references, these are opaque and unforgeable to Wasm.
+///
+/// # Example
+///
+/// ```
+/// use wasmtime::*;
+///
+/// # fn foo() -> Result<()> {
+/// let mut config = Config::new();
+/// config.wasm_exceptions(true);
+///
+/// let engine = Engine::new(&config)?;
+/// let mut store = Store::new(&engine, ());
+///
+/// let module = Module::new(
+///     &engine,
+///     r#"
+///         (module
+///             (tag (export "oops") (param i32))
+///             (func (export "run")
+///                 i32.const 42
+///                 throw 0
+///             )
+///         )
+///     "#,
+/// )?;
+/// let instance = Instance::new(&mut store, &module, &[])?;
+/// let run = instance.get_typed_func::<(), ()>(&mut store, "run")?;
+/// let oops = instance.get_tag(&mut store, "oops").unwrap();
+///
+/// let err = run.call(&mut store, ()).unwrap_err();
+/// assert!(err.is::<ThrownException>());
+///
+/// let exn = store.take_pending_exception().unwrap();
+/// assert!(Tag::eq(&exn.tag(&mut store)?, &oops, &store));
+/// assert_eq!(exn.field(&mut store, 0)?.unwrap_i32(), 42);
+/// # Ok(())
+/// # }
+/// # foo().unwrap();
+/// ```
+#[derive(Debug)]
+#[repr(transparent)]
+pub struct ExnRef {
+    pub(super) inner: GcRootIndex,
+}
+
+unsafe impl GcRefImpl for ExnRef {
+    #[allow(private_interfaces)]
+    fn transmute_ref(index: &GcRootIndex) -> &Self {
+        // Safety: `ExnRef` is a newtype of a `GcRootIndex`.
+        let me: &Self = unsafe { mem::transmute(index) };
+
+        // Assert we really are just a newtype of a `GcRootIndex`.
+        assert!(matches!(
+            me,
+            Self {
+                inner: GcRootIndex { .. },
+            }
+        ));
+
+        me
+    }
+}
+
+impl ExnRef {
+    /// Allocate a new exception with the given tag and payload values.
+    ///
+    /// The resulting exception may be thrown into Wasm from a host function
+    /// with [`Caller::throw`][crate::Caller::throw].
+    ///
+    /// # Errors
+    ///
+    /// Returns an error if the `fields` do not match the parameters of the
+    /// tag's type, or if the GC heap is out of memory.
+    ///
+    /// # Panics
+    ///
+    /// Panics if the tag or any of the field values is not associated with the
+    /// given store.
+    pub fn new(mut store: impl AsContextMut, tag: &Tag, fields: &[Val]) -> Result<Rooted<ExnRef>> {
+        let store = store.as_context_mut().0;
+        assert!(
+            tag.comes_from_same_store(store),
+            "tag used with the wrong store"
+        );
+        let ty = tag._ty(store);
+        let definition = tag.definition(store);
+        Self::_new(store, definition, ty.ty(), fields)
+    }
+
+    /// Allocate a new exception for the tag `definition`, whose signature is
+    /// `tag_ty`.
+    pub(crate) fn _new(
+        store: &mut StoreOpaque,
+        definition: *mut VMTagDefinition,
+        tag_ty: &FuncType,
+        fields: &[Val],
+    ) -> Result<Rooted<ExnRef>> {
+        let expected_len = tag_ty.params().len();
+        let actual_len = fields.len();
+        ensure!(
+            actual_len == expected_len,
+            "expected {expected_len} exception payload values, got {actual_len}"
+        );
+
+        // Exceptions are represented as structs whose first field is the
+        // address of the tag definition and whose remaining fields are the
+        // payload. The struct type is private to Wasmtime: Wasm can only ever
+        // see these objects as `exnref`s, so it can never access their fields
+        // directly.
+        //
+        // Our GC heaps do not support the 16-byte alignment that a `v128`
+        // field requires, so `v128` payload values are split into two `i64`
+        // fields, low half first.
+        let struct_ty = store.exn_struct_type(tag_ty)?;
+        let allocator = StructRefPre::_new(store, struct_ty);
+        let mut values = Vec::with_capacity(actual_len + 1);
+        values.push(Val::I64(definition as usize as i64));
+        for field in fields {
+            match field {
+                Val::V128(v) => {
+                    let bytes = v.as_u128().to_le_bytes();
+                    let (lo, hi) = bytes.split_at(8);
+                    values.push(Val::I64(i64::from_le_bytes(lo.try_into().unwrap())));
+                    values.push(Val::I64(i64::from_le_bytes(hi.try_into().unwrap())));
+                }
+                _ => values.push(*field),
+            }
+        }
+        let structref = StructRef::_new(store, &allocator, &values)?;
+        Ok(structref.unchecked_cast())
+    }
+
+    #[inline]
+    pub(crate) fn comes_from_same_store(&self, store: &StoreOpaque) -> bool {
+        self.inner.comes_from_same_store(store)
+    }
+
+    fn as_structref(&self) -> &StructRef {
+        <StructRef as GcRefImpl>::transmute_ref(&self.inner)
+    }
+
+    /// Get the tag that this exception was thrown with.
+    ///
+    /// # Errors
+    ///
+    /// Return an error if this reference has been unrooted.
+    ///
+    /// # Panics
+    ///
+    /// Panics if this reference is associated with a different store.
+    pub fn tag(&self, mut store: impl AsContextMut) -> Result<Tag> {
+        let store = store.as_context_mut().0;
+        let definition = self.tag_definition(store)?;
+        Ok(unsafe { Tag::from_definition(store, definition) })
+    }
+
+    pub(crate) fn tag_definition(&self, store: &mut StoreOpaque) -> Result<*mut VMTagDefinition> {
+        assert!(self.comes_from_same_store(store));
+        let mut store = AutoAssertNoGc::new(store);
+        let raw = self.as_structref()._field(&mut store, 0)?.unwrap_i64();
+        Ok(usize::try_from(raw as u64).unwrap() as *mut VMTagDefinition)
+    }
+
+    /// Get the values of this exception's payload.
+    ///
+    /// # Errors
+    ///
+    /// Return an error if this reference has been unrooted.
+    ///
+    /// # Panics
+    ///
+    /// Panics if this reference is associated with a different store.
+    pub fn fields<'a, T: 'a>(
+        &'a self,
+        store: impl Into<StoreContextMut<'a, T>>,
+    ) -> Result<impl ExactSizeIterator<Item = Val> + 'a> {
+        self._fields(store.into().0)
+    }
+
+    pub(crate) fn _fields<'a>(
+        &'a self,
+        store: &'a mut StoreOpaque,
+    ) -> Result<impl ExactSizeIterator<Item = Val> + 'a> {
+        assert!(self.comes_from_same_store(store));
+        let definition = self.tag_definition(store)?;
+        let tag_ty =
+            unsafe { FuncType::from_shared_type_index(store.engine(), (*definition).type_index) };
+        let mut raw = self.as_structref()._fields(store)?.skip(1);
+        let mut fields = Vec::with_capacity(tag_ty.params().len());
+        for ty in tag_ty.params() {
+            fields.push(match ty {
+                ValType::V128 => {
+                    let lo = raw.next().unwrap().unwrap_i64() as u64;
+                    let hi = raw.next().unwrap().unwrap_i64() as u64;
+                    Val::V128(((u128::from(hi) << 64) | u128::from(lo)).into())
+                }
+                _ => raw.next().unwrap(),
+            });
+        }
+        Ok(fields.into_iter())
+    }
+
+    /// Get this exception's `index`th payload value.
+    ///
+    /// # Errors
+    ///
+    /// Returns an `Err(_)` if the index is out of bounds or this reference has
+    /// been unrooted.
+    ///
+    /// # Panics
+    ///
+    /// Panics if this reference is associated with a different store.
+    pub fn field(&self, mut store: impl AsContextMut, index: usize) -> Result<Val> {
+        let mut fields = self._fields(store.as_context_mut().0)?;
+        let len = fields.len();
+        ensure!(
+            index < len,
+            "cannot access field {index}: exception only has {len} fields"
+        );
+        Ok(fields.nth(index).unwrap())
+    }
+
+    /// Creates a new strongly-owned [`ExnRef`] from the raw value provided.
+    ///
+    /// This is intended to be used in conjunction with [`Func::new_unchecked`],
+    /// [`Func::call_unchecked`], and [`ValRaw`] with its `exnref` field.
+    ///
+    /// # Unsafety
+    ///
+    /// This function has the same safety requirements as
+    /// [`AnyRef::from_raw`][crate::AnyRef::from_raw].
+    ///
+    /// [`Func::call_unchecked`]: crate::Func::call_unchecked
+    /// [`Func::new_unchecked`]: crate::Func::new_unchecked
+    /// [`ValRaw`]: crate::ValRaw
+    pub unsafe fn from_raw(mut store: impl AsContextMut, raw: u32) -> Option<Rooted<Self>> {
+        let mut store = AutoAssertNoGc::new(store.as_context_mut().0);
+        Self::_from_raw(&mut store, raw)
+    }
+
+    // (Not actually memory unsafe since we have indexed GC heaps.)
+    pub(crate) fn _from_raw(store: &mut AutoAssertNoGc, raw: u32) -> Option<Rooted<Self>> {
+        let gc_ref = VMGcRef::from_raw_u32(raw)?;
+        let gc_ref = store.unwrap_gc_store_mut().clone_gc_ref(&gc_ref);
+        Some(Self::from_cloned_gc_ref(store, gc_ref))
+    }
+
+    /// Create a new `Rooted<ExnRef>` from the given GC reference.
+    ///
+    /// `gc_ref` should point to a valid exception object and should belong to
+    /// the store's GC heap. Failure to uphold these invariants is memory safe
+    /// but will lead to general incorrectness such as panics or wrong results.
+    pub(crate) fn from_cloned_gc_ref(
+        store: &mut AutoAssertNoGc<'_>,
+        gc_ref: VMGcRef,
+    ) -> Rooted<Self> {
+        debug_assert!(gc_ref.is_structref(&*store.unwrap_gc_store().gc_heap));
+        Rooted::new(store, gc_ref)
+    }
+
+    /// Converts this [`ExnRef`] to a raw value suitable to store within a
+    /// [`ValRaw`].
+    ///
+    /// Returns an error if this `exnref` has been unrooted.
+    ///
+    /// # Unsafety
+    ///
+    /// Produces a raw value which is only safe to pass into a store if a GC
+    /// doesn't happen between when the value is produce and when it's passed
+    /// into the store.
+    ///
+    /// [`ValRaw`]: crate::ValRaw
+    pub unsafe fn to_raw(&self, mut store: impl AsContextMut) -> Result<u32> {
+        let mut store = AutoAssertNoGc::new(store.as_context_mut().0);
+        self._to_raw(&mut store)
+    }
+
+    pub(crate) unsafe fn _to_raw(&self, store: &mut AutoAssertNoGc<'_>) -> Result<u32> {
+        let gc_ref = self.inner.try_clone_gc_ref(store)?;
+        let raw = gc_ref.as_raw_u32();
+        store.gc_store_mut()?.expose_gc_ref_to_wasm(gc_ref);
+        Ok(raw)
+    }
+}
+
+unsafe impl WasmTy for Rooted<ExnRef> {
+    #[inline]
+    fn valtype() -> ValType {
+        ValType::Ref(RefType::new(false, HeapType::Exn))
+    }
+
+    #[inline]
+    fn compatible_with_store(&self, store: &StoreOpaque) -> bool {
+        self.comes_from_same_store(store)
+    }
+
+    #[inline]
+    fn dynamic_concrete_type_check(&self, _: &StoreOpaque, _: bool, _: &HeapType) -> Result<()> {
+        unreachable!()
+    }
+
+    fn store(self, store: &mut AutoAssertNoGc<'_>, ptr: &mut MaybeUninit<ValRaw>) -> Result<()> {
+        self.wasm_ty_store(store, ptr, ValRaw::exnref)
+    }
+
+    unsafe fn load(store: &mut AutoAssertNoGc<'_>, ptr: &ValRaw) -> Self {
+        Self::wasm_ty_load(store, ptr.get_exnref(), ExnRef::from_cloned_gc_ref)
+    }
+}
+
+unsafe impl WasmTy for Option<Rooted<ExnRef>> {
+    #[inline]
+    fn valtype() -> ValType {
+        ValType::EXNREF
+    }
+
+    #[inline]
+    fn compatible_with_store(&self, store: &StoreOpaque) -> bool {
+        self.map_or(true, |x| x.comes_from_same_store(store))
+    }
+
+    #[inline]
+    fn dynamic_concrete_type_check(&self, _: &StoreOpaque, _: bool, _: &HeapType) -> Result<()> {
+        unreachable!()
+    }
+
+    #[inline]
+    fn is_vmgcref_and_points_to_object(&self) -> bool {
+        self.is_some()
+    }
+
+    fn store(self, store: &mut AutoAssertNoGc<'_>, ptr: &mut MaybeUninit<ValRaw>) -> Result<()> {
+        <Rooted<ExnRef>>::wasm_ty_option_store(self, store, ptr, ValRaw::exnref)
+    }
+
+    unsafe fn load(store: &mut AutoAssertNoGc<'_>, ptr: &ValRaw) -> Self {
+        <Rooted<ExnRef>>::wasm_ty_option_load(store, ptr.get_exnref(), ExnRef::from_cloned_gc_ref)
+    }
+}
+
+unsafe impl WasmTy for ManuallyRooted<ExnRef> {
+    #[inline]
+    fn valtype() -> ValType {
+        ValType::Ref(RefType::new(false, HeapType::Exn))
+    }
+
+    #[inline]
+    fn compatible_with_store(&self, store: &StoreOpaque) -> bool {
+        self.comes_from_same_store(store)
+    }
+
+    #[inline]
+    fn dynamic_concrete_type_check(&self, _: &StoreOpaque, _: bool, _: &HeapType) -> Result<()> {
+        unreachable!()
+    }
+
+    #[inline]
+    fn is_vmgcref_and_points_to_object(&self) -> bool {
+        true
+    }
+
+    fn store(self, store: &mut AutoAssertNoGc<'_>, ptr: &mut MaybeUninit<ValRaw>) -> Result<()> {
+        self.wasm_ty_store(store, ptr, ValRaw::exnref)
+    }
+
+    unsafe fn load(store: &mut AutoAssertNoGc<'_>, ptr: &ValRaw) -> Self {
+        Self::wasm_ty_load(store, ptr.get_exnref(), ExnRef::from_cloned_gc_ref)
+    }
+}
+
+unsafe impl WasmTy for Option<ManuallyRooted<ExnRef>> {
+    #[inline]
+    fn valtype() -> ValType {
+        ValType::EXNREF
+    }
+
+    #[inline]
+    fn compatible_with_store(&self, store: &StoreOpaque) -> bool {
+        self.as_ref()
+            .map_or(true, |x| x.comes_from_same_store(store))
+    }
+
+    #[inline]
+    fn dynamic_concrete_type_check(&self, _: &StoreOpaque, _: bool, _: &HeapType) -> Result<()> {
+        unreachable!()
+    }
+
+    #[inline]
+    fn is_vmgcref_and_points_to_object(&self) -> bool {
+        self.is_some()
+    }
+
+    fn store(self, store: &mut AutoAssertNoGc<'_>, ptr: &mut MaybeUninit<ValRaw>) -> Result<()> {
+        <ManuallyRooted<ExnRef>>::wasm_ty_option_store(self, store, ptr, ValRaw::exnref)
+    }
+
+    unsafe fn load(store: &mut AutoAssertNoGc<'_>, ptr: &ValRaw) -> Self {
+        <ManuallyRooted<ExnRef>>::wasm_ty_option_load(
+            store,
+            ptr.get_exnref(),
+            ExnRef::from_cloned_gc_ref,
+        )
+    }
+}
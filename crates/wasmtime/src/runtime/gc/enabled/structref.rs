@@ -596,7 +596,12 @@ unsafe impl WasmTy for Rooted<StructRef> {
             | HeapType::I31
             | HeapType::Array
             | HeapType::ConcreteArray(_)
-            | HeapType::None => bail!(
+            | HeapType::None
+            | HeapType::Exn
+            | HeapType::NoExn
+            | HeapType::Cont
+            | HeapType::ConcreteCont(_)
+            | HeapType::NoCont => bail!(
                 "type mismatch: expected `(ref {ty})`, got `(ref {})`",
                 self._ty(store)?,
             ),
@@ -690,7 +695,12 @@ unsafe impl WasmTy for ManuallyRooted<StructRef> {
             | HeapType::I31
             | HeapType::Array
             | HeapType::ConcreteArray(_)
-            | HeapType::None => bail!(
+            | HeapType::None
+            | HeapType::Exn
+            | HeapType::NoExn
+            | HeapType::Cont
+            | HeapType::ConcreteCont(_)
+            | HeapType::NoCont => bail!(
                 "type mismatch: expected `(ref {ty})`, got `(ref {})`",
                 self._ty(store)?,
             ),
@@ -6,6 +6,8 @@ pub const DEFAULT_INSTANCE_LIMIT: usize = 10000;
 pub const DEFAULT_TABLE_LIMIT: usize = 10000;
 /// Value returned by [`ResourceLimiter::memories`] default method
 pub const DEFAULT_MEMORY_LIMIT: usize = 10000;
+/// Value returned by [`ResourceLimiter::continuations`] default method
+pub const DEFAULT_CONTINUATION_LIMIT: usize = 10000;
 
 /// Used by hosts to limit resource consumption of instances.
 ///
@@ -151,6 +153,18 @@ pub trait ResourceLimiter {
     fn memories(&self) -> usize {
         DEFAULT_MEMORY_LIMIT
     }
+
+    /// The maximum number of continuations, created by the stack-switching
+    /// proposal's `cont.new`, that can exist at once in a `Store`.
+    ///
+    /// Each continuation that has been resumed uses a fiber stack until it
+    /// finishes or becomes unreachable. Creating a continuation will trap if
+    /// this limit is exceeded.
+    ///
+    /// This value defaults to 10,000.
+    fn continuations(&self) -> usize {
+        DEFAULT_CONTINUATION_LIMIT
+    }
 }
 
 /// Used by hosts to limit resource consumption of instances, blocking
@@ -215,6 +229,11 @@ pub trait ResourceLimiterAsync {
     fn memories(&self) -> usize {
         DEFAULT_MEMORY_LIMIT
     }
+
+    /// Identical to [`ResourceLimiter::continuations`]`
+    fn continuations(&self) -> usize {
+        DEFAULT_CONTINUATION_LIMIT
+    }
 }
 
 /// Used to build [`StoreLimits`].
@@ -284,6 +303,17 @@ impl StoreLimitsBuilder {
         self
     }
 
+    /// The maximum number of continuations that can exist at once in a
+    /// [`Store`](crate::Store).
+    ///
+    /// Creating a continuation will trap if this limit is exceeded.
+    ///
+    /// This value defaults to 10,000.
+    pub fn continuations(mut self, continuations: usize) -> Self {
+        self.0.continuations = continuations;
+        self
+    }
+
     /// Indicates that a trap should be raised whenever a growth operation
     /// would fail.
     ///
@@ -319,6 +349,7 @@ pub struct StoreLimits {
     instances: usize,
     tables: usize,
     memories: usize,
+    continuations: usize,
     trap_on_grow_failure: bool,
 }
 
@@ -330,6 +361,7 @@ impl Default for StoreLimits {
             instances: DEFAULT_INSTANCE_LIMIT,
             tables: DEFAULT_TABLE_LIMIT,
             memories: DEFAULT_MEMORY_LIMIT,
+            continuations: DEFAULT_CONTINUATION_LIMIT,
             trap_on_grow_failure: false,
         }
     }
@@ -405,4 +437,8 @@ impl ResourceLimiter for StoreLimits {
     fn memories(&self) -> usize {
         self.memories
     }
+
+    fn continuations(&self) -> usize {
+        self.continuations
+    }
 }
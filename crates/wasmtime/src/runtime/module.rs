@@ -1091,6 +1091,18 @@ impl Module {
 
         Some(&info.stack_maps[index].stack_map)
     }
+
+    /// Lookup where the function containing a program counter value records
+    /// its Wasm-level frame state, if it does.
+    #[cfg(feature = "coredump")]
+    pub(crate) fn lookup_frame_state(
+        &self,
+        pc: usize,
+    ) -> Option<&wasmtime_environ::FrameStateInfo> {
+        let text_offset = pc - self.inner.module.text().as_ptr() as usize;
+        let (index, _) = self.inner.module.func_by_text_offset(text_offset)?;
+        self.inner.module.wasm_func_info(index).frame_state.as_ref()
+    }
 }
 
 /// Describes a function for a given module.
@@ -88,10 +88,12 @@ use crate::runtime::vm::{
     OnDemandInstanceAllocator, SignalHandler, StoreBox, StorePtr, VMContext, VMFuncRef, VMGcRef,
     VMRuntimeLimits, WasmFault,
 };
-use crate::trampoline::VMHostGlobalContext;
+use crate::trampoline::{VMHostGlobalContext, VMHostTagContext};
 use crate::type_registry::RegisteredType;
 use crate::RootSet;
 use crate::{module::ModuleRegistry, Engine, Module, Trap, Val, ValRaw};
+#[cfg(feature = "gc")]
+use crate::{ExnRef, Rooted, RootedGcRefImpl, StructType, ThrownException};
 use crate::{Global, Instance, Memory, RootScope, Table, Uninhabited};
 use alloc::sync::Arc;
 use core::cell::UnsafeCell;
@@ -104,6 +106,8 @@ use core::ops::{Deref, DerefMut, Range};
 use core::pin::Pin;
 use core::ptr;
 use core::task::{Context, Poll};
+#[cfg(feature = "gc")]
+use wasmtime_environ::VMSharedTypeIndex;
 
 mod context;
 pub use self::context::*;
@@ -319,6 +323,7 @@ pub struct StoreOpaque {
     modules: ModuleRegistry,
     func_refs: FuncRefs,
     host_globals: Vec<StoreBox<VMHostGlobalContext>>,
+    host_tags: Vec<StoreBox<VMHostTagContext>>,
 
     // GC-related fields.
     gc_store: Option<GcStore>,
@@ -326,6 +331,10 @@ pub struct StoreOpaque {
     gc_roots_list: GcRootsList,
     // Types for which the embedder has created an allocator for.
     gc_host_alloc_types: HashSet<RegisteredType>,
+    // The struct types used to represent exception objects, keyed by the
+    // signature of the exception's tag.
+    #[cfg(feature = "gc")]
+    exn_struct_types: crate::hash_map::HashMap<VMSharedTypeIndex, StructType>,
 
     // Numbers of resources instantiated in this store, and their limits
     instance_count: usize,
@@ -336,6 +345,9 @@ pub struct StoreOpaque {
     table_limit: usize,
     #[cfg(feature = "async")]
     async_state: AsyncState,
+    // The continuations created by the stack-switching proposal's `cont.new`.
+    #[cfg(feature = "stack-switching")]
+    continuations: crate::runtime::vm::Continuations,
     // If fuel_yield_interval is enabled, then we store the remaining fuel (that isn't in
     // runtime_limits) here. The total amount of fuel is the runtime limits and reserve added
     // together. Then when we run out of gas, we inject the yield amount from the reserve
@@ -544,9 +556,12 @@ impl<T> Store<T> {
                 gc_roots: RootSet::default(),
                 gc_roots_list: GcRootsList::default(),
                 gc_host_alloc_types: HashSet::default(),
+                #[cfg(feature = "gc")]
+                exn_struct_types: Default::default(),
                 modules: ModuleRegistry::default(),
                 func_refs: FuncRefs::default(),
                 host_globals: Vec::new(),
+                host_tags: Vec::new(),
                 instance_count: 0,
                 instance_limit: crate::DEFAULT_INSTANCE_LIMIT,
                 memory_count: 0,
@@ -558,6 +573,8 @@ impl<T> Store<T> {
                     current_suspend: UnsafeCell::new(ptr::null_mut()),
                     current_poll_cx: UnsafeCell::new(PollContext::default()),
                 },
+                #[cfg(feature = "stack-switching")]
+                continuations: crate::runtime::vm::Continuations::new(),
                 fuel_reserve: 0,
                 fuel_yield_interval: None,
                 store_data: ManuallyDrop::new(StoreData::new()),
@@ -725,16 +742,19 @@ impl<T> Store<T> {
         &mut self,
         mut limiter: impl FnMut(&mut T) -> &mut (dyn crate::ResourceLimiter) + Send + Sync + 'static,
     ) {
-        // Apply the limits on instances, tables, and memory given by the limiter:
+        // Apply the limits on instances, tables, memory, and continuations given
+        // by the limiter:
         let inner = &mut self.inner;
-        let (instance_limit, table_limit, memory_limit) = {
+        let (instance_limit, table_limit, memory_limit, _continuation_limit) = {
             let l = limiter(&mut inner.data);
-            (l.instances(), l.tables(), l.memories())
+            (l.instances(), l.tables(), l.memories(), l.continuations())
         };
         let innermost = &mut inner.inner;
         innermost.instance_limit = instance_limit;
         innermost.table_limit = table_limit;
         innermost.memory_limit = memory_limit;
+        #[cfg(feature = "stack-switching")]
+        innermost.continuations.set_limit(_continuation_limit);
 
         // Save the limiter accessor function:
         inner.limiter = Some(ResourceLimiterInner::Sync(Box::new(limiter)));
@@ -772,16 +792,19 @@ impl<T> Store<T> {
             + 'static,
     ) {
         debug_assert!(self.inner.async_support());
-        // Apply the limits on instances, tables, and memory given by the limiter:
+        // Apply the limits on instances, tables, memory, and continuations given
+        // by the limiter:
         let inner = &mut self.inner;
-        let (instance_limit, table_limit, memory_limit) = {
+        let (instance_limit, table_limit, memory_limit, _continuation_limit) = {
             let l = limiter(&mut inner.data);
-            (l.instances(), l.tables(), l.memories())
+            (l.instances(), l.tables(), l.memories(), l.continuations())
         };
         let innermost = &mut inner.inner;
         innermost.instance_limit = instance_limit;
         innermost.table_limit = table_limit;
         innermost.memory_limit = memory_limit;
+        #[cfg(feature = "stack-switching")]
+        innermost.continuations.set_limit(_continuation_limit);
 
         // Save the limiter accessor function:
         inner.limiter = Some(ResourceLimiterInner::Async(Box::new(limiter)));
@@ -861,6 +884,40 @@ impl<T> Store<T> {
         self.inner.gc_async().await;
     }
 
+    /// Throw the exception `exn` into the Wasm code that called the host.
+    ///
+    /// This is intended to be used from host functions: the returned error
+    /// should be propagated as the host function's result, at which point the
+    /// exception will be dispatched to the handlers of the calling Wasm code.
+    /// If no handler catches it, then the call into Wasm fails with a
+    /// [`ThrownException`](crate::ThrownException) error and the exception
+    /// remains pending in this store.
+    ///
+    /// This method always returns `Err`.
+    ///
+    /// This method is only available when the `gc` Cargo feature is enabled.
+    ///
+    /// # Panics
+    ///
+    /// Panics if `exn` is unrooted or belongs to a different store.
+    #[cfg(feature = "gc")]
+    pub fn throw<R>(&mut self, exn: Rooted<ExnRef>) -> Result<R, ThrownException> {
+        self.inner.throw_impl(exn);
+        Err(ThrownException)
+    }
+
+    /// Take the exception that was thrown by Wasm code and not caught, if any.
+    ///
+    /// When a call into Wasm returns an error whose root cause is
+    /// [`ThrownException`](crate::ThrownException), this returns the uncaught
+    /// exception. The pending exception is discarded when Wasm is next called.
+    ///
+    /// This method is only available when the `gc` Cargo feature is enabled.
+    #[cfg(feature = "gc")]
+    pub fn take_pending_exception(&mut self) -> Option<Rooted<ExnRef>> {
+        self.inner.take_pending_exception_rooted()
+    }
+
     /// Returns the amount fuel in this [`Store`]. When fuel is enabled, it must
     /// be configured via [`Store::set_fuel`].
     ///
@@ -1118,6 +1175,27 @@ impl<'a, T> StoreContextMut<'a, T> {
         self.0.gc_async().await;
     }
 
+    /// Throw the exception `exn` into the Wasm code that called the host.
+    ///
+    /// Same as [`Store::throw`].
+    ///
+    /// This method is only available when the `gc` Cargo feature is enabled.
+    #[cfg(feature = "gc")]
+    pub fn throw<R>(&mut self, exn: Rooted<ExnRef>) -> Result<R, ThrownException> {
+        self.0.throw_impl(exn);
+        Err(ThrownException)
+    }
+
+    /// Take the exception that was thrown by Wasm code and not caught, if any.
+    ///
+    /// Same as [`Store::take_pending_exception`].
+    ///
+    /// This method is only available when the `gc` Cargo feature is enabled.
+    #[cfg(feature = "gc")]
+    pub fn take_pending_exception(&mut self) -> Option<Rooted<ExnRef>> {
+        self.0.take_pending_exception_rooted()
+    }
+
     /// Returns remaining fuel in this store.
     ///
     /// For more information see [`Store::get_fuel`]
@@ -1353,6 +1431,10 @@ impl StoreOpaque {
         &mut self.host_globals
     }
 
+    pub(crate) fn host_tags(&mut self) -> &mut Vec<StoreBox<VMHostTagContext>> {
+        &mut self.host_tags
+    }
+
     pub fn module_for_instance(&self, instance: InstanceId) -> Option<&'_ Module> {
         match self.instances[instance.0].kind {
             StoreInstanceKind::Dummy => None,
@@ -1541,7 +1623,7 @@ impl StoreOpaque {
             let (index, heap) = if engine.features().gc_types() {
                 engine
                     .allocator()
-                    .allocate_gc_heap(&**engine.gc_runtime())?
+                    .allocate_gc_heap(engine, &**engine.gc_runtime())?
             } else {
                 (
                     GcHeapAllocationIndex::default(),
@@ -1650,6 +1732,7 @@ impl StoreOpaque {
         self.trace_wasm_stack_roots(gc_roots_list);
         self.trace_vmctx_roots(gc_roots_list);
         self.trace_user_roots(gc_roots_list);
+        self.trace_pending_exception_root(gc_roots_list);
 
         log::trace!("End trace GC roots")
     }
@@ -1708,63 +1791,80 @@ impl StoreOpaque {
         self.trace_vmctx_roots(gc_roots_list);
         Yield::new().await;
         self.trace_user_roots(gc_roots_list);
+        Yield::new().await;
+        self.trace_pending_exception_root(gc_roots_list);
 
         log::trace!("End trace GC roots")
     }
 
     #[cfg(feature = "gc")]
     fn trace_wasm_stack_roots(&mut self, gc_roots_list: &mut GcRootsList) {
-        use crate::runtime::vm::SendSyncPtr;
-        use core::ptr::NonNull;
-
         log::trace!("Begin trace GC roots :: Wasm stack");
 
         Backtrace::trace(self.vmruntime_limits().cast_const(), |frame| {
-            let pc = frame.pc();
-            debug_assert!(pc != 0, "we should always get a valid PC for Wasm frames");
+            self.trace_wasm_frame_roots(frame, gc_roots_list);
+            core::ops::ControlFlow::Continue(())
+        });
 
-            let fp = frame.fp() as *mut usize;
-            debug_assert!(
-                !fp.is_null(),
-                "we should always get a valid frame pointer for Wasm frames"
-            );
+        // Suspended continuations' frames aren't on the current stack, but
+        // they may still hold GC references.
+        #[cfg(feature = "stack-switching")]
+        self.continuations.trace_suspended(|frame| {
+            self.trace_wasm_frame_roots(frame, gc_roots_list);
+            core::ops::ControlFlow::Continue(())
+        });
 
-            let module_info = self
-                .modules()
-                .lookup_module_by_pc(pc)
-                .expect("should have module info for Wasm frame");
+        log::trace!("End trace GC roots :: Wasm stack");
+    }
 
-            let stack_map = match module_info.lookup_stack_map(pc) {
-                Some(sm) => sm,
-                None => {
-                    log::trace!("No stack map for this Wasm frame");
-                    return core::ops::ControlFlow::Continue(());
-                }
-            };
-            log::trace!(
-                "We have a stack map that maps {} bytes in this Wasm frame",
-                stack_map.frame_size()
-            );
+    #[cfg(feature = "gc")]
+    fn trace_wasm_frame_roots(
+        &self,
+        frame: crate::runtime::vm::Frame,
+        gc_roots_list: &mut GcRootsList,
+    ) {
+        use crate::runtime::vm::SendSyncPtr;
+        use core::ptr::NonNull;
 
-            let sp = unsafe { stack_map.sp(fp) };
-            for stack_slot in unsafe { stack_map.live_gc_refs(sp) } {
-                let raw: u32 = unsafe { core::ptr::read(stack_slot) };
-                log::trace!("Stack slot @ {stack_slot:p} = {raw:#x}");
-
-                let gc_ref = VMGcRef::from_raw_u32(raw);
-                if gc_ref.is_some() {
-                    unsafe {
-                        gc_roots_list.add_wasm_stack_root(SendSyncPtr::new(
-                            NonNull::new(stack_slot).unwrap(),
-                        ));
-                    }
-                }
+        let pc = frame.pc();
+        debug_assert!(pc != 0, "we should always get a valid PC for Wasm frames");
+
+        let fp = frame.fp() as *mut usize;
+        debug_assert!(
+            !fp.is_null(),
+            "we should always get a valid frame pointer for Wasm frames"
+        );
+
+        let module_info = self
+            .modules()
+            .lookup_module_by_pc(pc)
+            .expect("should have module info for Wasm frame");
+
+        let stack_map = match module_info.lookup_stack_map(pc) {
+            Some(sm) => sm,
+            None => {
+                log::trace!("No stack map for this Wasm frame");
+                return;
             }
+        };
+        log::trace!(
+            "We have a stack map that maps {} bytes in this Wasm frame",
+            stack_map.frame_size()
+        );
 
-            core::ops::ControlFlow::Continue(())
-        });
+        let sp = unsafe { stack_map.sp(fp) };
+        for stack_slot in unsafe { stack_map.live_gc_refs(sp) } {
+            let raw: u32 = unsafe { core::ptr::read(stack_slot) };
+            log::trace!("Stack slot @ {stack_slot:p} = {raw:#x}");
 
-        log::trace!("End trace GC roots :: Wasm stack");
+            let gc_ref = VMGcRef::from_raw_u32(raw);
+            if gc_ref.is_some() {
+                unsafe {
+                    gc_roots_list
+                        .add_wasm_stack_root(SendSyncPtr::new(NonNull::new(stack_slot).unwrap()));
+                }
+            }
+        }
     }
 
     #[cfg(feature = "gc")]
@@ -1782,6 +1882,156 @@ impl StoreOpaque {
         log::trace!("End trace GC roots :: user");
     }
 
+    #[cfg(feature = "gc")]
+    fn trace_pending_exception_root(&mut self, gc_roots_list: &mut GcRootsList) {
+        use crate::runtime::vm::SendSyncPtr;
+        use core::ptr::NonNull;
+
+        log::trace!("Begin trace GC roots :: pending exception");
+        let slot = self.pending_exception_slot();
+        if unsafe { (*slot).is_some() } {
+            unsafe {
+                gc_roots_list.add_root(
+                    SendSyncPtr::new(NonNull::new(slot.cast::<VMGcRef>()).unwrap()),
+                    "pending exception",
+                );
+            }
+        }
+        log::trace!("End trace GC roots :: pending exception");
+    }
+
+    /// Get the struct type used to represent exceptions whose tag has the
+    /// signature `tag_ty`.
+    ///
+    /// The first field of these structs is the address of the exception's
+    /// `VMTagDefinition` and the rest are the exception's payload.
+    #[cfg(feature = "gc")]
+    pub(crate) fn exn_struct_type(&mut self, tag_ty: &crate::FuncType) -> Result<StructType> {
+        use crate::{FieldType, Mutability, StorageType, ValType};
+
+        let index = tag_ty.type_index();
+        if let Some(ty) = self.exn_struct_types.get(&index) {
+            return Ok(ty.clone());
+        }
+
+        // See `ExnRef::_new` for details on this representation.
+        let mut field_tys = vec![ValType::I64];
+        for ty in tag_ty.params() {
+            match ty {
+                ValType::V128 => field_tys.extend([ValType::I64, ValType::I64]),
+                ty => field_tys.push(ty),
+            }
+        }
+        let fields = field_tys
+            .into_iter()
+            .map(|ty| FieldType::new(Mutability::Const, StorageType::ValType(ty)));
+        let ty = StructType::new(self.engine(), fields)?;
+        self.exn_struct_types.insert(index, ty.clone());
+        Ok(ty)
+    }
+
+    #[cfg(feature = "gc")]
+    fn pending_exception_slot(&self) -> *mut Option<VMGcRef> {
+        self.runtime_limits.pending_exception.get().cast()
+    }
+
+    /// Is there an exception that has been thrown but not yet caught?
+    #[cfg(feature = "gc")]
+    pub(crate) fn has_pending_exception(&self) -> bool {
+        unsafe { (*self.pending_exception_slot()).is_some() }
+    }
+
+    /// Set the pending exception, which Wasm will dispatch to its handlers
+    /// once control returns to it.
+    ///
+    /// Takes ownership of `exn`.
+    #[cfg(feature = "gc")]
+    pub(crate) fn set_pending_exception(&mut self, exn: VMGcRef) {
+        let slot = self.pending_exception_slot();
+        let gc_store = self.unwrap_gc_store_mut();
+        unsafe {
+            gc_store.write_gc_ref(&mut *slot, Some(&exn));
+        }
+        gc_store.drop_gc_ref(exn);
+    }
+
+    /// Take the pending exception, if any, transferring ownership of its GC
+    /// reference to the caller.
+    #[cfg(feature = "gc")]
+    pub(crate) fn take_pending_exception_gc_ref(&mut self) -> Option<VMGcRef> {
+        unsafe { (*self.pending_exception_slot()).take() }
+    }
+
+    #[cfg(feature = "gc")]
+    fn throw_impl(&mut self, exn: Rooted<ExnRef>) {
+        let mut store = AutoAssertNoGc::new(self);
+        assert!(
+            exn.comes_from_same_store(&store),
+            "exception used with the wrong store"
+        );
+        let gc_ref = exn
+            .try_clone_gc_ref(&mut store)
+            .expect("cannot throw an unrooted exception");
+        store.set_pending_exception(gc_ref);
+    }
+
+    /// Get a new root for the pending exception, if any, leaving it pending.
+    #[cfg(feature = "gc")]
+    pub(crate) fn pending_exception(&mut self) -> Option<Rooted<ExnRef>> {
+        let gc_ref = unsafe { (*self.pending_exception_slot()).as_ref()? };
+        let mut store = AutoAssertNoGc::new(self);
+        let gc_ref = store.unwrap_gc_store_mut().clone_gc_ref(gc_ref);
+        Some(ExnRef::from_cloned_gc_ref(&mut store, gc_ref))
+    }
+
+    #[cfg(feature = "gc")]
+    fn take_pending_exception_rooted(&mut self) -> Option<Rooted<ExnRef>> {
+        let gc_ref = self.take_pending_exception_gc_ref()?;
+        let mut store = AutoAssertNoGc::new(self);
+        Some(ExnRef::from_cloned_gc_ref(&mut store, gc_ref))
+    }
+
+    /// Discard the pending exception, if any.
+    #[cfg(feature = "gc")]
+    pub(crate) fn clear_pending_exception(&mut self) {
+        if let Some(exn) = self.take_pending_exception_gc_ref() {
+            self.unwrap_gc_store_mut().drop_gc_ref(exn);
+        }
+    }
+
+    /// Finish a call from Wasm into the host, which returned `result`.
+    ///
+    /// If the host function threw an exception with
+    /// [`Caller::throw`][crate::Caller::throw], then the `ThrownException`
+    /// error is passed on, and raising it unwinds the stack to the innermost
+    /// Wasm exception handler with the exception still pending.
+    #[cfg(feature = "gc")]
+    pub(crate) fn finish_host_call(&mut self, result: Result<()>) -> Result<()> {
+        match result {
+            Ok(()) => {
+                self.clear_pending_exception();
+                Ok(())
+            }
+            Err(e) if e.is::<crate::ThrownException>() => {
+                if self.has_pending_exception() {
+                    Err(e)
+                } else {
+                    Err(e.context("host function returned `ThrownException` without throwing"))
+                }
+            }
+            Err(e) => {
+                self.clear_pending_exception();
+                Err(e)
+            }
+        }
+    }
+
+    #[cfg(not(feature = "gc"))]
+    #[inline]
+    pub(crate) fn finish_host_call(&mut self, result: Result<()>) -> Result<()> {
+        result
+    }
+
     /// Insert a host-allocated GC type into this store.
     ///
     /// This makes it suitable for the embedder to allocate instances of this
@@ -1904,6 +2154,12 @@ impl StoreOpaque {
         &self.runtime_limits as *const VMRuntimeLimits as *mut VMRuntimeLimits
     }
 
+    #[inline]
+    #[cfg(feature = "stack-switching")]
+    pub(crate) fn continuations_mut(&mut self) -> &mut crate::runtime::vm::Continuations {
+        &mut self.continuations
+    }
+
     #[inline]
     pub fn default_caller(&self) -> *mut VMContext {
         self.default_caller.vmctx()
@@ -2744,6 +3000,13 @@ impl Drop for StoreOpaque {
         // NB it's important that this destructor does not access `self.data`.
         // That is deallocated by `Drop for Store<T>` above.
 
+        // Unwind any suspended continuations before the instances that their
+        // stacks refer to are deallocated.
+        #[cfg(feature = "stack-switching")]
+        unsafe {
+            crate::runtime::vm::Continuations::cancel_all(self);
+        }
+
         unsafe {
             let allocator = self.engine.allocator();
             let ondemand = OnDemandInstanceAllocator::default();
@@ -4,9 +4,11 @@ mod func;
 mod global;
 mod memory;
 mod table;
+mod tag;
 
 pub use self::func::*;
 pub use self::global::*;
+pub use self::tag::*;
 pub(crate) use memory::MemoryCreatorProxy;
 
 use self::memory::create_memory;
@@ -57,6 +57,15 @@ pub fn generate_global_export(
                 let new = new.as_ref();
                 global.write_gc_ref(store.gc_store_mut()?, new);
             }
+            Val::ExnRef(e) => {
+                let new = match e {
+                    None => None,
+                    #[cfg_attr(not(feature = "gc"), allow(unreachable_patterns))]
+                    Some(e) => Some(e.try_gc_ref(&store)?.unchecked_copy()),
+                };
+                let new = new.as_ref();
+                global.write_gc_ref(store.gc_store_mut()?, new);
+            }
         }
         global
     };
@@ -279,6 +279,7 @@ unsafe impl InstanceAllocatorImpl for SingleMemoryInstance<'_> {
     #[cfg(feature = "gc")]
     fn allocate_gc_heap(
         &self,
+        _engine: &crate::Engine,
         _gc_runtime: &dyn crate::runtime::vm::GcRuntime,
     ) -> Result<(GcHeapAllocationIndex, Box<dyn crate::runtime::vm::GcHeap>)> {
         unreachable!()
@@ -0,0 +1,25 @@
+use crate::runtime::vm::{StoreBox, VMTagDefinition};
+use crate::store::StoreOpaque;
+use crate::TagType;
+use core::ptr;
+
+#[repr(C)]
+pub struct VMHostTagContext {
+    pub(crate) ty: TagType,
+    pub(crate) tag: VMTagDefinition,
+}
+
+pub fn generate_tag_export(store: &mut StoreOpaque, ty: &TagType) -> crate::runtime::vm::ExportTag {
+    let type_index = ty.ty().type_index();
+    let ctx = StoreBox::new(VMHostTagContext {
+        ty: ty.clone(),
+        tag: VMTagDefinition::new(type_index),
+    });
+    let definition = unsafe { ptr::addr_of_mut!((*ctx.get()).tag) };
+    store.host_tags().push(ctx);
+    crate::runtime::vm::ExportTag {
+        definition,
+        vmctx: ptr::null_mut(),
+        tag: ty.to_wasm_type(),
+    }
+}
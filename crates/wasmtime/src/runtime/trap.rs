@@ -68,6 +68,28 @@ use wasmtime_environ::{demangle_function_name, demangle_function_name_or_index,
 /// ```
 pub use wasmtime_environ::Trap;
 
+/// The error returned when a WebAssembly exception is thrown and not caught.
+///
+/// When a call into Wasm, such as [`Func::call`](crate::Func::call), returns
+/// an error whose root cause is a `ThrownException`, the uncaught exception
+/// remains pending in the store and can be inspected with
+/// [`Store::take_pending_exception`](crate::Store::take_pending_exception).
+///
+/// Host functions may throw exceptions into their Wasm caller with
+/// [`Caller::throw`](crate::Caller::throw), which returns this error for the
+/// host function to propagate.
+#[derive(Debug)]
+pub struct ThrownException;
+
+impl fmt::Display for ThrownException {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        write!(f, "thrown Wasm exception")
+    }
+}
+
+#[cfg(feature = "std")]
+impl std::error::Error for ThrownException {}
+
 // Same safety requirements and caveats as
 // `crate::runtime::vm::raise_user_trap`.
 pub(crate) unsafe fn raise(error: anyhow::Error) -> ! {
@@ -137,8 +159,7 @@ pub(crate) fn from_runtime_box(
     let _ = &coredumpstack;
     #[cfg(feature = "coredump")]
     if let Some(coredump) = coredumpstack {
-        let bt = WasmBacktrace::from_captured(store, coredump.bt, pc);
-        let cd = WasmCoreDump::new(store, bt);
+        let cd = WasmCoreDump::new(store, coredump, pc);
         error = error.context(cd);
     }
 
@@ -279,7 +300,26 @@ impl WasmBacktrace {
         )
     }
 
-    fn from_captured(
+    /// Returns the program counter to look up information about `frame` with.
+    pub(crate) fn pc_to_lookup(frame: &crate::runtime::vm::Frame, trap_pc: Option<usize>) -> usize {
+        // Note that we need to be careful about the pc we pass in
+        // here to lookup frame information. This program counter is
+        // used to translate back to an original source location in
+        // the origin wasm module. If this pc is the exact pc that
+        // the trap happened at, then we look up that pc precisely.
+        // Otherwise backtrace information typically points at the
+        // pc *after* the call instruction (because otherwise it's
+        // likely a call instruction on the stack). In that case we
+        // want to lookup information for the previous instruction
+        // (the call instruction) so we subtract one as the lookup.
+        if Some(frame.pc()) == trap_pc {
+            frame.pc()
+        } else {
+            frame.pc() - 1
+        }
+    }
+
+    pub(crate) fn from_captured(
         store: &StoreOpaque,
         runtime_trace: crate::runtime::vm::Backtrace,
         trap_pc: Option<usize>,
@@ -292,21 +332,7 @@ impl WasmBacktrace {
         for frame in runtime_trace.frames() {
             debug_assert!(frame.pc() != 0);
 
-            // Note that we need to be careful about the pc we pass in
-            // here to lookup frame information. This program counter is
-            // used to translate back to an original source location in
-            // the origin wasm module. If this pc is the exact pc that
-            // the trap happened at, then we look up that pc precisely.
-            // Otherwise backtrace information typically points at the
-            // pc *after* the call instruction (because otherwise it's
-            // likely a call instruction on the stack). In that case we
-            // want to lookup information for the previous instruction
-            // (the call instruction) so we subtract one as the lookup.
-            let pc_to_lookup = if Some(frame.pc()) == trap_pc {
-                frame.pc()
-            } else {
-                frame.pc() - 1
-            };
+            let pc_to_lookup = Self::pc_to_lookup(frame, trap_pc);
 
             // NB: The PC we are looking up _must_ be a Wasm PC since
             // `crate::runtime::vm::Backtrace` only contains Wasm frames.
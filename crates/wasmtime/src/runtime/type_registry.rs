@@ -807,7 +807,8 @@ impl TypeRegistryInner {
         );
 
         let gc_layout = match &ty.composite_type {
-            wasmtime_environ::WasmCompositeType::Func(_) => None,
+            wasmtime_environ::WasmCompositeType::Func(_)
+            | wasmtime_environ::WasmCompositeType::Cont(_) => None,
             wasmtime_environ::WasmCompositeType::Array(a) => {
                 Some(gc_runtime.layouts().array_layout(a).into())
             }
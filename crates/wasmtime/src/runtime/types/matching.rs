@@ -33,6 +33,29 @@ impl MatchCx<'_> {
         Err(concrete_type_mismatch(msg, &expected, &actual))
     }
 
+    fn tag_type(&self, expected: VMSharedTypeIndex, actual: VMSharedTypeIndex) -> Result<()> {
+        // Tag signatures are invariant, so require exactly the same type
+        // rather than a subtype.
+        if expected == actual {
+            return Ok(());
+        }
+
+        let expected = match self.engine.signatures().borrow(expected) {
+            Some(ty) => ty,
+            None => panic!("{expected:?} is not registered"),
+        };
+        let actual = match self.engine.signatures().borrow(actual) {
+            Some(ty) => ty,
+            None => panic!("{actual:?} is not registered"),
+        };
+
+        Err(concrete_type_mismatch(
+            "tag types incompatible",
+            &expected,
+            &actual,
+        ))
+    }
+
     /// Validates that the `expected` type matches the type of `actual`
     pub(crate) fn definition(&self, expected: &EntityType, actual: &DefinitionType) -> Result<()> {
         match expected {
@@ -58,7 +81,12 @@ impl MatchCx<'_> {
                 }
                 _ => bail!("expected func, but found {}", actual.desc()),
             },
-            EntityType::Tag(_) => unimplemented!(),
+            EntityType::Tag(expected) => match actual {
+                DefinitionType::Tag(actual) => {
+                    self.tag_type(expected.signature.unwrap_engine_type_index(), *actual)
+                }
+                _ => bail!("expected tag, but found {}", actual.desc()),
+            },
         }
     }
 }
@@ -99,7 +127,22 @@ pub fn entity_ty(
             }
             _ => bail!("expected func found {}", entity_desc(actual)),
         },
-        EntityType::Tag(_) => unimplemented!(),
+        EntityType::Tag(expected) => match actual {
+            EntityType::Tag(actual) => {
+                let expected = &expected_types[expected.signature.unwrap_module_type_index()];
+                let actual = &actual_types[actual.signature.unwrap_module_type_index()];
+                if expected == actual {
+                    Ok(())
+                } else {
+                    Err(concrete_type_mismatch(
+                        "tag types incompatible",
+                        expected,
+                        actual,
+                    ))
+                }
+            }
+            _ => bail!("expected tag found {}", entity_desc(actual)),
+        },
     }
 }
 
@@ -182,6 +225,7 @@ fn match_heap(expected: WasmHeapType, actual: WasmHeapType, desc: &str) -> Resul
         (H::ConcreteArray(actual), H::ConcreteArray(expected)) => actual == expected,
         (H::ConcreteFunc(actual), H::ConcreteFunc(expected)) => actual == expected,
         (H::ConcreteStruct(actual), H::ConcreteStruct(expected)) => actual == expected,
+        (H::ConcreteCont(actual), H::ConcreteCont(expected)) => actual == expected,
 
         (H::NoFunc, H::NoFunc) => true,
         (_, H::NoFunc) => false,
@@ -240,6 +284,21 @@ fn match_heap(expected: WasmHeapType, actual: WasmHeapType, desc: &str) -> Resul
 
         (H::None, H::None) => true,
         (_, H::None) => false,
+
+        (H::Exn | H::NoExn, H::Exn) => true,
+        (_, H::Exn) => false,
+
+        (H::NoExn, H::NoExn) => true,
+        (_, H::NoExn) => false,
+
+        (H::NoCont, H::ConcreteCont(_)) => true,
+        (_, H::ConcreteCont(_)) => false,
+
+        (H::Cont | H::ConcreteCont(_) | H::NoCont, H::Cont) => true,
+        (_, H::Cont) => false,
+
+        (H::NoCont, H::NoCont) => true,
+        (_, H::NoCont) => false,
     };
     if result {
         Ok(())
@@ -1,8 +1,8 @@
 use crate::runtime::vm::TableElement;
 use crate::store::{AutoAssertNoGc, StoreOpaque};
 use crate::{
-    prelude::*, AnyRef, ArrayRef, AsContext, AsContextMut, ExternRef, Func, HeapType, RefType,
-    Rooted, RootedGcRefImpl, StructRef, ValType, V128,
+    prelude::*, AnyRef, ArrayRef, AsContext, AsContextMut, ExnRef, ExternRef, Func, HeapType,
+    RefType, Rooted, RootedGcRefImpl, StructRef, ValType, V128,
 };
 use core::ptr;
 
@@ -47,6 +47,9 @@ pub enum Val {
 
     /// An internal reference.
     AnyRef(Option<Rooted<AnyRef>>),
+
+    /// An exception reference.
+    ExnRef(Option<Rooted<ExnRef>>),
 }
 
 macro_rules! accessors {
@@ -109,6 +112,15 @@ impl Val {
         Val::AnyRef(None)
     }
 
+    /// Returns the null exception reference value.
+    ///
+    /// The return value has type `(ref null noexn)` aka `nullexnref` and is a
+    /// subtype of all exception references.
+    #[inline]
+    pub const fn null_exn_ref() -> Val {
+        Val::ExnRef(None)
+    }
+
     /// Returns the default value for the given type, if any exists.
     ///
     /// Returns `None` if there is no default value for the given type (for
@@ -162,6 +174,8 @@ impl Val {
             )),
             Val::AnyRef(None) => ValType::NULLREF,
             Val::AnyRef(Some(a)) => ValType::Ref(RefType::new(false, a._ty(store)?)),
+            Val::ExnRef(None) => ValType::NULLEXNREF,
+            Val::ExnRef(Some(_)) => ValType::Ref(RefType::new(false, HeapType::Exn)),
         })
     }
 
@@ -191,6 +205,7 @@ impl Val {
                 Ref::from(*e)._matches_ty(store, ref_ty)?
             }
             (Val::AnyRef(a), ValType::Ref(ref_ty)) => Ref::from(*a)._matches_ty(store, ref_ty)?,
+            (Val::ExnRef(e), ValType::Ref(ref_ty)) => Ref::from(*e)._matches_ty(store, ref_ty)?,
 
             (Val::I32(_), _)
             | (Val::I64(_), _)
@@ -199,7 +214,8 @@ impl Val {
             | (Val::V128(_), _)
             | (Val::FuncRef(_), _)
             | (Val::ExternRef(_), _)
-            | (Val::AnyRef(_), _) => false,
+            | (Val::AnyRef(_), _)
+            | (Val::ExnRef(_), _) => false,
         })
     }
 
@@ -242,6 +258,10 @@ impl Val {
                 None => 0,
                 Some(e) => e.to_raw(store)?,
             })),
+            Val::ExnRef(e) => Ok(ValRaw::exnref(match e {
+                None => 0,
+                Some(e) => e.to_raw(store)?,
+            })),
             Val::FuncRef(f) => Ok(ValRaw::funcref(match f {
                 Some(f) => f.to_raw(store),
                 None => ptr::null_mut(),
@@ -249,6 +269,34 @@ impl Val {
         }
     }
 
+    /// Same as [`Val::to_raw`] but for use within a no-GC scope.
+    #[cfg(feature = "gc")]
+    pub(crate) unsafe fn _to_raw(&self, store: &mut AutoAssertNoGc<'_>) -> Result<ValRaw> {
+        match self {
+            Val::I32(i) => Ok(ValRaw::i32(*i)),
+            Val::I64(i) => Ok(ValRaw::i64(*i)),
+            Val::F32(u) => Ok(ValRaw::f32(*u)),
+            Val::F64(u) => Ok(ValRaw::f64(*u)),
+            Val::V128(b) => Ok(ValRaw::v128(b.as_u128())),
+            Val::ExternRef(e) => Ok(ValRaw::externref(match e {
+                None => 0,
+                Some(e) => e._to_raw(store)?,
+            })),
+            Val::AnyRef(e) => Ok(ValRaw::anyref(match e {
+                None => 0,
+                Some(e) => e._to_raw(store)?,
+            })),
+            Val::ExnRef(e) => Ok(ValRaw::exnref(match e {
+                None => 0,
+                Some(e) => e._to_raw(store)?,
+            })),
+            Val::FuncRef(f) => Ok(ValRaw::funcref(match f {
+                Some(f) => f.vm_func_ref(store).as_ptr().cast(),
+                None => ptr::null_mut(),
+            })),
+        }
+    }
+
     /// Convenience method to convert a [`ValRaw`] into a [`Val`].
     ///
     /// # Unsafety
@@ -295,6 +343,14 @@ impl Val {
                     }
 
                     HeapType::None => Ref::Any(None),
+
+                    HeapType::Exn => ExnRef::_from_raw(store, raw.get_exnref()).into(),
+
+                    HeapType::NoExn => Ref::Exn(None),
+
+                    HeapType::Cont | HeapType::ConcreteCont(_) | HeapType::NoCont => {
+                        unreachable!("continuation references are never passed to the host")
+                    }
                 };
                 assert!(
                     ref_ty.is_nullable() || !ref_.is_null(),
@@ -315,6 +371,7 @@ impl Val {
         (FuncRef(Option<&Func>) func_ref unwrap_func_ref e.as_ref())
         (ExternRef(Option<&Rooted<ExternRef>>) extern_ref unwrap_extern_ref e.as_ref())
         (AnyRef(Option<&Rooted<AnyRef>>) any_ref unwrap_any_ref e.as_ref())
+        (ExnRef(Option<&Rooted<ExnRef>>) exn_ref unwrap_exn_ref e.as_ref())
         (V128(V128) v128 unwrap_v128 *e)
     }
 
@@ -325,6 +382,7 @@ impl Val {
             Val::FuncRef(f) => Some(Ref::Func(f)),
             Val::ExternRef(e) => Some(Ref::Extern(e)),
             Val::AnyRef(a) => Some(Ref::Any(a)),
+            Val::ExnRef(e) => Some(Ref::Exn(e)),
             Val::I32(_) | Val::I64(_) | Val::F32(_) | Val::F64(_) | Val::V128(_) => None,
         }
     }
@@ -391,6 +449,37 @@ impl Val {
         self.anyref().expect("expected anyref")
     }
 
+    /// Attempt to access the underlying `exnref` value of this `Val`.
+    ///
+    /// If this is not an `exnref`, then `None` is returned.
+    ///
+    /// If this is a null `exnref`, then `Some(None)` is returned.
+    ///
+    /// If this is a non-null `exnref`, then `Some(Some(..))` is returned.
+    #[inline]
+    pub fn exnref(&self) -> Option<Option<&Rooted<ExnRef>>> {
+        match self {
+            Val::ExnRef(None) => Some(None),
+            Val::ExnRef(Some(e)) => Some(Some(e)),
+            _ => None,
+        }
+    }
+
+    /// Returns the underlying `exnref` value of this `Val`, panicking if it's the
+    /// wrong type.
+    ///
+    /// If this is a null `exnref`, then `None` is returned.
+    ///
+    /// If this is a non-null `exnref`, then `Some(..)` is returned.
+    ///
+    /// # Panics
+    ///
+    /// Panics if `self` is not a (nullable) `exnref`.
+    #[inline]
+    pub fn unwrap_exnref(&self) -> Option<&Rooted<ExnRef>> {
+        self.exnref().expect("expected exnref")
+    }
+
     /// Attempt to access the underlying `funcref` value of this `Val`.
     ///
     /// If this is not an `funcref`, then `None` is returned.
@@ -434,6 +523,9 @@ impl Val {
             Val::AnyRef(Some(a)) => a.comes_from_same_store(store),
             Val::AnyRef(None) => true,
 
+            Val::ExnRef(Some(e)) => e.comes_from_same_store(store),
+            Val::ExnRef(None) => true,
+
             // Integers, floats, and vectors have no association with any
             // particular store, so they're always considered as "yes I came
             // from that store",
@@ -477,6 +569,7 @@ impl From<Ref> for Val {
             Ref::Extern(e) => Val::ExternRef(e),
             Ref::Func(f) => Val::FuncRef(f),
             Ref::Any(a) => Val::AnyRef(a),
+            Ref::Exn(e) => Val::ExnRef(e),
         }
     }
 }
@@ -509,6 +602,20 @@ impl From<Option<Rooted<AnyRef>>> for Val {
     }
 }
 
+impl From<Rooted<ExnRef>> for Val {
+    #[inline]
+    fn from(val: Rooted<ExnRef>) -> Val {
+        Val::ExnRef(Some(val))
+    }
+}
+
+impl From<Option<Rooted<ExnRef>>> for Val {
+    #[inline]
+    fn from(val: Option<Rooted<ExnRef>>) -> Val {
+        Val::ExnRef(val)
+    }
+}
+
 impl From<Rooted<StructRef>> for Val {
     #[inline]
     fn from(val: Rooted<StructRef>) -> Val {
@@ -567,7 +674,7 @@ impl From<V128> for Val {
 
 /// A reference.
 ///
-/// References come in three broad flavors:
+/// References come in four broad flavors:
 ///
 /// 1. Function references. These are references to a function that can be
 ///    invoked.
@@ -579,6 +686,9 @@ impl From<V128> for Val {
 ///    Wasm's heap, such as structs and arrays. These are part of the GC
 ///    proposal, and not yet implemented in Wasmtime.
 ///
+/// 4. Exception references. These are references to exceptions thrown by
+///    Wasm or the host, and are part of the exception-handling proposal.
+///
 /// At the Wasm level, there are nullable and non-nullable variants of each type
 /// of reference. Both variants are represented with `Ref` at the Wasmtime API
 /// level. For example, values of both `(ref extern)` and `(ref null extern)`
@@ -649,6 +759,13 @@ pub enum Ref {
     /// Unlike `externref`, Wasm guests can directly allocate `anyref`s, and
     /// does not need to rely on the host to do that.
     Any(Option<Rooted<AnyRef>>),
+
+    /// An exception reference.
+    ///
+    /// Wasm guests get non-null `exnref`s by catching exceptions with a
+    /// `try_table` handler, and may rethrow them with `throw_ref`. The host
+    /// can create exceptions with [`ExnRef::new`].
+    Exn(Option<Rooted<ExnRef>>),
 }
 
 impl From<Func> for Ref {
@@ -693,6 +810,20 @@ impl From<Option<Rooted<AnyRef>>> for Ref {
     }
 }
 
+impl From<Rooted<ExnRef>> for Ref {
+    #[inline]
+    fn from(e: Rooted<ExnRef>) -> Ref {
+        Ref::Exn(Some(e))
+    }
+}
+
+impl From<Option<Rooted<ExnRef>>> for Ref {
+    #[inline]
+    fn from(e: Option<Rooted<ExnRef>>) -> Ref {
+        Ref::Exn(e)
+    }
+}
+
 impl From<Rooted<StructRef>> for Ref {
     #[inline]
     fn from(e: Rooted<StructRef>) -> Ref {
@@ -729,6 +860,7 @@ impl Ref {
             HeapType::Any => Ref::Any(None),
             HeapType::Extern => Ref::Extern(None),
             HeapType::Func => Ref::Func(None),
+            HeapType::Exn => Ref::Exn(None),
             ty => unreachable!("not a heap type: {ty:?}"),
         }
     }
@@ -737,8 +869,10 @@ impl Ref {
     #[inline]
     pub fn is_null(&self) -> bool {
         match self {
-            Ref::Any(None) | Ref::Extern(None) | Ref::Func(None) => true,
-            Ref::Any(Some(_)) | Ref::Extern(Some(_)) | Ref::Func(Some(_)) => false,
+            Ref::Any(None) | Ref::Extern(None) | Ref::Func(None) | Ref::Exn(None) => true,
+            Ref::Any(Some(_)) | Ref::Extern(Some(_)) | Ref::Func(Some(_)) | Ref::Exn(Some(_)) => {
+                false
+            }
         }
     }
 
@@ -815,6 +949,39 @@ impl Ref {
         self.as_any().expect("Ref::unwrap_any on non-any reference")
     }
 
+    /// Is this an `exn` reference?
+    #[inline]
+    pub fn is_exn(&self) -> bool {
+        matches!(self, Ref::Exn(_))
+    }
+
+    /// Get the underlying `exn` reference, if any.
+    ///
+    /// Returns `None` if this `Ref` is not an `exn` reference, eg it is a
+    /// `func` reference.
+    ///
+    /// Returns `Some(None)` if this `Ref` is a null `exn` reference.
+    ///
+    /// Returns `Some(Some(_))` if this `Ref` is a non-null `exn` reference.
+    #[inline]
+    pub fn as_exn(&self) -> Option<Option<&Rooted<ExnRef>>> {
+        match self {
+            Ref::Exn(e) => Some(e.as_ref()),
+            _ => None,
+        }
+    }
+
+    /// Get the underlying `exn` reference, panicking if this is a different
+    /// kind of reference.
+    ///
+    /// Returns `None` if this `Ref` is a null `exn` reference.
+    ///
+    /// Returns `Some(_)` if this `Ref` is a non-null `exn` reference.
+    #[inline]
+    pub fn unwrap_exn(&self) -> Option<&Rooted<ExnRef>> {
+        self.as_exn().expect("Ref::unwrap_exn on non-exn reference")
+    }
+
     /// Is this a `func` reference?
     #[inline]
     pub fn is_func(&self) -> bool {
@@ -878,6 +1045,9 @@ impl Ref {
 
                 Ref::Any(None) => HeapType::None,
                 Ref::Any(Some(a)) => a._ty(store)?,
+
+                Ref::Exn(None) => HeapType::NoExn,
+                Ref::Exn(Some(_)) => HeapType::Exn,
             },
         ))
     }
@@ -933,6 +1103,10 @@ impl Ref {
                 | HeapType::Array,
             ) => true,
             (Ref::Any(_), _) => false,
+
+            (Ref::Exn(_), HeapType::Exn) => true,
+            (Ref::Exn(None), HeapType::NoExn) => true,
+            (Ref::Exn(_), _) => false,
         })
     }
 
@@ -959,6 +1133,8 @@ impl Ref {
             Ref::Extern(None) => true,
             Ref::Any(Some(a)) => a.comes_from_same_store(store),
             Ref::Any(None) => true,
+            Ref::Exn(Some(e)) => e.comes_from_same_store(store),
+            Ref::Exn(None) => true,
         }
     }
 
@@ -1008,6 +1184,18 @@ impl Ref {
                 }
             },
 
+            (Ref::Exn(e), HeapType::Exn) => match e {
+                None => {
+                    assert!(ty.is_nullable());
+                    Ok(TableElement::GcRef(None))
+                }
+                #[cfg_attr(not(feature = "gc"), allow(unreachable_patterns))]
+                Some(e) => {
+                    let gc_ref = e.try_clone_gc_ref(&mut store)?;
+                    Ok(TableElement::GcRef(Some(gc_ref)))
+                }
+            },
+
             _ => unreachable!("checked that the value matches the type above"),
         }
     }
@@ -61,3 +61,17 @@ pub fn assert_fp_is_aligned(_fp: usize) {
     //
     // [0]: https://github.com/ARM-software/abi-aa/blob/2022Q1/aapcs64/aapcs64.rst#the-frame-pointer
 }
+
+pub unsafe fn resume_to_exception_landing_pad(pc: usize, sp: usize, fp: usize) -> ! {
+    // Note that `ret` is used rather than `br` since landing pads don't start
+    // with a BTI instruction.
+    core::arch::asm!(
+        "mov sp, {sp}",
+        "mov x29, {fp}",
+        "ret {pc}",
+        pc = in(reg) pc,
+        sp = in(reg) sp,
+        fp = in(reg) fp,
+        options(noreturn),
+    )
+}
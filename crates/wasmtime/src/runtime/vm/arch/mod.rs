@@ -43,3 +43,17 @@ pub const NEXT_OLDER_FP_FROM_FP_OFFSET: usize = imp::NEXT_OLDER_FP_FROM_FP_OFFSE
 pub fn assert_fp_is_aligned(fp: usize) {
     imp::assert_fp_is_aligned(fp)
 }
+
+/// Resume execution at the exception landing pad `pc` in the Wasm frame with
+/// the given stack and frame pointers.
+///
+/// No other registers are restored, since Cranelift assumes that a call which
+/// is an exception landing pad clobbers all of them.
+///
+/// # Safety
+///
+/// All of the frames younger than the landing pad's frame are discarded
+/// without running any destructors.
+pub unsafe fn resume_to_exception_landing_pad(pc: usize, sp: usize, fp: usize) -> ! {
+    imp::resume_to_exception_landing_pad(pc, sp, fp)
+}
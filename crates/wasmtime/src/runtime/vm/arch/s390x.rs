@@ -20,3 +20,7 @@ pub const NEXT_OLDER_FP_FROM_FP_OFFSET: usize = 0;
 pub fn assert_fp_is_aligned(fp: usize) {
     assert_eq!(fp % 8, 0, "stack should always be aligned to 8");
 }
+
+pub unsafe fn resume_to_exception_landing_pad(_pc: usize, _sp: usize, _fp: usize) -> ! {
+    unreachable!("Wasm exceptions are not supported on s390x")
+}
@@ -34,3 +34,7 @@ pub const NEXT_OLDER_FP_FROM_FP_OFFSET: usize = 0;
 pub fn assert_fp_is_aligned(_fp: usize) {
     panic!()
 }
+
+pub unsafe fn resume_to_exception_landing_pad(_pc: usize, _sp: usize, _fp: usize) -> ! {
+    panic!()
+}
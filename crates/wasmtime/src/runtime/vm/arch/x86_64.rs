@@ -26,3 +26,15 @@ pub const NEXT_OLDER_FP_FROM_FP_OFFSET: usize = 0;
 pub fn assert_fp_is_aligned(fp: usize) {
     assert_eq!(fp % 16, 0, "stack should always be aligned to 16");
 }
+
+pub unsafe fn resume_to_exception_landing_pad(pc: usize, sp: usize, fp: usize) -> ! {
+    core::arch::asm!(
+        "mov rsp, {sp}",
+        "mov rbp, {fp}",
+        "jmp {pc}",
+        pc = in(reg) pc,
+        sp = in(reg) sp,
+        fp = in(reg) fp,
+        options(noreturn),
+    )
+}
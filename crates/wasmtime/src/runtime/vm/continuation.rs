@@ -0,0 +1,844 @@
+//! Runtime support for the WebAssembly stack-switching proposal.
+//!
+//! Every continuation created by `cont.new` runs its function on a fiber of its
+//! own. Fiber stacks are allocated by the engine's instance allocator, exactly
+//! like the stacks used for async calls, which means that they are sized by
+//! `Config::async_stack_size` and come from the stack pool when the pooling
+//! allocator is in use.
+//!
+//! Wasm refers to continuations with `i64` handles into the store's table of
+//! continuations. The low 32 bits of a handle are one more than the
+//! continuation's slab index and the high 32 bits are a generation, so that the
+//! all-zero handle is the null continuation reference. A continuation has at
+//! most one live handle at a time: `resume`, `resume_throw`, `switch`, and
+//! `cont.bind` consume the handle they are given, and using a consumed handle
+//! again traps.
+//!
+//! Resuming a continuation pushes a handler "level" that records the `resume`
+//! instruction's handler clauses. `suspend` and `switch` search these levels
+//! from the innermost outwards for a handler for their tag, and then suspend to
+//! the continuation's direct resumer. When that resumer does not handle the tag
+//! itself, it forwards the suspension by suspending its own continuation in
+//! turn, until the suspension reaches the resumer that handles it.
+//!
+//! Host frames are barriers to suspension: a continuation may only suspend to a
+//! handler when there are no host frames between the `suspend` and the
+//! handling `resume`.
+//!
+//! Continuation references are included in stack maps, and handles can't be
+//! stored anywhere but on the stack: tables, globals, GC objects, and
+//! exceptions can't hold them. When a store has many continuations, `cont.new`
+//! walks the Wasm frames on the current stack and on the stacks of reachable
+//! continuations to find the continuations that still have a live handle, and
+//! cancels the rest so that their stacks are returned to the allocator. The
+//! number of continuations that a store may have at once is limited by
+//! `ResourceLimiter::continuations`.
+
+use crate::prelude::*;
+use crate::runtime::vm::traphandlers::tls;
+use crate::runtime::vm::{
+    catch_traps, get_stack_pointer, AsyncWasmCallState, Backtrace, Frame, Instance, Trap,
+    TrapReason, VMContext, VMFuncRef, VMOpaqueContext, VMRuntimeLimits, VMTagDefinition,
+};
+use crate::store::StoreOpaque;
+use crate::ValRaw;
+use core::ops::ControlFlow;
+use core::panic::AssertUnwindSafe;
+use core::ptr::{self, NonNull};
+use wasmtime_environ::{TagIndex, RESUME_HANDLER_SWITCH};
+use wasmtime_fiber::{Fiber, Suspend};
+use wasmtime_slab::{Id, Slab};
+
+type ContinuationFiber = Fiber<'static, Resumption, Suspension, Result<(), Box<Trap>>>;
+type ContinuationSuspend = Suspend<Resumption, Suspension, Result<(), Box<Trap>>>;
+
+/// The number of continuations at which a store first looks for unreachable
+/// continuations to reclaim.
+const INITIAL_COLLECTION_THRESHOLD: usize = 64;
+
+/// The continuations owned by a store.
+pub struct Continuations {
+    slab: Slab<Box<Continuation>>,
+    next_generation: u32,
+    /// The handler levels of the `resume`s that are currently executing,
+    /// outermost first.
+    levels: Vec<Level>,
+    /// The maximum number of continuations that may exist at once.
+    limit: usize,
+    /// The number of continuations at which `cont.new` next reclaims
+    /// unreachable continuations.
+    collection_threshold: usize,
+}
+
+// Continuations hold raw pointers to their fibers' stacks and to Wasm
+// instances, which are only ever accessed by the thread that is currently
+// using the store. See the comments on `FiberFuture` in `store.rs` for why
+// moving fiber stacks between threads is sound.
+unsafe impl Send for Continuations {}
+unsafe impl Sync for Continuations {}
+
+struct Continuation {
+    /// The generation of this continuation's live handle, or zero if it does
+    /// not have a live handle.
+    generation: u32,
+    state: State,
+    func: NonNull<VMFuncRef>,
+    /// Values bound by `cont.bind`, which are prepended to the values that
+    /// this continuation is next resumed with.
+    bound: Vec<ValRaw>,
+    /// Storage for the function's parameters and results.
+    buffer: Vec<ValRaw>,
+    results: usize,
+    fiber: Option<ContinuationFiber>,
+    suspend: *mut ContinuationSuspend,
+    /// The activations on this continuation's stack while it is suspended.
+    call_state: Option<AsyncWasmCallState>,
+    /// This continuation's registers while it is suspended.
+    registers: Registers,
+    /// The continuation on whose stack this one was last resumed, if any. A
+    /// `Nested` continuation is reachable exactly when this one is.
+    parent: Option<Id>,
+}
+
+#[derive(Clone, Copy, Debug, PartialEq, Eq)]
+enum State {
+    /// Created by `cont.new` but not yet resumed.
+    Fresh,
+    /// Currently executing.
+    Running,
+    /// Suspended to the handler of the `resume` that last resumed it.
+    Suspended,
+    /// Suspended within a continuation that forwarded the suspension to an
+    /// outer handler.
+    Nested,
+}
+
+/// The parts of `VMRuntimeLimits` describing the stack that is currently
+/// executing.
+#[derive(Clone, Copy, Default)]
+struct Registers {
+    last_wasm_exit_pc: usize,
+    last_wasm_exit_fp: usize,
+    last_wasm_entry_fp: usize,
+    stack_limit: usize,
+}
+
+impl Registers {
+    unsafe fn save(limits: *const VMRuntimeLimits) -> Registers {
+        Registers {
+            last_wasm_exit_pc: *(*limits).last_wasm_exit_pc.get(),
+            last_wasm_exit_fp: *(*limits).last_wasm_exit_fp.get(),
+            last_wasm_entry_fp: *(*limits).last_wasm_entry_fp.get(),
+            stack_limit: *(*limits).stack_limit.get(),
+        }
+    }
+
+    unsafe fn restore(&self, limits: *const VMRuntimeLimits) {
+        *(*limits).last_wasm_exit_pc.get() = self.last_wasm_exit_pc;
+        *(*limits).last_wasm_exit_fp.get() = self.last_wasm_exit_fp;
+        *(*limits).last_wasm_entry_fp.get() = self.last_wasm_entry_fp;
+        *(*limits).stack_limit.get() = self.stack_limit;
+    }
+}
+
+/// The handler clauses of a `resume` that is currently executing.
+struct Level {
+    /// The continuation being resumed.
+    id: Id,
+    handlers: *const u32,
+    len: usize,
+    /// The instance containing the `resume`, which defines the handlers'
+    /// tags.
+    vmctx: *mut VMContext,
+    /// The activation that was executing the `resume`.
+    parent: tls::Ptr,
+}
+
+impl Level {
+    /// Find the index of this level's handler for `tag`.
+    unsafe fn find(&self, tag: *mut VMTagDefinition, switch: bool) -> Option<usize> {
+        if self.len == 0 {
+            return None;
+        }
+        let handlers = core::slice::from_raw_parts(self.handlers, self.len);
+        Instance::from_vmctx(self.vmctx, |instance| {
+            handlers.iter().position(|&handler| {
+                let index = TagIndex::from_u32(handler & !RESUME_HANDLER_SWITCH);
+                (handler & RESUME_HANDLER_SWITCH != 0) == switch
+                    && instance.get_exported_tag(index).definition == tag
+            })
+        })
+    }
+}
+
+/// The values that a continuation is resumed with.
+enum Resumption {
+    /// Resume with the given values.
+    Values(*const ValRaw, usize),
+    /// Resume by throwing the store's pending exception.
+    Throw,
+    /// Unwind the continuation with a trap, used when it is unreachable or its
+    /// store is dropped.
+    Cancel,
+}
+
+/// The values that a continuation suspends with.
+struct Suspension {
+    tag: *mut VMTagDefinition,
+    /// For `switch`, the continuation to switch to.
+    switch_to: Option<Id>,
+    values: *const ValRaw,
+    len: usize,
+}
+
+fn cancelled() -> TrapReason {
+    TrapReason::User {
+        error: anyhow!("continuation cancelled"),
+        needs_backtrace: false,
+    }
+}
+
+/// The reason to unwind with when throwing the store's pending exception.
+fn thrown() -> TrapReason {
+    TrapReason::User {
+        error: crate::ThrownException.into(),
+        needs_backtrace: false,
+    }
+}
+
+fn current_activation() -> tls::Ptr {
+    tls::with(|state| state.map_or(ptr::null(), |state| state as *const _))
+}
+
+impl Continuations {
+    pub fn new() -> Continuations {
+        Continuations {
+            slab: Slab::new(),
+            next_generation: 0,
+            levels: Vec::new(),
+            limit: crate::DEFAULT_CONTINUATION_LIMIT,
+            collection_threshold: INITIAL_COLLECTION_THRESHOLD,
+        }
+    }
+
+    /// Set the maximum number of continuations that may exist at once.
+    pub fn set_limit(&mut self, limit: usize) {
+        self.limit = limit;
+    }
+
+    /// Create a new live handle for the continuation `id`.
+    fn issue(&mut self, id: Id) -> u64 {
+        self.next_generation = self.next_generation.wrapping_add(1).max(1);
+        self.slab[id].generation = self.next_generation;
+        (u64::from(self.next_generation) << 32) | u64::from(id.into_raw() + 1)
+    }
+
+    /// Get the continuation that `handle` is the live handle of, if any.
+    fn live(&self, handle: u64) -> Option<Id> {
+        let generation = u32::try_from(handle >> 32).unwrap();
+        let index = u32::try_from(handle & u64::from(u32::MAX)).unwrap();
+        let id = Id::from_raw(index.checked_sub(1)?);
+        match self.slab.get(id) {
+            Some(cont) if cont.generation != 0 && cont.generation == generation => Some(id),
+            _ => None,
+        }
+    }
+
+    /// Consume the live handle `handle`, returning its continuation.
+    fn take(&mut self, handle: u64) -> Result<Id, TrapReason> {
+        if handle == 0 {
+            return Err(wasmtime_environ::Trap::NullReference.into());
+        }
+        let id = self
+            .live(handle)
+            .ok_or(wasmtime_environ::Trap::ContinuationAlreadyConsumed)?;
+        self.slab[id].generation = 0;
+        Ok(id)
+    }
+
+    /// Is there a handler for `tag` that the current continuation can suspend
+    /// to?
+    unsafe fn has_handler(&self, tag: *mut VMTagDefinition, switch: bool) -> bool {
+        let mut activation = current_activation();
+        for level in self.levels.iter().rev() {
+            // The continuation at this level must have been executing directly
+            // on top of its resumer, without any host frames in between.
+            if activation.is_null() || (*activation).prev() != level.parent {
+                return false;
+            }
+            if level.find(tag, switch).is_some() {
+                return true;
+            }
+            activation = level.parent;
+        }
+        false
+    }
+
+    /// Suspend the currently executing continuation.
+    unsafe fn suspend_current(&self, suspension: Suspension) -> Resumption {
+        let level = self
+            .levels
+            .last()
+            .expect("suspensions are only made with a handler");
+        let suspend = self.slab[level.id].suspend;
+        (*suspend).suspend(suspension)
+    }
+
+    /// Walk the Wasm frames of every suspended continuation, calling `f` for
+    /// each frame.
+    pub fn trace_suspended(&self, mut f: impl FnMut(Frame) -> ControlFlow<()>) {
+        for (_, cont) in self.slab.iter() {
+            let Some(call_state) = &cont.call_state else {
+                continue;
+            };
+            let registers = &cont.registers;
+            unsafe {
+                Backtrace::trace_suspended(
+                    registers.last_wasm_exit_pc,
+                    registers.last_wasm_exit_fp,
+                    registers.last_wasm_entry_fp,
+                    call_state,
+                    &mut f,
+                );
+            }
+        }
+    }
+
+    /// Cancel all of the store's suspended continuations and deallocate every
+    /// continuation.
+    ///
+    /// This is called when the store is dropped, and unwinds each suspended
+    /// continuation's stack with a trap so that its fiber finishes.
+    pub unsafe fn cancel_all(store: &mut StoreOpaque) {
+        let store: *mut StoreOpaque = store;
+        let conts: *mut Continuations = (*store).continuations_mut();
+        let suspended = (*conts)
+            .slab
+            .iter()
+            .filter(|(_, cont)| cont.state == State::Suspended)
+            .map(|(id, _)| id)
+            .collect::<Vec<_>>();
+        for id in suspended {
+            cancel(store, id);
+        }
+
+        // Cancelling a suspended continuation also unwinds every continuation
+        // nested within it, so all of the remaining fibers have finished.
+        for (_, cont) in (*conts).slab.drain() {
+            if let Some(fiber) = cont.fiber {
+                (*store)
+                    .engine()
+                    .allocator()
+                    .deallocate_fiber_stack(fiber.into_stack());
+            }
+        }
+    }
+
+    /// Cancel and deallocate every continuation that can no longer be resumed
+    /// because no Wasm frame holds its handle.
+    ///
+    /// Handles are found in the stack maps of the Wasm frames on the current
+    /// stack and on the stacks of reachable continuations, in values bound to
+    /// reachable continuations, and in `roots`.
+    unsafe fn collect(store: *mut StoreOpaque, roots: &[ValRaw]) {
+        log::trace!("Begin collecting unreachable continuations");
+        let conts: *mut Continuations = (*store).continuations_mut();
+        let mut marker = Marker {
+            conts,
+            marked: Vec::new(),
+            worklist: Vec::new(),
+        };
+
+        // Marking only reads the continuations, so borrow them once for it.
+        let unreachable = {
+            let conts = &*conts;
+
+            // The continuations without a live handle are running, or are being
+            // resumed, so they are reachable. The exception are nested
+            // continuations, which are reachable when the continuation that they
+            // are nested within is.
+            let mut nested = Vec::new();
+            for (id, cont) in conts.slab.iter() {
+                match (cont.state, cont.parent) {
+                    (State::Nested, Some(parent)) => nested.push((parent, id)),
+                    _ if cont.generation == 0 => marker.mark(id),
+                    _ => {}
+                }
+            }
+            for root in roots {
+                marker.mark_handle(root.get_u64());
+            }
+            Backtrace::trace((*store).vmruntime_limits().cast_const(), |frame| {
+                marker.mark_frame(&*store, frame);
+                ControlFlow::Continue(())
+            });
+
+            while let Some(id) = marker.worklist.pop() {
+                let cont = &conts.slab[id];
+                for value in cont.bound.iter() {
+                    marker.mark_handle(value.get_u64());
+                }
+                if let Some(call_state) = &cont.call_state {
+                    let registers = cont.registers;
+                    Backtrace::trace_suspended(
+                        registers.last_wasm_exit_pc,
+                        registers.last_wasm_exit_fp,
+                        registers.last_wasm_entry_fp,
+                        call_state,
+                        |frame| {
+                            marker.mark_frame(&*store, frame);
+                            ControlFlow::Continue(())
+                        },
+                    );
+                }
+                for &(parent, child) in nested.iter() {
+                    if parent == id {
+                        marker.mark(child);
+                    }
+                }
+            }
+
+            // Unreachable nested continuations are unwound along with the
+            // suspended continuation that they are nested within.
+            conts
+                .slab
+                .iter()
+                .filter(|(id, cont)| cont.state != State::Nested && !marker.is_marked(*id))
+                .map(|(id, cont)| (id, cont.state))
+                .collect::<Vec<_>>()
+        };
+        log::trace!("Reclaiming {} continuations", unreachable.len());
+        for (id, state) in unreachable {
+            match state {
+                State::Fresh => {
+                    (*conts).slab.dealloc(id);
+                }
+                State::Suspended => cancel(store, id),
+                State::Running | State::Nested => {
+                    unreachable!("running continuations are reachable")
+                }
+            }
+        }
+
+        (*conts).collection_threshold = INITIAL_COLLECTION_THRESHOLD.max(2 * (*conts).slab.len());
+        log::trace!("End collecting unreachable continuations");
+    }
+
+    /// Allocate a stack for a continuation's fiber, reclaiming unreachable
+    /// continuations to make room for it if the allocator has none to spare.
+    unsafe fn allocate_stack(
+        store: *mut StoreOpaque,
+        roots: &[ValRaw],
+    ) -> Result<wasmtime_fiber::FiberStack, TrapReason> {
+        let allocator = (*store).engine().allocator();
+        if let Ok(stack) = allocator.allocate_fiber_stack() {
+            return Ok(stack);
+        }
+        Continuations::collect(store, roots);
+        allocator
+            .allocate_fiber_stack()
+            .map_err(TrapReason::user_without_backtrace)
+    }
+}
+
+/// The state of `Continuations::collect`'s search for reachable continuations.
+struct Marker {
+    conts: *const Continuations,
+    marked: Vec<bool>,
+    worklist: Vec<Id>,
+}
+
+impl Marker {
+    fn is_marked(&self, id: Id) -> bool {
+        let index = usize::try_from(id.into_raw()).unwrap();
+        self.marked.get(index).copied().unwrap_or(false)
+    }
+
+    fn mark(&mut self, id: Id) {
+        if self.is_marked(id) {
+            return;
+        }
+        let index = usize::try_from(id.into_raw()).unwrap();
+        if self.marked.len() <= index {
+            self.marked.resize(index + 1, false);
+        }
+        self.marked[index] = true;
+        self.worklist.push(id);
+    }
+
+    /// Mark the continuation that `handle` is the live handle of, if any.
+    ///
+    /// Bound values and `roots` are not typed, so this must be robust to
+    /// values that are not handles at all.
+    unsafe fn mark_handle(&mut self, handle: u64) {
+        if let Some(id) = (*self.conts).live(handle) {
+            self.mark(id);
+        }
+    }
+
+    /// Mark the continuations whose handles are live in `frame`.
+    unsafe fn mark_frame(&mut self, store: &StoreOpaque, frame: Frame) {
+        let pc = frame.pc();
+        let Some(stack_map) = store
+            .modules()
+            .lookup_module_by_pc(pc)
+            .and_then(|module| module.lookup_stack_map(pc))
+        else {
+            return;
+        };
+        let sp = stack_map.sp(frame.fp() as *mut usize);
+        for slot in stack_map.live_cont_refs(sp) {
+            self.mark_handle(ptr::read(slot));
+        }
+    }
+}
+
+/// Unwind the suspended continuation `id` with a trap and deallocate it.
+unsafe fn cancel(store: *mut StoreOpaque, id: Id) {
+    log::trace!("cancelling continuation {id:?}");
+    (*store).continuations_mut().slab[id].generation = 0;
+    let level = Level {
+        id,
+        handlers: ptr::null(),
+        len: 0,
+        vmctx: ptr::null_mut(),
+        parent: ptr::null(),
+    };
+    let result = run(store, level, Resumption::Cancel, ptr::null_mut());
+    debug_assert!(result.is_err());
+}
+
+impl Continuation {
+    /// Create the fiber that runs this continuation's function on `stack`.
+    unsafe fn start(
+        &mut self,
+        store: &StoreOpaque,
+        stack: wasmtime_fiber::FiberStack,
+    ) -> Result<(), TrapReason> {
+        let engine = store.engine();
+        let guard_range = stack
+            .guard_range()
+            .unwrap_or(ptr::null_mut()..ptr::null_mut());
+        let signal_handler = store.signal_handler();
+        let capture_backtrace = engine.config().wasm_backtrace;
+        let capture_coredump = engine.config().coredump_on_trap;
+        let max_wasm_stack = engine.config().max_wasm_stack;
+        let caller = store.default_caller();
+        let limits = store.vmruntime_limits();
+
+        let cont: *mut Continuation = self;
+        let fiber = Fiber::new(stack, move |resumption, suspend| unsafe {
+            (*cont).suspend = suspend;
+            let Resumption::Values(values, len) = resumption else {
+                unreachable!("fresh continuations are only started with values")
+            };
+            ptr::copy_nonoverlapping(values, (*cont).buffer.as_mut_ptr(), len);
+
+            // Wasm running on this fiber gets `max_wasm_stack` bytes of it,
+            // which is no larger than the fiber's stack.
+            *(*limits).stack_limit.get() = get_stack_pointer() - max_wasm_stack;
+
+            catch_traps(
+                signal_handler,
+                capture_backtrace,
+                capture_coredump,
+                guard_range,
+                caller,
+                |caller| {
+                    let func = (*cont).func.as_ref();
+                    (func.array_call)(
+                        func.vmctx,
+                        VMOpaqueContext::from_vmcontext(caller),
+                        (*cont).buffer.as_mut_ptr(),
+                        (*cont).buffer.len(),
+                    )
+                },
+            )
+        })
+        .map_err(|e| TrapReason::user_without_backtrace(e.into()))?;
+        self.fiber = Some(fiber);
+        Ok(())
+    }
+}
+
+/// Resume the continuation at `level`, until it either returns or suspends to
+/// one of `level`'s handlers.
+///
+/// Returns zero if the continuation returned, in which case its results have
+/// been written to `values`, and `n + 1` if it suspended to the `n`th handler,
+/// in which case the tag's payload followed by the continuation's new handle
+/// have been written to `values`.
+unsafe fn run(
+    store: *mut StoreOpaque,
+    mut level: Level,
+    mut resumption: Resumption,
+    values: *mut ValRaw,
+) -> Result<u32, TrapReason> {
+    let conts: *mut Continuations = (*store).continuations_mut();
+    let limits = (*store).vmruntime_limits().cast_const();
+    // The continuation whose stack we are running on, if any.
+    let owner = (*conts).levels.last().map(|level| level.id);
+
+    // Storage for the values passed to the continuation when they are not
+    // simply the resumer's values.
+    let mut args: Vec<ValRaw>;
+
+    loop {
+        let parent = Registers::save(limits);
+        level.parent = current_activation();
+        let cont: *mut Continuation = &mut **(*conts).slab.get_mut(level.id).unwrap();
+        (*cont).parent = owner;
+
+        if let Resumption::Values(values, len) = resumption {
+            if !(*cont).bound.is_empty() {
+                let mut bound = core::mem::take(&mut (*cont).bound);
+                bound.extend_from_slice(core::slice::from_raw_parts(values, len));
+                args = bound;
+                resumption = Resumption::Values(args.as_ptr(), args.len());
+            }
+        }
+
+        let call_state = match (*cont).state {
+            State::Fresh => match resumption {
+                Resumption::Values(values, len) => {
+                    let values = core::slice::from_raw_parts(values, len);
+                    let stack = Continuations::allocate_stack(store, values)?;
+                    (*cont).start(&*store, stack)?;
+                    AsyncWasmCallState::new()
+                }
+                // A continuation that never started has nothing to unwind, so
+                // throwing into it simply rethrows the pending exception at
+                // the resumer.
+                Resumption::Throw => {
+                    (*conts).slab.dealloc(level.id);
+                    return Err(thrown());
+                }
+                Resumption::Cancel => {
+                    (*conts).slab.dealloc(level.id);
+                    return Err(cancelled());
+                }
+            },
+            State::Suspended | State::Nested => {
+                let call_state = (*cont).call_state.take().unwrap();
+                call_state.reparent(limits);
+                (*cont).registers.restore(limits);
+                call_state
+            }
+            State::Running => unreachable!("running continuations have no handle"),
+        };
+        (*cont).state = State::Running;
+
+        let depth = (*conts).levels.len();
+        let id = level.id;
+        (*conts).levels.push(level);
+        let previous = call_state.push();
+        let fiber = (*cont).fiber.as_ref().unwrap();
+        let result = std::panic::catch_unwind(AssertUnwindSafe(|| fiber.resume(resumption)));
+        let call_state = previous.restore();
+        // Remove this level along with any left behind by resumes that were
+        // unwound within the continuation.
+        level = (*conts).levels.drain(depth..).next().unwrap();
+        debug_assert_eq!(level.id, id);
+        let result = result.unwrap_or_else(|panic| std::panic::resume_unwind(panic));
+
+        let suspension = match result {
+            Ok(result) => {
+                // The continuation's function returned or trapped, so it is
+                // finished.
+                parent.restore(limits);
+                call_state.assert_null();
+                let mut cont = (*conts).slab.dealloc(level.id);
+                (*store)
+                    .engine()
+                    .allocator()
+                    .deallocate_fiber_stack(cont.fiber.take().unwrap().into_stack());
+                return match result {
+                    Ok(()) => {
+                        if !values.is_null() {
+                            ptr::copy_nonoverlapping(cont.buffer.as_ptr(), values, cont.results);
+                        }
+                        Ok(0)
+                    }
+                    Err(trap) => Err(TrapReason::User {
+                        error: crate::trap::from_runtime_box(&mut *store, trap),
+                        needs_backtrace: false,
+                    }),
+                };
+            }
+            Err(suspension) => suspension,
+        };
+
+        (*cont).registers = Registers::save(limits);
+        (*cont).call_state = Some(call_state);
+        parent.restore(limits);
+
+        if level
+            .find(suspension.tag, suspension.switch_to.is_some())
+            .is_none()
+        {
+            // This resume doesn't handle the tag, so forward the suspension to
+            // our own resumer, and then resume the continuation with whatever
+            // we are resumed with in turn.
+            (*cont).state = State::Nested;
+            resumption = (*conts).suspend_current(suspension);
+            continue;
+        }
+
+        (*cont).state = State::Suspended;
+        let handle = (*conts).issue(level.id);
+        match suspension.switch_to {
+            None => {
+                let index = level
+                    .find(suspension.tag, false)
+                    .expect("checked to have a handler above");
+                ptr::copy_nonoverlapping(suspension.values, values, suspension.len);
+                *values.add(suspension.len) = ValRaw::u64(handle);
+                return Ok(u32::try_from(index + 1).unwrap());
+            }
+            // Switch to the target continuation in place of the suspended one,
+            // passing it the suspended continuation's handle.
+            Some(target) => {
+                args = core::slice::from_raw_parts(suspension.values, suspension.len).to_vec();
+                args.push(ValRaw::u64(handle));
+                resumption = Resumption::Values(args.as_ptr(), args.len());
+                level.id = target;
+            }
+        }
+    }
+}
+
+/// Implementation of `cont.new`.
+pub unsafe fn cont_new(instance: &mut Instance, func: *mut VMFuncRef) -> Result<u64, TrapReason> {
+    let func = NonNull::new(func).ok_or(wasmtime_environ::Trap::NullReference)?;
+    let store: *mut StoreOpaque = (*instance.store()).store_opaque_mut();
+
+    let conts = (*store).continuations_mut();
+    if conts.slab.len() >= conts.collection_threshold.min(conts.limit) {
+        Continuations::collect(store, &[]);
+    }
+    let conts = (*store).continuations_mut();
+    if conts.slab.len() >= conts.limit {
+        return Err(TrapReason::user_without_backtrace(anyhow!(
+            "resource limit exceeded: continuation count too high at {}",
+            conts.slab.len() + 1
+        )));
+    }
+
+    let store = &mut *store;
+    let ty = store
+        .engine()
+        .signatures()
+        .borrow(func.as_ref().type_index)
+        .expect("function types are registered while their functions are alive");
+    let ty = ty.unwrap_func();
+    let (params, results) = (ty.params().len(), ty.returns().len());
+
+    let conts = store.continuations_mut();
+    let id = conts.slab.alloc(Box::new(Continuation {
+        generation: 0,
+        state: State::Fresh,
+        func,
+        bound: Vec::new(),
+        buffer: vec![ValRaw::u64(0); params.max(results)],
+        results,
+        fiber: None,
+        suspend: ptr::null_mut(),
+        call_state: None,
+        registers: Registers::default(),
+        parent: None,
+    }));
+    let handle = conts.issue(id);
+    log::trace!("cont_new({func:p}) -> {handle:#x}");
+    Ok(handle)
+}
+
+/// Implementation of `cont.bind`.
+pub unsafe fn cont_bind(
+    instance: &mut Instance,
+    handle: u64,
+    values: *const ValRaw,
+    len: usize,
+) -> Result<u64, TrapReason> {
+    let conts = (*instance.store()).store_opaque_mut().continuations_mut();
+    let id = conts.take(handle)?;
+    conts.slab[id]
+        .bound
+        .extend_from_slice(core::slice::from_raw_parts(values, len));
+    let new_handle = conts.issue(id);
+    log::trace!("cont_bind({handle:#x}, {len} values) -> {new_handle:#x}");
+    Ok(new_handle)
+}
+
+/// Implementation of `resume` and `resume_throw`.
+///
+/// When `throw` is true the continuation is resumed by throwing the store's
+/// pending exception at its suspension point, otherwise it is resumed with
+/// the `len` values in `values`.
+pub unsafe fn resume(
+    instance: &mut Instance,
+    handle: u64,
+    throw: bool,
+    values: *mut ValRaw,
+    len: usize,
+    handlers: *const u32,
+    handlers_len: usize,
+) -> Result<u32, TrapReason> {
+    log::trace!("resume({handle:#x}, throw={throw})");
+    let store: *mut StoreOpaque = (*instance.store()).store_opaque_mut();
+    let id = (*store).continuations_mut().take(handle)?;
+    let level = Level {
+        id,
+        handlers,
+        len: handlers_len,
+        vmctx: instance.vmctx(),
+        parent: ptr::null(),
+    };
+    let resumption = if throw {
+        Resumption::Throw
+    } else {
+        Resumption::Values(values, len)
+    };
+    run(store, level, resumption, values)
+}
+
+/// Implementation of `suspend`, and of `switch` when `switch_to` is the target
+/// continuation's handle.
+///
+/// The `len` payload values are read from `values`, and the values that the
+/// current continuation is resumed with are written back into it.
+pub unsafe fn suspend(
+    instance: &mut Instance,
+    tag: u32,
+    switch_to: Option<u64>,
+    values: *mut ValRaw,
+    len: usize,
+) -> Result<(), TrapReason> {
+    let tag = instance
+        .get_exported_tag(TagIndex::from_u32(tag))
+        .definition;
+    log::trace!("suspend(tag={tag:p}, switch_to={switch_to:x?})");
+    let conts = (*instance.store()).store_opaque_mut().continuations_mut();
+    if !conts.has_handler(tag, switch_to.is_some()) {
+        return Err(wasmtime_environ::Trap::UnhandledTag.into());
+    }
+    let switch_to = match switch_to {
+        Some(handle) => Some(conts.take(handle)?),
+        None => None,
+    };
+
+    let conts: *const Continuations = conts;
+    match (*conts).suspend_current(Suspension {
+        tag,
+        switch_to,
+        values,
+        len,
+    }) {
+        Resumption::Values(resumed, len) => {
+            ptr::copy_nonoverlapping(resumed, values, len);
+            Ok(())
+        }
+        // Throw the pending exception from the suspension point.
+        Resumption::Throw => Err(thrown()),
+        Resumption::Cancel => Err(cancelled()),
+    }
+}
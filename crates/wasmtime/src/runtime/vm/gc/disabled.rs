@@ -18,7 +18,7 @@ pub fn default_gc_runtime() -> impl GcRuntime {
 struct DisabledCollector;
 
 unsafe impl GcRuntime for DisabledCollector {
-    fn new_gc_heap(&self) -> Result<Box<dyn GcHeap>> {
+    fn new_gc_heap(&self, _engine: &crate::Engine) -> Result<Box<dyn GcHeap>> {
         unreachable!()
     }
 
@@ -5,21 +5,18 @@ mod data;
 mod drc;
 mod externref;
 mod free_list;
+mod mark_sweep;
+mod null;
 mod structref;
 
 pub use arrayref::*;
 pub use data::*;
 pub use drc::*;
 pub use externref::*;
+pub use mark_sweep::*;
+pub use null::*;
 pub use structref::*;
 
-use crate::runtime::vm::GcRuntime;
-
-/// Get the default GC runtime.
-pub fn default_gc_runtime() -> impl GcRuntime {
-    DrcCollector::default()
-}
-
 /// The default GC heap capacity: 512KiB.
 #[cfg(not(miri))]
 const DEFAULT_GC_HEAP_CAPACITY: usize = 1 << 19;
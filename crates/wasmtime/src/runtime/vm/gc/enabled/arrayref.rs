@@ -4,7 +4,7 @@ use crate::{
     runtime::vm::{GcHeap, GcStore, VMGcRef},
     store::{AutoAssertNoGc, StoreOpaque},
     vm::{FuncRefTableId, SendSyncPtr},
-    AnyRef, ExternRef, Func, HeapType, RootedGcRefImpl, StorageType, Val, ValType,
+    AnyRef, ExnRef, ExternRef, Func, HeapType, RootedGcRefImpl, StorageType, Val, ValType,
 };
 use core::fmt;
 use wasmtime_environ::{GcArrayLayout, VMGcKind};
@@ -164,6 +164,10 @@ impl VMArrayRef {
                     let raw = data.read_u32(offset);
                     Val::AnyRef(AnyRef::_from_raw(store, raw))
                 }
+                HeapType::Exn => {
+                    let raw = data.read_u32(offset);
+                    Val::ExnRef(ExnRef::_from_raw(store, raw))
+                }
                 HeapType::Func => {
                     let func_ref_id = data.read_u32(offset);
                     let func_ref_id = FuncRefTableId::from_raw(func_ref_id);
@@ -249,6 +253,17 @@ impl VMArrayRef {
                 let mut data = store.gc_store_mut()?.gc_object_data(self.as_gc_ref());
                 data.write_u32(offset, gc_ref.map_or(0, |r| r.as_raw_u32()));
             }
+            Val::ExnRef(e) => {
+                let raw = data.read_u32(offset);
+                let mut gc_ref = VMGcRef::from_raw_u32(raw);
+                let e = match e {
+                    Some(e) => Some(e.try_gc_ref(store)?.unchecked_copy()),
+                    None => None,
+                };
+                store.gc_store_mut()?.write_gc_ref(&mut gc_ref, e.as_ref());
+                let mut data = store.gc_store_mut()?.gc_object_data(self.as_gc_ref());
+                data.write_u32(offset, gc_ref.map_or(0, |r| r.as_raw_u32()));
+            }
 
             Val::FuncRef(f) => {
                 let func_ref = match f {
@@ -352,6 +367,16 @@ impl VMArrayRef {
                     .gc_object_data(self.as_gc_ref())
                     .write_u32(offset, x);
             }
+            Val::ExnRef(e) => {
+                let e = match e {
+                    None => 0,
+                    Some(e) => e.try_clone_gc_ref(store)?.as_raw_u32(),
+                };
+                store
+                    .gc_store_mut()?
+                    .gc_object_data(self.as_gc_ref())
+                    .write_u32(offset, e);
+            }
 
             Val::FuncRef(f) => {
                 let func_ref = match f {
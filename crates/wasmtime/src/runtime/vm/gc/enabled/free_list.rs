@@ -14,8 +14,8 @@ pub(crate) struct FreeList {
 
 /// Our minimum and maximum supported alignment. Every allocation is aligned to
 /// this.
-const ALIGN_U32: u32 = 8;
-const ALIGN_USIZE: usize = ALIGN_U32 as usize;
+pub(crate) const ALIGN_U32: u32 = 8;
+pub(crate) const ALIGN_USIZE: usize = ALIGN_U32 as usize;
 
 /// Our minimum allocation size.
 const MIN_BLOCK_SIZE: u32 = 24;
@@ -62,14 +62,19 @@ impl FreeList {
         let alloc_size = u32::try_from(layout.size())
             .err2anyhow()
             .context("requested allocation's size does not fit in a u32")?;
-        alloc_size
+        let alloc_size = alloc_size
             .checked_next_multiple_of(ALIGN_U32)
             .ok_or_else(|| {
                 anyhow!(
                     "failed to round allocation size of {alloc_size} up to next \
                      multiple of {ALIGN_USIZE}"
                 )
-            })
+            })?;
+
+        // Never hand out blocks smaller than `MIN_BLOCK_SIZE`. Deallocation
+        // assumes that any gap between blocks that is smaller than that is
+        // slop from a block that we chose not to split, and merges across it.
+        Ok(alloc_size.max(MIN_BLOCK_SIZE))
     }
 
     /// Find the first free block that can hold an allocation of the given size
@@ -0,0 +1,783 @@
+//! The mark-and-sweep collector.
+//!
+//! This is a simple, non-moving, stop-the-world tracing collector. Unlike the
+//! deferred reference-counting collector, it is capable of reclaiming cycles
+//! between GC objects.
+//!
+//! Collection happens in three phases:
+//!
+//! 1. **Mark roots:** Every GC root (Wasm stack roots discovered via stack
+//!    maps, globals, tables, and host-held roots) is marked and pushed onto the
+//!    mark stack.
+//!
+//! 2. **Trace:** Objects are popped off the mark stack and each of their
+//!    outgoing GC edges is marked and pushed onto the mark stack, if it wasn't
+//!    already marked. This continues until the mark stack is empty, at which
+//!    point every reachable object has been marked.
+//!
+//! 3. **Sweep:** Every allocated object that is not marked is unreachable and
+//!    is returned to the free list. If it was an `externref`, its host data is
+//!    removed from the host data table.
+//!
+//! Mark bits are kept in a side bitmap, with one bit for each potential object
+//! start in the heap, rather than in object headers. A second bitmap of the
+//! same shape records which heap indices are the start of an allocated object,
+//! which is what lets us find unmarked objects during sweeping.
+//!
+//! To find the outgoing edges of an object, we need to know which of its fields
+//! are GC references. That information is derived from the object's type in
+//! the engine's type registry and cached per type in a `TraceInfo` when the
+//! object is first allocated.
+//!
+//! Because this collector never moves objects and never mutates anything
+//! besides its side tables while Wasm is running, compiled Wasm code does not
+//! need to emit any read or write barriers for it.
+
+use super::free_list::{self, FreeList};
+use super::{VMArrayRef, VMGcObjectDataMut, VMStructRef};
+use crate::hash_map::HashMap;
+use crate::prelude::*;
+use crate::runtime::vm::{
+    ExternRefHostDataId, ExternRefHostDataTable, GarbageCollection, GcHeap, GcHeapObject,
+    GcProgress, GcRootsIter, GcRuntime, Mmap, TypedGcRef, VMExternRef, VMGcHeader, VMGcRef,
+};
+use crate::{Engine, EngineWeak};
+use core::ops::Range;
+use core::{alloc::Layout, any::Any, mem, num::NonZeroU32, num::NonZeroUsize};
+use wasmtime_environ::mark_sweep::{self, MarkSweepTypeLayouts};
+use wasmtime_environ::{
+    GcArrayLayout, GcLayout, GcStructLayout, GcTypeLayouts, VMGcKind, VMSharedTypeIndex,
+    WasmStorageType,
+};
+
+/// The number of objects to trace in each increment of a collection.
+const TRACE_INCREMENT: usize = 1024;
+
+/// The size of a GC reference stored inside the GC heap.
+const GC_REF_SIZE: u32 = 4;
+
+/// The mark-and-sweep collector.
+///
+/// This is a tracing collector, so it can reclaim garbage cycles.
+///
+/// This is not a moving collector; it doesn't have a nursery or do any
+/// compaction.
+#[derive(Default)]
+pub struct MarkSweepCollector {
+    layouts: MarkSweepTypeLayouts,
+}
+
+unsafe impl GcRuntime for MarkSweepCollector {
+    fn layouts(&self) -> &dyn GcTypeLayouts {
+        &self.layouts
+    }
+
+    fn new_gc_heap(&self, engine: &Engine) -> Result<Box<dyn GcHeap>> {
+        let heap = MarkSweepHeap::new(engine)?;
+        Ok(Box::new(heap) as _)
+    }
+}
+
+/// How to trace the GC references inside an object of a particular type.
+enum TraceInfo {
+    /// A struct type.
+    Struct {
+        /// The offsets of the struct's fields that hold GC references.
+        gc_ref_offsets: Box<[u32]>,
+    },
+
+    /// An array type.
+    Array {
+        /// The offset of the array's first element, if its elements are GC
+        /// references, or `None` if they are not.
+        gc_ref_elems_offset: Option<u32>,
+    },
+}
+
+/// A bitmap with one bit for every potential object start in a GC heap.
+///
+/// Every object in the heap is aligned to the free list's alignment, so we
+/// only need a bit for each multiple of that alignment.
+struct ObjectBitmap {
+    words: Vec<u64>,
+}
+
+impl ObjectBitmap {
+    /// Create a new, empty bitmap for a heap of the given size.
+    fn new(heap_len: usize) -> Self {
+        let slots = heap_len / free_list::ALIGN_USIZE;
+        let words = vec![0; slots.div_ceil(64)];
+        Self { words }
+    }
+
+    /// Get the word index and bit mask for the given heap index.
+    fn position(index: NonZeroU32) -> (usize, u64) {
+        let index = usize::try_from(index.get()).unwrap();
+        debug_assert_eq!(index % free_list::ALIGN_USIZE, 0);
+        let slot = index / free_list::ALIGN_USIZE;
+        (slot / 64, 1 << (slot % 64))
+    }
+
+    /// Set the bit for the given heap index, returning whether it was
+    /// previously unset.
+    fn insert(&mut self, index: NonZeroU32) -> bool {
+        let (word, mask) = Self::position(index);
+        let was_unset = self.words[word] & mask == 0;
+        self.words[word] |= mask;
+        was_unset
+    }
+
+    /// Unset the bit for the given heap index.
+    fn remove(&mut self, index: NonZeroU32) {
+        let (word, mask) = Self::position(index);
+        self.words[word] &= !mask;
+    }
+
+    /// Unset every bit in this bitmap.
+    fn clear(&mut self) {
+        self.words.fill(0);
+    }
+}
+
+/// A mark-and-sweep heap.
+struct MarkSweepHeap {
+    engine: EngineWeak,
+    no_gc_count: u64,
+    heap: Mmap,
+    free_list: FreeList,
+
+    /// Which heap indices are the start of a currently-allocated object.
+    allocated: ObjectBitmap,
+
+    /// Which heap indices are the start of an object that has been found to be
+    /// reachable during the current collection.
+    ///
+    /// This is only non-empty during collection.
+    marked: ObjectBitmap,
+
+    /// Objects that have been marked but whose outgoing edges have not been
+    /// traced yet.
+    ///
+    /// This is only non-empty during collection. It is part of the heap so that
+    /// we can reuse its allocation across collections.
+    mark_stack: Vec<VMGcRef>,
+
+    /// Cached tracing information for each type of object allocated in this
+    /// heap.
+    trace_infos: HashMap<VMSharedTypeIndex, TraceInfo>,
+}
+
+impl MarkSweepHeap {
+    /// Construct a new, default mark-and-sweep heap.
+    fn new(engine: &Engine) -> Result<Self> {
+        Self::with_capacity(engine, super::DEFAULT_GC_HEAP_CAPACITY)
+    }
+
+    /// Create a new mark-and-sweep heap with the given capacity.
+    fn with_capacity(engine: &Engine, capacity: usize) -> Result<Self> {
+        let heap = Mmap::with_at_least(capacity)?;
+        let free_list = FreeList::new(heap.len());
+        let allocated = ObjectBitmap::new(heap.len());
+        let marked = ObjectBitmap::new(heap.len());
+        Ok(Self {
+            engine: engine.weak(),
+            no_gc_count: 0,
+            heap,
+            free_list,
+            allocated,
+            marked,
+            mark_stack: Vec::new(),
+            trace_infos: HashMap::new(),
+        })
+    }
+
+    fn engine(&self) -> Engine {
+        self.engine
+            .upgrade()
+            .expect("the engine should outlive its GC heaps")
+    }
+
+    fn heap_slice(&self) -> &[u8] {
+        let ptr = self.heap.as_ptr();
+        let len = self.heap.len();
+        unsafe { core::slice::from_raw_parts(ptr, len) }
+    }
+
+    fn heap_slice_mut(&mut self) -> &mut [u8] {
+        let ptr = self.heap.as_mut_ptr();
+        let len = self.heap.len();
+        unsafe { core::slice::from_raw_parts_mut(ptr, len) }
+    }
+
+    /// Make sure that we have tracing information for the given type.
+    fn ensure_trace_info(&mut self, ty: VMSharedTypeIndex) {
+        if self.trace_infos.contains_key(&ty) {
+            return;
+        }
+
+        let engine = self.engine();
+        let types = engine.signatures();
+        let sub_ty = types
+            .borrow(ty)
+            .expect("should have a registered type for allocated GC objects");
+        let layout = types
+            .layout(ty)
+            .expect("should have a GC layout for allocated GC objects");
+
+        let info = match layout {
+            GcLayout::Struct(layout) => TraceInfo::Struct {
+                gc_ref_offsets: sub_ty
+                    .unwrap_struct()
+                    .fields
+                    .iter()
+                    .zip(layout.fields.iter())
+                    .filter_map(|(field, offset)| {
+                        if is_traced(&field.element_type) {
+                            Some(*offset)
+                        } else {
+                            None
+                        }
+                    })
+                    .collect(),
+            },
+            GcLayout::Array(layout) => TraceInfo::Array {
+                gc_ref_elems_offset: if is_traced(&sub_ty.unwrap_array().0.element_type) {
+                    Some(layout.base_size)
+                } else {
+                    None
+                },
+            },
+        };
+
+        let old_entry = self.trace_infos.insert(ty, info);
+        debug_assert!(old_entry.is_none());
+    }
+
+    fn dealloc(&mut self, gc_ref: VMGcRef) {
+        let index = gc_ref.as_heap_index().unwrap();
+        let size = self.header(&gc_ref).reserved_u27();
+        let layout = FreeList::layout(usize::try_from(size).unwrap());
+        self.allocated.remove(index);
+        self.free_list.dealloc(index, layout);
+    }
+
+    fn object_range(&self, gc_ref: &VMGcRef) -> Range<usize> {
+        let start = gc_ref.as_heap_index().unwrap().get();
+        let start = usize::try_from(start).unwrap();
+        let size = self.header(gc_ref).reserved_u27();
+        let size = usize::try_from(size).unwrap();
+        let end = start.checked_add(size).unwrap();
+        start..end
+    }
+
+    /// Index into this heap and get a shared reference to the `T` that `gc_ref`
+    /// points to.
+    ///
+    /// # Panics
+    ///
+    /// Panics on out of bounds or if the `gc_ref` is an `i31ref`.
+    fn index<T>(&self, gc_ref: &TypedGcRef<T>) -> &T
+    where
+        T: GcHeapObject,
+    {
+        assert!(!mem::needs_drop::<T>());
+        let gc_ref = gc_ref.as_untyped();
+        let start = gc_ref.as_heap_index().unwrap().get();
+        let start = usize::try_from(start).unwrap();
+        let len = mem::size_of::<T>();
+        let slice = &self.heap_slice()[start..][..len];
+        unsafe { &*(slice.as_ptr().cast::<T>()) }
+    }
+
+    /// Index into this heap and get an exclusive reference to the `T` that
+    /// `gc_ref` points to.
+    ///
+    /// # Panics
+    ///
+    /// Panics on out of bounds or if the `gc_ref` is an `i31ref`.
+    fn index_mut<T>(&mut self, gc_ref: &TypedGcRef<T>) -> &mut T
+    where
+        T: GcHeapObject,
+    {
+        assert!(!mem::needs_drop::<T>());
+        let gc_ref = gc_ref.as_untyped();
+        let start = gc_ref.as_heap_index().unwrap().get();
+        let start = usize::try_from(start).unwrap();
+        let len = mem::size_of::<T>();
+        let slice = &mut self.heap_slice_mut()[start..][..len];
+        unsafe { &mut *(slice.as_mut_ptr().cast::<T>()) }
+    }
+
+    /// Mark every object referenced by the given roots.
+    fn mark_roots(&mut self, roots: &mut GcRootsIter<'_>) {
+        debug_assert!(self.mark_stack.is_empty());
+        for root in roots {
+            let gc_ref = root.get();
+            log::trace!("Found GC root: {gc_ref:#p}");
+            mark(&mut self.marked, &mut self.mark_stack, gc_ref);
+        }
+    }
+
+    /// Mark the GC references contained within the given object.
+    fn trace_object(&mut self, gc_ref: &VMGcRef) {
+        let ty = match self.header(gc_ref).ty() {
+            Some(ty) => ty,
+            // `externref`s don't have types and never contain GC references.
+            None => return,
+        };
+
+        // Split the borrow of `self` so that we can read from the heap and our
+        // trace infos while updating our mark bits and mark stack.
+        let MarkSweepHeap {
+            heap,
+            marked,
+            mark_stack,
+            trace_infos,
+            ..
+        } = self;
+        let heap = unsafe { core::slice::from_raw_parts(heap.as_ptr(), heap.len()) };
+
+        match trace_infos.get(&ty).expect("should have trace info") {
+            TraceInfo::Struct { gc_ref_offsets } => {
+                for offset in gc_ref_offsets.iter() {
+                    let raw = read_u32(heap, gc_ref, *offset);
+                    if let Some(child) = VMGcRef::from_raw_u32(raw) {
+                        mark(marked, mark_stack, child);
+                    }
+                }
+            }
+            TraceInfo::Array {
+                gc_ref_elems_offset: Some(elems_offset),
+            } => {
+                let len = read_u32(heap, gc_ref, mark_sweep::ARRAY_LENGTH_OFFSET);
+                for i in 0..len {
+                    let raw = read_u32(heap, gc_ref, elems_offset + i * GC_REF_SIZE);
+                    if let Some(child) = VMGcRef::from_raw_u32(raw) {
+                        mark(marked, mark_stack, child);
+                    }
+                }
+            }
+            TraceInfo::Array {
+                gc_ref_elems_offset: None,
+            } => {}
+        }
+    }
+
+    /// Trace up to `limit` objects from the mark stack.
+    ///
+    /// Returns whether the mark stack is now empty.
+    fn trace(&mut self, limit: usize) -> bool {
+        for _ in 0..limit {
+            let Some(gc_ref) = self.mark_stack.pop() else {
+                return true;
+            };
+            self.trace_object(&gc_ref);
+        }
+        self.mark_stack.is_empty()
+    }
+
+    /// Reclaim every allocated object that was not marked.
+    fn sweep(&mut self, host_data_table: &mut ExternRefHostDataTable) {
+        debug_assert!(self.mark_stack.is_empty());
+
+        let mut dead: Vec<VMGcRef> = Vec::new();
+        for (i, (allocated, marked)) in self
+            .allocated
+            .words
+            .iter()
+            .zip(self.marked.words.iter())
+            .enumerate()
+        {
+            let mut unmarked = *allocated & !*marked;
+            while unmarked != 0 {
+                let bit = unmarked.trailing_zeros();
+                unmarked &= unmarked - 1;
+                let slot = i * 64 + usize::try_from(bit).unwrap();
+                let index = u32::try_from(slot * free_list::ALIGN_USIZE).unwrap();
+                dead.push(VMGcRef::from_heap_index(NonZeroU32::new(index).unwrap()).unwrap());
+            }
+        }
+
+        for gc_ref in dead {
+            log::trace!("Sweeping unreachable {gc_ref:#p}");
+
+            // If this was an `externref`, remove its associated entry from the
+            // host data table.
+            if let Some(externref) = gc_ref.as_typed::<VMMarkSweepExternRef>(self) {
+                let host_data_id = self.index(externref).host_data;
+                host_data_table.dealloc(host_data_id);
+            }
+
+            self.dealloc(gc_ref);
+        }
+
+        self.marked.clear();
+    }
+}
+
+/// Mark the given GC reference, pushing it onto the mark stack if it was not
+/// already marked.
+fn mark(marked: &mut ObjectBitmap, mark_stack: &mut Vec<VMGcRef>, gc_ref: VMGcRef) {
+    if gc_ref.is_i31() {
+        return;
+    }
+    let index = gc_ref.as_heap_index().unwrap();
+    if marked.insert(index) {
+        log::trace!("Marked {gc_ref:#p}");
+        mark_stack.push(gc_ref);
+    }
+}
+
+/// Read the raw, little-endian `u32` at the given offset within the given
+/// object.
+///
+/// Panics on out-of-bounds accesses.
+fn read_u32(heap: &[u8], gc_ref: &VMGcRef, offset: u32) -> u32 {
+    let start = gc_ref.as_heap_index().unwrap().get();
+    let start = usize::try_from(start).unwrap();
+    let offset = usize::try_from(offset).unwrap();
+    let bytes = &heap[start..][offset..][..mem::size_of::<u32>()];
+    u32::from_le_bytes(bytes.try_into().unwrap())
+}
+
+/// Is a field or element of the given type a GC reference that we need to
+/// trace?
+fn is_traced(ty: &WasmStorageType) -> bool {
+    match ty {
+        WasmStorageType::Val(v) => v.is_vmgcref_type_and_not_i31(),
+        WasmStorageType::I8 | WasmStorageType::I16 => false,
+    }
+}
+
+/// The common header for all arrays in the mark-and-sweep collector.
+#[repr(C)]
+struct VMMarkSweepArrayHeader {
+    header: VMGcHeader,
+    length: u32,
+}
+
+unsafe impl GcHeapObject for VMMarkSweepArrayHeader {
+    #[inline]
+    fn is(header: &VMGcHeader) -> bool {
+        header.kind() == VMGcKind::ArrayRef
+    }
+}
+
+/// The representation of an `externref` in the mark-and-sweep collector.
+#[repr(C)]
+struct VMMarkSweepExternRef {
+    header: VMGcHeader,
+    host_data: ExternRefHostDataId,
+}
+
+unsafe impl GcHeapObject for VMMarkSweepExternRef {
+    #[inline]
+    fn is(header: &VMGcHeader) -> bool {
+        header.kind() == VMGcKind::ExternRef
+    }
+}
+
+/// Convert a generic `externref` to a typed reference to our concrete
+/// `externref` type.
+fn externref_to_mark_sweep(externref: &VMExternRef) -> &TypedGcRef<VMMarkSweepExternRef> {
+    let gc_ref = externref.as_gc_ref();
+    debug_assert!(!gc_ref.is_i31());
+    gc_ref.as_typed_unchecked()
+}
+
+unsafe impl GcHeap for MarkSweepHeap {
+    fn as_any(&self) -> &dyn Any {
+        self as _
+    }
+
+    fn as_any_mut(&mut self) -> &mut dyn Any {
+        self as _
+    }
+
+    fn enter_no_gc_scope(&mut self) {
+        self.no_gc_count += 1;
+    }
+
+    fn exit_no_gc_scope(&mut self) {
+        self.no_gc_count -= 1;
+    }
+
+    fn header(&self, gc_ref: &VMGcRef) -> &VMGcHeader {
+        self.index(gc_ref.as_typed_unchecked())
+    }
+
+    fn clone_gc_ref(&mut self, gc_ref: &VMGcRef) -> VMGcRef {
+        gc_ref.unchecked_copy()
+    }
+
+    fn write_gc_ref(
+        &mut self,
+        _host_data_table: &mut ExternRefHostDataTable,
+        destination: &mut Option<VMGcRef>,
+        source: Option<&VMGcRef>,
+    ) {
+        // No barriers are needed: reachability is determined entirely by
+        // tracing at collection time.
+        *destination = source.map(|s| s.unchecked_copy());
+    }
+
+    fn expose_gc_ref_to_wasm(&mut self, _gc_ref: VMGcRef) {
+        // Nothing to do: on-stack references are found via stack maps during
+        // collection.
+    }
+
+    fn need_gc_before_entering_wasm(&self, _num_gc_refs: NonZeroUsize) -> bool {
+        false
+    }
+
+    fn alloc_externref(&mut self, host_data: ExternRefHostDataId) -> Result<Option<VMExternRef>> {
+        let gc_ref = match self.alloc_raw(
+            VMGcHeader::externref(),
+            Layout::new::<VMMarkSweepExternRef>(),
+        )? {
+            None => return Ok(None),
+            Some(gc_ref) => gc_ref,
+        };
+        self.index_mut::<VMMarkSweepExternRef>(gc_ref.as_typed_unchecked())
+            .host_data = host_data;
+        Ok(Some(gc_ref.into_externref_unchecked()))
+    }
+
+    fn externref_host_data(&self, externref: &VMExternRef) -> ExternRefHostDataId {
+        let typed_ref = externref_to_mark_sweep(externref);
+        self.index(typed_ref).host_data
+    }
+
+    fn alloc_raw(&mut self, mut header: VMGcHeader, layout: Layout) -> Result<Option<VMGcRef>> {
+        let size = u32::try_from(layout.size()).unwrap();
+        if !VMGcKind::value_fits_in_unused_bits(size) {
+            return Err(crate::Trap::AllocationTooLarge.into_anyhow());
+        }
+
+        if let Some(ty) = header.ty() {
+            self.ensure_trace_info(ty);
+        }
+
+        let index = match self.free_list.alloc(layout)? {
+            None => return Ok(None),
+            Some(index) => index,
+        };
+        let gc_ref = VMGcRef::from_heap_index(index).unwrap();
+
+        debug_assert_eq!(header.reserved_u27(), 0);
+        header.set_reserved_u27(size);
+        *self.index_mut(gc_ref.as_typed_unchecked::<VMGcHeader>()) = header;
+
+        let is_new = self.allocated.insert(index);
+        debug_assert!(is_new);
+
+        log::trace!("Allocated {gc_ref:#p}");
+        Ok(Some(gc_ref))
+    }
+
+    fn alloc_uninit_struct(
+        &mut self,
+        ty: VMSharedTypeIndex,
+        layout: &GcStructLayout,
+    ) -> Result<Option<VMStructRef>> {
+        let gc_ref = match self.alloc_raw(
+            VMGcHeader::from_kind_and_index(VMGcKind::StructRef, ty),
+            layout.layout(),
+        )? {
+            None => return Ok(None),
+            Some(gc_ref) => gc_ref,
+        };
+        Ok(Some(gc_ref.into_structref_unchecked()))
+    }
+
+    fn dealloc_uninit_struct(&mut self, structref: VMStructRef) {
+        self.dealloc(structref.into());
+    }
+
+    fn gc_object_data(&mut self, gc_ref: &VMGcRef) -> VMGcObjectDataMut<'_> {
+        let range = self.object_range(gc_ref);
+        let data = &mut self.heap_slice_mut()[range];
+        VMGcObjectDataMut::new(data)
+    }
+
+    fn gc_object_data_pair(
+        &mut self,
+        a: &VMGcRef,
+        b: &VMGcRef,
+    ) -> (VMGcObjectDataMut<'_>, VMGcObjectDataMut<'_>) {
+        assert_ne!(a, b);
+
+        let a_range = self.object_range(a);
+        let b_range = self.object_range(b);
+
+        // Assert that the two objects do not overlap.
+        assert!(a_range.start <= a_range.end);
+        assert!(b_range.start <= b_range.end);
+        assert!(a_range.end <= b_range.start || b_range.end <= a_range.start);
+
+        let (a_data, b_data) = if a_range.start < b_range.start {
+            let (a_half, b_half) = self.heap_slice_mut().split_at_mut(b_range.start);
+            let b_len = b_range.end - b_range.start;
+            (&mut a_half[a_range], &mut b_half[..b_len])
+        } else {
+            let (b_half, a_half) = self.heap_slice_mut().split_at_mut(a_range.start);
+            let a_len = a_range.end - a_range.start;
+            (&mut a_half[..a_len], &mut b_half[b_range])
+        };
+
+        (
+            VMGcObjectDataMut::new(a_data),
+            VMGcObjectDataMut::new(b_data),
+        )
+    }
+
+    fn alloc_uninit_array(
+        &mut self,
+        ty: VMSharedTypeIndex,
+        length: u32,
+        layout: &GcArrayLayout,
+    ) -> Result<Option<VMArrayRef>> {
+        let gc_ref = match self.alloc_raw(
+            VMGcHeader::from_kind_and_index(VMGcKind::ArrayRef, ty),
+            layout.layout(length),
+        )? {
+            None => return Ok(None),
+            Some(gc_ref) => gc_ref,
+        };
+        self.index_mut::<VMMarkSweepArrayHeader>(gc_ref.as_typed_unchecked())
+            .length = length;
+        Ok(Some(gc_ref.into_arrayref_unchecked()))
+    }
+
+    fn dealloc_uninit_array(&mut self, arrayref: VMArrayRef) {
+        self.dealloc(arrayref.into())
+    }
+
+    fn array_len(&self, arrayref: &VMArrayRef) -> u32 {
+        debug_assert!(arrayref
+            .as_gc_ref()
+            .is_typed::<VMMarkSweepArrayHeader>(self));
+        self.index::<VMMarkSweepArrayHeader>(arrayref.as_gc_ref().as_typed_unchecked())
+            .length
+    }
+
+    fn gc<'a>(
+        &'a mut self,
+        roots: GcRootsIter<'a>,
+        host_data_table: &'a mut ExternRefHostDataTable,
+    ) -> Box<dyn GarbageCollection<'a> + 'a> {
+        assert_eq!(self.no_gc_count, 0, "Cannot GC inside a no-GC scope!");
+        Box::new(MarkSweepCollection {
+            roots,
+            host_data_table,
+            heap: self,
+            phase: MarkSweepCollectionPhase::MarkRoots,
+        })
+    }
+
+    unsafe fn vmctx_gc_heap_base(&self) -> *mut u8 {
+        self.heap.as_ptr().cast_mut()
+    }
+
+    unsafe fn vmctx_gc_heap_bound(&self) -> usize {
+        self.heap.len()
+    }
+
+    unsafe fn vmctx_gc_heap_data(&self) -> *mut u8 {
+        // This collector has no data that is accessed by JIT code.
+        core::ptr::null_mut()
+    }
+
+    #[cfg(feature = "pooling-allocator")]
+    fn reset(&mut self) {
+        let MarkSweepHeap {
+            engine: _,
+            no_gc_count,
+            heap: _,
+            free_list,
+            allocated,
+            marked,
+            mark_stack,
+            trace_infos,
+        } = self;
+
+        *no_gc_count = 0;
+        free_list.reset();
+        allocated.clear();
+        marked.clear();
+        mark_stack.clear();
+
+        // Type indices may be unregistered and reused for new types by the
+        // time this heap is used again, so forget our cached trace infos.
+        trace_infos.clear();
+    }
+}
+
+struct MarkSweepCollection<'a> {
+    roots: GcRootsIter<'a>,
+    host_data_table: &'a mut ExternRefHostDataTable,
+    heap: &'a mut MarkSweepHeap,
+    phase: MarkSweepCollectionPhase,
+}
+
+enum MarkSweepCollectionPhase {
+    MarkRoots,
+    Trace,
+    Sweep,
+    Done,
+}
+
+impl<'a> GarbageCollection<'a> for MarkSweepCollection<'a> {
+    fn collect_increment(&mut self) -> GcProgress {
+        match self.phase {
+            MarkSweepCollectionPhase::MarkRoots => {
+                log::trace!("Begin mark-and-sweep root marking");
+                self.heap.mark_roots(&mut self.roots);
+                log::trace!("End mark-and-sweep root marking");
+                self.phase = MarkSweepCollectionPhase::Trace;
+                GcProgress::Continue
+            }
+            MarkSweepCollectionPhase::Trace => {
+                log::trace!("Begin mark-and-sweep trace increment");
+                let done = self.heap.trace(TRACE_INCREMENT);
+                log::trace!("End mark-and-sweep trace increment");
+                if done {
+                    self.phase = MarkSweepCollectionPhase::Sweep;
+                }
+                GcProgress::Continue
+            }
+            MarkSweepCollectionPhase::Sweep => {
+                log::trace!("Begin mark-and-sweep sweep");
+                self.heap.sweep(self.host_data_table);
+                log::trace!("End mark-and-sweep sweep");
+                self.phase = MarkSweepCollectionPhase::Done;
+                GcProgress::Complete
+            }
+            MarkSweepCollectionPhase::Done => GcProgress::Complete,
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn object_bitmap() {
+        let mut bitmap = ObjectBitmap::new(1 << 12);
+        let a = NonZeroU32::new(8).unwrap();
+        let b = NonZeroU32::new(8 * 64).unwrap();
+
+        assert!(bitmap.insert(a));
+        assert!(!bitmap.insert(a));
+        assert!(bitmap.insert(b));
+        assert_eq!(bitmap.words[0], 1 << 1);
+        assert_eq!(bitmap.words[1], 1);
+
+        bitmap.remove(a);
+        assert!(bitmap.insert(a));
+
+        bitmap.clear();
+        assert!(bitmap.words.iter().all(|w| *w == 0));
+    }
+}
@@ -0,0 +1,451 @@
+//! The null collector.
+//!
+//! The null collector bump allocates objects until it runs out of space, at
+//! which point allocations fail. It never collects garbage. This is useful for
+//! short-lived programs whose GC heaps are thrown away before they would ever
+//! fill up, and as a baseline when benchmarking other collectors.
+//!
+//! The bump pointer lives in a `VMNullHeapData` that compiled Wasm code can
+//! access via the `VMContext`'s GC heap data pointer, so that Wasm can do
+//! inline bump allocation without calling out to the runtime.
+
+use super::free_list;
+use super::{VMArrayRef, VMGcObjectDataMut, VMStructRef};
+use crate::prelude::*;
+use crate::runtime::vm::{
+    ExternRefHostDataId, ExternRefHostDataTable, GarbageCollection, GcHeap, GcHeapObject,
+    GcProgress, GcRootsIter, GcRuntime, Mmap, TypedGcRef, VMExternRef, VMGcHeader, VMGcRef,
+};
+use crate::Engine;
+use core::ops::Range;
+use core::{alloc::Layout, any::Any, cell::UnsafeCell, mem, num::NonZeroU32, num::NonZeroUsize};
+use wasmtime_environ::null::NullTypeLayouts;
+use wasmtime_environ::{GcArrayLayout, GcStructLayout, GcTypeLayouts, VMGcKind, VMSharedTypeIndex};
+
+/// The null collector.
+#[derive(Default)]
+pub struct NullCollector {
+    layouts: NullTypeLayouts,
+}
+
+unsafe impl GcRuntime for NullCollector {
+    fn layouts(&self) -> &dyn GcTypeLayouts {
+        &self.layouts
+    }
+
+    fn new_gc_heap(&self, _engine: &Engine) -> Result<Box<dyn GcHeap>> {
+        let heap = NullHeap::new()?;
+        Ok(Box::new(heap) as _)
+    }
+}
+
+/// The data for a null heap that is shared with compiled Wasm code.
+#[repr(C)]
+struct VMNullHeapData {
+    /// The heap index where the next object will be allocated.
+    ///
+    /// This is always aligned to the free list's alignment, which is the
+    /// maximum alignment of any GC object, so that compiled Wasm code doesn't
+    /// need to align it up before allocating.
+    next: UnsafeCell<NonZeroU32>,
+}
+
+// Although this contains an `UnsafeCell`, that is just for allowing the field
+// to be written to by JIT code, and it is only read/modified when we have
+// access to an appropriate borrow of the heap.
+unsafe impl Send for VMNullHeapData {}
+unsafe impl Sync for VMNullHeapData {}
+
+const _: () = {
+    use core::mem::offset_of;
+    // NB: `VMOffsets` doesn't depend on the pointer size for this field.
+    assert!(offset_of!(VMNullHeapData, next) == 0);
+};
+
+/// A null heap.
+struct NullHeap {
+    no_gc_count: u64,
+    heap: Mmap,
+
+    /// Boxed so that its address is stable for compiled Wasm code.
+    heap_data: Box<VMNullHeapData>,
+}
+
+impl NullHeap {
+    /// Construct a new, default null heap.
+    fn new() -> Result<Self> {
+        Self::with_capacity(super::DEFAULT_GC_HEAP_CAPACITY)
+    }
+
+    /// Create a new null heap with the given capacity.
+    fn with_capacity(capacity: usize) -> Result<Self> {
+        let heap = Mmap::with_at_least(capacity)?;
+        Ok(Self {
+            no_gc_count: 0,
+            heap,
+            heap_data: Box::new(VMNullHeapData {
+                next: UnsafeCell::new(Self::initial_next()),
+            }),
+        })
+    }
+
+    /// The first index that we allocate at.
+    ///
+    /// Index zero is the null reference, so we start at the first non-zero
+    /// index that is suitably aligned.
+    fn initial_next() -> NonZeroU32 {
+        NonZeroU32::new(free_list::ALIGN_U32).unwrap()
+    }
+
+    fn next(&self) -> NonZeroU32 {
+        unsafe { *self.heap_data.next.get() }
+    }
+
+    fn set_next(&mut self, next: NonZeroU32) {
+        *self.heap_data.next.get_mut() = next;
+    }
+
+    fn heap_slice(&self) -> &[u8] {
+        let ptr = self.heap.as_ptr();
+        let len = self.heap.len();
+        unsafe { core::slice::from_raw_parts(ptr, len) }
+    }
+
+    fn heap_slice_mut(&mut self) -> &mut [u8] {
+        let ptr = self.heap.as_mut_ptr();
+        let len = self.heap.len();
+        unsafe { core::slice::from_raw_parts_mut(ptr, len) }
+    }
+
+    fn object_range(&self, gc_ref: &VMGcRef) -> Range<usize> {
+        let start = gc_ref.as_heap_index().unwrap().get();
+        let start = usize::try_from(start).unwrap();
+        let size = self.header(gc_ref).reserved_u27();
+        let size = usize::try_from(size).unwrap();
+        let end = start.checked_add(size).unwrap();
+        start..end
+    }
+
+    /// Index into this heap and get a shared reference to the `T` that `gc_ref`
+    /// points to.
+    ///
+    /// # Panics
+    ///
+    /// Panics on out of bounds or if the `gc_ref` is an `i31ref`.
+    fn index<T>(&self, gc_ref: &TypedGcRef<T>) -> &T
+    where
+        T: GcHeapObject,
+    {
+        assert!(!mem::needs_drop::<T>());
+        let gc_ref = gc_ref.as_untyped();
+        let start = gc_ref.as_heap_index().unwrap().get();
+        let start = usize::try_from(start).unwrap();
+        let len = mem::size_of::<T>();
+        let slice = &self.heap_slice()[start..][..len];
+        unsafe { &*(slice.as_ptr().cast::<T>()) }
+    }
+
+    /// Index into this heap and get an exclusive reference to the `T` that
+    /// `gc_ref` points to.
+    ///
+    /// # Panics
+    ///
+    /// Panics on out of bounds or if the `gc_ref` is an `i31ref`.
+    fn index_mut<T>(&mut self, gc_ref: &TypedGcRef<T>) -> &mut T
+    where
+        T: GcHeapObject,
+    {
+        assert!(!mem::needs_drop::<T>());
+        let gc_ref = gc_ref.as_untyped();
+        let start = gc_ref.as_heap_index().unwrap().get();
+        let start = usize::try_from(start).unwrap();
+        let len = mem::size_of::<T>();
+        let slice = &mut self.heap_slice_mut()[start..][..len];
+        unsafe { &mut *(slice.as_mut_ptr().cast::<T>()) }
+    }
+}
+
+/// The common header for all arrays in the null collector.
+#[repr(C)]
+struct VMNullArrayHeader {
+    header: VMGcHeader,
+    length: u32,
+}
+
+unsafe impl GcHeapObject for VMNullArrayHeader {
+    #[inline]
+    fn is(header: &VMGcHeader) -> bool {
+        header.kind() == VMGcKind::ArrayRef
+    }
+}
+
+/// The representation of an `externref` in the null collector.
+#[repr(C)]
+struct VMNullExternRef {
+    header: VMGcHeader,
+    host_data: ExternRefHostDataId,
+}
+
+unsafe impl GcHeapObject for VMNullExternRef {
+    #[inline]
+    fn is(header: &VMGcHeader) -> bool {
+        header.kind() == VMGcKind::ExternRef
+    }
+}
+
+/// Convert a generic `externref` to a typed reference to our concrete
+/// `externref` type.
+fn externref_to_null(externref: &VMExternRef) -> &TypedGcRef<VMNullExternRef> {
+    let gc_ref = externref.as_gc_ref();
+    debug_assert!(!gc_ref.is_i31());
+    gc_ref.as_typed_unchecked()
+}
+
+unsafe impl GcHeap for NullHeap {
+    fn as_any(&self) -> &dyn Any {
+        self as _
+    }
+
+    fn as_any_mut(&mut self) -> &mut dyn Any {
+        self as _
+    }
+
+    fn enter_no_gc_scope(&mut self) {
+        self.no_gc_count += 1;
+    }
+
+    fn exit_no_gc_scope(&mut self) {
+        self.no_gc_count -= 1;
+    }
+
+    fn header(&self, gc_ref: &VMGcRef) -> &VMGcHeader {
+        self.index(gc_ref.as_typed_unchecked())
+    }
+
+    fn clone_gc_ref(&mut self, gc_ref: &VMGcRef) -> VMGcRef {
+        gc_ref.unchecked_copy()
+    }
+
+    fn write_gc_ref(
+        &mut self,
+        _host_data_table: &mut ExternRefHostDataTable,
+        destination: &mut Option<VMGcRef>,
+        source: Option<&VMGcRef>,
+    ) {
+        *destination = source.map(|s| s.unchecked_copy());
+    }
+
+    fn expose_gc_ref_to_wasm(&mut self, _gc_ref: VMGcRef) {
+        // Nothing to do: we never collect, so we don't need to keep track of
+        // which references Wasm might be holding onto.
+    }
+
+    fn need_gc_before_entering_wasm(&self, _num_gc_refs: NonZeroUsize) -> bool {
+        false
+    }
+
+    fn alloc_externref(&mut self, host_data: ExternRefHostDataId) -> Result<Option<VMExternRef>> {
+        let gc_ref =
+            match self.alloc_raw(VMGcHeader::externref(), Layout::new::<VMNullExternRef>())? {
+                None => return Ok(None),
+                Some(gc_ref) => gc_ref,
+            };
+        self.index_mut::<VMNullExternRef>(gc_ref.as_typed_unchecked())
+            .host_data = host_data;
+        Ok(Some(gc_ref.into_externref_unchecked()))
+    }
+
+    fn externref_host_data(&self, externref: &VMExternRef) -> ExternRefHostDataId {
+        let typed_ref = externref_to_null(externref);
+        self.index(typed_ref).host_data
+    }
+
+    fn alloc_raw(&mut self, mut header: VMGcHeader, layout: Layout) -> Result<Option<VMGcRef>> {
+        let size = u32::try_from(layout.size()).unwrap();
+        if !VMGcKind::value_fits_in_unused_bits(size) {
+            return Err(crate::Trap::AllocationTooLarge.into_anyhow());
+        }
+        ensure!(
+            layout.align() <= free_list::ALIGN_USIZE,
+            "requested allocation's alignment of {} is greater than max supported \
+             alignment of {}",
+            layout.align(),
+            free_list::ALIGN_USIZE,
+        );
+
+        // Bump the `next` index, keeping it aligned for the next allocation.
+        let start = self.next().get();
+        let end = match start
+            .checked_add(size)
+            .and_then(|end| end.checked_next_multiple_of(free_list::ALIGN_U32))
+        {
+            Some(end) if usize::try_from(end).unwrap() <= self.heap.len() => end,
+            _ => return Ok(None),
+        };
+        self.set_next(NonZeroU32::new(end).unwrap());
+
+        let gc_ref = VMGcRef::from_heap_index(NonZeroU32::new(start).unwrap()).unwrap();
+
+        debug_assert_eq!(header.reserved_u27(), 0);
+        header.set_reserved_u27(size);
+        *self.index_mut(gc_ref.as_typed_unchecked::<VMGcHeader>()) = header;
+
+        log::trace!("Allocated {gc_ref:#p}");
+        Ok(Some(gc_ref))
+    }
+
+    fn alloc_uninit_struct(
+        &mut self,
+        ty: VMSharedTypeIndex,
+        layout: &GcStructLayout,
+    ) -> Result<Option<VMStructRef>> {
+        let gc_ref = match self.alloc_raw(
+            VMGcHeader::from_kind_and_index(VMGcKind::StructRef, ty),
+            layout.layout(),
+        )? {
+            None => return Ok(None),
+            Some(gc_ref) => gc_ref,
+        };
+        Ok(Some(gc_ref.into_structref_unchecked()))
+    }
+
+    fn dealloc_uninit_struct(&mut self, _structref: VMStructRef) {
+        // We never reclaim memory.
+    }
+
+    fn gc_object_data(&mut self, gc_ref: &VMGcRef) -> VMGcObjectDataMut<'_> {
+        let range = self.object_range(gc_ref);
+        let data = &mut self.heap_slice_mut()[range];
+        VMGcObjectDataMut::new(data)
+    }
+
+    fn gc_object_data_pair(
+        &mut self,
+        a: &VMGcRef,
+        b: &VMGcRef,
+    ) -> (VMGcObjectDataMut<'_>, VMGcObjectDataMut<'_>) {
+        assert_ne!(a, b);
+
+        let a_range = self.object_range(a);
+        let b_range = self.object_range(b);
+
+        // Assert that the two objects do not overlap.
+        assert!(a_range.start <= a_range.end);
+        assert!(b_range.start <= b_range.end);
+        assert!(a_range.end <= b_range.start || b_range.end <= a_range.start);
+
+        let (a_data, b_data) = if a_range.start < b_range.start {
+            let (a_half, b_half) = self.heap_slice_mut().split_at_mut(b_range.start);
+            let b_len = b_range.end - b_range.start;
+            (&mut a_half[a_range], &mut b_half[..b_len])
+        } else {
+            let (b_half, a_half) = self.heap_slice_mut().split_at_mut(a_range.start);
+            let a_len = a_range.end - a_range.start;
+            (&mut a_half[..a_len], &mut b_half[b_range])
+        };
+
+        (
+            VMGcObjectDataMut::new(a_data),
+            VMGcObjectDataMut::new(b_data),
+        )
+    }
+
+    fn alloc_uninit_array(
+        &mut self,
+        ty: VMSharedTypeIndex,
+        length: u32,
+        layout: &GcArrayLayout,
+    ) -> Result<Option<VMArrayRef>> {
+        let gc_ref = match self.alloc_raw(
+            VMGcHeader::from_kind_and_index(VMGcKind::ArrayRef, ty),
+            layout.layout(length),
+        )? {
+            None => return Ok(None),
+            Some(gc_ref) => gc_ref,
+        };
+        self.index_mut::<VMNullArrayHeader>(gc_ref.as_typed_unchecked())
+            .length = length;
+        Ok(Some(gc_ref.into_arrayref_unchecked()))
+    }
+
+    fn dealloc_uninit_array(&mut self, _arrayref: VMArrayRef) {
+        // We never reclaim memory.
+    }
+
+    fn array_len(&self, arrayref: &VMArrayRef) -> u32 {
+        debug_assert!(arrayref.as_gc_ref().is_typed::<VMNullArrayHeader>(self));
+        self.index::<VMNullArrayHeader>(arrayref.as_gc_ref().as_typed_unchecked())
+            .length
+    }
+
+    fn gc<'a>(
+        &'a mut self,
+        _roots: GcRootsIter<'a>,
+        _host_data_table: &'a mut ExternRefHostDataTable,
+    ) -> Box<dyn GarbageCollection<'a> + 'a> {
+        assert_eq!(self.no_gc_count, 0, "Cannot GC inside a no-GC scope!");
+        Box::new(NullCollection {})
+    }
+
+    unsafe fn vmctx_gc_heap_base(&self) -> *mut u8 {
+        self.heap.as_ptr().cast_mut()
+    }
+
+    unsafe fn vmctx_gc_heap_bound(&self) -> usize {
+        self.heap.len()
+    }
+
+    unsafe fn vmctx_gc_heap_data(&self) -> *mut u8 {
+        let ptr: *const VMNullHeapData = &*self.heap_data;
+        ptr.cast_mut().cast::<u8>()
+    }
+
+    #[cfg(feature = "pooling-allocator")]
+    fn reset(&mut self) {
+        let NullHeap {
+            no_gc_count,
+            heap: _,
+            heap_data: _,
+        } = self;
+
+        *no_gc_count = 0;
+        self.set_next(Self::initial_next());
+    }
+}
+
+/// A garbage collection that never collects anything.
+struct NullCollection {}
+
+impl<'a> GarbageCollection<'a> for NullCollection {
+    fn collect_increment(&mut self) -> GcProgress {
+        GcProgress::Complete
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn bump_allocate_until_exhausted() -> Result<()> {
+        let mut heap = NullHeap::with_capacity(64)?;
+        let capacity = u32::try_from(heap.heap.len()).unwrap();
+        let layout = Layout::from_size_align(12, 4).unwrap();
+
+        let mut last = None;
+        while let Some(gc_ref) = heap.alloc_raw(VMGcHeader::externref(), layout)? {
+            let index = gc_ref.as_heap_index().unwrap().get();
+            assert_eq!(index % free_list::ALIGN_U32, 0);
+            assert!(index + 12 <= capacity);
+            if let Some(last) = last {
+                assert!(index >= last + 12);
+            }
+            last = Some(index);
+        }
+        assert!(last.is_some());
+
+        // Once exhausted, the heap stays exhausted.
+        assert!(heap.alloc_raw(VMGcHeader::externref(), layout)?.is_none());
+        Ok(())
+    }
+}
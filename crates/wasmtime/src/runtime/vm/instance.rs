@@ -10,11 +10,11 @@ use crate::runtime::vm::table::{Table, TableElement, TableElementType};
 use crate::runtime::vm::vmcontext::{
     VMBuiltinFunctionsArray, VMContext, VMFuncRef, VMFunctionImport, VMGlobalDefinition,
     VMGlobalImport, VMMemoryDefinition, VMMemoryImport, VMOpaqueContext, VMRuntimeLimits,
-    VMTableDefinition, VMTableImport,
+    VMTableDefinition, VMTableImport, VMTagDefinition, VMTagImport,
 };
 use crate::runtime::vm::{
-    ExportFunction, ExportGlobal, ExportMemory, ExportTable, GcStore, Imports, ModuleRuntimeInfo,
-    SendSyncPtr, VMFunctionBody, VMGcRef, VMStore, WasmFault,
+    ExportFunction, ExportGlobal, ExportMemory, ExportTable, ExportTag, GcStore, Imports,
+    ModuleRuntimeInfo, SendSyncPtr, VMFunctionBody, VMGcRef, VMStore, WasmFault,
 };
 use alloc::sync::Arc;
 use core::alloc::Layout;
@@ -26,10 +26,11 @@ use core::{mem, ptr};
 use sptr::Strict;
 use wasmtime_environ::{
     packed_option::ReservedValue, DataIndex, DefinedGlobalIndex, DefinedMemoryIndex,
-    DefinedTableIndex, ElemIndex, EntityIndex, EntityRef, EntitySet, FuncIndex, GlobalIndex,
-    HostPtr, MemoryIndex, MemoryPlan, Module, ModuleInternedTypeIndex, PrimaryMap, PtrSize,
-    TableIndex, TableInitialValue, TableSegmentElements, Trap, VMOffsets, VMSharedTypeIndex,
-    WasmHeapTopType, VMCONTEXT_MAGIC,
+    DefinedTableIndex, DefinedTagIndex, ElemIndex, EngineOrModuleTypeIndex, EntityIndex, EntityRef,
+    EntitySet, FuncIndex, GlobalIndex, HostPtr, MemoryIndex, MemoryPlan, Module,
+    ModuleInternedTypeIndex, PrimaryMap, PtrSize, TableIndex, TableInitialValue,
+    TableSegmentElements, Tag, TagIndex, Trap, VMOffsets, VMSharedTypeIndex, WasmHeapTopType,
+    VMCONTEXT_MAGIC,
 };
 #[cfg(feature = "wmemcheck")]
 use wasmtime_wmemcheck::Wmemcheck;
@@ -301,6 +302,16 @@ impl Instance {
         unsafe { &*self.vmctx_plus_offset(self.offsets().vmctx_vmglobal_import(index)) }
     }
 
+    /// Return the indexed `VMTagImport`.
+    fn imported_tag(&self, index: TagIndex) -> &VMTagImport {
+        unsafe { &*self.vmctx_plus_offset(self.offsets().vmctx_vmtag_import(index)) }
+    }
+
+    /// Return the indexed `VMTagDefinition`.
+    fn tag_ptr(&mut self, index: DefinedTagIndex) -> *mut VMTagDefinition {
+        unsafe { self.vmctx_plus_offset_mut(self.offsets().vmctx_vmtag_definition(index)) }
+    }
+
     /// Return the indexed `VMTableDefinition`.
     #[allow(dead_code)]
     fn table(&mut self, index: DefinedTableIndex) -> VMTableDefinition {
@@ -604,6 +615,22 @@ impl Instance {
         }
     }
 
+    pub(crate) fn get_exported_tag(&mut self, index: TagIndex) -> ExportTag {
+        let definition = if let Some(def_index) = self.env_module().defined_tag_index(index) {
+            self.tag_ptr(def_index)
+        } else {
+            self.imported_tag(index).from
+        };
+        let signature = unsafe { (*definition).type_index };
+        ExportTag {
+            definition,
+            vmctx: self.vmctx(),
+            tag: Tag {
+                signature: EngineOrModuleTypeIndex::Engine(signature),
+            },
+        }
+    }
+
     /// Return an iterator over the exports of this instance.
     ///
     /// Specifically, it provides access to the key-value pairs, where the keys
@@ -942,6 +969,15 @@ impl Instance {
                             VMGcRef::from_raw_u32(raw.get_anyref())
                         }),
                     )?,
+                    WasmHeapTopType::Exn => table.init_gc_refs(
+                        dst,
+                        exprs.iter().map(|expr| unsafe {
+                            let raw = const_evaluator
+                                .eval(&mut context, expr)
+                                .expect("const expr should be valid");
+                            VMGcRef::from_raw_u32(raw.get_exnref())
+                        }),
+                    )?,
                     WasmHeapTopType::Func => table.init_func(
                         dst,
                         exprs.iter().map(|expr| unsafe {
@@ -952,6 +988,9 @@ impl Instance {
                                 .cast()
                         }),
                     )?,
+                    WasmHeapTopType::Cont => {
+                        unreachable!("tables of continuation references are rejected")
+                    }
                 }
             }
         }
@@ -1280,6 +1319,12 @@ impl Instance {
             self.vmctx_plus_offset_mut(offsets.vmctx_imported_globals_begin()),
             imports.globals.len(),
         );
+        debug_assert_eq!(imports.tags.len(), module.num_imported_tags);
+        ptr::copy_nonoverlapping(
+            imports.tags.as_ptr(),
+            self.vmctx_plus_offset_mut(offsets.vmctx_imported_tags_begin()),
+            imports.tags.len(),
+        );
 
         // N.B.: there is no need to initialize the funcrefs array because we
         // eagerly construct each element in it whenever asked for a reference
@@ -1325,6 +1370,14 @@ impl Instance {
         for (index, _init) in module.global_initializers.iter() {
             ptr::write(self.global_ptr(index), VMGlobalDefinition::new());
         }
+
+        // Initialize the defined tags with their engine-level signatures,
+        // which is what tag type checks at runtime compare against.
+        for (index, tag) in module.tags.iter().skip(module.num_imported_tags) {
+            let def_index = module.defined_tag_index(index).unwrap();
+            let type_index = self.engine_type_index(tag.signature.unwrap_module_type_index());
+            ptr::write(self.tag_ptr(def_index), VMTagDefinition::new(type_index));
+        }
     }
 
     fn wasm_fault(&self, addr: usize) -> Option<WasmFault> {
@@ -1389,6 +1442,11 @@ impl InstanceHandle {
         self.instance_mut().get_exported_table(export)
     }
 
+    /// Lookup a tag by index.
+    pub fn get_exported_tag(&mut self, export: TagIndex) -> ExportTag {
+        self.instance_mut().get_exported_tag(export)
+    }
+
     /// Lookup an item with the given index.
     pub fn get_export_by_index(&mut self, export: EntityIndex) -> Export {
         match export {
@@ -1396,6 +1454,7 @@ impl InstanceHandle {
             EntityIndex::Global(i) => Export::Global(self.get_exported_global(i)),
             EntityIndex::Table(i) => Export::Table(self.get_exported_table(i)),
             EntityIndex::Memory(i) => Export::Memory(self.get_exported_memory(i)),
+            EntityIndex::Tag(i) => Export::Tag(self.get_exported_tag(i)),
         }
     }
 
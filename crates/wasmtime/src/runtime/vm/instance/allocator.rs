@@ -299,6 +299,7 @@ pub unsafe trait InstanceAllocatorImpl {
     #[cfg(feature = "gc")]
     fn allocate_gc_heap(
         &self,
+        engine: &crate::Engine,
         gc_runtime: &dyn GcRuntime,
     ) -> Result<(GcHeapAllocationIndex, Box<dyn GcHeap>)>;
 
@@ -592,11 +593,23 @@ fn initialize_tables(
                         table.init_gc_refs(0, items).err2anyhow()?;
                     }
 
+                    WasmHeapTopType::Exn => {
+                        let gc_ref = VMGcRef::from_raw_u32(raw.get_exnref());
+                        let gc_store = unsafe { (*context.instance.store()).gc_store_mut()? };
+                        let items = (0..table.size())
+                            .map(|_| gc_ref.as_ref().map(|r| gc_store.clone_gc_ref(r)));
+                        table.init_gc_refs(0, items).err2anyhow()?;
+                    }
+
                     WasmHeapTopType::Func => {
                         let funcref = raw.get_funcref().cast::<VMFuncRef>();
                         let items = (0..table.size()).map(|_| funcref);
                         table.init_func(0, items).err2anyhow()?;
                     }
+
+                    WasmHeapTopType::Cont => {
+                        unreachable!("tables of continuation references are rejected")
+                    }
                 }
             }
         }
@@ -58,8 +58,12 @@ use crate::prelude::*;
 use crate::runtime::vm::table::{Table, TableElementType};
 use crate::runtime::vm::vmcontext::VMFuncRef;
 use crate::runtime::vm::{Instance, TrapReason, VMGcRef};
+#[cfg(any(feature = "gc", feature = "stack-switching"))]
+use crate::ValRaw;
 #[cfg(feature = "threads")]
 use core::time::Duration;
+#[cfg(feature = "gc")]
+use wasmtime_environ::TagIndex;
 use wasmtime_environ::Unsigned;
 use wasmtime_environ::{DataIndex, ElemIndex, FuncIndex, MemoryIndex, TableIndex, Trap};
 #[cfg(feature = "wmemcheck")]
@@ -463,7 +467,7 @@ unsafe fn gc_alloc_raw(
     size: u32,
     align: u32,
 ) -> Result<u32> {
-    use crate::{vm::VMGcHeader, GcHeapOutOfMemory};
+    use crate::vm::VMGcHeader;
     use core::alloc::Layout;
     use wasmtime_environ::{ModuleInternedTypeIndex, VMGcKind};
 
@@ -499,8 +503,7 @@ unsafe fn gc_alloc_raw(
             (*instance.store())
                 .unwrap_gc_store_mut()
                 .alloc_raw(header, layout)?
-                .ok_or_else(|| GcHeapOutOfMemory::new(()))
-                .err2anyhow()?
+                .ok_or_else(|| Trap::GcHeapOutOfMemory.into_anyhow())?
         }
     };
 
@@ -569,7 +572,7 @@ unsafe fn array_new_data(
     src: u32,
     len: u32,
 ) -> Result<u32> {
-    use crate::{ArrayType, GcHeapOutOfMemory};
+    use crate::ArrayType;
     use wasmtime_environ::ModuleInternedTypeIndex;
 
     let array_type_index = ModuleInternedTypeIndex::from_u32(array_type_index);
@@ -615,8 +618,8 @@ unsafe fn array_new_data(
             (*instance.store()).gc(None)?;
             (*instance.store())
                 .unwrap_gc_store_mut()
-                .alloc_uninit_array(shared_ty, u32::try_from(byte_len).unwrap(), &array_layout)?
-                .ok_or_else(|| GcHeapOutOfMemory::new(()).into_anyhow())?
+                .alloc_uninit_array(shared_ty, len, &array_layout)?
+                .ok_or_else(|| Trap::GcHeapOutOfMemory.into_anyhow())?
         }
     };
 
@@ -794,17 +797,24 @@ unsafe fn array_new_elem(
             }
         }
 
-        let array = match ArrayRef::_new_fixed((*instance.store()).store_opaque_mut(), &pre, &vals)
-        {
-            Ok(a) => a,
-            Err(e) if e.is::<GcHeapOutOfMemory<()>>() => {
-                // Collect garbage to hopefully free up space, then try the
-                // allocation again.
-                (*instance.store()).gc(None)?;
-                ArrayRef::_new_fixed((*instance.store()).store_opaque_mut(), &pre, &vals)?
-            }
-            Err(e) => return Err(e),
-        };
+        let array =
+            match ArrayRef::_new_fixed((*instance.store()).store_opaque_mut(), &pre, &vals) {
+                Ok(a) => a,
+                Err(e) if e.is::<GcHeapOutOfMemory<()>>() => {
+                    // Collect garbage to hopefully free up space, then try the
+                    // allocation again.
+                    (*instance.store()).gc(None)?;
+                    ArrayRef::_new_fixed((*instance.store()).store_opaque_mut(), &pre, &vals)
+                        .map_err(|e| {
+                            if e.is::<GcHeapOutOfMemory<()>>() {
+                                Trap::GcHeapOutOfMemory.into_anyhow()
+                            } else {
+                                e
+                            }
+                        })?
+                }
+                Err(e) => return Err(e),
+            };
 
         let mut store = AutoAssertNoGc::new((*instance.store()).store_opaque_mut());
         let gc_ref = array.try_clone_gc_ref(&mut store)?;
@@ -1022,6 +1032,239 @@ unsafe fn is_subtype(
     is_subtype
 }
 
+/// Allocate a new exception with the given tag, whose payload values have been
+/// written into `values`, and throw it.
+#[cfg(feature = "gc")]
+unsafe fn throw(instance: &mut Instance, tag: u32, values: *mut u8) -> Result<()> {
+    log::trace!("throw(tag={tag})");
+    set_pending_exception(instance, tag, values)?;
+    Err(crate::ThrownException.into())
+}
+
+/// Allocate a new exception with the given tag, whose payload values have been
+/// written into `values`, and make it the store's pending exception.
+#[cfg(feature = "gc")]
+unsafe fn set_pending_exception(instance: &mut Instance, tag: u32, values: *mut u8) -> Result<()> {
+    use crate::{store::AutoAssertNoGc, ExnRef, FuncType, GcHeapOutOfMemory, RootedGcRefImpl, Val};
+
+    let tag = instance.get_exported_tag(TagIndex::from_u32(tag));
+    let ty = FuncType::from_shared_type_index(
+        (*instance.store()).engine(),
+        tag.tag.signature.unwrap_engine_type_index(),
+    );
+    let values = values.cast::<ValRaw>();
+
+    instance.with_gc_lifo_scope(|instance| {
+        let fields = {
+            let mut store = AutoAssertNoGc::new((*instance.store()).store_opaque_mut());
+            ty.params()
+                .enumerate()
+                .map(|(i, ty)| Val::_from_raw(&mut store, *values.add(i), &ty))
+                .collect::<Vec<_>>()
+        };
+
+        let exn = match ExnRef::_new(
+            (*instance.store()).store_opaque_mut(),
+            tag.definition,
+            &ty,
+            &fields,
+        ) {
+            Ok(exn) => exn,
+            Err(e) if e.is::<GcHeapOutOfMemory<()>>() => {
+                // Collect garbage to hopefully free up space, then try the
+                // allocation again.
+                (*instance.store()).gc(None)?;
+                ExnRef::_new(
+                    (*instance.store()).store_opaque_mut(),
+                    tag.definition,
+                    &ty,
+                    &fields,
+                )
+                .map_err(|e| {
+                    if e.is::<GcHeapOutOfMemory<()>>() {
+                        Trap::GcHeapOutOfMemory.into_anyhow()
+                    } else {
+                        e
+                    }
+                })?
+            }
+            Err(e) => return Err(e),
+        };
+
+        let mut store = AutoAssertNoGc::new((*instance.store()).store_opaque_mut());
+        let gc_ref = exn.try_clone_gc_ref(&mut store)?;
+        store.set_pending_exception(gc_ref);
+        Ok(())
+    })
+}
+
+/// Throw the given exception.
+#[cfg(feature = "gc")]
+unsafe fn throw_ref(instance: &mut Instance, exn: u32) -> Result<()> {
+    log::trace!("throw_ref({exn:#x})");
+    let exn = VMGcRef::from_raw_u32(exn).ok_or_else(|| Trap::NullReference.into_anyhow())?;
+    let store = (*instance.store()).store_opaque_mut();
+    let exn = store.unwrap_gc_store_mut().clone_gc_ref(&exn);
+    store.set_pending_exception(exn);
+    Err(crate::ThrownException.into())
+}
+
+/// Does the pending exception have the given tag?
+#[cfg(feature = "gc")]
+unsafe fn pending_exception_matches(instance: &mut Instance, tag: u32) -> Result<u32> {
+    let tag = instance.get_exported_tag(TagIndex::from_u32(tag));
+    instance.with_gc_lifo_scope(|instance| {
+        let store = (*instance.store()).store_opaque_mut();
+        let matches = match store.pending_exception() {
+            Some(exn) => exn.tag_definition(store)? == tag.definition,
+            None => false,
+        };
+        log::trace!(
+            "pending_exception_matches(tag={:p}) -> {matches}",
+            tag.definition
+        );
+        Ok(u32::from(matches))
+    })
+}
+
+/// Take the pending exception, returning it to Wasm.
+#[cfg(feature = "gc")]
+unsafe fn take_pending_exception(instance: &mut Instance) -> Result<u32> {
+    let store = (*instance.store()).store_opaque_mut();
+    match store.take_pending_exception_gc_ref() {
+        Some(exn) => {
+            let raw = exn.as_raw_u32();
+            log::trace!("take_pending_exception() -> {raw:#x}");
+            store.unwrap_gc_store_mut().expose_gc_ref_to_wasm(exn);
+            Ok(raw)
+        }
+        None => Ok(0),
+    }
+}
+
+/// Write the given exception's payload values into `values`.
+#[cfg(feature = "gc")]
+unsafe fn exception_payload(instance: &mut Instance, exn: u32, values: *mut u8) -> Result<()> {
+    use crate::{store::AutoAssertNoGc, ExnRef};
+
+    log::trace!("exception_payload({exn:#x})");
+    let exn = VMGcRef::from_raw_u32(exn).ok_or_else(|| Trap::NullReference.into_anyhow())?;
+    let values = values.cast::<ValRaw>();
+
+    instance.with_gc_lifo_scope(|instance| {
+        let mut store = AutoAssertNoGc::new((*instance.store()).store_opaque_mut());
+        let exn = store.unwrap_gc_store_mut().clone_gc_ref(&exn);
+        let exn = ExnRef::from_cloned_gc_ref(&mut store, exn);
+        let fields = exn._fields(&mut store)?.collect::<Vec<_>>();
+        for (i, field) in fields.iter().enumerate() {
+            *values.add(i) = field._to_raw(&mut store)?;
+        }
+        Ok(())
+    })
+}
+
+// Implementation of `cont.new`.
+#[cfg(feature = "stack-switching")]
+unsafe fn cont_new(instance: &mut Instance, func: *mut u8) -> Result<u64, TrapReason> {
+    crate::runtime::vm::continuation::cont_new(instance, func.cast::<VMFuncRef>())
+}
+
+// Implementation of `cont.bind`.
+#[cfg(feature = "stack-switching")]
+unsafe fn cont_bind(
+    instance: &mut Instance,
+    cont: u64,
+    values: *mut u8,
+    len: u32,
+) -> Result<u64, TrapReason> {
+    crate::runtime::vm::continuation::cont_bind(
+        instance,
+        cont,
+        values.cast::<ValRaw>(),
+        usize::try_from(len).unwrap(),
+    )
+}
+
+// Implementation of `resume`.
+#[cfg(feature = "stack-switching")]
+unsafe fn resume(
+    instance: &mut Instance,
+    cont: u64,
+    values: *mut u8,
+    len: u32,
+    handlers: *mut u8,
+    handlers_len: u32,
+) -> Result<u32, TrapReason> {
+    crate::runtime::vm::continuation::resume(
+        instance,
+        cont,
+        false,
+        values.cast::<ValRaw>(),
+        usize::try_from(len).unwrap(),
+        handlers.cast::<u32>(),
+        usize::try_from(handlers_len).unwrap(),
+    )
+}
+
+// Implementation of `resume_throw`, which makes an exception with the given
+// tag and payload the pending exception and then resumes the continuation by
+// throwing it.
+#[cfg(all(feature = "stack-switching", feature = "gc"))]
+unsafe fn resume_throw(
+    instance: &mut Instance,
+    cont: u64,
+    tag: u32,
+    values: *mut u8,
+    handlers: *mut u8,
+    handlers_len: u32,
+) -> Result<u32, TrapReason> {
+    set_pending_exception(instance, tag, values)?;
+    crate::runtime::vm::continuation::resume(
+        instance,
+        cont,
+        true,
+        values.cast::<ValRaw>(),
+        0,
+        handlers.cast::<u32>(),
+        usize::try_from(handlers_len).unwrap(),
+    )
+}
+
+// Implementation of `suspend`.
+#[cfg(feature = "stack-switching")]
+unsafe fn suspend(
+    instance: &mut Instance,
+    tag: u32,
+    values: *mut u8,
+    len: u32,
+) -> Result<(), TrapReason> {
+    crate::runtime::vm::continuation::suspend(
+        instance,
+        tag,
+        None,
+        values.cast::<ValRaw>(),
+        usize::try_from(len).unwrap(),
+    )
+}
+
+// Implementation of `switch`.
+#[cfg(feature = "stack-switching")]
+unsafe fn switch(
+    instance: &mut Instance,
+    tag: u32,
+    cont: u64,
+    values: *mut u8,
+    len: u32,
+) -> Result<(), TrapReason> {
+    crate::runtime::vm::continuation::suspend(
+        instance,
+        tag,
+        Some(cont),
+        values.cast::<ValRaw>(),
+        usize::try_from(len).unwrap(),
+    )
+}
+
 // Implementation of `memory.atomic.notify` for locally defined memories.
 #[cfg(feature = "threads")]
 fn memory_atomic_notify(
@@ -262,7 +262,12 @@ impl From<DynamicGcRefTable> for Table {
 fn wasm_to_table_type(ty: WasmRefType) -> TableElementType {
     match ty.heap_type.top() {
         WasmHeapTopType::Func => TableElementType::Func,
-        WasmHeapTopType::Any | WasmHeapTopType::Extern => TableElementType::GcRef,
+        WasmHeapTopType::Any | WasmHeapTopType::Extern | WasmHeapTopType::Exn => {
+            TableElementType::GcRef
+        }
+        WasmHeapTopType::Cont => {
+            unreachable!("tables of continuation references are rejected")
+        }
     }
 }
 
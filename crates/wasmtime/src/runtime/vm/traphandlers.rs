@@ -13,14 +13,18 @@ mod coredump;
 use crate::prelude::*;
 use crate::runtime::module::lookup_code;
 use crate::runtime::vm::sys::traphandlers;
-use crate::runtime::vm::{Instance, VMContext, VMRuntimeLimits};
+use crate::runtime::vm::{arch, Instance, VMContext, VMRuntimeLimits, VMStore};
 use crate::sync::RwLock;
 use core::cell::{Cell, UnsafeCell};
 use core::mem::MaybeUninit;
+#[cfg(feature = "gc")]
+use core::ops::ControlFlow;
 use core::ops::Range;
 use core::ptr;
 
-pub use self::backtrace::Backtrace;
+pub use self::backtrace::{Backtrace, Frame};
+#[cfg(feature = "coredump")]
+pub use self::coredump::CoreDumpFrameState;
 pub use self::coredump::CoreDumpStack;
 pub use self::tls::{tls_eager_initialize, AsyncWasmCallState, PreviousAsyncWasmCallState};
 
@@ -256,10 +260,19 @@ where
 {
     let limits = Instance::from_vmctx(caller, |i| i.runtime_limits());
 
+    // Core dumps need the store to figure out how to read the state of each
+    // frame.
+    let store = if capture_coredump {
+        Some(Instance::from_vmctx(caller, |i| i.store()))
+    } else {
+        None
+    };
+
     let result = CallThreadState::new(
         signal_handler,
         capture_backtrace,
         capture_coredump,
+        store,
         *limits,
         async_guard_range,
     )
@@ -306,6 +319,8 @@ mod call_thread_state {
         pub(super) capture_backtrace: bool,
         #[cfg(feature = "coredump")]
         pub(super) capture_coredump: bool,
+        #[cfg(feature = "coredump")]
+        pub(super) store: Option<*mut dyn VMStore>,
 
         pub(crate) limits: *const VMRuntimeLimits,
 
@@ -342,10 +357,11 @@ mod call_thread_state {
             signal_handler: Option<*const SignalHandler<'static>>,
             capture_backtrace: bool,
             capture_coredump: bool,
+            store: Option<*mut dyn VMStore>,
             limits: *const VMRuntimeLimits,
             async_guard_range: Range<*mut u8>,
         ) -> CallThreadState {
-            let _ = capture_coredump;
+            let _ = (capture_coredump, store);
 
             CallThreadState {
                 unwind: UnsafeCell::new(MaybeUninit::uninit()),
@@ -354,6 +370,8 @@ mod call_thread_state {
                 capture_backtrace,
                 #[cfg(feature = "coredump")]
                 capture_coredump,
+                #[cfg(feature = "coredump")]
+                store,
                 limits,
                 async_guard_range,
                 prev: Cell::new(ptr::null()),
@@ -378,6 +396,22 @@ mod call_thread_state {
             self.old_last_wasm_entry_fp.get()
         }
 
+        /// Replace the saved registers of the previous `CallThreadState`.
+        ///
+        /// This is used when the activation is moved, along with the stack it
+        /// lives on, on top of a different previous activation.
+        #[cfg(feature = "stack-switching")]
+        pub(crate) fn set_old_last_wasm_registers(
+            &self,
+            exit_pc: usize,
+            exit_fp: usize,
+            entry_fp: usize,
+        ) {
+            self.old_last_wasm_exit_pc.set(exit_pc);
+            self.old_last_wasm_exit_fp.set(exit_fp);
+            self.old_last_wasm_entry_fp.set(entry_fp);
+        }
+
         /// Get the previous `CallThreadState`.
         pub fn prev(&self) -> tls::Ptr {
             self.prev.get()
@@ -425,6 +459,8 @@ impl CallThreadState {
     }
 
     fn unwind_with(&self, reason: UnwindReason) -> ! {
+        #[cfg(feature = "gc")]
+        let reason = self.unwind_exception(reason);
         let (backtrace, coredump) = match reason {
             // Panics don't need backtraces. There is nowhere to attach the
             // hypothetical backtrace to and it doesn't really make sense to try
@@ -439,9 +475,16 @@ impl CallThreadState {
                 needs_backtrace: false,
                 ..
             }) => (None, None),
-            UnwindReason::Trap(_) => (
+            UnwindReason::Trap(ref reason) => (
                 self.capture_backtrace(self.limits, None),
-                self.capture_coredump(self.limits, None),
+                self.capture_coredump(
+                    self.limits,
+                    None,
+                    matches!(
+                        reason,
+                        TrapReason::Wasm(wasmtime_environ::Trap::StackOverflow)
+                    ),
+                ),
             ),
         };
         unsafe {
@@ -452,6 +495,56 @@ impl CallThreadState {
         }
     }
 
+    /// If `reason` is a thrown Wasm exception, which is still pending, unwind
+    /// the stack to the innermost exception landing pad in the current
+    /// activation.
+    ///
+    /// If there is no such landing pad, then the exception propagates out of
+    /// this activation, and the reason to unwind it with is returned. In that
+    /// case the exception stays pending, and no backtrace is captured since
+    /// the exception may well be caught further up the stack.
+    #[cfg(feature = "gc")]
+    fn unwind_exception(&self, reason: UnwindReason) -> UnwindReason {
+        let error = match reason {
+            UnwindReason::Trap(TrapReason::User { error, .. })
+                if error.is::<crate::ThrownException>()
+                    && unsafe { *(*self.limits).pending_exception.get() != 0 } =>
+            {
+                error
+            }
+            reason => return reason,
+        };
+
+        let mut landing_pad = None;
+        unsafe {
+            Backtrace::trace_innermost_activation(self.limits, |frame| {
+                let Some((code, text_offset)) = lookup_code(frame.pc()) else {
+                    return ControlFlow::Continue(());
+                };
+                let Some(pad) = code.lookup_exception_landing_pad(text_offset) else {
+                    return ControlFlow::Continue(());
+                };
+                let text = code.text().as_ptr() as usize;
+                let pc = text + usize::try_from(pad.landing_pad_offset).unwrap();
+                let sp = frame.fp() - usize::try_from(pad.frame_size).unwrap();
+                landing_pad = Some((pc, sp, frame.fp()));
+                ControlFlow::Break(())
+            });
+        }
+
+        match landing_pad {
+            Some((pc, sp, fp)) => {
+                log::trace!("unwinding exception to landing pad {pc:#x} (sp={sp:#x}, fp={fp:#x})");
+                drop(error);
+                unsafe { arch::resume_to_exception_landing_pad(pc, sp, fp) }
+            }
+            None => UnwindReason::Trap(TrapReason::User {
+                error,
+                needs_backtrace: false,
+            }),
+        }
+    }
+
     /// Trap handler using our thread-local state.
     ///
     /// * `pc` - the program counter the trap happened at
@@ -518,7 +611,11 @@ impl CallThreadState {
         trap: wasmtime_environ::Trap,
     ) {
         let backtrace = self.capture_backtrace(self.limits, Some((pc, fp)));
-        let coredump = self.capture_coredump(self.limits, Some((pc, fp)));
+        let coredump = self.capture_coredump(
+            self.limits,
+            Some((pc, fp)),
+            trap == wasmtime_environ::Trap::StackOverflow,
+        );
         unsafe {
             (*self.unwind.get()).as_mut_ptr().write((
                 UnwindReason::Trap(TrapReason::Jit {
@@ -563,6 +660,8 @@ pub(crate) mod tls {
     use super::CallThreadState;
     use core::mem;
     use core::ops::Range;
+    #[cfg(feature = "stack-switching")]
+    use {crate::prelude::*, crate::runtime::vm::VMRuntimeLimits};
 
     pub use raw::Ptr;
 
@@ -698,6 +797,58 @@ pub(crate) mod tls {
             assert!(self.state.is_null());
         }
 
+        /// Moves the activations in this state on top of the activation that
+        /// is currently executing with `limits`.
+        ///
+        /// The oldest activation in this state saved the `last_wasm_*`
+        /// registers of whichever activation was executing when it was first
+        /// pushed. When a suspended continuation is resumed somewhere else,
+        /// those registers are stale, so this replaces them with the current
+        /// registers in `limits` to keep backtraces correct. This must be
+        /// called just before `push`.
+        ///
+        /// # Unsafety
+        ///
+        /// The `limits` pointer must be valid.
+        #[cfg(feature = "stack-switching")]
+        pub unsafe fn reparent(&self, limits: *const VMRuntimeLimits) {
+            if let Some(oldest) = self.state.as_ref() {
+                oldest.set_old_last_wasm_registers(
+                    *(*limits).last_wasm_exit_pc.get(),
+                    *(*limits).last_wasm_exit_fp.get(),
+                    *(*limits).last_wasm_entry_fp.get(),
+                );
+            }
+        }
+
+        /// Returns the `(last_wasm_exit_pc, last_wasm_exit_fp,
+        /// last_wasm_entry_fp)` registers saved by the activations in this
+        /// state, youngest first.
+        ///
+        /// The oldest activation's saved registers are excluded since they
+        /// belong to whichever activation this state was suspended from,
+        /// rather than to this state itself.
+        #[cfg(feature = "stack-switching")]
+        pub fn saved_registers(&self) -> Vec<(usize, usize, usize)> {
+            let mut registers = Vec::new();
+            let mut ptr = self.state;
+            while let Some(state) = unsafe { ptr.as_ref() } {
+                registers.push((
+                    state.old_last_wasm_exit_pc(),
+                    state.old_last_wasm_exit_fp(),
+                    state.old_last_wasm_entry_fp(),
+                ));
+                ptr = state.prev();
+            }
+            // The list is stored oldest-first, so drop the oldest activation's
+            // registers and then put the rest in youngest-first order.
+            if !registers.is_empty() {
+                registers.remove(0);
+            }
+            registers.reverse();
+            registers
+        }
+
         /// Asserts that the current CallThreadState pointer, if present, is not
         /// in the `range` specified.
         ///
@@ -23,6 +23,8 @@
 
 use crate::prelude::*;
 use crate::runtime::vm::arch;
+#[cfg(feature = "stack-switching")]
+use crate::runtime::vm::AsyncWasmCallState;
 use crate::runtime::vm::{
     traphandlers::{tls, CallThreadState},
     VMRuntimeLimits,
@@ -157,6 +159,52 @@ impl Backtrace {
         log::trace!("====== Done Capturing Backtrace (reached end of activations) ======");
     }
 
+    /// Walk the Wasm frames of the innermost activation, i.e. those younger
+    /// than the most recent entry into Wasm, calling `f` for each frame we
+    /// walk.
+    ///
+    /// This must only be called while Wasm has exited to the host through a
+    /// trampoline, rather than from a trap handler.
+    #[cfg(feature = "gc")]
+    pub(crate) unsafe fn trace_innermost_activation(
+        limits: *const VMRuntimeLimits,
+        f: impl FnMut(Frame) -> ControlFlow<()>,
+    ) {
+        let pc = *(*limits).last_wasm_exit_pc.get();
+        let fp = *(*limits).last_wasm_exit_fp.get();
+        let entry_fp = *(*limits).last_wasm_entry_fp.get();
+        if pc == 0 {
+            return;
+        }
+        let _ = Self::trace_through_wasm(pc, fp, entry_fp, f);
+    }
+
+    /// Walk the Wasm frames on the stack of a suspended continuation, calling
+    /// `f` for each frame we walk.
+    ///
+    /// The `last_wasm_*` registers are those of the continuation's youngest
+    /// activation at the time it was suspended, and `state` holds the
+    /// continuation's activations.
+    #[cfg(feature = "stack-switching")]
+    pub(crate) unsafe fn trace_suspended(
+        last_wasm_exit_pc: usize,
+        last_wasm_exit_fp: usize,
+        last_wasm_entry_fp: usize,
+        state: &AsyncWasmCallState,
+        mut f: impl FnMut(Frame) -> ControlFlow<()>,
+    ) {
+        log::trace!("====== Capturing Suspended Backtrace ======");
+        let activations =
+            core::iter::once((last_wasm_exit_pc, last_wasm_exit_fp, last_wasm_entry_fp))
+                .chain(state.saved_registers())
+                .take_while(|&(pc, _, _)| pc != 0);
+        for (pc, fp, sp) in activations {
+            if let ControlFlow::Break(()) = Self::trace_through_wasm(pc, fp, sp, &mut f) {
+                return;
+            }
+        }
+    }
+
     /// Walk through a contiguous sequence of Wasm frames starting with the
     /// frame at the given PC and FP and ending at `trampoline_sp`.
     unsafe fn trace_through_wasm(
@@ -10,6 +10,7 @@ impl CallThreadState {
         &self,
         _limits: *const VMRuntimeLimits,
         _trap_pc_and_fp: Option<(usize, usize)>,
+        _stack_overflow: bool,
     ) -> Option<CoreDumpStack> {
         None
     }
@@ -1,7 +1,12 @@
 use super::CallThreadState;
 use crate::prelude::*;
-use crate::runtime::vm::{Backtrace, VMRuntimeLimits};
+use crate::runtime::vm::{Backtrace, SendSyncPtr, VMContext, VMRuntimeLimits};
+use core::ptr::NonNull;
 use wasm_encoder::CoreDumpValue;
+use wasmtime_environ::{
+    FrameStateInfo, WasmValType, FRAME_STATE_OPERAND_SIZE, FRAME_STATE_TAG_F32,
+    FRAME_STATE_TAG_F64, FRAME_STATE_TAG_I32, FRAME_STATE_TAG_I64,
+};
 
 /// A WebAssembly Coredump
 #[derive(Debug)]
@@ -9,17 +14,22 @@ pub struct CoreDumpStack {
     /// The backtrace containing the stack frames for the CoreDump
     pub bt: Backtrace,
 
-    /// The locals for each frame in the backtrace.
-    ///
-    /// This is not currently implemented.
-    #[allow(dead_code)]
-    pub locals: Vec<Vec<CoreDumpValue>>,
-
-    /// The operands for each stack frame
-    ///
-    /// This is not currently implemented.
-    #[allow(dead_code)]
-    pub operand_stack: Vec<Vec<CoreDumpValue>>,
+    /// The recorded state of each frame in the backtrace, if the frame's
+    /// function was compiled to record it.
+    pub frame_states: Vec<Option<CoreDumpFrameState>>,
+}
+
+/// The state of a single stack frame captured in a [`CoreDumpStack`].
+#[derive(Debug)]
+pub struct CoreDumpFrameState {
+    /// The `VMContext` of the instance that this frame belongs to.
+    pub vmctx: Option<SendSyncPtr<VMContext>>,
+
+    /// The values of the frame's locals.
+    pub locals: Vec<CoreDumpValue>,
+
+    /// The values on the frame's operand stack, bottom-most first.
+    pub operand_stack: Vec<CoreDumpValue>,
 }
 
 impl CallThreadState {
@@ -27,16 +37,92 @@ impl CallThreadState {
         &self,
         limits: *const VMRuntimeLimits,
         trap_pc_and_fp: Option<(usize, usize)>,
+        stack_overflow: bool,
     ) -> Option<CoreDumpStack> {
         if !self.capture_coredump {
             return None;
         }
         let bt = unsafe { Backtrace::new_with_trap_state(limits, self, trap_pc_and_fp) };
 
-        Some(CoreDumpStack {
-            bt,
-            locals: vec![],
-            operand_stack: vec![],
+        // The state of each frame must be read now, before these frames are
+        // unwound.
+        let store = unsafe { (*self.store.unwrap()).store_opaque() };
+        let trap_pc = trap_pc_and_fp.map(|(pc, _)| pc);
+        let frame_states = bt
+            .frames()
+            .enumerate()
+            .map(|(i, frame)| {
+                // The youngest frame of a stack overflow trapped before it
+                // could record anything.
+                if i == 0 && stack_overflow {
+                    return None;
+                }
+                let pc = crate::WasmBacktrace::pc_to_lookup(frame, trap_pc);
+                let info = store
+                    .modules()
+                    .lookup_module_by_pc(pc)?
+                    .lookup_frame_state(pc)?;
+                Some(unsafe { read_frame_state(frame.fp(), info) })
+            })
+            .collect();
+
+        Some(CoreDumpStack { bt, frame_states })
+    }
+}
+
+/// Reads the state described by `info` out of the frame at `fp`.
+unsafe fn read_frame_state(fp: usize, info: &FrameStateInfo) -> CoreDumpFrameState {
+    let at = |offset: i32| fp.wrapping_add_signed(offset as isize) as *const u8;
+    let read = |ptr: *const u8, tag: u32| match tag {
+        FRAME_STATE_TAG_I32 => CoreDumpValue::I32(ptr.cast::<i32>().read_unaligned()),
+        FRAME_STATE_TAG_I64 => CoreDumpValue::I64(ptr.cast::<i64>().read_unaligned()),
+        FRAME_STATE_TAG_F32 => CoreDumpValue::F32(ptr.cast::<f32>().read_unaligned()),
+        FRAME_STATE_TAG_F64 => CoreDumpValue::F64(ptr.cast::<f64>().read_unaligned()),
+        _ => CoreDumpValue::Missing,
+    };
+
+    let vmctx = at(info.vmctx_offset)
+        .cast::<*mut VMContext>()
+        .read_unaligned();
+
+    let locals = info
+        .locals
+        .iter()
+        .map(|local| match local.offset {
+            Some(offset) => {
+                let tag = match local.ty {
+                    WasmValType::I32 => FRAME_STATE_TAG_I32,
+                    WasmValType::I64 => FRAME_STATE_TAG_I64,
+                    WasmValType::F32 => FRAME_STATE_TAG_F32,
+                    WasmValType::F64 => FRAME_STATE_TAG_F64,
+                    _ => unreachable!("only numeric locals are recorded"),
+                };
+                read(at(offset), tag)
+            }
+            None => CoreDumpValue::Missing,
         })
+        .collect();
+
+    let operand_stack = match &info.operand_stack {
+        Some(stack) => {
+            // Be defensive about the recorded depth in case this frame hadn't
+            // yet recorded one.
+            let depth = at(stack.depth_offset).cast::<u32>().read_unaligned();
+            (0..depth.min(stack.max_depth))
+                .map(|i| {
+                    let entry =
+                        at(stack.entries_offset).add((i * FRAME_STATE_OPERAND_SIZE) as usize);
+                    let tag = entry.cast::<u32>().read_unaligned();
+                    read(entry.add(8), tag)
+                })
+                .collect()
+        }
+        None => Vec::new(),
+    };
+
+    CoreDumpFrameState {
+        vmctx: NonNull::new(vmctx).map(SendSyncPtr::new),
+        locals,
+        operand_stack,
     }
 }
@@ -253,6 +253,77 @@ mod test_vmglobal_import {
     }
 }
 
+/// The fields needed to identify a WebAssembly exception tag imported from
+/// another instance.
+#[derive(Debug, Copy, Clone)]
+#[repr(C)]
+pub struct VMTagImport {
+    /// A pointer to the imported tag description.
+    pub from: *mut VMTagDefinition,
+}
+
+// Declare that this type is send/sync, it's the responsibility of users of
+// `VMTagImport` to uphold this guarantee.
+unsafe impl Send for VMTagImport {}
+unsafe impl Sync for VMTagImport {}
+
+#[cfg(test)]
+mod test_vmtag_import {
+    use super::VMTagImport;
+    use core::mem::offset_of;
+    use std::mem::size_of;
+    use wasmtime_environ::{HostPtr, Module, VMOffsets};
+
+    #[test]
+    fn check_vmtag_import_offsets() {
+        let module = Module::new();
+        let offsets = VMOffsets::new(HostPtr, &module);
+        assert_eq!(
+            size_of::<VMTagImport>(),
+            usize::from(offsets.size_of_vmtag_import())
+        );
+        assert_eq!(
+            offset_of!(VMTagImport, from),
+            usize::from(offsets.vmtag_import_from())
+        );
+    }
+}
+
+/// The storage for a WebAssembly exception tag defined within the instance.
+///
+/// A tag's identity is the address of its definition: two tags are the same
+/// tag if and only if they have the same `VMTagDefinition`.
+#[derive(Debug)]
+#[repr(C)]
+pub struct VMTagDefinition {
+    /// The tag's function signature, registered with the engine.
+    pub type_index: VMSharedTypeIndex,
+}
+
+impl VMTagDefinition {
+    /// Create a new tag definition with the given signature.
+    pub fn new(type_index: VMSharedTypeIndex) -> Self {
+        Self { type_index }
+    }
+}
+
+#[cfg(test)]
+mod test_vmtag_definition {
+    use super::VMTagDefinition;
+    use std::mem::size_of;
+    use wasmtime_environ::{HostPtr, Module, VMOffsets};
+
+    #[test]
+    fn check_vmtag_definition_offsets() {
+        let module = Module::new();
+        let offsets = VMOffsets::new(HostPtr, &module);
+        assert_eq!(
+            size_of::<VMTagDefinition>(),
+            usize::from(offsets.size_of_vmtag_definition())
+        );
+    }
+}
+
 /// The fields compiled code needs to access to utilize a WebAssembly linear
 /// memory defined within the instance, namely the start address and the
 /// size in bytes.
@@ -446,7 +517,14 @@ impl VMGlobalDefinition {
                     let r = VMGcRef::from_raw_u32(raw.get_anyref());
                     global.init_gc_ref(store.gc_store_mut()?, r.as_ref())
                 }
+                WasmHeapTopType::Exn => {
+                    let r = VMGcRef::from_raw_u32(raw.get_exnref());
+                    global.init_gc_ref(store.gc_store_mut()?, r.as_ref())
+                }
                 WasmHeapTopType::Func => *global.as_func_ref_mut() = raw.get_funcref().cast(),
+                WasmHeapTopType::Cont => {
+                    unreachable!("globals of continuation references are rejected")
+                }
             },
         }
         Ok(global)
@@ -479,7 +557,16 @@ impl VMGlobalDefinition {
                         None => 0,
                     }
                 }),
+                WasmHeapTopType::Exn => ValRaw::exnref({
+                    match self.as_gc_ref() {
+                        Some(r) => store.gc_store_mut()?.clone_gc_ref(r).as_raw_u32(),
+                        None => 0,
+                    }
+                }),
                 WasmHeapTopType::Func => ValRaw::funcref(self.as_func_ref().cast()),
+                WasmHeapTopType::Cont => {
+                    unreachable!("globals of continuation references are rejected")
+                }
             },
         })
     }
@@ -835,6 +922,15 @@ pub struct VMRuntimeLimits {
     /// Used to find the end of a contiguous sequence of Wasm frames when
     /// walking the stack.
     pub last_wasm_entry_fp: UnsafeCell<usize>,
+
+    /// The raw `VMGcRef` of the exception that is currently being thrown, or
+    /// `0` if no exception is being thrown.
+    ///
+    /// Compiled Wasm code checks this after every call to see whether the
+    /// callee threw an exception that it should dispatch to one of its
+    /// handlers or propagate to its own caller. It is only written to by the
+    /// host, and is a GC root while it is non-zero.
+    pub pending_exception: UnsafeCell<u32>,
 }
 
 // The `VMRuntimeLimits` type is a pod-type with no destructor, and we don't
@@ -853,6 +949,7 @@ impl Default for VMRuntimeLimits {
             last_wasm_exit_fp: UnsafeCell::new(0),
             last_wasm_exit_pc: UnsafeCell::new(0),
             last_wasm_entry_fp: UnsafeCell::new(0),
+            pending_exception: UnsafeCell::new(0),
         }
     }
 }
@@ -891,6 +988,10 @@ mod test_vmruntime_limits {
             offset_of!(VMRuntimeLimits, last_wasm_entry_fp),
             usize::from(offsets.ptr.vmruntime_limits_last_wasm_entry_fp())
         );
+        assert_eq!(
+            offset_of!(VMRuntimeLimits, pending_exception),
+            usize::from(offsets.ptr.vmruntime_limits_pending_exception())
+        );
     }
 }
 
@@ -1034,6 +1135,17 @@ pub union ValRaw {
     ///
     /// This value is always stored in a little-endian format.
     anyref: u32,
+
+    /// A WebAssembly `exnref` value (or one of its subtypes).
+    ///
+    /// The payload here is a compressed pointer value which is
+    /// runtime-defined. This is one of the main points of unsafety about the
+    /// `ValRaw` type as the validity of the pointer here is not easily verified
+    /// and must be preserved by carefully calling the correct functions
+    /// throughout the runtime.
+    ///
+    /// This value is always stored in a little-endian format.
+    exnref: u32,
 }
 
 // The `ValRaw` type is matched as `wasmtime_val_raw_t` in the C API so these
@@ -1071,6 +1183,7 @@ impl fmt::Debug for ValRaw {
                 .field("funcref", &self.funcref)
                 .field("externref", &Hex(self.externref))
                 .field("anyref", &Hex(self.anyref))
+                .field("exnref", &Hex(self.exnref))
                 .finish()
         }
     }
@@ -1078,12 +1191,13 @@ impl fmt::Debug for ValRaw {
 
 impl ValRaw {
     /// Create a null reference that is compatible with any of
-    /// `{any,extern,func}ref`.
+    /// `{any,extern,func,exn}ref`.
     pub fn null() -> ValRaw {
         unsafe {
             let raw = mem::MaybeUninit::<Self>::zeroed().assume_init();
             debug_assert_eq!(raw.get_anyref(), 0);
             debug_assert_eq!(raw.get_externref(), 0);
+            debug_assert_eq!(raw.get_exnref(), 0);
             debug_assert_eq!(raw.get_funcref(), ptr::null_mut());
             raw
         }
@@ -1167,6 +1281,13 @@ impl ValRaw {
         ValRaw { anyref: r.to_le() }
     }
 
+    /// Creates a WebAssembly `exnref` value
+    #[inline]
+    pub fn exnref(r: u32) -> ValRaw {
+        assert!(cfg!(feature = "gc") || r == 0);
+        ValRaw { exnref: r.to_le() }
+    }
+
     /// Gets the WebAssembly `i32` value
     #[inline]
     pub fn get_i32(&self) -> i32 {
@@ -1230,6 +1351,14 @@ impl ValRaw {
         assert!(cfg!(feature = "gc") || anyref == 0);
         anyref
     }
+
+    /// Gets the WebAssembly `exnref` value
+    #[inline]
+    pub fn get_exnref(&self) -> u32 {
+        let exnref = u32::from_le(unsafe { self.exnref });
+        assert!(cfg!(feature = "gc") || exnref == 0);
+        exnref
+    }
 }
 
 /// An "opaque" version of `VMContext` which must be explicitly casted to a
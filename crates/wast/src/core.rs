@@ -30,6 +30,10 @@ pub fn val<T>(store: &mut Store<T>, v: &WastArgCore<'_>) -> Result<Val> {
             shared: false,
             ty: AbstractHeapType::None,
         }) => Val::AnyRef(None),
+        RefNull(HeapType::Abstract {
+            shared: false,
+            ty: AbstractHeapType::Exn | AbstractHeapType::NoExn,
+        }) => Val::ExnRef(None),
         RefExtern(x) => Val::ExternRef(Some(ExternRef::new(store, *x)?)),
         RefHost(x) => {
             let x = ExternRef::new(&mut *store, *x)?;
@@ -78,7 +82,7 @@ pub fn match_val<T>(store: &mut Store<T>, actual: &Val, expected: &WastRetCore)
 
         // Null references.
         (
-            Val::FuncRef(None) | Val::ExternRef(None) | Val::AnyRef(None),
+            Val::FuncRef(None) | Val::ExternRef(None) | Val::AnyRef(None) | Val::ExnRef(None),
             WastRetCore::RefNull(_),
         )
         | (Val::ExternRef(None), WastRetCore::RefExtern(None)) => Ok(()),
@@ -104,7 +108,10 @@ pub fn match_val<T>(store: &mut Store<T>, actual: &Val, expected: &WastRetCore)
                 Some(x) => bail!("expected null externref, found non-null externref of {x}"),
             }
         }
-        (Val::ExternRef(Some(_)) | Val::FuncRef(Some(_)), WastRetCore::RefNull(_)) => {
+        (
+            Val::ExternRef(Some(_)) | Val::FuncRef(Some(_)) | Val::ExnRef(Some(_)),
+            WastRetCore::RefNull(_),
+        ) => {
             bail!("expected null, found non-null reference: {actual:?}")
         }
 
@@ -396,6 +396,18 @@ where
         bail!("expected '{}', got '{}'", expected, actual)
     }
 
+    fn assert_exception(&mut self, result: Outcome) -> Result<()> {
+        match result {
+            Outcome::Ok(values) => bail!("expected exception, got {:?}", values),
+            Outcome::Trap(err) if err.downcast_ref::<ThrownException>().is_some() => {
+                // Discard the uncaught exception now that we've seen it.
+                self.store.take_pending_exception();
+                Ok(())
+            }
+            Outcome::Trap(err) => bail!("expected exception, got '{:?}'", err),
+        }
+    }
+
     /// Run a wast script from a byte buffer.
     pub fn run_buffer(&mut self, filename: &str, wast: &[u8]) -> Result<()> {
         let wast = str::from_utf8(wast)?;
@@ -563,7 +575,10 @@ where
                     )
                 }
             }
-            AssertException { .. } => bail!("unimplemented assert_exception"),
+            AssertException { span: _, exec } => {
+                let result = self.perform_execute(exec)?;
+                self.assert_exception(result)?;
+            }
 
             Thread(thread) => {
                 let mut core_linker = Linker::new(self.store.engine());
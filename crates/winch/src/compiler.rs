@@ -129,6 +129,9 @@ impl wasmtime_environ::Compiler for Compiler {
             WasmFunctionInfo {
                 start_srcloc: func.metadata().address_map.start_srcloc,
                 stack_maps: Box::new([]),
+                // Winch always keeps locals in its frames, so describing
+                // where they are is free.
+                frame_state: func.metadata().frame_state.clone(),
             },
             Box::new(func),
         ))
@@ -370,6 +370,24 @@ impl Wasmtime {
     }
 
     fn generate(&mut self, resolve: &Resolve, id: WorldId) -> anyhow::Result<String> {
+        // `future` and `stream` types belong to the component model's async
+        // ABI which neither the component binary format parsed by Wasmtime nor
+        // its runtime support yet, so reject them up front rather than
+        // generating bindings that could never be instantiated.
+        let mut live = LiveTypes::default();
+        live.add_world(resolve, id);
+        for ty in live.iter() {
+            let kind = match &resolve.types[ty].kind {
+                TypeDefKind::Future(_) => "future",
+                TypeDefKind::Stream(_) => "stream",
+                _ => continue,
+            };
+            bail!(
+                "`{kind}` types are not supported by Wasmtime yet since they \
+                 require the component model async ABI"
+            );
+        }
+
         self.types.analyze(resolve, id);
 
         self.world_link_options.write_struct(&mut self.src);
@@ -1664,8 +1682,9 @@ impl<'a> InterfaceGenerator<'a> {
             TypeDefKind::Result(r) => self.type_result(id, name, r, &ty.docs),
             TypeDefKind::List(t) => self.type_list(id, name, t, &ty.docs),
             TypeDefKind::Type(t) => self.type_alias(id, name, t, &ty.docs),
-            TypeDefKind::Future(_) => todo!("generate for future"),
-            TypeDefKind::Stream(_) => todo!("generate for stream"),
+            TypeDefKind::Future(_) | TypeDefKind::Stream(_) => {
+                unreachable!("`future` and `stream` types are rejected in `generate`")
+            }
             TypeDefKind::Handle(handle) => self.type_handle(id, name, handle, &ty.docs),
             TypeDefKind::Resource => self.type_resource(id, name, ty, &ty.docs),
             TypeDefKind::Unknown => unreachable!(),
@@ -3358,3 +3377,64 @@ fn get_world_resources<'a>(
             _ => None,
         })
 }
+
+#[cfg(test)]
+mod tests {
+    use super::Opts;
+    use wit_parser::Resolve;
+
+    fn generate(wit: &str) -> anyhow::Result<String> {
+        let mut resolve = Resolve::default();
+        let pkg = resolve.push_str("test.wit", wit)?;
+        let world = resolve.select_world(pkg, None)?;
+        Opts::default().generate(&resolve, world)
+    }
+
+    #[test]
+    fn future_and_stream_are_rejected() {
+        for (ty, name) in [("future<u32>", "future"), ("stream<u8>", "stream")] {
+            let err = generate(&format!(
+                "
+                    package a:b;
+
+                    interface i {{
+                        f: func(x: {ty});
+                    }}
+
+                    world w {{
+                        import i;
+                    }}
+                "
+            ))
+            .unwrap_err();
+            assert_eq!(
+                err.to_string(),
+                format!(
+                    "`{name}` types are not supported by Wasmtime yet since they \
+                     require the component model async ABI"
+                ),
+            );
+        }
+    }
+
+    #[test]
+    fn unused_future_is_ignored() {
+        generate(
+            "
+                package a:b;
+
+                interface i {
+                    type t = future<u32>;
+                    f: func();
+                }
+
+                world w {
+                    import j: interface {
+                        g: func();
+                    }
+                }
+            ",
+        )
+        .unwrap();
+    }
+}
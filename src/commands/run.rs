@@ -5,6 +5,8 @@
     allow(irrefutable_let_patterns, unreachable_patterns)
 )]
 
+#[cfg(feature = "coredump")]
+use crate::common::CoreDumpWriter;
 use crate::common::{Profile, RunCommon, RunTarget};
 
 use anyhow::{anyhow, bail, Context as _, Error, Result};
@@ -15,7 +17,7 @@ use std::sync::{Arc, Mutex};
 use std::thread;
 use wasi_common::sync::{ambient_authority, Dir, TcpListener, WasiCtxBuilder};
 use wasmtime::{Engine, Func, Module, Store, StoreLimits, Val, ValType};
-use wasmtime_wasi::WasiView;
+use wasmtime_wasi::{Trace, WasiView};
 
 #[cfg(feature = "wasi-nn")]
 use wasmtime_wasi_nn::wit::WasiNnView;
@@ -24,11 +26,13 @@ use wasmtime_wasi_nn::wit::WasiNnView;
 use wasmtime_wasi_threads::WasiThreadsCtx;
 
 #[cfg(feature = "wasi-config")]
-use wasmtime_wasi_config::{WasiConfig, WasiConfigVariables};
+use wasmtime_wasi_config::{ConfigProvider, WasiConfig};
+#[cfg(feature = "wasi-http")]
+use wasmtime_wasi_http::replay::{Recording, ReplayTransport};
 #[cfg(feature = "wasi-http")]
 use wasmtime_wasi_http::WasiHttpCtx;
 #[cfg(feature = "wasi-keyvalue")]
-use wasmtime_wasi_keyvalue::{WasiKeyValue, WasiKeyValueCtx, WasiKeyValueCtxBuilder};
+use wasmtime_wasi_keyvalue::{WasiKeyValue, WasiKeyValueCtx};
 
 fn parse_preloads(s: &str) -> Result<(String, PathBuf)> {
     let parts: Vec<&str> = s.splitn(2, '=').collect();
@@ -67,6 +71,24 @@ pub struct RunCommand {
     #[arg(long)]
     pub argv0: Option<String>,
 
+    /// Record the results of the nondeterministic WASI calls made by the
+    /// guest, such as reading clocks, stdin or files, to a trace at PATH.
+    ///
+    /// The trace can be replayed later with `--replay` to reproduce this run
+    /// without the original environment. Socket calls other than name lookups
+    /// aren't recorded, so this can't be combined with options granting
+    /// access to sockets, such as `-S inherit-network` or `-S socket-allow`.
+    #[arg(long, value_name = "PATH", conflicts_with = "replay")]
+    pub record: Option<PathBuf>,
+
+    /// Replay the trace at PATH recorded with `--record`, instead of using
+    /// the clocks, random numbers, stdin, directories, environment and HTTP
+    /// requests of the host.
+    ///
+    /// Arguments passed to the guest are also taken from the trace.
+    #[arg(long, value_name = "PATH")]
+    pub replay: Option<PathBuf>,
+
     /// The WebAssembly module to run and arguments to pass to it.
     ///
     /// Arguments passed to the wasm module will be configured as WASI CLI
@@ -111,12 +133,11 @@ impl RunCommand {
             .run
             .load_module(&engine, self.module_and_args[0].as_ref())?;
 
-        // Validate coredump-on-trap argument
-        if let Some(path) = &self.run.common.debug.coredump {
-            if path.contains("%") {
-                bail!("the coredump-on-trap path does not support patterns yet.")
-            }
-        }
+        // Validate the coredump-on-trap arguments up front; the writer is used
+        // when a trap reaches `handle_core_dump`.
+        #[cfg(feature = "coredump")]
+        let coredumps =
+            CoreDumpWriter::new(&self.run.common, self.module_and_args[0].as_ref(), false)?;
 
         let mut linker = match &main {
             RunTarget::Core(_) => CliLinker::Core(wasmtime::Linker::new(&engine)),
@@ -137,7 +158,12 @@ impl RunCommand {
             }
         }
 
-        let host = Host::default();
+        let host = Host {
+            trace: self.trace()?,
+            #[cfg(feature = "coredump")]
+            coredumps: coredumps.map(Arc::new),
+            ..Host::default()
+        };
         let mut store = Store::new(&engine, host);
         self.populate_with_wasi(&mut linker, &mut store, &main)?;
 
@@ -217,6 +243,11 @@ impl RunCommand {
             .await
         });
 
+        // The process may exit below, so make sure the whole trace is written.
+        if let Some(trace) = &store.data().trace {
+            trace.flush()?;
+        }
+
         // Load the main wasm module.
         match result.unwrap_or_else(|elapsed| {
             Err(anyhow::Error::from(wasmtime::Trap::Interrupt))
@@ -254,6 +285,34 @@ impl RunCommand {
         Ok(())
     }
 
+    fn trace(&self) -> Result<Option<Trace>> {
+        // Only name lookups are recorded, so the data read from sockets
+        // couldn't be replayed.
+        if self.record.is_some() || self.replay.is_some() {
+            let wasi = &self.run.common.wasi;
+            let socket_access = [
+                (wasi.inherit_network == Some(true), "inherit-network"),
+                (!wasi.socket_allow.is_empty(), "socket-allow"),
+                (wasi.socket_policy.is_some(), "socket-policy"),
+                (!wasi.tcplisten.is_empty(), "tcplisten"),
+                (wasi.listenfd == Some(true), "listenfd"),
+            ];
+            if let Some((_, flag)) = socket_access.iter().find(|(granted, _)| *granted) {
+                bail!(
+                    "`--record` and `--replay` are not supported with access to sockets, \
+                     which `-S {flag}` grants"
+                );
+            }
+        }
+        if let Some(path) = &self.record {
+            return Ok(Some(Trace::record_file(path)?));
+        }
+        if let Some(path) = &self.replay {
+            return Ok(Some(Trace::replay_file(path)?));
+        }
+        Ok(None)
+    }
+
     fn compute_argv(&self) -> Result<Vec<String>> {
         let mut result = Vec::new();
 
@@ -561,6 +620,8 @@ impl RunCommand {
                 Val::FuncRef(Some(_)) => println!("<funcref>"),
                 Val::AnyRef(None) => println!("<null anyref>"),
                 Val::AnyRef(Some(_)) => println!("<anyref>"),
+                Val::ExnRef(None) => println!("<null exnref>"),
+                Val::ExnRef(Some(_)) => println!("<exnref>"),
             }
         }
 
@@ -569,22 +630,9 @@ impl RunCommand {
 
     #[cfg(feature = "coredump")]
     fn handle_core_dump(&self, store: &mut Store<Host>, err: Error) -> Error {
-        let coredump_path = match &self.run.common.debug.coredump {
-            Some(path) => path,
-            None => return err,
-        };
-        if !err.is::<wasmtime::Trap>() {
-            return err;
-        }
-        let source_name = self.module_and_args[0]
-            .to_str()
-            .unwrap_or_else(|| "unknown");
-
-        if let Err(coredump_err) = write_core_dump(store, &err, &source_name, coredump_path) {
-            eprintln!("warning: coredump failed to generate: {coredump_err}");
-            err
-        } else {
-            err.context(format!("core dumped at {coredump_path}"))
+        match store.data().coredumps.clone() {
+            Some(writer) => writer.handle_trap(store, err, None),
+            None => err,
         }
     }
 
@@ -709,19 +757,11 @@ impl RunCommand {
                         bail!("Cannot enable wasi-config for core wasm modules");
                     }
                     CliLinker::Component(linker) => {
-                        let vars = WasiConfigVariables::from_iter(
-                            self.run
-                                .common
-                                .wasi
-                                .config_var
-                                .iter()
-                                .map(|v| (v.key.clone(), v.value.clone())),
-                        );
-
+                        let provider = self.run.wasi_config_provider()?;
                         wasmtime_wasi_config::add_to_linker(linker, |h| {
-                            WasiConfig::new(Arc::get_mut(h.wasi_config.as_mut().unwrap()).unwrap())
+                            WasiConfig::new(&**h.wasi_config.as_ref().unwrap())
                         })?;
-                        store.data_mut().wasi_config = Some(Arc::new(vars));
+                        store.data_mut().wasi_config = Some(provider);
                     }
                 }
             }
@@ -739,16 +779,7 @@ impl RunCommand {
                         bail!("Cannot enable wasi-keyvalue for core wasm modules");
                     }
                     CliLinker::Component(linker) => {
-                        let ctx = WasiKeyValueCtxBuilder::new()
-                            .in_memory_data(
-                                self.run
-                                    .common
-                                    .wasi
-                                    .keyvalue_in_memory_data
-                                    .iter()
-                                    .map(|v| (v.key.clone(), v.value.clone())),
-                            )
-                            .build();
+                        let ctx = self.run.wasi_keyvalue_ctx()?;
 
                         wasmtime_wasi_keyvalue::add_to_linker(linker, |h| {
                             let preview2_ctx =
@@ -810,7 +841,10 @@ impl RunCommand {
                     }
                 }
 
-                store.data_mut().wasi_http = Some(Arc::new(WasiHttpCtx::new()));
+                store.data_mut().wasi_http = Some(Arc::new(self.run.wasi_http_ctx()?));
+                if let Some(trace) = store.data().trace.clone() {
+                    store.data_mut().http_transport = Some(traced_http_transport(trace)?);
+                }
             }
         }
 
@@ -818,6 +852,14 @@ impl RunCommand {
     }
 
     fn set_preview1_ctx(&self, store: &mut Store<Host>) -> Result<()> {
+        if self.run.common.wasi.deterministic == Some(true) {
+            bail!("`-S deterministic` is not supported with `-S preview2=n` or wasi-threads");
+        }
+        if store.data().trace.is_some() {
+            bail!(
+                "`--record` and `--replay` are not supported with `-S preview2=n` or wasi-threads"
+            );
+        }
         let mut builder = WasiCtxBuilder::new();
         builder.inherit_stdio().args(&self.compute_argv()?)?;
 
@@ -868,6 +910,9 @@ impl RunCommand {
         let mut builder = wasmtime_wasi::WasiCtxBuilder::new();
         builder.inherit_stdio().args(&self.compute_argv()?);
         self.run.configure_wasip2(&mut builder)?;
+        if let Some(trace) = &store.data().trace {
+            builder.trace(trace.clone());
+        }
         let ctx = builder.build_p1();
         store.data_mut().preview2_ctx = Some(Arc::new(Mutex::new(ctx)));
         Ok(())
@@ -907,12 +952,19 @@ struct Host {
     wasi_threads: Option<Arc<WasiThreadsCtx<Host>>>,
     #[cfg(feature = "wasi-http")]
     wasi_http: Option<Arc<WasiHttpCtx>>,
+    /// Replaces the network for outgoing requests when a trace is being
+    /// recorded or replayed.
+    #[cfg(feature = "wasi-http")]
+    http_transport: Option<ReplayTransport>,
+    trace: Option<Trace>,
+    #[cfg(feature = "coredump")]
+    coredumps: Option<Arc<CoreDumpWriter>>,
     limits: StoreLimits,
     #[cfg(feature = "profiling")]
     guest_profiler: Option<Arc<wasmtime::GuestProfiler>>,
 
     #[cfg(feature = "wasi-config")]
-    wasi_config: Option<Arc<WasiConfigVariables>>,
+    wasi_config: Option<Arc<dyn ConfigProvider>>,
     #[cfg(feature = "wasi-keyvalue")]
     wasi_keyvalue: Option<Arc<WasiKeyValueCtx>>,
 }
@@ -950,6 +1002,42 @@ impl wasmtime_wasi_http::types::WasiHttpView for Host {
     fn table(&mut self) -> &mut wasmtime::component::ResourceTable {
         self.preview2_ctx().table()
     }
+
+    fn send_request(
+        &mut self,
+        request: hyper::Request<wasmtime_wasi_http::body::HyperOutgoingBody>,
+        config: wasmtime_wasi_http::types::OutgoingRequestConfig,
+    ) -> wasmtime_wasi_http::HttpResult<wasmtime_wasi_http::types::HostFutureIncomingResponse> {
+        Ok(match &self.http_transport {
+            Some(transport) => transport.send_request(request, config),
+            None => wasmtime_wasi_http::types::default_send_request(request, config),
+        })
+    }
+}
+
+/// The name of the records of outgoing HTTP requests in a trace.
+#[cfg(feature = "wasi-http")]
+const HTTP_TRACE_RECORDS: &str = "wasi-http";
+
+/// Returns a transport which records outgoing HTTP requests into `trace`, or
+/// replays them from it.
+#[cfg(feature = "wasi-http")]
+fn traced_http_transport(trace: Trace) -> Result<ReplayTransport> {
+    if trace.is_replay() {
+        let mut recording = Recording::default();
+        for json in trace.custom_records(HTTP_TRACE_RECORDS) {
+            recording
+                .entries
+                .extend(Recording::from_json(&json)?.entries);
+        }
+        return Ok(ReplayTransport::replay(recording));
+    }
+    Ok(ReplayTransport::record_with(move |entry| {
+        let recording = Recording {
+            entries: vec![entry.clone()],
+        };
+        trace.record_custom(HTTP_TRACE_RECORDS, &recording.to_json())
+    }))
 }
 
 #[cfg(not(unix))]
@@ -980,27 +1068,3 @@ fn ctx_set_listenfd(mut num_fd: usize, builder: &mut WasiCtxBuilder) -> Result<u
 
     Ok(num_fd)
 }
-
-#[cfg(feature = "coredump")]
-fn write_core_dump(
-    store: &mut Store<Host>,
-    err: &anyhow::Error,
-    name: &str,
-    path: &str,
-) -> Result<()> {
-    use std::fs::File;
-    use std::io::Write;
-
-    let core_dump = err
-        .downcast_ref::<wasmtime::WasmCoreDump>()
-        .expect("should have been configured to capture core dumps");
-
-    let core_dump = core_dump.serialize(store, name);
-
-    let mut core_dump_file =
-        File::create(path).context(format!("failed to create file at `{path}`"))?;
-    core_dump_file
-        .write_all(&core_dump)
-        .with_context(|| format!("failed to write core dump file at `{path}`"))?;
-    Ok(())
-}
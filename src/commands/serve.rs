@@ -1,26 +1,43 @@
+#[cfg(feature = "coredump")]
+use crate::common::CoreDumpWriter;
 use crate::common::{Profile, RunCommon, RunTarget};
-use anyhow::{anyhow, bail, Result};
+use anyhow::{anyhow, bail, Context as _, Result};
 use clap::Parser;
+use http_body_util::{BodyExt, Empty};
+use hyper::server::conn::{http1, http2};
+use std::future::Future;
 use std::net::SocketAddr;
 use std::{
     path::PathBuf,
+    pin::Pin,
     sync::{
         atomic::{AtomicBool, AtomicU64, Ordering},
         Arc,
     },
+    task::{Context, Poll},
+    time::{Duration, Instant},
 };
+use tokio::io::{AsyncRead, AsyncReadExt, AsyncWrite, ReadBuf};
+use tokio::net::TcpStream;
+use tokio::sync::{mpsc, watch, Semaphore};
+use tokio_rustls::{rustls, TlsAcceptor};
 use wasmtime::component::Linker;
-use wasmtime::{Config, Engine, Memory, MemoryType, Store, StoreLimits};
+use wasmtime::{Config, Engine, Memory, MemoryType, Store, StoreLimits, Trap, UpdateDeadline};
+use wasmtime_cli_flags::opt::WasmtimeOptionValue;
 use wasmtime_wasi::{StreamError, StreamResult, WasiCtx, WasiCtxBuilder, WasiView};
 use wasmtime_wasi_http::bindings::http::types::Scheme;
 use wasmtime_wasi_http::bindings::ProxyPre;
 use wasmtime_wasi_http::io::TokioIo;
 use wasmtime_wasi_http::{body::HyperOutgoingBody, WasiHttpCtx, WasiHttpView};
 
+#[cfg(feature = "wasi-keyvalue")]
+use tokio::sync::broadcast;
 #[cfg(feature = "wasi-config")]
-use wasmtime_wasi_config::{WasiConfig, WasiConfigVariables};
+use wasmtime_wasi_config::{ConfigProvider, WasiConfig};
 #[cfg(feature = "wasi-keyvalue")]
-use wasmtime_wasi_keyvalue::{WasiKeyValue, WasiKeyValueCtx, WasiKeyValueCtxBuilder};
+use wasmtime_wasi_keyvalue::{
+    KeyValueEvent, Subscription, WasiKeyValue, WasiKeyValueCtx, WatchServicePre,
+};
 #[cfg(feature = "wasi-nn")]
 use wasmtime_wasi_nn::wit::WasiNnCtx;
 
@@ -35,7 +52,7 @@ struct Host {
     nn: Option<WasiNnCtx>,
 
     #[cfg(feature = "wasi-config")]
-    wasi_config: Option<WasiConfigVariables>,
+    wasi_config: Option<Arc<dyn ConfigProvider>>,
 
     #[cfg(feature = "wasi-keyvalue")]
     wasi_keyvalue: Option<WasiKeyValueCtx>,
@@ -76,6 +93,52 @@ pub struct ServeCommand {
     #[arg(long = "addr", value_name = "SOCKADDR", default_value_t = DEFAULT_ADDR )]
     addr: SocketAddr,
 
+    /// Serve HTTPS using the PEM-encoded certificate chain in this file.
+    ///
+    /// Requires `--tls-key`. Clients may negotiate HTTP/2 via ALPN.
+    #[arg(long = "tls-cert", value_name = "PATH", requires = "tls_key")]
+    tls_cert: Option<PathBuf>,
+
+    /// The PEM-encoded private key for `--tls-cert`.
+    #[arg(long = "tls-key", value_name = "PATH", requires = "tls_cert")]
+    tls_key: Option<PathBuf>,
+
+    /// How long to wait for in-flight requests when shutting down.
+    ///
+    /// Upon SIGINT or SIGTERM the server stops accepting connections and waits
+    /// for in-flight requests to finish. Any still running after this long are
+    /// interrupted and the server exits with an error.
+    #[arg(
+        long = "shutdown-timeout",
+        value_name = "DURATION",
+        default_value = "30s",
+        value_parser = parse_duration,
+    )]
+    shutdown_timeout: Duration,
+
+    /// Maximum number of requests to handle concurrently.
+    ///
+    /// Further requests are queued until one of the in-flight requests
+    /// finishes, see `--max-queued-requests`.
+    #[arg(
+        long = "max-concurrent-requests",
+        value_name = "N",
+        value_parser = clap::value_parser!(u32).range(1..),
+    )]
+    max_concurrent_requests: Option<u32>,
+
+    /// Maximum number of requests to queue once `--max-concurrent-requests`
+    /// are in flight.
+    ///
+    /// Requests beyond this are responded to with 503 Service Unavailable.
+    /// By default the queue is unbounded.
+    #[arg(
+        long = "max-queued-requests",
+        value_name = "N",
+        requires = "max_concurrent_requests"
+    )]
+    max_queued_requests: Option<u32>,
+
     /// The WebAssembly component to run.
     #[arg(value_name = "WASM", required = true)]
     component: PathBuf,
@@ -117,22 +180,18 @@ impl ServeCommand {
             .enable_io()
             .build()?;
 
-        runtime.block_on(async move {
-            tokio::select! {
-                _ = tokio::signal::ctrl_c() => {
-                    Ok::<_, anyhow::Error>(())
-                }
-
-                res = self.serve() => {
-                    res
-                }
-            }
-        })?;
-
-        Ok(())
+        runtime.block_on(self.serve())
     }
 
-    fn new_store(&self, engine: &Engine, req_id: u64) -> Result<Store<Host>> {
+    fn new_store(
+        &self,
+        engine: &Engine,
+        req_id: u64,
+        cancelled: &Arc<AtomicBool>,
+        http: &WasiHttpCtx,
+        #[cfg(feature = "wasi-config")] config: Option<&Arc<dyn ConfigProvider>>,
+        #[cfg(feature = "wasi-keyvalue")] keyvalue: Option<&WasiKeyValueCtx>,
+    ) -> Result<Store<Host>> {
         let mut builder = WasiCtxBuilder::new();
         self.run.configure_wasip2(&mut builder)?;
 
@@ -151,16 +210,16 @@ impl ServeCommand {
         let mut host = Host {
             table: wasmtime::component::ResourceTable::new(),
             ctx: builder.build(),
-            http: WasiHttpCtx::new(),
+            http: http.clone(),
 
             limits: StoreLimits::default(),
 
             #[cfg(feature = "wasi-nn")]
             nn: None,
             #[cfg(feature = "wasi-config")]
-            wasi_config: None,
+            wasi_config: config.cloned(),
             #[cfg(feature = "wasi-keyvalue")]
-            wasi_keyvalue: None,
+            wasi_keyvalue: keyvalue.cloned(),
         };
 
         if self.run.common.wasi.nn == Some(true) {
@@ -179,43 +238,28 @@ impl ServeCommand {
             }
         }
 
-        if self.run.common.wasi.config == Some(true) {
-            #[cfg(feature = "wasi-config")]
-            {
-                let vars = WasiConfigVariables::from_iter(
-                    self.run
-                        .common
-                        .wasi
-                        .config_var
-                        .iter()
-                        .map(|v| (v.key.clone(), v.value.clone())),
-                );
-                host.wasi_config.replace(vars);
-            }
-        }
+        let mut store = Store::new(engine, host);
 
-        if self.run.common.wasi.keyvalue == Some(true) {
-            #[cfg(feature = "wasi-keyvalue")]
+        // Yield back to the event loop on every epoch so that a busy guest
+        // can't starve other connections or shutdown, and interrupt the guest
+        // once it times out or the server gives up on draining requests. Epochs
+        // only advance with `-W timeout` or once the server is shutting down.
+        let deadline = self
+            .run
+            .common
+            .wasm
+            .timeout
+            .map(|timeout| Instant::now() + timeout);
+        let cancelled = cancelled.clone();
+        store.set_epoch_deadline(1);
+        store.epoch_deadline_callback(move |_| {
+            if cancelled.load(Ordering::Relaxed)
+                || deadline.is_some_and(|deadline| Instant::now() >= deadline)
             {
-                let ctx = WasiKeyValueCtxBuilder::new()
-                    .in_memory_data(
-                        self.run
-                            .common
-                            .wasi
-                            .keyvalue_in_memory_data
-                            .iter()
-                            .map(|v| (v.key.clone(), v.value.clone())),
-                    )
-                    .build();
-                host.wasi_keyvalue.replace(ctx);
+                return Err(Trap::Interrupt.into());
             }
-        }
-
-        let mut store = Store::new(engine, host);
-
-        if self.run.common.wasm.timeout.is_some() {
-            store.set_epoch_deadline(u64::from(EPOCH_PRECISION) + 1);
-        }
+            Ok(UpdateDeadline::Yield(1))
+        });
 
         store.data_mut().limits = self.run.store_limits();
         store.limiter(|t| &mut t.limits);
@@ -283,7 +327,7 @@ impl ServeCommand {
             #[cfg(feature = "wasi-config")]
             {
                 wasmtime_wasi_config::add_to_linker(linker, |h| {
-                    WasiConfig::from(h.wasi_config.as_ref().unwrap())
+                    WasiConfig::new(&**h.wasi_config.as_ref().unwrap())
                 })?;
             }
         }
@@ -313,8 +357,6 @@ impl ServeCommand {
     }
 
     async fn serve(mut self) -> Result<()> {
-        use hyper::server::conn::http1;
-
         let mut config = self
             .run
             .common
@@ -322,9 +364,7 @@ impl ServeCommand {
         config.wasm_component_model(true);
         config.async_support(true);
 
-        if self.run.common.wasm.timeout.is_some() {
-            config.epoch_interruption(true);
-        }
+        config.epoch_interruption(true);
 
         match self.run.profile {
             Some(Profile::Native(s)) => {
@@ -350,6 +390,31 @@ impl ServeCommand {
         let instance = linker.instantiate_pre(&component)?;
         let instance = ProxyPre::new(instance)?;
 
+        #[cfg(feature = "coredump")]
+        let coredumps = CoreDumpWriter::new(&self.run.common, &self.component, true)?;
+
+        // Providers are created once so that files are only watched once.
+        #[cfg(feature = "wasi-config")]
+        let config = match self.run.common.wasi.config {
+            Some(true) => Some(self.run.wasi_config_provider()?),
+            _ => None,
+        };
+        // Stores are opened once so that all requests share their data.
+        #[cfg(feature = "wasi-keyvalue")]
+        let keyvalue = match self.run.common.wasi.keyvalue {
+            Some(true) => Some(self.run.wasi_keyvalue_ctx()?),
+            _ => None,
+        };
+        // Components exporting `wasi:keyvalue/watcher` are told about the
+        // changes that requests make to stores.
+        #[cfg(feature = "wasi-keyvalue")]
+        let watcher = match &keyvalue {
+            Some(keyvalue) => WatchServicePre::new(instance.instance_pre().clone())
+                .ok()
+                .map(|watcher| (watcher, keyvalue.subscribe())),
+            None => None,
+        };
+
         let socket = match &self.addr {
             SocketAddr::V4(_) => tokio::net::TcpSocket::new_v4()?,
             SocketAddr::V6(_) => tokio::net::TcpSocket::new_v6()?,
@@ -366,47 +431,363 @@ impl ServeCommand {
         socket.bind(self.addr)?;
         let listener = socket.listen(100)?;
 
-        eprintln!("Serving HTTP on http://{}/", listener.local_addr()?);
+        let tls = self.tls_acceptor()?;
+        let scheme = if tls.is_some() { "https" } else { "http" };
+        eprintln!("Serving HTTP on {scheme}://{}/", listener.local_addr()?);
 
-        let _epoch_thread = if let Some(timeout) = self.run.common.wasm.timeout {
-            Some(EpochThread::spawn(
-                timeout / EPOCH_PRECISION,
-                engine.clone(),
-            ))
-        } else {
-            None
-        };
+        // Epochs only need to advance to enforce `-W timeout`, otherwise
+        // they're started once shutdown is requested.
+        let epoch_thread = self
+            .run
+            .common
+            .wasm
+            .timeout
+            .map(|timeout| EpochThread::spawn(timeout / EPOCH_PRECISION, engine.clone()));
+        let mut shutdown = ShutdownSignals::spawn(epoch_thread.is_none().then(|| engine.clone()));
 
         log::info!("Listening on {}", self.addr);
 
-        let handler = ProxyHandler::new(self, engine, instance);
+        let shutdown_timeout = self.shutdown_timeout;
+        let (shutdown_tx, shutdown_rx) = watch::channel(false);
+        let (in_flight, mut drained) = mpsc::channel(1);
+        let cancelled = Arc::new(AtomicBool::new(false));
+        let http = self.run.wasi_http_ctx()?;
+        let handler = ProxyHandler::new(
+            self,
+            engine.clone(),
+            instance,
+            shutdown_rx,
+            cancelled.clone(),
+            in_flight,
+            http,
+            #[cfg(feature = "coredump")]
+            coredumps,
+            #[cfg(feature = "wasi-config")]
+            config,
+            #[cfg(feature = "wasi-keyvalue")]
+            keyvalue,
+        );
+
+        #[cfg(feature = "wasi-keyvalue")]
+        if let Some((watcher, events)) = watcher {
+            tokio::task::spawn(watch_keyvalue(handler.inner.clone(), watcher, events));
+        }
 
         loop {
-            let (stream, _) = listener.accept().await?;
-            let stream = TokioIo::new(stream);
+            let (stream, _) = tokio::select! {
+                res = listener.accept() => res?,
+                res = shutdown.recv() => {
+                    res?;
+                    break;
+                }
+            };
             let h = handler.clone();
+            let tls = tls.clone();
             tokio::task::spawn(async {
-                if let Err(e) = http1::Builder::new()
-                    .keep_alive(true)
-                    .serve_connection(
-                        stream,
-                        hyper::service::service_fn(move |req| handle_request(h.clone(), req)),
-                    )
-                    .await
-                {
+                if let Err(e) = serve_connection(h, stream, tls).await {
                     eprintln!("error: {e:?}");
                 }
             });
         }
+
+        // Stop accepting connections, ask the open ones to close once their
+        // in-flight requests are done, and wait for those requests to finish.
+        // Everything serving a connection or request holds a clone of the
+        // handler, so once they're all gone `drained` is closed.
+        eprintln!("Shutting down, waiting up to {shutdown_timeout:?} for in-flight requests");
+        drop(listener);
+        shutdown_tx.send_replace(true);
+        drop(handler);
+        let finished = tokio::select! {
+            res = tokio::time::timeout(shutdown_timeout, drained.recv()) => res.is_ok(),
+            res = shutdown.recv() => {
+                res?;
+                eprintln!("Shutdown requested again, cancelling in-flight requests");
+                false
+            }
+        };
+        if finished {
+            return Ok(());
+        }
+
+        cancelled.store(true, Ordering::Relaxed);
+        // Give the cancelled requests a moment to unwind, but don't wait on
+        // any which are blocked outside of wasm.
+        let _ = tokio::time::timeout(Duration::from_secs(1), drained.recv()).await;
+        bail!("in-flight requests did not finish before shutting down and were cancelled")
+    }
+
+    /// Loads the certificate and key given by `--tls-cert` and `--tls-key`,
+    /// if any.
+    fn tls_acceptor(&self) -> Result<Option<TlsAcceptor>> {
+        let (Some(cert_path), Some(key_path)) = (&self.tls_cert, &self.tls_key) else {
+            return Ok(None);
+        };
+        let open = |path: &PathBuf| -> Result<_> {
+            let file = std::fs::File::open(path)
+                .with_context(|| format!("failed to open `{}`", path.display()))?;
+            Ok(std::io::BufReader::new(file))
+        };
+
+        let certs = rustls_pemfile::certs(&mut open(cert_path)?)
+            .collect::<Result<Vec<_>, _>>()
+            .with_context(|| {
+                format!("failed to read certificates from `{}`", cert_path.display())
+            })?;
+        if certs.is_empty() {
+            bail!("no certificates found in `{}`", cert_path.display());
+        }
+        let key = rustls_pemfile::private_key(&mut open(key_path)?)
+            .with_context(|| format!("failed to read private key from `{}`", key_path.display()))?
+            .ok_or_else(|| anyhow!("no private key found in `{}`", key_path.display()))?;
+
+        let mut config = rustls::ServerConfig::builder()
+            .with_no_client_auth()
+            .with_single_cert(certs, key)
+            .context("invalid TLS certificate or key")?;
+        config.alpn_protocols = vec![b"h2".to_vec(), b"http/1.1".to_vec()];
+        Ok(Some(TlsAcceptor::from(Arc::new(config))))
+    }
+}
+
+/// Serves a single accepted connection, terminating TLS first if configured.
+///
+/// The HTTP version is negotiated through ALPN for TLS connections, while
+/// plaintext connections are HTTP/2 if they start with its connection preface
+/// (i.e. with prior knowledge) and HTTP/1.1 otherwise.
+async fn serve_connection(
+    handler: ProxyHandler,
+    stream: TcpStream,
+    tls: Option<TlsAcceptor>,
+) -> Result<()> {
+    match tls {
+        Some(tls) => {
+            let stream = tls.accept(stream).await?;
+            let http2 = stream.get_ref().1.alpn_protocol() == Some(b"h2");
+            serve_http(handler, stream, Scheme::Https, http2).await
+        }
+        None => {
+            let (stream, http2) = Rewind::detect_http2(stream).await?;
+            serve_http(handler, stream, Scheme::Http, http2).await
+        }
+    }
+}
+
+async fn serve_http<S>(handler: ProxyHandler, stream: S, scheme: Scheme, http2: bool) -> Result<()>
+where
+    S: AsyncRead + AsyncWrite + Unpin + Send + 'static,
+{
+    let mut shutdown = handler.inner.shutdown.clone();
+    let stream = TokioIo::new(stream);
+    let service =
+        hyper::service::service_fn(move |req| handle_request(handler.clone(), scheme.clone(), req));
+
+    // Once the server starts shutting down the connection is closed as soon
+    // as its in-flight requests are done.
+    macro_rules! serve {
+        ($conn:expr) => {{
+            let mut conn = std::pin::pin!($conn);
+            tokio::select! {
+                res = conn.as_mut() => return Ok(res?),
+                _ = shutdown.wait_for(|shutdown| *shutdown) => {
+                    conn.as_mut().graceful_shutdown();
+                }
+            }
+            conn.await?;
+        }};
+    }
+    if http2 {
+        serve!(http2::Builder::new(TokioExecutor).serve_connection(stream, service));
+    } else {
+        serve!(http1::Builder::new()
+            .keep_alive(true)
+            .serve_connection(stream, service));
+    }
+    Ok(())
+}
+
+/// Resolves once the server is asked to shut down with SIGINT or SIGTERM, or
+/// with Ctrl-C on platforms without them.
+async fn shutdown_requested() -> Result<()> {
+    #[cfg(unix)]
+    {
+        use tokio::signal::unix::{signal, SignalKind};
+        let mut interrupt = signal(SignalKind::interrupt())?;
+        let mut terminate = signal(SignalKind::terminate())?;
+        tokio::select! {
+            _ = interrupt.recv() => {}
+            _ = terminate.recv() => {}
+        }
+    }
+    #[cfg(not(unix))]
+    tokio::signal::ctrl_c().await?;
+    Ok(())
+}
+
+/// Waits for shutdown requests on a dedicated thread.
+///
+/// Guests only yield back to the event loop when epochs advance, so when
+/// there's no `-W timeout` busy guests can occupy every worker of the server's
+/// runtime and keep it from ever noticing a signal. Signals are handled on a
+/// separate runtime instead, which starts advancing epochs once shutdown is
+/// first requested so that busy guests yield and can be cancelled.
+struct ShutdownSignals {
+    requests: mpsc::UnboundedReceiver<Result<()>>,
+}
+
+impl ShutdownSignals {
+    /// Starts waiting for signals, advancing the epochs of `engine`, if any,
+    /// once one arrives.
+    fn spawn(engine: Option<Engine>) -> ShutdownSignals {
+        let (tx, requests) = mpsc::unbounded_channel();
+        std::thread::spawn(move || {
+            let runtime = match tokio::runtime::Builder::new_current_thread()
+                .enable_io()
+                .build()
+            {
+                Ok(runtime) => runtime,
+                Err(e) => {
+                    let _ = tx.send(Err(e.into()));
+                    return;
+                }
+            };
+            runtime.block_on(async {
+                let mut _epoch_thread = None;
+                loop {
+                    let res = shutdown_requested().await;
+                    if let Some(engine) = &engine {
+                        _epoch_thread.get_or_insert_with(|| {
+                            EpochThread::spawn(YIELD_INTERVAL, engine.clone())
+                        });
+                    }
+                    let failed = res.is_err();
+                    if tx.send(res).is_err() || failed {
+                        break;
+                    }
+                }
+            });
+        });
+        ShutdownSignals { requests }
+    }
+
+    /// Waits for the next shutdown request.
+    async fn recv(&mut self) -> Result<()> {
+        match self.requests.recv().await {
+            Some(res) => res,
+            None => std::future::pending().await,
+        }
+    }
+}
+
+fn parse_duration(s: &str) -> Result<Duration> {
+    Duration::parse(Some(s))
+}
+
+/// Spawns the tasks of HTTP/2 connections onto the Tokio runtime.
+#[derive(Clone)]
+struct TokioExecutor;
+
+impl<F> hyper::rt::Executor<F> for TokioExecutor
+where
+    F: Future + Send + 'static,
+    F::Output: Send + 'static,
+{
+    fn execute(&self, fut: F) {
+        tokio::task::spawn(fut);
+    }
+}
+
+/// The preface which starts every HTTP/2 connection.
+const HTTP2_PREFACE: &[u8] = b"PRI * HTTP/2.0\r\n\r\nSM\r\n\r\n";
+
+/// A stream which replays the bytes that were read from it to detect the
+/// HTTP version before reading any more.
+struct Rewind<S> {
+    prefix: Vec<u8>,
+    inner: S,
+}
+
+impl Rewind<TcpStream> {
+    /// Reads as much of `stream` as is needed to tell whether it starts with
+    /// the HTTP/2 preface, returning the rewound stream and whether it does.
+    async fn detect_http2(mut stream: TcpStream) -> Result<(Self, bool)> {
+        let mut prefix = Vec::with_capacity(HTTP2_PREFACE.len());
+        while prefix.len() < HTTP2_PREFACE.len() && HTTP2_PREFACE.starts_with(&prefix) {
+            let mut buf = [0; HTTP2_PREFACE.len()];
+            let n = stream
+                .read(&mut buf[..HTTP2_PREFACE.len() - prefix.len()])
+                .await?;
+            if n == 0 {
+                break;
+            }
+            prefix.extend_from_slice(&buf[..n]);
+        }
+        let http2 = prefix == HTTP2_PREFACE;
+        Ok((
+            Rewind {
+                prefix,
+                inner: stream,
+            },
+            http2,
+        ))
     }
 }
 
-/// This is the number of epochs that we will observe before expiring a request handler. As
-/// instances may be started at any point within an epoch, and epochs are counted globally per
-/// engine, we expire after `EPOCH_PRECISION + 1` epochs have been observed. This gives a maximum
-/// overshoot of `timeout / EPOCH_PRECISION`, which is more desirable than expiring early.
+impl<S: AsyncRead + Unpin> AsyncRead for Rewind<S> {
+    fn poll_read(
+        mut self: Pin<&mut Self>,
+        cx: &mut Context<'_>,
+        buf: &mut ReadBuf<'_>,
+    ) -> Poll<std::io::Result<()>> {
+        if !self.prefix.is_empty() && buf.remaining() > 0 {
+            let n = self.prefix.len().min(buf.remaining());
+            buf.put_slice(&self.prefix[..n]);
+            self.prefix.drain(..n);
+            return Poll::Ready(Ok(()));
+        }
+        Pin::new(&mut self.inner).poll_read(cx, buf)
+    }
+}
+
+impl<S: AsyncWrite + Unpin> AsyncWrite for Rewind<S> {
+    fn poll_write(
+        mut self: Pin<&mut Self>,
+        cx: &mut Context<'_>,
+        buf: &[u8],
+    ) -> Poll<std::io::Result<usize>> {
+        Pin::new(&mut self.inner).poll_write(cx, buf)
+    }
+
+    fn poll_write_vectored(
+        mut self: Pin<&mut Self>,
+        cx: &mut Context<'_>,
+        bufs: &[std::io::IoSlice<'_>],
+    ) -> Poll<std::io::Result<usize>> {
+        Pin::new(&mut self.inner).poll_write_vectored(cx, bufs)
+    }
+
+    fn is_write_vectored(&self) -> bool {
+        self.inner.is_write_vectored()
+    }
+
+    fn poll_flush(mut self: Pin<&mut Self>, cx: &mut Context<'_>) -> Poll<std::io::Result<()>> {
+        Pin::new(&mut self.inner).poll_flush(cx)
+    }
+
+    fn poll_shutdown(mut self: Pin<&mut Self>, cx: &mut Context<'_>) -> Poll<std::io::Result<()>> {
+        Pin::new(&mut self.inner).poll_shutdown(cx)
+    }
+}
+
+/// This is the number of epochs per request timeout. Request handlers check
+/// whether they've timed out on every epoch, which gives a maximum overshoot of
+/// `timeout / EPOCH_PRECISION`.
 const EPOCH_PRECISION: u32 = 10;
 
+/// How often guests yield back to the event loop while shutting down when
+/// there's no timeout.
+const YIELD_INTERVAL: Duration = Duration::from_millis(10);
+
 struct EpochThread {
     shutdown: Arc<AtomicBool>,
     handle: Option<std::thread::JoinHandle<()>>,
@@ -444,6 +825,26 @@ struct ProxyHandlerInner {
     engine: Engine,
     instance_pre: ProxyPre<Host>,
     next_id: AtomicU64,
+    /// Set to `true` once the server starts shutting down.
+    shutdown: watch::Receiver<bool>,
+    /// Set once the server gives up on in-flight requests, interrupting them.
+    cancelled: Arc<AtomicBool>,
+    /// Permits for requests to be handled, if their concurrency is limited.
+    concurrency: Option<Arc<Semaphore>>,
+    /// Permits for requests to wait for a `concurrency` permit, if the queue
+    /// is bounded.
+    queue: Option<Arc<Semaphore>>,
+    #[cfg(feature = "coredump")]
+    coredumps: Option<CoreDumpWriter>,
+    /// Shared by the stores of all requests so that their outgoing requests
+    /// reuse connections.
+    http: WasiHttpCtx,
+    /// Shared by the stores of all requests.
+    #[cfg(feature = "wasi-config")]
+    config: Option<Arc<dyn ConfigProvider>>,
+    /// Shared by the stores of all requests.
+    #[cfg(feature = "wasi-keyvalue")]
+    keyvalue: Option<WasiKeyValueCtx>,
 }
 
 impl ProxyHandlerInner {
@@ -453,23 +854,66 @@ impl ProxyHandlerInner {
 }
 
 #[derive(Clone)]
-struct ProxyHandler(Arc<ProxyHandlerInner>);
+struct ProxyHandler {
+    inner: Arc<ProxyHandlerInner>,
+    /// Never sent on, this only tracks how many clones of the handler are
+    /// still serving connections or requests so that shutdown can wait for
+    /// them.
+    in_flight: mpsc::Sender<()>,
+}
 
 impl ProxyHandler {
-    fn new(cmd: ServeCommand, engine: Engine, instance_pre: ProxyPre<Host>) -> Self {
-        Self(Arc::new(ProxyHandlerInner {
-            cmd,
-            engine,
-            instance_pre,
-            next_id: AtomicU64::from(0),
-        }))
+    fn new(
+        cmd: ServeCommand,
+        engine: Engine,
+        instance_pre: ProxyPre<Host>,
+        shutdown: watch::Receiver<bool>,
+        cancelled: Arc<AtomicBool>,
+        in_flight: mpsc::Sender<()>,
+        http: WasiHttpCtx,
+        #[cfg(feature = "coredump")] coredumps: Option<CoreDumpWriter>,
+        #[cfg(feature = "wasi-config")] config: Option<Arc<dyn ConfigProvider>>,
+        #[cfg(feature = "wasi-keyvalue")] keyvalue: Option<WasiKeyValueCtx>,
+    ) -> Self {
+        let permits = |max: Option<u32>| max.map(|max| Arc::new(Semaphore::new(max as usize)));
+        let concurrency = permits(cmd.max_concurrent_requests);
+        let queue = permits(cmd.max_queued_requests);
+        Self {
+            inner: Arc::new(ProxyHandlerInner {
+                cmd,
+                engine,
+                instance_pre,
+                next_id: AtomicU64::from(0),
+                shutdown,
+                cancelled,
+                concurrency,
+                queue,
+                #[cfg(feature = "coredump")]
+                coredumps,
+                http,
+                #[cfg(feature = "wasi-config")]
+                config,
+                #[cfg(feature = "wasi-keyvalue")]
+                keyvalue,
+            }),
+            in_flight,
+        }
     }
 }
 
 type Request = hyper::Request<hyper::body::Incoming>;
 
+/// The response to requests which can't be handled right now.
+fn service_unavailable() -> hyper::Response<HyperOutgoingBody> {
+    let body = Empty::new().map_err(|never| match never {}).boxed();
+    let mut response = hyper::Response::new(body);
+    *response.status_mut() = hyper::StatusCode::SERVICE_UNAVAILABLE;
+    response
+}
+
 async fn handle_request(
-    ProxyHandler(inner): ProxyHandler,
+    ProxyHandler { inner, in_flight }: ProxyHandler,
+    scheme: Scheme,
     req: Request,
 ) -> Result<hyper::Response<HyperOutgoingBody>> {
     let (sender, receiver) = tokio::sync::oneshot::channel();
@@ -482,18 +926,66 @@ async fn handle_request(
         req.uri()
     );
 
-    let mut store = inner.cmd.new_store(&inner.engine, req_id)?;
-
-    let req = store.data_mut().new_incoming_request(Scheme::Http, req)?;
+    let permit = match &inner.concurrency {
+        Some(concurrency) => match concurrency.clone().try_acquire_owned() {
+            Ok(permit) => Some(permit),
+            Err(_) => {
+                let _queued = match &inner.queue {
+                    Some(queue) => match queue.clone().try_acquire_owned() {
+                        Ok(permit) => Some(permit),
+                        Err(_) => {
+                            log::warn!("[{req_id}] :: too many requests queued");
+                            return Ok(service_unavailable());
+                        }
+                    },
+                    None => None,
+                };
+                Some(concurrency.clone().acquire_owned().await?)
+            }
+        },
+        None => None,
+    };
+
+    let mut store = inner.cmd.new_store(
+        &inner.engine,
+        req_id,
+        &inner.cancelled,
+        &inner.http,
+        #[cfg(feature = "wasi-config")]
+        inner.config.as_ref(),
+        #[cfg(feature = "wasi-keyvalue")]
+        inner.keyvalue.as_ref(),
+    )?;
+
+    let req = store.data_mut().new_incoming_request(scheme, req)?;
     let out = store.data_mut().new_response_outparam(sender)?;
-    let proxy = inner.instance_pre.instantiate_async(&mut store).await?;
+    let proxy = match inner.instance_pre.instantiate_async(&mut store).await {
+        Ok(proxy) => proxy,
+        // Running out of slots in the pooling allocator is a transient
+        // condition, so let the client retry instead of failing outright.
+        #[cfg(feature = "pooling-allocator")]
+        Err(e) if e.is::<wasmtime::PoolConcurrencyLimitError>() => {
+            log::warn!("[{req_id}] :: {e}");
+            return Ok(service_unavailable());
+        }
+        Err(e) => return Err(e),
+    };
 
     let task = tokio::task::spawn(async move {
+        // The guest may keep running after the response is sent, e.g. to
+        // stream its body, so it's in flight until it returns.
+        let _in_flight = in_flight;
+        let _permit = permit;
         if let Err(e) = proxy
             .wasi_http_incoming_handler()
-            .call_handle(store, req, out)
+            .call_handle(&mut store, req, out)
             .await
         {
+            #[cfg(feature = "coredump")]
+            let e = match &inner.coredumps {
+                Some(coredumps) => coredumps.handle_trap(&mut store, e, Some(req_id)),
+                None => e,
+            };
             log::error!("[{req_id}] :: {:#?}", e);
             return Err(e);
         }
@@ -521,6 +1013,70 @@ async fn handle_request(
     }
 }
 
+/// Delivers `events` to the `wasi:keyvalue/watcher` exported by the component
+/// until the server shuts down, instantiating it anew for each event.
+#[cfg(feature = "wasi-keyvalue")]
+async fn watch_keyvalue(
+    inner: Arc<ProxyHandlerInner>,
+    watcher: WatchServicePre<Host>,
+    mut events: Subscription,
+) {
+    let mut shutdown = inner.shutdown.clone();
+    loop {
+        let event = tokio::select! {
+            event = events.recv() => event,
+            _ = shutdown.wait_for(|shutdown| *shutdown) => return,
+        };
+        match event {
+            Ok(event) => {
+                let req_id = inner.next_req_id();
+                log::info!(
+                    "Request {req_id} handling change to `{}` in store `{}`",
+                    event.key,
+                    event.identifier
+                );
+                if let Err(e) = deliver_keyvalue_event(&inner, &watcher, req_id, &event).await {
+                    log::error!("[{req_id}] :: {e:?}");
+                }
+            }
+            Err(broadcast::error::RecvError::Lagged(n)) => {
+                log::warn!("missed {n} changes to key-value stores");
+            }
+            Err(broadcast::error::RecvError::Closed) => return,
+        }
+    }
+}
+
+#[cfg(feature = "wasi-keyvalue")]
+async fn deliver_keyvalue_event(
+    inner: &ProxyHandlerInner,
+    watcher: &WatchServicePre<Host>,
+    req_id: u64,
+    event: &KeyValueEvent,
+) -> Result<()> {
+    let mut store = inner.cmd.new_store(
+        &inner.engine,
+        req_id,
+        &inner.cancelled,
+        &inner.http,
+        #[cfg(feature = "wasi-config")]
+        inner.config.as_ref(),
+        inner.keyvalue.as_ref(),
+    )?;
+    let watcher = watcher.instantiate_async(&mut store).await?;
+    let result = event
+        .deliver(&mut store, &watcher, |h: &mut Host| {
+            WasiKeyValue::new(h.wasi_keyvalue.as_ref().unwrap(), &mut h.table)
+        })
+        .await;
+    #[cfg(feature = "coredump")]
+    let result = result.map_err(|e| match &inner.coredumps {
+        Some(coredumps) => coredumps.handle_trap(&mut store, e, Some(req_id)),
+        None => e,
+    });
+    result
+}
+
 #[derive(Clone)]
 enum Output {
     Stdout,
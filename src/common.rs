@@ -7,10 +7,22 @@ use std::{path::Path, time::Duration};
 use wasmtime::{Engine, Module, Precompiled, StoreLimits, StoreLimitsBuilder};
 use wasmtime_cli_flags::{opt::WasmtimeOptionValue, CommonOptions};
 use wasmtime_wasi::bindings::LinkOptions;
-use wasmtime_wasi::WasiCtxBuilder;
+use wasmtime_wasi::{SocketPolicy, WasiCtxBuilder};
 
+#[cfg(any(feature = "wasi-config", feature = "wasi-keyvalue"))]
+use std::sync::Arc;
 #[cfg(feature = "component-model")]
 use wasmtime::component::Component;
+#[cfg(feature = "wasi-config")]
+use wasmtime_wasi_config::{
+    ConfigProvider, EnvProvider, FileProvider, LayeredProvider, WasiConfigVariables,
+};
+#[cfg(feature = "wasi-http")]
+use wasmtime_wasi_http::{policy::OutgoingPolicy, tls::TlsConfig, WasiHttpCtx};
+#[cfg(feature = "wasi-keyvalue")]
+use wasmtime_wasi_keyvalue::{
+    FileBackend, InMemoryBackend, KeyValueBackend, WasiKeyValueCtx, WasiKeyValueCtxBuilder,
+};
 
 pub enum RunTarget {
     Core(Module),
@@ -304,8 +316,15 @@ impl RunCommon {
             bail!("components do not support --tcplisten");
         }
 
-        if self.common.wasi.inherit_network == Some(true) {
-            builder.inherit_network();
+        match self.socket_policy()? {
+            Some(policy) => {
+                builder.socket_policy(policy);
+            }
+            None => {
+                if self.common.wasi.inherit_network == Some(true) {
+                    builder.inherit_network();
+                }
+            }
         }
         if let Some(enable) = self.common.wasi.allow_ip_name_lookup {
             builder.allow_ip_name_lookup(enable);
@@ -316,10 +335,49 @@ impl RunCommon {
         if let Some(enable) = self.common.wasi.udp {
             builder.allow_udp(enable);
         }
+        if self.common.wasi.deterministic == Some(true) {
+            builder.deterministic(self.common.wasi.deterministic_seed.unwrap_or(0));
+        }
 
         Ok(())
     }
 
+    /// Creates the policy for the socket addresses of `wasi:sockets` out of
+    /// the `-S socket-*` options, if any are given.
+    fn socket_policy(&self) -> Result<Option<SocketPolicy>> {
+        let wasi = &self.common.wasi;
+        if wasi.socket_allow.is_empty()
+            && wasi.socket_deny.is_empty()
+            && wasi.socket_policy.is_none()
+            && wasi.socket_log_denied.is_none()
+        {
+            return Ok(None);
+        }
+        let mut policy = SocketPolicy::builder();
+        if wasi.inherit_network == Some(true) {
+            policy.allow("*")?;
+        }
+        if let Some(path) = &wasi.socket_policy {
+            let rules = std::fs::read_to_string(path)
+                .with_context(|| format!("failed to read socket policy `{path}`"))?;
+            policy
+                .rules(&rules)
+                .with_context(|| format!("failed to load socket policy `{path}`"))?;
+        }
+        for rule in &wasi.socket_allow {
+            policy.allow(rule)?;
+        }
+        for rule in &wasi.socket_deny {
+            policy.deny(rule)?;
+        }
+        if wasi.socket_log_denied == Some(true) {
+            policy.on_denied(|addr, reason| {
+                eprintln!("warning: socket policy denied {reason:?} of {addr}");
+            });
+        }
+        Ok(Some(policy.build()))
+    }
+
     pub fn compute_preopen_sockets(&self) -> Result<Vec<TcpListener>> {
         let mut listeners = vec![];
 
@@ -340,6 +398,117 @@ impl RunCommon {
         options.network_error_code(self.common.wasi.network_error_code.unwrap_or(false));
         options
     }
+
+    /// Creates the context for `wasi:keyvalue`, opening the stores configured
+    /// with `-S keyvalue-store`.
+    ///
+    /// Clones of the returned context share its stores.
+    #[cfg(feature = "wasi-keyvalue")]
+    pub fn wasi_keyvalue_ctx(&self) -> Result<WasiKeyValueCtx> {
+        let wasi = &self.common.wasi;
+        let mut builder = WasiKeyValueCtxBuilder::new().in_memory_data(
+            wasi.keyvalue_in_memory_data
+                .iter()
+                .map(|v| (v.key.clone(), v.value.clone())),
+        );
+        for store in &wasi.keyvalue_store {
+            if store.key.is_empty() && !wasi.keyvalue_in_memory_data.is_empty() {
+                bail!("`-S keyvalue-in-memory-data` cannot be combined with a `-S keyvalue-store` for the empty identifier");
+            }
+            let backend: Arc<dyn KeyValueBackend> =
+                if store.value == "memory" {
+                    Arc::new(InMemoryBackend::new())
+                } else if let Some(path) = store.value.strip_prefix("file:") {
+                    Arc::new(FileBackend::open(path).with_context(|| {
+                        format!("failed to open key-value store `{}`", store.key)
+                    })?)
+                } else {
+                    bail!(
+                        "invalid key-value store `{}`, expected `memory` or `file:<path>`",
+                        store.value
+                    );
+                };
+            builder = builder.backend(store.key.clone(), backend);
+        }
+        Ok(builder.build())
+    }
+
+    /// Creates the context for `wasi:http`, configuring outgoing requests
+    /// with the `-S http-*` options.
+    ///
+    /// Clones of the returned context share its pool of connections.
+    #[cfg(feature = "wasi-http")]
+    pub fn wasi_http_ctx(&self) -> Result<WasiHttpCtx> {
+        let wasi = &self.common.wasi;
+        let read =
+            |path: &String| std::fs::read(path).with_context(|| format!("failed to read `{path}`"));
+        let mut ctx = WasiHttpCtx::new();
+
+        if wasi.http_ca_bundle.is_some()
+            || wasi.http_client_cert.is_some()
+            || wasi.http_client_key.is_some()
+        {
+            let mut tls = TlsConfig::builder();
+            if let Some(path) = &wasi.http_ca_bundle {
+                tls.add_ca_pem(read(path)?);
+            }
+            match (&wasi.http_client_cert, &wasi.http_client_key) {
+                (Some(cert), Some(key)) => {
+                    tls.client_auth_pem(read(cert)?, read(key)?);
+                }
+                (None, None) => {}
+                _ => bail!("`-S http-client-cert` and `-S http-client-key` must be used together"),
+            }
+            ctx.set_tls_config(
+                tls.build()
+                    .context("invalid TLS configuration for wasi-http")?,
+            );
+        }
+
+        let mut policy = OutgoingPolicy::builder();
+        for rule in &wasi.http_allow_host {
+            policy.allow(rule)?;
+        }
+        for rule in &wasi.http_deny_host {
+            policy.deny(rule)?;
+        }
+        policy.https_only(wasi.http_https_only.unwrap_or(false));
+        for header in &wasi.http_header {
+            policy.header(&header.key, &header.value)?;
+        }
+        if let Some(size) = wasi.http_max_request_body_size {
+            policy.max_request_body_size(size);
+        }
+        if let Some(proxy) = &wasi.http_proxy {
+            policy.proxy(proxy)?;
+        }
+        ctx.set_outgoing_policy(policy.build());
+        Ok(ctx)
+    }
+
+    /// Creates the provider of `wasi:config` variables, layering the
+    /// `-S config-var` options over `-S config-env-prefix` over
+    /// `-S config-file`.
+    #[cfg(feature = "wasi-config")]
+    pub fn wasi_config_provider(&self) -> Result<Arc<dyn ConfigProvider>> {
+        let wasi = &self.common.wasi;
+        let vars = WasiConfigVariables::from_iter(
+            wasi.config_var
+                .iter()
+                .map(|v| (v.key.clone(), v.value.clone())),
+        );
+        if wasi.config_file.is_none() && wasi.config_env_prefix.is_none() {
+            return Ok(Arc::new(vars));
+        }
+        let mut provider = LayeredProvider::new().layer(vars);
+        if let Some(prefix) = &wasi.config_env_prefix {
+            provider = provider.layer(EnvProvider::new(prefix));
+        }
+        if let Some(path) = &wasi.config_file {
+            provider = provider.layer(FileProvider::open(path)?);
+        }
+        Ok(Arc::new(provider))
+    }
 }
 
 #[derive(Clone, PartialEq)]
@@ -372,3 +541,300 @@ impl Profile {
         }
     }
 }
+
+/// Writes core dumps to the path configured with `-D coredump`, expanding any
+/// placeholders in its file name and pruning old core dumps according to
+/// `-D coredump-max-count` and `-D coredump-max-size`.
+#[cfg(feature = "coredump")]
+pub struct CoreDumpWriter {
+    dir: std::path::PathBuf,
+    file_name: Vec<CoreDumpPathPart>,
+    /// The module's path, which is recorded as the name of each core dump.
+    name: String,
+    /// The module's file stem, which is substituted for `{module}`.
+    module: String,
+    max_count: Option<usize>,
+    max_size: Option<u64>,
+}
+
+#[cfg(feature = "coredump")]
+#[derive(PartialEq)]
+enum CoreDumpPathPart {
+    Literal(String),
+    Pid,
+    Timestamp,
+    RequestId,
+    Module,
+}
+
+#[cfg(feature = "coredump")]
+impl CoreDumpWriter {
+    /// Creates a writer for core dumps of the module at `module`, returning
+    /// `None` if core dumps aren't enabled.
+    ///
+    /// The `{request_id}` placeholder is only accepted if `has_requests` is
+    /// true.
+    pub fn new(common: &CommonOptions, module: &Path, has_requests: bool) -> Result<Option<Self>> {
+        let Some(pattern) = &common.debug.coredump else {
+            return Ok(None);
+        };
+        if common.debug.coredump_max_count == Some(0) {
+            bail!("`-D coredump-max-count` must be at least 1");
+        }
+
+        let pattern = Path::new(pattern);
+        let dir = pattern.parent().unwrap_or(Path::new("")).to_path_buf();
+        if dir.to_string_lossy().contains('{') {
+            bail!("placeholders are only supported in the file name of the coredump path");
+        }
+        let file_name = pattern
+            .file_name()
+            .and_then(|s| s.to_str())
+            .ok_or_else(|| anyhow!("invalid coredump path `{}`", pattern.display()))?;
+
+        let mut parts = Vec::new();
+        let mut rest = file_name;
+        while let Some(start) = rest.find('{') {
+            let end = rest[start..].find('}').map(|i| start + i).ok_or_else(|| {
+                anyhow!("unterminated placeholder in coredump path `{file_name}`")
+            })?;
+            if start > 0 {
+                parts.push(CoreDumpPathPart::Literal(rest[..start].to_string()));
+            }
+            parts.push(match &rest[start + 1..end] {
+                "pid" => CoreDumpPathPart::Pid,
+                "timestamp" => CoreDumpPathPart::Timestamp,
+                "module" => CoreDumpPathPart::Module,
+                "request_id" if has_requests => CoreDumpPathPart::RequestId,
+                "request_id" => bail!("`{{request_id}}` is only supported by `wasmtime serve`"),
+                other => bail!(
+                    "unknown placeholder `{{{other}}}` in coredump path, expected one of \
+                     `{{pid}}`, `{{timestamp}}`, `{{request_id}}` or `{{module}}`"
+                ),
+            });
+            rest = &rest[end + 1..];
+        }
+        if !rest.is_empty() {
+            parts.push(CoreDumpPathPart::Literal(rest.to_string()));
+        }
+
+        Ok(Some(CoreDumpWriter {
+            dir,
+            file_name: parts,
+            name: module.display().to_string(),
+            module: module
+                .file_stem()
+                .map(|s| s.to_string_lossy().into_owned())
+                .unwrap_or_else(|| "unknown".to_string()),
+            max_count: common.debug.coredump_max_count,
+            max_size: common.debug.coredump_max_size,
+        }))
+    }
+
+    /// Writes the core dump attached to `err`, if it's a trap, and returns
+    /// `err` with the location of the core dump added as context.
+    ///
+    /// Failure to write the core dump is reported as a warning rather than
+    /// replacing `err`.
+    pub fn handle_trap(
+        &self,
+        store: impl wasmtime::AsContextMut,
+        err: anyhow::Error,
+        request_id: Option<u64>,
+    ) -> anyhow::Error {
+        if !err.is::<wasmtime::Trap>() {
+            return err;
+        }
+        match self.write(store, &err, request_id) {
+            Ok(path) => err.context(format!("core dumped at {}", path.display())),
+            Err(coredump_err) => {
+                eprintln!("warning: coredump failed to generate: {coredump_err:#}");
+                err
+            }
+        }
+    }
+
+    fn write(
+        &self,
+        store: impl wasmtime::AsContextMut,
+        err: &anyhow::Error,
+        request_id: Option<u64>,
+    ) -> Result<std::path::PathBuf> {
+        use std::io::Write;
+
+        let core_dump = err
+            .downcast_ref::<wasmtime::WasmCoreDump>()
+            .expect("should have been configured to capture core dumps");
+        let core_dump = core_dump.serialize(store, &self.name);
+
+        let size = u64::try_from(core_dump.len()).unwrap();
+        if let Some(max) = self.max_size {
+            if size > max {
+                bail!("core dump of {size} bytes is larger than `-D coredump-max-size={max}`");
+            }
+        }
+
+        let (path, mut core_dump_file) = self.create(request_id)?;
+        core_dump_file
+            .write_all(&core_dump)
+            .with_context(|| format!("failed to write core dump file at `{}`", path.display()))?;
+        drop(core_dump_file);
+
+        if self.max_count.is_some() || self.max_size.is_some() {
+            if let Err(e) = self.prune(&path) {
+                eprintln!("warning: failed to remove old core dumps: {e:#}");
+            }
+        }
+        Ok(path)
+    }
+
+    /// Creates the file for a new core dump.
+    ///
+    /// A path without placeholders is overwritten, but one with placeholders
+    /// is expected to name a new file for each core dump. Placeholders may
+    /// still expand to an existing file, for example with two traps within
+    /// the same second, so a `-N` suffix is added before the extension of the
+    /// file name until it doesn't exist.
+    fn create(&self, request_id: Option<u64>) -> Result<(std::path::PathBuf, std::fs::File)> {
+        let file_name = self.expand(request_id);
+        let fixed = self
+            .file_name
+            .iter()
+            .all(|part| matches!(part, CoreDumpPathPart::Literal(_)));
+        let mut path = self.dir.join(&file_name);
+        for n in 1.. {
+            let result = if fixed {
+                std::fs::File::create(&path)
+            } else {
+                std::fs::File::create_new(&path)
+            };
+            match result {
+                Ok(file) => return Ok((path, file)),
+                Err(e) if e.kind() == std::io::ErrorKind::AlreadyExists => {
+                    let (stem, extension) = split_extension(&file_name);
+                    path = self.dir.join(format!("{stem}-{n}{extension}"));
+                }
+                Err(e) => {
+                    return Err(e)
+                        .with_context(|| format!("failed to create file at `{}`", path.display()))
+                }
+            }
+        }
+        unreachable!()
+    }
+
+    /// Expands the placeholders in the configured file name.
+    fn expand(&self, request_id: Option<u64>) -> String {
+        let mut file_name = String::new();
+        for part in &self.file_name {
+            match part {
+                CoreDumpPathPart::Literal(s) => file_name.push_str(s),
+                CoreDumpPathPart::Pid => file_name.push_str(&std::process::id().to_string()),
+                CoreDumpPathPart::Timestamp => {
+                    let now = std::time::SystemTime::now()
+                        .duration_since(std::time::UNIX_EPOCH)
+                        .unwrap_or_default();
+                    file_name.push_str(&now.as_secs().to_string());
+                }
+                CoreDumpPathPart::RequestId => {
+                    file_name.push_str(&request_id.unwrap_or(0).to_string())
+                }
+                CoreDumpPathPart::Module => file_name.push_str(&self.module),
+            }
+        }
+        file_name
+    }
+
+    /// Removes the oldest core dumps matching the configured path, other than
+    /// `newest`, until they're within the configured limits.
+    fn prune(&self, newest: &Path) -> Result<()> {
+        let dir = if self.dir.as_os_str().is_empty() {
+            Path::new(".")
+        } else {
+            &self.dir
+        };
+        let mut dumps = Vec::new();
+        for entry in std::fs::read_dir(dir)? {
+            let entry = entry?;
+            let matches = entry
+                .file_name()
+                .to_str()
+                .is_some_and(|name| self.matches(name));
+            if !matches || entry.file_name() == newest.file_name().unwrap_or_default() {
+                continue;
+            }
+            let metadata = entry.metadata()?;
+            if metadata.is_file() {
+                dumps.push((metadata.modified()?, metadata.len(), entry.path()));
+            }
+        }
+
+        // Newest first, with the core dump that was just written always kept.
+        dumps.sort_by(|a, b| b.0.cmp(&a.0));
+        let mut count = 1;
+        let mut size = std::fs::metadata(newest)?.len();
+        for (_, len, path) in dumps {
+            count += 1;
+            size += len;
+            let too_many = self.max_count.is_some_and(|max| count > max);
+            let too_large = self.max_size.is_some_and(|max| size > max);
+            if too_many || too_large {
+                match std::fs::remove_file(&path) {
+                    Ok(()) => {}
+                    Err(e) if e.kind() == std::io::ErrorKind::NotFound => {}
+                    Err(e) => {
+                        return Err(e)
+                            .with_context(|| format!("failed to remove `{}`", path.display()))
+                    }
+                }
+                count -= 1;
+                size -= len;
+            }
+        }
+        Ok(())
+    }
+
+    /// Returns whether `name` could have been produced by `expand`.
+    fn matches(&self, name: &str) -> bool {
+        fn matches(parts: &[CoreDumpPathPart], module: &str, name: &str) -> bool {
+            let Some((first, rest)) = parts.split_first() else {
+                return name.is_empty();
+            };
+            match first {
+                CoreDumpPathPart::Literal(s) => name
+                    .strip_prefix(s.as_str())
+                    .is_some_and(|name| matches(rest, module, name)),
+                CoreDumpPathPart::Module => name
+                    .strip_prefix(module)
+                    .is_some_and(|name| matches(rest, module, name)),
+                CoreDumpPathPart::Pid
+                | CoreDumpPathPart::Timestamp
+                | CoreDumpPathPart::RequestId => {
+                    let digits = name.bytes().take_while(|b| b.is_ascii_digit()).count();
+                    (1..=digits).any(|n| matches(rest, module, &name[n..]))
+                }
+            }
+        }
+        if matches(&self.file_name, &self.module, name) {
+            return true;
+        }
+
+        // Also match names with the suffix added by `create`.
+        let (stem, extension) = split_extension(name);
+        match stem.rsplit_once('-') {
+            Some((stem, n)) if !n.is_empty() && n.bytes().all(|b| b.is_ascii_digit()) => {
+                matches(&self.file_name, &self.module, &format!("{stem}{extension}"))
+            }
+            _ => false,
+        }
+    }
+}
+
+/// Splits `name` into its stem and its extension, including the `.`, if any.
+#[cfg(feature = "coredump")]
+fn split_extension(name: &str) -> (&str, &str) {
+    match name.rfind('.') {
+        Some(i) if i > 0 => name.split_at(i),
+        _ => (name, ""),
+    }
+}
@@ -131,6 +131,77 @@ fn run_coredump_smoketest() -> Result<()> {
     Ok(())
 }
 
+#[test]
+fn run_coredump_path_pattern() -> Result<()> {
+    let wasm = build_wasm("tests/all/cli_tests/coredump_smoketest.wat")?;
+    let stem = wasm.path().file_stem().unwrap().to_str().unwrap();
+
+    // The second pattern expands to the same name for runs within the same
+    // second, which must not overwrite each other.
+    for pattern in [
+        "{module}.{pid}.{timestamp}.core",
+        "{module}.{timestamp}.core",
+    ] {
+        let dir = TempDir::new()?;
+        let coredump_arg = format!("-Dcoredump={}", dir.path().join(pattern).display());
+
+        // Each run writes a new core dump, and only the newest two are kept.
+        let mut dumps = Vec::new();
+        for _ in 0..3 {
+            let err = run_wasmtime(&[
+                "run",
+                "--invoke",
+                "a",
+                "-Ccache=n",
+                &coredump_arg,
+                "-Dcoredump-max-count=2",
+                wasm.path().to_str().unwrap(),
+            ])
+            .unwrap_err();
+            let msg = err.to_string();
+            let start = msg.find("core dumped at ").unwrap() + "core dumped at ".len();
+            let path = PathBuf::from(msg[start..].lines().next().unwrap().trim());
+            assert!(path
+                .file_name()
+                .unwrap()
+                .to_str()
+                .unwrap()
+                .starts_with(&format!("{stem}.")));
+            assert!(!dumps.contains(&path));
+            dumps.push(path);
+        }
+        let mut remaining = std::fs::read_dir(dir.path())?
+            .map(|e| Ok(e?.path()))
+            .collect::<Result<Vec<_>>>()?;
+        remaining.sort();
+        let mut expected = dumps[1..].to_vec();
+        expected.sort();
+        assert_eq!(remaining, expected);
+    }
+    Ok(())
+}
+
+#[test]
+fn run_coredump_path_pattern_errors() -> Result<()> {
+    let wasm = build_wasm("tests/all/cli_tests/coredump_smoketest.wat")?;
+    let run = |arg: &str| {
+        run_wasmtime(&[
+            "run",
+            "--invoke",
+            "a",
+            "-Ccache=n",
+            arg,
+            wasm.path().to_str().unwrap(),
+        ])
+        .unwrap_err()
+        .to_string()
+    };
+    assert!(run("-Dcoredump=core.{request_id}").contains("only supported by `wasmtime serve`"));
+    assert!(run("-Dcoredump=core.{bogus}").contains("unknown placeholder `{bogus}`"));
+    assert!(run("-Dcoredump-max-count=2").contains("require `-D coredump`"));
+    Ok(())
+}
+
 // Running simple wat
 #[test]
 fn run_wasmtime_simple_wat() -> Result<()> {
@@ -166,6 +237,25 @@ fn run_wasmtime_simple_wat() -> Result<()> {
     Ok(())
 }
 
+#[test]
+fn run_deterministic_floats() -> Result<()> {
+    let wasm = build_wasm("tests/all/cli_tests/deterministic-floats.wat")?;
+    let invoke = |func: &str| {
+        run_wasmtime(&[
+            "run",
+            "-Sdeterministic",
+            "-Wrelaxed-simd",
+            "-Ccache=n",
+            "--invoke",
+            func,
+            wasm.path().to_str().unwrap(),
+        ])
+    };
+    assert_eq!(invoke("nan")?, format!("{}\n", 0x7fc00000));
+    assert_eq!(invoke("relaxed_trunc")?, "0\n");
+    Ok(())
+}
+
 // Running a wat that traps.
 #[test]
 fn run_wasmtime_unreachable_wat() -> Result<()> {
@@ -1106,7 +1196,7 @@ fn increase_stack_size() -> Result<()> {
 }
 
 mod test_programs {
-    use super::{get_wasmtime_command, run_wasmtime};
+    use super::{get_wasmtime_command, run_wasmtime, run_wasmtime_for_output};
     use anyhow::{bail, Context, Result};
     use http_body_util::BodyExt;
     use hyper::header::HeaderValue;
@@ -1239,6 +1329,92 @@ mod test_programs {
         Ok(())
     }
 
+    #[test]
+    fn cli_record_replay() -> Result<()> {
+        use std::net::TcpListener;
+
+        // Serve a single HTTP request, so replaying can't reach the server.
+        let listener = TcpListener::bind("127.0.0.1:0")?;
+        let addr = listener.local_addr()?;
+        let server = std::thread::spawn(move || -> Result<()> {
+            let (stream, _) = listener.accept()?;
+            let mut reader = BufReader::new(&stream);
+            let mut line = String::new();
+            while line != "\r\n" {
+                line.clear();
+                reader.read_line(&mut line)?;
+            }
+            (&stream).write_all(
+                b"HTTP/1.1 200 OK\r\ncontent-length: 5\r\nconnection: close\r\n\r\nhello",
+            )?;
+            Ok(())
+        });
+
+        let dir = tempfile::tempdir()?;
+        std::fs::write(dir.path().join("bar.txt"), b"And stood awhile in thought")?;
+        let stdin = dir.path().join("stdin.txt");
+        std::fs::write(&stdin, b"O frabjous day!")?;
+        let trace_dir = tempfile::tempdir()?;
+        let trace = trace_dir.path().join("trace");
+
+        let recorded = run_wasmtime_for_output(
+            &[
+                "run",
+                "-Wcomponent-model",
+                "-Shttp",
+                &format!("--record={}", trace.display()),
+                &format!("--dir={}::.", dir.path().display()),
+                &format!("--env=HTTP_SERVER={addr}"),
+                CLI_RECORD_REPLAY_COMPONENT,
+            ],
+            Some(&stdin),
+        )?;
+        server.join().unwrap()?;
+        assert!(
+            recorded.status.success(),
+            "{}",
+            String::from_utf8_lossy(&recorded.stderr)
+        );
+        let recorded = String::from_utf8(recorded.stdout)?;
+        assert!(recorded.contains("stdin: \"O frabjous day!\"\n"));
+        assert!(recorded.contains("bar.txt: \"And stood awhile in thought\"\n"));
+        assert!(recorded.contains("http: 200 \"hello\"\n"));
+
+        // Replay without the directory, stdin or server of the recording.
+        drop(dir);
+        let replayed = run_wasmtime(&[
+            "run",
+            "-Wcomponent-model",
+            "-Shttp",
+            &format!("--replay={}", trace.display()),
+            CLI_RECORD_REPLAY_COMPONENT,
+        ])?;
+        assert_eq!(recorded, replayed);
+
+        // Anything granting access to sockets is rejected.
+        for (flag, arg) in [
+            ("inherit-network", "-Sinherit-network"),
+            ("socket-allow", "-Ssocket-allow=connect=127.0.0.1:*"),
+            ("socket-policy", "-Ssocket-policy=policy.txt"),
+            ("tcplisten", "-Stcplisten=127.0.0.1:0"),
+        ] {
+            let output = run_wasmtime_for_output(
+                &[
+                    "run",
+                    "-Wcomponent-model",
+                    arg,
+                    &format!("--record={}", trace.display()),
+                    CLI_RECORD_REPLAY_COMPONENT,
+                ],
+                None,
+            )?;
+            assert!(!output.status.success());
+            let stderr = String::from_utf8_lossy(&output.stderr);
+            assert!(stderr.contains(&format!("`-S {flag}`")), "{stderr}");
+        }
+        Ok(())
+    }
+
     #[test]
     fn cli_file_read() -> Result<()> {
         let dir = tempfile::tempdir()?;
@@ -1364,6 +1540,48 @@ mod test_programs {
         Ok(())
     }
 
+    #[test]
+    fn cli_deterministic() -> Result<()> {
+        // `-S deterministic-seed` implies `-S deterministic`.
+        let run = |seed: &str| -> Result<String> {
+            // Create the files in an order which listing the directory
+            // wouldn't be sorted by on most filesystems.
+            let dir = tempfile::tempdir()?;
+            for name in ["c.txt", "a.txt", "d.txt", "b.txt"] {
+                std::fs::write(dir.path().join(name), name)?;
+            }
+            run_wasmtime(&[
+                "run",
+                "-Wcomponent-model",
+                seed,
+                &format!("--dir={}::.", dir.path().display()),
+                CLI_DETERMINISTIC_COMPONENT,
+            ])
+        };
+
+        let output = run("-Sdeterministic-seed=0")?;
+        assert!(output.contains("entries: [\"a.txt\", \"b.txt\", \"c.txt\", \"d.txt\"]\n"));
+        assert!(output.contains("accessed: 0ns\nmodified: 0ns\n"));
+        assert!(output.contains("wall clock: 0ns\nslept: 1s\n"));
+        assert!(output.contains("terminals: false false false\n"));
+        assert_eq!(output, run("-Sdeterministic-seed=0")?);
+
+        // The seed only changes the random numbers.
+        let other = run("-Sdeterministic-seed=1")?;
+        assert_ne!(output, other);
+        fn without_random(s: &str) -> Vec<&str> {
+            s.lines()
+                .filter(|l| {
+                    !["random:", "insecure:", "seed:"]
+                        .iter()
+                        .any(|p| l.starts_with(p))
+                })
+                .collect()
+        }
+        assert_eq!(without_random(&output), without_random(&other));
+        Ok(())
+    }
+
     #[test]
     fn cli_default_clocks() -> Result<()> {
         run_wasmtime(&["run", "-Wcomponent-model", CLI_DEFAULT_CLOCKS_COMPONENT])?;
@@ -1634,6 +1852,102 @@ mod test_programs {
                 .context("failed http handshake")?;
             Ok((send, tokio::task::spawn(conn)))
         }
+
+        /// Send a request to this server over HTTP/2 with prior knowledge and
+        /// wait for the response.
+        async fn send_http2_request(
+            &self,
+            req: http::Request<String>,
+        ) -> Result<http::Response<String>> {
+            let tcp = TcpStream::connect(&self.addr)
+                .await
+                .context("failed to connect")?;
+            let tcp = wasmtime_wasi_http::io::TokioIo::new(tcp);
+            let (mut send, conn) = hyper::client::conn::http2::handshake(TokioExecutor, tcp)
+                .await
+                .context("failed http handshake")?;
+            let conn_task = tokio::task::spawn(conn);
+
+            let response = send
+                .send_request(req)
+                .await
+                .context("error sending request")?;
+            drop(send);
+            let (parts, body) = response.into_parts();
+            let body = body.collect().await.context("failed to read body")?;
+            let body = std::str::from_utf8(&body.to_bytes())?.to_string();
+
+            conn_task.await??;
+
+            Ok(http::Response::from_parts(parts, body))
+        }
+
+        /// Send a request to this server over TLS, trusting the certificate
+        /// authority in `ca`, and wait for the response.
+        ///
+        /// HTTP/2 is offered through ALPN, so the response says which version
+        /// the server picked.
+        async fn send_https_request(
+            &self,
+            ca: &[u8],
+            req: http::Request<String>,
+        ) -> Result<http::Response<String>> {
+            use std::sync::Arc;
+            use tokio_rustls::rustls::{self, pki_types::ServerName};
+
+            let mut roots = rustls::RootCertStore::empty();
+            for cert in rustls_pemfile::certs(&mut &ca[..]) {
+                roots.add(cert?)?;
+            }
+            let mut config = rustls::ClientConfig::builder()
+                .with_root_certificates(roots)
+                .with_no_client_auth();
+            config.alpn_protocols = vec![b"h2".to_vec(), b"http/1.1".to_vec()];
+
+            let tcp = TcpStream::connect(&self.addr)
+                .await
+                .context("failed to connect")?;
+            let tls = tokio_rustls::TlsConnector::from(Arc::new(config))
+                .connect(ServerName::try_from("localhost")?, tcp)
+                .await
+                .context("failed TLS handshake")?;
+            let http2 = tls.get_ref().1.alpn_protocol() == Some(b"h2");
+            let tcp = wasmtime_wasi_http::io::TokioIo::new(tls);
+
+            let response = if http2 {
+                let (mut send, conn) = hyper::client::conn::http2::handshake(TokioExecutor, tcp)
+                    .await
+                    .context("failed http handshake")?;
+                tokio::task::spawn(conn);
+                send.send_request(req).await
+            } else {
+                let (mut send, conn) = hyper::client::conn::http1::handshake(tcp)
+                    .await
+                    .context("failed http handshake")?;
+                tokio::task::spawn(conn);
+                send.send_request(req).await
+            }
+            .context("error sending request")?;
+            let (parts, body) = response.into_parts();
+            let body = body.collect().await.context("failed to read body")?;
+            let body = std::str::from_utf8(&body.to_bytes())?.to_string();
+
+            Ok(http::Response::from_parts(parts, body))
+        }
+    }
+
+    /// Runs the background tasks of HTTP/2 connections.
+    #[derive(Clone)]
+    struct TokioExecutor;
+
+    impl<F> hyper::rt::Executor<F> for TokioExecutor
+    where
+        F: std::future::Future + Send + 'static,
+        F::Output: Send + 'static,
+    {
+        fn execute(&self, fut: F) {
+            tokio::task::spawn(fut);
+        }
     }
 
     // Don't leave child processes running by accident so kill the child process
@@ -1711,6 +2025,165 @@ mod test_programs {
         Ok(())
     }
 
+    #[tokio::test]
+    async fn cli_serve_http2_prior_knowledge() -> Result<()> {
+        let server = WasmtimeServe::new(CLI_SERVE_ECHO_ENV_COMPONENT, |cmd| {
+            cmd.arg("-Scli");
+            cmd.arg("--env=FOO=bar");
+        })?;
+
+        let resp = server
+            .send_http2_request(
+                hyper::Request::builder()
+                    .uri("http://localhost/")
+                    .header("env", "FOO")
+                    .body(String::new())
+                    .context("failed to make request")?,
+            )
+            .await?;
+        assert!(resp.status().is_success());
+        assert_eq!(resp.version(), http::Version::HTTP_2);
+        assert_eq!(
+            resp.headers().get("env"),
+            Some(&HeaderValue::from_static("bar"))
+        );
+
+        // HTTP/1.1 continues to work on the same listener.
+        let resp = server
+            .send_request(
+                hyper::Request::builder()
+                    .uri("http://localhost/")
+                    .header("env", "FOO")
+                    .body(String::new())
+                    .context("failed to make request")?,
+            )
+            .await?;
+        assert!(resp.status().is_success());
+        assert_eq!(resp.version(), http::Version::HTTP_11);
+
+        server.finish()?;
+        Ok(())
+    }
+
+    #[tokio::test]
+    #[cfg(unix)]
+    async fn cli_serve_graceful_shutdown() -> Result<()> {
+        let mut server = WasmtimeServe::new(CLI_SERVE_ECHO_ENV_COMPONENT, |cmd| {
+            cmd.arg("-Scli");
+        })?;
+
+        let resp = server
+            .send_request(
+                hyper::Request::builder()
+                    .uri("http://localhost/")
+                    .header("env", "FOO")
+                    .body(String::new())
+                    .context("failed to make request")?,
+            )
+            .await?;
+        assert!(resp.status().is_success());
+
+        let child = server.child.take().unwrap();
+        let status = Command::new("kill")
+            .arg("-TERM")
+            .arg(child.id().to_string())
+            .status()?;
+        assert!(status.success());
+        let output = child.wait_with_output()?;
+        let stderr = String::from_utf8_lossy(&output.stderr);
+        assert!(output.status.success(), "bad exit: {output:?}");
+        assert!(stderr.contains("Shutting down"), "bad stderr: {stderr}");
+        Ok(())
+    }
+
+    #[tokio::test]
+    #[cfg_attr(not(unix), ignore)]
+    async fn cli_serve_spin() -> Result<()> {
+        // Without `-W timeout` epochs only start advancing once the server is
+        // shutting down, which is still enough to cancel a busy request.
+        let mut server = WasmtimeServe::new(CLI_SERVE_SPIN_COMPONENT, |cmd| {
+            cmd.arg("-Scli");
+            cmd.arg("--shutdown-timeout=100ms");
+        })?;
+
+        let mut stream = std::net::TcpStream::connect(server.addr)?;
+        stream.write_all(b"GET / HTTP/1.1\r\nhost: localhost\r\n\r\n")?;
+        tokio::time::sleep(std::time::Duration::from_millis(500)).await;
+
+        let child = server.child.take().unwrap();
+        let status = Command::new("kill")
+            .arg("-TERM")
+            .arg(child.id().to_string())
+            .status()?;
+        assert!(status.success());
+        let output = child.wait_with_output()?;
+        let stderr = String::from_utf8_lossy(&output.stderr);
+        assert!(!output.status.success(), "bad exit: {output:?}");
+        assert!(stderr.contains("were cancelled"), "bad stderr: {stderr}");
+        Ok(())
+    }
+
+    #[tokio::test]
+    async fn cli_serve_saturated() -> Result<()> {
+        let server = WasmtimeServe::new(API_PROXY_STREAMING_COMPONENT, |cmd| {
+            cmd.arg("-Scli");
+            cmd.arg("--max-concurrent-requests=1");
+            cmd.arg("--max-queued-requests=0");
+        })?;
+
+        // Occupy the only request slot with a request whose body is never
+        // sent, so the guest keeps waiting to echo it. The guest responds
+        // before reading the body, so once the response starts the request
+        // is known to hold the slot.
+        let mut stream = std::net::TcpStream::connect(server.addr)?;
+        stream
+            .write_all(b"POST /echo HTTP/1.1\r\nhost: localhost\r\ncontent-length: 100\r\n\r\n")?;
+        let mut status = String::new();
+        BufReader::new(&stream).read_line(&mut status)?;
+        assert!(status.starts_with("HTTP/1.1 200"), "bad status: {status}");
+
+        let resp = server
+            .send_request(
+                hyper::Request::builder()
+                    .uri("http://localhost/")
+                    .body(String::new())
+                    .context("failed to make request")?,
+            )
+            .await?;
+        assert_eq!(resp.status(), http::StatusCode::SERVICE_UNAVAILABLE);
+
+        drop(stream);
+        server.finish()?;
+        Ok(())
+    }
+
+    #[tokio::test]
+    async fn cli_serve_trap() -> Result<()> {
+        let dir = tempfile::TempDir::new()?;
+        let pattern = dir.path().join("req-{request_id}.core");
+        let server = WasmtimeServe::new(CLI_SERVE_TRAP_COMPONENT, |cmd| {
+            cmd.arg("-Scli");
+            cmd.arg(format!("-Dcoredump={}", pattern.display()));
+        })?;
+
+        for _ in 0..2 {
+            let result = server
+                .send_request(
+                    hyper::Request::builder()
+                        .uri("http://localhost/")
+                        .body(String::new())
+                        .context("failed to make request")?,
+                )
+                .await;
+            assert!(result.is_err());
+        }
+
+        assert!(dir.path().join("req-0.core").exists());
+        assert!(dir.path().join("req-1.core").exists());
+        server.finish()?;
+        Ok(())
+    }
+
     #[tokio::test]
     #[ignore] // TODO: printing stderr in the child and killing the child at the
               // end of this test race so the stderr may be present or not. Need
@@ -1899,6 +2372,10 @@ stderr [1] :: after empty
             )
             .await?;
         assert!(resp.status().is_success());
+        assert_eq!(
+            resp.headers().get("scheme"),
+            Some(&HeaderValue::from_static("http"))
+        );
 
         let resp = server
             .send_request(
@@ -1914,6 +2391,37 @@ stderr [1] :: after empty
         Ok(())
     }
 
+    #[tokio::test]
+    async fn cli_serve_tls() -> Result<()> {
+        // The certificates from the `wasmtime-wasi-http` TLS tests, with the
+        // server's issued for `localhost`.
+        let tls = std::path::Path::new("crates/wasi-http/tests/all/tls");
+        let server = WasmtimeServe::new(CLI_SERVE_AUTHORITY_AND_SCHEME_COMPONENT, |cmd| {
+            cmd.arg("-Scli");
+            cmd.arg(format!("--tls-cert={}", tls.join("server.pem").display()));
+            cmd.arg(format!("--tls-key={}", tls.join("server.key").display()));
+        })?;
+
+        let resp = server
+            .send_https_request(
+                &std::fs::read(tls.join("ca.pem"))?,
+                hyper::Request::builder()
+                    .uri("https://localhost/")
+                    .body(String::new())
+                    .context("failed to make request")?,
+            )
+            .await?;
+        assert!(resp.status().is_success());
+        assert_eq!(resp.version(), http::Version::HTTP_2);
+        assert_eq!(
+            resp.headers().get("scheme"),
+            Some(&HeaderValue::from_static("https"))
+        );
+
+        server.finish()?;
+        Ok(())
+    }
+
     #[test]
     fn cli_argv0() -> Result<()> {
         run_wasmtime(&["run", "--argv0=a", CLI_ARGV0, "a"])?;
@@ -1977,6 +2485,84 @@ stderr [1] :: after empty
         Ok(())
     }
 
+    #[tokio::test]
+    async fn cli_serve_keyvalue_counter() -> Result<()> {
+        let dir = tempfile::tempdir()?;
+        let store = format!(
+            "-Skeyvalue-store=counter=file:{}",
+            dir.path().join("counter.kv").display()
+        );
+        let request = || {
+            hyper::Request::builder()
+                .uri("http://localhost/")
+                .body(String::new())
+                .context("failed to make request")
+        };
+
+        // Requests share the store...
+        let server = WasmtimeServe::new(CLI_SERVE_KEYVALUE_COUNTER_COMPONENT, |cmd| {
+            cmd.arg("-Scli");
+            cmd.arg("-Skeyvalue");
+            cmd.arg(&store);
+        })?;
+        assert_eq!(server.send_request(request()?).await?.body(), "1");
+        assert_eq!(server.send_request(request()?).await?.body(), "2");
+        server.finish()?;
+
+        // ... and its data outlives the server.
+        let server = WasmtimeServe::new(CLI_SERVE_KEYVALUE_COUNTER_COMPONENT, |cmd| {
+            cmd.arg("-Scli");
+            cmd.arg("-Skeyvalue");
+            cmd.arg(&store);
+        })?;
+        assert_eq!(server.send_request(request()?).await?.body(), "3");
+        server.finish()?;
+        Ok(())
+    }
+
+    #[tokio::test]
+    async fn cli_serve_keyvalue_watcher() -> Result<()> {
+        let server = WasmtimeServe::new(CLI_SERVE_KEYVALUE_WATCHER_COMPONENT, |cmd| {
+            cmd.arg("-Scli");
+            cmd.arg("-Skeyvalue");
+        })?;
+
+        let resp = server
+            .send_request(
+                hyper::Request::builder()
+                    .method("POST")
+                    .uri("http://localhost/hello")
+                    .body(String::new())
+                    .context("failed to make request")?,
+            )
+            .await?;
+        assert!(resp.status().is_success());
+
+        // The change is delivered to the watcher in the background, so wait
+        // for it to show up.
+        let start = std::time::Instant::now();
+        loop {
+            let resp = server
+                .send_request(
+                    hyper::Request::builder()
+                        .uri("http://localhost/")
+                        .body(String::new())
+                        .context("failed to make request")?,
+                )
+                .await?;
+            if resp.body() == "/hello" {
+                break;
+            }
+            assert!(
+                start.elapsed() < std::time::Duration::from_secs(10),
+                "watcher never ran"
+            );
+            tokio::time::sleep(std::time::Duration::from_millis(10)).await;
+        }
+        server.finish()?;
+        Ok(())
+    }
+
     #[test]
     fn cli_keyvalue() -> Result<()> {
         run_wasmtime(&[
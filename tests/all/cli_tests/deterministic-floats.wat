@@ -0,0 +1,13 @@
+(module
+  ;; Produces a NaN with a payload, which is canonicalized under
+  ;; `-W nan-canonicalization`.
+  (func (export "nan") (result i32)
+    (i32.reinterpret_f32
+      (f32.add (f32.reinterpret_i32 (i32.const 0x7fc00001)) (f32.const 1))))
+
+  ;; Truncates a NaN, which results in 0 under
+  ;; `-W relaxed-simd-deterministic`.
+  (func (export "relaxed_trunc") (result i32)
+    (i32x4.extract_lane 0
+      (i32x4.relaxed_trunc_f32x4_s (f32x4.splat (f32.const nan)))))
+)
@@ -258,3 +258,149 @@ fn multiple_globals_memories_and_instances() -> Result<()> {
 
     Ok(())
 }
+
+const FRAME_STATE_WAT: &str = r#"
+    (module
+        (func (export "f") (param $x i32) (result i32)
+            (local $y i64) (local $z f64) (local $r funcref)
+            (local.set $y (i64.const 7))
+            (local.set $z (f64.const 1.5))
+            (call $g (i32.const 3) (local.get $x))
+        )
+        (func $g (param i32 i32) (result i32)
+            (i32.add
+                (i32.const 100)
+                (i32.div_u (local.get 0) (i32.const 0)))
+        )
+    )
+"#;
+
+/// Calls `f` in the first of two instances of `FRAME_STATE_WAT` and returns
+/// the resulting core dump along with its serialized form.
+fn frame_state_coredump(config: &mut Config) -> Result<(WasmCoreDump, Vec<u8>)> {
+    config.coredump_on_trap(true);
+    let engine = Engine::new(config)?;
+    let mut store = Store::<()>::new(&engine, ());
+    let module = Module::new(&engine, FRAME_STATE_WAT)?;
+    let first = Instance::new(&mut store, &module, &[])?;
+    Instance::new(&mut store, &module, &[])?;
+    let f = first.get_typed_func::<i32, i32>(&mut store, "f")?;
+
+    let e = f.call(&mut store, 42).unwrap_err();
+    assert_eq!(
+        *e.downcast_ref::<Trap>().unwrap(),
+        Trap::IntegerDivisionByZero
+    );
+    let cd = e.downcast::<WasmCoreDump>()?;
+    assert_eq!(cd.frames().len(), 2);
+    let dump = cd.serialize(&mut store, "frame-state");
+    Ok((cd, dump))
+}
+
+/// Parses the stack frames out of a serialized core dump.
+fn corestack_frames(dump: &[u8]) -> Result<Vec<wasmparser::CoreDumpStackFrame>> {
+    for payload in wasmparser::Parser::new(0).parse_all(dump) {
+        if let wasmparser::Payload::CustomSection(s) = payload? {
+            if s.name() == "corestack" {
+                let reader = wasmparser::BinaryReader::new(s.data(), s.data_offset());
+                return Ok(wasmparser::CoreDumpStackSection::new(reader)?.frames);
+            }
+        }
+    }
+    bail!("no corestack section")
+}
+
+fn i32s(vals: &[Option<Val>]) -> Vec<i32> {
+    vals.iter()
+        .map(|v| v.as_ref().unwrap().unwrap_i32())
+        .collect()
+}
+
+fn assert_frame_state_locals(cd: &WasmCoreDump) {
+    assert_eq!(i32s(cd.frame_locals(0).unwrap()), [3, 42]);
+
+    let locals = cd.frame_locals(1).unwrap();
+    assert_eq!(locals.len(), 4);
+    assert_eq!(locals[0].as_ref().unwrap().unwrap_i32(), 42);
+    assert_eq!(locals[1].as_ref().unwrap().unwrap_i64(), 7);
+    assert_eq!(locals[2].as_ref().unwrap().unwrap_f64(), 1.5);
+    assert!(locals[3].is_none());
+}
+
+#[test]
+#[cfg_attr(miri, ignore)]
+fn coredump_has_cranelift_frame_state() -> Result<()> {
+    let mut config = Config::new();
+    config
+        .strategy(Strategy::Cranelift)
+        .record_frame_state(true);
+    let (cd, dump) = frame_state_coredump(&mut config)?;
+
+    assert!(cd.frame_instance(0).is_some());
+    assert!(cd.frame_instance(1).is_some());
+    assert_frame_state_locals(&cd);
+    assert_eq!(i32s(cd.frame_operand_stack(0).unwrap()), [100, 3, 0]);
+    assert_eq!(i32s(cd.frame_operand_stack(1).unwrap()), [3, 42]);
+
+    // Both frames belong to the first instance, even though guessing from
+    // the module would pick the second.
+    let frames = corestack_frames(&dump)?;
+    assert_eq!(frames.len(), 2);
+    assert_eq!(frames[0].instanceidx, 0);
+    assert_eq!(frames[1].instanceidx, 0);
+    assert!(matches!(
+        frames[0].stack[..],
+        [
+            wasmparser::CoreDumpValue::I32(100),
+            wasmparser::CoreDumpValue::I32(3),
+            wasmparser::CoreDumpValue::I32(0),
+        ]
+    ));
+    assert!(matches!(
+        frames[1].locals[..],
+        [
+            wasmparser::CoreDumpValue::I32(42),
+            wasmparser::CoreDumpValue::I64(7),
+            wasmparser::CoreDumpValue::F64(_),
+            wasmparser::CoreDumpValue::Missing,
+        ]
+    ));
+    Ok(())
+}
+
+#[test]
+#[cfg_attr(miri, ignore)]
+fn coredump_has_winch_frame_state() -> Result<()> {
+    if !cfg!(target_arch = "x86_64") {
+        return Ok(());
+    }
+    let mut config = Config::new();
+    config.strategy(Strategy::Winch);
+    let (cd, dump) = frame_state_coredump(&mut config)?;
+
+    assert!(cd.frame_instance(0).is_some());
+    assert_frame_state_locals(&cd);
+    assert!(cd.frame_operand_stack(0).unwrap().is_empty());
+
+    let frames = corestack_frames(&dump)?;
+    assert_eq!(frames[0].instanceidx, 0);
+    assert_eq!(frames[1].instanceidx, 0);
+    assert_eq!(frames[1].locals.len(), 4);
+    Ok(())
+}
+
+#[test]
+#[cfg_attr(miri, ignore)]
+fn coredump_frame_state_is_off_by_default() -> Result<()> {
+    let mut config = Config::new();
+    config.strategy(Strategy::Cranelift);
+    let (cd, dump) = frame_state_coredump(&mut config)?;
+
+    assert!(cd.frame_instance(0).is_none());
+    assert!(cd.frame_locals(0).is_none());
+    assert!(cd.frame_operand_stack(0).is_none());
+    let frames = corestack_frames(&dump)?;
+    assert!(frames[0].locals.is_empty());
+    assert!(frames[0].stack.is_empty());
+    Ok(())
+}
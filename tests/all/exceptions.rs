@@ -0,0 +1,323 @@
+use wasmtime::*;
+
+fn exceptions_store() -> Result<Store<()>> {
+    let mut config = Config::new();
+    config.wasm_exceptions(true);
+    let engine = Engine::new(&config)?;
+    Ok(Store::new(&engine, ()))
+}
+
+#[test]
+#[cfg_attr(miri, ignore)]
+fn uncaught_exception_is_pending_after_call() -> Result<()> {
+    let mut store = exceptions_store()?;
+    let module = Module::new(
+        store.engine(),
+        r#"
+            (module
+                (tag (export "e") (param i32 i64))
+                (func $thrower (param i32)
+                    local.get 0
+                    i64.const 100
+                    throw 0
+                )
+                (func (export "run") (param i32) (result i32)
+                    local.get 0
+                    call $thrower
+                    i32.const 1
+                )
+            )
+        "#,
+    )?;
+    let instance = Instance::new(&mut store, &module, &[])?;
+    let run = instance.get_typed_func::<i32, i32>(&mut store, "run")?;
+    let e = instance.get_tag(&mut store, "e").unwrap();
+
+    assert!(store.take_pending_exception().is_none());
+
+    let err = run.call(&mut store, 7).unwrap_err();
+    assert!(err.is::<ThrownException>(), "{err:?}");
+
+    let exn = store.take_pending_exception().unwrap();
+    assert!(Tag::eq(&exn.tag(&mut store)?, &e, &store));
+    let fields = exn.fields(&mut store)?.collect::<Vec<_>>();
+    assert_eq!(fields.len(), 2);
+    assert_eq!(fields[0].unwrap_i32(), 7);
+    assert_eq!(fields[1].unwrap_i64(), 100);
+    assert!(exn.field(&mut store, 2).is_err());
+
+    // Taking the exception clears it.
+    assert!(store.take_pending_exception().is_none());
+
+    // And the instance is still usable afterwards.
+    let err = run.call(&mut store, 8).unwrap_err();
+    assert!(err.is::<ThrownException>());
+    Ok(())
+}
+
+#[test]
+#[cfg_attr(miri, ignore)]
+fn host_throws_to_wasm() -> Result<()> {
+    let mut store = exceptions_store()?;
+    let tag_ty = TagType::new(FuncType::new(store.engine(), [ValType::I32], []))?;
+    let tag = Tag::new(&mut store, &tag_ty)?;
+
+    let throw = Func::wrap(
+        &mut store,
+        move |mut caller: Caller<'_, ()>, x: i32| -> Result<i32> {
+            if x < 0 {
+                return Ok(-x);
+            }
+            let exn = ExnRef::new(&mut caller, &tag, &[Val::I32(x * 2)])?;
+            Ok(caller.throw(exn)?)
+        },
+    );
+
+    let module = Module::new(
+        store.engine(),
+        r#"
+            (module
+                (import "" "tag" (tag $t (param i32)))
+                (import "" "throw" (func $throw (param i32) (result i32)))
+                (func (export "run") (param i32) (result i32)
+                    (block $h (result i32)
+                        (try_table (catch $t $h)
+                            (return (call $throw (local.get 0))))
+                        (unreachable))
+                    i32.const 1000
+                    i32.add
+                )
+                (func (export "run-uncaught") (param i32) (result i32)
+                    (call $throw (local.get 0))
+                )
+            )
+        "#,
+    )?;
+    let instance = Instance::new(&mut store, &module, &[tag.into(), throw.into()])?;
+    let run = instance.get_typed_func::<i32, i32>(&mut store, "run")?;
+    assert_eq!(run.call(&mut store, -5)?, 5);
+    assert_eq!(run.call(&mut store, 5)?, 1010);
+
+    let run_uncaught = instance.get_typed_func::<i32, i32>(&mut store, "run-uncaught")?;
+    let err = run_uncaught.call(&mut store, 3).unwrap_err();
+    assert!(err.is::<ThrownException>(), "{err:?}");
+    let exn = store.take_pending_exception().unwrap();
+    assert!(Tag::eq(&exn.tag(&mut store)?, &tag, &store));
+    assert_eq!(exn.field(&mut store, 0)?.unwrap_i32(), 6);
+    Ok(())
+}
+
+#[test]
+#[cfg_attr(miri, ignore)]
+fn host_returning_thrown_exception_without_throwing_is_an_error() -> Result<()> {
+    let mut store = exceptions_store()?;
+    let func = Func::wrap(&mut store, || -> Result<()> { Err(ThrownException.into()) });
+    let module = Module::new(
+        store.engine(),
+        r#"
+            (module
+                (import "" "f" (func $f))
+                (func (export "run")
+                    (block $h
+                        (try_table (catch_all $h)
+                            (call $f)))
+                )
+            )
+        "#,
+    )?;
+    let instance = Instance::new(&mut store, &module, &[func.into()])?;
+    let run = instance.get_typed_func::<(), ()>(&mut store, "run")?;
+    let err = run.call(&mut store, ()).unwrap_err();
+    assert!(
+        format!("{err:?}").contains("without throwing"),
+        "unexpected error: {err:?}"
+    );
+    Ok(())
+}
+
+#[test]
+#[cfg_attr(miri, ignore)]
+fn unwinding_skips_frames_and_preserves_locals() -> Result<()> {
+    let mut store = exceptions_store()?;
+    let module = Module::new(
+        store.engine(),
+        r#"
+            (module
+                (tag $e (param i32))
+                (func $recurse (param i32) (result i32)
+                    (if (i32.eqz (local.get 0))
+                        (then (throw $e (i32.const 42))))
+                    (i32.add
+                        (call $recurse (i32.sub (local.get 0) (i32.const 1)))
+                        (i32.const 1000))
+                )
+                ;; Many values are live across the call, so that some of them
+                ;; would be in registers if they weren't all clobbered by it.
+                (func (export "run") (param i32 i32 i64 f64 v128) (result i32 i64 f64 i32 i32)
+                    (local.get 1)
+                    (local.get 2)
+                    (local.get 3)
+                    (i32x4.extract_lane 3 (local.get 4))
+                    (block $h (result i32)
+                        (try_table (result i32) (catch $e $h)
+                            (call $recurse (local.get 0)))
+                    )
+                )
+            )
+        "#,
+    )?;
+    let instance = Instance::new(&mut store, &module, &[])?;
+    let run = instance.get_func(&mut store, "run").unwrap();
+    let v = V128::from(u128::from_le_bytes([0xab; 16]));
+    for depth in [0, 1, 10, 1000] {
+        let mut results = [
+            Val::I32(0),
+            Val::I64(0),
+            Val::F64(0),
+            Val::I32(0),
+            Val::I32(0),
+        ];
+        run.call(
+            &mut store,
+            &[
+                Val::I32(depth),
+                Val::I32(7),
+                Val::I64(-8),
+                Val::F64(9.5f64.to_bits()),
+                Val::V128(v),
+            ],
+            &mut results,
+        )?;
+        assert_eq!(results[0].unwrap_i32(), 7);
+        assert_eq!(results[1].unwrap_i64(), -8);
+        assert_eq!(results[2].unwrap_f64(), 9.5);
+        assert_eq!(results[3].unwrap_i32(), 0xabababab_u32 as i32);
+        assert_eq!(results[4].unwrap_i32(), 42);
+    }
+    assert!(store.take_pending_exception().is_none());
+    Ok(())
+}
+
+#[test]
+#[cfg_attr(miri, ignore)]
+fn exnref_round_trips_through_host() -> Result<()> {
+    let mut store = exceptions_store()?;
+    let module = Module::new(
+        store.engine(),
+        r#"
+            (module
+                (tag $e (export "e") (param i32 v128))
+                (func (export "catch") (param i32) (result exnref)
+                    (block $h (result exnref)
+                        (try_table (catch_all_ref $h)
+                            (throw $e (local.get 0) (v128.const i64x2 1 2)))
+                        (unreachable))
+                )
+                (func (export "rethrow") (param exnref)
+                    (throw_ref (local.get 0))
+                )
+                (func (export "payload") (param exnref) (result i32)
+                    (block $h (result i32 v128)
+                        (try_table (catch $e $h)
+                            (throw_ref (local.get 0)))
+                        (unreachable))
+                    (drop)
+                )
+            )
+        "#,
+    )?;
+    let instance = Instance::new(&mut store, &module, &[])?;
+    let catch = instance.get_func(&mut store, "catch").unwrap();
+    let rethrow = instance.get_func(&mut store, "rethrow").unwrap();
+    let payload = instance.get_typed_func::<Option<Rooted<ExnRef>>, i32>(&mut store, "payload")?;
+
+    let mut results = [Val::null_exn_ref()];
+    catch.call(&mut store, &[Val::I32(42)], &mut results)?;
+    let exn = results[0].unwrap_exnref().copied().unwrap();
+    assert_eq!(exn.field(&mut store, 0)?.unwrap_i32(), 42);
+    assert_eq!(
+        exn.field(&mut store, 1)?.unwrap_v128().as_u128(),
+        (2u128 << 64) | 1
+    );
+
+    // The exception survives collections while held by the host.
+    store.gc();
+    assert_eq!(payload.call(&mut store, Some(exn))?, 42);
+
+    // Throwing it again from Wasm leaves the same exception pending.
+    let err = rethrow
+        .call(&mut store, &[Val::ExnRef(Some(exn))], &mut [])
+        .unwrap_err();
+    assert!(err.is::<ThrownException>());
+    let pending = store.take_pending_exception().unwrap();
+    assert!(Rooted::ref_eq(&store, &pending, &exn)?);
+
+    // Throwing a null `exnref` traps.
+    let err = rethrow
+        .call(&mut store, &[Val::null_exn_ref()], &mut [])
+        .unwrap_err();
+    assert_eq!(err.downcast::<Trap>()?, Trap::NullReference);
+    Ok(())
+}
+
+#[test]
+#[cfg_attr(miri, ignore)]
+fn exn_ref_new_checks_payload() -> Result<()> {
+    let mut store = exceptions_store()?;
+    let tag_ty = TagType::new(FuncType::new(store.engine(), [ValType::I32], []))?;
+    let tag = Tag::new(&mut store, &tag_ty)?;
+
+    assert!(ExnRef::new(&mut store, &tag, &[]).is_err());
+    assert!(ExnRef::new(&mut store, &tag, &[Val::I64(1)]).is_err());
+    let exn = ExnRef::new(&mut store, &tag, &[Val::I32(1)])?;
+    assert!(Tag::eq(&exn.tag(&mut store)?, &tag, &store));
+    Ok(())
+}
+
+#[test]
+#[cfg_attr(miri, ignore)]
+fn tags_type_check_on_import() -> Result<()> {
+    let mut store = exceptions_store()?;
+    let tag_ty = TagType::new(FuncType::new(store.engine(), [ValType::I64], []))?;
+    let tag = Tag::new(&mut store, &tag_ty)?;
+    let module = Module::new(
+        store.engine(),
+        r#"(module (import "" "" (tag (param i32))))"#,
+    )?;
+    assert!(Instance::new(&mut store, &module, &[tag.into()]).is_err());
+    Ok(())
+}
+
+#[test]
+fn exceptions_are_disabled_by_default() -> Result<()> {
+    let engine = Engine::default();
+    assert!(Module::new(&engine, r#"(module (tag))"#).is_err());
+    Ok(())
+}
+
+#[test]
+#[cfg(feature = "component-model")]
+fn components_reject_core_tags() -> Result<()> {
+    use wasm_encoder::{ComponentBuilder, ExportKind};
+
+    let mut config = Config::new();
+    config.wasm_exceptions(true);
+    config.wasm_component_model(true);
+    let engine = Engine::new(&config)?;
+
+    // The text format can't yet encode core tag aliases, so build the
+    // component by hand.
+    let mut builder = ComponentBuilder::default();
+    let module = builder.core_module_raw(&wat::parse_str(r#"(module (tag (export "e")))"#)?);
+    let instance = builder.core_instantiate(module, []);
+    builder.core_alias_export(instance, "e", ExportKind::Tag);
+
+    let err = component::Component::new(&engine, builder.finish())
+        .err()
+        .expect("tag aliases should be rejected");
+    assert!(
+        format!("{err:?}").contains("aliasing core wasm tags in components is not supported"),
+        "{err:?}"
+    );
+    Ok(())
+}
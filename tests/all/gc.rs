@@ -882,3 +882,224 @@ fn table_copy_doesnt_leak() -> Result<()> {
     assert!(flag.load(SeqCst));
     Ok(())
 }
+
+fn mark_sweep_store() -> Result<Store<()>> {
+    let mut config = Config::new();
+    config.wasm_function_references(true);
+    config.wasm_gc(true);
+    config.collector(Collector::MarkSweep);
+    let engine = Engine::new(&config)?;
+    Ok(Store::new(&engine, ()))
+}
+
+#[test]
+#[cfg_attr(miri, ignore)]
+fn mark_sweep_collects_cycles() -> Result<()> {
+    let _ = env_logger::try_init();
+
+    let mut store = mark_sweep_store()?;
+    let module = Module::new(
+        store.engine(),
+        r#"
+            (module
+                (type $s (struct (field (mut (ref null $s))) (field externref)))
+                (func (export "make_cycle") (param externref)
+                    (local $a (ref null $s))
+                    (local $b (ref null $s))
+                    (local.set $a (struct.new $s (ref.null $s) (local.get 0)))
+                    (local.set $b (struct.new $s (local.get $a) (ref.null extern)))
+                    (struct.set $s 0 (local.get $a) (local.get $b))
+                )
+            )
+        "#,
+    )?;
+    let instance = Instance::new(&mut store, &module, &[])?;
+    let make_cycle =
+        instance.get_typed_func::<Option<Rooted<ExternRef>>, ()>(&mut store, "make_cycle")?;
+
+    let flag = Arc::new(AtomicBool::new(false));
+    {
+        let mut scope = RootScope::new(&mut store);
+        let x = ExternRef::new(&mut scope, SetFlagOnDrop(flag.clone()))?;
+        make_cycle.call(&mut scope, Some(x))?;
+        scope.as_context_mut().gc();
+        assert!(!flag.load(SeqCst), "not dropped when still rooted");
+    }
+
+    store.gc();
+    assert!(flag.load(SeqCst), "cycle was collected");
+    Ok(())
+}
+
+#[test]
+#[cfg_attr(miri, ignore)]
+fn mark_sweep_keeps_live_objects() -> Result<()> {
+    let _ = env_logger::try_init();
+
+    let mut store = mark_sweep_store()?;
+    let module = Module::new(
+        store.engine(),
+        r#"
+            (module
+                (type $node (struct (field i32) (field (ref null $node))))
+                (type $garbage (array i32))
+                (import "" "gc" (func $gc))
+
+                (func (export "run") (param $n i32) (result i32)
+                    (local $i i32)
+                    (local $list (ref null $node))
+                    (local $sum i32)
+
+                    ;; Build a linked list of `n` nodes, allocating garbage and
+                    ;; periodically collecting along the way.
+                    (loop $build
+                        (local.set $list (struct.new $node (local.get $i) (local.get $list)))
+                        (drop (array.new_default $garbage (i32.const 100)))
+                        (if (i32.eqz (i32.rem_u (local.get $i) (i32.const 100)))
+                            (then (call $gc)))
+                        (local.set $i (i32.add (local.get $i) (i32.const 1)))
+                        (br_if $build (i32.lt_u (local.get $i) (local.get $n)))
+                    )
+
+                    ;; Sum the list's values.
+                    (block $done
+                        (loop $sum
+                            (br_if $done (ref.is_null (local.get $list)))
+                            (local.set $sum
+                                (i32.add
+                                    (local.get $sum)
+                                    (struct.get $node 0 (local.get $list))))
+                            (local.set $list (struct.get $node 1 (local.get $list)))
+                            (br $sum)
+                        )
+                    )
+                    (local.get $sum)
+                )
+            )
+        "#,
+    )?;
+
+    let gc = Func::wrap(&mut store, |mut caller: Caller<'_, ()>| caller.gc());
+    let instance = Instance::new(&mut store, &module, &[gc.into()])?;
+    let run = instance.get_typed_func::<i32, i32>(&mut store, "run")?;
+
+    let n = 1000;
+    assert_eq!(run.call(&mut store, n)?, (0..n).sum::<i32>());
+    Ok(())
+}
+
+#[test]
+#[cfg_attr(miri, ignore)]
+fn array_new_data_after_collection_has_right_length() -> Result<()> {
+    let _ = env_logger::try_init();
+
+    let mut config = Config::new();
+    config.wasm_function_references(true);
+    config.wasm_gc(true);
+    let engine = Engine::new(&config)?;
+    let mut store = Store::new(&engine, ());
+    let module = Module::new(
+        store.engine(),
+        r#"
+            (module
+                (type $arr (array i32))
+                (data $d "\01\00\00\00\02\00\00\00")
+                (func (export "run") (param i32) (result i32)
+                    (local $len i32)
+                    (loop $l
+                        ;; Enough arrays are allocated to fill the GC heap, so
+                        ;; some of them are allocated after a collection.
+                        (local.set $len
+                            (array.len (array.new_data $arr $d (i32.const 0) (i32.const 2))))
+                        (if (i32.ne (local.get $len) (i32.const 2))
+                            (then (return (local.get $len))))
+                        (br_if $l
+                            (local.tee 0 (i32.sub (local.get 0) (i32.const 1))))
+                    )
+                    (local.get $len)
+                )
+            )
+        "#,
+    )?;
+    let instance = Instance::new(&mut store, &module, &[])?;
+    let run = instance.get_typed_func::<i32, i32>(&mut store, "run")?;
+    assert_eq!(run.call(&mut store, 100_000)?, 2);
+    Ok(())
+}
+
+fn null_store() -> Result<Store<()>> {
+    let mut config = Config::new();
+    config.wasm_function_references(true);
+    config.wasm_gc(true);
+    config.collector(Collector::Null);
+    let engine = Engine::new(&config)?;
+    Ok(Store::new(&engine, ()))
+}
+
+#[test]
+#[cfg_attr(miri, ignore)]
+fn null_collector_alloc_and_read() -> Result<()> {
+    let _ = env_logger::try_init();
+
+    let mut store = null_store()?;
+    let module = Module::new(
+        store.engine(),
+        r#"
+            (module
+                (type $pair (struct (field i32) (field (ref null $pair))))
+                (type $arr (array i64))
+                (func (export "run") (result i32)
+                    (local $p (ref null $pair))
+                    (local $a (ref null $arr))
+                    (local.set $p (struct.new $pair (i32.const 40) (ref.null $pair)))
+                    (local.set $p (struct.new $pair (i32.const 2) (local.get $p)))
+                    (local.set $a (array.new $arr (i64.const 7) (i32.const 3)))
+                    (i32.add
+                        (i32.add
+                            (struct.get $pair 0 (local.get $p))
+                            (struct.get $pair 0 (struct.get $pair 1 (local.get $p))))
+                        (i32.wrap_i64 (array.get $arr (local.get $a) (i32.const 2))))
+                )
+            )
+        "#,
+    )?;
+    let instance = Instance::new(&mut store, &module, &[])?;
+    let run = instance.get_typed_func::<(), i32>(&mut store, "run")?;
+    assert_eq!(run.call(&mut store, ())?, 49);
+
+    // Collections are no-ops, so the objects are still intact afterwards.
+    let x = ExternRef::new(&mut store, 1234_u32)?;
+    store.gc();
+    assert_eq!(
+        x.data(&store)?.unwrap().downcast_ref::<u32>().copied(),
+        Some(1234)
+    );
+    Ok(())
+}
+
+#[test]
+#[cfg_attr(miri, ignore)]
+fn null_collector_traps_when_heap_exhausted() -> Result<()> {
+    let _ = env_logger::try_init();
+
+    let mut store = null_store()?;
+    let module = Module::new(
+        store.engine(),
+        r#"
+            (module
+                (type $s (struct (field i64) (field i64)))
+                (func (export "run")
+                    (loop $l
+                        (drop (struct.new $s (i64.const 0) (i64.const 0)))
+                        (br $l)
+                    )
+                )
+            )
+        "#,
+    )?;
+    let instance = Instance::new(&mut store, &module, &[])?;
+    let run = instance.get_typed_func::<(), ()>(&mut store, "run")?;
+    let e = run.call(&mut store, ()).unwrap_err();
+    assert_eq!(e.downcast_ref::<Trap>(), Some(&Trap::GcHeapOutOfMemory));
+    Ok(())
+}
@@ -10,6 +10,7 @@ mod coredump;
 mod debug;
 mod defaults;
 mod epoch_interruption;
+mod exceptions;
 mod externals;
 mod fuel;
 mod func;
@@ -36,6 +37,7 @@ mod pooling_allocator;
 mod relocs;
 mod stack_creator;
 mod stack_overflow;
+mod stack_switching;
 mod store;
 mod structs;
 mod table;
@@ -136,3 +136,27 @@ fn detect_precompiled() -> Result<()> {
     );
     Ok(())
 }
+
+#[test]
+#[cfg_attr(miri, ignore)]
+fn test_collector_mismatch() -> Result<()> {
+    let mut config = Config::new();
+    config.collector(Collector::DeferredReferenceCounting);
+    let drc_engine = Engine::new(&config)?;
+    let buffer = serialize(&drc_engine, "(module)")?;
+
+    let mut config = Config::new();
+    config.collector(Collector::MarkSweep);
+    let mark_sweep_engine = Engine::new(&config)?;
+    match unsafe { Module::deserialize(&mark_sweep_engine, &buffer) } {
+        Ok(_) => bail!("expected deserialization to fail"),
+        Err(e) => assert!(
+            format!("{e:?}").contains("compiled for the deferred reference-counting collector"),
+            "bad error: {e:?}"
+        ),
+    }
+
+    let buffer = serialize(&mark_sweep_engine, "(module)")?;
+    unsafe { Module::deserialize(&mark_sweep_engine, &buffer)? };
+    Ok(())
+}
@@ -0,0 +1,362 @@
+use wasmtime::*;
+
+fn stack_switching_config() -> Config {
+    let mut config = Config::new();
+    config
+        .wasm_function_references(true)
+        .wasm_stack_switching(true)
+        .wasm_exceptions(true);
+    config
+}
+
+fn stack_switching_store() -> Result<Store<()>> {
+    let engine = Engine::new(&stack_switching_config())?;
+    Ok(Store::new(&engine, ()))
+}
+
+#[test]
+#[cfg_attr(miri, ignore)]
+fn trap_in_continuation_has_backtrace() -> Result<()> {
+    let mut store = stack_switching_store()?;
+    let module = Module::new(
+        store.engine(),
+        r#"
+            (module
+                (type $f (func))
+                (type $c (cont $f))
+                (func $boom (unreachable))
+                (func $body (call $boom))
+                (elem declare func $body)
+                (func $run (export "run")
+                    (resume $c (cont.new $c (ref.func $body)))
+                )
+            )
+        "#,
+    )?;
+    let instance = Instance::new(&mut store, &module, &[])?;
+    let run = instance.get_typed_func::<(), ()>(&mut store, "run")?;
+
+    let err = run.call(&mut store, ()).unwrap_err();
+    assert_eq!(
+        *err.downcast_ref::<Trap>().unwrap(),
+        Trap::UnreachableCodeReached
+    );
+
+    // The backtrace continues from the continuation's stack into its
+    // resumer's.
+    let trace = err.downcast_ref::<WasmBacktrace>().unwrap();
+    let names = trace
+        .frames()
+        .iter()
+        .map(|f| f.func_name().unwrap_or("<unknown>"))
+        .collect::<Vec<_>>();
+    assert_eq!(names, ["boom", "body", "run"]);
+
+    // The store remains usable afterwards.
+    assert!(run.call(&mut store, ()).is_err());
+    Ok(())
+}
+
+#[test]
+#[cfg_attr(miri, ignore)]
+fn gc_refs_survive_suspension() -> Result<()> {
+    let mut config = stack_switching_config();
+    config.wasm_gc(true).collector(Collector::MarkSweep);
+    let engine = Engine::new(&config)?;
+    let mut store = Store::new(&engine, ());
+    let gc = Func::wrap(&mut store, |mut caller: Caller<'_, ()>| caller.gc());
+    let module = Module::new(
+        &engine,
+        r#"
+            (module
+                (import "" "gc" (func $gc))
+                (type $s (struct (field i32)))
+                (type $f (func (result i32)))
+                (type $c (cont $f))
+                (tag $yield)
+                (func $body (result i32)
+                    (local $r (ref null $s))
+                    (local.set $r (struct.new $s (i32.const 42)))
+                    (suspend $yield)
+                    (struct.get $s 0 (local.get $r))
+                )
+                (elem declare func $body)
+                (func (export "run") (result i32)
+                    (local $k (ref null $c))
+                    (block $h (result (ref $c))
+                        (return (resume $c (on $yield $h) (cont.new $c (ref.func $body)))))
+                    (local.set $k)
+                    ;; The struct is only reachable from the suspended
+                    ;; continuation's stack while we collect and then
+                    ;; allocate more structs.
+                    (call $gc)
+                    (drop (struct.new $s (i32.const 1)))
+                    (drop (struct.new $s (i32.const 2)))
+                    (resume $c (local.get $k))
+                )
+            )
+        "#,
+    )?;
+    let instance = Instance::new(&mut store, &module, &[gc.into()])?;
+    let run = instance.get_typed_func::<(), i32>(&mut store, "run")?;
+    assert_eq!(run.call(&mut store, ())?, 42);
+    Ok(())
+}
+
+#[test]
+#[cfg_attr(miri, ignore)]
+fn host_frames_are_suspension_barriers() -> Result<()> {
+    let mut store = stack_switching_store()?;
+    let host = Func::wrap(&mut store, |mut caller: Caller<'_, ()>| -> Result<()> {
+        let suspend = caller
+            .get_export("suspend")
+            .and_then(|e| e.into_func())
+            .unwrap();
+        suspend.call(&mut caller, &[], &mut [])
+    });
+    let module = Module::new(
+        store.engine(),
+        r#"
+            (module
+                (import "" "host" (func $host))
+                (type $f (func))
+                (type $c (cont $f))
+                (tag $t)
+                (func (export "suspend") (suspend $t))
+                (func $body (call $host))
+                (elem declare func $body)
+                (func (export "run")
+                    (block $h (result (ref $c))
+                        (resume $c (on $t $h) (cont.new $c (ref.func $body)))
+                        (return))
+                    (unreachable)
+                )
+            )
+        "#,
+    )?;
+    let instance = Instance::new(&mut store, &module, &[host.into()])?;
+    let run = instance.get_typed_func::<(), ()>(&mut store, "run")?;
+    let err = run.call(&mut store, ()).unwrap_err();
+    assert_eq!(*err.downcast_ref::<Trap>().unwrap(), Trap::UnhandledTag);
+    Ok(())
+}
+
+#[test]
+#[cfg_attr(miri, ignore)]
+fn continuation_stacks_come_from_the_pool() -> Result<()> {
+    if crate::skip_pooling_allocator_tests() {
+        return Ok(());
+    }
+
+    let mut pool = crate::small_pool_config();
+    pool.total_stacks(2);
+    let mut config = stack_switching_config();
+    config.allocation_strategy(InstanceAllocationStrategy::Pooling(pool));
+    let engine = Engine::new(&config)?;
+    let module = Module::new(
+        &engine,
+        r#"
+            (module
+                (type $f (func))
+                (type $c (cont $f))
+                (tag $t)
+                (func $body (suspend $t))
+                (elem declare func $body)
+                (func $park (result (ref $c))
+                    (block $h (result (ref $c))
+                        (resume $c (on $t $h) (cont.new $c (ref.func $body)))
+                        (unreachable))
+                )
+                ;; Start a continuation and leave it suspended forever.
+                (func (export "park")
+                    (drop (call $park))
+                )
+                ;; Park three continuations while keeping the first two, and
+                ;; then finish those.
+                (func (export "hold")
+                    (local $a (ref null $c))
+                    (local $b (ref null $c))
+                    (local.set $a (call $park))
+                    (local.set $b (call $park))
+                    (drop (call $park))
+                    (resume $c (local.get $a))
+                    (resume $c (local.get $b))
+                )
+            )
+        "#,
+    )?;
+
+    for _ in 0..3 {
+        let mut store = Store::new(&engine, ());
+        let instance = Instance::new(&mut store, &module, &[])?;
+        let park = instance.get_typed_func::<(), ()>(&mut store, "park")?;
+        let hold = instance.get_typed_func::<(), ()>(&mut store, "hold")?;
+
+        // Continuations that can't be resumed anymore return their stacks to
+        // the pool when another continuation needs one.
+        for _ in 0..10 {
+            park.call(&mut store, ())?;
+        }
+
+        // Reachable continuations keep theirs.
+        let err = hold.call(&mut store, ()).unwrap_err();
+        assert!(
+            format!("{err:?}").contains("maximum concurrent limit of 2 for fibers reached"),
+            "unexpected error: {err:?}"
+        );
+        park.call(&mut store, ())?;
+
+        // Dropping the store unwinds its suspended continuations and returns
+        // their stacks to the pool for the next iteration.
+        drop(store);
+    }
+    Ok(())
+}
+
+#[test]
+#[cfg_attr(miri, ignore)]
+fn reachable_continuations_survive_collection() -> Result<()> {
+    let mut store = stack_switching_store()?;
+    let module = Module::new(
+        store.engine(),
+        r#"
+            (module
+                (type $f (func))
+                (type $c (cont $f))
+                (type $fi (func (result i32)))
+                (type $ci (cont $fi))
+                (tag $t)
+                (func $body (suspend $t))
+                (func $answer (result i32)
+                    (suspend $t)
+                    (i32.const 42))
+                ;; Holds a suspended `$answer` in a local while suspended
+                ;; itself, and then finishes it.
+                (func $outer (result i32)
+                    (local $inner (ref null $ci))
+                    (local.set $inner
+                        (block $h (result (ref $ci))
+                            (resume $ci (on $t $h) (cont.new $ci (ref.func $answer)))
+                            (unreachable)))
+                    (suspend $t)
+                    (resume $ci (local.get $inner)))
+                (elem declare func $body $answer $outer)
+                (func $churn
+                    (local $i i32)
+                    (loop $l
+                        (block $h (result (ref $c))
+                            (resume $c (on $t $h) (cont.new $c (ref.func $body)))
+                            (unreachable))
+                        (drop)
+                        (local.tee $i (i32.add (local.get $i) (i32.const 1)))
+                        (br_if $l (i32.lt_u (i32.const 500))))
+                )
+                (func (export "run") (result i32)
+                    (local $k (ref null $ci))
+                    (local.set $k
+                        (block $h (result (ref $ci))
+                            (resume $ci (on $t $h) (cont.new $ci (ref.func $outer)))
+                            (unreachable)))
+                    (call $churn)
+                    (resume $ci (local.get $k)))
+            )
+        "#,
+    )?;
+    let instance = Instance::new(&mut store, &module, &[])?;
+    let run = instance.get_typed_func::<(), i32>(&mut store, "run")?;
+    assert_eq!(run.call(&mut store, ())?, 42);
+    Ok(())
+}
+
+#[test]
+#[cfg_attr(miri, ignore)]
+fn continuation_count_is_limited() -> Result<()> {
+    let engine = Engine::new(&stack_switching_config())?;
+    let mut store = Store::new(&engine, StoreLimitsBuilder::new().continuations(3).build());
+    store.limiter(|limits| limits);
+    let module = Module::new(
+        &engine,
+        r#"
+            (module
+                (type $f (func))
+                (type $c (cont $f))
+                (func $body)
+                (elem declare func $body)
+                (func $new (result (ref $c))
+                    (cont.new $c (ref.func $body)))
+                ;; Create and drop many continuations.
+                (func (export "drop") (param $n i32)
+                    (loop $l
+                        (drop (call $new))
+                        (local.tee $n (i32.sub (local.get $n) (i32.const 1)))
+                        (br_if $l))
+                )
+                ;; Create `$n` continuations and keep them all alive.
+                (func $keep (export "keep") (param $n i32)
+                    (local $k (ref null $c))
+                    (if (local.get $n)
+                        (then
+                            (local.set $k (call $new))
+                            (call $keep (i32.sub (local.get $n) (i32.const 1)))
+                            (resume $c (local.get $k))))
+                )
+            )
+        "#,
+    )?;
+    let instance = Instance::new(&mut store, &module, &[])?;
+    let drop_ = instance.get_typed_func::<i32, ()>(&mut store, "drop")?;
+    let keep = instance.get_typed_func::<i32, ()>(&mut store, "keep")?;
+
+    drop_.call(&mut store, 100)?;
+    keep.call(&mut store, 3)?;
+    let err = keep.call(&mut store, 4).unwrap_err();
+    assert!(
+        format!("{err:?}").contains("continuation count too high at 4"),
+        "unexpected error: {err:?}"
+    );
+    keep.call(&mut store, 3)?;
+    Ok(())
+}
+
+#[test]
+#[cfg_attr(miri, ignore)]
+fn continuations_cannot_cross_into_the_host() -> Result<()> {
+    let mut store = stack_switching_store()?;
+    let module = Module::new(
+        store.engine(),
+        r#"
+            (module
+                (type $f (func))
+                (type $c (cont $f))
+                (func $body)
+                (elem declare func $body)
+                (func (export "new") (result (ref null $c))
+                    (cont.new $c (ref.func $body))
+                )
+            )
+        "#,
+    )?;
+    let instance = Instance::new(&mut store, &module, &[])?;
+    let new = instance.get_func(&mut store, "new").unwrap();
+    let mut results = [Val::I32(0)];
+    let err = new.call(&mut store, &[], &mut results).unwrap_err();
+    assert!(
+        err.to_string().contains("continuation references"),
+        "unexpected error: {err:?}"
+    );
+    assert!(instance
+        .get_typed_func::<(), Option<Rooted<AnyRef>>>(&mut store, "new")
+        .is_err());
+
+    let ty = new.ty(&store);
+    let result = ty.results().next().unwrap();
+    assert!(result.unwrap_ref().heap_type().is_concrete_cont());
+    Ok(())
+}
+
+#[test]
+fn stack_switching_is_disabled_by_default() -> Result<()> {
+    let engine = Engine::default();
+    assert!(Module::new(&engine, r#"(module (type $f (func)) (type (cont $f)))"#).is_err());
+    Ok(())
+}
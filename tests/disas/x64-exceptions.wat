@@ -0,0 +1,117 @@
+;;! target = "x86_64"
+;;! test = "compile"
+;;! flags = "-Wexceptions"
+
+;; Calls within a `try_table` are exception landing pads, which clobber every
+;; register and are followed by a check for a pending exception. Calls outside
+;; of any `try_table` need neither.
+
+(module
+  (tag $e (param i32))
+  (import "" "f" (func $f (param i32) (result i32)))
+
+  (func $outside (param i32) (result i32)
+    (call $f (local.get 0)))
+
+  (func $inside (param i32) (result i32)
+    (block $h (result i32)
+      (try_table (result i32) (catch $e $h)
+        (call $f (local.get 0)))))
+
+  (func $throw (param i32)
+    (throw $e (local.get 0)))
+)
+;; wasm[0]::function[1]::outside:
+;;       pushq   %rbp
+;;       movq    %rsp, %rbp
+;;       movq    8(%rdi), %r10
+;;       movq    (%r10), %r10
+;;       addq    $0x10, %r10
+;;       cmpq    %rsp, %r10
+;;       ja      0x2e
+;;   18: movq    0x58(%rdi), %r8
+;;       movq    %rdi, %r11
+;;       movq    0x68(%r11), %rdi
+;;       movq    %r11, %rsi
+;;       callq   *%r8
+;;       movq    %rbp, %rsp
+;;       popq    %rbp
+;;       retq
+;;   2e: ud2
+;;
+;; wasm[0]::function[2]::inside:
+;;       pushq   %rbp
+;;       movq    %rsp, %rbp
+;;       movq    8(%rdi), %r10
+;;       movq    (%r10), %r10
+;;       addq    $0x60, %r10
+;;       cmpq    %rsp, %r10
+;;       ja      0x123
+;;   58: subq    $0x50, %rsp
+;;       movq    %rbx, 0x20(%rsp)
+;;       movq    %r12, 0x28(%rsp)
+;;       movq    %r13, 0x30(%rsp)
+;;       movq    %r14, 0x38(%rsp)
+;;       movq    %r15, 0x40(%rsp)
+;;       movq    %rdi, 0x10(%rsp)
+;;       movq    0x10(%rsp), %rdi
+;;       movq    0x58(%rdi), %r11
+;;       movq    0x68(%rdi), %rsi
+;;       movq    %rsi, %rdi
+;;       movq    0x10(%rsp), %rsi
+;;       callq   *%r11
+;;       movq    0x10(%rsp), %rdi
+;;       movq    8(%rdi), %r11
+;;       movl    0x30(%r11), %r11d
+;;       testl   %r11d, %r11d
+;;       jne     0x108
+;;       jmp     0xe6
+;;   ad: movq    0x10(%rsp), %rdi
+;;       callq   0x282
+;;       movq    %rax, %rsi
+;;       movq    0x10(%rsp), %rdi
+;;       callq   0x2c8
+;;       ud2
+;;       movq    0x10(%rsp), %rdi
+;;       callq   0x282
+;;       leaq    (%rsp), %rbx
+;;       movq    %rax, %rsi
+;;       movq    0x10(%rsp), %rdi
+;;       movq    %rbx, %rdx
+;;       callq   0x30e
+;;       movl    (%rbx), %eax
+;;       movq    0x20(%rsp), %rbx
+;;       movq    0x28(%rsp), %r12
+;;       movq    0x30(%rsp), %r13
+;;       movq    0x38(%rsp), %r14
+;;       movq    0x40(%rsp), %r15
+;;       addq    $0x50, %rsp
+;;       movq    %rbp, %rsp
+;;       popq    %rbp
+;;       retq
+;;  108: xorl    %ecx, %ecx
+;;  10a: movl    %ecx, %esi
+;;  10c: movq    0x10(%rsp), %rdi
+;;  111: callq   0x355
+;;  116: testl   %eax, %eax
+;;  118: jne     0xc6
+;;  11e: jmp     0xad
+;;  123: ud2
+;;
+;; wasm[0]::function[3]::throw:
+;;       pushq   %rbp
+;;       movq    %rsp, %rbp
+;;       movq    8(%rdi), %r10
+;;       movq    (%r10), %r10
+;;       addq    $0x20, %r10
+;;       cmpq    %rsp, %r10
+;;       ja      0x173
+;;  158: subq    $0x10, %rsp
+;;       leaq    (%rsp), %r8
+;;       movl    %edx, (%r8)
+;;       xorl    %r9d, %r9d
+;;       movl    %r9d, %esi
+;;       movq    %r8, %rdx
+;;       callq   0x3a1
+;;       ud2
+;;       ud2
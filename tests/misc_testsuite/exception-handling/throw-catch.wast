@@ -0,0 +1,135 @@
+;; Basic `throw` and `try_table` behavior, including exceptions that unwind
+;; through calls.
+
+(module
+  (tag $e0)
+  (tag $e1 (param i32))
+  (tag $e2 (param i64 f32 f64 v128))
+
+  (func $throw-if (param i32)
+    (if (local.get 0) (then (throw $e0))))
+
+  (func (export "catch-local") (result i32)
+    (block $h
+      (try_table (catch $e0 $h)
+        (throw $e0))
+      (return (i32.const 0)))
+    (i32.const 1))
+
+  (func (export "catch-call") (param i32) (result i32)
+    (block $h
+      (try_table (catch $e0 $h)
+        (call $throw-if (local.get 0)))
+      (return (i32.const 0)))
+    (i32.const 1))
+
+  (func (export "catch-payload") (param i32) (result i32)
+    (block $h (result i32)
+      (try_table (catch $e1 $h)
+        (throw $e1 (local.get 0)))
+      (unreachable)))
+
+  (func (export "catch-multi-payload") (result i64 f32 f64 i32)
+    (block $h (result i64 f32 f64 v128)
+      (try_table (catch $e2 $h)
+        (throw $e2 (i64.const 7) (f32.const 1.5) (f64.const -2.25)
+                   (v128.const i32x4 1 2 3 4)))
+      (unreachable))
+    (i32x4.extract_lane 3))
+
+  (func (export "first-matching-clause") (result i32)
+    (block $h0
+      (block $h1 (result i32)
+        (try_table (catch $e1 $h1) (catch $e0 $h0) (catch_all $h0)
+          (throw $e1 (i32.const 42)))
+        (unreachable))
+      (return))
+    (i32.const -1))
+
+  (func (export "catch-all") (result i32)
+    (block $h
+      (try_table (catch_all $h)
+        (throw $e1 (i32.const 1)))
+      (return (i32.const 0)))
+    (i32.const 1))
+
+  (func (export "nested") (result i32)
+    (block $outer
+      (try_table (catch $e0 $outer)
+        (block $inner (result i32)
+          (try_table (catch $e1 $inner)
+            (throw $e0))
+          (unreachable))
+        (return))
+      (return (i32.const 0)))
+    (i32.const 2))
+
+  (func (export "no-exception") (result i32)
+    (block $h
+      (try_table (result i32) (catch_all $h)
+        (call $throw-if (i32.const 0))
+        (i32.const 5))
+      (return))
+    (i32.const -1))
+
+  (func (export "uncaught") (param i32) (result i32)
+    (block $h (result i32)
+      (try_table (catch $e1 $h)
+        (call $throw-if (local.get 0)))
+      (return (i32.const 0)))
+    (drop)
+    (i32.const 1))
+
+  (func $loop-body (param i32) (result i32)
+    (if (i32.lt_u (local.get 0) (i32.const 10))
+      (then (throw $e1 (i32.add (local.get 0) (i32.const 1)))))
+    (local.get 0))
+
+  (func (export "catch-into-loop") (result i32)
+    (i32.const 0)
+    (loop $l (param i32) (result i32)
+      (try_table (param i32) (result i32) (catch $e1 $l)
+        (call $loop-body))))
+)
+
+(assert_return (invoke "catch-local") (i32.const 1))
+(assert_return (invoke "catch-call" (i32.const 0)) (i32.const 0))
+(assert_return (invoke "catch-call" (i32.const 1)) (i32.const 1))
+(assert_return (invoke "catch-payload" (i32.const 123)) (i32.const 123))
+(assert_return (invoke "catch-multi-payload")
+  (i64.const 7) (f32.const 1.5) (f64.const -2.25) (i32.const 4))
+(assert_return (invoke "first-matching-clause") (i32.const 42))
+(assert_return (invoke "catch-all") (i32.const 1))
+(assert_return (invoke "nested") (i32.const 2))
+(assert_return (invoke "no-exception") (i32.const 5))
+(assert_return (invoke "uncaught" (i32.const 0)) (i32.const 0))
+(assert_exception (invoke "uncaught" (i32.const 1)))
+(assert_return (invoke "catch-into-loop") (i32.const 10))
+
+;; Exceptions are identified by tag identity, including across modules.
+(module $a
+  (tag (export "e") (param i32))
+  (func (export "throw") (param i32)
+    (throw 0 (local.get 0))))
+(register "a" $a)
+
+(module
+  (tag $e (import "a" "e") (param i32))
+  (tag $local (param i32))
+  (func $throw (import "a" "throw") (param i32))
+
+  (func (export "catch-imported") (param i32) (result i32)
+    (block $h (result i32)
+      (try_table (catch $e $h)
+        (call $throw (local.get 0)))
+      (i32.const -1)))
+
+  (func (export "distinct-tag") (result i32)
+    (block $h (result i32)
+      (try_table (catch $local $h)
+        (call $throw (i32.const 1)))
+      (i32.const -1)))
+)
+
+(assert_return (invoke "catch-imported" (i32.const 9)) (i32.const 9))
+(assert_exception (invoke "distinct-tag"))
@@ -0,0 +1,67 @@
+;; `catch_ref`, `catch_all_ref`, and `throw_ref`.
+
+(module
+  (tag $e (param i32))
+  (tag $f)
+
+  (func $throw (param i32)
+    (throw $e (local.get 0)))
+
+  (func (export "rethrow") (param i32) (result i32)
+    (block $outer (result i32)
+      (try_table (catch $e $outer)
+        (block $inner (result i32 exnref)
+          (try_table (catch_ref $e $inner)
+            (call $throw (local.get 0)))
+          (unreachable))
+        (throw_ref))
+      (unreachable))
+    (i32.const 1)
+    (i32.add))
+
+  (func (export "rethrow-all") (result i32)
+    (block $outer
+      (try_table (catch $f $outer)
+        (block $inner (result exnref)
+          (try_table (catch_all_ref $inner)
+            (throw $f))
+          (unreachable))
+        (throw_ref))
+      (return (i32.const 0)))
+    (i32.const 1))
+
+  (global $saved (mut exnref) (ref.null exn))
+
+  (func (export "save") (param i32)
+    (block $h (result exnref)
+      (try_table (catch_all_ref $h)
+        (call $throw (local.get 0)))
+      (unreachable))
+    (global.set $saved))
+
+  (func (export "throw-saved")
+    (throw_ref (global.get $saved)))
+
+  (func (export "catch-saved") (result i32)
+    (block $h (result i32)
+      (try_table (catch $e $h)
+        (throw_ref (global.get $saved)))
+      (unreachable)))
+
+  (func (export "throw-null")
+    (throw_ref (ref.null exn)))
+
+  (func (export "is-null") (result i32)
+    (ref.is_null (global.get $saved)))
+)
+
+(assert_return (invoke "rethrow" (i32.const 41)) (i32.const 42))
+(assert_return (invoke "rethrow-all") (i32.const 1))
+(assert_return (invoke "is-null") (i32.const 1))
+(assert_trap (invoke "throw-saved") "null reference")
+(invoke "save" (i32.const 5))
+(assert_return (invoke "is-null") (i32.const 0))
+(assert_return (invoke "catch-saved") (i32.const 5))
+(assert_return (invoke "catch-saved") (i32.const 5))
+(assert_exception (invoke "throw-saved"))
+(assert_trap (invoke "throw-null") "null reference")
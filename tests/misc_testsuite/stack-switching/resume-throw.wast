@@ -0,0 +1,59 @@
+(module
+  (type $f (func (result i32)))
+  (type $c (cont $f))
+  (tag $yield)
+  (tag $e (param i32))
+
+  (func $catcher (result i32)
+    (block $h (result i32)
+      (try_table (catch $e $h)
+        (suspend $yield))
+      (return (i32.const -1)))
+    (i32.const 100)
+    (i32.add))
+  (func $no-catch (result i32)
+    (suspend $yield)
+    (i32.const 0))
+  (func $thrower (result i32)
+    (throw $e (i32.const 3)))
+  (elem declare func $catcher $no-catch $thrower)
+
+  ;; The exception is caught within the continuation.
+  (func (export "caught-inside") (result i32)
+    (local $k (ref null $c))
+    (block $h (result (ref $c))
+      (return (resume $c (on $yield $h) (cont.new $c (ref.func $catcher)))))
+    (local.set $k)
+    (resume_throw $c $e (i32.const 5) (local.get $k)))
+
+  ;; The exception unwinds the continuation and is caught by its resumer.
+  (func (export "caught-outside") (result i32)
+    (local $k (ref null $c))
+    (block $h (result (ref $c))
+      (return (resume $c (on $yield $h) (cont.new $c (ref.func $no-catch)))))
+    (local.set $k)
+    (block $caught (result i32)
+      (try_table (catch $e $caught)
+        (return (resume_throw $c $e (i32.const 7) (local.get $k))))
+      (unreachable)))
+
+  ;; Throwing into a continuation that never started rethrows at the resumer.
+  (func (export "fresh") (result i32)
+    (block $caught (result i32)
+      (try_table (catch $e $caught)
+        (return
+          (resume_throw $c $e (i32.const 9) (cont.new $c (ref.func $catcher)))))
+      (unreachable)))
+
+  ;; Exceptions thrown by a continuation propagate to its resumer.
+  (func (export "thrown-inside") (result i32)
+    (block $caught (result i32)
+      (try_table (catch $e $caught)
+        (return (resume $c (cont.new $c (ref.func $thrower)))))
+      (unreachable)))
+)
+
+(assert_return (invoke "caught-inside") (i32.const 105))
+(assert_return (invoke "caught-outside") (i32.const 7))
+(assert_return (invoke "fresh") (i32.const 9))
+(assert_return (invoke "thrown-inside") (i32.const 3))
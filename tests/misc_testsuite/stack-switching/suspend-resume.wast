@@ -0,0 +1,117 @@
+;; A generator that yields the numbers 1 to 10 to its consumer.
+(module
+  (type $gen_f (func))
+  (type $gen (cont $gen_f))
+  (tag $yield (param i32))
+
+  (func $producer
+    (local $i i32)
+    (local.set $i (i32.const 1))
+    (loop $l
+      (suspend $yield (local.get $i))
+      (local.set $i (i32.add (local.get $i) (i32.const 1)))
+      (br_if $l (i32.le_u (local.get $i) (i32.const 10)))))
+  (elem declare func $producer)
+
+  (func (export "sum") (result i32)
+    (local $sum i32)
+    (local $k (ref null $gen))
+    (local.set $k (cont.new $gen (ref.func $producer)))
+    (block $done
+      (loop $l
+        (block $on_yield (result i32 (ref $gen))
+          (resume $gen (on $yield $on_yield) (local.get $k))
+          (br $done))
+        (local.set $k)
+        (local.set $sum (i32.add (local.get $sum)))
+        (br $l)))
+    (local.get $sum))
+)
+
+(assert_return (invoke "sum") (i32.const 55))
+(assert_return (invoke "sum") (i32.const 55))
+
+;; Continuations take arguments, return results, and may have arguments bound
+;; ahead of time.
+(module
+  (type $f2 (func (param i32 i32) (result i32)))
+  (type $c2 (cont $f2))
+  (type $f1 (func (param i32) (result i32)))
+  (type $c1 (cont $f1))
+
+  (func $sub (param i32 i32) (result i32)
+    (i32.sub (local.get 0) (local.get 1)))
+  (elem declare func $sub)
+
+  (func (export "resume") (param i32 i32) (result i32)
+    (resume $c2 (local.get 0) (local.get 1) (cont.new $c2 (ref.func $sub))))
+
+  (func (export "bind") (param i32 i32) (result i32)
+    (resume $c1
+      (local.get 1)
+      (cont.bind $c2 $c1 (local.get 0) (cont.new $c2 (ref.func $sub)))))
+)
+
+(assert_return (invoke "resume" (i32.const 10) (i32.const 3)) (i32.const 7))
+(assert_return (invoke "bind" (i32.const 10) (i32.const 3)) (i32.const 7))
+
+;; Tags with results pass values back into the suspended continuation.
+(module
+  (type $asker_f (func (result i32)))
+  (type $asker_c (cont $asker_f))
+  (type $resumed_f (func (param i32) (result i32)))
+  (type $resumed_c (cont $resumed_f))
+  (tag $ask (param i32) (result i32))
+
+  (func $asker (result i32)
+    (i32.add (suspend $ask (i32.const 1)) (suspend $ask (i32.const 2))))
+  (elem declare func $asker)
+
+  (func (export "ask") (result i32)
+    (local $k (ref null $resumed_c))
+    (local $n i32)
+    (block $h (result i32 (ref $resumed_c))
+      (return (resume $asker_c (on $ask $h) (cont.new $asker_c (ref.func $asker)))))
+    (local.set $k)
+    (local.set $n)
+    (block $h (result i32 (ref $resumed_c))
+      (return
+        (resume $resumed_c (on $ask $h)
+          (i32.mul (local.get $n) (i32.const 10))
+          (local.get $k))))
+    (local.set $k)
+    (local.set $n)
+    (resume $resumed_c (i32.mul (local.get $n) (i32.const 100)) (local.get $k)))
+)
+
+(assert_return (invoke "ask") (i32.const 210))
+
+;; Suspensions are forwarded through resumers that don't handle their tag.
+(module
+  (type $f (func (result i32)))
+  (type $c (cont $f))
+  (tag $outer (param i32))
+  (tag $inner)
+
+  (func $leaf (result i32)
+    (suspend $outer (i32.const 42))
+    (i32.const 1))
+  (func $middle (result i32)
+    (block $h (result (ref $c))
+      (return
+        (i32.add
+          (resume $c (on $inner $h) (cont.new $c (ref.func $leaf)))
+          (i32.const 10))))
+    (unreachable))
+  (elem declare func $leaf $middle)
+
+  (func (export "forward") (result i32)
+    (local $k (ref null $c))
+    (block $h (result i32 (ref $c))
+      (return (resume $c (on $outer $h) (cont.new $c (ref.func $middle)))))
+    (local.set $k)
+    ;; 42 + (1 + 10)
+    (i32.add (resume $c (local.get $k))))
+)
+
+(assert_return (invoke "forward") (i32.const 53))
@@ -0,0 +1,42 @@
+;; Two continuations that switch back and forth between each other.
+(module
+  (rec
+    (type $ft (func (param (ref null $ct))))
+    (type $ct (cont $ft)))
+  (tag $sw)
+
+  (global $log (mut i32) (i32.const 0))
+  (func $log (param i32)
+    (global.set $log
+      (i32.add (i32.mul (global.get $log) (i32.const 10)) (local.get 0))))
+
+  (func $a (type $ft) (param $k (ref null $ct))
+    (call $log (i32.const 1))
+    (local.set $k (switch $ct $sw (local.get $k)))
+    (call $log (i32.const 3))
+    (drop (switch $ct $sw (local.get $k)))
+    (unreachable))
+
+  (func $b (type $ft) (param $k (ref null $ct))
+    (call $log (i32.const 2))
+    (local.set $k (switch $ct $sw (local.get $k)))
+    (call $log (i32.const 4)))
+
+  (elem declare func $a $b)
+
+  (func (export "run") (result i32)
+    (global.set $log (i32.const 0))
+    (resume $ct (on $sw switch)
+      (cont.new $ct (ref.func $b))
+      (cont.new $ct (ref.func $a)))
+    (global.get $log))
+
+  (func (export "no-handler")
+    (resume $ct
+      (cont.new $ct (ref.func $b))
+      (cont.new $ct (ref.func $a))))
+)
+
+(assert_return (invoke "run") (i32.const 1234))
+(assert_return (invoke "run") (i32.const 1234))
+(assert_trap (invoke "no-handler") "unhandled tag")
@@ -0,0 +1,46 @@
+(module
+  (type $f (func))
+  (type $c (cont $f))
+  (tag $t)
+  (tag $other)
+
+  (func $boom (unreachable))
+  (func $unhandled (suspend $other))
+  (func $yield (suspend $t))
+  (func $recurse (call $recurse))
+  (elem declare func $boom $unhandled $yield $recurse)
+
+  (func (export "trap")
+    (resume $c (cont.new $c (ref.func $boom))))
+
+  (func (export "unhandled")
+    (block $h (result (ref $c))
+      (resume $c (on $t $h) (cont.new $c (ref.func $unhandled)))
+      (return))
+    (drop))
+
+  (func (export "suspend-outside-continuation")
+    (suspend $t))
+
+  (func (export "null")
+    (resume $c (ref.null $c)))
+
+  (func (export "resume-twice")
+    (local $k (ref null $c))
+    (local.set $k (cont.new $c (ref.func $yield)))
+    (block $h (result (ref $c))
+      (resume $c (on $t $h) (local.get $k))
+      (return))
+    (drop)
+    (resume $c (local.get $k)))
+
+  (func (export "stack-overflow")
+    (resume $c (cont.new $c (ref.func $recurse))))
+)
+
+(assert_trap (invoke "trap") "unreachable")
+(assert_trap (invoke "unhandled") "unhandled tag")
+(assert_trap (invoke "suspend-outside-continuation") "unhandled tag")
+(assert_trap (invoke "null") "null reference")
+(assert_trap (invoke "resume-twice") "continuation already consumed")
+(assert_exhaustion (invoke "stack-overflow") "call stack exhausted")
@@ -189,6 +189,8 @@ fn should_fail(test: &Path, strategy: Strategy) -> bool {
             "tail-call",
             "relaxed-simd",
             "threads",
+            "exception-handling",
+            "stack-switching",
             // Winch technically supports memory64 but the upstream tests have
             // gc/function-references/exceptions/etc all merged in now so Winch
             // can no longer run those tests without panicking.
@@ -205,28 +207,10 @@ fn should_fail(test: &Path, strategy: Strategy) -> bool {
     }
 
     for part in test.iter() {
-        // Not implemented in Wasmtime yet
-        if part == "exception-handling" {
-            return !test.ends_with("binary.wast");
-        }
-
-        if part == "memory64" {
-            if [
-                // wasmtime doesn't implement exceptions yet
-                "imports.wast",
-                "ref_null.wast",
-                "exports.wast",
-                "throw.wast",
-                "throw_ref.wast",
-                "try_table.wast",
-                "tag.wast",
-                "instance.wast",
-            ]
-            .iter()
-            .any(|i| test.ends_with(i))
-            {
-                return true;
-            }
+        // The legacy exception-handling instructions (`try`, `catch`,
+        // `rethrow`, and `delegate`) are not implemented in Wasmtime.
+        if part == "legacy" && feature_found(test, "exception-handling") {
+            return true;
         }
     }
 
@@ -252,7 +236,8 @@ fn run_wast(wast: &Path, strategy: Strategy, pooling: bool) -> anyhow::Result<()
         || memory64
         || misc;
     let threads = feature_found(wast, "threads");
-    let gc = feature_found(wast, "gc") || memory64;
+    let stack_switching = feature_found(wast, "stack-switching");
+    let gc = feature_found(wast, "gc") || memory64 || stack_switching;
     let function_references = gc || memory64 || feature_found(wast, "function-references");
     let reference_types = !(threads && feature_found(wast, "proposals"));
     let relaxed_simd = feature_found(wast, "relaxed-simd");
@@ -261,6 +246,7 @@ fn run_wast(wast: &Path, strategy: Strategy, pooling: bool) -> anyhow::Result<()
         || feature_found_src(&wast_bytes, "shared)");
     let extended_const = feature_found(wast, "extended-const") || memory64;
     let wide_arithmetic = feature_found(wast, "wide-arithmetic");
+    let exceptions = feature_found(wast, "exception-handling") || memory64 || stack_switching;
 
     if pooling && use_shared_memory {
         log::warn!("skipping pooling test with shared memory");
@@ -284,6 +270,8 @@ fn run_wast(wast: &Path, strategy: Strategy, pooling: bool) -> anyhow::Result<()
         .wasm_custom_page_sizes(custom_page_sizes)
         .wasm_extended_const(extended_const)
         .wasm_wide_arithmetic(wide_arithmetic)
+        .wasm_exceptions(exceptions)
+        .wasm_stack_switching(stack_switching)
         .strategy(strategy);
 
     if is_cranelift {
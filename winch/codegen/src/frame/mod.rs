@@ -6,7 +6,7 @@ use anyhow::Result;
 use smallvec::SmallVec;
 use std::ops::Range;
 use wasmparser::{BinaryReader, FuncValidator, ValidatorResources};
-use wasmtime_environ::{TypeConvert, WasmValType};
+use wasmtime_environ::{FrameStateInfo, FrameStateLocal, TypeConvert, WasmValType};
 
 // TODO:
 // SpiderMonkey's implementation uses 16;
@@ -170,6 +170,42 @@ impl Frame {
             .unwrap_or_else(|| panic!(" Expected WebAssembly local at slot: {index}"))
     }
 
+    /// Describes where the `VMContext` and the WebAssembly locals of this
+    /// frame are stored, relative to the frame pointer, so that they can be
+    /// recovered from a stack frame when capturing a core dump.
+    ///
+    /// The values on the operand stack aren't described since their location
+    /// changes throughout the function.
+    pub fn frame_state(&self) -> FrameStateInfo {
+        // Locals addressed from the stack pointer are at a fixed offset below
+        // the frame pointer, while stack arguments are above it.
+        let fp_offset = |slot: &LocalSlot| {
+            let offset = i32::try_from(slot.offset).unwrap();
+            if slot.addressed_from_sp() {
+                -offset
+            } else {
+                offset
+            }
+        };
+        FrameStateInfo {
+            vmctx_offset: fp_offset(&self.vmctx_slot),
+            locals: self.locals[Self::WASM_LOCALS_OFFSET..]
+                .iter()
+                .map(|slot| FrameStateLocal {
+                    ty: slot.ty,
+                    offset: match slot.ty {
+                        WasmValType::I32
+                        | WasmValType::I64
+                        | WasmValType::F32
+                        | WasmValType::F64 => Some(fp_offset(slot)),
+                        _ => None,
+                    },
+                })
+                .collect(),
+            operand_stack: None,
+        }
+    }
+
     /// Get the [LocalSlot] for a frame local.
     /// This method doesn't make any asumptions about the local index passed in,
     /// and simply delegates the [LocalSlot] retrieval to the underlying locals
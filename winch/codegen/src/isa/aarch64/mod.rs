@@ -129,11 +129,10 @@ impl TargetIsa for Aarch64 {
         codegen.emit(&mut body, validator)?;
         let names = codegen.env.take_name_map();
         let base = codegen.source_location.base;
-        Ok(CompiledFunction::new(
-            masm.finalize(base),
-            names,
-            self.function_alignment(),
-        ))
+        let frame_state = codegen.context.frame.frame_state();
+        let mut func = CompiledFunction::new(masm.finalize(base), names, self.function_alignment());
+        func.set_frame_state(frame_state);
+        Ok(func)
     }
 
     fn text_section_builder(&self, num_funcs: usize) -> Box<dyn TextSectionBuilder> {